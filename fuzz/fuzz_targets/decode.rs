@@ -0,0 +1,71 @@
+//! Decodes every model type from arbitrary bytes. The first byte selects the
+//! type, the remaining bytes are the encoded value.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use robbot::model::channel::{
+    Attachment, CategoryChannel, Channel, ChannelKind, Embed, GuildChannel, Message, MessageKind,
+    MessageReaction, MessageReference, PermissionOverwrite, PrivateChannel, Reaction, ReactionType,
+};
+use robbot::model::emoji::Emoji;
+use robbot::model::guild::{Member, PartialMember};
+use robbot::model::id::{ChannelId, GuildId, MessageId, RoleId, UserId};
+use robbot::model::permissions::Permissions;
+use robbot::model::user::{OnlineStatus, User};
+use robbot::remote::{Decode, Decoder, Encode, Encoder};
+
+/// Decodes a `T`. Values that decode successfully must encode and decode
+/// into the same bytes again.
+fn decode<T>(data: &[u8])
+where
+    T: Decode + Encode,
+{
+    let value = match T::decode(&mut Decoder::new(data)) {
+        Ok(value) => value,
+        Err(_) => return,
+    };
+
+    let mut buf = Vec::new();
+    value.encode(&mut Encoder::new(&mut buf)).unwrap();
+
+    let decoded = T::decode(&mut Decoder::new(&buf[..])).unwrap();
+
+    let mut reencoded = Vec::new();
+    decoded.encode(&mut Encoder::new(&mut reencoded)).unwrap();
+    assert_eq!(buf, reencoded);
+}
+
+fuzz_target!(|data: &[u8]| {
+    let (kind, data) = match data.split_first() {
+        Some((kind, data)) => (*kind, data),
+        None => return,
+    };
+
+    match kind % 25 {
+        0 => decode::<Message>(data),
+        1 => decode::<MessageReference>(data),
+        2 => decode::<Embed>(data),
+        3 => decode::<MessageReaction>(data),
+        4 => decode::<ReactionType>(data),
+        5 => decode::<Attachment>(data),
+        6 => decode::<MessageKind>(data),
+        7 => decode::<Reaction>(data),
+        8 => decode::<ChannelKind>(data),
+        9 => decode::<Channel>(data),
+        10 => decode::<GuildChannel>(data),
+        11 => decode::<PermissionOverwrite>(data),
+        12 => decode::<PrivateChannel>(data),
+        13 => decode::<CategoryChannel>(data),
+        14 => decode::<Emoji>(data),
+        15 => decode::<Member>(data),
+        16 => decode::<PartialMember>(data),
+        17 => decode::<Permissions>(data),
+        18 => decode::<User>(data),
+        19 => decode::<OnlineStatus>(data),
+        20 => decode::<ChannelId>(data),
+        21 => decode::<GuildId>(data),
+        22 => decode::<MessageId>(data),
+        23 => decode::<RoleId>(data),
+        _ => decode::<UserId>(data),
+    }
+});
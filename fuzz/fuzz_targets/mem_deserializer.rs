@@ -0,0 +1,42 @@
+//! Reads entries of the `MemStore` from arbitrary buffers through the
+//! bounds-checked `deserialize_entry`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use robbot::model::emoji::Emoji;
+use robbot::model::id::{GuildId, UserId};
+use robbot::store::{Decimal, StoreEnum};
+use robbot::StoreData;
+use robbot_core::settings::GuildSetting;
+use robbot_core::store::mem::deserialize_entry;
+
+#[derive(Copy, Clone, Debug, StoreEnum)]
+enum Kind {
+    First,
+    Second,
+}
+
+#[derive(Clone, Debug, StoreData)]
+struct Entry {
+    flag: bool,
+    small: i8,
+    short: u16,
+    int: i32,
+    long: u64,
+    wide: i128,
+    float: f64,
+    text: String,
+    price: Decimal,
+    guild_id: GuildId,
+    user_id: UserId,
+    emoji: Emoji,
+    #[store(as = "u8")]
+    kind: Kind,
+    #[store(as = "string")]
+    named: Kind,
+}
+
+fuzz_target!(|data: &[u8]| {
+    let _ = deserialize_entry::<Entry>(data);
+    let _ = deserialize_entry::<GuildSetting>(data);
+});
@@ -0,0 +1,37 @@
+//! Runs the argument parsers on arbitrary strings.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use robbot::arguments::{ChannelMention, MessageRef, RoleMention, UserMention};
+use robbot::model::emoji::Emoji;
+use robbot::store::Decimal;
+use robbot::task::{parse_duration, TaskSchedule};
+
+/// Parses `s` as a `T`. Values that parse successfully must parse into an
+/// equal value from their displayed form.
+fn parse<T>(s: &str)
+where
+    T: std::str::FromStr + ToString + PartialEq + std::fmt::Debug,
+{
+    if let Ok(value) = s.parse::<T>() {
+        let displayed = value.to_string();
+        assert_eq!(displayed.parse::<T>().ok(), Some(value), "{}", displayed);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let s = match std::str::from_utf8(data) {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+
+    parse::<ChannelMention>(s);
+    parse::<RoleMention>(s);
+    parse::<UserMention>(s);
+    parse::<Decimal>(s);
+    parse::<TaskSchedule>(s);
+
+    let _ = s.parse::<Emoji>();
+    let _ = s.parse::<MessageRef>();
+    let _ = parse_duration(s);
+});
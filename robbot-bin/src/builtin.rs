@@ -1,26 +1,100 @@
+pub mod disable;
+mod ignore;
+mod settings;
+mod store;
+
+use crate::dispatch;
 use crate::help;
+use crate::logger;
+use crate::plugins::log::{self, LogEvent, LogLevel};
 
-use robbot::arguments::{ArgumentsExt, CommandArguments};
+use robbot::arguments::{Arguments, ArgumentsExt, UserMention};
 use robbot::builder::CreateMessage;
-use robbot::{command, Result};
+use robbot::store::create;
+use robbot::{command, task, Error, Result};
 use robbot_core::command::Command;
-use robbot_core::context::MessageContext;
+use robbot_core::context::{Context, MessageContext};
+use robbot_core::cooldown::{CooldownEntry, QuotaEntry};
+use robbot_core::ignore::IgnoredChannel;
+use robbot_core::settings::{GuildSetting, TimeDisplay};
 use robbot_core::state::State;
+use robbot_core::theme::EMBED_COLOR;
 
-use serenity::utils::Color;
+use chrono::Utc;
 
-/// The color of the embed used by all builtin commands.
-pub const EMBED_COLOR: Color = Color::from_rgb(0xFF, 0xA6, 0x00);
+use std::fmt::Write;
 
-/// Loads all builtin functions into the [`State`]. If state
-/// is new or has no commands loaded, `init` will never fail.
-pub fn init(state: &State) -> Result {
-    const COMMANDS: &[fn() -> Command] = &[help, uptime, version];
+/// Loads all builtin functions into the [`State`] and creates the
+/// stores they need. If state is new or has no commands loaded,
+/// `init` only fails if the stores cannot be created.
+pub async fn init(state: &State) -> Result {
+    const COMMANDS: &[fn() -> Command] =
+        &[help, loglevel, maintenance, sudo, tasks, uptime, version];
+    const IGNORE_COMMANDS: &[fn() -> Command] = &[ignore::add, ignore::list, ignore::remove];
+    const STORE_COMMANDS: &[fn() -> Command] = &[store::export, store::import];
+    const SETTINGS_COMMANDS: &[fn() -> Command] = &[settings::set, settings::reset, settings::list];
+    const COMMAND_COMMANDS: &[fn() -> Command] = &[
+        disable::commands::disable,
+        disable::commands::enable,
+        disable::commands::disabled,
+    ];
+    const HOOK_COMMANDS: &[fn() -> Command] = &[disable::hooks::disable, disable::hooks::enable];
+    const TASK_COMMANDS: &[fn() -> Command] = &[disable::tasks::disable, disable::tasks::enable];
 
     for f in COMMANDS {
         state.commands().load_command(f(), None)?;
     }
 
+    state
+        .commands()
+        .load_command(Command::new("ignore"), None)?;
+    for f in IGNORE_COMMANDS {
+        state.commands().load_command(f(), Some("ignore"))?;
+    }
+
+    state
+        .commands()
+        .load_command(Command::new("settings"), None)?;
+    for f in SETTINGS_COMMANDS {
+        state.commands().load_command(f(), Some("settings"))?;
+    }
+
+    state.commands().load_command(Command::new("store"), None)?;
+    for f in STORE_COMMANDS {
+        state.commands().load_command(f(), Some("store"))?;
+    }
+
+    for (name, commands) in [
+        ("command", COMMAND_COMMANDS),
+        ("hook", HOOK_COMMANDS),
+        ("task", TASK_COMMANDS),
+    ] {
+        state.commands().load_command(Command::new(name), None)?;
+        for f in commands {
+            state.commands().load_command(f(), Some(name))?;
+        }
+    }
+
+    create!(state.store(), IgnoredChannel).await?;
+    create!(state.store(), GuildSetting).await?;
+    create!(state.store(), CooldownEntry).await?;
+    create!(state.store(), QuotaEntry).await?;
+
+    state.tasks().add_task(cooldown_cleanup()).await;
+
+    Ok(())
+}
+
+/// Removes expired cooldowns and quotas of past days.
+#[task(interval = "1h", on_load = true, mutates_data = true)]
+async fn cooldown_cleanup(ctx: Context<()>) -> Result {
+    ctx.state.store().check_writable()?;
+
+    ctx.state
+        .cooldowns()
+        .cleanup(Utc::now().timestamp())
+        .await?;
+
     Ok(())
 }
 
@@ -33,33 +107,235 @@ pub fn init(state: &State) -> Result {
     usage = "[Path to Command]"
 )]
 async fn help(ctx: MessageContext) -> Result {
-    // FIXME: Calling `Arguments::to_owned` clones all strings which isn't necessary here.
-    let mut args = CommandArguments::from(ctx.args.as_args().to_owned());
-
-    let command = ctx.state.commands().get_command(&mut args);
-    let path = args.as_parsed_args().join(" ");
-
-    let description = match ctx.args.is_empty() {
-        // Try to show command help.
-        false => match command {
-            Some(command) => help::command(&command, &path, &ctx.state.config.prefix),
-            // Cannot find command, show global help instead.
-            None => help::global(
-                &ctx.state.commands().list_root_commands(),
-                &ctx.state.config.prefix,
-            ),
-        },
-        // Show global help.
-        true => help::global(
+    let args: &[String] = ctx.args.as_ref();
+
+    let command = ctx.state.commands().resolve_path(&Arguments::new(args));
+    let path = match &command {
+        Some((_, depth)) => args[..*depth].join(" "),
+        None => String::new(),
+    };
+
+    let embed = match command {
+        // Show command help.
+        Some((command, _)) if !ctx.args.is_empty() => {
+            help::command(&command, &path, &ctx.state.config.prefix)
+        }
+        // Cannot find command or no command given, show global help instead.
+        _ => help::global(
             &ctx.state.commands().list_root_commands(),
             &ctx.state.config.prefix,
         ),
     };
 
+    ctx.respond(embed).await?;
+
+    Ok(())
+}
+
+/// The `loglevel` command overrides the level of a log target and all its
+/// submodules until the logger is configured again. Only admins defined in the
+/// config file can use it.
+#[command(
+    description = "Change the log level of a target and its submodules until the bot restarts.",
+    usage = "<Target> <Level>",
+    example = "sqlx warn",
+    arg_choices(level = ["off", "error", "warn", "info", "debug", "trace"])
+)]
+async fn loglevel(mut ctx: MessageContext) -> Result {
+    if !ctx.state.config.admins.contains(&ctx.event.author.id) {
+        ctx.respond(":no_entry_sign: You are not allowed to run this command.")
+            .await?;
+        return Ok(());
+    }
+
+    let (target, level) = match (ctx.args.pop(), ctx.args.pop()) {
+        (Some(target), Some(level)) if ctx.args.is_empty() => (target, level),
+        _ => return Err(Error::InvalidCommandUsage),
+    };
+
+    let level: ::log::LevelFilter = match level.parse() {
+        Ok(level) => level,
+        Err(_) => return Err(Error::InvalidCommandUsage),
+    };
+
+    logger::set_filter(&target, level);
+
+    ctx.respond(format!(
+        ":white_check_mark: Set the log level of `{}` to `{}`.",
+        target, level
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// The `maintenance` command toggles the read-only maintenance mode of the
+/// bot, see [`robbot_core::maintenance`]. The optional message is appended to
+/// rejected commands. Only admins defined in the config file can use it.
+#[command(
+    description = "Toggle maintenance mode. Commands and tasks writing data are disabled while it is on, the message is shown to members running them.",
+    usage = "<State> [Message...]",
+    example = "on Database migration, back at 18:00 UTC.",
+    arg_choices(state = ["on", "off"])
+)]
+async fn maintenance(mut ctx: MessageContext) -> Result {
+    if !ctx.state.config.admins.contains(&ctx.event.author.id) {
+        ctx.respond(":no_entry_sign: You are not allowed to run this command.")
+            .await?;
+        return Ok(());
+    }
+
+    let state = ctx.args.pop().ok_or(Error::InvalidCommandUsage)?;
+
+    let args: &[String] = ctx.args.as_ref();
+    let message = match args.join(" ").trim() {
+        "" => None,
+        message => Some(message.to_owned()),
+    };
+
+    let content = match state.as_str() {
+        "on" => {
+            ctx.state.maintenance().enable(message);
+            ::log::info!("[CORE] Maintenance mode enabled by {}", ctx.event.author.id);
+
+            ":construction: Maintenance mode is on, commands and tasks writing data are disabled."
+        }
+        "off" if message.is_none() => match ctx.state.maintenance().disable() {
+            true => {
+                ::log::info!(
+                    "[CORE] Maintenance mode disabled by {}",
+                    ctx.event.author.id
+                );
+
+                ":white_check_mark: Maintenance mode is off."
+            }
+            false => ":x: Maintenance mode is not on.",
+        },
+        _ => return Err(Error::InvalidCommandUsage),
+    };
+
+    ctx.respond(content).await?;
+    Ok(())
+}
+
+/// The `sudo` command runs a command with the permissions of another user.
+/// Only the command routing and permission checks use the target user, the
+/// command itself still runs in the context of the calling message. Only
+/// admins defined in the config file can use `sudo`.
+#[command(
+    description = "Run a command with the permissions of another user.",
+    usage = "<@User> <Command...>",
+    example = "@User permissions list @User"
+)]
+async fn sudo(mut ctx: MessageContext) -> Result {
+    if !ctx.state.config.admins.contains(&ctx.event.author.id) {
+        ctx.respond(":no_entry_sign: You are not allowed to run this command.")
+            .await?;
+        return Ok(());
+    }
+
+    let user: UserMention = ctx.args.pop_parse()?;
+
+    if ctx.args.is_empty() {
+        return Err(Error::InvalidCommandUsage);
+    }
+
+    // Rebuild the command string, quoting arguments that contain spaces.
+    let content = ctx
+        .args
+        .as_args()
+        .iter()
+        .map(|arg| match arg.contains(' ') {
+            true => format!("\"{}\"", arg),
+            false => arg.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if let Some(guild_id) = ctx.event.guild_id {
+        log::log(LogEvent {
+            level: LogLevel::Info,
+            guild_id,
+            target: None,
+            content: format!(
+                "{} used sudo as {}: `{}`",
+                ctx.event.author.id.mention(),
+                user,
+                content
+            ),
+        });
+    }
+
+    dispatch::dispatch(
+        ctx.state.clone(),
+        ctx.raw_ctx.clone(),
+        ctx.event.clone(),
+        &content,
+        Some(user.id),
+    )
+    .await;
+
+    Ok(())
+}
+
+/// The `tasks` command lists all tasks known to the task scheduler with
+/// their schedule, whether the schedule is overridden, the result of their last run and the time of their next
+/// run.
+#[command(
+    description = "List all scheduled tasks.",
+    permissions = ["admin"],
+    bot_permissions = [EMBED_LINKS]
+)]
+async fn tasks(ctx: MessageContext) -> Result {
+    let mut description = String::new();
+
+    let tasks = ctx.state.tasks().get_tasks().await;
+    if tasks.is_empty() {
+        description.push_str("No tasks scheduled.");
+    }
+
+    let settings = ctx.time_settings().await?;
+    let format = |time| settings.format(time, TimeDisplay::Markdown);
+
+    for task in tasks {
+        let last_run = match (task.last_exec, task.last_result) {
+            (None, _) => String::from("never"),
+            (Some(time), None) => format!("{} (running)", format(time)),
+            (Some(time), Some(Ok(()))) => format!("{} :white_check_mark:", format(time)),
+            (Some(time), Some(Err(err))) => format!("{} :x: `{}`", format(time), err),
+        };
+
+        let next_run = match task.next_exec {
+            Some(time) if task.enabled => format(time),
+            _ => String::from("never"),
+        };
+
+        let schedule = match task.default_schedule {
+            Some(default) => format!("{}, overridden, default: {}", task.schedule, default),
+            None => format!("{}, default", task.schedule),
+        };
+
+        let _ = writeln!(
+            description,
+            "**{}** ({})\nLast run: {}\nNext run: {}",
+            task.name, schedule, last_run, next_run
+        );
+
+        if let Some(reason) = &task.disabled {
+            let _ = writeln!(
+                description,
+                "{}",
+                disable::format_reason(reason, &settings, TimeDisplay::Markdown)
+            );
+        }
+
+        description.push('\n');
+    }
+
     ctx.respond(CreateMessage::new(|m| {
         m.embed(|e| {
             e.color(EMBED_COLOR);
-            e.title("Help");
+            e.title("Tasks");
             e.description(description);
         });
     }))
@@ -100,34 +376,40 @@ async fn uptime(ctx: MessageContext) -> Result {
     Ok(())
 }
 
+/// The git version of the bot, set by the Makefile. `None` in debug builds.
+#[cfg(debug_assertions)]
+pub(crate) const VERSION: Option<&str> = None;
+
+#[cfg(not(debug_assertions))]
+pub(crate) const VERSION: Option<&str> = Some(env!(
+    "ROBBOT_VERSION",
+    "ROBBOT_VERSION environment variable is undefined"
+));
+
+/// The build time of the bot, set by the Makefile. `None` in debug builds.
+#[cfg(debug_assertions)]
+pub(crate) const BUILT: Option<&str> = None;
+
+#[cfg(not(debug_assertions))]
+pub(crate) const BUILT: Option<&str> = Some(env!(
+    "ROBBOT_BUILT",
+    "ROBBOT_BUILT environment variable is undefined"
+));
+
 /// The `version` command displays the current git version of the bot.
 /// The version string is loaded using the Makefile, it is not displayed
 /// in the debug version.
 #[command(description = "Show the bot version.")]
 async fn version(ctx: MessageContext) -> Result {
-    #[cfg(debug_assertions)]
-    const VERSION: &str = "`None`";
-
-    #[cfg(not(debug_assertions))]
-    const VERSION: &str = env!(
-        "ROBBOT_VERSION",
-        "ROBBOT_VERSION environment variable is undefined"
-    );
-
-    #[cfg(debug_assertions)]
-    const BUILT: &str = "`None`";
-
-    #[cfg(not(debug_assertions))]
-    const BUILT: &str = env!(
-        "ROBBOT_BUILT",
-        "ROBBOT_BUILT environment variable is undefined"
-    );
-
     ctx.respond(CreateMessage::new(|m| {
         m.embed(|e| {
             e.color(EMBED_COLOR);
             e.title("Version");
-            e.description(format!("{}\nBuilt: {}", VERSION, BUILT));
+            e.description(format!(
+                "{}\nBuilt: {}",
+                VERSION.unwrap_or("`None`"),
+                BUILT.unwrap_or("`None`")
+            ));
         });
     }))
     .await?;
@@ -0,0 +1,272 @@
+//! Commands to disable and enable commands, hooks and tasks at runtime.
+//! Disabling applies to all guilds, so only the admins defined in the config
+//! file can use these commands.
+
+use robbot::builder::EmbedBuilder;
+use robbot::model::id::UserId;
+use robbot::prelude::ArgumentsExt;
+use robbot::{Error, Result};
+use robbot_core::context::MessageContext;
+use robbot_core::disable::{DisableReason, DisabledBy, DisabledSet};
+use robbot_core::settings::{TimeDisplay, TimeSettings};
+use robbot_core::theme::EMBED_COLOR;
+
+/// Formats a [`DisableReason`] for display in a message, using the time
+/// settings of the guild it is shown in.
+pub fn format_reason(reason: &DisableReason, time: &TimeSettings, display: TimeDisplay) -> String {
+    let by = match reason.by {
+        DisabledBy::Automatic => String::from("automatically"),
+        DisabledBy::User(user_id) => format!("by {}", user_id.mention()),
+    };
+
+    format!(
+        "Disabled {} at {}: `{}`",
+        by,
+        time.format(reason.at, display),
+        reason.detail
+    )
+}
+
+/// Returns `true` if the author is an admin, responds otherwise.
+async fn check_admin(ctx: &MessageContext) -> std::result::Result<bool, Error> {
+    if ctx.state.config.admins.contains(&ctx.event.author.id) {
+        return Ok(true);
+    }
+
+    ctx.respond(":no_entry_sign: You are not allowed to run this command.")
+        .await?;
+    Ok(false)
+}
+
+/// Disables `name` in `set` and responds with the result.
+async fn disable(
+    ctx: &MessageContext,
+    set: &DisabledSet,
+    kind: &str,
+    name: &str,
+    user_id: UserId,
+) -> Result {
+    let reason = DisableReason::manual(user_id, ctx.event.content.as_str());
+
+    match set.disable(name, reason) {
+        true => ctx.respond(format!(":white_check_mark: Disabled {} `{}`.", kind, name)),
+        false => ctx.respond(format!(":x: The {} `{}` is already disabled.", kind, name)),
+    }
+    .await?;
+
+    Ok(())
+}
+
+/// Enables `name` in `set` and responds with the result.
+async fn enable(ctx: &MessageContext, set: &DisabledSet, kind: &str, name: &str) -> Result {
+    match set.enable(name) {
+        Some(_) => ctx.respond(format!(":white_check_mark: Enabled {} `{}`.", kind, name)),
+        None => ctx.respond(format!(":x: The {} `{}` is not disabled.", kind, name)),
+    }
+    .await?;
+
+    Ok(())
+}
+
+pub mod commands {
+    use super::*;
+
+    use robbot::arguments::Arguments;
+    use robbot::command;
+
+    /// Returns the path of the command named by the arguments, if it exists.
+    fn command_path(ctx: &MessageContext) -> Option<String> {
+        let args: &[String] = ctx.args.as_ref();
+
+        match ctx.state.commands().resolve_path(&Arguments::new(args)) {
+            Some((_, depth)) if depth == args.len() => Some(args.join(" ")),
+            _ => None,
+        }
+    }
+
+    /// The `command disable` command disables a command in all guilds. The
+    /// `command` commands themselves cannot be disabled.
+    #[command(
+        description = "Disable a command in all guilds.",
+        usage = "<Path to Command>",
+        example = "automod add"
+    )]
+    async fn disable(ctx: MessageContext) -> Result {
+        if !check_admin(&ctx).await? {
+            return Ok(());
+        }
+
+        let path = command_path(&ctx).ok_or(Error::InvalidCommandUsage)?;
+
+        if path == "command" || path.starts_with("command ") {
+            ctx.respond(":x: This command cannot be disabled.").await?;
+            return Ok(());
+        }
+
+        super::disable(
+            &ctx,
+            ctx.state.commands().disabled(),
+            "command",
+            &path,
+            ctx.event.author.id,
+        )
+        .await
+    }
+
+    /// The `command enable` command enables a disabled command.
+    #[command(
+        description = "Enable a disabled command.",
+        usage = "<Path to Command>",
+        example = "automod add"
+    )]
+    async fn enable(ctx: MessageContext) -> Result {
+        if !check_admin(&ctx).await? {
+            return Ok(());
+        }
+
+        let path = command_path(&ctx).ok_or(Error::InvalidCommandUsage)?;
+
+        super::enable(&ctx, ctx.state.commands().disabled(), "command", &path).await
+    }
+
+    /// The `command disabled` command lists all disabled commands and the
+    /// reason they were disabled.
+    #[command(
+        description = "List all disabled commands.",
+        bot_permissions = [EMBED_LINKS]
+    )]
+    async fn disabled(ctx: MessageContext) -> Result {
+        if !check_admin(&ctx).await? {
+            return Ok(());
+        }
+
+        let disabled = ctx.state.commands().disabled().list();
+        let time = ctx.time_settings().await?;
+
+        let mut embed = EmbedBuilder::new()
+            .color(EMBED_COLOR)
+            .title("Disabled Commands");
+
+        match disabled.is_empty() {
+            true => embed = embed.description("No commands are disabled."),
+            false => {
+                embed = embed.fields_from(disabled.iter().map(|(path, reason)| {
+                    (
+                        path,
+                        format_reason(reason, &time, TimeDisplay::Markdown),
+                        false,
+                    )
+                }))
+            }
+        }
+
+        ctx.respond(embed).await?;
+        Ok(())
+    }
+}
+
+pub mod hooks {
+    use super::*;
+
+    use robbot::command;
+
+    /// Pops the name of an existing hook from the arguments.
+    async fn hook_name(ctx: &mut MessageContext) -> std::result::Result<String, Error> {
+        let name = ctx.args.pop().ok_or(Error::InvalidCommandUsage)?;
+
+        let hooks = ctx.state.hooks().list_hooks().await;
+        match hooks.iter().any(|hook| hook.name == name) {
+            true => Ok(name),
+            false => Err(Error::InvalidCommandUsage),
+        }
+    }
+
+    /// The `hook disable` command stops a hook from receiving events.
+    #[command(description = "Disable a hook.", usage = "<Name>", example = "message")]
+    async fn disable(mut ctx: MessageContext) -> Result {
+        if !check_admin(&ctx).await? {
+            return Ok(());
+        }
+
+        let name = hook_name(&mut ctx).await?;
+
+        super::disable(
+            &ctx,
+            ctx.state.hooks().disabled(),
+            "hook",
+            &name,
+            ctx.event.author.id,
+        )
+        .await
+    }
+
+    /// The `hook enable` command enables a disabled hook. Hooks are disabled
+    /// automatically when they panic.
+    #[command(
+        description = "Enable a disabled hook.",
+        usage = "<Name>",
+        example = "message"
+    )]
+    async fn enable(mut ctx: MessageContext) -> Result {
+        if !check_admin(&ctx).await? {
+            return Ok(());
+        }
+
+        let name = hook_name(&mut ctx).await?;
+
+        super::enable(&ctx, ctx.state.hooks().disabled(), "hook", &name).await
+    }
+}
+
+pub mod tasks {
+    use super::*;
+
+    use robbot::command;
+
+    /// Pops the name of an existing task from the arguments.
+    async fn task_name(ctx: &mut MessageContext) -> std::result::Result<String, Error> {
+        let name = ctx.args.pop().ok_or(Error::InvalidCommandUsage)?;
+
+        let tasks = ctx.state.tasks().get_tasks().await;
+        match tasks.iter().any(|task| task.name == name) {
+            true => Ok(name),
+            false => Err(Error::InvalidCommandUsage),
+        }
+    }
+
+    /// The `task disable` command skips all runs of a task until it is
+    /// enabled again.
+    #[command(
+        description = "Disable a task. The task keeps its schedule, but does not run.",
+        usage = "<Name>"
+    )]
+    async fn disable(mut ctx: MessageContext) -> Result {
+        if !check_admin(&ctx).await? {
+            return Ok(());
+        }
+
+        let name = task_name(&mut ctx).await?;
+
+        super::disable(
+            &ctx,
+            ctx.state.tasks().disabled(),
+            "task",
+            &name,
+            ctx.event.author.id,
+        )
+        .await
+    }
+
+    /// The `task enable` command enables a disabled task. Tasks are disabled
+    /// automatically when they panic.
+    #[command(description = "Enable a disabled task.", usage = "<Name>")]
+    async fn enable(mut ctx: MessageContext) -> Result {
+        if !check_admin(&ctx).await? {
+            return Ok(());
+        }
+
+        let name = task_name(&mut ctx).await?;
+
+        super::enable(&ctx, ctx.state.tasks().disabled(), "task", &name).await
+    }
+}
@@ -0,0 +1,101 @@
+use robbot::arguments::ChannelMention;
+use robbot::builder::CreateMessage;
+use robbot::prelude::ArgumentsExt;
+use robbot::{command, Result};
+use robbot_core::context::GuildMessageContext;
+use robbot_core::theme::EMBED_COLOR;
+
+use std::fmt::Write;
+
+/// The `ignore add` command stops the bot from routing commands from a
+/// channel. Hooks can check the ignore list using
+/// [`Context::is_ignored_channel`].
+///
+/// [`Context::is_ignored_channel`]: robbot_core::context::Context::is_ignored_channel
+#[command(
+    description = "Ignore all messages in a channel.",
+    usage = "<@Channel>",
+    permissions = ["admin"],
+    audited,
+    mutates_data,
+)]
+async fn add(mut ctx: GuildMessageContext) -> Result {
+    let channel: ChannelMention = ctx.args.pop_parse()?;
+
+    ctx.state.store().check_writable()?;
+
+    let added = ctx
+        .state
+        .ignores()
+        .add(ctx.event.guild_id, channel.id)
+        .await?;
+
+    match added {
+        true => ctx.respond(format!(":white_check_mark: Ignoring {}.", channel)),
+        false => ctx.respond(format!(":x: {} is already ignored.", channel)),
+    }
+    .await?;
+
+    Ok(())
+}
+
+/// The `ignore remove` command removes a channel from the ignore list.
+#[command(
+    description = "Stop ignoring messages in a channel.",
+    usage = "<@Channel>",
+    permissions = ["admin"],
+    audited,
+    mutates_data,
+)]
+async fn remove(mut ctx: GuildMessageContext) -> Result {
+    let channel: ChannelMention = ctx.args.pop_parse()?;
+
+    ctx.state.store().check_writable()?;
+
+    let removed = ctx
+        .state
+        .ignores()
+        .remove(ctx.event.guild_id, channel.id)
+        .await?;
+
+    match removed {
+        true => ctx.respond(format!(
+            ":white_check_mark: No longer ignoring {}.",
+            channel
+        )),
+        false => ctx.respond(format!(":x: {} is not ignored.", channel)),
+    }
+    .await?;
+
+    Ok(())
+}
+
+/// The `ignore list` command lists all ignored channels in the guild.
+#[command(
+    description = "List all ignored channels.",
+    permissions = ["admin"],
+    bot_permissions = [EMBED_LINKS]
+)]
+async fn list(ctx: GuildMessageContext) -> Result {
+    let channels = ctx.state.ignores().list(ctx.event.guild_id).await?;
+
+    let mut description = String::new();
+    if channels.is_empty() {
+        description.push_str("No channels ignored.");
+    }
+
+    for channel_id in channels {
+        let _ = writeln!(description, "{}", ChannelMention::new(channel_id));
+    }
+
+    ctx.respond(CreateMessage::new(|m| {
+        m.embed(|e| {
+            e.color(EMBED_COLOR);
+            e.title("Ignored Channels");
+            e.description(description);
+        });
+    }))
+    .await?;
+
+    Ok(())
+}
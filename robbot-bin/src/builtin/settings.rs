@@ -0,0 +1,105 @@
+use robbot::builder::EmbedBuilder;
+use robbot::prelude::ArgumentsExt;
+use robbot::{command, Error, Result};
+use robbot_core::context::GuildMessageContext;
+use robbot_core::settings::{Setting, TimeDisplay};
+use robbot_core::theme::EMBED_COLOR;
+
+use chrono::Utc;
+
+use std::fmt::Write;
+
+/// The `settings set` command changes a setting of the guild.
+#[command(
+    description = "Change a setting of this guild. `timezone` takes a name from the tz database, `timeformat` takes `24h` or `12h`.",
+    usage = "<Setting> <Value>",
+    example = "timezone Europe/Berlin",
+    arg_choices(setting = ["timezone", "timeformat"]),
+    permissions = ["admin"],
+    audited,
+    mutates_data,
+)]
+async fn set(mut ctx: GuildMessageContext) -> Result {
+    let setting: Setting = ctx.args.pop_parse()?;
+    let value = ctx.args.pop().ok_or(Error::InvalidCommandUsage)?;
+
+    let value = match setting.parse_value(&value) {
+        Ok(value) => value,
+        Err(err) => {
+            ctx.respond(format!(":x: {}.", err)).await?;
+            return Ok(());
+        }
+    };
+
+    ctx.state.store().check_writable()?;
+
+    ctx.state
+        .settings()
+        .set(ctx.event.guild_id, setting, value.clone())
+        .await?;
+
+    let now = ctx.format_time(Utc::now(), TimeDisplay::Plain).await?;
+
+    ctx.respond(format!(
+        ":white_check_mark: Set `{}` to `{}`. Times are now shown as `{}`.",
+        setting, value, now
+    ))
+    .await?;
+    Ok(())
+}
+
+/// The `settings reset` command resets a setting of the guild to its
+/// default.
+#[command(
+    description = "Reset a setting of this guild to the default.",
+    usage = "<Setting>",
+    example = "timeformat",
+    arg_choices(setting = ["timezone", "timeformat"]),
+    permissions = ["admin"],
+    audited,
+    mutates_data,
+)]
+async fn reset(mut ctx: GuildMessageContext) -> Result {
+    let setting: Setting = ctx.args.pop_parse()?;
+
+    ctx.state.store().check_writable()?;
+
+    ctx.state
+        .settings()
+        .reset(ctx.event.guild_id, setting)
+        .await?;
+
+    ctx.respond(format!(":white_check_mark: Reset `{}`.", setting))
+        .await?;
+    Ok(())
+}
+
+/// The `settings list` command lists all settings of the guild.
+#[command(
+    description = "List the settings of this guild.",
+    permissions = ["admin"],
+    bot_permissions = [EMBED_LINKS],
+)]
+async fn list(ctx: GuildMessageContext) -> Result {
+    let values = ctx.state.settings().list(ctx.event.guild_id).await?;
+
+    let mut description = String::new();
+    for setting in Setting::ALL {
+        let _ = match values.iter().find(|(s, _)| s == setting) {
+            Some((_, value)) => writeln!(description, "`{}`: `{}`", setting, value),
+            None => writeln!(description, "`{}`: default", setting),
+        };
+    }
+
+    let now = ctx.format_time(Utc::now(), TimeDisplay::Plain).await?;
+    let _ = write!(description, "\nTimes are shown as `{}`.", now);
+
+    ctx.respond(
+        EmbedBuilder::new()
+            .color(EMBED_COLOR)
+            .title("Settings")
+            .description(description),
+    )
+    .await?;
+    Ok(())
+}
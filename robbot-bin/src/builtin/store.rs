@@ -0,0 +1,173 @@
+use robbot::builder::CreateMessage;
+use robbot::prelude::ArgumentsExt;
+use robbot::{command, Error, Result};
+use robbot_core::context::MessageContext;
+use robbot_core::store::ImportStatus;
+use robbot_core::theme::EMBED_COLOR;
+
+use tokio::sync::mpsc;
+
+use std::fmt::Write;
+
+/// The maximum size of a single export file. Discord rejects uploads bigger
+/// than 8 MiB, the rest is left for the request overhead.
+const PART_SIZE: usize = 8 * 1024 * 1024 - 64 * 1024;
+
+/// The `store export` command exports the full store and uploads it as
+/// attachments. Only admins defined in the config file can use it.
+#[command(
+    description = "Export all data in the store.",
+    bot_permissions = [ATTACH_FILES, EMBED_LINKS]
+)]
+async fn export(ctx: MessageContext) -> Result {
+    if !ctx.state.config.admins.contains(&ctx.event.author.id) {
+        ctx.respond(":no_entry_sign: You are not allowed to run this command.")
+            .await?;
+        return Ok(());
+    }
+
+    let _guard = match ctx.state.store_lock().try_exclusive() {
+        Some(guard) => guard,
+        None => {
+            ctx.respond(":x: The store is currently busy, try again later.")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    // Parts are uploaded while the export is running. Files are sent one at
+    // a time to stay below the request size limit.
+    let (sender, receiver) = mpsc::channel(1);
+    let upload = async {
+        // Dropped when the upload ends, so a failed upload aborts the export.
+        let mut receiver = receiver;
+        let mut parts = 0;
+        while let Some(part) = receiver.recv().await {
+            parts += 1;
+            let filename = format!("robbot-export.part{:03}.jsonl", parts);
+
+            ctx.respond(CreateMessage::new(|m| {
+                m.content(format!("Part {}", parts));
+                m.attachment(filename, part);
+            }))
+            .await?;
+        }
+
+        Ok::<_, Error>(parts)
+    };
+
+    // The error of a failed upload is reported instead of the aborted export.
+    let (rows, parts) = tokio::join!(ctx.state.store().export(PART_SIZE, sender), upload);
+    let parts = parts?;
+    let rows = rows?;
+
+    let mut description = String::new();
+    for (resource, rows) in &rows {
+        let _ = writeln!(description, "`{}`: {} rows", resource, rows);
+    }
+    let _ = write!(description, "{} parts", parts);
+
+    ctx.respond(CreateMessage::new(|m| {
+        m.embed(|e| {
+            e.color(EMBED_COLOR);
+            e.title("Store Export");
+            e.description(description);
+        });
+    }))
+    .await?;
+
+    Ok(())
+}
+
+/// The `store import` command imports an export created by `store export`
+/// from the attachments of the message. All parts must be attached to the
+/// same message. Only admins defined in the config file can use it.
+#[command(
+    description = "Import data exported using `store export`. All existing data is deleted if `wipe` is given, otherwise the data is merged.",
+    usage = "[wipe]",
+    bot_permissions = [EMBED_LINKS],
+    mutates_data,
+)]
+async fn import(mut ctx: MessageContext) -> Result {
+    if !ctx.state.config.admins.contains(&ctx.event.author.id) {
+        ctx.respond(":no_entry_sign: You are not allowed to run this command.")
+            .await?;
+        return Ok(());
+    }
+
+    let wipe = match ctx.args.pop().as_deref() {
+        Some("wipe") => true,
+        Some(_) => return Err(Error::InvalidCommandUsage),
+        None => false,
+    };
+
+    if ctx.event.attachments.is_empty() {
+        ctx.respond(":x: Attach the exported files to the message.")
+            .await?;
+        return Ok(());
+    }
+
+    ctx.state.store().check_writable()?;
+
+    let _guard = match ctx.state.store_lock().try_exclusive() {
+        Some(guard) => guard,
+        None => {
+            ctx.respond(":x: The store is currently busy, try again later.")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    // Parts are named in order, concatenating them restores the export.
+    let mut attachments: Vec<_> = ctx.event.attachments.iter().collect();
+    attachments.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+    // Attachments are downloaded and imported one at a time. Wiping deletes
+    // existing rows while importing, all attachments are checked first so an
+    // invalid export leaves the store untouched.
+    if wipe {
+        let mut importer = ctx.state.store().validate_import();
+        for attachment in &attachments {
+            importer.write(&download(&attachment.url).await?).await?;
+        }
+        importer.finish().await?;
+    }
+
+    let mut importer = ctx.state.store().import(wipe);
+    for attachment in &attachments {
+        importer.write(&download(&attachment.url).await?).await?;
+    }
+    let results = importer.finish().await?;
+
+    let mut description = String::new();
+    for (resource, status) in results {
+        let _ = match status {
+            ImportStatus::Imported(rows) => {
+                writeln!(description, "`{}`: {} rows imported", resource, rows)
+            }
+            ImportStatus::Skipped(rows) => writeln!(
+                description,
+                "`{}`: {} rows skipped (unknown resource)",
+                resource, rows
+            ),
+        };
+    }
+
+    ctx.respond(CreateMessage::new(|m| {
+        m.embed(|e| {
+            e.color(EMBED_COLOR);
+            e.title("Store Import");
+            e.description(description);
+        });
+    }))
+    .await?;
+
+    Ok(())
+}
+
+/// Downloads the attachment at `url`.
+async fn download(url: &str) -> std::result::Result<Vec<u8>, Error> {
+    let bytes = reqwest::get(url).await?.error_for_status()?.bytes().await?;
+
+    Ok(bytes.into())
+}
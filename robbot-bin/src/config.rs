@@ -1,21 +1,304 @@
-use robbot::Error;
-use robbot_core::config::Config;
+//! Loading and migration of the config file.
+//!
+//! Every config file has a `config_version`. Files without a version predate
+//! versioning and are version 1. Older files are upgraded in memory by
+//! applying all [`MIGRATIONS`] in order when they are loaded, the file on
+//! disk is only changed by [`migrate_file`].
+use robbot_core::config::{Config, CONFIG_VERSION};
 
-use std::fs::File;
-use std::io::Read;
-use std::path::Path;
+use toml::value::{Table, Value};
 
-/// Reads the [`Config`] from the file with the given `path`.
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The name of the version key.
+const VERSION_KEY: &str = "config_version";
+
+/// The version of config files without a `config_version`.
+const UNVERSIONED: u32 = 1;
+
+/// A step upgrading a config from version `from` to `from + 1`.
+struct Migration {
+    from: u32,
+    description: &'static str,
+    migrate: fn(&mut Table) -> Result<(), Error>,
+}
+
+/// All migrations, ordered by version. The last migration upgrades to
+/// [`CONFIG_VERSION`].
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    description: "added `config_version`",
+    migrate: migrate_v1,
+}];
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// `config_version` is not a positive integer.
+    InvalidVersion,
+    /// The config was written for a newer version of the bot.
+    UnsupportedVersion(u32),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Parse(err) => write!(f, "invalid config: {}", err),
+            Self::Serialize(err) => write!(f, "failed to serialize config: {}", err),
+            Self::InvalidVersion => write!(f, "`{}` must be a positive integer", VERSION_KEY),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "config version {} is newer than the latest supported version {}, please update the bot",
+                version, CONFIG_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(err: toml::de::Error) -> Self {
+        Self::Parse(err)
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(err: toml::ser::Error) -> Self {
+        Self::Serialize(err)
+    }
+}
+
+/// Reads the [`Config`] from the file with the given `path`, migrating it
+/// to the current version if necessary.
 pub fn from_file<P>(path: P) -> Result<Config, Error>
 where
     P: AsRef<Path>,
 {
-    let mut file = File::open(path)?;
+    let buf = fs::read_to_string(path)?;
+
+    from_str(&buf)
+}
+
+/// Parses a [`Config`], migrating it to the current version if necessary.
+pub fn from_str(s: &str) -> Result<Config, Error> {
+    let mut table: Table = toml::from_str(s)?;
+
+    if migrate(&mut table)? != CONFIG_VERSION {
+        log::warn!("[CONFIG] The config file uses an old format, run the bot with `--migrate-config` to update it");
+    }
+
+    Ok(Value::Table(table).try_into()?)
+}
+
+/// Migrates the config file at `path` to the current version. The original
+/// file is copied to `<path>.bak` first. Returns `false` if the file already
+/// had the current version.
+///
+/// Note that comments are not preserved.
+pub fn migrate_file<P>(path: P) -> Result<bool, Error>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+
+    let buf = fs::read_to_string(path)?;
+
+    let mut table: Table = toml::from_str(&buf)?;
+    if migrate(&mut table)? == CONFIG_VERSION {
+        return Ok(false);
+    }
+
+    // Make sure the result is a valid config before touching the file.
+    let table = Value::Table(table);
+    let _: Config = table.clone().try_into()?;
+
+    let mut backup = PathBuf::from(path).into_os_string();
+    backup.push(".bak");
+    fs::copy(path, &backup)?;
+
+    // Serializing a `Value` writes plain values before tables.
+    fs::write(path, toml::to_string_pretty(&table)?)?;
+
+    Ok(true)
+}
+
+/// Migrates `table` to [`CONFIG_VERSION`] and returns the version it had
+/// before.
+fn migrate(table: &mut Table) -> Result<u32, Error> {
+    let version = match table.get(VERSION_KEY) {
+        Some(Value::Integer(version)) => match u32::try_from(*version) {
+            Ok(version) if version >= UNVERSIONED => version,
+            _ => return Err(Error::InvalidVersion),
+        },
+        Some(_) => return Err(Error::InvalidVersion),
+        None => UNVERSIONED,
+    };
+
+    if version > CONFIG_VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= version) {
+        (migration.migrate)(table)?;
+
+        log::info!(
+            "[CONFIG] Migrated config from version {} to {}: {}",
+            migration.from,
+            migration.from + 1,
+            migration.description
+        );
+    }
+
+    table.insert(
+        VERSION_KEY.to_owned(),
+        Value::Integer(CONFIG_VERSION.into()),
+    );
+
+    Ok(version)
+}
+
+/// Version 1 to 2: only `config_version` was added, which [`migrate`] sets
+/// after the last migration.
+fn migrate_v1(_table: &mut Table) -> Result<(), Error> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_str, migrate, migrate_file, Error, MIGRATIONS};
+
+    use robbot_core::config::{LogFormat, CONFIG_VERSION};
+
+    use log::LevelFilter;
+    use toml::value::Table;
+
+    const FIXTURE_V1: &str = include_str!("../fixtures/config/v1.toml");
+
+    #[test]
+    fn test_migrations_complete() {
+        for (migration, from) in MIGRATIONS.iter().zip(1..) {
+            assert_eq!(migration.from, from);
+        }
+
+        assert_eq!(MIGRATIONS.last().unwrap().from + 1, CONFIG_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_v1() {
+        let mut table: Table = toml::from_str(FIXTURE_V1).unwrap();
+        assert_eq!(migrate(&mut table).unwrap(), 1);
+
+        // Only the version is added.
+        let mut expected: Table = toml::from_str(FIXTURE_V1).unwrap();
+        expected.insert(String::from("config_version"), CONFIG_VERSION.into());
+        assert_eq!(table, expected);
+
+        let config = from_str(FIXTURE_V1).unwrap();
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        assert_eq!(config.loglevel, LevelFilter::Info);
+        assert_eq!(config.prefix, "!");
+        assert_eq!(config.database.driver, "mysql");
+        assert!(config.ignore.bots);
+        assert_eq!(config.tasks["example_task"], "every 6h");
+    }
+
+    #[test]
+    fn test_migrate_current() {
+        let config = include_str!("../../config.toml");
+
+        let mut table: Table = toml::from_str(config).unwrap();
+        let original = table.clone();
+
+        assert_eq!(migrate(&mut table).unwrap(), CONFIG_VERSION);
+        assert_eq!(table, original);
+    }
+
+    #[test]
+    fn test_migrate_version() {
+        let config = format!("config_version = {}\n", CONFIG_VERSION + 1);
+        let err = from_str(&config).unwrap_err();
+
+        assert!(matches!(err, Error::UnsupportedVersion(v) if v == CONFIG_VERSION + 1));
+        assert!(err.to_string().contains("please update the bot"));
+
+        for version in ["0", "-1", "\"2\""] {
+            let mut table: Table =
+                toml::from_str(&format!("config_version = {}\n", version)).unwrap();
+            assert!(matches!(migrate(&mut table), Err(Error::InvalidVersion)));
+        }
+    }
+
+    #[test]
+    fn test_migrate_file() {
+        let dir = std::env::temp_dir().join(format!("robbot-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("config.toml");
+        std::fs::write(&path, FIXTURE_V1).unwrap();
+
+        assert!(migrate_file(&path).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("config.toml.bak")).unwrap(),
+            FIXTURE_V1
+        );
+
+        let migrated = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(from_str(&migrated).unwrap().config_version, CONFIG_VERSION);
+
+        // Already migrated.
+        assert!(!migrate_file(&path).unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_logging_section() {
+        let config = from_str(&format!(
+            "{}\n{}",
+            FIXTURE_V1,
+            r#"
+            [logging]
+            format = "json"
+            filters = { "sqlx" = "warn", "robbot_core::store" = "DEBUG" }
+
+            [logging.file]
+            path = "robbot.log"
+            "#
+        ))
+        .unwrap();
+
+        let logging = config.logging;
+        assert_eq!(logging.format, LogFormat::Json);
+        assert_eq!(logging.filters["sqlx"], LevelFilter::Warn);
+        assert_eq!(logging.filters["robbot_core::store"], LevelFilter::Debug);
 
-    let mut buf = Vec::new();
-    file.read_to_end(&mut buf)?;
+        let file = logging.file.unwrap();
+        assert_eq!(file.path, "robbot.log");
+        assert_eq!(file.max_size, 10 * 1024 * 1024);
+        assert_eq!(file.keep, 5);
 
-    let config = toml::from_slice(&buf)?;
+        // The section is optional.
+        let logging = from_str(FIXTURE_V1).unwrap().logging;
+        assert_eq!(logging.format, LogFormat::Pretty);
+        assert!(logging.filters.is_empty());
+        assert!(logging.file.is_none());
 
-    Ok(config)
+        for section in ["format = \"xml\"", "filters = { sqlx = \"loud\" }"] {
+            let config = format!("{}\n[logging]\n{}\n", FIXTURE_V1, section);
+            assert!(matches!(from_str(&config), Err(Error::Parse(_))));
+        }
+    }
 }
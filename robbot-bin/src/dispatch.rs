@@ -0,0 +1,184 @@
+use crate::help;
+
+use robbot::arguments::CommandArguments;
+use robbot::model::channel::{GuildMessage, Message};
+use robbot::model::id::UserId;
+use robbot::{Command as _, Error};
+use robbot_core::command::MessageExecutor;
+use robbot_core::concurrency::TooBusy;
+use robbot_core::context::MessageContext;
+use robbot_core::middleware::EffectiveUser;
+use robbot_core::router::parse_args;
+use robbot_core::state::State;
+use robbot_core::store::{Conflict, Degraded};
+use serenity::client::Context;
+
+use std::sync::Arc;
+
+/// Routes the command string `content` (without the prefix) and runs the
+/// command in response to `message`. No hooks are dispatched.
+///
+/// Permissions are checked against `subject` if given, otherwise against the
+/// author of `message`. The executor always runs in the context of `message`.
+pub async fn dispatch(
+    state: Arc<State>,
+    raw_ctx: Context,
+    message: Message,
+    content: &str,
+    subject: Option<UserId>,
+) {
+    let mut args = parse_args(content);
+    let mut cmd_args = CommandArguments::from(args.clone());
+
+    let cmd = match state.commands().get_command(&mut cmd_args) {
+        Some(cmd) => cmd,
+        None => return,
+    };
+
+    // Only retain the base path of the called command.
+    for _ in 0..cmd_args.as_args().len() {
+        args.remove(args.len() - 1);
+    }
+
+    if let Some(subject) = subject {
+        log::info!(
+            "[SUDO] {} runs '{}' as {}",
+            message.author.id,
+            content,
+            subject
+        );
+    }
+
+    let ctx = MessageContext::new_with_args(
+        raw_ctx.clone(),
+        state.clone(),
+        message.clone(),
+        cmd_args.clone(),
+    );
+
+    ctx.extensions()
+        .insert(EffectiveUser(subject.unwrap_or(message.author.id)));
+
+    let middlewares = state.middlewares().list();
+    for middleware in &middlewares {
+        if middleware.before(&ctx, &cmd).await.is_break() {
+            log::debug!(
+                "[CORE] Middleware '{}' aborted command '{}'",
+                middleware.name(),
+                args
+            );
+            return;
+        }
+    }
+
+    let path = cmd_args.as_parsed_args().join(" ");
+
+    // Commands run with sudo are already counted by the sudo command.
+    let _execution = match subject {
+        Some(_) => None,
+        None => match state.executions().try_acquire(message.guild_id) {
+            Ok(permit) => Some(permit),
+            Err(err) => {
+                let msg = match err {
+                    TooBusy::Guild => {
+                        ":x: Too many commands are running in this server, try again shortly."
+                    }
+                    TooBusy::Global => ":x: Too many commands are running, try again shortly.",
+                };
+
+                log::debug!("[CORE] Rejected command '{}': {}", args, err);
+                let _ = ctx.respond(msg).await;
+                return;
+            }
+        },
+    };
+
+    // Held until the executor returns.
+    let _permit = match cmd.concurrency_group() {
+        Some(group) => match state.concurrency().acquire(group) {
+            Ok(ticket) => {
+                // Sent without tracking the response, `ack_only` commands
+                // still acknowledge their completion.
+                if let Some(queued) = ticket.queued() {
+                    let _ = (*ctx)
+                        .respond(format!(
+                            ":hourglass: Queued behind {} other operations.",
+                            queued
+                        ))
+                        .await;
+                }
+
+                Some(ticket.wait().await)
+            }
+            Err(_) => {
+                let _ = ctx
+                    .respond(":x: Too many operations are queued, try again later.")
+                    .await;
+                return;
+            }
+        },
+        None => None,
+    };
+
+    match cmd.executor() {
+        Some(executor) => {
+            let res = match executor {
+                MessageExecutor::Message(executor) => executor.call(ctx.clone()).await,
+                MessageExecutor::GuildMessage(executor) => {
+                    let ctx = match GuildMessage::try_from(ctx.event.clone()) {
+                        Ok(event) => ctx.clone().swap(event).0,
+                        Err(_) => {
+                            let _ = ctx
+                                .respond(":x: This command can only be used in guilds.")
+                                .await;
+                            return;
+                        }
+                    };
+
+                    executor.call(ctx).await
+                }
+            };
+
+            for middleware in middlewares.iter().rev() {
+                middleware.after(&ctx, &cmd, &res).await;
+            }
+
+            if res.is_ok() && cmd.ack_only() && !ctx.has_responded() {
+                let _ = ctx.ack().await;
+            }
+
+            if let Err(err) = res {
+                match err {
+                    // Display command help message.
+                    Error::InvalidCommandUsage => {
+                        let _ = ctx
+                            .respond(help::command(&cmd, &path, &state.config.prefix))
+                            .await;
+                    }
+                    Error::Other(err) if err.is::<Conflict>() => {
+                        let _ = ctx
+                            .respond(":warning: Someone else modified this, please try again.")
+                            .await;
+                    }
+                    Error::Other(err) if err.is::<Degraded>() => {
+                        let _ = ctx
+                            .respond(
+                                ":warning: The bot is running without its database. Try again later.",
+                            )
+                            .await;
+                    }
+                    _ => {
+                        let _ = ctx.respond(":warning: Internal Server Error").await;
+                        log::error!("Command '{}' returned an error: {:?}", args, err);
+                    }
+                }
+            }
+        }
+        None => {
+            // Ignore error
+            let _ = ctx
+                .respond(help::command(&cmd, &path, &state.config.prefix))
+                .await;
+        }
+    }
+}
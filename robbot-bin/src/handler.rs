@@ -0,0 +1,513 @@
+use crate::{dispatch, intents};
+
+use async_trait::async_trait;
+use robbot_core::router;
+use robbot_core::state::State;
+use serenity::client::{Context, EventHandler};
+use serenity::model::channel::{GuildChannel, Message, Reaction};
+use serenity::model::event::GuildMembersChunkEvent;
+use serenity::model::gateway::Ready;
+use serenity::model::guild::Member;
+use serenity::model::id::{ChannelId, GuildId, MessageId};
+use serenity::model::user::User;
+
+use std::sync::Arc;
+
+/// Receives all gateway events and passes them on to the hooks and
+/// commands of the [`State`].
+pub struct Handler {
+    state: Arc<State>,
+}
+
+impl Handler {
+    pub fn new(state: Arc<State>) -> Self {
+        Self { state }
+    }
+}
+
+#[async_trait]
+impl EventHandler for Handler {
+    async fn channel_create(&self, _ctx: Context, channel: &GuildChannel) {
+        let event = robbot::hook::ChannelCreateData(channel.clone());
+
+        self.state.hooks().dispatch_event(event).await;
+    }
+
+    async fn guild_member_addition(&self, _ctx: Context, guild_id: GuildId, member: Member) {
+        let event = robbot::hook::GuildMemberAdditionData { guild_id, member };
+
+        self.state.hooks().dispatch_event(event).await;
+    }
+
+    async fn guild_member_removal(
+        &self,
+        _ctx: Context,
+        guild_id: GuildId,
+        user: User,
+        member: Option<Member>,
+    ) {
+        let event = robbot::hook::GuildMemberRemovalData {
+            guild_id,
+            user,
+            member,
+        };
+
+        self.state.hooks().dispatch_event(event).await;
+    }
+
+    async fn guild_members_chunk(&self, _ctx: Context, chunk: GuildMembersChunkEvent) {
+        self.state.member_chunks().handle_chunk(
+            chunk.guild_id.into(),
+            chunk.nonce.as_deref(),
+            chunk.chunk_index,
+            chunk.chunk_count,
+            chunk.members.len(),
+        );
+    }
+
+    async fn guild_member_update(&self, _ctx: Context, old_member: Option<Member>, member: Member) {
+        let event = robbot::hook::GuildMemberUpdateData { old_member, member };
+
+        self.state.hooks().dispatch_event(event).await;
+    }
+
+    async fn message(&self, raw_ctx: Context, message: Message) {
+        let mut message = robbot::model::channel::Message::from(message);
+
+        let bot_id = raw_ctx.cache.current_user_id().await.into();
+        if intents::CONTENT.observe(&message, bot_id) {
+            intents::warn_degraded(
+                intents::Intent::MessageContent,
+                "not enabled for the application, messages arrive without content",
+            );
+        }
+
+        {
+            let event = robbot::hook::MessageData(message.clone());
+
+            self.state.hooks().dispatch_event(event).await;
+        }
+
+        // Commands are never routed from ignored channels.
+        if router::is_ignored(self.state.ignores(), &message).await {
+            return;
+        }
+
+        // Without the message content intent the content is still available
+        // through the REST API for direct messages and mentions of the bot.
+        if intents::lacks_content(&message)
+            && (message.guild_id.is_none() || message.mentions.iter().any(|u| u.id == bot_id))
+        {
+            let ctx = robbot_core::context::Context::new(
+                raw_ctx.clone(),
+                self.state.clone(),
+                message.clone(),
+            );
+
+            match ctx.fetch_message_content().await {
+                Ok(content) => message.content = content,
+                Err(err) => log::debug!("[CORE] Failed to fetch message content: {:?}", err),
+            }
+        }
+
+        let config = &self.state.config;
+        let mention = match config.mention_prefix {
+            true => *self.state.bot_id.read().unwrap(),
+            false => None,
+        };
+
+        // Direct messages without a prefix are only run if they route to a
+        // command, `dispatch` ignores them otherwise.
+        let msg = match router::route(config, &message, mention) {
+            Some(msg) => msg.to_owned(),
+            None => return,
+        };
+
+        dispatch::dispatch(self.state.clone(), raw_ctx, message, &msg, None).await;
+    }
+
+    async fn message_delete(
+        &self,
+        _ctx: Context,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        guild_id: Option<GuildId>,
+    ) {
+        let event = robbot::hook::MessageDeleteData {
+            channel_id,
+            message_id,
+            guild_id,
+        };
+
+        self.state.hooks().dispatch_event(event).await;
+    }
+
+    async fn message_delete_bulk(
+        &self,
+        _ctx: Context,
+        channel_id: ChannelId,
+        message_ids: Vec<MessageId>,
+        guild_id: Option<GuildId>,
+    ) {
+        for message_id in message_ids {
+            let event = robbot::hook::MessageDeleteData {
+                channel_id,
+                message_id,
+                guild_id,
+            };
+
+            self.state.hooks().dispatch_event(event).await;
+        }
+    }
+
+    async fn reaction_add(&self, _ctx: Context, reaction: Reaction) {
+        let event = robbot::hook::ReactionAddData(reaction);
+
+        self.state.hooks().dispatch_event(event).await;
+    }
+
+    async fn reaction_remove(&self, _ctx: Context, reaction: Reaction) {
+        let event = robbot::hook::ReactionRemoveData(reaction);
+
+        self.state.hooks().dispatch_event(event).await;
+    }
+
+    async fn reaction_remove_all(
+        &self,
+        _ctx: Context,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) {
+        let event = robbot::hook::ReactionRemoveAllData {
+            channel_id,
+            message_id,
+        };
+
+        self.state.hooks().dispatch_event(event).await;
+    }
+
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        log::info!("[BOT] Bot online");
+
+        *self.state.bot_id.write().unwrap() = Some(ready.user.id.into());
+
+        let ctx = robbot_core::context::Context::new(ctx, self.state.clone(), ());
+
+        {
+            let mut connect_time = self.state.connect_time.write().unwrap();
+            *connect_time = Some(std::time::Instant::now());
+        }
+        {
+            let mut context = self.state.context.write().unwrap();
+            *context = Some(ctx.clone());
+        }
+
+        self.state.tasks().update_context(Some(ctx.clone())).await;
+        self.state.modules().ready(ctx).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Handler;
+
+    use robbot::hook::{EventData, EventKind, GuildMemberAdditionData, ReactionAddData};
+    use robbot::{command, Result};
+    use robbot_core::config::Config;
+    use robbot_core::context::{GuildMessageContext, MessageContext};
+    use robbot_core::hook::Hook;
+    use robbot_core::state::State;
+
+    use serde_json::{json, Value};
+    use serenity::cache::Cache;
+    use serenity::client::bridge::gateway::ShardMessenger;
+    use serenity::client::{Context, EventHandler};
+    use serenity::http::HttpBuilder;
+    use serenity::prelude::{RwLock, TypeMap};
+    use tokio::sync::broadcast::Receiver;
+    use tokio::time::{timeout, Duration};
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const GUILD_ID: u64 = 1;
+    const CHANNEL_ID: u64 = 2;
+    const USER_ID: u64 = 3;
+    const BOT_ID: u64 = 4;
+
+    static PING_CALLS: AtomicUsize = AtomicUsize::new(0);
+    static GUILD_CALLS: AtomicUsize = AtomicUsize::new(0);
+    static INFO_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    #[command(description = "Records its invocation.")]
+    async fn ping(_ctx: MessageContext) -> Result {
+        PING_CALLS.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    #[command(description = "Records its invocation in guilds.")]
+    async fn guild(_ctx: GuildMessageContext) -> Result {
+        GUILD_CALLS.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    #[command(description = "Records its invocation.")]
+    async fn info(_ctx: MessageContext) -> Result {
+        INFO_CALLS.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns a context not connected to a gateway. HTTP requests are sent
+    /// to a closed local port and fail immediately.
+    async fn raw_context() -> Context {
+        let http = HttpBuilder::new("test")
+            .proxy("http://127.0.0.1:1")
+            .unwrap()
+            .ratelimiter_disabled(true)
+            .await
+            .unwrap();
+
+        let (tx, _rx) = futures::channel::mpsc::unbounded();
+
+        Context {
+            data: Arc::new(RwLock::new(TypeMap::new())),
+            shard: ShardMessenger::new(tx),
+            shard_id: 0,
+            http: Arc::new(http),
+            cache: Arc::new(Cache::default()),
+        }
+    }
+
+    fn user(id: u64) -> Value {
+        json!({
+            "id": id.to_string(),
+            "username": "test",
+            "discriminator": "0001",
+            "avatar": null,
+            "bot": id == BOT_ID,
+            "mfa_enabled": false,
+        })
+    }
+
+    fn message(content: &str, guild: bool) -> serenity::model::channel::Message {
+        let mut message = json!({
+            "id": "10",
+            "channel_id": CHANNEL_ID.to_string(),
+            "author": user(USER_ID),
+            "content": content,
+            "timestamp": "2022-01-01T00:00:00+00:00",
+            "edited_timestamp": null,
+            "tts": false,
+            "mention_everyone": false,
+            "mentions": [],
+            "mention_roles": [],
+            "attachments": [],
+            "embeds": [],
+            "pinned": false,
+            "type": 0,
+        });
+
+        if guild {
+            message["guild_id"] = json!(GUILD_ID.to_string());
+            message["member"] = json!({
+                "roles": [],
+                "joined_at": "2022-01-01T00:00:00+00:00",
+                "deaf": false,
+                "mute": false,
+            });
+        }
+
+        serde_json::from_value(message).unwrap()
+    }
+
+    async fn next_event(
+        rx: &mut Receiver<(EventData, robbot_core::context::Context<()>)>,
+    ) -> EventData {
+        let (event, _) = timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("no event received")
+            .unwrap();
+
+        event
+    }
+
+    #[tokio::test]
+    async fn test_handler_events() {
+        let state = Arc::new(State::new(Config::default()));
+        let handler = Handler::new(state.clone());
+
+        let ready = serde_json::from_value(json!({
+            "application": { "id": BOT_ID.to_string(), "flags": 0 },
+            "guilds": [],
+            "presences": [],
+            "private_channels": [],
+            "session_id": "session",
+            "shard": [0, 1],
+            "user": user(BOT_ID),
+            "v": 9,
+        }))
+        .unwrap();
+
+        handler.ready(raw_context().await, ready).await;
+
+        assert!(state.connect_time.read().unwrap().is_some());
+        assert!(state.context.read().unwrap().is_some());
+        assert_eq!(*state.bot_id.read().unwrap(), Some(BOT_ID.into()));
+
+        let mut member_rx = state
+            .hooks()
+            .add_hook(Hook {
+                name: String::from("member"),
+                on_event: EventKind::GuildMemberAddition,
+            })
+            .await;
+        let mut reaction_rx = state
+            .hooks()
+            .add_hook(Hook {
+                name: String::from("reaction"),
+                on_event: EventKind::ReactionAdd,
+            })
+            .await;
+
+        let member = serde_json::from_value(json!({
+            "guild_id": GUILD_ID.to_string(),
+            "user": user(USER_ID),
+            "roles": [],
+            "joined_at": "2022-01-01T00:00:00+00:00",
+            "deaf": false,
+            "mute": false,
+        }))
+        .unwrap();
+
+        handler
+            .guild_member_addition(raw_context().await, GUILD_ID.into(), member)
+            .await;
+
+        let event = GuildMemberAdditionData::try_from(next_event(&mut member_rx).await).unwrap();
+        assert_eq!(event.guild_id.0, GUILD_ID);
+        assert_eq!(event.member.user.id.0, USER_ID);
+
+        let reaction = serde_json::from_value(json!({
+            "channel_id": CHANNEL_ID.to_string(),
+            "message_id": "10",
+            "user_id": USER_ID.to_string(),
+            "guild_id": GUILD_ID.to_string(),
+            "emoji": { "id": null, "name": "👍" },
+        }))
+        .unwrap();
+
+        handler.reaction_add(raw_context().await, reaction).await;
+
+        let event = ReactionAddData::try_from(next_event(&mut reaction_rx).await).unwrap();
+        assert_eq!(event.0.channel_id.0, CHANNEL_ID);
+    }
+
+    #[tokio::test]
+    async fn test_handler_commands() {
+        let config = Config {
+            prefix: String::from("!"),
+            ..Default::default()
+        };
+
+        let state = Arc::new(State::new(config));
+        state.commands().load_command(ping(), None).unwrap();
+        state.commands().load_command(guild(), None).unwrap();
+
+        let handler = Handler::new(state.clone());
+        handler
+            .message(raw_context().await, message("!ping", true))
+            .await;
+        assert_eq!(PING_CALLS.load(Ordering::SeqCst), 1);
+
+        // Messages without the prefix are not commands.
+        handler
+            .message(raw_context().await, message("ping", true))
+            .await;
+        assert_eq!(PING_CALLS.load(Ordering::SeqCst), 1);
+
+        // Guild commands are rejected in direct messages.
+        handler
+            .message(raw_context().await, message("!guild", false))
+            .await;
+        assert_eq!(GUILD_CALLS.load(Ordering::SeqCst), 0);
+
+        handler
+            .message(raw_context().await, message("!guild", true))
+            .await;
+        assert_eq!(GUILD_CALLS.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_handler_invocation_forms() {
+        // Mentions are accepted as the prefix by default.
+        let config = Config {
+            prefix: String::from("!"),
+            ..Default::default()
+        };
+
+        let state = Arc::new(State::new(config));
+        state.commands().load_command(info(), None).unwrap();
+        *state.bot_id.write().unwrap() = Some(BOT_ID.into());
+
+        let handler = Handler::new(state.clone());
+        let calls = || INFO_CALLS.load(Ordering::SeqCst);
+
+        // Direct messages work with and without the prefix.
+        for content in ["info", "!info", "  info with args"] {
+            handler
+                .message(raw_context().await, message(content, false))
+                .await;
+        }
+        assert_eq!(calls(), 3);
+
+        // Unknown commands are ignored.
+        handler
+            .message(raw_context().await, message("hello there", false))
+            .await;
+        assert_eq!(calls(), 3);
+
+        // Guild messages need the prefix or a leading mention of the bot.
+        for content in ["<@4> info", "<@!4>info", "!info"] {
+            handler
+                .message(raw_context().await, message(content, true))
+                .await;
+        }
+        assert_eq!(calls(), 6);
+
+        for content in ["info", "hey <@4> info", "<@5> info", "`<@4> info`"] {
+            handler
+                .message(raw_context().await, message(content, true))
+                .await;
+        }
+        assert_eq!(calls(), 6);
+
+        // The prefix is required in direct messages once disabled, and
+        // mentions are no longer accepted in guilds.
+        let config = Config {
+            prefix: String::from("!"),
+            mention_prefix: false,
+            dm_without_prefix: false,
+            ..Default::default()
+        };
+
+        let state = Arc::new(State::new(config));
+        state.commands().load_command(info(), None).unwrap();
+        *state.bot_id.write().unwrap() = Some(BOT_ID.into());
+
+        let handler = Handler::new(state);
+        handler
+            .message(raw_context().await, message("info", false))
+            .await;
+        assert_eq!(calls(), 6);
+
+        handler
+            .message(raw_context().await, message("!info", false))
+            .await;
+        assert_eq!(calls(), 7);
+
+        handler
+            .message(raw_context().await, message("<@4> info", true))
+            .await;
+        assert_eq!(calls(), 7);
+    }
+}
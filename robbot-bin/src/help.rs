@@ -1,14 +1,14 @@
-use robbot::command::Command;
+use robbot::builder::EmbedBuilder;
+use robbot::command::{ArgChoices, Command};
+use robbot_core::theme::EMBED_COLOR;
 
 use std::fmt::Write;
 
-/// Returns a new global help message string.
-pub(crate) fn global(commands: &[String], prefix: &str) -> String {
+/// Returns a new global help message embed.
+pub(crate) fn global(commands: &[String], prefix: &str) -> EmbedBuilder {
     // FIXME: Can pre-allocate at least the prefix and suffix of the string.
     let mut string = String::new();
 
-    let _ = writeln!(string, "__**Commands:**__");
-
     for command in commands {
         let _ = writeln!(string, "- {}", command);
     }
@@ -19,48 +19,109 @@ pub(crate) fn global(commands: &[String], prefix: &str) -> String {
         prefix
     );
 
-    string
+    EmbedBuilder::new()
+        .color(EMBED_COLOR)
+        .title("Help")
+        .field("Commands", string, false)
 }
 
-/// Return a new help message for a specific command.
+/// Return a new help message embed for a specific command.
 ///
 /// The given `path` and `prefix` values are used to correctly construct the "Usage"
 /// and "Example" fields.
-pub(crate) fn command<T>(command: &T, path: &str, prefix: &str) -> String
+pub(crate) fn command<T>(command: &T, path: &str, prefix: &str) -> EmbedBuilder
 where
     T: Command,
 {
-    let mut string = String::new();
+    let mut embed = EmbedBuilder::new()
+        .color(EMBED_COLOR)
+        .title(format!("Command Help: {}", path))
+        .field("Name", command.name(), true);
 
-    let _ = writeln!(string, "**Name**: {}", command.name());
-    let _ = writeln!(string, "**Description**: {}", command.description());
+    // Discord rejects fields with an empty value.
+    if !command.description().is_empty() {
+        embed = embed.field("Description", command.description(), false);
+    }
 
     if command.executor().is_some() {
-        let _ = writeln!(string, "**Usage**: {}{} {}", prefix, path, command.usage());
-        let _ = writeln!(
-            string,
-            "**Example**: {}{} {}",
-            prefix,
-            path,
-            command.example()
-        );
+        embed = embed
+            .field("Usage", invocation(prefix, path, command.usage()), false)
+            .field(
+                "Example",
+                invocation(prefix, path, command.example()),
+                false,
+            );
     }
 
-    if !command.sub_commands().is_empty() {
-        writeln!(string, "**Sub-Commands**:").unwrap();
+    if !command.arg_choices().is_empty() {
+        embed = embed.field("Choices", choices(command.arg_choices()), false);
+    }
 
+    if !command.sub_commands().is_empty() {
+        let mut string = String::new();
         for command in command.sub_commands() {
-            writeln!(string, "- {}", command.name()).unwrap();
+            let _ = writeln!(string, "- {}", command.name());
         }
+
+        embed = embed.field("Sub-Commands", string, false);
     }
 
     if !command.permissions().is_empty() {
+        embed = embed.field(
+            "Required Permissions",
+            format!("`{}`", command.permissions().join("`,`")),
+            false,
+        );
+    }
+
+    if !command.bot_permissions().is_empty() {
+        embed = embed.field(
+            "Required Bot Permissions",
+            format!("`{}`", command.bot_permissions().names().join("`,`")),
+            false,
+        );
+    }
+
+    embed
+}
+
+/// Formats the accepted values of the arguments, one argument per line.
+fn choices(arg_choices: &[ArgChoices]) -> String {
+    let mut string = String::new();
+
+    for choices in arg_choices {
         let _ = writeln!(
             string,
-            "**Required Permissions**: `{}`",
-            command.permissions().join("`,`")
+            "{}: `{}`",
+            choices.name,
+            choices.choices.join("`, `")
         );
     }
 
     string
 }
+
+/// Formats an invocation of the command at `path` with the arguments `args`.
+fn invocation(prefix: &str, path: &str, args: &str) -> String {
+    format!("`{}`", format!("{}{} {}", prefix, path, args).trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::choices;
+
+    use robbot::command::ArgChoices;
+
+    #[test]
+    fn test_choices() {
+        let arg_choices = [
+            ArgChoices::new("level", ["error", "warn"]),
+            ArgChoices::new("category", ["audit"]),
+        ];
+
+        assert_eq!(
+            choices(&arg_choices),
+            "level: `error`, `warn`\ncategory: `audit`\n"
+        );
+    }
+}
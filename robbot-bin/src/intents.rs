@@ -0,0 +1,259 @@
+//! Gateway intents and the features depending on them.
+//!
+//! The bot requests all intents in [`REQUESTED`]. The privileged message
+//! content intent cannot be requested by the gateway client and must be
+//! enabled for the application instead, `intents.message_content` in the
+//! config file declares whether it is. Without it messages in guilds arrive
+//! without their content, except for messages mentioning the bot.
+//!
+//! The content of messages is also checked while running: after
+//! [`EMPTY_THRESHOLD`] consecutive guild messages without any content the
+//! intent is considered unavailable and a warning is logged once.
+use robbot::model::channel::{Message, MessageKind};
+use robbot::model::id::UserId;
+use robbot_core::config::Config;
+
+use serenity::client::bridge::gateway::GatewayIntents;
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// The number of consecutive guild messages without content after which the
+/// message content intent is considered unavailable.
+pub const EMPTY_THRESHOLD: usize = 10;
+
+/// Tracks whether messages arrive without their content.
+pub static CONTENT: ContentDetector = ContentDetector::new();
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Intent {
+    Guilds,
+    GuildMembers,
+    GuildBans,
+    GuildEmojis,
+    GuildInvites,
+    GuildVoiceStates,
+    GuildMessages,
+    GuildMessageReactions,
+    DirectMessages,
+    DirectMessageReactions,
+    MessageContent,
+}
+
+impl Intent {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Guilds => "GUILDS",
+            Self::GuildMembers => "GUILD_MEMBERS",
+            Self::GuildBans => "GUILD_BANS",
+            Self::GuildEmojis => "GUILD_EMOJIS",
+            Self::GuildInvites => "GUILD_INVITES",
+            Self::GuildVoiceStates => "GUILD_VOICE_STATES",
+            Self::GuildMessages => "GUILD_MESSAGES",
+            Self::GuildMessageReactions => "GUILD_MESSAGE_REACTIONS",
+            Self::DirectMessages => "DIRECT_MESSAGES",
+            Self::DirectMessageReactions => "DIRECT_MESSAGE_REACTIONS",
+            Self::MessageContent => "MESSAGE_CONTENT",
+        }
+    }
+
+    /// Returns `true` if the intent must be enabled for the application.
+    #[cfg(feature = "debug")]
+    pub fn is_privileged(self) -> bool {
+        matches!(self, Self::GuildMembers | Self::MessageContent)
+    }
+
+    /// Returns the gateway intent, `None` if the gateway client cannot
+    /// request it.
+    fn gateway_intents(self) -> Option<GatewayIntents> {
+        let intents = match self {
+            Self::Guilds => GatewayIntents::GUILDS,
+            Self::GuildMembers => GatewayIntents::GUILD_MEMBERS,
+            Self::GuildBans => GatewayIntents::GUILD_BANS,
+            Self::GuildEmojis => GatewayIntents::GUILD_EMOJIS,
+            Self::GuildInvites => GatewayIntents::GUILD_INVITES,
+            Self::GuildVoiceStates => GatewayIntents::GUILD_VOICE_STATES,
+            Self::GuildMessages => GatewayIntents::GUILD_MESSAGES,
+            Self::GuildMessageReactions => GatewayIntents::GUILD_MESSAGE_REACTIONS,
+            Self::DirectMessages => GatewayIntents::DIRECT_MESSAGES,
+            Self::DirectMessageReactions => GatewayIntents::DIRECT_MESSAGE_REACTIONS,
+            Self::MessageContent => return None,
+        };
+
+        Some(intents)
+    }
+}
+
+/// The intents requested when connecting to the gateway.
+pub const REQUESTED: &[Intent] = &[
+    Intent::Guilds,
+    Intent::GuildMembers,
+    Intent::GuildBans,
+    Intent::GuildEmojis,
+    Intent::GuildInvites,
+    Intent::GuildVoiceStates,
+    Intent::GuildMessages,
+    Intent::GuildMessageReactions,
+    Intent::DirectMessages,
+    Intent::DirectMessageReactions,
+];
+
+/// A feature that only works with a privileged intent.
+#[derive(Copy, Clone, Debug)]
+pub struct Requirement {
+    pub feature: &'static str,
+    pub intent: Intent,
+}
+
+/// All features depending on a privileged intent.
+pub const REQUIREMENTS: &[Requirement] = &[
+    Requirement {
+        feature: "prefix commands in guilds",
+        intent: Intent::MessageContent,
+    },
+    Requirement {
+        feature: "automod word and invite rules",
+        intent: Intent::MessageContent,
+    },
+    Requirement {
+        feature: "gate",
+        intent: Intent::GuildMembers,
+    },
+    Requirement {
+        feature: "guild member chunk requests",
+        intent: Intent::GuildMembers,
+    },
+];
+
+/// Returns the intents to request when connecting to the gateway.
+pub fn gateway_intents() -> GatewayIntents {
+    REQUESTED
+        .iter()
+        .filter_map(|intent| intent.gateway_intents())
+        .fold(GatewayIntents::empty(), |acc, intents| acc | intents)
+}
+
+/// Returns `true` if `intent` is expected to be available.
+pub fn is_available(config: &Config, intent: Intent) -> bool {
+    match intent {
+        Intent::MessageContent => config.intents.message_content && !CONTENT.is_degraded(),
+        intent => REQUESTED.contains(&intent),
+    }
+}
+
+/// Logs a warning listing all features depending on `intent`.
+pub fn warn_degraded(intent: Intent, reason: &str) {
+    let features: Vec<&str> = REQUIREMENTS
+        .iter()
+        .filter(|requirement| requirement.intent == intent)
+        .map(|requirement| requirement.feature)
+        .collect();
+
+    log::warn!(
+        "[CORE] The {} intent is {}, the following features will not work: {}",
+        intent.name(),
+        reason,
+        features.join(", ")
+    );
+}
+
+/// Detects messages arriving without their content.
+#[derive(Debug)]
+pub struct ContentDetector {
+    /// The number of consecutive guild messages without content.
+    empty: AtomicUsize,
+    detected: AtomicBool,
+}
+
+impl ContentDetector {
+    pub const fn new() -> Self {
+        Self {
+            empty: AtomicUsize::new(0),
+            detected: AtomicBool::new(false),
+        }
+    }
+
+    /// Records a received message. Returns `true` if the message content
+    /// intent was detected as unavailable by this message.
+    pub fn observe(&self, message: &Message, bot_id: UserId) -> bool {
+        if !is_guild_message(message, bot_id) {
+            return false;
+        }
+
+        self.record(lacks_content(message))
+    }
+
+    /// Records a guild message with or without content.
+    fn record(&self, empty: bool) -> bool {
+        if !empty {
+            self.empty.store(0, Ordering::Relaxed);
+            return false;
+        }
+
+        let empty = self.empty.fetch_add(1, Ordering::Relaxed) + 1;
+
+        empty >= EMPTY_THRESHOLD && !self.detected.swap(true, Ordering::Relaxed)
+    }
+
+    /// Returns `true` if messages were detected to arrive without content.
+    pub fn is_degraded(&self) -> bool {
+        self.detected.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for ContentDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns `true` for messages whose content is only sent with the message
+/// content intent: messages by users in guilds not mentioning the bot.
+fn is_guild_message(message: &Message, bot_id: UserId) -> bool {
+    message.guild_id.is_some()
+        && !message.author.bot
+        && message.webhook_id.is_none()
+        && matches!(message.kind, MessageKind::Regular)
+        && !message.mentions.iter().any(|user| user.id == bot_id)
+}
+
+/// Returns `true` if `message` has neither content, attachments nor embeds.
+/// Such messages are sent without the message content intent.
+pub fn lacks_content(message: &Message) -> bool {
+    message.content.is_empty() && message.attachments.is_empty() && message.embeds.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gateway_intents, ContentDetector, Intent, EMPTY_THRESHOLD, REQUESTED};
+
+    use serenity::client::bridge::gateway::GatewayIntents;
+
+    #[test]
+    fn test_gateway_intents() {
+        let intents = gateway_intents();
+
+        assert!(intents.contains(GatewayIntents::GUILD_MEMBERS | GatewayIntents::GUILD_MESSAGES));
+        assert!(!REQUESTED.contains(&Intent::MessageContent));
+    }
+
+    #[test]
+    fn test_content_detector() {
+        let detector = ContentDetector::new();
+
+        for _ in 0..EMPTY_THRESHOLD - 1 {
+            assert!(!detector.record(true));
+        }
+
+        // A message with content resets the count.
+        assert!(!detector.record(false));
+        for _ in 0..EMPTY_THRESHOLD - 1 {
+            assert!(!detector.record(true));
+        }
+        assert!(!detector.is_degraded());
+
+        // Detected exactly once.
+        assert!(detector.record(true));
+        assert!(!detector.record(true));
+        assert!(detector.is_degraded());
+    }
+}
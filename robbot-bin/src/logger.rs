@@ -1,30 +1,95 @@
+//! The logger of the bot. Lines are written to stdout or a rotated log file,
+//! either human readable or as JSON objects. The level of every target is
+//! resolved by [`Filters`], which can be changed at runtime using
+//! [`set_filter`]. The most recent warnings and errors are kept in memory, see
+//! [`recent`].
 use chrono::Local;
 use log::{Level, LevelFilter, Log, Metadata, Record};
-use robbot_core::config::Config;
+use parking_lot::{const_mutex, const_rwlock, Mutex, RwLock};
+use robbot_core::config::{Config, LogFile, LogFormat};
 
-pub fn set_log_level(config: &Config) {
-    log::set_max_level(config.loglevel);
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// The number of warnings and errors kept by [`recent`].
+const RECENT_CAPACITY: usize = 200;
+
+static LOGGER: Logger = Logger {
+    filters: const_rwlock(Filters {
+        default: LevelFilter::Error,
+        targets: Vec::new(),
+    }),
+    output: const_mutex(Output {
+        format: LogFormat::Pretty,
+        file: None,
+    }),
+    recent: const_mutex(RingBuffer::new(RECENT_CAPACITY)),
+};
+
+/// Applies the logging configuration. The previous log file is kept if the
+/// new one cannot be opened.
+pub fn configure(config: &Config) -> io::Result<()> {
+    {
+        let mut output = LOGGER.output.lock();
+        output.format = config.logging.format;
+        output.file = match &config.logging.file {
+            Some(file) => Some(RotatingFile::open(file)?),
+            None => None,
+        };
+    }
+
+    let filters = Filters::new(config.loglevel, &config.logging.filters);
+    log::set_max_level(filters.max_level());
+    *LOGGER.filters.write() = filters;
+
+    Ok(())
+}
+
+/// Overrides the level of `target` and all its submodules until the logger is
+/// configured again.
+pub fn set_filter(target: &str, level: LevelFilter) {
+    let mut filters = LOGGER.filters.write();
+    filters.set(target, level);
+
+    log::set_max_level(filters.max_level());
+}
+
+/// Returns the most recent warnings and errors, oldest first.
+#[cfg(feature = "debug")]
+pub fn recent() -> Vec<String> {
+    LOGGER.recent.lock().lines.iter().cloned().collect()
 }
 
 pub fn init() {
-    log::set_logger(&Logger).unwrap();
+    log::set_logger(&LOGGER).unwrap();
     log::set_max_level(LevelFilter::Error);
 }
 
-pub struct Logger;
+pub struct Logger {
+    filters: RwLock<Filters>,
+    output: Mutex<Output>,
+    recent: Mutex<RingBuffer>,
+}
 
 impl Log for Logger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.target().starts_with("robbot")
+        metadata.level() <= self.filters.read().level(metadata.target())
     }
 
     fn log(&self, record: &Record) {
-        let now = Local::now().format("%Y-%m-%d %H:%M:%S");
+        if !self.enabled(record.metadata()) {
+            return;
+        }
 
-        if self.enabled(record.metadata()) {
-            println!(
+        let now = Local::now();
+        let mut output = self.output.lock();
+
+        let line = match output.format {
+            LogFormat::Pretty => format!(
                 "[{}] [{}:{}] [{}] {}",
-                now,
+                now.format("%Y-%m-%d %H:%M:%S"),
                 record.file().unwrap_or("???"),
                 record.line().unwrap_or(0),
                 match record.level() {
@@ -35,9 +100,323 @@ impl Log for Logger {
                     Level::Trace => "TRACE",
                 },
                 record.args()
-            );
+            ),
+            LogFormat::Json => serde_json::json!({
+                "timestamp": now.to_rfc3339(),
+                "level": record.level().to_string(),
+                "target": record.target(),
+                "file": record.file(),
+                "line": record.line(),
+                "message": record.args().to_string(),
+            })
+            .to_string(),
+        };
+
+        if record.level() <= Level::Warn {
+            self.recent.lock().push(line.clone());
+        }
+
+        match &mut output.file {
+            Some(file) => {
+                if let Err(err) = file.write_line(&line) {
+                    // Don't lose the line if the file becomes unwritable.
+                    eprintln!("Failed to write to log file: {}", err);
+                    println!("{}", line);
+                }
+            }
+            None => println!("{}", line),
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &mut self.output.lock().file {
+            let _ = file.file.flush();
+        }
+    }
+}
+
+struct Output {
+    format: LogFormat,
+    file: Option<RotatingFile>,
+}
+
+/// The last `capacity` lines, the oldest line is dropped first.
+struct RingBuffer {
+    lines: VecDeque<String>,
+    capacity: usize,
+}
+
+impl RingBuffer {
+    const fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    fn push(&mut self, line: String) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+
+        self.lines.push_back(line);
+    }
+}
+
+/// The levels of all targets. A filter applies to its target and all
+/// submodules of it, the most specific filter wins.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Filters {
+    /// The level of the bot's own targets without a filter.
+    default: LevelFilter,
+    /// Filters sorted by descending target length, so the first matching
+    /// filter is the most specific one.
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl Filters {
+    pub fn new(default: LevelFilter, filters: &HashMap<String, LevelFilter>) -> Self {
+        let mut this = Self {
+            default,
+            targets: Vec::with_capacity(filters.len()),
+        };
+
+        for (target, level) in filters {
+            this.set(target, *level);
         }
+
+        this
     }
 
-    fn flush(&self) {}
+    /// Sets the level of `target`, replacing an existing filter.
+    pub fn set(&mut self, target: &str, level: LevelFilter) {
+        self.targets.retain(|(t, _)| t != target);
+
+        let index = self
+            .targets
+            .iter()
+            .position(|(t, _)| t.len() < target.len())
+            .unwrap_or(self.targets.len());
+        self.targets.insert(index, (target.to_owned(), level));
+    }
+
+    /// Returns the level of `target`. Targets outside of the bot are disabled
+    /// unless they have a filter.
+    pub fn level(&self, target: &str) -> LevelFilter {
+        for (filter, level) in &self.targets {
+            if is_submodule(target, filter) {
+                return *level;
+            }
+        }
+
+        match target.starts_with("robbot") {
+            true => self.default,
+            false => LevelFilter::Off,
+        }
+    }
+
+    /// Returns the most verbose level of all filters.
+    pub fn max_level(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+/// Returns `true` if `target` is `module` or one of its submodules.
+fn is_submodule(target: &str, module: &str) -> bool {
+    match target.strip_prefix(module) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+        None => false,
+    }
+}
+
+/// A log file that is rotated once it grows past its maximum size.
+struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    keep: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(config: &LogFile) -> io::Result<Self> {
+        let path = PathBuf::from(&config.path);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            max_size: config.max_size,
+            keep: config.keep,
+            file,
+            size,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+
+        if should_rotate(self.size, len, self.max_size) {
+            self.rotate()?;
+        }
+
+        writeln!(self.file, "{}", line)?;
+        self.size += len;
+
+        Ok(())
+    }
+
+    /// Shifts all rotated files by one, dropping the oldest one, and starts a
+    /// new file.
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            self.file.set_len(0)?;
+            self.size = 0;
+            return Ok(());
+        }
+
+        for index in (1..self.keep).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(from, self.rotated_path(index + 1))?;
+            }
+        }
+
+        fs::rename(&self.path, self.rotated_path(1))?;
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+}
+
+/// Returns `true` if writing `len` bytes to a file of `size` bytes exceeds
+/// `max_size`. A line is always written to an empty file, even if it is
+/// longer than `max_size`.
+fn should_rotate(size: u64, len: u64, max_size: u64) -> bool {
+    size > 0 && size + len > max_size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_submodule, should_rotate, Filters, RingBuffer, RotatingFile};
+
+    use log::LevelFilter;
+    use robbot_core::config::LogFile;
+
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn test_filters() {
+        let filters = Filters::new(
+            LevelFilter::Info,
+            &HashMap::from([
+                (String::from("sqlx"), LevelFilter::Warn),
+                (String::from("robbot_core"), LevelFilter::Error),
+                (String::from("robbot_core::store"), LevelFilter::Debug),
+            ]),
+        );
+
+        assert_eq!(filters.level("robbot"), LevelFilter::Info);
+        assert_eq!(filters.level("robbot_bin::middleware"), LevelFilter::Info);
+        assert_eq!(filters.level("robbot_core::state"), LevelFilter::Error);
+        assert_eq!(filters.level("robbot_core::store"), LevelFilter::Debug);
+        assert_eq!(filters.level("robbot_core::store::mem"), LevelFilter::Debug);
+        assert_eq!(filters.level("robbot_core::storage"), LevelFilter::Error);
+        assert_eq!(filters.level("sqlx::query"), LevelFilter::Warn);
+        assert_eq!(filters.level("sqlxx"), LevelFilter::Off);
+        assert_eq!(filters.level("serenity::gateway"), LevelFilter::Off);
+        assert_eq!(filters.max_level(), LevelFilter::Debug);
+
+        let mut filters = filters;
+        filters.set("robbot_core::store", LevelFilter::Off);
+        filters.set("serenity", LevelFilter::Trace);
+        assert_eq!(filters.level("robbot_core::store::mem"), LevelFilter::Off);
+        assert_eq!(filters.level("serenity::gateway"), LevelFilter::Trace);
+        assert_eq!(filters.max_level(), LevelFilter::Trace);
+        assert_eq!(filters.targets.len(), 4);
+
+        assert!(is_submodule("a::b", "a"));
+        assert!(is_submodule("a", "a"));
+        assert!(!is_submodule("ab", "a"));
+        assert!(!is_submodule("a", "a::b"));
+    }
+
+    #[test]
+    fn test_ring_buffer() {
+        let mut buffer = RingBuffer::new(3);
+        for line in ["a", "b", "c", "d"] {
+            buffer.push(line.to_owned());
+        }
+
+        assert_eq!(buffer.lines, ["b", "c", "d"]);
+
+        let mut buffer = RingBuffer::new(0);
+        buffer.push(String::from("a"));
+        assert!(buffer.lines.is_empty());
+    }
+
+    #[test]
+    fn test_should_rotate() {
+        assert!(!should_rotate(0, 10, 100));
+        assert!(!should_rotate(90, 10, 100));
+        assert!(should_rotate(91, 10, 100));
+        // A line longer than the maximum size still fills an empty file.
+        assert!(!should_rotate(0, 200, 100));
+        assert!(should_rotate(1, 200, 100));
+    }
+
+    #[test]
+    fn test_rotating_file() {
+        let dir = std::env::temp_dir().join(format!("robbot-logger-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("robbot.log");
+        let mut file = RotatingFile::open(&LogFile {
+            path: path.to_str().unwrap().to_owned(),
+            max_size: 10,
+            keep: 2,
+        })
+        .unwrap();
+
+        for line in ["aaaa", "bbbb", "cccc", "dddd"] {
+            file.write_line(line).unwrap();
+        }
+
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("robbot.log"), "cccc\ndddd\n");
+        assert_eq!(read("robbot.log.1"), "aaaa\nbbbb\n");
+
+        file.write_line("eeee").unwrap();
+        assert_eq!(read("robbot.log"), "eeee\n");
+        assert_eq!(read("robbot.log.1"), "cccc\ndddd\n");
+        assert_eq!(read("robbot.log.2"), "aaaa\nbbbb\n");
+
+        // The oldest file is dropped.
+        file.write_line("ffffffff").unwrap();
+        assert_eq!(read("robbot.log"), "ffffffff\n");
+        assert_eq!(read("robbot.log.1"), "eeee\n");
+        assert_eq!(read("robbot.log.2"), "cccc\ndddd\n");
+        assert!(!dir.join("robbot.log.3").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
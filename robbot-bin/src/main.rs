@@ -1,9 +1,13 @@
 mod builtin;
 mod config;
+mod dispatch;
+mod handler;
 mod help;
+mod intents;
 mod logger;
-mod macros;
+mod middleware;
 mod model;
+#[cfg(feature = "permissions")]
 mod permissions;
 mod plugins;
 mod signal;
@@ -11,30 +15,23 @@ mod signal;
 /// Path of the default config.toml file.
 const DEFAULT_CONFIG: &str = "./config.toml";
 
-use async_trait::async_trait;
 use clap::Parser;
-use robbot::builder::CreateMessage;
-use robbot::model::channel::GuildMessage;
-use robbot::{arguments::CommandArguments, Command as _, Error};
-use robbot_core::command::MessageExecutor;
-use robbot_core::{router::parse_args, state::State};
-use serenity::{
-    client::{bridge::gateway::GatewayIntents, Client, Context, EventHandler},
-    model::channel::Message,
-};
+use handler::Handler;
+use robbot_core::state::State;
+use serenity::client::Client;
 use std::sync::Arc;
 
 use tokio::task;
 
-use serenity::model::guild::Member;
-use serenity::model::id::GuildId;
-use serenity::model::user::User;
-
 #[derive(Clone, Debug, Parser)]
 #[clap(version, long_about = None)]
 struct Args {
     #[clap(short, long, value_name = "FILE", default_value_t = String::from(DEFAULT_CONFIG))]
     config: String,
+    /// Upgrade the config file to the current version and exit. The original
+    /// file is kept as `<FILE>.bak`.
+    #[clap(long)]
+    migrate_config: bool,
 }
 
 #[tokio::main]
@@ -44,11 +41,28 @@ async fn main() {
     // Initialize the logger as early as possible.
     logger::init();
 
+    // Show the applied config migrations. The configured log level is set
+    // once the config is loaded.
+    log::set_max_level(log::LevelFilter::Info);
+
+    if args.migrate_config {
+        match config::migrate_file(&args.config) {
+            Ok(true) => log::info!("Migrated config file {}", args.config),
+            Ok(false) => log::info!("Config file {} is up to date", args.config),
+            Err(err) => {
+                log::error!("Failed to migrate config file {}: {}", args.config, err);
+                std::process::exit(1);
+            }
+        }
+
+        return;
+    }
+
     // Load the config.toml file.
     let config = match config::from_file(&args.config) {
         Ok(config) => config,
         Err(err) => {
-            log::error!("Failed to read config file {}: {:?}", args.config, err);
+            log::error!("Failed to read config file {}: {}", args.config, err);
             std::process::exit(1);
         }
     };
@@ -62,39 +76,56 @@ async fn main() {
 
     signal::init();
 
-    logger::set_log_level(&config);
+    if let Err(err) = logger::configure(&config) {
+        log::error!("Failed to open log file: {}", err);
+        std::process::exit(1);
+    }
 
-    let gateway_intents = GatewayIntents::GUILDS
-        | GatewayIntents::GUILD_MEMBERS
-        | GatewayIntents::GUILD_BANS
-        | GatewayIntents::GUILD_EMOJIS
-        | GatewayIntents::GUILD_INVITES
-        | GatewayIntents::GUILD_VOICE_STATES
-        | GatewayIntents::GUILD_MESSAGES
-        | GatewayIntents::GUILD_MESSAGE_REACTIONS
-        | GatewayIntents::DIRECT_MESSAGES
-        | GatewayIntents::DIRECT_MESSAGE_REACTIONS;
+    if !intents::is_available(&config, intents::Intent::MessageContent) {
+        intents::warn_degraded(intents::Intent::MessageContent, "disabled in the config");
+    }
 
     let state = Arc::new(State::new(config));
 
+    log::info!("[CORE] Connecting to store");
+
+    {
+        let database = &state.config.database;
+
+        if let Err(err) = state
+            .store()
+            .connect(database.retries, database.fallback)
+            .await
+        {
+            log::error!("[CORE] Failed to connect to store: {}", err);
+            log::error!("[CORE] Fatal error, exiting");
+            std::process::exit(1);
+        }
+    }
+
+    middleware::init(&state);
+
     log::info!("[CORE] Loading builtin commands");
 
     // Load all builtin functions.
-    if let Err(err) = builtin::init(&state) {
+    if let Err(err) = builtin::init(&state).await {
         log::error!("[CORE] Failed to load builtin functions: {:?}", err);
         log::error!("[CORE] Fatal error, exiting");
         std::process::exit(1);
     }
 
-    if let Err(err) = plugins::init(state.clone()).await {
-        log::error!("[CORE] Failed to load plugin: {:?}", err);
+    plugins::init(state.clone()).await;
+    state.modules().log_summary();
+
+    for name in state.tasks().unknown_overrides().await {
+        log::warn!("[TASK] Schedule configured for unknown task '{}'", name);
     }
 
     log::info!("[BOT] Connecting");
 
     let mut client = Client::builder(&state.config.token)
-        .intents(gateway_intents)
-        .event_handler(Handler { state })
+        .intents(intents::gateway_intents())
+        .event_handler(Handler::new(state.clone()))
         .await
         .unwrap();
 
@@ -107,181 +138,12 @@ async fn main() {
         shard_manager.lock().await.shutdown_all().await;
     });
 
-    client.start().await.unwrap();
-}
-
-pub struct Handler {
-    state: Arc<State>,
-}
-
-#[async_trait]
-impl EventHandler for Handler {
-    async fn guild_member_addition(&self, _ctx: Context, guild_id: GuildId, member: Member) {
-        let event = robbot::hook::GuildMemberAdditionData { guild_id, member };
-
-        self.state.hooks().dispatch_event(event).await;
+    if let Err(err) = client.start().await {
+        log::error!("[BOT] Client error: {:?}", err);
     }
 
-    async fn guild_member_removal(
-        &self,
-        _ctx: Context,
-        guild_id: GuildId,
-        user: User,
-        member: Option<Member>,
-    ) {
-        let event = robbot::hook::GuildMemberRemovalData {
-            guild_id,
-            user,
-            member,
-        };
-
-        self.state.hooks().dispatch_event(event).await;
-    }
-
-    async fn guild_member_update(&self, _ctx: Context, old_member: Option<Member>, member: Member) {
-        let event = robbot::hook::GuildMemberUpdateData { old_member, member };
-
-        self.state.hooks().dispatch_event(event).await;
-    }
-
-    async fn message(&self, raw_ctx: Context, message: Message) {
-        let message = robbot::model::channel::Message::from(message);
-
-        {
-            let event = robbot::hook::MessageData(message.clone());
-
-            self.state.hooks().dispatch_event(event).await;
-        }
-
-        let msg = match message.content.strip_prefix(&self.state.config.prefix) {
-            Some(msg) => msg,
-            None => return,
-        };
-
-        let mut args = parse_args(msg);
-        let mut cmd_args = CommandArguments::from(args.clone());
-
-        let cmd = match self.state.commands().get_command(&mut cmd_args) {
-            Some(cmd) => cmd,
-            None => return,
-        };
-
-        // Only retain the base path of the called command.
-        for _ in 0..cmd_args.as_args().len() {
-            args.remove(args.len() - 1);
-        }
-
-        let ctx = robbot_core::context::Context::new_with_args(
-            raw_ctx.clone(),
-            self.state.clone(),
-            message.clone(),
-            cmd_args.clone(),
-        );
-
-        // Return if the command is guild-only and the message is
-        // not send from within a guild.
-        if cmd.guild_only() && message.guild_id.is_none() {
-            let _ = ctx
-                .respond(":x: This command can only be used in guilds.")
-                .await;
-
-            return;
-        }
-
-        #[cfg(feature = "permissions")]
-        match permissions::has_permission(&ctx, cmd.permissions()).await {
-            Ok(ok) => {
-                if !ok {
-                    let _ = ctx
-                        .respond(":no_entry_sign: You are not allowed to run this command.")
-                        .await;
-                    return;
-                }
-            }
-            Err(err) => {
-                log::error!("Failed to check permissions: {:?}", err);
-                let _ = ctx.respond(":warning: Internal Server Error").await;
-                return;
-            }
-        }
-
-        let path = cmd_args.as_parsed_args().join(" ");
-
-        match cmd.executor() {
-            Some(executor) => {
-                let res = match executor {
-                    MessageExecutor::Message(executor) => executor.call(ctx.clone()).await,
-                    MessageExecutor::GuildMessage(executor) => {
-                        let ctx = match GuildMessage::try_from(ctx.event.clone()) {
-                            Ok(event) => ctx.clone().swap(event).0,
-                            Err(_) => {
-                                let _ = ctx
-                                    .respond(":x: This command can only be used in guilds.")
-                                    .await;
-                                return;
-                            }
-                        };
-
-                        executor.call(ctx).await
-                    }
-                };
-
-                if let Err(err) = res {
-                    match err {
-                        // Display command help message.
-                        Error::InvalidCommandUsage => {
-                            let _ = ctx
-                                .respond(CreateMessage::new(|m| {
-                                    m.embed(|e| {
-                                        e.title(format!("Command Help: {}", path));
-                                        e.color(builtin::EMBED_COLOR);
-                                        e.description(help::command(
-                                            &cmd,
-                                            &path,
-                                            &self.state.config.prefix,
-                                        ));
-                                    });
-                                }))
-                                .await;
-                        }
-                        _ => {
-                            let _ = ctx.respond(":warning: Internal Server Error").await;
-                            log::error!("Command '{}' returned an error: {:?}", args, err);
-                        }
-                    }
-                }
-            }
-            None => {
-                help::command(&cmd, &path, &self.state.config.prefix);
-
-                // Ignore error
-                let _ = ctx
-                    .respond(CreateMessage::new(|m| {
-                        m.embed(|e| {
-                            e.title(format!("Command Help: {}", path));
-                            e.color(builtin::EMBED_COLOR);
-                            e.description(help::command(&cmd, &path, &self.state.config.prefix));
-                        });
-                    }))
-                    .await;
-            }
-        }
-    }
-
-    async fn ready(&self, ctx: Context, _ready: serenity::model::gateway::Ready) {
-        log::info!("[BOT] Bot online");
-
-        let ctx = robbot_core::context::Context::new(ctx, self.state.clone(), ());
-
-        {
-            let mut connect_time = self.state.connect_time.write().unwrap();
-            *connect_time = Some(std::time::Instant::now());
-        }
-        {
-            let mut context = self.state.context.write().unwrap();
-            *context = Some(ctx.clone());
-        }
-
-        self.state.tasks().update_context(Some(ctx)).await;
-    }
+    // The client only returns once all shards are shut down. All shutdown
+    // paths go through `signal::terminate`.
+    log::info!("[CORE] Shutting down modules");
+    state.modules().shutdown(state.clone()).await;
 }
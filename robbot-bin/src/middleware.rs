@@ -0,0 +1,323 @@
+//! The builtin [`CommandMiddleware`]s run before every command.
+
+#[cfg(feature = "permissions")]
+use crate::permissions;
+
+use robbot::command::check_choices;
+use robbot::model::id::{ChannelId, GuildId};
+use robbot::model::permissions::Permissions;
+use robbot::Command as _;
+use robbot_core::command::SubCommand;
+use robbot_core::context::MessageContext;
+use robbot_core::cooldown::Acquire;
+use robbot_core::middleware::CommandMiddleware;
+#[cfg(feature = "permissions")]
+use robbot_core::middleware::EffectiveUser;
+use robbot_core::state::State;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serenity::client::Context;
+
+use std::ops::ControlFlow;
+
+/// Registers all builtin middlewares. The order of registration is the order
+/// in which the checks run.
+pub fn init(state: &State) {
+    state.middlewares().push(Disabled);
+    state.middlewares().push(Maintenance);
+    state.middlewares().push(GuildOnly);
+    state.middlewares().push(UserPermissions);
+    state.middlewares().push(BotPermissions);
+    state.middlewares().push(ArgChoices);
+    state.middlewares().push(Cooldown);
+    state.middlewares().push(crate::plugins::audit::Audit);
+}
+
+/// Rejects disabled commands.
+struct Disabled;
+
+#[async_trait]
+impl CommandMiddleware for Disabled {
+    fn name(&self) -> &str {
+        "disabled"
+    }
+
+    async fn before(&self, ctx: &MessageContext, _command: &SubCommand) -> ControlFlow<()> {
+        let path = ctx.args.as_parsed_args().join(" ");
+
+        if ctx.state.commands().disabled().is_disabled(&path) {
+            let _ = ctx
+                .respond(":no_entry_sign: This command is disabled.")
+                .await;
+            return ControlFlow::Break(());
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+/// Rejects commands marked `mutates_data` in maintenance mode.
+struct Maintenance;
+
+#[async_trait]
+impl CommandMiddleware for Maintenance {
+    fn name(&self) -> &str {
+        "maintenance"
+    }
+
+    async fn before(&self, ctx: &MessageContext, command: &SubCommand) -> ControlFlow<()> {
+        let mode = ctx.state.maintenance().get();
+
+        match mode.rejection(command.mutates_data()) {
+            Some(content) => {
+                let _ = ctx.respond(content).await;
+                ControlFlow::Break(())
+            }
+            None => ControlFlow::Continue(()),
+        }
+    }
+}
+
+/// Rejects guild-only commands outside of guilds.
+struct GuildOnly;
+
+#[async_trait]
+impl CommandMiddleware for GuildOnly {
+    fn name(&self) -> &str {
+        "guild_only"
+    }
+
+    async fn before(&self, ctx: &MessageContext, command: &SubCommand) -> ControlFlow<()> {
+        if command.guild_only() && ctx.event.guild_id.is_none() {
+            let _ = ctx
+                .respond(":x: This command can only be used in guilds.")
+                .await;
+
+            return ControlFlow::Break(());
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+/// Checks the permissions of the [`EffectiveUser`] of the invocation.
+struct UserPermissions;
+
+#[async_trait]
+impl CommandMiddleware for UserPermissions {
+    fn name(&self) -> &str {
+        "permissions"
+    }
+
+    // Permission metadata is always present on commands, but it is only enforced
+    // with the `permissions` feature.
+    #[cfg(not(feature = "permissions"))]
+    async fn before(&self, _ctx: &MessageContext, command: &SubCommand) -> ControlFlow<()> {
+        if !command.permissions().is_empty() {
+            static WARN_PERMISSIONS: std::sync::Once = std::sync::Once::new();
+
+            WARN_PERMISSIONS.call_once(|| {
+                log::warn!("[CORE] Commands declare permissions, but the `permissions` feature is disabled; permissions are not enforced");
+            });
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    #[cfg(feature = "permissions")]
+    async fn before(&self, ctx: &MessageContext, command: &SubCommand) -> ControlFlow<()> {
+        let subject = match ctx.extensions().get::<EffectiveUser>() {
+            Some(EffectiveUser(user_id)) => user_id,
+            None => ctx.event.author.id,
+        };
+
+        match permissions::has_permission(ctx, subject, command.permissions()).await {
+            Ok(true) => ControlFlow::Continue(()),
+            Ok(false) => {
+                let _ = ctx
+                    .respond(":no_entry_sign: You are not allowed to run this command.")
+                    .await;
+                ControlFlow::Break(())
+            }
+            Err(err) => {
+                log::error!("Failed to check permissions: {:?}", err);
+                let _ = ctx.respond(":warning: Internal Server Error").await;
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+/// Checks that the bot has all permissions required by the command before
+/// running it, instead of failing midway with an HTTP 403.
+struct BotPermissions;
+
+#[async_trait]
+impl CommandMiddleware for BotPermissions {
+    fn name(&self) -> &str {
+        "bot_permissions"
+    }
+
+    async fn before(&self, ctx: &MessageContext, command: &SubCommand) -> ControlFlow<()> {
+        let guild_id = match ctx.event.guild_id {
+            Some(guild_id) => guild_id,
+            None => return ControlFlow::Continue(()),
+        };
+
+        if command.bot_permissions().is_empty() {
+            return ControlFlow::Continue(());
+        }
+
+        match bot_permissions(&ctx.raw_ctx, guild_id, ctx.event.channel_id).await {
+            Some(permissions) => {
+                let missing = permissions.missing(command.bot_permissions());
+
+                if !missing.is_empty() {
+                    let _ = ctx.respond(missing_permissions(missing)).await;
+                    return ControlFlow::Break(());
+                }
+            }
+            // Let the command run and fail on its own.
+            None => log::warn!(
+                "[BOT] Cannot determine bot permissions in guild {}, channel {}",
+                guild_id,
+                ctx.event.channel_id
+            ),
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+/// Rejects arguments that are not one of the choices of the command, before
+/// they count towards the cooldown.
+struct ArgChoices;
+
+#[async_trait]
+impl CommandMiddleware for ArgChoices {
+    fn name(&self) -> &str {
+        "arg_choices"
+    }
+
+    async fn before(&self, ctx: &MessageContext, command: &SubCommand) -> ControlFlow<()> {
+        match check_choices(command, ctx.args.as_ref()) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(err) => {
+                let _ = ctx.respond(format!(":x: {}.", err)).await;
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+/// Enforces the cooldowns and daily quotas of commands. The use is recorded
+/// before the command runs, failed invocations count as well.
+struct Cooldown;
+
+#[async_trait]
+impl CommandMiddleware for Cooldown {
+    fn name(&self) -> &str {
+        "cooldown"
+    }
+
+    async fn before(&self, ctx: &MessageContext, command: &SubCommand) -> ControlFlow<()> {
+        let limits = command.limits();
+        if limits.is_empty() {
+            return ControlFlow::Continue(());
+        }
+
+        let path = ctx.args.as_parsed_args().join(" ");
+        let now = Utc::now().timestamp();
+
+        let res = ctx
+            .state
+            .cooldowns()
+            .acquire(&path, ctx.event.author.id.0, limits, now)
+            .await;
+
+        let content = match res {
+            Ok(Acquire::Allowed) => return ControlFlow::Continue(()),
+            Ok(Acquire::Cooldown { expires_at }) => format!(
+                ":hourglass: You can use this command again <t:{}:R>.",
+                expires_at
+            ),
+            Ok(Acquire::Quota { resets_at }) => format!(
+                ":hourglass: You reached the daily limit of this command. It resets <t:{}:R>.",
+                resets_at
+            ),
+            // Let the command run rather than locking everyone out while the
+            // store is unavailable.
+            Err(err) => {
+                log::warn!("[CORE] Failed to check cooldown of '{}': {:?}", path, err);
+                return ControlFlow::Continue(());
+            }
+        };
+
+        let _ = ctx.respond(content).await;
+        ControlFlow::Break(())
+    }
+}
+
+/// Returns the effective permissions of the bot in the channel `channel_id`,
+/// including channel overwrites. Returns `None` if the guild or the bot member
+/// is not cached.
+async fn bot_permissions(
+    raw_ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Option<Permissions> {
+    let user_id = raw_ctx.cache.current_user_id().await;
+    let member = raw_ctx.cache.member(guild_id, user_id).await?;
+
+    raw_ctx
+        .cache
+        .guild_field(guild_id, |guild| {
+            if guild.owner_id == user_id {
+                return Permissions::all();
+            }
+
+            let roles: Vec<_> = member.roles.iter().map(|id| (*id).into()).collect();
+
+            // The @everyone role shares the id of the guild.
+            let base: Permissions = guild
+                .roles
+                .values()
+                .filter(|role| role.id.0 == guild_id.0 || member.roles.contains(&role.id))
+                .map(|role| Permissions::from(role.permissions))
+                .collect();
+
+            // Threads are not cached as guild channels, use the guild-wide
+            // permissions for them.
+            let overwrites: Vec<_> = guild
+                .channels
+                .get(&channel_id.into())
+                .map(|channel| {
+                    channel
+                        .permission_overwrites
+                        .iter()
+                        .cloned()
+                        .filter_map(|overwrite| overwrite.try_into().ok())
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            base.with_overwrites(&overwrites, guild_id, user_id.into(), &roles)
+        })
+        .await
+}
+
+/// Formats the response for missing bot permissions.
+fn missing_permissions(missing: Permissions) -> String {
+    let mut names = missing.names();
+
+    match names.pop() {
+        Some(last) if !names.is_empty() => format!(
+            ":x: I need the {} and {} permissions.",
+            names.join(", "),
+            last
+        ),
+        Some(last) => format!(":x: I need the {} permission.", last),
+        None => String::new(),
+    }
+}
@@ -1,10 +1,15 @@
-use robbot::{model::id::RoleId, Error};
+use robbot::model::id::{RoleId, UserId};
+use robbot::Error;
 use robbot_core::context::MessageContext;
 
-/// Returns whether the command caller (determined by `ctx.author`) satisfies
-/// all `permissions`. If `has_permission` returns an Error, the command should
-/// either be aborted or rejected.
-pub async fn has_permission(ctx: &MessageContext, permissions: &[String]) -> Result<bool, Error> {
+/// Returns whether the user `subject` (usually the command caller) satisfies
+/// all `permissions` in the guild of `ctx`. If `has_permission` returns an
+/// Error, the command should either be aborted or rejected.
+pub async fn has_permission(
+    ctx: &MessageContext,
+    subject: UserId,
+    permissions: &[String],
+) -> Result<bool, Error> {
     // Skip the permission checks if the command
     // requires no permissions.
     if permissions.is_empty() {
@@ -18,30 +23,20 @@ pub async fn has_permission(ctx: &MessageContext, permissions: &[String]) -> Res
     };
 
     // All admins defined in the config file are always allowed.
-    if ctx.state.config.admins.contains(&ctx.event.author.id) {
+    if ctx.state.config.admins.contains(&subject) {
         return Ok(true);
     }
 
-    // Get the message author.
+    // Get the member of the subject.
     let member = {
         // Try member from cache.
-        match ctx
-            .raw_ctx
-            .cache
-            .member(guild_id.0, ctx.event.author.id.0)
-            .await
-        {
+        match ctx.raw_ctx.cache.member(guild_id.0, subject.0).await {
             Some(member) => member,
-            None => {
-                ctx.raw_ctx
-                    .http
-                    .get_member(guild_id.0, ctx.event.author.id.0)
-                    .await?
-            }
+            None => ctx.raw_ctx.http.get_member(guild_id.0, subject.0).await?,
         }
     };
 
-    let user_id = ctx.event.author.id;
+    let user_id = subject;
     let roles: Vec<_> = member
         .roles
         .into_iter()
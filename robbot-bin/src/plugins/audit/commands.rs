@@ -0,0 +1,127 @@
+use super::{nodes, AuditEntry};
+
+use robbot::builder::EmbedBuilder;
+use robbot::prelude::ArgumentsExt;
+use robbot::store::{delete, get, OrderBy};
+use robbot::{command, Error, Result};
+use robbot_core::context::GuildMessageContext;
+use robbot_core::settings::TimeDisplay;
+
+use chrono::{TimeZone, Utc};
+
+use std::fmt::Write;
+
+/// The number of entries listed by default.
+const DEFAULT_LIMIT: usize = 10;
+/// The maximum number of entries listed. Keeps the embed description within
+/// its length limit.
+const MAX_LIMIT: usize = 20;
+
+#[command(
+    description = "List the most recent configuration changes in this guild.",
+    usage = "[Limit]",
+    example = "20",
+    permissions = [nodes::VIEW],
+    bot_permissions = [EMBED_LINKS],
+)]
+async fn log(mut ctx: GuildMessageContext) -> Result {
+    let limit = match ctx.args.pop() {
+        Some(arg) => match arg.parse::<usize>() {
+            Ok(limit) if (1..=MAX_LIMIT).contains(&limit) => limit,
+            _ => return Err(Error::InvalidCommandUsage),
+        },
+        None => DEFAULT_LIMIT,
+    };
+
+    let query = ctx
+        .state
+        .store()
+        .make_query::<AuditEntry>()
+        .guild_id(ctx.event.guild_id);
+
+    let entries = ctx
+        .state
+        .store()
+        .get_ordered(
+            ctx.state.store().make_descriptor::<AuditEntry>(),
+            query,
+            OrderBy::desc("timestamp"),
+        )
+        .await?;
+
+    let settings = ctx.time_settings().await?;
+
+    let mut description = String::new();
+    for entry in entries.iter().take(limit) {
+        let time = Utc
+            .timestamp_opt(entry.timestamp, 0)
+            .single()
+            .unwrap_or_default();
+
+        let _ = writeln!(
+            description,
+            "{} {}: `{}`",
+            settings.format(time, TimeDisplay::Markdown),
+            entry.user_id.mention(),
+            entry.summary
+        );
+    }
+
+    if entries.is_empty() {
+        description.push_str("No configuration changes recorded.");
+    }
+
+    ctx.respond(
+        EmbedBuilder::new()
+            .title("Audit Log")
+            .description(description),
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "Delete all audit entries of this guild. Run `audit clear confirm` to confirm.",
+    usage = "[confirm]",
+    permissions = [nodes::CLEAR],
+    ack_only,
+    mutates_data,
+)]
+async fn clear(mut ctx: GuildMessageContext) -> Result {
+    let guild_id = ctx.event.guild_id;
+
+    match ctx.args.pop().as_deref() {
+        Some("confirm") => (),
+        Some(_) => return Err(Error::InvalidCommandUsage),
+        None => {
+            let entries = get!(ctx.state.store(), AuditEntry => {
+                guild_id == guild_id,
+            })
+            .await?;
+
+            ctx.respond(format!(
+                ":warning: This deletes all {} audit entries of this guild. Run `{}audit clear confirm` to continue.",
+                entries.len(),
+                ctx.state.config.prefix
+            ))
+            .await?;
+            return Ok(());
+        }
+    }
+
+    ctx.state.store().check_writable()?;
+
+    delete!(ctx.state.store(), AuditEntry => {
+        guild_id == guild_id,
+    })
+    .await?;
+
+    log::info!(
+        "[AUDIT] {} cleared the audit log of guild {}",
+        ctx.event.author.id,
+        guild_id
+    );
+
+    Ok(())
+}
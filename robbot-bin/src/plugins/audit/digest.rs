@@ -0,0 +1,46 @@
+use super::AuditEntry;
+
+use crate::plugins::digest::{DigestLine, DigestProvider};
+
+use async_trait::async_trait;
+use robbot::model::id::GuildId;
+use robbot::store::get;
+use robbot::Error;
+use robbot_core::state::State;
+
+use std::collections::HashSet;
+use std::result;
+
+/// Reports configuration changes recorded by the audit plugin.
+pub struct Digest;
+
+#[async_trait]
+impl DigestProvider for Digest {
+    fn name(&self) -> &str {
+        "Configuration"
+    }
+
+    async fn collect(
+        &self,
+        state: &State,
+        guild_id: GuildId,
+        since: i64,
+    ) -> result::Result<Vec<DigestLine>, Error> {
+        let entries = get!(state.store(), AuditEntry => {
+            guild_id == guild_id,
+        })
+        .await?;
+
+        let entries: Vec<_> = entries.iter().filter(|e| e.timestamp >= since).collect();
+        if entries.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let users: HashSet<_> = entries.iter().map(|e| e.user_id).collect();
+
+        Ok(vec![
+            DigestLine::new("Changes", entries.len()),
+            DigestLine::new("Moderators", users.len()),
+        ])
+    }
+}
@@ -0,0 +1,305 @@
+//! # Audit plugin
+//! Records who changed the configuration of a guild. Every successful
+//! invocation of a command marked `audited` in a guild is stored as an
+//! [`AuditEntry`] containing the command path and its arguments. Commands
+//! marked `private_args` are recorded without their arguments.
+//!
+//! At most [`MAX_ENTRIES`] entries are kept per guild, older entries are
+//! pruned daily. Entries older than the retention window of the guild are
+//! deleted by the retention plugin.
+mod commands;
+mod digest;
+mod profile;
+
+pub use digest::Digest;
+pub use profile::Profile;
+
+use chrono::Utc;
+use robbot::model::id::{GuildId, UserId};
+use robbot::store::{delete, insert};
+use robbot::{module, task, Result, StoreData};
+use robbot_core::command::SubCommand;
+use robbot_core::context::{Context, MessageContext};
+use robbot_core::middleware::CommandMiddleware;
+
+use async_trait::async_trait;
+
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+
+/// The number of days entries are kept without a retention policy.
+pub const RETENTION_DAYS: i64 = 90;
+/// The maximum number of entries kept per guild.
+pub const MAX_ENTRIES: usize = 1000;
+
+/// The maximum number of characters of a single argument in a summary.
+const MAX_ARG_LEN: usize = 32;
+/// The maximum number of characters of a summary.
+const MAX_SUMMARY_LEN: usize = 100;
+
+pub mod nodes {
+    robbot::permission_nodes! {
+        "audit" => {
+            VIEW = "view",
+            CLEAR = "clear",
+        }
+    }
+}
+
+module! {
+    name: "audit",
+    cmds: {
+        "audit": {
+            commands::log,
+            commands::clear,
+        },
+    },
+    store: [
+        AuditEntry,
+    ],
+    tasks: [
+        prune,
+    ],
+    permissions: nodes::NODES,
+}
+
+/// A successful invocation of an audited command.
+#[derive(Clone, Debug, StoreData)]
+pub struct AuditEntry {
+    pub guild_id: GuildId,
+    pub user_id: UserId,
+    pub command_path: String,
+    /// The command path and the sanitized arguments, see [`summary`].
+    pub summary: String,
+    /// The time of the invocation as a unix timestamp in seconds.
+    pub timestamp: i64,
+}
+
+/// Records successful invocations of audited commands in guilds.
+pub struct Audit;
+
+#[async_trait]
+impl CommandMiddleware for Audit {
+    fn name(&self) -> &str {
+        "audit"
+    }
+
+    async fn before(&self, _ctx: &MessageContext, _command: &SubCommand) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    async fn after(&self, ctx: &MessageContext, command: &SubCommand, result: &Result) {
+        let guild_id = match ctx.event.guild_id {
+            Some(guild_id) => guild_id,
+            None => return,
+        };
+
+        if result.is_err() || !command.audited() {
+            return;
+        }
+
+        if ctx.state.store().check_writable().is_err() {
+            log::warn!(
+                "[AUDIT] Store is not writable, dropping audit entry in guild {}",
+                guild_id
+            );
+            return;
+        }
+
+        let command_path = ctx.args.as_parsed_args().join(" ");
+        let summary = summary(&command_path, ctx.args.as_ref(), command.private_args());
+
+        let res = insert!(
+            ctx.state.store(),
+            AuditEntry {
+                guild_id,
+                user_id: ctx.event.author.id,
+                command_path,
+                summary,
+                timestamp: Utc::now().timestamp(),
+            }
+        )
+        .await;
+
+        if let Err(err) = res {
+            log::error!("[AUDIT] Failed to insert audit entry: {:?}", err);
+        }
+    }
+}
+
+/// Returns the summary of an invocation of the command at `path` with
+/// `args`. Backticks and control characters are removed from the arguments
+/// and long arguments are truncated. If `private` is `true` the arguments
+/// are omitted.
+pub fn summary(path: &str, args: &[String], private: bool) -> String {
+    let mut summary = String::from(path);
+
+    if private && !args.is_empty() {
+        summary.push_str(" [arguments hidden]");
+        return summary;
+    }
+
+    for arg in args {
+        let arg: String = arg
+            .chars()
+            .filter(|c| *c != '`')
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+
+        summary.push(' ');
+        summary.push_str(&truncate(&arg, MAX_ARG_LEN));
+    }
+
+    truncate(&summary, MAX_SUMMARY_LEN)
+}
+
+/// Truncates `s` to at most `len` characters, ending truncated strings with
+/// an ellipsis.
+fn truncate(s: &str, len: usize) -> String {
+    match s.char_indices().nth(len) {
+        Some(_) => {
+            let mut s: String = s.chars().take(len - 1).collect();
+            s.push('…');
+            s
+        }
+        None => s.to_owned(),
+    }
+}
+
+/// Returns the timestamps of all entries to remove by guild. Entries are
+/// removed if they are older than `min_timestamp` or if more than
+/// `max_entries` newer entries exist in the same guild.
+fn expired(
+    entries: &[AuditEntry],
+    min_timestamp: i64,
+    max_entries: usize,
+) -> HashMap<GuildId, Vec<i64>> {
+    let mut guilds: HashMap<GuildId, Vec<i64>> = HashMap::new();
+    for entry in entries {
+        guilds
+            .entry(entry.guild_id)
+            .or_default()
+            .push(entry.timestamp);
+    }
+
+    guilds
+        .into_iter()
+        .filter_map(|(guild_id, mut timestamps)| {
+            // Newest first.
+            timestamps.sort_unstable_by(|a, b| b.cmp(a));
+
+            let cutoff = match timestamps.get(max_entries) {
+                // Keep all entries sharing the timestamp of the last kept
+                // entry, they cannot be deleted individually.
+                Some(timestamp) if timestamps[max_entries - 1] == *timestamp => *timestamp,
+                Some(timestamp) => timestamp + 1,
+                None => min_timestamp,
+            }
+            .max(min_timestamp);
+
+            let mut expired: Vec<i64> = timestamps.into_iter().filter(|t| *t < cutoff).collect();
+            expired.dedup();
+
+            (!expired.is_empty()).then_some((guild_id, expired))
+        })
+        .collect()
+}
+
+#[task(interval = "1d", on_load = true, mutates_data = true)]
+async fn prune(ctx: Context<()>) -> Result {
+    ctx.state.store().check_writable()?;
+
+    let entries = ctx
+        .state
+        .store()
+        .get_all(ctx.state.store().make_descriptor::<AuditEntry>())
+        .await?;
+
+    for (guild_id, timestamps) in expired(&entries, i64::MIN, MAX_ENTRIES) {
+        log::debug!(
+            "[AUDIT] Pruning {} audit timestamps in guild {}",
+            timestamps.len(),
+            guild_id
+        );
+
+        delete!(ctx.state.store(), AuditEntry => {
+            guild_id == guild_id,
+            timestamp in timestamps,
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expired, summary, AuditEntry};
+
+    use robbot::model::id::{GuildId, UserId};
+
+    fn entry(guild_id: u64, timestamp: i64) -> AuditEntry {
+        AuditEntry {
+            guild_id: GuildId(guild_id),
+            user_id: UserId(1),
+            command_path: String::from("log set"),
+            summary: String::from("log set"),
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(summary("log set", &[], false), "log set");
+        assert_eq!(
+            summary("log set", &args(&["<#123>"]), false),
+            "log set <#123>"
+        );
+        assert_eq!(
+            summary("automod add", &args(&["word", "`a`\nb"]), false),
+            "automod add word a b"
+        );
+        assert_eq!(
+            summary("log set", &args(&["secret"]), true),
+            "log set [arguments hidden]"
+        );
+        assert_eq!(summary("log set", &[], true), "log set");
+
+        let long = "x".repeat(100);
+        let s = summary("log set", &args(&[&long]), false);
+        assert_eq!(s.chars().count(), "log set ".len() + 32);
+        assert!(s.ends_with('…'));
+
+        let many = args(&[&long, &long, &long, &long]);
+        assert_eq!(summary("log set", &many, false).chars().count(), 100);
+    }
+
+    #[test]
+    fn test_expired() {
+        let entries = [
+            entry(1, 10),
+            entry(1, 20),
+            entry(1, 30),
+            entry(1, 40),
+            entry(2, 5),
+            entry(2, 50),
+        ];
+
+        // By age.
+        let expired_by_age = expired(&entries, 25, 100);
+        assert_eq!(expired_by_age[&GuildId(1)], vec![20, 10]);
+        assert_eq!(expired_by_age[&GuildId(2)], vec![5]);
+
+        // By count.
+        let expired_by_count = expired(&entries, 0, 2);
+        assert_eq!(expired_by_count[&GuildId(1)], vec![20, 10]);
+        assert!(!expired_by_count.contains_key(&GuildId(2)));
+
+        // Entries sharing the timestamp of the last kept entry are kept.
+        let entries = [entry(1, 10), entry(1, 20), entry(1, 20), entry(1, 30)];
+        assert_eq!(expired(&entries, 0, 2)[&GuildId(1)], vec![10]);
+        assert!(expired(&entries, 0, 4).is_empty());
+    }
+}
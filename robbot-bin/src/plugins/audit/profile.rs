@@ -0,0 +1,52 @@
+use super::AuditEntry;
+
+use crate::plugins::profile::{ProfileProvider, ProfileSection};
+
+use async_trait::async_trait;
+use robbot::model::id::{GuildId, UserId};
+use robbot::store::get;
+use robbot::Error;
+use robbot_core::state::State;
+
+use std::result;
+
+/// The number of recent configuration changes shown.
+const RECENT: usize = 3;
+
+/// Shows the recent configuration changes made by a member.
+pub struct Profile;
+
+#[async_trait]
+impl ProfileProvider for Profile {
+    fn name(&self) -> &str {
+        "Configuration changes"
+    }
+
+    async fn section(
+        &self,
+        state: &State,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> result::Result<Option<ProfileSection>, Error> {
+        let mut entries = get!(state.store(), AuditEntry => {
+            guild_id == guild_id,
+            user_id == user_id,
+        })
+        .await?;
+
+        // Most members never change the configuration.
+        if entries.is_empty() {
+            return Ok(None);
+        }
+
+        entries.sort_by_key(|entry| -entry.timestamp);
+
+        let lines = entries
+            .iter()
+            .take(RECENT)
+            .map(|entry| format!("<t:{}:R> `{}`", entry.timestamp, entry.summary))
+            .collect();
+
+        Ok(Some(ProfileSection::new(self.name(), lines)))
+    }
+}
@@ -0,0 +1,161 @@
+use super::rules::is_valid_word_pattern;
+use super::{nodes, rules, Action, AutomodRule, RuleKind};
+
+use robbot::arguments::RoleMention;
+use robbot::builder::CreateMessage;
+use robbot::model::id::RoleId;
+use robbot::prelude::ArgumentsExt;
+use robbot::store::{delete, get_one, insert};
+use robbot::{command, Error, Result};
+use robbot_core::context::GuildMessageContext;
+
+use std::fmt::Write;
+
+#[command(
+    description = "Add a new rule. Kinds are `word <pattern>` (`*` matches any characters), `invite` and `mentions <count>`. Actions are `delete`, `warn` and `delete+warn`. Members with the exempt role are not affected by the rule.",
+    usage = "<word <pattern>|invite|mentions <count>> <delete|warn|delete+warn> [@Exempt]",
+    example = "word badword* delete",
+    permissions = [nodes::MANAGE],
+    audited,
+    mutates_data,
+)]
+async fn add(mut ctx: GuildMessageContext) -> Result {
+    let kind: RuleKind = ctx.args.pop_parse()?;
+
+    let pattern = match kind {
+        RuleKind::BannedWord => {
+            let pattern = ctx.args.pop().ok_or(Error::InvalidCommandUsage)?;
+
+            if !is_valid_word_pattern(&pattern) {
+                ctx.respond(":x: Word patterns may only contain letters, digits and `*`.")
+                    .await?;
+                return Ok(());
+            }
+
+            pattern.to_lowercase()
+        }
+        RuleKind::InviteLink => String::new(),
+        RuleKind::MassMention => {
+            let count: usize = ctx.args.pop_parse()?;
+
+            if count == 0 {
+                return Err(Error::InvalidCommandUsage);
+            }
+
+            count.to_string()
+        }
+    };
+
+    let action: Action = ctx.args.pop_parse()?;
+
+    let exempt_role = match ctx.args.pop() {
+        Some(arg) => {
+            let role: RoleMention = arg.parse().or(Err(Error::InvalidCommandUsage))?;
+            role.id
+        }
+        None => RoleId(0),
+    };
+
+    ctx.state.store().check_writable()?;
+
+    let guild_id = ctx.event.guild_id;
+
+    let id = rules(&ctx, guild_id)
+        .await?
+        .last()
+        .map(|rule| rule.id + 1)
+        .unwrap_or(1);
+
+    insert!(
+        ctx.state.store(),
+        AutomodRule {
+            id,
+            guild_id,
+            kind,
+            pattern,
+            action,
+            exempt_role,
+        }
+    )
+    .await?;
+
+    ctx.respond(format!(":white_check_mark: Added rule #{}.", id))
+        .await?;
+    Ok(())
+}
+
+#[command(
+    description = "List all rules of the guild.",
+    permissions = [nodes::MANAGE],
+)]
+async fn list(ctx: GuildMessageContext) -> Result {
+    let rules = rules(&ctx, ctx.event.guild_id).await?;
+
+    let mut description = String::new();
+    for rule in &rules {
+        let _ = write!(description, "`#{}` `{}`", rule.id, rule.kind);
+
+        if !rule.pattern.is_empty() {
+            let _ = write!(description, " `{}`", rule.pattern);
+        }
+
+        let _ = write!(description, ": `{}`", rule.action);
+
+        if let Some(role_id) = rule.exempt_role() {
+            let _ = write!(description, " (exempt: {})", role_id.mention());
+        }
+
+        description.push('\n');
+    }
+
+    if rules.is_empty() {
+        description.push_str("No rules configured.");
+    }
+
+    ctx.respond(CreateMessage::new(|m| {
+        m.embed(|e| {
+            e.title("Automod Rules");
+            e.description(description);
+        });
+    }))
+    .await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "Remove a rule.",
+    usage = "<ID>",
+    example = "1",
+    permissions = [nodes::MANAGE],
+    ack_only,
+    audited,
+    mutates_data,
+)]
+async fn remove(mut ctx: GuildMessageContext) -> Result {
+    let id: u64 = ctx.args.pop_parse()?;
+
+    ctx.state.store().check_writable()?;
+
+    let guild_id = ctx.event.guild_id;
+
+    let rule = get_one!(ctx.state.store(), AutomodRule => {
+        guild_id == guild_id,
+        id == id,
+    })
+    .await?;
+
+    if rule.is_none() {
+        ctx.respond(format!(":x: There is no rule #{}.", id))
+            .await?;
+        return Ok(());
+    }
+
+    delete!(ctx.state.store(), AutomodRule => {
+        guild_id == guild_id,
+        id == id,
+    })
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use super::AutomodWarning;
+
+use crate::plugins::digest::{DigestLine, DigestProvider};
+
+use async_trait::async_trait;
+use robbot::model::id::GuildId;
+use robbot::store::get;
+use robbot::Error;
+use robbot_core::state::State;
+
+use std::collections::HashSet;
+use std::result;
+
+/// Reports warnings issued by automod.
+pub struct Digest;
+
+#[async_trait]
+impl DigestProvider for Digest {
+    fn name(&self) -> &str {
+        "Automod"
+    }
+
+    async fn collect(
+        &self,
+        state: &State,
+        guild_id: GuildId,
+        since: i64,
+    ) -> result::Result<Vec<DigestLine>, Error> {
+        let warnings = get!(state.store(), AutomodWarning => {
+            guild_id == guild_id,
+        })
+        .await?;
+
+        let warnings: Vec<_> = warnings.iter().filter(|w| w.timestamp >= since).collect();
+        if warnings.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let users: HashSet<_> = warnings.iter().map(|w| w.user_id).collect();
+
+        Ok(vec![
+            DigestLine::new("Warnings issued", warnings.len()),
+            DigestLine::new("Members warned", users.len()),
+        ])
+    }
+}
@@ -0,0 +1,220 @@
+//! # Automod plugin
+//! Checks messages against per-guild rules and deletes the message and/or
+//! warns the author when a rule matches. Rules can ban words, invite links
+//! or messages mentioning too many users and roles.
+//!
+//! Messages in ignored channels, messages from bots and members with the
+//! exempt role of a rule are never checked.
+mod commands;
+mod digest;
+mod profile;
+mod rules;
+
+pub use digest::Digest;
+pub use profile::Profile;
+pub use rules::{Action, RuleKind};
+
+use crate::plugins::log::{LogEvent, LogLevel};
+
+use chrono::Utc;
+use robbot::model::channel::Message;
+use robbot::model::id::{GuildId, RoleId, UserId};
+use robbot::store::{get, insert};
+use robbot::{hook, module, Error, Result, StoreData};
+use robbot_core::bus::WarningIssued;
+use robbot_core::context::Context;
+use robbot_core::hook::MessageContext;
+
+use std::collections::HashSet;
+use std::result;
+
+pub mod nodes {
+    robbot::permission_nodes! {
+        "automod" => {
+            MANAGE = "manage",
+        }
+    }
+}
+
+module! {
+    name: "automod",
+    cmds: {
+        "automod": {
+            commands::add,
+            commands::list,
+            commands::remove,
+        },
+    },
+    store: [
+        AutomodRule,
+        AutomodWarning,
+    ],
+    hooks: [
+        message,
+    ],
+    permissions: nodes::NODES,
+}
+
+/// A rule of a guild.
+#[derive(Clone, Debug, StoreData)]
+pub struct AutomodRule {
+    /// The id of the rule, unique within the guild.
+    pub id: u64,
+    pub guild_id: GuildId,
+    #[store(as = "string")]
+    pub kind: RuleKind,
+    pub pattern: String,
+    #[store(as = "string")]
+    pub action: Action,
+    /// Members with this role are not affected by the rule. `RoleId(0)` if
+    /// no role is exempt.
+    pub exempt_role: RoleId,
+}
+
+impl AutomodRule {
+    pub fn exempt_role(&self) -> Option<RoleId> {
+        (self.exempt_role.0 != 0).then_some(self.exempt_role)
+    }
+}
+
+/// A warning issued to a member for violating a rule.
+#[derive(Clone, Debug, StoreData)]
+pub struct AutomodWarning {
+    pub guild_id: GuildId,
+    pub user_id: UserId,
+    pub rule_id: u64,
+    /// The time of the warning as a unix timestamp in seconds.
+    pub timestamp: i64,
+}
+
+/// Returns all rules of a guild, ordered by id.
+pub async fn rules<T>(
+    ctx: &Context<T>,
+    guild_id: GuildId,
+) -> result::Result<Vec<AutomodRule>, Error>
+where
+    T: Send + Sync,
+{
+    let mut rules = get!(ctx.state.store(), AutomodRule => {
+        guild_id == guild_id,
+    })
+    .await?;
+
+    rules.sort_by_key(|rule| rule.id);
+    Ok(rules)
+}
+
+/// Returns the number of distinct users and roles mentioned in a message.
+fn mention_count(message: &Message) -> usize {
+    let users: HashSet<_> = message.mentions.iter().map(|user| user.id).collect();
+    let roles: HashSet<_> = message.mention_roles.iter().collect();
+
+    users.len() + roles.len()
+}
+
+#[hook]
+async fn message(ctx: MessageContext) -> Result {
+    let message = &ctx.event.0;
+
+    let guild_id = match message.guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+
+    if message.author.bot || ctx.is_ignored_channel().await? {
+        return Ok(());
+    }
+
+    let rules = rules(&ctx, guild_id).await?;
+    if rules.is_empty() {
+        return Ok(());
+    }
+
+    let mentions = mention_count(message);
+
+    // The roles of the author are only fetched if a matching rule has an
+    // exempt role.
+    let mut roles: Option<Vec<RoleId>> = None;
+
+    for rule in rules {
+        if !rule
+            .kind
+            .is_match(&rule.pattern, &message.content, mentions)
+        {
+            continue;
+        }
+
+        if let Some(exempt_role) = rule.exempt_role() {
+            if roles.is_none() {
+                roles = Some(match &message.member {
+                    Some(member) => member.roles.clone(),
+                    None => ctx.member(guild_id, message.author.id).await?.roles,
+                });
+            }
+
+            if roles.as_ref().unwrap().contains(&exempt_role) {
+                continue;
+            }
+        }
+
+        return apply(&ctx, guild_id, &rule).await;
+    }
+
+    Ok(())
+}
+
+/// Performs the action of a matched rule.
+async fn apply(ctx: &MessageContext, guild_id: GuildId, rule: &AutomodRule) -> Result {
+    let message = &ctx.event.0;
+    let action = rule.action;
+
+    if action.delete() {
+        ctx.delete_message(message.channel_id, message.id).await?;
+    }
+
+    if action.warn() {
+        if ctx.state.store().check_writable().is_ok() {
+            insert!(
+                ctx.state.store(),
+                AutomodWarning {
+                    guild_id,
+                    user_id: message.author.id,
+                    rule_id: rule.id,
+                    timestamp: Utc::now().timestamp(),
+                }
+            )
+            .await?;
+        }
+
+        ctx.state.bus().publish(WarningIssued {
+            guild_id,
+            user_id: message.author.id,
+            reason: format!("Automod rule #{} (`{}`)", rule.id, rule.kind),
+        });
+
+        ctx.send_message(
+            message.channel_id,
+            format!(
+                ":warning: {}, your message violates the rules of this server.",
+                message.author.id.mention()
+            ),
+        )
+        .await?;
+    }
+
+    crate::plugins::log::log(LogEvent {
+        level: LogLevel::Info,
+        guild_id,
+        target: Some("automod".to_owned()),
+        content: format!(
+            "Rule #{} (`{}`) matched a message of {} in {}, action: `{}`",
+            rule.id,
+            rule.kind,
+            message.author.id.mention(),
+            message.channel_id.mention(),
+            action
+        ),
+    });
+
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use super::AutomodWarning;
+
+use crate::plugins::profile::{ProfileProvider, ProfileSection};
+
+use async_trait::async_trait;
+use robbot::model::id::{GuildId, UserId};
+use robbot::store::get;
+use robbot::Error;
+use robbot_core::state::State;
+
+use std::result;
+
+/// Shows the warnings issued to a member by automod.
+pub struct Profile;
+
+#[async_trait]
+impl ProfileProvider for Profile {
+    fn name(&self) -> &str {
+        "Warnings"
+    }
+
+    async fn section(
+        &self,
+        state: &State,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> result::Result<Option<ProfileSection>, Error> {
+        let warnings = get!(state.store(), AutomodWarning => {
+            guild_id == guild_id,
+            user_id == user_id,
+        })
+        .await?;
+
+        let mut lines = vec![format!("Warnings: **{}**", warnings.len())];
+        if let Some(last) = warnings.iter().map(|w| w.timestamp).max() {
+            lines.push(format!("Last warning: <t:{}:R>", last));
+        }
+
+        Ok(Some(ProfileSection::new(self.name(), lines)))
+    }
+}
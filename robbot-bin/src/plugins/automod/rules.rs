@@ -0,0 +1,230 @@
+//! The matching engine of the automod plugin.
+//!
+//! Matching works on the raw message content. Unicode lookalikes (e.g. a
+//! cyrillic `а` in place of a latin `a`), zero-width characters and other
+//! obfuscation are not normalized and are out of scope for now.
+use regex::Regex;
+use robbot::store::StoreEnum;
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// The kind of an automod rule. The pattern of a rule is interpreted
+/// depending on the kind.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, StoreEnum)]
+pub enum RuleKind {
+    /// Matches a word using a case-insensitive pattern. A `*` in the pattern
+    /// matches any number of characters.
+    #[store(rename = "word")]
+    BannedWord,
+    /// Matches any Discord invite link. The pattern is unused.
+    #[store(rename = "invite")]
+    InviteLink,
+    /// Matches if a message mentions at least the number of users and roles
+    /// given in the pattern.
+    #[store(rename = "mentions")]
+    MassMention,
+}
+
+impl RuleKind {
+    /// Returns `true` if `content` or `mentions` violate the rule.
+    pub fn is_match(self, pattern: &str, content: &str, mentions: usize) -> bool {
+        match self {
+            Self::BannedWord => contains_word(pattern, content),
+            Self::InviteLink => contains_invite(content),
+            Self::MassMention => match pattern.parse::<usize>() {
+                Ok(threshold) => mentions >= threshold,
+                Err(_) => false,
+            },
+        }
+    }
+}
+
+impl Display for RuleKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_name())
+    }
+}
+
+impl FromStr for RuleKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_name(s).ok_or(())
+    }
+}
+
+/// The action taken when a message matches a rule.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, StoreEnum)]
+pub enum Action {
+    Delete,
+    Warn,
+    #[store(rename = "delete+warn")]
+    DeleteAndWarn,
+}
+
+impl Action {
+    pub fn delete(self) -> bool {
+        matches!(self, Self::Delete | Self::DeleteAndWarn)
+    }
+
+    pub fn warn(self) -> bool {
+        matches!(self, Self::Warn | Self::DeleteAndWarn)
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_name())
+    }
+}
+
+impl FromStr for Action {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_name(s).ok_or(())
+    }
+}
+
+/// Returns `true` if `pattern` is a valid [`RuleKind::BannedWord`] pattern.
+/// Patterns consist of alphanumeric characters and `*` and must contain at
+/// least one alphanumeric character.
+pub fn is_valid_word_pattern(pattern: &str) -> bool {
+    pattern.chars().all(|c| c.is_alphanumeric() || c == '*')
+        && pattern.chars().any(char::is_alphanumeric)
+}
+
+/// Returns `true` if any word of `content` matches `pattern`. Words are
+/// separated by any non-alphanumeric character.
+pub fn contains_word(pattern: &str, content: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().flat_map(char::to_lowercase).collect();
+
+    content
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .any(|word| {
+            let word: Vec<char> = word.chars().flat_map(char::to_lowercase).collect();
+            glob_match(&pattern, &word)
+        })
+}
+
+/// Matches `input` against `pattern`, where `*` matches any number of
+/// characters.
+fn glob_match(pattern: &[char], input: &[char]) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // The position of the last `*` in the pattern and the input position it
+    // was matched at.
+    let mut backtrack = None;
+
+    while i < input.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, i));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == input[i] {
+            p += 1;
+            i += 1;
+        } else if let Some((star, pos)) = backtrack {
+            // Let the last `*` consume one more character.
+            backtrack = Some((star, pos + 1));
+            p = star + 1;
+            i = pos + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Returns `true` if `content` contains a Discord invite link.
+pub fn contains_invite(content: &str) -> bool {
+    static INVITE: OnceLock<Regex> = OnceLock::new();
+
+    INVITE
+        .get_or_init(|| {
+            Regex::new(r"(?i)\b(?:discord(?:app)?\.com/invite|discord\.(?:gg|io|me|li))/[a-z0-9-]+")
+                .unwrap()
+        })
+        .is_match(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{contains_invite, contains_word, is_valid_word_pattern, Action, RuleKind};
+
+    #[test]
+    fn test_contains_word() {
+        assert!(contains_word("badword", "this is a badword"));
+        assert!(contains_word("badword", "BadWord!"));
+        assert!(contains_word("BADWORD", "no,badword,here"));
+        assert!(!contains_word("badword", "badwords"));
+        assert!(!contains_word("badword", "notabadword"));
+
+        assert!(contains_word("bad*", "badwords are bad"));
+        assert!(contains_word("*word", "notabadword"));
+        assert!(contains_word("b*d", "bd"));
+        assert!(contains_word("b*d", "bread"));
+        assert!(contains_word("*bad*", "xxbadxx"));
+        assert!(!contains_word("b*d", "bade"));
+        assert!(!contains_word("bad*", "a b a d"));
+
+        assert!(contains_word("größe", "GRÖßE"));
+        assert!(!contains_word("word", ""));
+    }
+
+    #[test]
+    fn test_contains_word_lookalikes() {
+        // Unicode lookalikes are not normalized. The second word uses a
+        // cyrillic `а` and is not matched.
+        assert!(contains_word("bad", "bad"));
+        assert!(!contains_word("bad", "bаd"));
+    }
+
+    #[test]
+    fn test_is_valid_word_pattern() {
+        assert!(is_valid_word_pattern("word"));
+        assert!(is_valid_word_pattern("*word*"));
+        assert!(!is_valid_word_pattern("*"));
+        assert!(!is_valid_word_pattern("two words"));
+        assert!(!is_valid_word_pattern(""));
+    }
+
+    #[test]
+    fn test_contains_invite() {
+        assert!(contains_invite("join discord.gg/abc123"));
+        assert!(contains_invite("https://discord.gg/abc-123"));
+        assert!(contains_invite("https://DISCORD.GG/abc"));
+        assert!(contains_invite("<https://discord.com/invite/abc>"));
+        assert!(contains_invite("https://discordapp.com/invite/abc"));
+        assert!(!contains_invite("discord.gg"));
+        assert!(!contains_invite("https://discord.com/channels/1/2"));
+        assert!(!contains_invite("notdiscord.gg/abc"));
+    }
+
+    #[test]
+    fn test_rule_kind_is_match() {
+        assert!(RuleKind::MassMention.is_match("5", "", 5));
+        assert!(!RuleKind::MassMention.is_match("5", "", 4));
+        assert!(!RuleKind::MassMention.is_match("invalid", "", 100));
+
+        assert!(RuleKind::InviteLink.is_match("", "discord.gg/abc", 0));
+        assert!(RuleKind::BannedWord.is_match("abc", "x abc", 0));
+    }
+
+    #[test]
+    fn test_parse() {
+        for kind in [
+            RuleKind::BannedWord,
+            RuleKind::InviteLink,
+            RuleKind::MassMention,
+        ] {
+            assert_eq!(kind.to_string().parse(), Ok(kind));
+        }
+
+        for action in [Action::Delete, Action::Warn, Action::DeleteAndWarn] {
+            assert_eq!(action.to_string().parse(), Ok(action));
+        }
+    }
+}
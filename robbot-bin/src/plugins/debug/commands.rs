@@ -1,6 +1,15 @@
+use crate::builtin::disable::format_reason;
+use crate::builtin::{BUILT, VERSION};
+use crate::{intents, logger};
+
 use robbot::builder::CreateMessage;
 use robbot::{command, Result};
-use robbot_core::context::MessageContext;
+use robbot_core::context::{GuildMessageContext, MessageContext};
+use robbot_core::settings::{TimeDisplay, TimeSettings};
+use robbot_core::state::State;
+
+use chrono::Utc;
+use serde_json::{json, Value};
 
 use std::fmt::Write;
 
@@ -22,12 +31,19 @@ async fn taskqueue(ctx: MessageContext) -> Result {
     match tasks.len() {
         0 => description.push_str("No tasks scheduled."),
         _ => {
-            for (task, execution_time) in ctx.state.tasks().get_tasks().await {
-                let _ = writeln!(
-                    description,
-                    "Task `{}` scheduled at `{}`.",
-                    task.name, execution_time
-                );
+            for task in tasks {
+                match task.next_exec {
+                    Some(execution_time) => {
+                        let _ = writeln!(
+                            description,
+                            "Task `{}` scheduled at `{}`.",
+                            task.name, execution_time
+                        );
+                    }
+                    None => {
+                        let _ = writeln!(description, "Task `{}` finished.", task.name);
+                    }
+                }
             }
         }
     }
@@ -48,15 +64,25 @@ async fn hooks(ctx: MessageContext) -> Result {
     let mut description = String::new();
 
     let hooks = ctx.state.hooks().list_hooks().await;
+    let time = ctx.time_settings().await?;
     match hooks.len() {
         0 => description.push_str("No Hooks loaded."),
         _ => {
             for hook in hooks {
-                let _ = writeln!(
-                    description,
-                    "Hook `{}` enabled for event `{}`",
-                    hook.name, hook.on_event
-                );
+                let _ = match ctx.state.hooks().disabled().get(&hook.name) {
+                    Some(reason) => writeln!(
+                        description,
+                        "Hook `{}` for event `{}`: {}",
+                        hook.name,
+                        hook.on_event,
+                        format_reason(&reason, &time, TimeDisplay::Markdown)
+                    ),
+                    None => writeln!(
+                        description,
+                        "Hook `{}` enabled for event `{}`",
+                        hook.name, hook.on_event
+                    ),
+                };
             }
         }
     }
@@ -72,10 +98,10 @@ async fn hooks(ctx: MessageContext) -> Result {
     Ok(())
 }
 
-#[command(description = "List all enabled modules.")]
+#[command(description = "List all enabled modules and the modules that failed to load.")]
 async fn modules(ctx: MessageContext) -> Result {
     let modules = ctx.state.modules().list_modules();
-    let description = match modules.len() {
+    let mut description = match modules.len() {
         0 => String::from("No modules loaded."),
         _ => {
             let mut string = String::new();
@@ -88,6 +114,14 @@ async fn modules(ctx: MessageContext) -> Result {
         }
     };
 
+    for module in ctx.state.modules().failed_modules() {
+        let _ = writeln!(
+            description,
+            ":x: `{}` failed to load: {}",
+            module.name, module.reason
+        );
+    }
+
     ctx.respond(CreateMessage::new(|m| {
         m.embed(|e| {
             e.title("__Modules__");
@@ -97,3 +131,197 @@ async fn modules(ctx: MessageContext) -> Result {
     .await?;
     Ok(())
 }
+
+#[command(
+    description = "List the requested gateway intents and the features not working without them."
+)]
+async fn intents(ctx: MessageContext) -> Result {
+    let config = &ctx.state.config;
+
+    let mut description = String::from("**Requested**\n");
+    for intent in intents::REQUESTED {
+        let _ = match intent.is_privileged() {
+            true => writeln!(description, "`{}` (privileged)", intent.name()),
+            false => writeln!(description, "`{}`", intent.name()),
+        };
+    }
+
+    let _ = writeln!(
+        description,
+        "\n**Message content**\nEnabled in config: `{}`\nMessages without content detected: `{}`",
+        config.intents.message_content,
+        intents::CONTENT.is_degraded()
+    );
+
+    description.push_str("\n**Features**\n");
+    for requirement in intents::REQUIREMENTS {
+        let status = match intents::is_available(config, requirement.intent) {
+            true => "available",
+            false => "degraded",
+        };
+
+        let _ = writeln!(
+            description,
+            "{} (`{}`): {}",
+            requirement.feature,
+            requirement.intent.name(),
+            status
+        );
+    }
+
+    ctx.respond(CreateMessage::new(|m| {
+        m.embed(|e| {
+            e.title("__Intents__");
+            e.description(description);
+        });
+    }))
+    .await?;
+    Ok(())
+}
+
+#[command(description = "List the roles managed by modules in this guild and their managers.")]
+async fn roles(ctx: GuildMessageContext) -> Result {
+    let roles = ctx.state.managed_roles().list(ctx.event.guild_id);
+
+    let mut description = String::new();
+    match roles.len() {
+        0 => description.push_str("No managed roles."),
+        _ => {
+            for (role_id, registrations) in roles {
+                let managers: Vec<_> = registrations
+                    .iter()
+                    .map(|r| format!("`{}` ({})", r.manager, r.priority))
+                    .collect();
+
+                let _ = writeln!(
+                    description,
+                    "{}: {}",
+                    role_id.mention(),
+                    managers.join(", ")
+                );
+            }
+        }
+    }
+
+    ctx.respond(CreateMessage::new(|m| {
+        m.embed(|e| {
+            e.title("__Managed Roles__");
+            e.description(description);
+        });
+    }))
+    .await?;
+    Ok(())
+}
+
+/// The `debug snapshot` command uploads a support bundle of the internal state
+/// as a JSON file. Secrets in the config are redacted. Only admins defined in
+/// the config file can use it.
+#[command(
+    description = "Upload a snapshot of the internal state for a support request. Secrets are redacted.",
+    bot_permissions = [ATTACH_FILES]
+)]
+async fn snapshot(ctx: MessageContext) -> Result {
+    if !ctx.state.config.admins.contains(&ctx.event.author.id) {
+        ctx.respond(":no_entry_sign: You are not allowed to run this command.")
+            .await?;
+        return Ok(());
+    }
+
+    let snapshot = serde_json::to_vec_pretty(&collect(&ctx.state).await)?;
+    let filename = format!(
+        "robbot-snapshot-{}.json",
+        Utc::now().format("%Y%m%d-%H%M%S")
+    );
+
+    ctx.respond(CreateMessage::new(|m| {
+        m.attachment(filename, snapshot);
+    }))
+    .await?;
+    Ok(())
+}
+
+/// Collects the support bundle of `state`.
+async fn collect(state: &State) -> Value {
+    let tasks: Vec<_> = state
+        .tasks()
+        .get_tasks()
+        .await
+        .into_iter()
+        .map(|task| {
+            json!({
+                "name": task.name,
+                "schedule": task.schedule.to_string(),
+                "enabled": task.enabled,
+                "next_exec": task.next_exec.map(|time| time.to_rfc3339()),
+                "last_exec": task.last_exec.map(|time| time.to_rfc3339()),
+                "last_result": task.last_result.map(|res| res.err().unwrap_or_else(|| String::from("ok"))),
+            })
+        })
+        .collect();
+
+    let hooks: Vec<_> = state
+        .hooks()
+        .list_hooks()
+        .await
+        .into_iter()
+        .map(|hook| {
+            json!({
+                "name": hook.name,
+                "event": hook.on_event.to_string(),
+                "disabled": state.hooks().disabled().get(&hook.name).map(|reason| {
+                    format_reason(&reason, &TimeSettings::default(), TimeDisplay::Plain)
+                }),
+            })
+        })
+        .collect();
+
+    let modules: Vec<_> = state
+        .modules()
+        .list_modules()
+        .into_iter()
+        .map(|module| json!({ "name": module.name, "id": module.id.0 }))
+        .collect();
+
+    let rows = match state.store().row_counts().await {
+        Ok(rows) => Value::Object(
+            rows.into_iter()
+                .map(|(name, count)| (name, Value::from(count)))
+                .collect(),
+        ),
+        Err(err) => json!({ "error": err.to_string() }),
+    };
+
+    let cache = state.hot_path().stats();
+
+    let rejections: serde_json::Map<_, _> = state
+        .executions()
+        .rejections()
+        .into_iter()
+        .map(|(guild_id, count)| (guild_id.to_string(), Value::from(count)))
+        .collect();
+
+    json!({
+        "version": VERSION,
+        "built": BUILT,
+        "created_at": Utc::now().to_rfc3339(),
+        "config": state.config.redacted(),
+        "modules": modules,
+        "commands": state.commands().count(),
+        "tasks": tasks,
+        "hooks": hooks,
+        "store": {
+            "degraded": state.store().is_degraded(),
+            "rows": rows,
+        },
+        "hot_path_cache": {
+            "hits": cache.hits,
+            "misses": cache.misses,
+            "len": cache.len,
+        },
+        "executions": {
+            "running": state.executions().running(),
+            "rejections": rejections,
+        },
+        "recent_logs": logger::recent(),
+    })
+}
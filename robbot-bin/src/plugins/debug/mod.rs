@@ -13,6 +13,9 @@ module! {
             commands::taskqueue,
             commands::hooks,
             commands::modules,
+            commands::intents,
+            commands::roles,
+            commands::snapshot,
         },
     },
 }
@@ -0,0 +1,108 @@
+use super::{digest, format_schedule, nodes, parse_weekday, DigestConfig, WEEK};
+
+use chrono::Utc;
+use robbot::arguments::ChannelMention;
+use robbot::prelude::ArgumentsExt;
+use robbot::store::{delete, get_one, insert};
+use robbot::{command, Error, Result};
+use robbot_core::context::GuildMessageContext;
+
+/// The weekday used if `digest enable` is given none.
+const DEFAULT_WEEKDAY: u8 = 0;
+/// The hour used if `digest enable` is given none.
+const DEFAULT_HOUR: u8 = 9;
+
+#[command(
+    description = "Post a weekly digest of the guild activity to a channel. The digest is posted on monday at 09:00 UTC unless a weekday and hour (UTC) are given.",
+    usage = "<@Channel> [Weekday] [Hour]",
+    example = "#mod-log friday 18",
+    permissions = [nodes::MANAGE],
+    audited,
+    mutates_data,
+)]
+async fn enable(mut ctx: GuildMessageContext) -> Result {
+    let channel: ChannelMention = ctx.args.pop_parse()?;
+
+    let weekday = match ctx.args.pop() {
+        Some(arg) => parse_weekday(&arg).ok_or(Error::InvalidCommandUsage)?,
+        None => DEFAULT_WEEKDAY,
+    };
+
+    let hour = match ctx.args.pop() {
+        Some(arg) => match arg.parse() {
+            Ok(hour) if hour < 24 => hour,
+            _ => return Err(Error::InvalidCommandUsage),
+        },
+        None => DEFAULT_HOUR,
+    };
+
+    ctx.state.store().check_writable()?;
+
+    let guild_id = ctx.event.guild_id;
+
+    let existing = get_one!(ctx.state.store(), DigestConfig => {
+        guild_id == guild_id,
+    })
+    .await?;
+
+    // The first digest covers the activity since enabling it.
+    let config = DigestConfig {
+        guild_id,
+        channel_id: channel.id,
+        weekday,
+        hour,
+        last_sent: Utc::now().timestamp(),
+        version: existing.as_ref().map_or(0, |config| config.version),
+    };
+
+    match existing {
+        Some(_) => {
+            let store = ctx.state.store();
+            let query = store.make_query::<DigestConfig>().guild_id(guild_id);
+
+            store.update(query, config.clone()).await?;
+        }
+        None => insert!(ctx.state.store(), config.clone()).await?,
+    }
+
+    ctx.respond(format!(
+        ":white_check_mark: Posting the weekly digest to {} every {}.",
+        channel,
+        format_schedule(&config)
+    ))
+    .await?;
+    Ok(())
+}
+
+#[command(
+    description = "Stop posting the weekly digest.",
+    permissions = [nodes::MANAGE],
+    audited,
+    mutates_data,
+)]
+async fn disable(ctx: GuildMessageContext) -> Result {
+    ctx.state.store().check_writable()?;
+
+    delete!(ctx.state.store(), DigestConfig => {
+        guild_id == ctx.event.guild_id,
+    })
+    .await?;
+
+    ctx.respond(":white_check_mark: Disabled the weekly digest.")
+        .await?;
+    Ok(())
+}
+
+#[command(
+    description = "Show the digest of the last 7 days.",
+    permissions = [nodes::MANAGE],
+    bot_permissions = [EMBED_LINKS],
+)]
+async fn preview(ctx: GuildMessageContext) -> Result {
+    let since = Utc::now().timestamp() - WEEK;
+
+    let embed = digest(&ctx.state, ctx.event.guild_id, since).await;
+
+    ctx.respond(embed).await?;
+    Ok(())
+}
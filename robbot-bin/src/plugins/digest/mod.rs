@@ -0,0 +1,405 @@
+//! # Digest plugin
+//! Posts a weekly summary of the activity in a guild. Plugins contribute to
+//! the digest by registering a [`DigestProvider`] with [`register`], which
+//! returns the [`DigestLine`]s of a guild since a given time.
+//!
+//! Guilds enable the digest with a channel, a weekday and an hour (UTC). An
+//! hourly task posts the digest of every guild whose scheduled time passed
+//! since its last digest, so a digest missed while the bot was offline is
+//! posted once it is back. A provider failing to collect its lines is shown
+//! as unavailable without affecting the other providers.
+mod commands;
+
+use async_trait::async_trait;
+use chrono::{Datelike, Duration, NaiveDateTime, Utc, Weekday};
+use parking_lot::RwLock;
+use robbot::builder::EmbedBuilder;
+use robbot::model::id::{ChannelId, GuildId};
+use robbot::{module, task, Error, Result, StoreData};
+use robbot_core::context::Context;
+use robbot_core::state::State;
+use robbot_core::store::Conflict;
+
+use std::result;
+use std::sync::Arc;
+
+/// The number of seconds in a week.
+const WEEK: i64 = 60 * 60 * 24 * 7;
+
+/// The maximum number of fields of an embed.
+const MAX_FIELDS: usize = 25;
+
+static PROVIDERS: RwLock<Vec<Arc<dyn DigestProvider>>> = RwLock::new(Vec::new());
+
+pub mod nodes {
+    robbot::permission_nodes! {
+        "digest" => {
+            MANAGE = "manage",
+        }
+    }
+}
+
+module! {
+    name: "digest",
+    cmds: {
+        "digest": {
+            commands::enable,
+            commands::disable,
+            commands::preview,
+        },
+    },
+    store: [
+        DigestConfig,
+    ],
+    tasks: [
+        send,
+    ],
+    permissions: nodes::NODES,
+}
+
+/// The weekly digest of a guild.
+#[derive(Clone, Debug, StoreData)]
+pub struct DigestConfig {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    /// The weekday the digest is posted on, counted from monday starting at
+    /// `0`.
+    pub weekday: u8,
+    /// The hour (UTC) the digest is posted at.
+    pub hour: u8,
+    /// The time the last digest was posted as a unix timestamp in seconds.
+    pub last_sent: i64,
+    /// Incremented on every update, see [`StoreHandler::update`].
+    ///
+    /// [`StoreHandler::update`]: robbot_core::store::StoreHandler::update
+    #[store(version)]
+    pub version: u64,
+}
+
+/// A line of a digest, e.g. `Warnings issued: 4`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DigestLine {
+    pub name: String,
+    pub value: String,
+}
+
+impl DigestLine {
+    pub fn new<N, V>(name: N, value: V) -> Self
+    where
+        N: ToString,
+        V: ToString,
+    {
+        Self {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+}
+
+/// A source of [`DigestLine`]s, registered with [`register`].
+#[async_trait]
+pub trait DigestProvider: Send + Sync {
+    /// The name of the section of the provider in the digest.
+    fn name(&self) -> &str;
+
+    /// Returns the lines of a guild for the activity since `since`, a unix
+    /// timestamp in seconds. Returning no lines omits the section.
+    async fn collect(
+        &self,
+        state: &State,
+        guild_id: GuildId,
+        since: i64,
+    ) -> result::Result<Vec<DigestLine>, Error>;
+}
+
+/// Registers a provider of digest lines. Sections are shown in the order
+/// their providers were registered.
+pub fn register<P>(provider: P)
+where
+    P: DigestProvider + 'static,
+{
+    PROVIDERS.write().push(Arc::new(provider));
+}
+
+/// A section of a digest. `None` if the provider failed.
+type Section = (String, Option<Vec<DigestLine>>);
+
+/// Collects the sections of all providers for a guild.
+async fn collect(state: &State, guild_id: GuildId, since: i64) -> Vec<Section> {
+    let providers = PROVIDERS.read().clone();
+
+    let mut sections = Vec::with_capacity(providers.len());
+    for provider in providers {
+        let lines = match provider.collect(state, guild_id, since).await {
+            Ok(lines) => Some(lines),
+            Err(err) => {
+                log::warn!(
+                    "[DIGEST] Provider {} failed in guild {}: {:?}",
+                    provider.name(),
+                    guild_id,
+                    err
+                );
+                None
+            }
+        };
+
+        sections.push((provider.name().to_owned(), lines));
+    }
+
+    sections
+}
+
+/// Renders a digest of the activity since `since`.
+fn render(sections: &[Section], since: i64) -> EmbedBuilder {
+    let fields = sections
+        .iter()
+        .filter_map(|(name, lines)| {
+            let value = match lines {
+                Some(lines) if lines.is_empty() => return None,
+                Some(lines) => lines
+                    .iter()
+                    .map(|line| format!("{}: **{}**", line.name, line.value))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                None => String::from("_Unavailable_"),
+            };
+
+            Some((name.clone(), value, false))
+        })
+        .take(MAX_FIELDS)
+        .collect::<Vec<_>>();
+
+    let description = match fields.is_empty() {
+        true => format!("No activity since <t:{}:D>.", since),
+        false => format!("Activity since <t:{}:D>.", since),
+    };
+
+    EmbedBuilder::new()
+        .title("Weekly digest")
+        .description(description)
+        .fields_from(fields)
+}
+
+/// Builds the digest of a guild for the activity since `since`.
+pub async fn digest(state: &State, guild_id: GuildId, since: i64) -> EmbedBuilder {
+    let sections = collect(state, guild_id, since).await;
+    render(&sections, since)
+}
+
+/// Returns the most recent scheduled time of a digest at or before `now`.
+fn last_scheduled(config: &DigestConfig, now: i64) -> i64 {
+    let now = NaiveDateTime::from_timestamp_opt(now, 0).unwrap_or_default();
+
+    let days_since =
+        (now.weekday().num_days_from_monday() as i64 - config.weekday as i64).rem_euclid(7);
+
+    let scheduled = (now.date() - Duration::days(days_since))
+        .and_hms_opt(config.hour as u32, 0, 0)
+        .unwrap_or(now);
+
+    // The scheduled hour is still ahead today.
+    let scheduled = match scheduled > now {
+        true => scheduled - Duration::weeks(1),
+        false => scheduled,
+    };
+
+    scheduled.timestamp()
+}
+
+/// Returns `true` if the digest is due at `now`.
+fn is_due(config: &DigestConfig, now: i64) -> bool {
+    config.last_sent < last_scheduled(config, now)
+}
+
+/// Parses a weekday, e.g. `mon` or `monday`, into its number counted from
+/// monday.
+pub fn parse_weekday(s: &str) -> Option<u8> {
+    s.parse::<Weekday>()
+        .ok()
+        .map(|weekday| weekday.num_days_from_monday() as u8)
+}
+
+/// Formats the schedule of a digest, e.g. `Monday 09:00 UTC`.
+pub fn format_schedule(config: &DigestConfig) -> String {
+    const WEEKDAYS: [&str; 7] = [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ];
+
+    let weekday = WEEKDAYS.get(config.weekday as usize).unwrap_or(&"Sunday");
+    format!("{} {:02}:00 UTC", weekday, config.hour)
+}
+
+#[task(interval = "1h", on_load = true, mutates_data = true)]
+async fn send(ctx: Context<()>) -> Result {
+    let configs = ctx
+        .state
+        .store()
+        .get_all(ctx.state.store().make_descriptor::<DigestConfig>())
+        .await?;
+
+    let now = Utc::now().timestamp();
+
+    for config in configs {
+        if !is_due(&config, now) {
+            continue;
+        }
+
+        ctx.state.store().check_writable()?;
+
+        let since = config.last_sent.max(now - WEEK);
+        let embed = digest(&ctx.state, config.guild_id, since).await;
+
+        if let Err(err) = ctx.send_message(config.channel_id, embed).await {
+            log::warn!(
+                "[DIGEST] Failed to send digest in guild {}: {:?}",
+                config.guild_id,
+                err
+            );
+        }
+
+        // The digest is not retried if sending fails, it would likely fail
+        // again every hour.
+        let guild_id = config.guild_id;
+        let query = ctx
+            .state
+            .store()
+            .make_query::<DigestConfig>()
+            .guild_id(guild_id);
+
+        let res = ctx
+            .state
+            .store()
+            .update(
+                query,
+                DigestConfig {
+                    last_sent: now,
+                    ..config
+                },
+            )
+            .await;
+
+        match res {
+            Ok(()) => (),
+            // The digest was changed or disabled while it was sent.
+            Err(err) if err.is::<Conflict>() => {
+                log::debug!(
+                    "[DIGEST] Digest of guild {} changed while sending",
+                    guild_id
+                );
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        format_schedule, is_due, last_scheduled, parse_weekday, render, DigestConfig, DigestLine,
+    };
+
+    use chrono::NaiveDate;
+    use robbot::model::id::{ChannelId, GuildId};
+
+    fn config(weekday: u8, hour: u8, last_sent: i64) -> DigestConfig {
+        DigestConfig {
+            guild_id: GuildId(1),
+            channel_id: ChannelId(2),
+            weekday,
+            hour,
+            last_sent,
+            version: 0,
+        }
+    }
+
+    fn timestamp(day: u32, hour: u32, minute: u32) -> i64 {
+        // 2024-01-01 is a monday.
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+            .timestamp()
+    }
+
+    #[test]
+    fn test_last_scheduled() {
+        // Wednesday 09:00.
+        let config = config(2, 9, 0);
+
+        assert_eq!(
+            last_scheduled(&config, timestamp(3, 9, 0)),
+            timestamp(3, 9, 0)
+        );
+        assert_eq!(
+            last_scheduled(&config, timestamp(3, 10, 30)),
+            timestamp(3, 9, 0)
+        );
+        assert_eq!(
+            last_scheduled(&config, timestamp(9, 23, 0)),
+            timestamp(3, 9, 0)
+        );
+        // Earlier on the scheduled day.
+        assert_eq!(
+            last_scheduled(&config, timestamp(10, 8, 59)),
+            timestamp(3, 9, 0)
+        );
+        assert_eq!(
+            last_scheduled(&config, timestamp(10, 9, 0)),
+            timestamp(10, 9, 0)
+        );
+    }
+
+    #[test]
+    fn test_is_due() {
+        // Sent on wednesday 09:00.
+        let sent = config(2, 9, timestamp(3, 9, 5));
+        assert!(!is_due(&sent, timestamp(3, 10, 0)));
+        assert!(!is_due(&sent, timestamp(10, 8, 0)));
+        assert!(is_due(&sent, timestamp(10, 9, 0)));
+        // Missed while offline.
+        assert!(is_due(&sent, timestamp(12, 0, 0)));
+
+        // Enabled on thursday, the first digest is posted next wednesday.
+        let enabled = config(2, 9, timestamp(4, 12, 0));
+        assert!(!is_due(&enabled, timestamp(5, 9, 0)));
+        assert!(is_due(&enabled, timestamp(10, 9, 0)));
+    }
+
+    #[test]
+    fn test_parse_weekday() {
+        assert_eq!(parse_weekday("mon"), Some(0));
+        assert_eq!(parse_weekday("Sunday"), Some(6));
+        assert_eq!(parse_weekday("someday"), None);
+
+        assert_eq!(format_schedule(&config(0, 9, 0)), "Monday 09:00 UTC");
+        assert_eq!(format_schedule(&config(6, 18, 0)), "Sunday 18:00 UTC");
+    }
+
+    #[test]
+    fn test_render() {
+        let sections = vec![
+            (
+                String::from("Automod"),
+                Some(vec![DigestLine::new("Warnings issued", 4)]),
+            ),
+            (String::from("Gate"), Some(Vec::new())),
+            (String::from("Audit"), None),
+        ];
+
+        let embed = robbot::builder::CreateEmbed::from(render(&sections, 0));
+        let embed = format!("{:?}", embed);
+
+        assert!(embed.contains("Warnings issued: **4**"));
+        assert!(embed.contains("_Unavailable_"));
+        // Sections without lines are omitted.
+        assert!(!embed.contains("Gate"));
+    }
+}
@@ -0,0 +1,187 @@
+use super::{
+    feedback as get_feedback, notify, quote, render, Feedback, FeedbackStatus, MAX_LENGTH,
+};
+
+use robbot::arguments::MessageRef;
+use robbot::model::emoji::Emoji;
+use robbot::prelude::ArgumentsExt;
+use robbot::store::insert;
+use robbot::{command, Error, Result};
+use robbot_core::command::Command;
+use robbot_core::context::{GuildMessageContext, MessageContext};
+
+use chrono::Utc;
+
+/// Returns the `feedback` command with `feedback reply` as its sub command.
+pub fn feedback_with_reply() -> Command {
+    let mut command = feedback();
+    command.sub_commands.insert(reply());
+    command
+}
+
+#[command(
+    description = "Send a bug report or feature suggestion to the operators of the bot.",
+    usage = "<Text>",
+    example = "The poll results should show who voted.",
+    quota = 3,
+    mutates_data
+)]
+async fn feedback(mut ctx: GuildMessageContext) -> Result {
+    let content: String = ctx.args.join_rest()?;
+    let content = content.trim().to_owned();
+    if content.is_empty() {
+        return Err(Error::InvalidCommandUsage);
+    }
+
+    if content.chars().count() > MAX_LENGTH {
+        ctx.respond(format!(
+            ":x: Feedback is limited to {} characters.",
+            MAX_LENGTH
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let feedback_channel = match ctx.state.config.feedback_channel {
+        Some(channel_id) => channel_id,
+        None => {
+            ctx.respond(":x: Feedback is not enabled on this bot.")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    ctx.state.store().check_writable()?;
+
+    let reports = ctx
+        .state
+        .store()
+        .get_all(ctx.state.store().make_descriptor::<Feedback>())
+        .await?;
+    let id = reports
+        .iter()
+        .map(|feedback| feedback.id)
+        .max()
+        .unwrap_or(0)
+        + 1;
+
+    let mut feedback = Feedback {
+        id,
+        guild_id: ctx.event.guild_id,
+        channel_id: ctx.event.channel_id,
+        user_id: ctx.event.author.id,
+        content,
+        message_id: ctx.event.id,
+        status: FeedbackStatus::Open,
+        created_at: Utc::now().timestamp(),
+    };
+
+    let guild_name = ctx
+        .raw_ctx
+        .cache
+        .guild_field(feedback.guild_id.0, |guild| guild.name.clone())
+        .await;
+
+    let message = match ctx
+        .send_message(feedback_channel, render(&feedback, guild_name.as_deref()))
+        .await
+    {
+        Ok(message) => message,
+        Err(err) => {
+            log::error!(
+                "[FEEDBACK] Failed to send feedback to channel {}: {:?}",
+                feedback_channel,
+                err
+            );
+
+            ctx.respond(":warning: Your feedback could not be delivered, please try again later.")
+                .await?;
+            return Ok(());
+        }
+    };
+    feedback.message_id = message.id;
+
+    insert!(ctx.state.store(), feedback).await?;
+
+    for emoji in [super::ACKNOWLEDGE, super::ARCHIVE] {
+        if let Err(err) = ctx
+            .react_to(MessageRef::from(&message), Emoji::Unicode(emoji.to_owned()))
+            .await
+        {
+            log::debug!(
+                "[FEEDBACK] Failed to add reaction to feedback {}: {:?}",
+                id,
+                err
+            );
+        }
+    }
+
+    ctx.respond(format!(
+        ":white_check_mark: Thanks! Your feedback was sent as #{}.",
+        id
+    ))
+    .await?;
+    Ok(())
+}
+
+/// The `feedback reply` command answers a report with a direct message to
+/// the reporter. Only admins defined in the config file can use it.
+#[command(
+    description = "Reply to a feedback report with a direct message to the reporter.",
+    usage = "<ID> <Text>",
+    example = "7 Thanks, this is fixed in the next version."
+)]
+async fn reply(mut ctx: MessageContext) -> Result {
+    if !ctx.state.config.admins.contains(&ctx.event.author.id) {
+        ctx.respond(":no_entry_sign: You are not allowed to run this command.")
+            .await?;
+        return Ok(());
+    }
+
+    let id: u64 = ctx.args.pop_parse()?;
+    let text: String = ctx.args.join_rest()?;
+    if text.trim().is_empty() {
+        return Err(Error::InvalidCommandUsage);
+    }
+
+    if text.chars().count() > MAX_LENGTH {
+        ctx.respond(format!(
+            ":x: Replies are limited to {} characters.",
+            MAX_LENGTH
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let feedback = match get_feedback(&ctx, id).await? {
+        Some(feedback) => feedback,
+        None => {
+            ctx.respond(format!(":x: No feedback with the id `{}`.", id))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let content = format!(
+        "Reply to your feedback #{}:\n{}\n\n{}",
+        feedback.id,
+        quote(&feedback.content),
+        text.trim()
+    );
+
+    if notify(&ctx, &feedback, content).await {
+        ctx.respond(format!(
+            ":white_check_mark: Sent the reply to {}.",
+            feedback.user_id.mention()
+        ))
+        .await?;
+    } else {
+        ctx.respond(format!(
+            ":warning: Could not message {}, they don't accept direct messages from the bot.",
+            feedback.user_id.mention()
+        ))
+        .await?;
+    }
+
+    Ok(())
+}
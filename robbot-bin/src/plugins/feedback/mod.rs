@@ -0,0 +1,299 @@
+//! # Feedback plugin
+//! Lets members report bugs or suggest features to the operators of the bot.
+//! `feedback <Text>` forwards the text as an embed to the channel configured
+//! with `feedback_channel` and stores it as a [`Feedback`] with a short
+//! numeric id. Every member can send a few reports per day.
+//!
+//! Admins defined in the config file handle reports by reacting in the
+//! feedback channel: ✅ acknowledges the report and thanks the reporter with a
+//! direct message, ❌ archives it silently. `feedback reply` answers a report
+//! with a direct message quoting it. Reporters that don't accept direct
+//! messages are skipped, the admin is told so where possible.
+mod commands;
+
+use robbot::builder::{CreateMessage, EditMessage, EmbedBuilder};
+use robbot::model::emoji::Emoji;
+use robbot::model::id::{ChannelId, GuildId, MessageId, UserId};
+use robbot::store::{delete, get_one, insert, StoreEnum};
+use robbot::{hook, module, Error, Result, StoreData};
+use robbot_core::context::Context;
+use robbot_core::hook::ReactionAddContext;
+use robbot_core::theme::{COLOR_INFO, EMBED_COLOR};
+
+use chrono::{TimeZone, Utc};
+
+use std::fmt::{self, Display, Formatter};
+use std::result;
+
+/// The reaction acknowledging a report.
+const ACKNOWLEDGE: &str = "✅";
+
+/// The reaction archiving a report.
+const ARCHIVE: &str = "❌";
+
+/// The maximum number of characters of a report or reply.
+pub const MAX_LENGTH: usize = 1500;
+
+module! {
+    name: "feedback",
+    cmds: {
+        commands::feedback_with_reply,
+    },
+    store: [
+        Feedback,
+    ],
+    hooks: [
+        reaction_add,
+    ],
+}
+
+/// The handling state of a [`Feedback`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, StoreEnum)]
+pub enum FeedbackStatus {
+    Open,
+    Acknowledged,
+    Archived,
+}
+
+impl Display for FeedbackStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Open => "Open",
+            Self::Acknowledged => "Acknowledged",
+            Self::Archived => "Archived",
+        })
+    }
+}
+
+/// A report sent with the `feedback` command.
+#[derive(Clone, Debug, StoreData)]
+pub struct Feedback {
+    /// The id of the report, incremented globally.
+    pub id: u64,
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    /// The reporter.
+    pub user_id: UserId,
+    pub content: String,
+    /// The message of the report in the feedback channel.
+    pub message_id: MessageId,
+    #[store(as = "string")]
+    pub status: FeedbackStatus,
+    /// The time of the report as a unix timestamp in seconds.
+    pub created_at: i64,
+}
+
+/// Renders a report for the feedback channel. `guild_name` is the name of
+/// the guild the report was sent in, if known.
+fn render(feedback: &Feedback, guild_name: Option<&str>) -> EmbedBuilder {
+    let guild = match guild_name {
+        Some(name) => format!("{} (`{}`)", name, feedback.guild_id),
+        None => format!("`{}`", feedback.guild_id),
+    };
+
+    let mut embed = EmbedBuilder::new()
+        .color(match feedback.status {
+            FeedbackStatus::Open => EMBED_COLOR,
+            FeedbackStatus::Acknowledged | FeedbackStatus::Archived => COLOR_INFO,
+        })
+        .title(format!("Feedback #{}", feedback.id))
+        .description(&feedback.content)
+        .field(
+            "User",
+            format!("{} (`{}`)", feedback.user_id.mention(), feedback.user_id),
+            true,
+        )
+        .field("Guild", guild, true)
+        .field("Channel", feedback.channel_id.mention().to_string(), true)
+        .footer(feedback.status.to_string());
+
+    if let Some(time) = Utc.timestamp_opt(feedback.created_at, 0).single() {
+        embed = embed.timestamp(time);
+    }
+
+    embed
+}
+
+/// Returns `text` quoted line by line.
+fn quote(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("> {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns the report with the id `id`.
+pub async fn feedback<T>(ctx: &Context<T>, id: u64) -> result::Result<Option<Feedback>, Error>
+where
+    T: Send + Sync,
+{
+    let feedback = get_one!(ctx.state.store(), Feedback => {
+        id == id,
+    })
+    .await?;
+
+    Ok(feedback)
+}
+
+/// Sends a direct message to the reporter of `feedback`. Returns `false` if
+/// the reporter does not accept direct messages or the message failed.
+pub async fn notify<T>(ctx: &Context<T>, feedback: &Feedback, content: String) -> bool
+where
+    T: Send + Sync,
+{
+    let message = CreateMessage::new(|m| {
+        m.content(content);
+    });
+
+    match ctx.direct_message(feedback.user_id, message).await {
+        Ok(Some(_)) => true,
+        Ok(None) => false,
+        Err(err) => {
+            log::warn!(
+                "[FEEDBACK] Failed to message reporter {} of feedback {}: {:?}",
+                feedback.user_id,
+                feedback.id,
+                err
+            );
+            false
+        }
+    }
+}
+
+/// Changes the status of a report and updates its message in the feedback
+/// channel.
+async fn set_status<T>(
+    ctx: &Context<T>,
+    feedback: &Feedback,
+    status: FeedbackStatus,
+    feedback_channel: ChannelId,
+) -> Result
+where
+    T: Send + Sync,
+{
+    ctx.state.store().check_writable()?;
+
+    let feedback = Feedback {
+        status,
+        ..feedback.clone()
+    };
+
+    delete!(ctx.state.store(), Feedback => {
+        id == feedback.id,
+    })
+    .await?;
+
+    insert!(ctx.state.store(), feedback.clone()).await?;
+
+    let guild_name = ctx
+        .raw_ctx
+        .cache
+        .guild_field(feedback.guild_id.0, |guild| guild.name.clone())
+        .await;
+
+    if let Err(err) = ctx
+        .edit_message(
+            feedback_channel,
+            feedback.message_id,
+            EditMessage::from(render(&feedback, guild_name.as_deref())),
+        )
+        .await
+    {
+        log::warn!(
+            "[FEEDBACK] Failed to edit message of feedback {}: {:?}",
+            feedback.id,
+            err
+        );
+    }
+
+    Ok(())
+}
+
+#[hook]
+async fn reaction_add(ctx: ReactionAddContext) -> Result {
+    let reaction = &ctx.event.0;
+
+    let feedback_channel = match ctx.state.config.feedback_channel {
+        Some(channel_id) if channel_id == reaction.channel_id.into() => channel_id,
+        _ => return Ok(()),
+    };
+
+    let user_id: UserId = match reaction.user_id {
+        Some(user_id) => user_id.into(),
+        None => return Ok(()),
+    };
+
+    if !ctx.state.config.admins.contains(&user_id) {
+        return Ok(());
+    }
+
+    let status = match Emoji::try_from(reaction.emoji.clone()) {
+        Ok(Emoji::Unicode(emoji)) if emoji == ACKNOWLEDGE => FeedbackStatus::Acknowledged,
+        Ok(Emoji::Unicode(emoji)) if emoji == ARCHIVE => FeedbackStatus::Archived,
+        _ => return Ok(()),
+    };
+
+    let message_id: MessageId = reaction.message_id.into();
+    let feedback = match get_one!(ctx.state.store(), Feedback => {
+        message_id == message_id,
+    })
+    .await?
+    {
+        Some(feedback) if feedback.status == FeedbackStatus::Open => feedback,
+        _ => return Ok(()),
+    };
+
+    set_status(&ctx, &feedback, status, feedback_channel).await?;
+
+    if status == FeedbackStatus::Acknowledged {
+        let content = format!(
+            "Thanks for your feedback! It was read by the operators of the bot:\n{}",
+            quote(&feedback.content)
+        );
+
+        if !notify(&ctx, &feedback, content).await {
+            log::debug!(
+                "[FEEDBACK] Reporter {} of feedback {} does not accept direct messages",
+                feedback.user_id,
+                feedback.id
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{quote, render, Feedback, FeedbackStatus};
+
+    use robbot::model::id::{ChannelId, GuildId, MessageId, UserId};
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("Hello"), "> Hello");
+        assert_eq!(quote("Line 1\nLine 2"), "> Line 1\n> Line 2");
+    }
+
+    #[test]
+    fn test_render() {
+        let feedback = Feedback {
+            id: 7,
+            guild_id: GuildId(1),
+            channel_id: ChannelId(2),
+            user_id: UserId(3),
+            content: String::from("The poll bars are too short."),
+            message_id: MessageId(4),
+            status: FeedbackStatus::Open,
+            created_at: 1_600_000_000,
+        };
+
+        let embed = robbot::builder::CreateEmbed::from(render(&feedback, Some("Robbot")));
+        let embed = format!("{:?}", embed);
+
+        assert!(embed.contains("Feedback #7"));
+        assert!(embed.contains("The poll bars are too short."));
+        assert!(embed.contains("Robbot (`1`)"));
+        assert!(embed.contains("<@3> (`3`)"));
+    }
+}
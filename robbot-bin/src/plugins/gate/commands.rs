@@ -0,0 +1,172 @@
+use super::{gate_config, nodes, verified, GateConfig, GatedMember};
+
+use robbot::arguments::{ChannelMention, RoleMention, UserMention};
+use robbot::prelude::ArgumentsExt;
+use robbot::store::{delete, insert};
+use robbot::{command, Error, Result};
+use robbot_core::context::GuildMessageContext;
+
+/// The number of days after which members are reminded by default.
+const DEFAULT_REMIND_AFTER_DAYS: u64 = 3;
+
+#[command(
+    description = "Enable the gate. New members are given the unverified role until they are verified in the verification channel. The timeouts of an existing gate are kept.",
+    usage = "<@Role> <#Channel>",
+    example = "@Unverified #verify",
+    permissions = [nodes::MANAGE],
+    bot_permissions = [MANAGE_ROLES],
+    audited,
+    mutates_data,
+)]
+async fn enable(mut ctx: GuildMessageContext) -> Result {
+    let role: RoleMention = ctx.args.pop_parse()?;
+    let channel: ChannelMention = ctx.args.pop_parse()?;
+
+    ctx.state.store().check_writable()?;
+
+    let guild_id = ctx.event.guild_id;
+
+    if !ctx.can_manage_role(guild_id, role.id).await {
+        ctx.respond(format!(
+            ":x: I cannot manage {}. Move it below my highest role.",
+            role.id.mention()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    match gate_config(&ctx, guild_id).await? {
+        Some(config) => {
+            update_config(
+                &ctx,
+                GateConfig {
+                    role_id: role.id,
+                    channel_id: channel.id,
+                    ..config
+                },
+            )
+            .await?
+        }
+        None => {
+            insert!(
+                ctx.state.store(),
+                GateConfig {
+                    guild_id,
+                    role_id: role.id,
+                    channel_id: channel.id,
+                    remind_after_days: DEFAULT_REMIND_AFTER_DAYS,
+                    kick_after_days: 0,
+                    version: 0,
+                }
+            )
+            .await?
+        }
+    }
+
+    ctx.respond(format!(
+        ":white_check_mark: Enabled the gate. New members are given {} and verify in {}.",
+        role.id.mention(),
+        channel.id.mention()
+    ))
+    .await?;
+    Ok(())
+}
+
+#[command(
+    description = "Disable the gate. Members waiting for verification keep the unverified role.",
+    permissions = [nodes::MANAGE],
+    audited,
+    mutates_data,
+)]
+async fn disable(ctx: GuildMessageContext) -> Result {
+    ctx.state.store().check_writable()?;
+
+    let guild_id = ctx.event.guild_id;
+
+    delete!(ctx.state.store(), GateConfig => {
+        guild_id == guild_id,
+    })
+    .await?;
+
+    delete!(ctx.state.store(), GatedMember => {
+        guild_id == guild_id,
+    })
+    .await?;
+
+    ctx.respond(":white_check_mark: Disabled the gate.").await?;
+    Ok(())
+}
+
+#[command(
+    description = "Set after how many days unverified members are reminded and kicked. `0` disables reminders or kicks.",
+    usage = "<Remind Days> <Kick Days>",
+    example = "3 7",
+    permissions = [nodes::MANAGE],
+    audited,
+    mutates_data,
+)]
+async fn timeouts(mut ctx: GuildMessageContext) -> Result {
+    let remind_after_days: u64 = ctx.args.pop_parse()?;
+    let kick_after_days: u64 = ctx.args.pop_parse()?;
+
+    if remind_after_days != 0 && kick_after_days != 0 && kick_after_days <= remind_after_days {
+        return Err(Error::InvalidCommandUsage);
+    }
+
+    ctx.state.store().check_writable()?;
+
+    let config = match gate_config(&ctx, ctx.event.guild_id).await? {
+        Some(config) => config,
+        None => {
+            ctx.respond(":x: The gate is not enabled.").await?;
+            return Ok(());
+        }
+    };
+
+    update_config(
+        &ctx,
+        GateConfig {
+            remind_after_days,
+            kick_after_days,
+            ..config
+        },
+    )
+    .await?;
+
+    ctx.respond(":white_check_mark: Updated the gate timeouts.")
+        .await?;
+    Ok(())
+}
+
+#[command(
+    description = "Verify a member, removing the unverified role.",
+    usage = "<@User>",
+    example = "@User",
+    permissions = [nodes::VERIFY],
+    bot_permissions = [MANAGE_ROLES],
+    audited,
+    ack_only,
+    mutates_data,
+)]
+async fn verify(mut ctx: GuildMessageContext) -> Result {
+    let user: UserMention = ctx.args.pop_parse()?;
+
+    if gate_config(&ctx, ctx.event.guild_id).await?.is_none() {
+        ctx.respond(":x: The gate is not enabled.").await?;
+        return Ok(());
+    }
+
+    verified(&ctx, ctx.event.guild_id, user.id).await
+}
+
+/// Replaces the gate of the guild. Fails with a [`Conflict`] if the gate
+/// changed since `config` was read.
+///
+/// [`Conflict`]: robbot_core::store::Conflict
+async fn update_config(ctx: &GuildMessageContext, config: GateConfig) -> Result {
+    let store = ctx.state.store();
+    let query = store.make_query::<GateConfig>().guild_id(config.guild_id);
+
+    store.update(query, config).await?;
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use super::GatedMember;
+
+use crate::plugins::digest::{DigestLine, DigestProvider};
+
+use async_trait::async_trait;
+use robbot::model::id::GuildId;
+use robbot::store::get;
+use robbot::Error;
+use robbot_core::state::State;
+
+use std::result;
+
+/// Reports members joined and still waiting for verification.
+pub struct Digest;
+
+#[async_trait]
+impl DigestProvider for Digest {
+    fn name(&self) -> &str {
+        "Gate"
+    }
+
+    async fn collect(
+        &self,
+        state: &State,
+        guild_id: GuildId,
+        since: i64,
+    ) -> result::Result<Vec<DigestLine>, Error> {
+        let members = get!(state.store(), GatedMember => {
+            guild_id == guild_id,
+        })
+        .await?;
+
+        if members.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let joined = members.iter().filter(|m| m.joined_at >= since).count();
+
+        Ok(vec![
+            DigestLine::new("Awaiting verification", members.len()),
+            DigestLine::new("Joined and unverified", joined),
+        ])
+    }
+}
@@ -0,0 +1,476 @@
+//! # Gate plugin
+//! Locks new members of a guild to a verification channel. Members joining a
+//! guild with an enabled gate are given the unverified role, which is
+//! expected to only grant access to the verification channel. The role is
+//! removed once the member is verified, either by a moderator using
+//! `gate verify` or by another plugin publishing a [`VerificationCompleted`]
+//! event, see [`register_events`].
+//!
+//! A daily task reminds members still unverified after
+//! [`GateConfig::remind_after_days`] in the verification channel and kicks
+//! members still unverified after [`GateConfig::kick_after_days`], sending
+//! them a direct message first. Every member waiting for verification has a
+//! [`GatedMember`] entry recording how far they got, so reminders and kicks
+//! are not repeated after a restart.
+mod commands;
+mod digest;
+
+pub use digest::Digest;
+
+use crate::plugins::log::{LogEvent, LogLevel};
+
+use chrono::Utc;
+use robbot::builder::CreateMessage;
+use robbot::model::id::{ChannelId, GuildId, RoleId, UserId};
+use robbot::store::{delete, get_one, insert};
+use robbot::{hook, module, task, Error, Result, StoreData};
+use robbot_core::bus::VerificationCompleted;
+use robbot_core::context::Context;
+use robbot_core::hook::{GuildMemberAdditionContext, GuildMemberRemovalContext};
+use robbot_core::state::State;
+use robbot_core::time::DAY;
+
+use std::collections::HashMap;
+use std::result;
+
+pub mod nodes {
+    robbot::permission_nodes! {
+        "gate" => {
+            MANAGE = "manage",
+            VERIFY = "verify",
+        }
+    }
+}
+
+module! {
+    name: "gate",
+    cmds: {
+        "gate": {
+            commands::enable,
+            commands::disable,
+            commands::timeouts,
+            commands::verify,
+        },
+    },
+    store: [
+        GateConfig,
+        GatedMember,
+    ],
+    hooks: [
+        member_addition,
+        member_removal,
+    ],
+    tasks: [
+        check,
+    ],
+    permissions: nodes::NODES,
+}
+
+/// The gate of a guild.
+#[derive(Clone, Debug, StoreData)]
+pub struct GateConfig {
+    pub guild_id: GuildId,
+    /// The role given to unverified members.
+    pub role_id: RoleId,
+    /// The channel in which members verify.
+    pub channel_id: ChannelId,
+    /// The number of days after joining unverified members are reminded.
+    /// `0` disables reminders.
+    pub remind_after_days: u64,
+    /// The number of days after joining unverified members are kicked. `0`
+    /// disables kicks.
+    pub kick_after_days: u64,
+    /// Incremented on every update, see [`StoreHandler::update`].
+    ///
+    /// [`StoreHandler::update`]: robbot_core::store::StoreHandler::update
+    #[store(version)]
+    pub version: u64,
+}
+
+/// A member waiting for verification.
+#[derive(Clone, Debug, StoreData)]
+pub struct GatedMember {
+    pub guild_id: GuildId,
+    pub user_id: UserId,
+    /// The time the member joined as a unix timestamp in seconds.
+    pub joined_at: i64,
+    /// See [`Stage`].
+    pub stage: u8,
+}
+
+/// The progress of a [`GatedMember`]. Every stage is stored before its
+/// action is taken, actions are therefore taken at most once.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Stage {
+    Joined = 0,
+    Reminded = 1,
+    Kicking = 2,
+}
+
+impl Stage {
+    fn from_u8(stage: u8) -> Self {
+        match stage {
+            0 => Self::Joined,
+            1 => Self::Reminded,
+            _ => Self::Kicking,
+        }
+    }
+}
+
+/// The action to take for a [`GatedMember`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Action {
+    None,
+    Remind,
+    Kick,
+}
+
+/// Returns the gate of a guild, if one is enabled.
+pub async fn gate_config<T>(
+    ctx: &Context<T>,
+    guild_id: GuildId,
+) -> result::Result<Option<GateConfig>, Error>
+where
+    T: Send + Sync,
+{
+    let config = get_one!(ctx.state.store(), GateConfig => {
+        guild_id == guild_id,
+    })
+    .await?;
+
+    Ok(config)
+}
+
+/// Verifies members on [`VerificationCompleted`] events of other plugins.
+pub fn register_events(state: &State) {
+    state
+        .bus()
+        .subscribe("gate", |ctx, event: VerificationCompleted| async move {
+            verified(&ctx, event.guild_id, event.user_id).await
+        });
+}
+
+/// Marks a member as verified, removing the unverified role. Does nothing if
+/// the guild has no gate.
+async fn verified<T>(ctx: &Context<T>, guild_id: GuildId, user_id: UserId) -> Result
+where
+    T: Send + Sync,
+{
+    let config = match gate_config(ctx, guild_id).await? {
+        Some(config) => config,
+        None => return Ok(()),
+    };
+
+    ctx.state.store().check_writable()?;
+
+    // Remove the role first, a failure leaves the member gated and can be
+    // retried.
+    ctx.remove_member_role(guild_id, user_id, config.role_id)
+        .await?;
+
+    delete!(ctx.state.store(), GatedMember => {
+        guild_id == guild_id,
+        user_id == user_id,
+    })
+    .await?;
+
+    log::debug!("[GATE] Verified member {} in guild {}", user_id, guild_id);
+
+    Ok(())
+}
+
+/// Returns the action due for `member` at `now`.
+fn due(config: &GateConfig, member: &GatedMember, now: i64) -> Action {
+    let days = (now - member.joined_at).max(0) / DAY;
+    let reached = |after: u64| after != 0 && days as u64 >= after;
+
+    let stage = Stage::from_u8(member.stage);
+
+    if stage == Stage::Kicking || reached(config.kick_after_days) {
+        Action::Kick
+    } else if stage < Stage::Reminded && reached(config.remind_after_days) {
+        Action::Remind
+    } else {
+        Action::None
+    }
+}
+
+/// Replaces the stored stage of `member`.
+async fn set_stage<T>(ctx: &Context<T>, member: &GatedMember, stage: Stage) -> Result
+where
+    T: Send + Sync,
+{
+    delete!(ctx.state.store(), GatedMember => {
+        guild_id == member.guild_id,
+        user_id == member.user_id,
+    })
+    .await?;
+
+    insert!(
+        ctx.state.store(),
+        GatedMember {
+            stage: stage as u8,
+            ..member.clone()
+        }
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[hook]
+async fn member_addition(ctx: GuildMemberAdditionContext) -> Result {
+    let guild_id: GuildId = ctx.event.guild_id.into();
+    let user_id: UserId = ctx.event.member.user.id.into();
+
+    if ctx.event.member.user.bot {
+        return Ok(());
+    }
+
+    let config = match gate_config(&ctx, guild_id).await? {
+        Some(config) => config,
+        None => return Ok(()),
+    };
+
+    ctx.state.store().check_writable()?;
+
+    let gated = get_one!(ctx.state.store(), GatedMember => {
+        guild_id == guild_id,
+        user_id == user_id,
+    })
+    .await?;
+
+    if gated.is_none() {
+        insert!(
+            ctx.state.store(),
+            GatedMember {
+                guild_id,
+                user_id,
+                joined_at: Utc::now().timestamp(),
+                stage: Stage::Joined as u8,
+            }
+        )
+        .await?;
+    }
+
+    // Skip the edit if it would fail anyway, e.g. because the role was moved
+    // above the highest role of the bot after the gate was enabled.
+    if !ctx.can_manage_role(guild_id, config.role_id).await {
+        crate::plugins::log::log(LogEvent {
+            level: LogLevel::Warn,
+            guild_id,
+            target: Some("gate".to_owned()),
+            content: format!(
+                "Cannot add the unverified role {} to {}. Move the role below the highest role of the bot.",
+                config.role_id.mention(),
+                user_id.mention()
+            ),
+        });
+
+        return Ok(());
+    }
+
+    if let Err(err) = ctx.add_member_role(guild_id, user_id, config.role_id).await {
+        log::warn!(
+            "[GATE] Failed to add unverified role to member {} in guild {}: {:?}",
+            user_id,
+            guild_id,
+            err
+        );
+
+        crate::plugins::log::log(LogEvent {
+            level: LogLevel::Warn,
+            guild_id,
+            target: Some("gate".to_owned()),
+            content: format!(
+                "Failed to add the unverified role to {}. Check that the role is below the highest role of the bot.",
+                user_id.mention()
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+#[hook]
+async fn member_removal(ctx: GuildMemberRemovalContext) -> Result {
+    let guild_id: GuildId = ctx.event.guild_id.into();
+    let user_id: UserId = ctx.event.user.id.into();
+
+    if ctx.state.store().check_writable().is_err() {
+        return Ok(());
+    }
+
+    delete!(ctx.state.store(), GatedMember => {
+        guild_id == guild_id,
+        user_id == user_id,
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[task(interval = "1d", on_load = true, mutates_data = true)]
+async fn check(ctx: Context<()>) -> Result {
+    ctx.state.store().check_writable()?;
+
+    let configs: HashMap<GuildId, GateConfig> = ctx
+        .state
+        .store()
+        .get_all(ctx.state.store().make_descriptor::<GateConfig>())
+        .await?
+        .into_iter()
+        .map(|config| (config.guild_id, config))
+        .collect();
+
+    let members = ctx
+        .state
+        .store()
+        .get_all(ctx.state.store().make_descriptor::<GatedMember>())
+        .await?;
+
+    let now = Utc::now().timestamp();
+
+    for member in members {
+        let config = match configs.get(&member.guild_id) {
+            Some(config) => config,
+            None => continue,
+        };
+
+        let res = match due(config, &member, now) {
+            Action::None => continue,
+            Action::Remind => remind(&ctx, config, &member).await,
+            Action::Kick => kick(&ctx, config, &member).await,
+        };
+
+        if let Err(err) = res {
+            log::warn!(
+                "[GATE] Failed to process member {} in guild {}: {:?}",
+                member.user_id,
+                member.guild_id,
+                err
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Reminds a member to verify in the verification channel.
+async fn remind(ctx: &Context<()>, config: &GateConfig, member: &GatedMember) -> Result {
+    set_stage(ctx, member, Stage::Reminded).await?;
+
+    ctx.send_message(
+        config.channel_id,
+        format!(
+            "{}, you have not been verified yet. Please follow the instructions in this channel to get access to the server.",
+            member.user_id.mention()
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Kicks a member after telling them why. The direct message is skipped if
+/// a previous kick attempt already sent it.
+async fn kick(ctx: &Context<()>, config: &GateConfig, member: &GatedMember) -> Result {
+    if Stage::from_u8(member.stage) != Stage::Kicking {
+        set_stage(ctx, member, Stage::Kicking).await?;
+
+        let guild_name = ctx
+            .raw_ctx
+            .cache
+            .guild_field(member.guild_id.0, |guild| guild.name.clone())
+            .await
+            .unwrap_or_else(|| String::from("the server"));
+
+        let message = CreateMessage::new(|m| {
+            m.content(format!(
+                "You have been removed from {} because you were not verified within {} days. You are welcome to rejoin and verify at any time.",
+                guild_name, config.kick_after_days
+            ));
+        });
+
+        // Members not accepting direct messages are kicked anyway.
+        ctx.direct_message(member.user_id, message).await?;
+    }
+
+    ctx.kick(
+        member.guild_id,
+        member.user_id,
+        Some("Not verified in time"),
+    )
+    .await?;
+
+    delete!(ctx.state.store(), GatedMember => {
+        guild_id == member.guild_id,
+        user_id == member.user_id,
+    })
+    .await?;
+
+    crate::plugins::log::log(LogEvent {
+        level: LogLevel::Info,
+        guild_id: member.guild_id,
+        target: Some("gate".to_owned()),
+        content: format!(
+            "Kicked {} for not verifying within {} days.",
+            member.user_id.mention(),
+            config.kick_after_days
+        ),
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{due, Action, GateConfig, GatedMember, Stage};
+
+    use robbot::model::id::{ChannelId, GuildId, RoleId, UserId};
+    use robbot_core::time::DAY;
+
+    fn config(remind_after_days: u64, kick_after_days: u64) -> GateConfig {
+        GateConfig {
+            guild_id: GuildId(1),
+            role_id: RoleId(2),
+            channel_id: ChannelId(3),
+            remind_after_days,
+            kick_after_days,
+            version: 0,
+        }
+    }
+
+    fn member(stage: Stage) -> GatedMember {
+        GatedMember {
+            guild_id: GuildId(1),
+            user_id: UserId(4),
+            joined_at: 0,
+            stage: stage as u8,
+        }
+    }
+
+    #[test]
+    fn test_due() {
+        let gate = config(3, 7);
+
+        assert_eq!(due(&gate, &member(Stage::Joined), 2 * DAY), Action::None);
+        assert_eq!(due(&gate, &member(Stage::Joined), 3 * DAY), Action::Remind);
+        // Reminders are only sent once.
+        assert_eq!(due(&gate, &member(Stage::Reminded), 5 * DAY), Action::None);
+        assert_eq!(due(&gate, &member(Stage::Reminded), 7 * DAY), Action::Kick);
+        // Interrupted kicks are retried.
+        assert_eq!(due(&gate, &member(Stage::Kicking), 0), Action::Kick);
+
+        // Disabled actions.
+        assert_eq!(
+            due(&config(0, 7), &member(Stage::Joined), 5 * DAY),
+            Action::None
+        );
+        assert_eq!(
+            due(&config(3, 0), &member(Stage::Reminded), 100 * DAY),
+            Action::None
+        );
+    }
+}
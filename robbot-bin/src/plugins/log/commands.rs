@@ -9,11 +9,15 @@ use super::LogChannel;
 #[command(
     description = "Setup a channel for logging.",
     usage = "<@Channel>",
-    permissions = ["admin"]
+    permissions = ["admin"],
+    audited,
+    mutates_data,
 )]
 async fn set(mut ctx: GuildMessageContext) -> Result {
     let channel: ChannelMention = ctx.args.pop_parse()?;
 
+    ctx.state.store().check_writable()?;
+
     insert!(
         ctx.state.store(),
         LogChannel {
@@ -35,10 +39,14 @@ async fn set(mut ctx: GuildMessageContext) -> Result {
     description = "Unset a logging channel.",
     usage = "<@Channel> | all",
     permissions = ["admin"],
+    audited,
+    mutates_data,
 )]
 async fn unset(mut ctx: GuildMessageContext) -> Result {
     let target = ctx.args.pop().ok_or(Error::InvalidCommandUsage)?;
 
+    ctx.state.store().check_writable()?;
+
     let guild_id = ctx.event.guild_id;
 
     // Remove all logging channels.
@@ -8,24 +8,31 @@
 //! there is no guarantee that the event was ever logged, and there currently is no functionality
 //! to check that.
 //!
+//! Disabled commands, hooks and tasks are logged to all guilds with a log channel. Conflicts
+//! between modules managing the same role are logged to the guild of the role. Completed
+//! verifications and expired temporary roles published on the event bus are logged to their
+//! guild, see [`register_events`].
+//!
 mod commands;
-mod tasks;
 
 use chrono::Utc;
-use futures::Future;
 use parking_lot::RwLock;
 use robbot::builder::CreateMessage;
 use robbot::model::id::{ChannelId, GuildId};
 use robbot::store::get_one;
 use robbot::util::color::Color;
-use robbot::{module, Error, StoreData};
+use robbot::{module, Result, StoreData};
+use robbot_core::bus::{TempRoleExpired, VerificationCompleted};
 use robbot_core::context::Context;
+use robbot_core::disable::{DisableEvent, DisabledSet};
+use robbot_core::roles::ManagedRoles;
+use robbot_core::state::State;
+use robbot_core::theme::{COLOR_ERROR, COLOR_INFO, COLOR_WARN};
+use tokio::sync::broadcast::error::RecvError;
 
-static CONTEXT: RwLock<Option<Context<()>>> = RwLock::new(None);
+use std::sync::Once;
 
-const COLOR_ERROR: Color = Color::from_rgb(255, 0, 0);
-const COLOR_WARN: Color = Color::from_rgb(252, 240, 20);
-const COLOR_INFO: Color = Color::from_rgb(15, 86, 252);
+static CONTEXT: RwLock<Option<Context<()>>> = RwLock::new(None);
 
 module! {
     name: "log",
@@ -35,14 +42,114 @@ module! {
             commands::unset,
         },
     },
-    tasks: [
-        tasks::update_context,
-    ],
     store: [
         LogChannel,
-    ]
+    ],
+    on_ready: on_ready,
+}
+
+/// Stores the context used to send log messages.
+async fn on_ready(ctx: Context<()>) -> Result {
+    static SUBSCRIBE: Once = Once::new();
+
+    SUBSCRIBE.call_once(|| {
+        subscribe(ctx.state.commands().disabled(), "command");
+        subscribe(ctx.state.hooks().disabled(), "hook");
+        subscribe(ctx.state.tasks().disabled(), "task");
+        subscribe_roles(ctx.state.managed_roles());
+    });
+
+    let mut cell = CONTEXT.write();
+    *cell = Some(ctx);
+
+    Ok(())
 }
 
+/// Logs all entries disabled in `set` to all guilds.
+fn subscribe(set: &DisabledSet, kind: &'static str) {
+    let mut rx = set.subscribe();
+
+    tokio::task::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(DisableEvent { name, reason }) => {
+                    let content = match reason.is_automatic() {
+                        true => format!(
+                            "The {} `{}` was disabled automatically: `{}`",
+                            kind, name, reason.detail
+                        ),
+                        false => format!(
+                            "The {} `{}` was disabled using `{}`",
+                            kind, name, reason.detail
+                        ),
+                    };
+
+                    log_all(LogLevel::Warn, Some(kind), content);
+                }
+                Err(RecvError::Lagged(_)) => (),
+                Err(RecvError::Closed) => return,
+            }
+        }
+    });
+}
+
+/// Logs all conflicts of managed roles to the guild of the role.
+fn subscribe_roles(roles: &ManagedRoles) {
+    let mut rx = roles.subscribe();
+
+    tokio::task::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(conflict) => log(LogEvent {
+                    level: LogLevel::Warn,
+                    guild_id: conflict.guild_id,
+                    target: Some(String::from("roles")),
+                    content: conflict.to_string(),
+                }),
+                Err(RecvError::Lagged(_)) => (),
+                Err(RecvError::Closed) => return,
+            }
+        }
+    });
+}
+
+/// Subscribes to the events of other plugins logged to their guild.
+pub fn register_events(state: &State) {
+    state
+        .bus()
+        .subscribe("log", |_, event: VerificationCompleted| async move {
+            let account = match event.account_name {
+                Some(name) => format!(" as `{}`", name),
+                None => String::new(),
+            };
+
+            log(LogEvent {
+                level: LogLevel::Info,
+                guild_id: event.guild_id,
+                target: Some(String::from("verification")),
+                content: format!("{} was verified{}", event.user_id.mention(), account),
+            });
+            Ok(())
+        });
+
+    state
+        .bus()
+        .subscribe("log", |_, event: TempRoleExpired| async move {
+            log(LogEvent {
+                level: LogLevel::Info,
+                guild_id: event.guild_id,
+                target: Some(String::from("roles")),
+                content: format!(
+                    "The temporary role {} of {} expired",
+                    event.role_id.mention(),
+                    event.user_id.mention()
+                ),
+            });
+            Ok(())
+        });
+}
+
+#[allow(unused)]
 #[derive(Clone, Debug)]
 pub struct LogEvent {
     pub level: LogLevel,
@@ -83,38 +190,70 @@ pub fn log(event: LogEvent) {
     });
 }
 
-fn log_impl(event: LogEvent) -> impl Future<Output = Result<(), Error>> {
-    async move {
-        let ctx = CONTEXT.read().clone();
+/// Logs an event not bound to a guild to all guilds with a log channel.
+pub fn log_all<T>(level: LogLevel, target: Option<T>, content: String)
+where
+    T: ToString,
+{
+    let target = target.map(|target| target.to_string());
 
-        if let Some(ctx) = ctx {
-            let channel = get_one!(ctx.state.store(), LogChannel => {
-                guild_id == event.guild_id,
-            })
-            .await?;
+    tokio::task::spawn(async move {
+        let ctx = match CONTEXT.read().clone() {
+            Some(ctx) => ctx,
+            None => return,
+        };
 
-            if let Some(channel) = channel {
-                let footer = format!("*INFO* at {}", Utc::now());
-
-                ctx.send_message(
-                    channel.channel_id,
-                    CreateMessage::new(|m| {
-                        m.embed(|e| {
-                            e.title("Event");
-                            e.description(event.content);
-                            e.color(event.level.color());
-                            e.footer(|f| {
-                                f.text(footer);
-                            });
-                        });
-                    }),
-                )
-                .await?;
-            }
-        } else {
-            log::trace!("Logging context not initialized, skipping event");
+        let store = ctx.state.store();
+        let channels: Vec<LogChannel> =
+            match store.get_all(store.make_descriptor::<LogChannel>()).await {
+                Ok(channels) => channels,
+                Err(err) => {
+                    log::error!("Failed to log event: {:?}", err);
+                    return;
+                }
+            };
+
+        for channel in channels {
+            log(LogEvent {
+                level,
+                guild_id: channel.guild_id,
+                target: target.clone(),
+                content: content.clone(),
+            });
         }
+    });
+}
+
+async fn log_impl(event: LogEvent) -> Result {
+    let ctx = CONTEXT.read().clone();
+
+    if let Some(ctx) = ctx {
+        let channel = get_one!(ctx.state.store(), LogChannel => {
+            guild_id == event.guild_id,
+        })
+        .await?;
 
-        Ok(())
+        if let Some(channel) = channel {
+            let footer = format!("*INFO* at {}", Utc::now());
+
+            ctx.send_message(
+                channel.channel_id,
+                CreateMessage::new(|m| {
+                    m.embed(|e| {
+                        e.title("Event");
+                        e.description(event.content);
+                        e.color(event.level.color());
+                        e.footer(|f| {
+                            f.text(footer);
+                        });
+                    });
+                }),
+            )
+            .await?;
+        }
+    } else {
+        log::trace!("Logging context not initialized, skipping event");
     }
+
+    Ok(())
 }
@@ -4,7 +4,18 @@ pub mod debug;
 #[cfg(feature = "permissions")]
 pub mod permissions;
 
+pub mod audit;
+pub mod automod;
+pub mod digest;
+pub mod feedback;
+pub mod gate;
 pub mod log;
+pub mod moderation;
+pub mod muterole;
+pub mod poll;
+pub mod profile;
+pub mod retention;
+pub mod say;
 
 // pub mod events;
 // pub mod guildsync;
@@ -12,19 +23,58 @@ pub mod log;
 // pub mod customcommands;
 // pub mod temprole;
 
-use robbot::Result;
 use robbot_core::state::State;
 
 use std::sync::Arc;
 
-pub async fn init(state: Arc<State>) -> Result {
-    log::init(&state).await?;
+/// Loads the given plugins in order. A plugin failing to load is logged, the
+/// remaining plugins are still loaded.
+macro_rules! load {
+    ($state:expr, $($plugin:ident),* $(,)?) => {
+        $(
+            if let Err(err) = $plugin::init(&$state).await {
+                ::log::error!(
+                    "[CORE] Failed to load plugin '{}': {:?}",
+                    stringify!($plugin),
+                    err
+                );
+            }
+        )*
+    };
+}
+
+pub async fn init(state: Arc<State>) {
+    load!(
+        state,
+        log,
+        muterole,
+        automod,
+        audit,
+        moderation,
+        gate,
+        digest,
+        retention,
+        poll,
+        feedback,
+        profile,
+        say,
+        robbot_plugin_starboard,
+    );
+
+    digest::register(audit::Digest);
+    digest::register(automod::Digest);
+    digest::register(gate::Digest);
+
+    profile::register(automod::Profile);
+    profile::register(moderation::Profile);
+    profile::register(audit::Profile);
+
+    gate::register_events(&state);
+    log::register_events(&state);
 
     #[cfg(feature = "debug")]
-    debug::init(&state).await?;
+    load!(state, debug);
 
     #[cfg(feature = "permissions")]
-    permissions::init(&state).await?;
-
-    Ok(())
+    load!(state, permissions);
 }
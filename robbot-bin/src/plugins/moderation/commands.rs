@@ -0,0 +1,283 @@
+use super::{lift, mechanism, nodes, Mechanism, Mute};
+use crate::plugins::log::{LogEvent, LogLevel};
+use crate::plugins::muterole::mute_role;
+
+use robbot::arguments::UserMention;
+use robbot::model::id::{RoleId, UserId};
+use robbot::prelude::ArgumentsExt;
+use robbot::store::{get_one, insert};
+use robbot::task::parse_duration;
+use robbot::{command, Error, Result};
+use robbot_core::bus::{MemberMuted, TempRoleAssigned};
+use robbot_core::context::GuildMessageContext;
+
+use chrono::Utc;
+
+/// The maximum number of messages deleted by a single `purge`.
+const MAX_PURGE: usize = 1000;
+
+/// Purges of more messages require `confirm` as an argument.
+const CONFIRM_THRESHOLD: usize = 100;
+
+/// The number of recent messages searched for messages of the user when
+/// purging the messages of a single user.
+const USER_SCAN_LIMIT: usize = 1000;
+
+#[command(
+    description = "Delete the most recent messages in this channel, optionally only those of a single user. Purging more than 100 messages requires `confirm`.",
+    usage = "<Count> [@User] [confirm]",
+    example = "20 @Robbbbbbb",
+    permissions = [nodes::PURGE],
+    bot_permissions = [MANAGE_MESSAGES, READ_MESSAGE_HISTORY],
+)]
+async fn purge(mut ctx: GuildMessageContext) -> Result {
+    let count: usize = ctx.args.pop_parse()?;
+    if count == 0 || count > MAX_PURGE {
+        return Err(Error::InvalidCommandUsage);
+    }
+
+    let mut user_id: Option<UserId> = None;
+    let mut confirmed = false;
+
+    while let Some(arg) = ctx.args.pop() {
+        match arg.as_str() {
+            "confirm" => confirmed = true,
+            _ => {
+                let user: UserMention = arg.parse().or(Err(Error::InvalidCommandUsage))?;
+                user_id = Some(user.id);
+            }
+        }
+    }
+
+    if count > CONFIRM_THRESHOLD && !confirmed {
+        ctx.respond(format!(
+            ":warning: This deletes up to {} messages. Add `confirm` to the command to continue.",
+            count
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let channel_id = ctx.event.channel_id;
+
+    // The invoking message is fetched as well, but not counted.
+    let limit = match user_id {
+        Some(_) => USER_SCAN_LIMIT.max(count + 1),
+        None => count + 1,
+    };
+
+    let message_ids: Vec<_> = ctx
+        .messages(channel_id, limit)
+        .await?
+        .into_iter()
+        .filter(|message| message.id != ctx.event.id)
+        .filter(|message| match user_id {
+            Some(user_id) => message.author.id == user_id,
+            None => true,
+        })
+        .take(count)
+        .map(|message| message.id)
+        .collect();
+
+    let deleted = message_ids.len();
+
+    ctx.bulk_delete_messages(channel_id, message_ids).await?;
+
+    ctx.respond(format!(":white_check_mark: Deleted {} messages.", deleted))
+        .await?;
+
+    let target = match user_id {
+        Some(user_id) => format!(" of {}", user_id.mention()),
+        None => String::new(),
+    };
+
+    crate::plugins::log::log(LogEvent {
+        level: LogLevel::Info,
+        guild_id: ctx.event.guild_id,
+        target: Some("moderation".to_owned()),
+        content: format!(
+            "{} purged {} messages{} in {}",
+            ctx.event.author.id.mention(),
+            deleted,
+            target,
+            channel_id.mention()
+        ),
+    });
+
+    Ok(())
+}
+
+#[command(
+    description = "Mute a member, optionally for a duration. Mutes of up to 28 days use a Discord timeout, longer or indefinite mutes use the mute role.",
+    usage = "<@User> [Duration]",
+    example = "@Robbbbbbb 1h",
+    permissions = [nodes::MUTE],
+    bot_permissions = [MODERATE_MEMBERS, MANAGE_ROLES],
+    audited,
+    mutates_data,
+)]
+async fn mute(mut ctx: GuildMessageContext) -> Result {
+    let user: UserMention = ctx.args.pop_parse()?;
+
+    let duration = match ctx.args.pop() {
+        Some(arg) => Some(parse_duration(&arg).ok_or(Error::InvalidCommandUsage)?),
+        None => None,
+    };
+
+    ctx.state.store().check_writable()?;
+
+    let guild_id = ctx.event.guild_id;
+    let until = match duration {
+        Some(duration) => Some(
+            Utc::now()
+                .checked_add_signed(duration)
+                .ok_or(Error::InvalidCommandUsage)?,
+        ),
+        None => None,
+    };
+    let timeout = mechanism(duration) == Mechanism::Timeout;
+
+    let role_id = match timeout {
+        true => RoleId(0),
+        false => match mute_role(&ctx, guild_id).await? {
+            Some(role_id) => role_id,
+            None => {
+                ctx.respond(":x: Mutes longer than 28 days need a mute role. Create one with `muterole setup`.")
+                    .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    if !timeout && !ctx.can_manage_role(guild_id, role_id).await {
+        ctx.respond(format!(
+            ":x: I cannot manage the mute role {}. Move it below my highest role.",
+            role_id.mention()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    // Replace an existing mute, which might use the other mechanism.
+    let existing = get_one!(ctx.state.store(), Mute => {
+        guild_id == guild_id,
+        user_id == user.id,
+    })
+    .await?;
+    if let Some(existing) = existing {
+        lift(&ctx, &existing).await?;
+    }
+
+    let mute = Mute {
+        guild_id,
+        user_id: user.id,
+        timeout,
+        role_id,
+        expires_at: until.map(|until| until.timestamp()).unwrap_or(0),
+    };
+
+    match until {
+        Some(until) if timeout => ctx.timeout_member(guild_id, user.id, until).await?,
+        _ => ctx.add_member_role(guild_id, user.id, role_id).await?,
+    }
+
+    insert!(ctx.state.store(), mute.clone()).await?;
+
+    ctx.state.bus().publish(MemberMuted {
+        guild_id,
+        user_id: user.id,
+        moderator_id: ctx.event.author.id,
+        until,
+    });
+
+    // Role mutes with a duration are temporary roles, timeouts are not.
+    if let Some(until) = until.filter(|_| !timeout) {
+        ctx.state.bus().publish(TempRoleAssigned {
+            guild_id,
+            user_id: user.id,
+            role_id,
+            expires_at: until,
+        });
+    }
+
+    let end = match until {
+        Some(until) => format!("until <t:{}:f>", until.timestamp()),
+        None => String::from("indefinitely"),
+    };
+
+    ctx.respond(format!(
+        ":white_check_mark: Muted {} {}.",
+        user.id.mention(),
+        end
+    ))
+    .await?;
+
+    crate::plugins::log::log(LogEvent {
+        level: LogLevel::Info,
+        guild_id,
+        target: Some("moderation".to_owned()),
+        content: format!(
+            "{} muted {} {} ({})",
+            ctx.event.author.id.mention(),
+            user.id.mention(),
+            end,
+            if timeout { "timeout" } else { "mute role" }
+        ),
+    });
+
+    Ok(())
+}
+
+#[command(
+    description = "Unmute a member, removing either the timeout or the mute role used to mute the member.",
+    usage = "<@User>",
+    example = "@Robbbbbbb",
+    permissions = [nodes::MUTE],
+    bot_permissions = [MODERATE_MEMBERS, MANAGE_ROLES],
+    audited,
+    mutates_data,
+)]
+async fn unmute(mut ctx: GuildMessageContext) -> Result {
+    let user: UserMention = ctx.args.pop_parse()?;
+
+    ctx.state.store().check_writable()?;
+
+    let guild_id = ctx.event.guild_id;
+
+    let mute = get_one!(ctx.state.store(), Mute => {
+        guild_id == guild_id,
+        user_id == user.id,
+    })
+    .await?;
+
+    match mute {
+        Some(mute) => lift(&ctx, &mute).await?,
+        // Members can also be timed out from the Discord client.
+        None => {
+            let member = ctx.member(guild_id, user.id).await?;
+            if !member.is_timed_out(Utc::now()) {
+                ctx.respond(format!(":x: {} is not muted.", user.id.mention()))
+                    .await?;
+                return Ok(());
+            }
+
+            ctx.remove_timeout(guild_id, user.id).await?;
+        }
+    }
+
+    ctx.respond(format!(":white_check_mark: Unmuted {}.", user.id.mention()))
+        .await?;
+
+    crate::plugins::log::log(LogEvent {
+        level: LogLevel::Info,
+        guild_id,
+        target: Some("moderation".to_owned()),
+        content: format!(
+            "{} unmuted {}",
+            ctx.event.author.id.mention(),
+            user.id.mention()
+        ),
+    });
+
+    Ok(())
+}
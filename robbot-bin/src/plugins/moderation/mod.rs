@@ -0,0 +1,182 @@
+//! # Moderation plugin
+//! Commands to clean up channels and mute members. `purge` deletes the most
+//! recent messages of a channel, optionally only those of a single user.
+//!
+//! `mute` uses a native Discord timeout for durations up to 28 days and the
+//! mute role of the [`muterole`](crate::plugins::muterole) plugin for longer
+//! or indefinite mutes. The mechanism is recorded with the mute, so `unmute`
+//! reverses the right one. Role mutes are lifted by a task once they expire,
+//! timeouts end on their own.
+mod commands;
+mod profile;
+
+pub use profile::Profile;
+
+use robbot::model::id::{GuildId, RoleId, UserId};
+use robbot::store::delete;
+use robbot::{module, task, Error, Result, StoreData};
+use robbot_core::bus::TempRoleExpired;
+use robbot_core::context::Context;
+
+use chrono::{Duration, Utc};
+
+use std::result;
+
+/// The longest timeout Discord allows, in days.
+const MAX_TIMEOUT_DAYS: i64 = 28;
+
+pub mod nodes {
+    robbot::permission_nodes! {
+        "moderation" => {
+            PURGE = "purge",
+            MUTE = "mute",
+        }
+    }
+}
+
+module! {
+    name: "moderation",
+    cmds: {
+        commands::purge,
+        commands::mute,
+        commands::unmute,
+    },
+    store: [
+        Mute,
+    ],
+    tasks: [
+        unmute_expired,
+    ],
+    permissions: nodes::NODES,
+}
+
+/// An active mute of a member.
+#[derive(Clone, Debug, StoreData)]
+pub struct Mute {
+    pub guild_id: GuildId,
+    pub user_id: UserId,
+    /// `true` if the member was timed out, `false` if the mute role was
+    /// added.
+    pub timeout: bool,
+    /// The mute role added to the member. `0` for timeouts.
+    pub role_id: RoleId,
+    /// The time the mute ends as a unix timestamp in seconds. `0` if the mute
+    /// does not end.
+    pub expires_at: i64,
+}
+
+/// The way a member is muted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Mechanism {
+    /// A native Discord timeout.
+    Timeout,
+    /// The mute role of the guild.
+    Role,
+}
+
+/// Returns the mechanism used for a mute of `duration`, `None` being an
+/// indefinite mute.
+fn mechanism(duration: Option<Duration>) -> Mechanism {
+    match duration {
+        Some(duration) if duration <= Duration::days(MAX_TIMEOUT_DAYS) => Mechanism::Timeout,
+        _ => Mechanism::Role,
+    }
+}
+
+/// Lifts a mute and removes it from the store.
+async fn lift<T>(ctx: &Context<T>, mute: &Mute) -> result::Result<(), Error>
+where
+    T: Send + Sync,
+{
+    match mute.timeout {
+        true => ctx.remove_timeout(mute.guild_id, mute.user_id).await?,
+        false => {
+            ctx.remove_member_role(mute.guild_id, mute.user_id, mute.role_id)
+                .await?
+        }
+    }
+
+    delete!(ctx.state.store(), Mute => {
+        guild_id == mute.guild_id,
+        user_id == mute.user_id,
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Lifts expired role mutes and removes expired timeouts from the store. A
+/// run must not overlap with the next one.
+#[task(interval = "1m", timeout = "1m", mutates_data = true)]
+async fn unmute_expired(ctx: Context<()>) -> Result {
+    let now = Utc::now().timestamp();
+
+    let mutes = ctx
+        .state
+        .store()
+        .get_all(ctx.state.store().make_descriptor::<Mute>())
+        .await?;
+
+    for mute in mutes {
+        if mute.expires_at == 0 || mute.expires_at > now {
+            continue;
+        }
+
+        ctx.state.store().check_writable()?;
+
+        // The timeout already ended.
+        if mute.timeout {
+            delete!(ctx.state.store(), Mute => {
+                guild_id == mute.guild_id,
+                user_id == mute.user_id,
+            })
+            .await?;
+            continue;
+        }
+
+        // The member might have left the guild, the mute is removed either
+        // way.
+        if let Err(err) = lift(&ctx, &mute).await {
+            log::warn!(
+                "[MODERATION] Failed to unmute {} in guild {}: {:?}",
+                mute.user_id,
+                mute.guild_id,
+                err
+            );
+
+            delete!(ctx.state.store(), Mute => {
+                guild_id == mute.guild_id,
+                user_id == mute.user_id,
+            })
+            .await?;
+            continue;
+        }
+
+        ctx.state.bus().publish(TempRoleExpired {
+            guild_id: mute.guild_id,
+            user_id: mute.user_id,
+            role_id: mute.role_id,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mechanism, Mechanism};
+
+    use chrono::Duration;
+
+    #[test]
+    fn test_mechanism() {
+        assert_eq!(mechanism(Some(Duration::minutes(10))), Mechanism::Timeout);
+        assert_eq!(mechanism(Some(Duration::days(28))), Mechanism::Timeout);
+        assert_eq!(
+            mechanism(Some(Duration::days(28) + Duration::seconds(1))),
+            Mechanism::Role
+        );
+        assert_eq!(mechanism(Some(Duration::weeks(52))), Mechanism::Role);
+        assert_eq!(mechanism(None), Mechanism::Role);
+    }
+}
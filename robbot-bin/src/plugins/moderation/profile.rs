@@ -0,0 +1,51 @@
+use super::Mute;
+
+use crate::plugins::profile::{ProfileProvider, ProfileSection};
+
+use async_trait::async_trait;
+use robbot::model::id::{GuildId, UserId};
+use robbot::store::get_one;
+use robbot::Error;
+use robbot_core::state::State;
+
+use std::result;
+
+/// Shows the active mute of a member.
+pub struct Profile;
+
+#[async_trait]
+impl ProfileProvider for Profile {
+    fn name(&self) -> &str {
+        "Mute"
+    }
+
+    async fn section(
+        &self,
+        state: &State,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> result::Result<Option<ProfileSection>, Error> {
+        let mute = get_one!(state.store(), Mute => {
+            guild_id == guild_id,
+            user_id == user_id,
+        })
+        .await?;
+
+        let line = match mute {
+            Some(mute) => {
+                let mechanism = match mute.timeout {
+                    true => "timeout",
+                    false => "mute role",
+                };
+
+                match mute.expires_at {
+                    0 => format!("Muted indefinitely ({})", mechanism),
+                    expires_at => format!("Muted until <t:{}:f> ({})", expires_at, mechanism),
+                }
+            }
+            None => String::from("Not muted"),
+        };
+
+        Ok(Some(ProfileSection::new(self.name(), vec![line])))
+    }
+}
@@ -0,0 +1,183 @@
+use super::{apply_overwrite, mute_role, nodes, MuteRole};
+
+use robbot::arguments::RoleMention;
+use robbot::model::id::{ChannelId, GuildId, RoleId};
+use robbot::model::permissions::Permissions;
+use robbot::prelude::ArgumentsExt;
+use robbot::store::{delete, insert};
+use robbot::{command, Error, Result};
+use robbot_core::context::GuildMessageContext;
+
+use std::fmt::Write;
+use std::result;
+
+/// The name of the role created by `muterole setup`.
+const ROLE_NAME: &str = "Muted";
+
+/// The progress message is updated after this many channels.
+const PROGRESS_INTERVAL: usize = 10;
+
+/// The maximum number of failed channels listed in the response.
+const MAX_FAILED_LISTED: usize = 40;
+
+#[command(
+    description = "Create the mute role and add its overwrites to all channels. An existing mute role is reused.",
+    permissions = [nodes::MANAGE],
+    bot_permissions = [MANAGE_ROLES, MANAGE_CHANNELS],
+    audited,
+    mutates_data,
+)]
+async fn setup(ctx: GuildMessageContext) -> Result {
+    ctx.state.store().check_writable()?;
+
+    let guild_id = ctx.event.guild_id;
+
+    let role_id = match mute_role(&ctx, guild_id).await? {
+        Some(role_id) if role_exists(&ctx, guild_id, role_id).await? => role_id,
+        _ => {
+            let role_id = ctx
+                .create_role(guild_id, ROLE_NAME, Permissions::empty())
+                .await?;
+
+            store_role(&ctx, role_id).await?;
+            role_id
+        }
+    };
+
+    update_channels(&ctx, role_id).await
+}
+
+#[command(
+    description = "Use an existing role as the mute role and add its overwrites to all channels.",
+    usage = "<@Role>",
+    example = "@Muted",
+    permissions = [nodes::MANAGE],
+    bot_permissions = [MANAGE_CHANNELS],
+    audited,
+    mutates_data,
+)]
+async fn set(mut ctx: GuildMessageContext) -> Result {
+    let role: RoleMention = ctx.args.pop_parse()?;
+
+    ctx.state.store().check_writable()?;
+
+    if !ctx.can_manage_role(ctx.event.guild_id, role.id).await {
+        ctx.respond(format!(
+            ":x: I cannot manage {}. Move it below my highest role.",
+            role.id.mention()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    store_role(&ctx, role.id).await?;
+
+    update_channels(&ctx, role.id).await
+}
+
+#[command(
+    description = "Unset the mute role. The role and its channel overwrites are kept.",
+    permissions = [nodes::MANAGE],
+    audited,
+    mutates_data,
+)]
+async fn unset(ctx: GuildMessageContext) -> Result {
+    ctx.state.store().check_writable()?;
+
+    let guild_id = ctx.event.guild_id;
+
+    delete!(ctx.state.store(), MuteRole => {
+        guild_id == guild_id,
+    })
+    .await?;
+
+    ctx.respond(":white_check_mark: Unset the mute role.")
+        .await?;
+    Ok(())
+}
+
+/// Replaces the mute role of the guild.
+async fn store_role(ctx: &GuildMessageContext, role_id: RoleId) -> Result {
+    let guild_id = ctx.event.guild_id;
+
+    delete!(ctx.state.store(), MuteRole => {
+        guild_id == guild_id,
+    })
+    .await?;
+
+    insert!(ctx.state.store(), MuteRole { guild_id, role_id }).await?;
+    Ok(())
+}
+
+/// Returns `true` if the role still exists in the guild.
+async fn role_exists(
+    ctx: &GuildMessageContext,
+    guild_id: GuildId,
+    role_id: RoleId,
+) -> result::Result<bool, Error> {
+    let roles = serenity::model::id::GuildId(guild_id.0)
+        .roles(&ctx.raw_ctx)
+        .await?;
+
+    Ok(roles.contains_key(&serenity::model::id::RoleId(role_id.0)))
+}
+
+/// Adds the mute role overwrite to all channels of the guild. Channels that
+/// cannot be updated are listed in the response instead of aborting.
+async fn update_channels(ctx: &GuildMessageContext, role_id: RoleId) -> Result {
+    let mut channels = ctx.channels(ctx.event.guild_id).await?;
+    channels.sort_by_key(|channel| channel.position);
+
+    let total = channels.len();
+
+    ctx.edit_or_respond(format!(":hourglass: Updating channels (0/{})...", total))
+        .await?;
+
+    let mut failed: Vec<ChannelId> = Vec::new();
+    for (index, channel) in channels.iter().enumerate() {
+        if let Err(err) = apply_overwrite(ctx, channel, role_id).await {
+            log::debug!(
+                "[MUTEROLE] Failed to add mute role overwrite to channel {}: {:?}",
+                channel.id,
+                err
+            );
+
+            failed.push(channel.id);
+        }
+
+        let done = index + 1;
+        if done % PROGRESS_INTERVAL == 0 && done != total {
+            let content = format!(":hourglass: Updating channels ({}/{})...", done, total);
+
+            let _ = ctx.edit_or_respond(content).await;
+        }
+    }
+
+    let mut content = format!(
+        ":white_check_mark: {} is the mute role. Updated {}/{} channels.",
+        role_id.mention(),
+        total - failed.len(),
+        total
+    );
+
+    if !failed.is_empty() {
+        let _ = write!(
+            content,
+            "\n:warning: Failed to update the overwrites in these channels, check the permissions of the bot: {}",
+            failed
+                .iter()
+                .take(MAX_FAILED_LISTED)
+                .map(|channel_id| channel_id.mention().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        if failed.len() > MAX_FAILED_LISTED {
+            let _ = write!(content, " and {} more", failed.len() - MAX_FAILED_LISTED);
+        }
+    }
+
+    ctx.edit_or_respond(content).await?;
+
+    Ok(())
+}
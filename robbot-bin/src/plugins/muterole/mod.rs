@@ -0,0 +1,134 @@
+//! # Mute role plugin
+//! Manages a per-guild mute role. The role is denied permission to send
+//! messages and speak in every channel of the guild, including channels
+//! created later.
+mod commands;
+
+use crate::plugins::log::{LogEvent, LogLevel};
+
+use robbot::model::channel::{GuildChannel, PermissionOverwrite, PermissionOverwriteKind};
+use robbot::model::id::{GuildId, RoleId};
+use robbot::model::permissions::Permissions;
+use robbot::store::get_one;
+use robbot::{hook, module, Error, Result, StoreData};
+use robbot_core::context::Context;
+use robbot_core::hook::ChannelCreateContext;
+
+use std::result;
+
+pub mod nodes {
+    robbot::permission_nodes! {
+        "muterole" => {
+            MANAGE = "manage",
+        }
+    }
+}
+
+/// The permissions denied to the mute role in every channel.
+const DENY: Permissions = Permissions {
+    bits: Permissions::SEND_MESSAGES.bits
+        | Permissions::SEND_MESSAGES_IN_THREADS.bits
+        | Permissions::CREATE_PUBLIC_THREADS.bits
+        | Permissions::CREATE_PRIVATE_THREADS.bits
+        | Permissions::ADD_REACTIONS.bits
+        | Permissions::SPEAK.bits,
+};
+
+module! {
+    name: "muterole",
+    cmds: {
+        "muterole": {
+            commands::setup,
+            commands::set,
+            commands::unset,
+        },
+    },
+    store: [
+        MuteRole,
+    ],
+    hooks: [
+        channel_create,
+    ],
+    permissions: nodes::NODES,
+}
+
+#[derive(Clone, Debug, StoreData)]
+pub struct MuteRole {
+    pub guild_id: GuildId,
+    pub role_id: RoleId,
+}
+
+/// Returns the mute role of a guild, if one is configured.
+pub async fn mute_role<T>(
+    ctx: &Context<T>,
+    guild_id: GuildId,
+) -> result::Result<Option<RoleId>, Error>
+where
+    T: Send + Sync,
+{
+    let mute_role = get_one!(ctx.state.store(), MuteRole => {
+        guild_id == guild_id,
+    })
+    .await?;
+
+    Ok(mute_role.map(|mute_role| mute_role.role_id))
+}
+
+/// Adds the mute role overwrite to a channel.
+async fn apply_overwrite<T>(ctx: &Context<T>, channel: &GuildChannel, role_id: RoleId) -> Result
+where
+    T: Send + Sync,
+{
+    let kind = PermissionOverwriteKind::Role(role_id);
+
+    // Keep other denied and allowed permissions of an existing overwrite.
+    let overwrite = match channel.permission_overwrites.iter().find(
+        |overwrite| matches!(overwrite.kind, PermissionOverwriteKind::Role(id) if id == role_id),
+    ) {
+        Some(overwrite) if overwrite.deny.contains(DENY) => return Ok(()),
+        Some(overwrite) => PermissionOverwrite {
+            allow: overwrite.allow & !DENY,
+            deny: overwrite.deny | DENY,
+            kind,
+        },
+        None => PermissionOverwrite {
+            allow: Permissions::empty(),
+            deny: DENY,
+            kind,
+        },
+    };
+
+    ctx.create_permission(channel.id, overwrite).await?;
+    Ok(())
+}
+
+/// Covers newly created channels with the mute role overwrite.
+#[hook]
+async fn channel_create(ctx: ChannelCreateContext) -> Result {
+    let channel: GuildChannel = ctx.event.0.clone().into();
+
+    let role_id = match mute_role(&ctx, channel.guild_id).await? {
+        Some(role_id) => role_id,
+        None => return Ok(()),
+    };
+
+    if let Err(err) = apply_overwrite(&ctx, &channel, role_id).await {
+        log::warn!(
+            "[MUTEROLE] Failed to add mute role overwrite to channel {}: {:?}",
+            channel.id,
+            err
+        );
+
+        crate::plugins::log::log(LogEvent {
+            level: LogLevel::Warn,
+            guild_id: channel.guild_id,
+            target: Some("muterole".to_owned()),
+            content: format!(
+                "Failed to add the mute role overwrite to {}. Check the permissions of the bot in the channel.",
+                channel.id.mention()
+            ),
+        });
+    }
+
+    Ok(())
+}
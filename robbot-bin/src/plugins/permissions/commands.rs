@@ -1,6 +1,6 @@
 use crate::plugins::log::{LogEvent, LogLevel};
 
-use super::PERMISSION_MANAGE;
+use super::nodes;
 
 use robbot::arguments::ArgumentsExt;
 use robbot::arguments::{RoleMention, UserMention};
@@ -16,7 +16,10 @@ use std::fmt::Write;
     description = "Add new permissions to a user or role.",
     usage = "<@User> <Permission...>",
     example = "@Robbbbbbb permissions.manage",
-    permissions = [PERMISSION_MANAGE],
+    permissions = [nodes::MANAGE],
+    ack_only,
+    audited,
+    mutates_data,
 )]
 async fn add(mut ctx: GuildMessageContext) -> Result {
     let id = ctx.args.pop().ok_or(Error::InvalidCommandUsage)?;
@@ -26,6 +29,8 @@ async fn add(mut ctx: GuildMessageContext) -> Result {
         return Err(Error::InvalidCommandUsage);
     }
 
+    ctx.state.store().check_writable()?;
+
     let guild_id = ctx.event.guild_id;
 
     if id.contains("@&") {
@@ -68,13 +73,6 @@ async fn add(mut ctx: GuildMessageContext) -> Result {
         ),
     });
 
-    let _ = ctx
-        .respond(format!(
-            ":white_check_mark: Added permissions `{}` to {}.",
-            ctx.args.as_ref().join("`,`"),
-            id
-        ))
-        .await;
     Ok(())
 }
 
@@ -82,7 +80,7 @@ async fn add(mut ctx: GuildMessageContext) -> Result {
     description = "List all permissions of a user or role.",
     usage = "<@User>",
     example = "@Robbbbbbb",
-    permissions = [PERMISSION_MANAGE],
+    permissions = [nodes::MANAGE],
 )]
 async fn list(mut ctx: GuildMessageContext) -> Result {
     let id = ctx.args.pop().ok_or(Error::InvalidCommandUsage)?;
@@ -134,7 +132,10 @@ async fn list(mut ctx: GuildMessageContext) -> Result {
     description = "Remove permissions from a user.",
     usage = "<@User> <Permission...>",
     example = "@Robbbbbbb",
-    permissions = [PERMISSION_MANAGE],
+    permissions = [nodes::MANAGE],
+    ack_only,
+    audited,
+    mutates_data,
 )]
 async fn remove(mut ctx: GuildMessageContext) -> Result {
     let id = ctx.args.pop().ok_or(Error::InvalidCommandUsage)?;
@@ -144,6 +145,8 @@ async fn remove(mut ctx: GuildMessageContext) -> Result {
         return Err(Error::InvalidCommandUsage);
     }
 
+    ctx.state.store().check_writable()?;
+
     let guild_id = ctx.event.guild_id;
 
     if id.contains("@&") {
@@ -184,12 +187,34 @@ async fn remove(mut ctx: GuildMessageContext) -> Result {
         ),
     });
 
-    let _ = ctx
-        .respond(format!(
-            ":white_check_mark: Removed permissions `{}` from {}.",
-            ctx.args.as_ref().join("`,`"),
-            id
-        ))
-        .await;
+    Ok(())
+}
+
+#[command(
+    description = "List all permission nodes known to the loaded modules.",
+    permissions = [nodes::MANAGE],
+)]
+async fn nodes(ctx: GuildMessageContext) -> Result {
+    let nodes = ctx.state.modules().permission_nodes();
+
+    let description = match nodes.is_empty() {
+        true => String::from("No permission nodes registered."),
+        false => {
+            let mut description = String::new();
+            for node in nodes {
+                let _ = writeln!(description, "`{}`", node);
+            }
+
+            description
+        }
+    };
+
+    ctx.respond(CreateMessage::new(|m| {
+        m.embed(|e| {
+            e.title("Permission Nodes");
+            e.description(description);
+        });
+    }))
+    .await?;
     Ok(())
 }
@@ -1,10 +1,16 @@
 mod commands;
 
-const PERMISSION_MANAGE: &str = "permissions.manage";
-
 use robbot::module;
 use robbot_core::permissions::{RolePermission, UserPermission};
 
+pub mod nodes {
+    robbot::permission_nodes! {
+        "permissions" => {
+            MANAGE = "manage",
+        }
+    }
+}
+
 module! {
     name: "permissions",
     cmds: {
@@ -12,10 +18,12 @@ module! {
             commands::add,
             commands::list,
             commands::remove,
+            commands::nodes,
         },
     },
     store: [
         UserPermission,
         RolePermission,
     ],
+    permissions: nodes::NODES,
 }
@@ -0,0 +1,247 @@
+use super::{close as close_poll, nodes, option_emoji, poll, render, Poll, MAX_OPTIONS};
+
+use chrono::{Duration, Utc};
+use robbot::arguments::MessageRef;
+use robbot::prelude::ArgumentsExt;
+use robbot::store::{get, insert};
+use robbot::task::parse_duration;
+use robbot::{command, Error, Result};
+use robbot_core::context::GuildMessageContext;
+
+/// The arguments of [`create`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CreateArgs {
+    question: String,
+    options: Vec<String>,
+    duration: Option<Duration>,
+    multi: bool,
+}
+
+/// Parses the arguments of [`create`]: the question followed by the options
+/// and the optional flags `--duration <Duration>` and `--multi`.
+fn parse_create<I>(args: I) -> Option<CreateArgs>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter();
+
+    let mut question = None;
+    let mut options = Vec::new();
+    let mut duration = None;
+    let mut multi = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--duration" => duration = Some(parse_duration(&args.next()?)?),
+            "--multi" => multi = true,
+            _ => {
+                // Options are stored separated by newlines.
+                let arg = arg.split_whitespace().collect::<Vec<_>>().join(" ");
+                if arg.is_empty() {
+                    return None;
+                }
+
+                match question {
+                    None => question = Some(arg),
+                    Some(_) => options.push(arg),
+                }
+            }
+        }
+    }
+
+    if !(2..=MAX_OPTIONS).contains(&options.len()) {
+        return None;
+    }
+
+    Some(CreateArgs {
+        question: question?,
+        options,
+        duration,
+        multi,
+    })
+}
+
+#[command(
+    description = "Create a poll members vote on with reactions. Polls take 2 to 10 options. Polls with a duration close automatically, `--multi` allows voting for multiple options.",
+    usage = "<\"Question\"> <\"Option\">... [--duration <Duration>] [--multi]",
+    example = "\"Movie night?\" \"Friday\" \"Saturday\" --duration 1d",
+    permissions = [nodes::CREATE],
+    bot_permissions = [EMBED_LINKS, ADD_REACTIONS],
+    mutates_data,
+)]
+async fn create(mut ctx: GuildMessageContext) -> Result {
+    let mut args = Vec::new();
+    while let Some(arg) = ctx.args.pop() {
+        args.push(arg);
+    }
+
+    let args = parse_create(args).ok_or(Error::InvalidCommandUsage)?;
+    let closes_at = match args.duration {
+        Some(duration) => Utc::now()
+            .checked_add_signed(duration)
+            .ok_or(Error::InvalidCommandUsage)?
+            .timestamp(),
+        None => 0,
+    };
+
+    ctx.state.store().check_writable()?;
+
+    let guild_id = ctx.event.guild_id;
+
+    let polls = get!(ctx.state.store(), Poll => {
+        guild_id == guild_id,
+    })
+    .await?;
+    let id = polls.iter().map(|poll| poll.id).max().unwrap_or(0) + 1;
+
+    let mut poll = Poll {
+        guild_id,
+        id,
+        channel_id: ctx.event.channel_id,
+        message_id: ctx.event.id,
+        user_id: ctx.event.author.id,
+        question: args.question,
+        options: args.options.join("\n"),
+        multi: args.multi,
+        closes_at,
+        closed: false,
+    };
+
+    let counts = vec![0; args.options.len()];
+    let message = ctx
+        .send_message(poll.channel_id, render(&poll, &counts))
+        .await?;
+    poll.message_id = message.id;
+
+    insert!(ctx.state.store(), poll.clone()).await?;
+
+    for index in 0..args.options.len() {
+        ctx.react_to(MessageRef::from(&message), option_emoji(index))
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[command(
+    description = "Close a poll before its duration ends, replacing the poll with its final results.",
+    usage = "<ID>",
+    example = "3",
+    permissions = [nodes::MANAGE],
+    audited,
+    mutates_data,
+)]
+async fn close(mut ctx: GuildMessageContext) -> Result {
+    let id: u64 = ctx.args.pop_parse()?;
+
+    let poll = match poll(&ctx, ctx.event.guild_id, id).await? {
+        Some(poll) => poll,
+        None => {
+            ctx.respond(format!(":x: No poll with the id `{}`.", id))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if poll.closed {
+        ctx.respond(format!(":x: Poll `{}` is already closed.", id))
+            .await?;
+        return Ok(());
+    }
+
+    close_poll(&ctx, &poll).await?;
+
+    ctx.respond(format!(":white_check_mark: Closed poll `{}`.", id))
+        .await?;
+    Ok(())
+}
+
+#[command(
+    description = "Show the current results of a poll.",
+    usage = "<ID>",
+    example = "3",
+    bot_permissions = [EMBED_LINKS],
+)]
+async fn results(mut ctx: GuildMessageContext) -> Result {
+    let id: u64 = ctx.args.pop_parse()?;
+
+    let poll = match poll(&ctx, ctx.event.guild_id, id).await? {
+        Some(poll) => poll,
+        None => {
+            ctx.respond(format!(":x: No poll with the id `{}`.", id))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let embed = super::results(&ctx, &poll).await?;
+
+    ctx.respond(embed).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_create, CreateArgs};
+
+    use chrono::Duration;
+
+    fn args(s: &[&str]) -> Vec<String> {
+        s.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_create() {
+        assert_eq!(
+            parse_create(args(&["Pizza?", "Yes", "No"])),
+            Some(CreateArgs {
+                question: String::from("Pizza?"),
+                options: args(&["Yes", "No"]),
+                duration: None,
+                multi: false,
+            })
+        );
+
+        assert_eq!(
+            parse_create(args(&[
+                "Toppings?",
+                "--multi",
+                "Ham",
+                "Olives\nand onions",
+                "--duration",
+                "1d"
+            ])),
+            Some(CreateArgs {
+                question: String::from("Toppings?"),
+                options: args(&["Ham", "Olives and onions"]),
+                duration: Some(Duration::days(1)),
+                multi: true,
+            })
+        );
+
+        // Too few or too many options.
+        assert_eq!(parse_create(args(&["Pizza?", "Yes"])), None);
+        let many: Vec<String> = (0..11).map(|i| i.to_string()).collect();
+        assert_eq!(
+            parse_create(args(&["Pizza?"]).into_iter().chain(many)),
+            None
+        );
+
+        // Invalid durations.
+        assert_eq!(
+            parse_create(args(&["Pizza?", "Yes", "No", "--duration"])),
+            None
+        );
+        assert_eq!(
+            parse_create(args(&["Pizza?", "Yes", "No", "--duration", "soon"])),
+            None
+        );
+        assert_eq!(parse_create(args(&["Pizza?", "Yes", " "])), None);
+
+        // Overflows when added to the current date.
+        assert_eq!(
+            parse_create(args(&["Pizza?", "Yes", "No", "--duration", "1000000000d"])),
+            None
+        );
+    }
+}
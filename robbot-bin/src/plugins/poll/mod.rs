@@ -0,0 +1,599 @@
+//! # Poll plugin
+//! Polls members with reactions. `poll create` sends the poll and reacts with
+//! a numbered emoji for every option. Members vote by reacting, every vote is
+//! stored as a [`PollVote`] so results survive a restart. Single choice polls
+//! only keep the latest vote of a member: voting for another option removes
+//! the previous vote and its reaction.
+//!
+//! Polls with a duration are closed by a minutely task, which also closes
+//! polls that became due while the bot was offline. Closing a poll replaces
+//! the poll message with the final results. Deleting the poll message deletes
+//! the poll and its votes.
+mod commands;
+
+use crate::plugins::log::{LogEvent, LogLevel};
+
+use chrono::Utc;
+use robbot::arguments::MessageRef;
+use robbot::builder::{EditMessage, EmbedBuilder};
+use robbot::model::emoji::Emoji;
+use robbot::model::id::{ChannelId, GuildId, MessageId, UserId};
+use robbot::store::{delete, get, get_one, insert};
+use robbot::{hook, module, task, Error, Result, StoreData};
+use robbot_core::context::Context;
+use robbot_core::hook::{
+    MessageDeleteContext, ReactionAddBatchContext, ReactionRemoveBatchContext,
+};
+use serenity::model::channel::Reaction;
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::result;
+
+/// The emojis used to vote for the options, in order.
+const OPTION_EMOJIS: [&str; 10] = ["1️⃣", "2️⃣", "3️⃣", "4️⃣", "5️⃣", "6️⃣", "7️⃣", "8️⃣", "9️⃣", "🔟"];
+
+/// The maximum number of options of a poll.
+pub const MAX_OPTIONS: usize = OPTION_EMOJIS.len();
+
+/// The width of the bars in the results.
+const BAR_WIDTH: usize = 10;
+
+pub mod nodes {
+    robbot::permission_nodes! {
+        "poll" => {
+            CREATE = "create",
+            MANAGE = "manage",
+        }
+    }
+}
+
+module! {
+    name: "poll",
+    cmds: {
+        "poll": {
+            commands::create,
+            commands::close,
+            commands::results,
+        },
+    },
+    store: [
+        Poll,
+        PollVote,
+    ],
+    hooks: [
+        reaction_add,
+        reaction_remove,
+        message_delete,
+    ],
+    tasks: [
+        close_due,
+    ],
+    permissions: nodes::NODES,
+}
+
+/// A poll in a guild.
+#[derive(Clone, Debug, StoreData)]
+pub struct Poll {
+    pub guild_id: GuildId,
+    /// The id of the poll, incremented per guild.
+    pub id: u64,
+    pub channel_id: ChannelId,
+    /// The message members vote on.
+    pub message_id: MessageId,
+    /// The creator of the poll.
+    pub user_id: UserId,
+    pub question: String,
+    /// The options of the poll, separated by newlines.
+    pub options: String,
+    /// Whether members can vote for multiple options.
+    pub multi: bool,
+    /// The time the poll closes as a unix timestamp in seconds. `0` keeps the
+    /// poll open until it is closed manually.
+    pub closes_at: i64,
+    pub closed: bool,
+}
+
+impl Poll {
+    pub fn options(&self) -> Vec<&str> {
+        self.options.lines().collect()
+    }
+}
+
+/// A vote of a member for an option of a [`Poll`].
+#[derive(Clone, Debug, StoreData)]
+pub struct PollVote {
+    pub guild_id: GuildId,
+    pub poll_id: u64,
+    pub user_id: UserId,
+    /// The index of the option.
+    pub option: u8,
+}
+
+/// Returns the emoji used to vote for the option at `index`.
+pub fn option_emoji(index: usize) -> Emoji {
+    Emoji::Unicode(OPTION_EMOJIS[index].to_owned())
+}
+
+/// Returns the index of the option voted for with `emoji`, if it belongs to
+/// one of the first `count` options.
+fn option_index(emoji: &Emoji, count: usize) -> Option<u8> {
+    OPTION_EMOJIS
+        .iter()
+        .take(count)
+        .position(|s| matches!(emoji, Emoji::Unicode(e) if e == s))
+        .map(|index| index as u8)
+}
+
+/// Returns a bar of [`BAR_WIDTH`] characters filled by `count` of `total`.
+fn bar(count: usize, total: usize) -> String {
+    let filled = match total {
+        0 => 0,
+        total => (count * BAR_WIDTH + total / 2) / total,
+    };
+
+    let mut bar = "█".repeat(filled);
+    bar.push_str(&"░".repeat(BAR_WIDTH - filled));
+    bar
+}
+
+/// Returns the options with their vote counts and the status of a poll.
+/// `counts` contains the number of votes of every option.
+fn describe(poll: &Poll, counts: &[usize]) -> String {
+    let total: usize = counts.iter().sum();
+
+    let mut description = String::new();
+    for (index, option) in poll.options().into_iter().enumerate() {
+        let count = counts.get(index).copied().unwrap_or(0);
+        let percent = match total {
+            0 => 0,
+            total => (count * 100 + total / 2) / total,
+        };
+
+        let _ = writeln!(
+            description,
+            "{} {}\n`{}` {}% ({})",
+            OPTION_EMOJIS[index],
+            option,
+            bar(count, total),
+            percent,
+            count
+        );
+    }
+
+    let status = if poll.closed {
+        String::from("Closed")
+    } else if poll.closes_at != 0 {
+        format!("Closes <t:{}:R>", poll.closes_at)
+    } else {
+        String::from("Open")
+    };
+
+    let choice = if poll.multi {
+        "Multiple choice"
+    } else {
+        "Single choice"
+    };
+
+    let _ = write!(description, "\n{} · {}", choice, status);
+    description
+}
+
+/// Renders a poll with the vote counts `counts`.
+fn render(poll: &Poll, counts: &[usize]) -> EmbedBuilder {
+    let total: usize = counts.iter().sum();
+
+    EmbedBuilder::new()
+        .title(&poll.question)
+        .description(describe(poll, counts))
+        .footer(format!("Poll #{} · {} votes", poll.id, total))
+}
+
+/// Returns the poll with the id `id` in a guild.
+pub async fn poll<T>(
+    ctx: &Context<T>,
+    guild_id: GuildId,
+    id: u64,
+) -> result::Result<Option<Poll>, Error>
+where
+    T: Send + Sync,
+{
+    let poll = get_one!(ctx.state.store(), Poll => {
+        guild_id == guild_id,
+        id == id,
+    })
+    .await?;
+
+    Ok(poll)
+}
+
+/// Returns the poll voted on with the message `message_id`.
+async fn poll_by_message<T>(
+    ctx: &Context<T>,
+    guild_id: GuildId,
+    message_id: MessageId,
+) -> result::Result<Option<Poll>, Error>
+where
+    T: Send + Sync,
+{
+    let poll = get_one!(ctx.state.store(), Poll => {
+        guild_id == guild_id,
+        message_id == message_id,
+    })
+    .await?;
+
+    Ok(poll)
+}
+
+/// Returns the number of votes of every option of a poll.
+async fn tally<T>(ctx: &Context<T>, poll: &Poll) -> result::Result<Vec<usize>, Error>
+where
+    T: Send + Sync,
+{
+    let votes = get!(ctx.state.store(), PollVote => {
+        guild_id == poll.guild_id,
+        poll_id == poll.id,
+    })
+    .await?;
+
+    let mut counts = vec![0; poll.options().len()];
+    for vote in votes {
+        if let Some(count) = counts.get_mut(vote.option as usize) {
+            *count += 1;
+        }
+    }
+
+    Ok(counts)
+}
+
+/// Returns the results of a poll.
+pub async fn results<T>(ctx: &Context<T>, poll: &Poll) -> result::Result<EmbedBuilder, Error>
+where
+    T: Send + Sync,
+{
+    let counts = tally(ctx, poll).await?;
+    Ok(render(poll, &counts))
+}
+
+/// Closes a poll and replaces the poll message with the final results.
+pub async fn close<T>(ctx: &Context<T>, poll: &Poll) -> Result
+where
+    T: Send + Sync,
+{
+    ctx.state.store().check_writable()?;
+
+    let poll = Poll {
+        closed: true,
+        ..poll.clone()
+    };
+
+    delete!(ctx.state.store(), Poll => {
+        guild_id == poll.guild_id,
+        id == poll.id,
+    })
+    .await?;
+
+    insert!(ctx.state.store(), poll.clone()).await?;
+
+    let embed = results(ctx, &poll).await?;
+
+    // The poll is closed even if the message is gone, the results are still
+    // available with `poll results`.
+    if let Err(err) = ctx
+        .edit_message(poll.channel_id, poll.message_id, EditMessage::from(embed))
+        .await
+    {
+        log::warn!(
+            "[POLL] Failed to edit message of poll {} in guild {}: {:?}",
+            poll.id,
+            poll.guild_id,
+            err
+        );
+    }
+
+    crate::plugins::log::log(LogEvent {
+        level: LogLevel::Info,
+        guild_id: poll.guild_id,
+        target: Some("poll".to_owned()),
+        content: format!("Closed poll #{}: {}", poll.id, poll.question),
+    });
+
+    Ok(())
+}
+
+/// Returns the open polls voted on with the messages `message_ids`, by
+/// message. Message ids are unique across guilds.
+async fn open_polls<T>(
+    ctx: &Context<T>,
+    mut message_ids: Vec<MessageId>,
+) -> result::Result<HashMap<MessageId, Poll>, Error>
+where
+    T: Send + Sync,
+{
+    message_ids.sort_unstable();
+    message_ids.dedup();
+
+    if message_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let polls = get!(ctx.state.store(), Poll => {
+        message_id in message_ids,
+    })
+    .await?;
+
+    Ok(polls
+        .into_iter()
+        .filter(|poll| !poll.closed)
+        .map(|poll| (poll.message_id, poll))
+        .collect())
+}
+
+/// Returns the reactions of members in guilds, with the guild and member.
+fn member_reactions<'a, I>(reactions: I) -> Vec<(GuildId, UserId, &'a Reaction)>
+where
+    I: IntoIterator<Item = &'a Reaction>,
+{
+    reactions
+        .into_iter()
+        .filter_map(|reaction| match (reaction.guild_id, reaction.user_id) {
+            (Some(guild_id), Some(user_id)) => Some((guild_id.into(), user_id.into(), reaction)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Adds the vote of a member for the option reacted with `emoji`.
+async fn vote<T>(ctx: &Context<T>, poll: &Poll, user_id: UserId, emoji: Emoji) -> Result
+where
+    T: Send + Sync,
+{
+    let guild_id = poll.guild_id;
+
+    let option = match option_index(&emoji, poll.options().len()) {
+        Some(option) => option,
+        None => return Ok(()),
+    };
+
+    ctx.state.store().check_writable()?;
+
+    let votes = get!(ctx.state.store(), PollVote => {
+        guild_id == guild_id,
+        poll_id == poll.id,
+        user_id == user_id,
+    })
+    .await?;
+
+    if votes.iter().any(|vote| vote.option == option) {
+        return Ok(());
+    }
+
+    if !poll.multi {
+        for vote in votes {
+            delete!(ctx.state.store(), PollVote => {
+                guild_id == guild_id,
+                poll_id == poll.id,
+                user_id == user_id,
+                option == vote.option,
+            })
+            .await?;
+
+            if let Err(err) = ctx
+                .remove_reaction(
+                    MessageRef::new(Some(guild_id), Some(poll.channel_id), poll.message_id),
+                    option_emoji(vote.option as usize),
+                    Some(user_id),
+                )
+                .await
+            {
+                log::debug!(
+                    "[POLL] Failed to remove previous vote reaction of {} on poll {}: {:?}",
+                    user_id,
+                    poll.id,
+                    err
+                );
+            }
+        }
+    }
+
+    insert!(
+        ctx.state.store(),
+        PollVote {
+            guild_id,
+            poll_id: poll.id,
+            user_id,
+            option,
+        }
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Removes the vote of a member for the option reacted with `emoji`.
+async fn unvote<T>(ctx: &Context<T>, poll: &Poll, user_id: UserId, emoji: Emoji) -> Result
+where
+    T: Send + Sync,
+{
+    let option = match option_index(&emoji, poll.options().len()) {
+        Some(option) => option,
+        None => return Ok(()),
+    };
+
+    ctx.state.store().check_writable()?;
+
+    delete!(ctx.state.store(), PollVote => {
+        guild_id == poll.guild_id,
+        poll_id == poll.id,
+        user_id == user_id,
+        option == option,
+    })
+    .await?;
+
+    Ok(())
+}
+
+// The reaction hooks look up the polls of a whole batch of reactions at once,
+// most reactions are not on polls.
+#[hook(batch = true)]
+async fn reaction_add(ctx: ReactionAddBatchContext) -> Result {
+    let bot_id = *ctx.state.bot_id.read().unwrap();
+
+    let reactions: Vec<_> = member_reactions(ctx.event.iter().map(|event| &event.0))
+        .into_iter()
+        .filter(|(_, user_id, _)| Some(*user_id) != bot_id)
+        .collect();
+
+    let message_ids = reactions
+        .iter()
+        .map(|(_, _, reaction)| reaction.message_id.into())
+        .collect();
+    let polls = open_polls(&ctx, message_ids).await?;
+
+    // A failed vote does not stop the other votes of the batch.
+    let mut result = Ok(());
+    for (guild_id, user_id, reaction) in reactions {
+        let poll = match polls.get(&reaction.message_id.into()) {
+            Some(poll) if poll.guild_id == guild_id => poll,
+            _ => continue,
+        };
+
+        if let Ok(emoji) = Emoji::try_from(reaction.emoji.clone()) {
+            if let Err(err) = vote(&ctx, poll, user_id, emoji).await {
+                result = Err(err);
+            }
+        }
+    }
+
+    result
+}
+
+#[hook(batch = true)]
+async fn reaction_remove(ctx: ReactionRemoveBatchContext) -> Result {
+    let reactions = member_reactions(ctx.event.iter().map(|event| &event.0));
+
+    let message_ids = reactions
+        .iter()
+        .map(|(_, _, reaction)| reaction.message_id.into())
+        .collect();
+    let polls = open_polls(&ctx, message_ids).await?;
+
+    let mut result = Ok(());
+    for (guild_id, user_id, reaction) in reactions {
+        let poll = match polls.get(&reaction.message_id.into()) {
+            Some(poll) if poll.guild_id == guild_id => poll,
+            _ => continue,
+        };
+
+        if let Ok(emoji) = Emoji::try_from(reaction.emoji.clone()) {
+            if let Err(err) = unvote(&ctx, poll, user_id, emoji).await {
+                result = Err(err);
+            }
+        }
+    }
+
+    result
+}
+
+#[hook]
+async fn message_delete(ctx: MessageDeleteContext) -> Result {
+    let guild_id: GuildId = match ctx.event.guild_id {
+        Some(guild_id) => guild_id.into(),
+        None => return Ok(()),
+    };
+
+    let poll = match poll_by_message(&ctx, guild_id, ctx.event.message_id.into()).await? {
+        Some(poll) => poll,
+        None => return Ok(()),
+    };
+
+    ctx.state.store().check_writable()?;
+
+    delete!(ctx.state.store(), PollVote => {
+        guild_id == guild_id,
+        poll_id == poll.id,
+    })
+    .await?;
+
+    delete!(ctx.state.store(), Poll => {
+        guild_id == guild_id,
+        id == poll.id,
+    })
+    .await?;
+
+    log::debug!(
+        "[POLL] Deleted poll {} in guild {} with its message",
+        poll.id,
+        guild_id
+    );
+
+    Ok(())
+}
+
+#[task(interval = "1m", on_load = true, mutates_data = true)]
+async fn close_due(ctx: Context<()>) -> Result {
+    let polls = ctx
+        .state
+        .store()
+        .get_all(ctx.state.store().make_descriptor::<Poll>())
+        .await?;
+
+    let now = Utc::now().timestamp();
+
+    for poll in polls {
+        if poll.closed || poll.closes_at == 0 || poll.closes_at > now {
+            continue;
+        }
+
+        close(&ctx, &poll).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bar, describe, option_emoji, option_index, Poll};
+
+    use robbot::model::emoji::Emoji;
+    use robbot::model::id::{ChannelId, GuildId, MessageId, UserId};
+
+    #[test]
+    fn test_option_index() {
+        assert_eq!(option_index(&option_emoji(0), 3), Some(0));
+        assert_eq!(option_index(&option_emoji(2), 3), Some(2));
+        // Reactions for options the poll doesn't have are ignored.
+        assert_eq!(option_index(&option_emoji(3), 3), None);
+        assert_eq!(option_index(&Emoji::Unicode(String::from("🔥")), 3), None);
+    }
+
+    #[test]
+    fn test_bar() {
+        assert_eq!(bar(0, 0), "░░░░░░░░░░");
+        assert_eq!(bar(1, 2), "█████░░░░░");
+        assert_eq!(bar(2, 3), "███████░░░");
+        assert_eq!(bar(3, 3), "██████████");
+    }
+
+    #[test]
+    fn test_describe() {
+        let poll = Poll {
+            guild_id: GuildId(1),
+            id: 4,
+            channel_id: ChannelId(2),
+            message_id: MessageId(3),
+            user_id: UserId(4),
+            question: String::from("Pizza?"),
+            options: String::from("Yes\nNo"),
+            multi: false,
+            closes_at: 0,
+            closed: true,
+        };
+
+        let description = describe(&poll, &[3, 1]);
+
+        assert!(description.contains("1️⃣ Yes\n`████████░░` 75% (3)"));
+        assert!(description.contains("2️⃣ No\n`███░░░░░░░` 25% (1)"));
+        assert!(description.ends_with("Single choice · Closed"));
+    }
+}
@@ -0,0 +1,42 @@
+use super::{nodes, profile as build_profile};
+
+use robbot::arguments::UserMention;
+use robbot::prelude::ArgumentsExt;
+use robbot::{command, Result};
+use robbot_core::context::GuildMessageContext;
+use robbot_core::settings::TimeDisplay;
+
+#[command(
+    description = "Show everything known about a member: join date, warnings, mutes and more.",
+    usage = "<@User>",
+    example = "@Robbbbbbb",
+    permissions = [nodes::VIEW],
+    bot_permissions = [EMBED_LINKS],
+)]
+async fn profile(mut ctx: GuildMessageContext) -> Result {
+    let user: UserMention = ctx.args.pop_parse()?;
+
+    let guild_id = ctx.event.guild_id;
+
+    // Users that left the guild still have a profile.
+    let joined = match ctx.member(guild_id, user.id).await {
+        Ok(member) => match member.joined_at {
+            Some(joined_at) => Some(ctx.format_time(joined_at, TimeDisplay::Markdown).await?),
+            None => Some(String::from("at an unknown time")),
+        },
+        Err(err) => {
+            log::debug!(
+                "[PROFILE] Failed to get member {} in guild {}: {:?}",
+                user.id,
+                guild_id,
+                err
+            );
+            None
+        }
+    };
+
+    let embed = build_profile(&ctx.state, guild_id, user.id, joined.as_deref()).await;
+
+    ctx.respond(embed).await?;
+    Ok(())
+}
@@ -0,0 +1,292 @@
+//! # Profile plugin
+//! Shows everything the bot knows about a member of a guild in one embed.
+//! Plugins contribute to the profile by registering a [`ProfileProvider`]
+//! with [`register`], which returns the [`ProfileSection`] of a member.
+//!
+//! Providers are queried concurrently and each has [`PROVIDER_TIMEOUT`] to
+//! answer. A provider failing or timing out is shown as an error line in
+//! its section without affecting the other providers.
+mod commands;
+
+use async_trait::async_trait;
+use futures::future;
+use parking_lot::RwLock;
+use robbot::builder::EmbedBuilder;
+use robbot::model::id::{GuildId, UserId};
+use robbot::{module, Error};
+use robbot_core::state::State;
+use tokio::time;
+
+use std::result;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The time a provider has to return its section.
+pub const PROVIDER_TIMEOUT: Duration = Duration::from_secs(5);
+
+static PROVIDERS: RwLock<Vec<Arc<dyn ProfileProvider>>> = RwLock::new(Vec::new());
+
+pub mod nodes {
+    robbot::permission_nodes! {
+        "profile" => {
+            VIEW = "view",
+        }
+    }
+}
+
+module! {
+    name: "profile",
+    cmds: {
+        commands::profile,
+    },
+    permissions: nodes::NODES,
+}
+
+/// A section of a profile, shown as a field.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProfileSection {
+    pub title: String,
+    pub lines: Vec<String>,
+}
+
+impl ProfileSection {
+    pub fn new<T>(title: T, lines: Vec<String>) -> Self
+    where
+        T: ToString,
+    {
+        Self {
+            title: title.to_string(),
+            lines,
+        }
+    }
+}
+
+/// A source of a [`ProfileSection`], registered with [`register`].
+#[async_trait]
+pub trait ProfileProvider: Send + Sync {
+    /// The name of the provider, used as the title of its section if it
+    /// fails.
+    fn name(&self) -> &str;
+
+    /// Returns the section of a member. Returning `None` omits the section.
+    async fn section(
+        &self,
+        state: &State,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> result::Result<Option<ProfileSection>, Error>;
+}
+
+/// Registers a provider of profile sections. Sections are shown in the
+/// order their providers were registered.
+pub fn register<P>(provider: P)
+where
+    P: ProfileProvider + 'static,
+{
+    PROVIDERS.write().push(Arc::new(provider));
+}
+
+/// The outcome of a provider.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Section {
+    Ok(ProfileSection),
+    /// The provider returned an error.
+    Failed(String),
+    /// The provider did not answer in time.
+    TimedOut(String),
+}
+
+/// Collects the sections of `providers` for a member concurrently.
+async fn collect(
+    providers: &[Arc<dyn ProfileProvider>],
+    state: &State,
+    guild_id: GuildId,
+    user_id: UserId,
+    timeout: Duration,
+) -> Vec<Section> {
+    let sections = providers.iter().map(|provider| async move {
+        let name = provider.name().to_owned();
+
+        match time::timeout(timeout, provider.section(state, guild_id, user_id)).await {
+            Ok(Ok(section)) => section.map(Section::Ok),
+            Ok(Err(err)) => {
+                log::warn!(
+                    "[PROFILE] Provider {} failed for {} in guild {}: {:?}",
+                    name,
+                    user_id,
+                    guild_id,
+                    err
+                );
+                Some(Section::Failed(name))
+            }
+            Err(_) => {
+                log::warn!(
+                    "[PROFILE] Provider {} timed out for {} in guild {}",
+                    name,
+                    user_id,
+                    guild_id
+                );
+                Some(Section::TimedOut(name))
+            }
+        }
+    });
+
+    future::join_all(sections)
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Renders the profile of a member. `joined` is the formatted time the
+/// member joined the guild, `None` if the user is not a member.
+fn render(user_id: UserId, joined: Option<&str>, sections: &[Section]) -> EmbedBuilder {
+    let description = match joined {
+        Some(joined) => format!("{}\nJoined {}", user_id.mention(), joined),
+        None => format!("{}\nNot a member of this server", user_id.mention()),
+    };
+
+    let fields = sections.iter().map(|section| match section {
+        Section::Ok(section) => {
+            let value = match section.lines.is_empty() {
+                true => String::from("_None_"),
+                false => section.lines.join("\n"),
+            };
+
+            (section.title.clone(), value, false)
+        }
+        Section::Failed(name) => (name.clone(), String::from(":x: _Unavailable_"), false),
+        Section::TimedOut(name) => (name.clone(), String::from(":x: _Timed out_"), false),
+    });
+
+    EmbedBuilder::new()
+        .title("Profile")
+        .description(description)
+        .fields_from(fields)
+}
+
+/// Builds the profile of a member with all registered providers.
+pub async fn profile(
+    state: &State,
+    guild_id: GuildId,
+    user_id: UserId,
+    joined: Option<&str>,
+) -> EmbedBuilder {
+    let providers = PROVIDERS.read().clone();
+    let sections = collect(&providers, state, guild_id, user_id, PROVIDER_TIMEOUT).await;
+
+    render(user_id, joined, &sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{collect, render, ProfileProvider, ProfileSection, Section};
+
+    use async_trait::async_trait;
+    use robbot::model::id::{GuildId, UserId};
+    use robbot::Error;
+    use robbot_core::config::Config;
+    use robbot_core::state::State;
+
+    use std::result;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    enum Provider {
+        Ok,
+        Empty,
+        Failed,
+        Slow,
+    }
+
+    #[async_trait]
+    impl ProfileProvider for Provider {
+        fn name(&self) -> &str {
+            match self {
+                Self::Ok => "Ok",
+                Self::Empty => "Empty",
+                Self::Failed => "Failed",
+                Self::Slow => "Slow",
+            }
+        }
+
+        async fn section(
+            &self,
+            _state: &State,
+            _guild_id: GuildId,
+            user_id: UserId,
+        ) -> result::Result<Option<ProfileSection>, Error> {
+            match self {
+                Self::Ok => Ok(Some(ProfileSection::new(
+                    "Section",
+                    vec![format!("User {}", user_id)],
+                ))),
+                Self::Empty => Ok(None),
+                Self::Failed => Err(Error::InvalidCommandUsage),
+                Self::Slow => {
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect() {
+        let state = State::new(Config::default());
+        let providers: Vec<Arc<dyn ProfileProvider>> = vec![
+            Arc::new(Provider::Slow),
+            Arc::new(Provider::Ok),
+            Arc::new(Provider::Empty),
+            Arc::new(Provider::Failed),
+        ];
+
+        let sections = collect(
+            &providers,
+            &state,
+            GuildId(1),
+            UserId(2),
+            Duration::from_millis(50),
+        )
+        .await;
+
+        assert_eq!(
+            sections,
+            vec![
+                Section::TimedOut(String::from("Slow")),
+                Section::Ok(ProfileSection::new("Section", vec![String::from("User 2")])),
+                Section::Failed(String::from("Failed")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let sections = vec![
+            Section::Ok(ProfileSection::new(
+                "Automod",
+                vec![String::from("Warnings: **3**")],
+            )),
+            Section::Ok(ProfileSection::new("Mute", Vec::new())),
+            Section::Failed(String::from("Audit")),
+            Section::TimedOut(String::from("Moderation")),
+        ];
+
+        let embed = robbot::builder::CreateEmbed::from(render(
+            UserId(2),
+            Some("<t:1600000000:D>"),
+            &sections,
+        ));
+        let embed = format!("{:?}", embed);
+
+        assert!(embed.contains("<@2>"));
+        assert!(embed.contains("Joined <t:1600000000:D>"));
+        assert!(embed.contains("Warnings: **3**"));
+        assert!(embed.contains("_None_"));
+        assert!(embed.contains(":x: _Unavailable_"));
+        assert!(embed.contains(":x: _Timed out_"));
+
+        let embed = robbot::builder::CreateEmbed::from(render(UserId(2), None, &[]));
+        assert!(format!("{:?}", embed).contains("Not a member of this server"));
+    }
+}
@@ -0,0 +1,124 @@
+use super::{format_window, nodes, parse_window, Category, RetentionPolicy};
+
+use robbot::builder::EmbedBuilder;
+use robbot::prelude::ArgumentsExt;
+use robbot::store::{delete, get, insert};
+use robbot::{command, Error, Result};
+use robbot_core::context::GuildMessageContext;
+
+use std::fmt::Write;
+
+#[command(
+    description = "Set how long data of a category is kept. Categories are `warnings` and `audit`. Windows are given in days, `off` keeps data forever. Older data is deleted daily.",
+    usage = "<Category> <Days>d|off",
+    arg_choices(category = ["warnings", "audit"]),
+    example = "warnings 180d",
+    permissions = [nodes::MANAGE],
+    audited,
+    mutates_data,
+)]
+async fn set(mut ctx: GuildMessageContext) -> Result {
+    let category: Category = ctx.args.pop_parse()?;
+    let days = ctx
+        .args
+        .pop()
+        .and_then(|arg| parse_window(&arg))
+        .ok_or(Error::InvalidCommandUsage)?;
+
+    ctx.state.store().check_writable()?;
+
+    let guild_id = ctx.event.guild_id;
+
+    delete!(ctx.state.store(), RetentionPolicy => {
+        guild_id == guild_id,
+        category == category.to_string(),
+    })
+    .await?;
+
+    insert!(
+        ctx.state.store(),
+        RetentionPolicy {
+            guild_id,
+            category: category.to_string(),
+            days,
+        }
+    )
+    .await?;
+
+    let message = match days {
+        0 => format!(":white_check_mark: Keeping {} forever.", category),
+        days => format!(":white_check_mark: Keeping {} for {} days.", category, days),
+    };
+
+    ctx.respond(message).await?;
+    Ok(())
+}
+
+#[command(
+    description = "Reset the retention window of a category to the default.",
+    usage = "<Category>",
+    arg_choices(category = ["warnings", "audit"]),
+    example = "warnings",
+    permissions = [nodes::MANAGE],
+    audited,
+    mutates_data,
+)]
+async fn reset(mut ctx: GuildMessageContext) -> Result {
+    let category: Category = ctx.args.pop_parse()?;
+
+    ctx.state.store().check_writable()?;
+
+    delete!(ctx.state.store(), RetentionPolicy => {
+        guild_id == ctx.event.guild_id,
+        category == category.to_string(),
+    })
+    .await?;
+
+    ctx.respond(format!(
+        ":white_check_mark: Reset the retention window of {} to `{}`.",
+        category,
+        format_window(category.default_days())
+    ))
+    .await?;
+    Ok(())
+}
+
+#[command(
+    description = "List the retention windows of all categories.",
+    permissions = [nodes::MANAGE],
+    bot_permissions = [EMBED_LINKS],
+)]
+async fn list(ctx: GuildMessageContext) -> Result {
+    let policies = get!(ctx.state.store(), RetentionPolicy => {
+        guild_id == ctx.event.guild_id,
+    })
+    .await?;
+
+    let mut description = String::new();
+    for category in Category::ALL {
+        let default = format_window(category.default_days());
+
+        let policy = policies
+            .iter()
+            .find(|policy| policy.category == category.name());
+
+        let _ = match policy {
+            Some(policy) => writeln!(
+                description,
+                "`{}`: `{}` (default `{}`)",
+                category,
+                format_window(policy.days),
+                default
+            ),
+            None => writeln!(description, "`{}`: `{}` (default)", category, default),
+        };
+    }
+
+    ctx.respond(
+        EmbedBuilder::new()
+            .title("Retention")
+            .description(description),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,356 @@
+//! # Retention plugin
+//! Deletes old moderation data. Every [`Category`] of data has a default
+//! retention window which guilds can override with a [`RetentionPolicy`].
+//!
+//! A daily task deletes all rows older than the window of their guild and
+//! logs the number of purged rows through the log plugin. Rows are deleted
+//! by their timestamps in chunks of at most [`CHUNK_SIZE`] timestamps, keeping
+//! single statements short.
+mod commands;
+
+use crate::plugins::audit::{self, AuditEntry};
+use crate::plugins::automod::AutomodWarning;
+use crate::plugins::log::{LogEvent, LogLevel};
+
+use chrono::Utc;
+use robbot::model::id::GuildId;
+use robbot::store::delete;
+use robbot::{module, task, Result, StoreData};
+use robbot_core::context::Context;
+use robbot_core::time::DAY;
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// The maximum number of timestamps deleted in a single statement.
+pub const CHUNK_SIZE: usize = 100;
+
+pub mod nodes {
+    robbot::permission_nodes! {
+        "retention" => {
+            MANAGE = "manage",
+        }
+    }
+}
+
+module! {
+    name: "retention",
+    cmds: {
+        "retention": {
+            commands::set,
+            commands::reset,
+            commands::list,
+        },
+    },
+    store: [
+        RetentionPolicy,
+    ],
+    tasks: [
+        purge,
+    ],
+    permissions: nodes::NODES,
+}
+
+/// The retention window of a [`Category`] in a guild, overriding the default
+/// of the category.
+#[derive(Clone, Debug, StoreData)]
+pub struct RetentionPolicy {
+    pub guild_id: GuildId,
+    /// The string representation of the [`Category`].
+    pub category: String,
+    /// The number of days rows are kept. `0` keeps rows forever.
+    pub days: u64,
+}
+
+/// A category of data with a retention window.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// Automod warnings.
+    Warnings,
+    /// Audit entries.
+    Audit,
+}
+
+impl Category {
+    pub const ALL: &'static [Self] = &[Self::Warnings, Self::Audit];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Warnings => "warnings",
+            Self::Audit => "audit",
+        }
+    }
+
+    /// Returns the number of days rows are kept without a policy. `0` keeps
+    /// rows forever.
+    pub fn default_days(self) -> u64 {
+        match self {
+            Self::Warnings => 0,
+            Self::Audit => audit::RETENTION_DAYS as u64,
+        }
+    }
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Category {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|category| category.name() == s)
+            .ok_or(())
+    }
+}
+
+/// Parses a retention window in days, e.g. `180d`. `off` keeps rows forever
+/// and is returned as `0`.
+pub fn parse_window(s: &str) -> Option<u64> {
+    if s == "off" {
+        return Some(0);
+    }
+
+    match s.strip_suffix('d')?.parse() {
+        Ok(days) if days > 0 => Some(days),
+        _ => None,
+    }
+}
+
+/// Formats a retention window as accepted by [`parse_window`].
+pub fn format_window(days: u64) -> String {
+    match days {
+        0 => String::from("off"),
+        days => format!("{}d", days),
+    }
+}
+
+/// The retention windows of all guilds with a policy.
+#[derive(Clone, Debug, Default)]
+struct Windows {
+    policies: HashMap<(GuildId, Category), u64>,
+}
+
+impl Windows {
+    fn new(policies: Vec<RetentionPolicy>) -> Self {
+        let policies = policies
+            .into_iter()
+            .filter_map(|policy| {
+                let category = policy.category.parse().ok()?;
+                Some(((policy.guild_id, category), policy.days))
+            })
+            .collect();
+
+        Self { policies }
+    }
+
+    /// Returns the oldest timestamp kept in `category` in a guild, `None` if
+    /// rows are kept forever.
+    fn min_timestamp(&self, guild_id: GuildId, category: Category, now: i64) -> Option<i64> {
+        let days = self
+            .policies
+            .get(&(guild_id, category))
+            .copied()
+            .unwrap_or_else(|| category.default_days());
+
+        (days != 0).then(|| now - days as i64 * DAY)
+    }
+}
+
+/// Rows to delete in a guild.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Expired {
+    /// The number of rows.
+    count: usize,
+    /// The distinct timestamps of the rows.
+    timestamps: Vec<i64>,
+}
+
+/// Returns the rows of `category` to delete by guild. `rows` contains the
+/// guild and timestamp of every row.
+fn expired<I>(rows: I, windows: &Windows, category: Category, now: i64) -> HashMap<GuildId, Expired>
+where
+    I: IntoIterator<Item = (GuildId, i64)>,
+{
+    let mut guilds: HashMap<GuildId, Expired> = HashMap::new();
+
+    for (guild_id, timestamp) in rows {
+        match windows.min_timestamp(guild_id, category, now) {
+            Some(min_timestamp) if timestamp < min_timestamp => {
+                let expired = guilds.entry(guild_id).or_default();
+                expired.count += 1;
+                expired.timestamps.push(timestamp);
+            }
+            _ => (),
+        }
+    }
+
+    for expired in guilds.values_mut() {
+        expired.timestamps.sort_unstable();
+        expired.timestamps.dedup();
+    }
+
+    guilds
+}
+
+#[task(interval = "1d", on_load = true, mutates_data = true)]
+async fn purge(ctx: Context<()>) -> Result {
+    ctx.state.store().check_writable()?;
+
+    let policies = ctx
+        .state
+        .store()
+        .get_all(ctx.state.store().make_descriptor::<RetentionPolicy>())
+        .await?;
+    let windows = Windows::new(policies);
+
+    let now = Utc::now().timestamp();
+
+    let mut purged: HashMap<GuildId, Vec<(Category, usize)>> = HashMap::new();
+
+    for category in Category::ALL.iter().copied() {
+        let expired = match category {
+            Category::Warnings => {
+                let warnings = ctx
+                    .state
+                    .store()
+                    .get_all(ctx.state.store().make_descriptor::<AutomodWarning>())
+                    .await?;
+
+                let rows = warnings.iter().map(|w| (w.guild_id, w.timestamp));
+                expired(rows, &windows, category, now)
+            }
+            Category::Audit => {
+                let entries = ctx
+                    .state
+                    .store()
+                    .get_all(ctx.state.store().make_descriptor::<AuditEntry>())
+                    .await?;
+
+                let rows = entries.iter().map(|e| (e.guild_id, e.timestamp));
+                expired(rows, &windows, category, now)
+            }
+        };
+
+        for (guild_id, expired) in expired {
+            for chunk in expired.timestamps.chunks(CHUNK_SIZE) {
+                let timestamps = chunk.to_vec();
+
+                match category {
+                    Category::Warnings => {
+                        delete!(ctx.state.store(), AutomodWarning => {
+                            guild_id == guild_id,
+                            timestamp in timestamps,
+                        })
+                        .await?;
+                    }
+                    Category::Audit => {
+                        delete!(ctx.state.store(), AuditEntry => {
+                            guild_id == guild_id,
+                            timestamp in timestamps,
+                        })
+                        .await?;
+                    }
+                }
+            }
+
+            purged
+                .entry(guild_id)
+                .or_default()
+                .push((category, expired.count));
+        }
+    }
+
+    for (guild_id, counts) in purged {
+        let counts: Vec<String> = counts
+            .iter()
+            .map(|(category, count)| format!("{} {}", count, category))
+            .collect();
+
+        log::debug!(
+            "[RETENTION] Purged {} in guild {}",
+            counts.join(", "),
+            guild_id
+        );
+
+        crate::plugins::log::log(LogEvent {
+            level: LogLevel::Info,
+            guild_id,
+            target: Some("retention".to_owned()),
+            content: format!("Purged expired data: {}.", counts.join(", ")),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        expired, format_window, parse_window, Category, Expired, RetentionPolicy, Windows,
+    };
+
+    use robbot::model::id::GuildId;
+    use robbot_core::time::DAY;
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!(parse_window("180d"), Some(180));
+        assert_eq!(parse_window("off"), Some(0));
+        assert_eq!(parse_window("0d"), None);
+        assert_eq!(parse_window("180"), None);
+        assert_eq!(parse_window("6m"), None);
+        assert_eq!(parse_window("-1d"), None);
+
+        assert_eq!(format_window(180), "180d");
+        assert_eq!(format_window(0), "off");
+    }
+
+    #[test]
+    fn test_expired() {
+        let windows = Windows::new(vec![
+            RetentionPolicy {
+                guild_id: GuildId(1),
+                category: String::from("warnings"),
+                days: 10,
+            },
+            RetentionPolicy {
+                guild_id: GuildId(2),
+                category: String::from("audit"),
+                days: 0,
+            },
+        ]);
+
+        let now = 1000 * DAY;
+        let rows = [
+            (GuildId(1), now - 20 * DAY),
+            (GuildId(1), now - 20 * DAY),
+            (GuildId(1), now - 11 * DAY),
+            (GuildId(1), now - 5 * DAY),
+            (GuildId(2), now - 500 * DAY),
+            (GuildId(3), now - 100 * DAY),
+        ];
+
+        // Guilds without a policy keep warnings forever.
+        let warnings = expired(rows, &windows, Category::Warnings, now);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[&GuildId(1)],
+            Expired {
+                count: 3,
+                timestamps: vec![now - 20 * DAY, now - 11 * DAY],
+            }
+        );
+
+        // Audit entries are kept for 90 days by default.
+        let audit = expired(rows, &windows, Category::Audit, now);
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[&GuildId(3)].count, 1);
+    }
+}
@@ -0,0 +1,132 @@
+use super::{may_mention, nodes, render, Placeholders, MAX_LENGTH};
+
+use robbot::arguments::ChannelMention;
+use robbot::prelude::ArgumentsExt;
+use robbot::{command, Error, Result};
+use robbot_core::context::GuildMessageContext;
+
+use std::result;
+
+/// The flag keeping mentions in the text.
+const ALLOW_MENTIONS: &str = "--allow-mentions";
+
+/// Parses the text of a message: the words of the text and the optional
+/// flag `--allow-mentions`. Returns the text and whether the flag was given.
+fn parse_text<I>(args: I) -> Option<(String, bool)>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut allow_mentions = false;
+    let mut words = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            ALLOW_MENTIONS => allow_mentions = true,
+            _ => words.push(arg),
+        }
+    }
+
+    let text = words.join(" ");
+    match text.trim().is_empty() {
+        true => None,
+        false => Some((text.trim().to_owned(), allow_mentions)),
+    }
+}
+
+/// Returns the text of the command, or responds with an error and returns
+/// `None` if the text is too long or mentions are not allowed.
+pub(super) async fn text(
+    ctx: &mut GuildMessageContext,
+) -> result::Result<Option<(String, bool)>, Error> {
+    let mut args = Vec::new();
+    while let Some(arg) = ctx.args.pop() {
+        args.push(arg);
+    }
+
+    let (text, allow_mentions) = parse_text(args).ok_or(Error::InvalidCommandUsage)?;
+
+    if text.chars().count() > MAX_LENGTH {
+        ctx.respond(format!(
+            ":x: Messages are limited to {} characters.",
+            MAX_LENGTH
+        ))
+        .await?;
+        return Ok(None);
+    }
+
+    if allow_mentions && !may_mention(ctx).await? {
+        ctx.respond(format!(
+            ":no_entry_sign: You need the `{}` permission to use `{}`.",
+            nodes::MENTIONS,
+            ALLOW_MENTIONS
+        ))
+        .await?;
+        return Ok(None);
+    }
+
+    Ok(Some((text, allow_mentions)))
+}
+
+#[command(
+    description = "Send a message as the bot. Mentions are defused unless `--allow-mentions` is given, which requires the `say.mentions` permission. The placeholders `{guild}`, `{channel}`, `{members}` and `{date}` are substituted.",
+    usage = "<#Channel> <Text> [--allow-mentions]",
+    example = "#announcements Welcome to {guild}!",
+    permissions = [nodes::SEND],
+)]
+async fn say(mut ctx: GuildMessageContext) -> Result {
+    let channel: ChannelMention = ctx.args.pop_parse()?;
+
+    let (text, allow_mentions) = match text(&mut ctx).await? {
+        Some(text) => text,
+        None => return Ok(()),
+    };
+
+    let placeholders = Placeholders::new(&ctx, ctx.event.guild_id, channel.id).await;
+    let content = render(&text, allow_mentions, &placeholders);
+
+    if let Err(err) = ctx.send_message(channel.id, content).await {
+        log::debug!(
+            "[SAY] Failed to send message in guild {} to channel {}: {:?}",
+            ctx.event.guild_id,
+            channel.id,
+            err
+        );
+
+        ctx.respond(format!(
+            ":x: Could not send the message to {}, check that the bot can send messages there.",
+            channel.id.mention()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    ctx.ack().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_text;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_text() {
+        assert_eq!(
+            parse_text(args(&["Hello", "world"])),
+            Some((String::from("Hello world"), false))
+        );
+        assert_eq!(
+            parse_text(args(&["--allow-mentions", "Hello", "@everyone"])),
+            Some((String::from("Hello @everyone"), true))
+        );
+        assert_eq!(
+            parse_text(args(&["Hello", "--allow-mentions"])),
+            Some((String::from("Hello"), true))
+        );
+        assert_eq!(parse_text(args(&["--allow-mentions"])), None);
+        assert_eq!(parse_text(args(&[])), None);
+    }
+}
@@ -0,0 +1,350 @@
+//! # Say plugin
+//! Lets the bot post announcements. `say` sends a message to a channel right
+//! away, `schedule say` stores it as a [`ScheduledMessage`] which is sent by
+//! a minutely task once it is due, also after a restart. A guild can have up
+//! to [`MAX_PENDING`] pending messages.
+//!
+//! Mentions in the text are defused unless `--allow-mentions` is given by a
+//! member with the `say.mentions` permission. The text supports the
+//! placeholders `{guild}`, `{channel}`, `{members}` and `{date}`, which are
+//! substituted when the message is sent.
+//!
+//! Scheduled messages whose channel was deleted are dropped. A message that
+//! fails for another reason, e.g. because the bot lost the permission to send
+//! messages, is retried once on the next run before it is dropped.
+mod commands;
+mod schedule;
+
+use robbot::context::Error as ContextError;
+use robbot::model::id::{ChannelId, GuildId, UserId};
+use robbot::store::{delete, insert};
+use robbot::{module, task, Error, Result, StoreData};
+use robbot_core::context::{Context, GuildMessageContext};
+
+use chrono::{DateTime, Utc};
+
+use std::result;
+
+/// The maximum number of pending scheduled messages of a guild.
+pub const MAX_PENDING: usize = 25;
+
+/// The maximum number of characters of a message before placeholders are
+/// substituted.
+pub const MAX_LENGTH: usize = 1800;
+
+/// The number of times a scheduled message is retried after it failed.
+const MAX_RETRIES: u8 = 1;
+
+/// The zero width space inserted into mentions to defuse them.
+const ZWSP: char = '\u{200b}';
+
+pub mod nodes {
+    robbot::permission_nodes! {
+        "say" => {
+            SEND = "send",
+            MENTIONS = "mentions",
+            SCHEDULE = "schedule",
+        }
+    }
+}
+
+module! {
+    name: "say",
+    cmds: {
+        commands::say,
+        schedule::schedule,
+    },
+    store: [
+        ScheduledMessage,
+    ],
+    tasks: [
+        deliver_due,
+    ],
+    permissions: nodes::NODES,
+}
+
+/// A message sent by the bot once it is due.
+#[derive(Clone, Debug, StoreData)]
+pub struct ScheduledMessage {
+    pub guild_id: GuildId,
+    /// The id of the message, incremented per guild.
+    pub id: u64,
+    pub channel_id: ChannelId,
+    /// The member who scheduled the message.
+    pub user_id: UserId,
+    /// The text of the message with its placeholders.
+    pub content: String,
+    /// Whether mentions in the text are kept.
+    pub allow_mentions: bool,
+    /// The time the message is sent as a unix timestamp in seconds.
+    pub send_at: i64,
+    /// The number of failed attempts to send the message.
+    pub attempts: u8,
+}
+
+/// Defuses `@everyone`, `@here` and user and role mentions by inserting a
+/// zero width space after every `@`.
+fn sanitize(text: &str) -> String {
+    text.replace('@', &format!("@{}", ZWSP))
+}
+
+/// The values of the placeholders of a message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Placeholders {
+    guild: String,
+    channel_id: ChannelId,
+    members: u64,
+    now: DateTime<Utc>,
+}
+
+impl Placeholders {
+    /// Returns the placeholders for a message sent to `channel_id` now.
+    async fn new<T>(ctx: &Context<T>, guild_id: GuildId, channel_id: ChannelId) -> Self
+    where
+        T: Send + Sync,
+    {
+        let (guild, members) = ctx
+            .raw_ctx
+            .cache
+            .guild_field(guild_id.0, |guild| (guild.name.clone(), guild.member_count))
+            .await
+            .unwrap_or_default();
+
+        Self {
+            guild,
+            channel_id,
+            members,
+            now: Utc::now(),
+        }
+    }
+
+    /// Substitutes the placeholders in `text`. Unknown placeholders are kept
+    /// as they are.
+    fn substitute(&self, text: &str) -> String {
+        text.replace("{guild}", &self.guild)
+            .replace("{channel}", &self.channel_id.mention().to_string())
+            .replace("{members}", &self.members.to_string())
+            .replace("{date}", &format!("<t:{}:D>", self.now.timestamp()))
+    }
+}
+
+/// Returns the text of a message as it is sent.
+fn render(text: &str, allow_mentions: bool, placeholders: &Placeholders) -> String {
+    let text = placeholders.substitute(text);
+
+    match allow_mentions {
+        true => text,
+        false => sanitize(&text),
+    }
+}
+
+/// Returns whether the author of the command may send mentions. Like the
+/// permissions of commands, the permission is only checked if the bot was
+/// compiled with the `permissions` feature.
+#[cfg(feature = "permissions")]
+async fn may_mention(ctx: &GuildMessageContext) -> result::Result<bool, Error> {
+    if ctx.state.config.admins.contains(&ctx.event.author.id) {
+        return Ok(true);
+    }
+
+    let allowed = ctx
+        .state
+        .permissions()
+        .has_permission(
+            ctx.event.author.id,
+            ctx.event.guild_id,
+            &ctx.event.member.roles,
+            nodes::MENTIONS,
+        )
+        .await?;
+
+    Ok(allowed)
+}
+
+#[cfg(not(feature = "permissions"))]
+async fn may_mention(_ctx: &GuildMessageContext) -> result::Result<bool, Error> {
+    Ok(true)
+}
+
+/// Returns `true` if `err` is the error returned by Discord when the channel
+/// does not exist anymore.
+fn is_unknown_channel(err: &ContextError) -> bool {
+    /// JSON error code for "Unknown Channel".
+    const UNKNOWN_CHANNEL: isize = 10003;
+
+    match err {
+        ContextError::Raw(serenity::Error::Http(err)) => matches!(
+            &**err,
+            serenity::http::HttpError::UnsuccessfulRequest(resp)
+                if resp.error.code == UNKNOWN_CHANNEL
+        ),
+        _ => false,
+    }
+}
+
+/// What happens with a scheduled message that failed to send.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Failure {
+    /// The message is tried again on the next run.
+    Retry,
+    /// The message is dropped.
+    Drop,
+}
+
+/// Returns what happens with `message` after it failed to send. `gone` is
+/// `true` if its channel does not exist anymore.
+fn on_failure(message: &ScheduledMessage, gone: bool) -> Failure {
+    match gone || message.attempts >= MAX_RETRIES {
+        true => Failure::Drop,
+        false => Failure::Retry,
+    }
+}
+
+/// Returns the pending messages of `messages` that are due at `now`.
+fn due(messages: Vec<ScheduledMessage>, now: i64) -> Vec<ScheduledMessage> {
+    messages
+        .into_iter()
+        .filter(|message| message.send_at <= now)
+        .collect()
+}
+
+/// Sends a scheduled message and removes it from the store, unless it is
+/// retried.
+async fn deliver(ctx: &Context<()>, message: &ScheduledMessage) -> Result {
+    // The message must not be sent again if it cannot be removed.
+    ctx.state.store().check_writable()?;
+
+    let placeholders = Placeholders::new(ctx, message.guild_id, message.channel_id).await;
+    let content = render(&message.content, message.allow_mentions, &placeholders);
+
+    let failure = match ctx.send_message(message.channel_id, content).await {
+        Ok(_) => None,
+        Err(err) => {
+            let gone = is_unknown_channel(&err);
+            let failure = on_failure(message, gone);
+
+            log::warn!(
+                "[SAY] Failed to send scheduled message {} in guild {} to channel {} ({:?}): {:?}",
+                message.id,
+                message.guild_id,
+                message.channel_id,
+                failure,
+                err
+            );
+
+            Some(failure)
+        }
+    };
+
+    delete!(ctx.state.store(), ScheduledMessage => {
+        guild_id == message.guild_id,
+        id == message.id,
+    })
+    .await?;
+
+    if failure == Some(Failure::Retry) {
+        insert!(
+            ctx.state.store(),
+            ScheduledMessage {
+                attempts: message.attempts + 1,
+                ..message.clone()
+            }
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Sends the scheduled messages that are due, including those that became
+/// due while the bot was offline.
+#[task(interval = "1m", on_load = true, mutates_data = true)]
+async fn deliver_due(ctx: Context<()>) -> Result {
+    let messages = ctx
+        .state
+        .store()
+        .get_all(ctx.state.store().make_descriptor::<ScheduledMessage>())
+        .await?;
+
+    for message in due(messages, Utc::now().timestamp()) {
+        deliver(&ctx, &message).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{due, on_failure, render, sanitize, Failure, Placeholders, ScheduledMessage};
+
+    use chrono::{TimeZone, Utc};
+    use robbot::model::id::{ChannelId, GuildId, UserId};
+
+    fn message(id: u64, send_at: i64, attempts: u8) -> ScheduledMessage {
+        ScheduledMessage {
+            guild_id: GuildId(1),
+            id,
+            channel_id: ChannelId(2),
+            user_id: UserId(3),
+            content: String::from("Hello"),
+            allow_mentions: false,
+            send_at,
+            attempts,
+        }
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("Hello"), "Hello");
+        assert_eq!(sanitize("@everyone"), "@\u{200b}everyone");
+        assert_eq!(sanitize("@here now"), "@\u{200b}here now");
+        assert_eq!(sanitize("Hi <@123>"), "Hi <@\u{200b}123>");
+        assert_eq!(sanitize("<@&456>"), "<@\u{200b}&456>");
+        assert_eq!(sanitize("See <#789>"), "See <#789>");
+    }
+
+    #[test]
+    fn test_render() {
+        let placeholders = Placeholders {
+            guild: String::from("Robbot"),
+            channel_id: ChannelId(2),
+            members: 42,
+            now: Utc.timestamp_opt(1_600_000_000, 0).unwrap(),
+        };
+
+        assert_eq!(
+            render(
+                "Welcome to {guild}, now {members} members! See {channel} {unknown}",
+                true,
+                &placeholders
+            ),
+            "Welcome to Robbot, now 42 members! See <#2> {unknown}"
+        );
+        assert_eq!(
+            render("{date} @everyone", true, &placeholders),
+            "<t:1600000000:D> @everyone"
+        );
+        assert_eq!(
+            render("{channel} @everyone", false, &placeholders),
+            "<#2> @\u{200b}everyone"
+        );
+    }
+
+    #[test]
+    fn test_due() {
+        let messages = vec![message(1, 100, 0), message(2, 200, 0), message(3, 50, 1)];
+
+        let ids: Vec<_> = due(messages, 100)
+            .into_iter()
+            .map(|message| message.id)
+            .collect();
+        assert_eq!(ids, [1, 3]);
+    }
+
+    #[test]
+    fn test_on_failure() {
+        assert_eq!(on_failure(&message(1, 0, 0), false), Failure::Retry);
+        assert_eq!(on_failure(&message(1, 0, 1), false), Failure::Drop);
+        assert_eq!(on_failure(&message(1, 0, 0), true), Failure::Drop);
+    }
+}
@@ -0,0 +1,171 @@
+use super::commands::text;
+use super::{nodes, sanitize, ScheduledMessage, MAX_PENDING};
+
+use chrono::Utc;
+use robbot::arguments::ChannelMention;
+use robbot::prelude::ArgumentsExt;
+use robbot::store::{delete, get, insert};
+use robbot::task::parse_duration;
+use robbot::{command, Error, Result};
+use robbot_core::command::Command;
+use robbot_core::context::GuildMessageContext;
+
+use std::fmt::Write;
+
+/// Returns the `schedule` command with its sub commands.
+pub fn schedule() -> Command {
+    let mut command = Command::new("schedule");
+    command.sub_commands.insert(say());
+    command.sub_commands.insert(list());
+    command.sub_commands.insert(cancel());
+    command
+}
+
+#[command(
+    description = "Schedule a message sent by the bot after the given duration, see `say` for mentions and placeholders. A server can have up to 25 pending messages.",
+    usage = "<#Channel> <Duration> <Text> [--allow-mentions]",
+    example = "#announcements 2h The event starts in an hour!",
+    permissions = [nodes::SCHEDULE],
+    mutates_data,
+)]
+async fn say(mut ctx: GuildMessageContext) -> Result {
+    let channel: ChannelMention = ctx.args.pop_parse()?;
+    let duration = ctx
+        .args
+        .pop()
+        .and_then(|arg| parse_duration(&arg))
+        .ok_or(Error::InvalidCommandUsage)?;
+    let send_at = Utc::now()
+        .checked_add_signed(duration)
+        .ok_or(Error::InvalidCommandUsage)?;
+
+    let (content, allow_mentions) = match text(&mut ctx).await? {
+        Some(text) => text,
+        None => return Ok(()),
+    };
+
+    ctx.state.store().check_writable()?;
+
+    let guild_id = ctx.event.guild_id;
+    let messages = get!(ctx.state.store(), ScheduledMessage => {
+        guild_id == guild_id,
+    })
+    .await?;
+
+    if messages.len() >= MAX_PENDING {
+        ctx.respond(format!(
+            ":x: This server already has {} scheduled messages, cancel one first.",
+            MAX_PENDING
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let id = messages.iter().map(|message| message.id).max().unwrap_or(0) + 1;
+
+    insert!(
+        ctx.state.store(),
+        ScheduledMessage {
+            guild_id,
+            id,
+            channel_id: channel.id,
+            user_id: ctx.event.author.id,
+            content,
+            allow_mentions,
+            send_at: send_at.timestamp(),
+            attempts: 0,
+        }
+    )
+    .await?;
+
+    ctx.respond(format!(
+        ":white_check_mark: Scheduled message #{} for {} <t:{}:R>.",
+        id,
+        channel.id.mention(),
+        send_at.timestamp()
+    ))
+    .await?;
+    Ok(())
+}
+
+#[command(
+    description = "List the pending scheduled messages of the server.",
+    permissions = [nodes::SCHEDULE],
+)]
+async fn list(ctx: GuildMessageContext) -> Result {
+    let guild_id = ctx.event.guild_id;
+    let mut messages = get!(ctx.state.store(), ScheduledMessage => {
+        guild_id == guild_id,
+    })
+    .await?;
+
+    if messages.is_empty() {
+        ctx.respond(":information_source: No messages are scheduled.")
+            .await?;
+        return Ok(());
+    }
+
+    messages.sort_by_key(|message| message.send_at);
+
+    let mut content = String::from("**Scheduled messages**\n");
+    for message in messages {
+        let preview: String = message.content.chars().take(50).collect();
+        let ellipsis = match message.content.chars().count() > 50 {
+            true => "…",
+            false => "",
+        };
+
+        let _ = writeln!(
+            content,
+            "`#{}` <t:{}:R> in {} by {}: {}{}",
+            message.id,
+            message.send_at,
+            message.channel_id.mention(),
+            message.user_id.mention(),
+            sanitize(&preview),
+            ellipsis
+        );
+    }
+
+    ctx.respond(content).await?;
+    Ok(())
+}
+
+#[command(
+    description = "Cancel a pending scheduled message.",
+    usage = "<ID>",
+    example = "3",
+    permissions = [nodes::SCHEDULE],
+    mutates_data,
+)]
+async fn cancel(mut ctx: GuildMessageContext) -> Result {
+    let id: u64 = ctx.args.pop_parse()?;
+
+    ctx.state.store().check_writable()?;
+
+    let guild_id = ctx.event.guild_id;
+    let messages = get!(ctx.state.store(), ScheduledMessage => {
+        guild_id == guild_id,
+        id == id,
+    })
+    .await?;
+
+    if messages.is_empty() {
+        ctx.respond(format!(":x: No scheduled message with the id `{}`.", id))
+            .await?;
+        return Ok(());
+    }
+
+    delete!(ctx.state.store(), ScheduledMessage => {
+        guild_id == guild_id,
+        id == id,
+    })
+    .await?;
+
+    ctx.respond(format!(
+        ":white_check_mark: Cancelled scheduled message #{}.",
+        id
+    ))
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,131 @@
+//! Benchmarks a reaction hook handling a burst of 1000 reactions, one event
+//! at a time and in batches (see `Batching`). Like the poll hooks, the hook
+//! looks up the polls of the reacted messages, 10 of the messages are polls.
+//!
+//! Before measuring, the bench asserts that the batched hook makes one store
+//! query per batch instead of one per reaction.
+use robbot::model::id::{GuildId, MessageId};
+use robbot::store::{create, get, get_one, insert};
+use robbot::StoreData;
+use robbot_core::hook::Batching;
+use robbot_core::store::mem::MemStore;
+use robbot_core::store::StoreHandler;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use tokio::runtime::Runtime;
+use tokio::sync::broadcast;
+
+const REACTIONS: u64 = 1000;
+const POLLS: u64 = 10;
+
+#[derive(Clone, Debug, StoreData)]
+struct Poll {
+    guild_id: GuildId,
+    message_id: MessageId,
+}
+
+type Store = StoreHandler<MemStore>;
+
+/// Returns a store with a poll on every 100th message.
+async fn setup() -> Store {
+    let store = StoreHandler::new_in_memory(MemStore::default());
+    create!(store, Poll).await.unwrap();
+
+    for poll in 0..POLLS {
+        insert!(
+            store,
+            Poll {
+                guild_id: GuildId(1),
+                message_id: MessageId(poll * (REACTIONS / POLLS)),
+            }
+        )
+        .await
+        .unwrap();
+    }
+
+    store
+}
+
+/// Returns a receiver of a burst of reactions, by message id.
+fn burst() -> broadcast::Receiver<MessageId> {
+    let (tx, rx) = broadcast::channel(REACTIONS as usize);
+    for message_id in 0..REACTIONS {
+        tx.send(MessageId(message_id)).unwrap();
+    }
+
+    rx
+}
+
+/// Handles every reaction on its own. Returns the number of store queries
+/// and the number of reactions on polls.
+async fn per_event(store: &Store, mut rx: broadcast::Receiver<MessageId>) -> (usize, usize) {
+    let (mut queries, mut votes) = (0, 0);
+
+    while let Ok(message_id) = rx.recv().await {
+        let poll = get_one!(store, Poll => {
+            message_id == message_id,
+        })
+        .await
+        .unwrap();
+
+        queries += 1;
+        votes += poll.is_some() as usize;
+    }
+
+    (queries, votes)
+}
+
+/// Handles the reactions in batches with one query per batch. Returns the
+/// number of store queries and the number of reactions on polls.
+async fn batched(store: &Store, mut rx: broadcast::Receiver<MessageId>) -> (usize, usize) {
+    let batching = Batching::default();
+    let (mut queries, mut votes) = (0, 0);
+
+    while let Some(message_ids) = batching.recv(&mut rx, "bench").await {
+        let polls = get!(store, Poll => {
+            message_id in message_ids.clone(),
+        })
+        .await
+        .unwrap();
+
+        queries += 1;
+        votes += message_ids
+            .iter()
+            .filter(|message_id| polls.iter().any(|poll| poll.message_id == **message_id))
+            .count();
+    }
+
+    (queries, votes)
+}
+
+fn assert_queries(runtime: &Runtime, store: &Store) {
+    let batching = Batching::default();
+
+    let (queries, votes) = runtime.block_on(per_event(store, burst()));
+    assert_eq!((queries, votes), (REACTIONS as usize, POLLS as usize));
+
+    let (queries, votes) = runtime.block_on(batched(store, burst()));
+    let batches = (REACTIONS as usize).div_ceil(batching.max_size);
+    assert_eq!((queries, votes), (batches, POLLS as usize));
+}
+
+fn bench_burst(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let store = runtime.block_on(setup());
+    assert_queries(&runtime, &store);
+
+    let mut group = c.benchmark_group("reaction_burst");
+
+    group.bench_function("per_event", |b| {
+        b.iter(|| runtime.block_on(per_event(&store, burst())))
+    });
+
+    group.bench_function("batched", |b| {
+        b.iter(|| runtime.block_on(batched(&store, burst())))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_burst);
+criterion_main!(benches);
@@ -0,0 +1,183 @@
+//! Benchmarks the routing decision made for every message through
+//! `router::is_ignored` and `router::route`, the calls made by
+//! `Handler::message`. The ignored channels are read from the
+//! `HotPathCache`, which holds 1000 guilds.
+//!
+//! Before measuring, the bench asserts that a message that is not a command
+//! performs no allocation once its guild is cached.
+use robbot::model::channel::{Message, MessageKind};
+use robbot::model::id::{ChannelId, GuildId, MessageId, UserId};
+use robbot::model::user::User;
+use robbot::store::{create, insert};
+use robbot_core::config::{Config, Ignore};
+use robbot_core::hotpath::{HotPathCache, DEFAULT_CAPACITY, DEFAULT_TTL};
+use robbot_core::ignore::{IgnoreHandler, IgnoredChannel};
+use robbot_core::router;
+use robbot_core::store::mem::MemStore;
+use robbot_core::store::StoreHandler;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use futures::executor::block_on;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const GUILDS: u64 = 1000;
+const BOT_ID: UserId = UserId(1);
+
+/// Counts all allocations.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+fn message(guild_id: u64, content: &str) -> Message {
+    Message {
+        id: MessageId(1),
+        attachments: Vec::new(),
+        author: User {
+            id: UserId(2),
+            avatar: None,
+            bot: false,
+            discriminator: 0,
+            name: String::new(),
+            banner: None,
+            accent_color: None,
+        },
+        channel_id: ChannelId(guild_id * 10 + 1),
+        content: content.to_owned(),
+        edited_timestamp: None,
+        embeds: Vec::new(),
+        guild_id: Some(GuildId(guild_id)),
+        kind: MessageKind::Regular,
+        member: None,
+        mention_everyone: false,
+        mention_roles: Vec::new(),
+        mention_channels: Vec::new(),
+        mentions: Vec::new(),
+        pinned: false,
+        reactions: Vec::new(),
+        timestamp: chrono::Utc::now(),
+        tts: false,
+        webhook_id: None,
+        message_reference: None,
+        referenced_message: None,
+    }
+}
+
+/// The routing decision of `Handler::message` after the hooks ran. Returns
+/// the command string if `message` is a command.
+async fn ingest<'a>(
+    ignores: &IgnoreHandler,
+    config: &Config,
+    message: &'a Message,
+) -> Option<&'a str> {
+    if router::is_ignored(ignores, message).await {
+        return None;
+    }
+
+    router::route(config, message, Some(BOT_ID))
+}
+
+fn config() -> Config {
+    Config {
+        prefix: String::from("!"),
+        mention_prefix: true,
+        ..Default::default()
+    }
+}
+
+/// Returns an `IgnoreHandler` with all guilds cached. Every guild has an
+/// ignored channel.
+fn setup() -> (IgnoreHandler, HotPathCache) {
+    let store = StoreHandler::new_in_memory(MemStore::default());
+    let cache = HotPathCache::new(store.clone(), DEFAULT_CAPACITY, DEFAULT_TTL);
+    let ignores = IgnoreHandler::new(store.clone(), Ignore::default(), cache.clone());
+
+    block_on(async {
+        create!(store, IgnoredChannel).await.unwrap();
+
+        for guild_id in 1..=GUILDS {
+            insert!(
+                store,
+                IgnoredChannel {
+                    guild_id: GuildId(guild_id),
+                    channel_id: ChannelId(guild_id * 10),
+                }
+            )
+            .await
+            .unwrap();
+
+            cache.guild(GuildId(guild_id)).await.unwrap();
+        }
+    });
+
+    (ignores, cache)
+}
+
+fn assert_no_allocation(ignores: &IgnoreHandler, config: &Config) {
+    let messages: Vec<_> = (1..=GUILDS)
+        .map(|guild_id| message(guild_id, "just chatting, <@1 not a command"))
+        .collect();
+
+    // Initializes the thread local state of the executor.
+    block_on(ingest(ignores, config, &messages[0]));
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for message in &messages {
+        assert_eq!(block_on(ingest(ignores, config, message)), None);
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    assert_eq!(allocations, 0, "the no-command fast path allocated");
+}
+
+fn bench_ingest(c: &mut Criterion) {
+    let (ignores, cache) = setup();
+    let config = config();
+    assert_no_allocation(&ignores, &config);
+
+    let not_command = message(GUILDS / 2, "just chatting");
+    let command = message(GUILDS / 2, "<@1> help");
+
+    let mut group = c.benchmark_group("ingest");
+
+    group.bench_function("not_command", |b| {
+        b.iter(|| block_on(ingest(&ignores, &config, black_box(&not_command))))
+    });
+
+    group.bench_function("command", |b| {
+        b.iter(|| block_on(ingest(&ignores, &config, black_box(&command))))
+    });
+
+    // The guild is loaded from the store for every message.
+    group.bench_function("not_command_uncached", |b| {
+        b.iter(|| {
+            cache.invalidate(GuildId(GUILDS / 2));
+            block_on(ingest(&ignores, &config, black_box(&not_command)))
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_ingest);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+//! The events published on the [`EventBus`] by the plugins of the bot.
+//!
+//! [`EventBus`]: super::EventBus
+use robbot::model::id::{GuildId, RoleId, UserId};
+
+use chrono::{DateTime, Utc};
+
+/// A member completed verification, e.g. by linking an external account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerificationCompleted {
+    pub guild_id: GuildId,
+    pub user_id: UserId,
+    /// The name of the verified account, if the verification used one.
+    pub account_name: Option<String>,
+}
+
+/// A role was given to a member until `expires_at`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TempRoleAssigned {
+    pub guild_id: GuildId,
+    pub user_id: UserId,
+    pub role_id: RoleId,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A temporary role expired and was removed from the member.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TempRoleExpired {
+    pub guild_id: GuildId,
+    pub user_id: UserId,
+    pub role_id: RoleId,
+}
+
+/// A member was warned.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WarningIssued {
+    pub guild_id: GuildId,
+    pub user_id: UserId,
+    /// A short description of the reason, e.g. the matched automod rule.
+    pub reason: String,
+}
+
+/// A member was muted by a moderator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemberMuted {
+    pub guild_id: GuildId,
+    pub user_id: UserId,
+    pub moderator_id: UserId,
+    /// The end of the mute, `None` for indefinite mutes.
+    pub until: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,398 @@
+//! Typed notifications between plugins.
+//!
+//! Plugins publish events, e.g. [`VerificationCompleted`], on the
+//! [`EventBus`] of the [`State`] instead of calling into other plugins.
+//! Other plugins subscribe to the event types they need when they are
+//! loaded. Any `Clone + Send + Sync + 'static` type can be used as an event,
+//! the events of the bundled plugins are in [`events`].
+//!
+//! # Delivery
+//! Every subscriber has its own queue of [`QUEUE_SIZE`] events and a task
+//! handling them one after another. [`EventBus::publish`] never waits:
+//! - A subscriber receives events of one type in the order they were
+//!   published. There is no ordering between subscribers or between
+//!   different event types.
+//! - If the queue of a subscriber is full the event is dropped for that
+//!   subscriber and a warning is logged. Other subscribers still receive it.
+//! - Subscribers are isolated like hooks: errors, panics and timeouts (see
+//!   [`Timeouts::hook`]) are logged with the name of the subscriber. A
+//!   subscriber keeps receiving events after a panic.
+//! - Events published before the bot is ready are dropped by subscribers
+//!   registered with [`EventBus::subscribe`], they have no context to run
+//!   with.
+//!
+//! [`State`]: crate::state::State
+//! [`Timeouts::hook`]: crate::executor::Timeouts::hook
+pub mod events;
+
+pub use events::{
+    MemberMuted, TempRoleAssigned, TempRoleExpired, VerificationCompleted, WarningIssued,
+};
+
+use crate::context::Context;
+use crate::disable::DisableReason;
+use crate::executor::{guard, Outcome};
+
+use parking_lot::RwLock;
+use tokio::sync::mpsc;
+
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The number of events queued for a subscriber before further events are
+/// dropped for it.
+pub const QUEUE_SIZE: usize = 64;
+
+/// Queues an event for a subscriber. Returns `false` if the queue is full.
+type Deliver = Arc<dyn Fn(&(dyn Any + Send + Sync)) -> bool + Send + Sync>;
+
+#[derive(Clone)]
+struct Subscriber {
+    name: String,
+    deliver: Deliver,
+}
+
+/// Routes events to the subscribers of their type. See the [module
+/// documentation](self) for the delivery guarantees.
+pub struct EventBus {
+    subscribers: RwLock<HashMap<TypeId, (&'static str, Vec<Subscriber>)>>,
+    context: Arc<std::sync::RwLock<Option<Context<()>>>>,
+    timeout: Option<Duration>,
+}
+
+impl EventBus {
+    /// Creates a new `EventBus` without any subscribers. Subscribers run with
+    /// `context` and are given up after `timeout`.
+    pub fn new(
+        context: Arc<std::sync::RwLock<Option<Context<()>>>>,
+        timeout: Option<Duration>,
+    ) -> Self {
+        Self {
+            subscribers: RwLock::default(),
+            context,
+            timeout,
+        }
+    }
+
+    /// Registers `handler` to be called with every published event of type
+    /// `E`. `name` identifies the subscriber in logs, e.g. `gate`.
+    ///
+    /// Must be called within a tokio runtime.
+    pub fn subscribe<E, F, Fut>(&self, name: &str, handler: F)
+    where
+        E: Clone + Send + Sync + 'static,
+        F: Fn(Context<()>, E) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = robbot::Result> + Send + 'static,
+    {
+        let context = self.context.clone();
+        let subscriber = name.to_owned();
+
+        self.subscribe_with(name, move |event: E| {
+            let future = context
+                .read()
+                .unwrap()
+                .clone()
+                .map(|ctx| handler(ctx, event));
+
+            let subscriber = subscriber.clone();
+            async move {
+                match future {
+                    Some(future) => future.await,
+                    None => {
+                        log::debug!(
+                            "[BUS] Subscriber '{}' dropped {} before ready",
+                            subscriber,
+                            event_name::<E>()
+                        );
+                        Ok(())
+                    }
+                }
+            }
+        });
+    }
+
+    /// Registers `handler` without a context, see [`subscribe`].
+    ///
+    /// [`subscribe`]: Self::subscribe
+    fn subscribe_with<E, F, Fut>(&self, name: &str, handler: F)
+    where
+        E: Clone + Send + Sync + 'static,
+        F: Fn(E) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = robbot::Result> + Send + 'static,
+    {
+        let (tx, mut rx) = mpsc::channel::<E>(QUEUE_SIZE);
+
+        let worker = name.to_owned();
+        let timeout = self.timeout;
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                // Call the handler within the guard to catch panics before
+                // the first poll as well.
+                run(
+                    &worker,
+                    event_name::<E>(),
+                    async { handler(event).await },
+                    timeout,
+                )
+                .await;
+            }
+        });
+
+        let deliver: Deliver = Arc::new(move |event| {
+            // Subscribers are keyed by the type id, the downcast cannot fail.
+            let event = event.downcast_ref::<E>().unwrap();
+            tx.try_send(event.clone()).is_ok()
+        });
+
+        self.subscribers
+            .write()
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| (event_name::<E>(), Vec::new()))
+            .1
+            .push(Subscriber {
+                name: name.to_owned(),
+                deliver,
+            });
+    }
+
+    /// Queues `event` for all subscribers of its type without waiting for
+    /// them. Returns the number of subscribers the event was queued for.
+    pub fn publish<E>(&self, event: E) -> usize
+    where
+        E: Clone + Send + Sync + 'static,
+    {
+        // Don't hold the lock while delivering.
+        let subscribers = match self.subscribers.read().get(&TypeId::of::<E>()) {
+            Some((_, subscribers)) => subscribers.clone(),
+            None => return 0,
+        };
+
+        let mut delivered = 0;
+        for subscriber in subscribers {
+            if (subscriber.deliver)(&event) {
+                delivered += 1;
+            } else {
+                log::warn!(
+                    "[BUS] Queue of subscriber '{}' is full, dropped {}",
+                    subscriber.name,
+                    event_name::<E>()
+                );
+            }
+        }
+
+        delivered
+    }
+}
+
+impl Debug for EventBus {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let subscribers: HashMap<_, _> = self
+            .subscribers
+            .read()
+            .values()
+            .map(|(event, subscribers)| {
+                let names: Vec<_> = subscribers.iter().map(|s| s.name.clone()).collect();
+                (*event, names)
+            })
+            .collect();
+
+        f.debug_struct("EventBus")
+            .field("subscribers", &subscribers)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+/// Returns the name of the event type `E` without its path.
+fn event_name<E>() -> &'static str {
+    let name = type_name::<E>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// Runs the handling of an event by a subscriber, logging any failure.
+async fn run<F>(name: &str, event: &str, future: F, timeout: Option<Duration>)
+where
+    F: Future<Output = robbot::Result>,
+{
+    match guard(future, timeout).await {
+        Outcome::Completed(Ok(())) => (),
+        Outcome::Completed(Err(err)) => {
+            log::error!(
+                "[BUS] Subscriber '{}' failed to handle {}: {:?}",
+                name,
+                event,
+                err
+            );
+        }
+        Outcome::Panicked(payload) => {
+            let reason = DisableReason::panic(payload.as_ref());
+            log::error!(
+                "[BUS] Subscriber '{}' {} while handling {}",
+                name,
+                reason.detail,
+                event
+            );
+        }
+        Outcome::TimedOut(timeout) => {
+            log::error!(
+                "[BUS] Subscriber '{}' timed out after {:?} handling {}",
+                name,
+                timeout,
+                event
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{event_name, EventBus, VerificationCompleted, WarningIssued, QUEUE_SIZE};
+
+    use robbot::model::id::{GuildId, UserId};
+    use tokio::sync::{mpsc, Notify};
+
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn bus() -> EventBus {
+        EventBus::new(Arc::default(), Some(Duration::from_secs(30)))
+    }
+
+    fn warning(user_id: u64) -> WarningIssued {
+        WarningIssued {
+            guild_id: GuildId(1),
+            user_id: UserId(user_id),
+            reason: String::from("test"),
+        }
+    }
+
+    #[test]
+    fn test_event_name() {
+        assert_eq!(event_name::<WarningIssued>(), "WarningIssued");
+        assert_eq!(event_name::<u64>(), "u64");
+    }
+
+    #[tokio::test]
+    async fn test_bus_routing() {
+        let bus = bus();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        assert_eq!(bus.publish(warning(1)), 0);
+
+        let warnings = tx.clone();
+        bus.subscribe_with("a", move |event: WarningIssued| {
+            let _ = warnings.send(format!("a {}", event.user_id));
+            async { Ok(()) }
+        });
+        bus.subscribe_with("b", move |event: VerificationCompleted| {
+            let _ = tx.send(format!("b {}", event.user_id));
+            async { Ok(()) }
+        });
+
+        assert_eq!(bus.publish(warning(1)), 1);
+        assert_eq!(
+            bus.publish(VerificationCompleted {
+                guild_id: GuildId(1),
+                user_id: UserId(2),
+                account_name: None,
+            }),
+            1
+        );
+        assert_eq!(bus.publish(3u64), 0);
+
+        let mut received = vec![rx.recv().await.unwrap(), rx.recv().await.unwrap()];
+        received.sort();
+        assert_eq!(received, ["a 1", "b 2"]);
+    }
+
+    #[tokio::test]
+    async fn test_bus_ordering_and_isolation() {
+        let bus = bus();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        bus.subscribe_with("faulty", |event: WarningIssued| async move {
+            match event.user_id.0 {
+                1 => panic!("test"),
+                2 => Err(robbot::Error::InvalidCommandUsage),
+                _ => Ok(()),
+            }
+        });
+        bus.subscribe_with("ordered", move |event: WarningIssued| {
+            let tx = tx.clone();
+            async move {
+                // Fails for the same events as the faulty subscriber.
+                let _ = tx.send(event.user_id.0);
+                match event.user_id.0 {
+                    1 => panic!("test"),
+                    _ => Ok(()),
+                }
+            }
+        });
+
+        for user_id in 1..=5 {
+            assert_eq!(bus.publish(warning(user_id)), 2);
+        }
+
+        for user_id in 1..=5 {
+            assert_eq!(rx.recv().await, Some(user_id));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bus_backpressure() {
+        let bus = bus();
+        let release = Arc::new(Notify::new());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let blocked = release.clone();
+        bus.subscribe_with("slow", move |event: WarningIssued| {
+            let blocked = blocked.clone();
+            let tx = tx.clone();
+            async move {
+                blocked.notified().await;
+                let _ = tx.send(event.user_id.0);
+                Ok(())
+            }
+        });
+
+        // The worker did not run yet, only the queue holds events.
+        for user_id in 0..QUEUE_SIZE as u64 {
+            assert_eq!(bus.publish(warning(user_id)), 1);
+        }
+        assert_eq!(bus.publish(warning(QUEUE_SIZE as u64)), 0);
+
+        for user_id in 0..QUEUE_SIZE as u64 {
+            release.notify_one();
+            assert_eq!(rx.recv().await, Some(user_id));
+        }
+
+        // The queue has space again.
+        assert_eq!(bus.publish(warning(1)), 1);
+    }
+
+    #[tokio::test]
+    async fn test_bus_subscribe_not_ready() {
+        let bus = bus();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let called = tx.clone();
+        bus.subscribe("ctx", move |_, _: WarningIssued| {
+            let _ = called.send("ctx");
+            async { Ok(()) }
+        });
+        bus.subscribe_with("after", move |_: WarningIssued| {
+            let _ = tx.send("after");
+            async { Ok(()) }
+        });
+
+        // Without a context the event is queued, but not handled.
+        assert_eq!(bus.publish(warning(1)), 2);
+        assert_eq!(rx.recv().await, Some("after"));
+        tokio::task::yield_now().await;
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,297 @@
+//! Bookkeeping for guild member chunk requests.
+//!
+//! Requesting the members of a guild over the gateway is much faster than
+//! paginating them over REST and populates the member cache. Discord answers
+//! a request with one or more `GUILD_MEMBERS_CHUNK` events carrying the nonce
+//! of the request. [`MemberChunks`] routes these events to the waiting
+//! [`ChunkRequest`] by nonce and resolves it once all chunks arrived.
+use robbot::model::id::GuildId;
+
+use parking_lot::Mutex;
+use thiserror::Error;
+use tokio::sync::oneshot;
+use tokio::time::{self, Duration};
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// The result of a completed chunk request.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChunkSummary {
+    /// The number of chunks received.
+    pub chunks: u32,
+    /// The number of members received.
+    pub members: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Error)]
+pub enum ChunkError {
+    /// Not all chunks arrived before the timeout. `received` holds the chunks
+    /// and members that did arrive, `expected` the number of chunks announced
+    /// by Discord, or `None` if no chunk arrived.
+    #[error("chunk request timed out after {} of {:?} chunks", received.chunks, expected)]
+    Timeout {
+        received: ChunkSummary,
+        expected: Option<u32>,
+    },
+}
+
+#[derive(Debug)]
+struct Pending {
+    guild_id: GuildId,
+    /// The indices of the received chunks. Discord may send chunks out of order.
+    received: HashSet<u32>,
+    members: usize,
+    /// The total number of chunks, known once the first chunk arrived.
+    count: Option<u32>,
+    tx: oneshot::Sender<ChunkSummary>,
+}
+
+impl Pending {
+    fn summary(&self) -> ChunkSummary {
+        ChunkSummary {
+            chunks: self.received.len() as u32,
+            members: self.members,
+        }
+    }
+}
+
+/// Tracks all pending guild member chunk requests.
+#[derive(Debug, Default)]
+pub struct MemberChunks {
+    pending: Arc<Mutex<HashMap<String, Pending>>>,
+    next_nonce: AtomicU64,
+}
+
+impl MemberChunks {
+    /// Creates a new `MemberChunks` without any pending requests.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a new request for the members of `guild_id`. The returned
+    /// [`ChunkRequest`] contains the nonce to send with the gateway request.
+    pub fn register(&self, guild_id: GuildId) -> ChunkRequest {
+        // Nonces are limited to 32 bytes.
+        let nonce = format!("robbot-{}", self.next_nonce.fetch_add(1, Ordering::Relaxed));
+
+        let (tx, rx) = oneshot::channel();
+
+        self.pending.lock().insert(
+            nonce.clone(),
+            Pending {
+                guild_id,
+                received: HashSet::new(),
+                members: 0,
+                count: None,
+                tx,
+            },
+        );
+
+        ChunkRequest {
+            nonce,
+            rx,
+            pending: self.pending.clone(),
+        }
+    }
+
+    /// Handles a received chunk. Returns `false` if the chunk does not belong
+    /// to a pending request, e.g. because the request already timed out.
+    pub fn handle_chunk(
+        &self,
+        guild_id: GuildId,
+        nonce: Option<&str>,
+        chunk_index: u32,
+        chunk_count: u32,
+        members: usize,
+    ) -> bool {
+        let nonce = match nonce {
+            Some(nonce) => nonce,
+            None => return false,
+        };
+
+        let mut pending = self.pending.lock();
+
+        let request = match pending.get_mut(nonce) {
+            Some(request) if request.guild_id == guild_id => request,
+            _ => return false,
+        };
+
+        if request.received.insert(chunk_index) {
+            request.members += members;
+        }
+        request.count = Some(chunk_count);
+
+        if request.received.len() as u32 >= chunk_count {
+            let request = pending.remove(nonce).unwrap();
+
+            log::debug!(
+                "[CHUNKS] Received {} members of guild {} in {} chunks",
+                request.members,
+                guild_id,
+                chunk_count
+            );
+
+            let summary = request.summary();
+            let _ = request.tx.send(summary);
+        }
+
+        true
+    }
+
+    /// Returns the number of pending requests.
+    pub fn len(&self) -> usize {
+        self.pending.lock().len()
+    }
+
+    /// Returns `true` if no requests are pending.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A pending request for the members of a guild. Dropping the request stops
+/// tracking it.
+#[derive(Debug)]
+pub struct ChunkRequest {
+    nonce: String,
+    rx: oneshot::Receiver<ChunkSummary>,
+    pending: Arc<Mutex<HashMap<String, Pending>>>,
+}
+
+impl ChunkRequest {
+    /// Returns the nonce identifying the request.
+    pub fn nonce(&self) -> &str {
+        &self.nonce
+    }
+
+    /// Waits until all chunks arrived or `timeout` elapsed. On a timeout the
+    /// chunks received so far are returned in the error, their members are
+    /// cached regardless.
+    pub async fn wait(mut self, timeout: Duration) -> Result<ChunkSummary, ChunkError> {
+        match time::timeout(timeout, &mut self.rx).await {
+            Ok(Ok(summary)) => Ok(summary),
+            Ok(Err(_)) | Err(_) => {
+                let pending = self.pending.lock().remove(&self.nonce);
+
+                match pending {
+                    Some(pending) => Err(ChunkError::Timeout {
+                        received: pending.summary(),
+                        expected: pending.count,
+                    }),
+                    // Completed between the timeout and taking the lock.
+                    None => self.rx.try_recv().map_err(|_| ChunkError::Timeout {
+                        received: ChunkSummary {
+                            chunks: 0,
+                            members: 0,
+                        },
+                        expected: None,
+                    }),
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ChunkRequest {
+    fn drop(&mut self) {
+        self.pending.lock().remove(&self.nonce);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChunkError, ChunkSummary, MemberChunks};
+
+    use robbot::model::id::GuildId;
+    use tokio::time::Duration;
+
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    #[tokio::test]
+    async fn test_member_chunks_complete() {
+        let chunks = MemberChunks::new();
+
+        let request = chunks.register(GuildId(1));
+        let nonce = request.nonce().to_owned();
+
+        // Chunks may arrive out of order, duplicates are ignored.
+        assert!(chunks.handle_chunk(GuildId(1), Some(&nonce), 2, 3, 10));
+        assert!(chunks.handle_chunk(GuildId(1), Some(&nonce), 0, 3, 1000));
+        assert!(chunks.handle_chunk(GuildId(1), Some(&nonce), 0, 3, 1000));
+        assert!(chunks.handle_chunk(GuildId(1), Some(&nonce), 1, 3, 1000));
+
+        assert_eq!(
+            request.wait(TIMEOUT).await,
+            Ok(ChunkSummary {
+                chunks: 3,
+                members: 2010,
+            })
+        );
+        assert!(chunks.is_empty());
+
+        // The request is completed, later chunks are not routed.
+        assert!(!chunks.handle_chunk(GuildId(1), Some(&nonce), 0, 3, 1000));
+    }
+
+    #[tokio::test]
+    async fn test_member_chunks_routing() {
+        let chunks = MemberChunks::new();
+
+        let a = chunks.register(GuildId(1));
+        let b = chunks.register(GuildId(2));
+        assert_ne!(a.nonce(), b.nonce());
+
+        // Unknown nonces, missing nonces and other guilds are not routed.
+        assert!(!chunks.handle_chunk(GuildId(1), Some("unknown"), 0, 1, 1));
+        assert!(!chunks.handle_chunk(GuildId(1), None, 0, 1, 1));
+        assert!(!chunks.handle_chunk(GuildId(1), Some(b.nonce()), 0, 1, 1));
+
+        assert!(chunks.handle_chunk(GuildId(2), Some(b.nonce()), 0, 1, 5));
+        assert_eq!(chunks.len(), 1);
+
+        assert_eq!(b.wait(TIMEOUT).await.unwrap().members, 5);
+
+        // Dropping a request stops tracking it.
+        drop(a);
+        assert!(chunks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_member_chunks_timeout() {
+        let chunks = MemberChunks::new();
+
+        let request = chunks.register(GuildId(1));
+        let nonce = request.nonce().to_owned();
+
+        assert!(chunks.handle_chunk(GuildId(1), Some(&nonce), 0, 3, 1000));
+
+        assert_eq!(
+            request.wait(TIMEOUT).await,
+            Err(ChunkError::Timeout {
+                received: ChunkSummary {
+                    chunks: 1,
+                    members: 1000,
+                },
+                expected: Some(3),
+            })
+        );
+
+        // Timed out requests are removed.
+        assert!(chunks.is_empty());
+        assert!(!chunks.handle_chunk(GuildId(1), Some(&nonce), 1, 3, 1000));
+
+        let request = chunks.register(GuildId(1));
+        assert_eq!(
+            request.wait(TIMEOUT).await,
+            Err(ChunkError::Timeout {
+                received: ChunkSummary {
+                    chunks: 0,
+                    members: 0,
+                },
+                expected: None,
+            })
+        );
+    }
+}
@@ -1,11 +1,14 @@
 #![allow(clippy::mutable_key_type)]
 
 use crate::context::{GuildMessageContext, MessageContext};
+use crate::cooldown::Limits;
+use crate::disable::DisabledSet;
 use crate::executor::Executor;
-use crate::router::{find_command, parse_args};
+use crate::router::{find_command, parse_args, resolve_command};
 
-use robbot::arguments::ArgumentsExt;
-use robbot::command::Command as CommandExt;
+use robbot::arguments::{Arguments, ArgumentsExt};
+use robbot::command::{shadowed_choice, ArgChoices, Command as CommandExt};
+use robbot::model::permissions::Permissions;
 use robbot::module::ModuleId;
 
 use parking_lot::RwLock;
@@ -28,6 +31,26 @@ pub struct Command {
     /// A list of permissions required to run the command.
     /// Setting this on a non-guild-only command has no effect.
     pub permissions: Vec<String>,
+    /// The Discord permissions the bot requires in the invoking channel.
+    /// Setting this on a non-guild-only command has no effect.
+    pub bot_permissions: Permissions,
+    /// Acknowledge a successful invocation with a reaction if the command
+    /// did not respond on its own.
+    pub ack_only: bool,
+    /// Record successful invocations in the audit trail of the guild.
+    pub audited: bool,
+    /// Whether the command writes to the store. Such commands are rejected
+    /// in maintenance mode, see [`crate::maintenance`].
+    pub mutates_data: bool,
+    /// Omit the arguments of the command from audit entries, e.g. for
+    /// commands taking tokens or other secrets.
+    pub private_args: bool,
+    /// The concurrency group the command runs in. See [`crate::concurrency`].
+    pub concurrency_group: Option<String>,
+    /// The cooldown and daily quota of the command. See [`crate::cooldown`].
+    pub limits: Limits,
+    /// The values accepted by arguments of the command.
+    pub arg_choices: Vec<ArgChoices>,
     pub sub_commands: HashSet<Self>,
     pub executor: Option<MessageExecutor>,
 }
@@ -44,6 +67,14 @@ impl Command {
             executor: None,
             sub_commands: HashSet::new(),
             permissions: Vec::new(),
+            bot_permissions: Permissions::empty(),
+            ack_only: false,
+            audited: false,
+            mutates_data: false,
+            private_args: false,
+            concurrency_group: None,
+            limits: Limits::default(),
+            arg_choices: Vec::new(),
         }
     }
 
@@ -89,6 +120,69 @@ impl Command {
         self.permissions = permissions.into_iter().map(|n| n.to_string()).collect();
     }
 
+    pub fn set_bot_permissions<I>(&mut self, permissions: I)
+    where
+        I: IntoIterator<Item = Permissions>,
+    {
+        self.bot_permissions = permissions.into_iter().collect();
+    }
+
+    pub fn set_ack_only(&mut self, ack_only: bool) {
+        self.ack_only = ack_only;
+    }
+
+    pub fn set_audited(&mut self, audited: bool) {
+        self.audited = audited;
+    }
+
+    pub fn set_mutates_data(&mut self, mutates_data: bool) {
+        self.mutates_data = mutates_data;
+    }
+
+    pub fn set_private_args(&mut self, private_args: bool) {
+        self.private_args = private_args;
+    }
+
+    pub fn set_concurrency_group<T>(&mut self, group: T)
+    where
+        T: ToString,
+    {
+        self.concurrency_group = Some(group.to_string());
+    }
+
+    /// Sets the cooldown of the command per user, e.g. `30s` or `1d`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cooldown` is not a valid duration.
+    pub fn set_cooldown(&mut self, cooldown: &str) {
+        self.limits.set_cooldown(cooldown);
+    }
+
+    /// Keep the cooldown of the command in the store, so it survives
+    /// restarts.
+    pub fn set_cooldown_persistent(&mut self, persistent: bool) {
+        self.limits.persistent = persistent;
+    }
+
+    /// Sets the maximum number of uses of the command per user and UTC day.
+    pub fn set_quota(&mut self, quota: u64) {
+        self.limits.quota = Some(quota);
+    }
+
+    /// Sets the values accepted by the argument `name`, replacing previous
+    /// choices of the argument. `name` is the name of the argument in the
+    /// usage of the command.
+    pub fn set_arg_choices<I, T>(&mut self, name: &str, choices: I)
+    where
+        I: IntoIterator<Item = T>,
+        T: ToString,
+    {
+        self.arg_choices
+            .retain(|c| !c.name.eq_ignore_ascii_case(name));
+        self.arg_choices.push(ArgChoices::new(name, choices));
+    }
+
     pub fn executor<E>(&mut self, executor: Option<E>)
     where
         E: Into<MessageExecutor>,
@@ -148,6 +242,18 @@ impl CommandExt for Command {
         &self.permissions
     }
 
+    fn bot_permissions(&self) -> Permissions {
+        self.bot_permissions
+    }
+
+    fn ack_only(&self) -> bool {
+        self.ack_only
+    }
+
+    fn arg_choices(&self) -> &[ArgChoices] {
+        &self.arg_choices
+    }
+
     fn executor(&self) -> Option<&Self::Executor> {
         self.executor.as_ref()
     }
@@ -167,6 +273,14 @@ pub struct LoadedCommand {
     pub sub_commands: HashSet<SubCommand>,
     pub executor: Option<MessageExecutor>,
     pub permissions: Vec<String>,
+    pub bot_permissions: Permissions,
+    pub ack_only: bool,
+    pub audited: bool,
+    pub mutates_data: bool,
+    pub private_args: bool,
+    pub concurrency_group: Option<String>,
+    pub limits: Limits,
+    pub arg_choices: Vec<ArgChoices>,
     pub module_id: ModuleId,
 }
 
@@ -186,6 +300,14 @@ impl LoadedCommand {
                 .collect(),
             executor: command.executor,
             permissions: command.permissions,
+            bot_permissions: command.bot_permissions,
+            ack_only: command.ack_only,
+            audited: command.audited,
+            mutates_data: command.mutates_data,
+            private_args: command.private_args,
+            concurrency_group: command.concurrency_group,
+            limits: command.limits,
+            arg_choices: command.arg_choices,
             module_id,
         }
     }
@@ -266,6 +388,26 @@ impl SubCommand {
         &mut self.cell.get_mut().guild_only
     }
 
+    pub fn audited(&self) -> bool {
+        self.get().audited
+    }
+
+    pub fn mutates_data(&self) -> bool {
+        self.get().mutates_data
+    }
+
+    pub fn private_args(&self) -> bool {
+        self.get().private_args
+    }
+
+    pub fn concurrency_group(&self) -> Option<&str> {
+        self.get().concurrency_group.as_deref()
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.get().limits
+    }
+
     pub fn sub_commands(&self) -> &HashSet<SubCommand> {
         &self.get().sub_commands
     }
@@ -321,6 +463,18 @@ impl CommandExt for SubCommand {
         &self.get().permissions
     }
 
+    fn bot_permissions(&self) -> Permissions {
+        self.get().bot_permissions
+    }
+
+    fn ack_only(&self) -> bool {
+        self.get().ack_only
+    }
+
+    fn arg_choices(&self) -> &[ArgChoices] {
+        &self.get().arg_choices
+    }
+
     fn sub_commands(&self) -> &HashSet<Self> {
         &self.get().sub_commands
     }
@@ -383,44 +537,72 @@ unsafe impl Sync for SubCommand {}
 #[derive(Default, Debug)]
 pub(crate) struct InnerCommandHandler {
     commands: RwLock<HashSet<SubCommand>>,
+    disabled: DisabledSet,
 }
 
 impl InnerCommandHandler {
+    /// Adds `commands` to the root or to the command at the path of `options`.
+    /// Fails with [`Error::DuplicateName`] without adding any command if a
+    /// command with the same name already exists or is added twice. Sub
+    /// commands shadowing an argument of their parent are logged, see
+    /// [`shadowed_choice`].
     pub fn add_commands<I>(&self, commands: I, options: AddOptions) -> Result<(), Error>
     where
         I: IntoIterator<Item = Command>,
     {
-        let mut commands_set = self.commands.write();
+        let module_id = options.module_id.unwrap_or_default();
 
-        let root_set = match options.path {
-            Some(path) => {
-                let cmd = match find_command(&commands_set, &mut parse_args(path).as_args()) {
-                    Some(cmd) => cmd,
-                    None => return Err(Error::InvalidPath),
-                };
+        let commands: Vec<_> = commands
+            .into_iter()
+            .map(|command| SubCommand::new(LoadedCommand::new(command, module_id)))
+            .collect();
 
-                // SAFETY: The current thread has exclusive access to `commands_set` due to the
-                // write lock. Also changing the `sub_commands` field has no effect on the hash.
-                unsafe { &mut cmd.get_mut().sub_commands }
-            }
-            None => &mut commands_set,
-        };
+        let mut commands_set = self.commands.write();
 
-        let module_id = match options.module_id {
-            Some(module_id) => module_id,
-            None => ModuleId::default(),
+        let parent = match options.path {
+            Some(path) => match find_command(&commands_set, &mut parse_args(path).as_args()) {
+                Some(cmd) => Some((cmd, path)),
+                None => return Err(Error::InvalidPath),
+            },
+            None => None,
         };
 
-        for command in commands.into_iter() {
-            let mut command = LoadedCommand::new(command, module_id);
+        {
+            let root_set = match parent {
+                Some((cmd, _)) => cmd.sub_commands(),
+                None => &commands_set,
+            };
+
+            let mut names = HashSet::new();
+            for command in &commands {
+                let existing = match root_set.get(command.name()) {
+                    Some(existing) => Some(existing.get().module_id),
+                    None if !names.insert(command.name()) => Some(module_id),
+                    None => None,
+                };
 
-            if let Some(module_id) = options.module_id {
-                command.module_id = module_id;
+                if let Some(module_id) = existing {
+                    return Err(Error::DuplicateName {
+                        path: command_path(options.path, command.name()),
+                        module_id,
+                    });
+                }
             }
+        }
 
-            root_set.insert(SubCommand::new(command));
+        for warning in shadowing(parent, &commands) {
+            log::warn!("[CORE] {}", warning);
         }
 
+        let root_set = match parent {
+            // SAFETY: The current thread has exclusive access to `commands_set` due to the
+            // write lock. Also changing the `sub_commands` field has no effect on the hash.
+            Some((cmd, _)) => unsafe { &mut cmd.get_mut().sub_commands },
+            None => &mut commands_set,
+        };
+
+        root_set.extend(commands);
+
         Ok(())
     }
 
@@ -545,9 +727,27 @@ impl CommandHandler {
     where
         A: ArgumentsExt,
     {
+        let (command, depth) = self.resolve_path(&Arguments::new(args.as_ref()))?;
+
+        for _ in 0..depth {
+            args.pop();
+        }
+
+        Some(command)
+    }
+
+    /// Returns the deepest command matching the leading `args` and the number
+    /// of arguments that make up its path, without consuming any arguments.
+    /// Returns `None` if no command matches.
+    pub fn resolve_path(&self, args: &Arguments<'_>) -> Option<(SubCommand, usize)> {
         let cmds = self.inner.commands.read();
-        let command = find_command(&cmds, args)?;
-        Some(command.clone())
+        let (command, depth) = resolve_command(&cmds, args)?;
+        Some((command.clone(), depth))
+    }
+
+    /// Returns the disabled commands, by their space-separated path.
+    pub fn disabled(&self) -> &DisabledSet {
+        &self.inner.disabled
     }
 
     /// Returns a list all command's names in the command root.
@@ -556,6 +756,37 @@ impl CommandHandler {
         cmds.iter().map(|c| c.name().to_string()).collect()
     }
 
+    /// Returns the number of loaded commands, including all sub commands.
+    pub fn count(&self) -> usize {
+        fn count(commands: &HashSet<SubCommand>) -> usize {
+            commands
+                .iter()
+                .map(|cmd| 1 + count(cmd.sub_commands()))
+                .sum()
+        }
+
+        count(&self.inner.commands.read())
+    }
+
+    /// Returns the number of commands of the module `module_id`, including all
+    /// sub commands.
+    pub fn count_module(&self, module_id: ModuleId) -> usize {
+        fn count(commands: &HashSet<SubCommand>, module_id: ModuleId) -> usize {
+            commands
+                .iter()
+                .map(|cmd| {
+                    let own = (cmd.get().module_id == module_id) as usize;
+                    own + count(cmd.sub_commands(), module_id)
+                })
+                .sum()
+        }
+
+        count(&self.inner.commands.read(), module_id)
+    }
+
+    /// Adds `commands` to the root or to the command at the path of `options`.
+    /// Fails with [`Error::DuplicateName`] without adding any command if a
+    /// command with the same name already exists.
     pub fn add_commands<I>(&self, commands: I, options: AddOptions) -> Result<(), Error>
     where
         I: IntoIterator<Item = Command>,
@@ -583,10 +814,51 @@ impl CommandHandler {
     }
 }
 
-#[derive(Copy, Clone, Debug, Error)]
+/// Returns the space-separated path of the command `name` added at `path`.
+fn command_path(path: Option<&str>, name: &str) -> String {
+    match path {
+        Some(path) => format!("{} {}", path, name),
+        None => name.to_owned(),
+    }
+}
+
+/// Returns a warning for every command in `commands` and their sub commands
+/// that shadows a choice of the first argument of its parent, see
+/// [`shadowed_choice`]. `parent` is the command `commands` are added to and
+/// its path.
+fn shadowing<'a, I>(parent: Option<(&SubCommand, &str)>, commands: I) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a SubCommand>,
+{
+    let mut warnings = Vec::new();
+
+    for command in commands {
+        let path = command_path(parent.map(|(_, path)| path), command.name());
+
+        if let Some((parent, parent_path)) = parent {
+            if let Some(choices) = shadowed_choice(parent, command.name()) {
+                warnings.push(format!(
+                    "Sub command `{}` shadows the value `{}` of the argument `{}` of `{}`",
+                    path,
+                    command.name(),
+                    choices.name,
+                    parent_path
+                ));
+            }
+        }
+
+        warnings.extend(shadowing(Some((command, &path)), command.sub_commands()));
+    }
+
+    warnings
+}
+
+#[derive(Clone, Debug, Error)]
 pub enum Error {
-    #[error("duplicate name")]
-    DuplicateName,
+    /// A command with the same path exists already. `module_id` is the module
+    /// of the existing command, `ModuleId(0)` for builtin commands.
+    #[error("duplicate name: `{path}` is already registered by module {}", .module_id.0)]
+    DuplicateName { path: String, module_id: ModuleId },
     #[error("invalid path")]
     InvalidPath,
 }
@@ -608,3 +880,126 @@ impl From<Executor<GuildMessageContext>> for MessageExecutor {
         Self::GuildMessage(exec)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{shadowing, AddOptions, Command, CommandHandler, Error, LoadedCommand, SubCommand};
+    use crate::context::MessageContext;
+    use crate::executor::Executor;
+
+    use robbot::command::check_choices;
+    use robbot::module::ModuleId;
+
+    async fn noop(_ctx: MessageContext) -> robbot::Result {
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_choices() {
+        let mut cmd = Command::new("set");
+        cmd.set_usage("<Category> <Days>d|off [--quiet] [Note]");
+        cmd.set_arg_choices("category", ["warnings", "audit"]);
+        cmd.set_arg_choices("note", ["a"]);
+
+        let args = |args: &[&str]| -> Vec<String> { args.iter().map(|a| a.to_string()).collect() };
+
+        assert!(check_choices(&cmd, &args(&["audit", "30d"])).is_ok());
+        assert!(check_choices(&cmd, &args(&["Audit", "30d"])).is_ok());
+        // Missing arguments are left to the command.
+        assert!(check_choices(&cmd, &[]).is_ok());
+        // Arguments after a flag are not checked.
+        assert!(check_choices(&cmd, &args(&["audit", "30d", "--quiet", "b"])).is_ok());
+
+        let args = args(&["logs", "30d"]);
+        let err = check_choices(&cmd, &args).unwrap_err();
+        assert_eq!(err.value, "logs");
+        assert_eq!(
+            err.to_string(),
+            "`logs` is not a valid value for `category`, expected one of `warnings`, `audit`"
+        );
+
+        // Setting the choices again replaces them.
+        cmd.set_arg_choices("Category", ["logs"]);
+        assert!(check_choices(&cmd, &args).is_ok());
+    }
+
+    #[test]
+    fn test_add_commands_duplicate() {
+        let handler = CommandHandler::new();
+        let first = || AddOptions::new().module_id(ModuleId(1));
+        let second = || AddOptions::new().module_id(ModuleId(2));
+
+        handler
+            .add_commands([Command::new("gate")], first())
+            .unwrap();
+
+        let err = handler
+            .add_commands([Command::new("poll"), Command::new("gate")], second())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::DuplicateName { ref path, module_id: ModuleId(1) } if path == "gate"
+        ));
+        // A failed batch adds no commands.
+        assert_eq!(handler.count(), 1);
+
+        let err = handler
+            .add_commands([Command::new("poll"), Command::new("poll")], second())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::DuplicateName { ref path, module_id: ModuleId(2) } if path == "poll"
+        ));
+        assert_eq!(handler.count(), 1);
+
+        handler
+            .add_commands([Command::new("verify")], first().path("gate"))
+            .unwrap();
+        let err = handler
+            .add_commands([Command::new("verify")], second().path("gate"))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "duplicate name: `gate verify` is already registered by module 1"
+        );
+
+        assert_eq!(handler.count_module(ModuleId(1)), 2);
+        assert_eq!(handler.count_module(ModuleId(2)), 0);
+    }
+
+    #[tokio::test]
+    async fn test_shadowing() {
+        let command = |name: &str, executor: bool| {
+            let mut cmd = Command::new(name);
+            cmd.set_usage("<State> [Message...]");
+            cmd.set_arg_choices("state", ["on", "off"]);
+            if executor {
+                cmd.executor(Some(Executor::from_fn(noop)));
+            }
+            cmd
+        };
+        let load = |cmd: Command| SubCommand::new(LoadedCommand::new(cmd, ModuleId(1)));
+
+        let mut parent = command("maintenance", true);
+        parent.sub_commands.insert(Command::new("on"));
+        parent.sub_commands.insert(Command::new("status"));
+
+        assert_eq!(
+            shadowing(None, &[load(parent)]),
+            ["Sub command `maintenance on` shadows the value `on` of the argument `state` of `maintenance`"]
+        );
+
+        // Commands without an executor take no arguments.
+        let mut group = command("gate", false);
+        group.sub_commands.insert(Command::new("on"));
+        assert!(shadowing(None, &[load(group)]).is_empty());
+
+        // Sub commands added to an existing command.
+        let parent = load(command("maintenance", true));
+        let added = [load(Command::new("OFF")), load(Command::new("status"))];
+        assert_eq!(
+            shadowing(Some((&parent, "maintenance")), &added),
+            ["Sub command `maintenance OFF` shadows the value `OFF` of the argument `state` of `maintenance`"]
+        );
+    }
+}
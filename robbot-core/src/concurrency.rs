@@ -0,0 +1,608 @@
+//! Named concurrency groups limit how many commands and tasks sharing an
+//! expensive resource (e.g. an external API) run at the same time.
+//!
+//! Commands and tasks join a group using the `concurrency_group` attribute.
+//! The limits of a group are read from the `[concurrency]` section of the
+//! config file, groups without an entry use [`ConcurrencyLimit::default`].
+//!
+//! Independent of the groups, [`GuildExecutions`] limits the number of
+//! commands running at the same time per guild and in total, so a single busy
+//! guild cannot starve the others.
+use robbot::model::id::GuildId;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+/// The limits of a single concurrency group.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConcurrencyLimit {
+    /// The number of operations running at the same time.
+    #[serde(default = "ConcurrencyLimit::default_limit")]
+    pub limit: usize,
+    /// The number of commands waiting for a running operation to complete.
+    /// Commands are rejected once the queue is full.
+    #[serde(default = "ConcurrencyLimit::default_queue")]
+    pub queue: usize,
+}
+
+impl ConcurrencyLimit {
+    fn default_limit() -> usize {
+        1
+    }
+
+    fn default_queue() -> usize {
+        5
+    }
+}
+
+impl Default for ConcurrencyLimit {
+    fn default() -> Self {
+        Self {
+            limit: Self::default_limit(),
+            queue: Self::default_queue(),
+        }
+    }
+}
+
+/// Returned by [`ConcurrencyGroups::acquire`] if the queue of the group is full.
+#[derive(Copy, Clone, Debug, Error)]
+#[error("concurrency queue is full")]
+pub struct QueueFull;
+
+#[derive(Debug)]
+struct Group {
+    limit: ConcurrencyLimit,
+    state: Mutex<GroupState>,
+}
+
+#[derive(Debug)]
+struct GroupState {
+    /// The number of permits not handed out.
+    available: usize,
+    /// The number of [`Ticket`]s waiting for a permit.
+    waiting: usize,
+    /// Notifies the waiting tickets in the order they were created.
+    queue: VecDeque<oneshot::Sender<()>>,
+}
+
+impl Group {
+    /// Passes a released permit on to the oldest waiting ticket.
+    fn release(&self) {
+        let mut state = self.state.lock();
+
+        while let Some(sender) = state.queue.pop_front() {
+            // Fails if the ticket was dropped in the meantime.
+            if sender.send(()).is_ok() {
+                return;
+            }
+        }
+
+        state.available += 1;
+    }
+}
+
+/// The named concurrency groups of the bot.
+#[derive(Debug, Default)]
+pub struct ConcurrencyGroups {
+    limits: HashMap<String, ConcurrencyLimit>,
+    groups: Mutex<HashMap<String, Arc<Group>>>,
+}
+
+impl ConcurrencyGroups {
+    /// Creates new `ConcurrencyGroups` using the configured `limits`.
+    pub fn new(limits: HashMap<String, ConcurrencyLimit>) -> Self {
+        Self {
+            limits,
+            groups: Mutex::default(),
+        }
+    }
+
+    /// Requests a permit of the group `name`. The returned [`Ticket`] is either
+    /// ready or queued behind other operations. Returns [`QueueFull`] if the
+    /// queue of the group is full.
+    pub fn acquire(&self, name: &str) -> Result<Ticket, QueueFull> {
+        self.ticket(name, true)
+    }
+
+    /// Requests a permit of the group `name` like [`Self::acquire`], but is
+    /// never rejected. Used by tasks, which should not skip a scheduled run.
+    pub fn enqueue(&self, name: &str) -> Ticket {
+        // Without the queue limit the request is never rejected.
+        self.ticket(name, false).unwrap()
+    }
+
+    fn ticket(&self, name: &str, bounded: bool) -> Result<Ticket, QueueFull> {
+        let group = self.group(name);
+        let mut state = group.state.lock();
+
+        if state.available > 0 {
+            state.available -= 1;
+            drop(state);
+
+            return Ok(Ticket {
+                permit: Some(ConcurrencyPermit {
+                    group: group.clone(),
+                }),
+                receiver: None,
+                queued: None,
+                group,
+            });
+        }
+
+        if bounded && state.waiting >= group.limit.queue {
+            return Err(QueueFull);
+        }
+
+        // The ticket joins the queue right away, not once it is waited on.
+        let (sender, receiver) = oneshot::channel();
+        state.queue.push_back(sender);
+
+        let queued = group.limit.limit.saturating_sub(state.available) + state.waiting;
+        state.waiting += 1;
+        drop(state);
+
+        Ok(Ticket {
+            group,
+            permit: None,
+            receiver: Some(receiver),
+            queued: Some(queued),
+        })
+    }
+
+    fn group(&self, name: &str) -> Arc<Group> {
+        let mut groups = self.groups.lock();
+
+        groups
+            .entry(name.to_owned())
+            .or_insert_with(|| {
+                let limit = self.limits.get(name).copied().unwrap_or_default();
+
+                Arc::new(Group {
+                    limit,
+                    state: Mutex::new(GroupState {
+                        // A group with a limit of zero would never run anything.
+                        available: limit.limit.max(1),
+                        waiting: 0,
+                        queue: VecDeque::new(),
+                    }),
+                })
+            })
+            .clone()
+    }
+}
+
+/// A request for a permit of a concurrency group. Permits are handed out in
+/// the order the tickets were created.
+#[derive(Debug)]
+pub struct Ticket {
+    group: Arc<Group>,
+    permit: Option<ConcurrencyPermit>,
+    /// Notified once a queued ticket is given a permit.
+    receiver: Option<oneshot::Receiver<()>>,
+    queued: Option<usize>,
+}
+
+impl Ticket {
+    /// Returns the number of operations running or waiting ahead of this ticket.
+    /// Returns `None` if a permit was available immediately.
+    pub fn queued(&self) -> Option<usize> {
+        self.queued
+    }
+
+    /// Waits until a permit is available. The operation may run until the
+    /// returned [`ConcurrencyPermit`] is dropped.
+    pub async fn wait(mut self) -> ConcurrencyPermit {
+        if let Some(permit) = self.permit.take() {
+            return permit;
+        }
+
+        // The receiver stays in the ticket while waiting, so a permit passed
+        // on right before the wait is cancelled is released on drop. Senders
+        // are only dropped after sending.
+        if let Some(receiver) = &mut self.receiver {
+            receiver.await.unwrap();
+            self.receiver = None;
+        }
+
+        ConcurrencyPermit {
+            group: self.group.clone(),
+        }
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        if let Some(receiver) = &mut self.receiver {
+            // Prevents a permit from being passed on after the ticket is gone.
+            receiver.close();
+
+            if receiver.try_recv().is_ok() {
+                self.group.release();
+            }
+        }
+
+        if self.queued.is_some() {
+            self.group.state.lock().waiting -= 1;
+        }
+    }
+}
+
+/// A permit to run an operation in a concurrency group. The permit is
+/// released when it is dropped.
+#[derive(Debug)]
+pub struct ConcurrencyPermit {
+    group: Arc<Group>,
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        self.group.release();
+    }
+}
+
+/// The limits of concurrently running commands, read from the `[executions]`
+/// section of the config file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionLimits {
+    /// The number of commands running at the same time in a single guild.
+    #[serde(default = "ExecutionLimits::default_per_guild")]
+    pub per_guild: usize,
+    /// The number of commands running at the same time in all guilds and
+    /// direct messages.
+    #[serde(default = "ExecutionLimits::default_global")]
+    pub global: usize,
+}
+
+impl ExecutionLimits {
+    fn default_per_guild() -> usize {
+        4
+    }
+
+    fn default_global() -> usize {
+        64
+    }
+}
+
+impl Default for ExecutionLimits {
+    fn default() -> Self {
+        Self {
+            per_guild: Self::default_per_guild(),
+            global: Self::default_global(),
+        }
+    }
+}
+
+/// Returned by [`GuildExecutions::try_acquire`] if a limit is reached.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Error)]
+pub enum TooBusy {
+    #[error("too many commands running in the guild")]
+    Guild,
+    #[error("too many commands running")]
+    Global,
+}
+
+/// Limits the number of commands running at the same time per guild and in
+/// total. Commands over the limit are rejected instead of queued.
+///
+/// The semaphore of a guild is created on its first command and removed once
+/// no command of the guild is running.
+#[derive(Debug)]
+pub struct GuildExecutions {
+    limits: ExecutionLimits,
+    global: Arc<Semaphore>,
+    guilds: Arc<Mutex<HashMap<GuildId, Arc<Semaphore>>>>,
+    rejections: Mutex<HashMap<GuildId, u64>>,
+}
+
+impl GuildExecutions {
+    /// Creates new `GuildExecutions` using the configured `limits`.
+    pub fn new(limits: ExecutionLimits) -> Self {
+        Self {
+            limits,
+            // Limits of zero would never run anything.
+            global: Arc::new(Semaphore::new(limits.global.max(1))),
+            guilds: Arc::default(),
+            rejections: Mutex::default(),
+        }
+    }
+
+    /// Requests a permit to run a command in `guild_id`, or in a direct
+    /// message if `None`. Direct messages only count towards the global limit.
+    /// Returns [`TooBusy`] without waiting if a limit is reached.
+    pub fn try_acquire(&self, guild_id: Option<GuildId>) -> Result<ExecutionPermit, TooBusy> {
+        let guild = match guild_id {
+            Some(guild_id) => {
+                let semaphore = self.guild(guild_id);
+                match semaphore.clone().try_acquire_owned() {
+                    Ok(permit) => Some(GuildPermit {
+                        guild_id,
+                        semaphore,
+                        permit: Some(permit),
+                        guilds: self.guilds.clone(),
+                    }),
+                    Err(_) => {
+                        // Removes the semaphore again if it was just created.
+                        drop(GuildPermit {
+                            guild_id,
+                            semaphore,
+                            permit: None,
+                            guilds: self.guilds.clone(),
+                        });
+
+                        self.reject(guild_id);
+                        return Err(TooBusy::Guild);
+                    }
+                }
+            }
+            None => None,
+        };
+
+        match self.global.clone().try_acquire_owned() {
+            Ok(global) => Ok(ExecutionPermit {
+                _guild: guild,
+                _global: global,
+            }),
+            Err(_) => {
+                if let Some(guild_id) = guild_id {
+                    self.reject(guild_id);
+                }
+
+                Err(TooBusy::Global)
+            }
+        }
+    }
+
+    /// Returns the number of rejected commands per guild since the start.
+    pub fn rejections(&self) -> HashMap<GuildId, u64> {
+        self.rejections.lock().clone()
+    }
+
+    /// Returns the number of commands currently running.
+    pub fn running(&self) -> usize {
+        self.limits
+            .global
+            .max(1)
+            .saturating_sub(self.global.available_permits())
+    }
+
+    /// Returns the number of guilds with running commands.
+    pub fn active_guilds(&self) -> usize {
+        self.guilds.lock().len()
+    }
+
+    fn guild(&self, guild_id: GuildId) -> Arc<Semaphore> {
+        self.guilds
+            .lock()
+            .entry(guild_id)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.limits.per_guild.max(1))))
+            .clone()
+    }
+
+    fn reject(&self, guild_id: GuildId) {
+        *self.rejections.lock().entry(guild_id).or_default() += 1;
+    }
+}
+
+/// A permit to run a command acquired from [`GuildExecutions`]. The permit is
+/// released when it is dropped.
+#[derive(Debug)]
+pub struct ExecutionPermit {
+    _guild: Option<GuildPermit>,
+    _global: OwnedSemaphorePermit,
+}
+
+#[derive(Debug)]
+struct GuildPermit {
+    guild_id: GuildId,
+    semaphore: Arc<Semaphore>,
+    permit: Option<OwnedSemaphorePermit>,
+    guilds: Arc<Mutex<HashMap<GuildId, Arc<Semaphore>>>>,
+}
+
+impl Drop for GuildPermit {
+    fn drop(&mut self) {
+        drop(self.permit.take());
+
+        // New references are only created while holding the lock. If the map
+        // and this permit hold the only references, no command of the guild
+        // is running.
+        let mut guilds = self.guilds.lock();
+        if let Some(semaphore) = guilds.get(&self.guild_id) {
+            if Arc::ptr_eq(semaphore, &self.semaphore) && Arc::strong_count(semaphore) == 2 {
+                guilds.remove(&self.guild_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConcurrencyGroups, ConcurrencyLimit, ExecutionLimits, GuildExecutions, TooBusy};
+
+    use robbot::model::id::GuildId;
+
+    use parking_lot::Mutex;
+    use tokio::time::{sleep, Duration};
+
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn groups(limit: usize, queue: usize) -> ConcurrencyGroups {
+        let mut limits = HashMap::new();
+        limits.insert(String::from("sync"), ConcurrencyLimit { limit, queue });
+
+        ConcurrencyGroups::new(limits)
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_groups_order() {
+        let groups = groups(1, 2);
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let tickets: Vec<_> = (0..3)
+            .map(|index| {
+                let ticket = groups.acquire("sync").unwrap();
+                assert_eq!(ticket.queued(), (index > 0).then_some(index));
+                ticket
+            })
+            .collect();
+
+        // Later tickets start waiting first, the permits still follow the
+        // order the tickets were created.
+        let mut handles = Vec::new();
+        for (index, ticket) in tickets.into_iter().enumerate().rev() {
+            let log = log.clone();
+            handles.push(tokio::spawn(async move {
+                let _permit = ticket.wait().await;
+
+                log.lock().push((index, "start"));
+                sleep(Duration::from_millis(10)).await;
+                log.lock().push((index, "end"));
+            }));
+            tokio::task::yield_now().await;
+        }
+
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert_eq!(
+            *log.lock(),
+            [
+                (0, "start"),
+                (0, "end"),
+                (1, "start"),
+                (1, "end"),
+                (2, "start"),
+                (2, "end"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_groups_dropped_ticket() {
+        let groups = groups(1, 2);
+
+        let running = groups.acquire("sync").unwrap().wait().await;
+        let first = groups.acquire("sync").unwrap();
+        let second = groups.acquire("sync").unwrap();
+
+        // The permit is passed on to `first`, which is dropped without
+        // waiting for it. The permit moves on to `second`.
+        drop(running);
+        drop(first);
+        let permit = second.wait().await;
+
+        drop(permit);
+        assert_eq!(groups.acquire("sync").unwrap().queued(), None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_groups_queue_full() {
+        let groups = groups(1, 1);
+
+        let running = groups.acquire("sync").unwrap().wait().await;
+        let queued = groups.acquire("sync").unwrap();
+        assert_eq!(queued.queued(), Some(1));
+
+        assert!(groups.acquire("sync").is_err());
+
+        // Tasks are never rejected.
+        let task = groups.enqueue("sync");
+        assert_eq!(task.queued(), Some(2));
+        drop(task);
+
+        // Leaving the queue frees a slot.
+        drop(queued);
+        assert!(groups.acquire("sync").is_ok());
+
+        // Other groups are not affected.
+        assert_eq!(groups.acquire("other").unwrap().queued(), None);
+
+        drop(running);
+        assert_eq!(groups.acquire("sync").unwrap().queued(), None);
+    }
+
+    #[tokio::test]
+    async fn test_guild_executions_isolation() {
+        let executions = Arc::new(GuildExecutions::new(ExecutionLimits {
+            per_guild: 2,
+            global: 10,
+        }));
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        // Five concurrent commands in a busy guild, two in a quiet guild.
+        let mut handles = Vec::new();
+        for (guild, count) in [(GuildId(1), 5), (GuildId(2), 2)] {
+            for _ in 0..count {
+                let executions = executions.clone();
+                let log = log.clone();
+                handles.push(tokio::spawn(async move {
+                    let res = executions.try_acquire(Some(guild));
+                    log.lock().push((guild, res.is_ok()));
+                    sleep(Duration::from_millis(20)).await;
+                    drop(res);
+                }));
+            }
+        }
+
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        let log = log.lock();
+        let count = |guild, ok| log.iter().filter(|(g, o)| *g == guild && *o == ok).count();
+
+        assert_eq!(count(GuildId(1), true), 2);
+        assert_eq!(count(GuildId(1), false), 3);
+        // The busy guild does not affect the quiet guild.
+        assert_eq!(count(GuildId(2), true), 2);
+        assert_eq!(count(GuildId(2), false), 0);
+
+        let rejections = executions.rejections();
+        assert_eq!(rejections.get(&GuildId(1)), Some(&3));
+        assert_eq!(rejections.get(&GuildId(2)), None);
+
+        // Idle guilds are cleaned up.
+        assert_eq!(executions.active_guilds(), 0);
+        assert_eq!(executions.running(), 0);
+    }
+
+    #[test]
+    fn test_guild_executions_global() {
+        let executions = GuildExecutions::new(ExecutionLimits {
+            per_guild: 3,
+            global: 2,
+        });
+
+        let first = executions.try_acquire(Some(GuildId(1))).unwrap();
+        let dm = executions.try_acquire(None).unwrap();
+        assert_eq!(executions.running(), 2);
+
+        assert_eq!(
+            executions.try_acquire(Some(GuildId(2))).unwrap_err(),
+            TooBusy::Global
+        );
+        assert_eq!(executions.try_acquire(None).unwrap_err(), TooBusy::Global);
+        assert_eq!(executions.rejections().get(&GuildId(2)), Some(&1));
+        // The rejected guild holds no semaphore.
+        assert_eq!(executions.active_guilds(), 1);
+
+        drop(dm);
+        let second = executions.try_acquire(Some(GuildId(1))).unwrap();
+        assert_eq!(
+            executions.try_acquire(Some(GuildId(1))).unwrap_err(),
+            TooBusy::Global
+        );
+
+        drop(first);
+        assert_eq!(executions.active_guilds(), 1);
+        drop(second);
+        assert_eq!(executions.active_guilds(), 0);
+    }
+}
@@ -1,29 +1,176 @@
-use robbot::model::id::UserId;
+use crate::concurrency::{ConcurrencyLimit, ExecutionLimits};
+use crate::executor::Timeouts;
+use crate::hook::Batching;
+use crate::maintenance::MaintenanceMode;
+
+use robbot::model::id::{ChannelId, UserId};
+use robbot::task::TaskSchedule;
 
 use log::LevelFilter;
 use serde::{Deserialize, Serialize};
 
+use std::collections::HashMap;
+
+/// The version of the config format supported by this version of the bot.
+/// Older config files are migrated when they are loaded.
+pub const CONFIG_VERSION: u32 = 2;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Config {
+    /// The version of the config format, see [`CONFIG_VERSION`].
+    pub config_version: u32,
     pub token: String,
     pub prefix: String,
+    /// Also accept a mention of the bot as the command prefix.
+    #[serde(default = "default_true")]
+    pub mention_prefix: bool,
+    /// Accept commands without the prefix in direct messages. Direct messages
+    /// without a prefix are only commands if they name an existing command.
+    #[serde(default = "default_true")]
+    pub dm_without_prefix: bool,
     pub loglevel: LevelFilter,
+    #[serde(default)]
+    pub logging: Logging,
     pub database: Database,
     pub admins: Vec<UserId>,
+    /// The channel receiving reports of the `feedback` command. Feedback is
+    /// disabled without a channel.
+    #[serde(default)]
+    pub feedback_channel: Option<ChannelId>,
+    #[serde(default)]
+    pub ignore: Ignore,
+    #[serde(default)]
+    pub intents: Intents,
+    /// Schedules overriding the default schedule of tasks, by task name. See
+    /// [`TaskSchedule`] for the accepted formats.
+    #[serde(default)]
+    pub tasks: HashMap<String, String>,
+    /// Limits of the concurrency groups, by group name.
+    #[serde(default)]
+    pub concurrency: HashMap<String, ConcurrencyLimit>,
+    /// Limits of concurrently running commands per guild and in total.
+    #[serde(default)]
+    pub executions: ExecutionLimits,
+    /// The default timeouts of tasks and hooks.
+    #[serde(default)]
+    pub timeouts: Timeouts,
+    /// How events are batched for hooks handling batches.
+    #[serde(default)]
+    pub batching: Batching,
+    /// Whether the bot starts in maintenance mode, see [`crate::maintenance`].
+    #[serde(default)]
+    pub maintenance: MaintenanceMode,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            config_version: CONFIG_VERSION,
             token: String::new(),
             prefix: String::new(),
+            mention_prefix: true,
+            dm_without_prefix: true,
             loglevel: LevelFilter::Info,
+            logging: Logging::default(),
             database: Database::default(),
             admins: Vec::new(),
+            feedback_channel: None,
+            ignore: Ignore::default(),
+            intents: Intents::default(),
+            tasks: HashMap::new(),
+            concurrency: HashMap::new(),
+            executions: ExecutionLimits::default(),
+            timeouts: Timeouts::default(),
+            batching: Batching::default(),
+            maintenance: MaintenanceMode::default(),
         }
     }
 }
 
+impl Config {
+    /// Returns the parsed schedule overrides from the `tasks` section. Invalid
+    /// schedules are logged and skipped, the task keeps its default schedule.
+    pub fn task_schedules(&self) -> HashMap<String, TaskSchedule> {
+        self.tasks
+            .iter()
+            .filter_map(|(name, schedule)| match schedule.parse() {
+                Ok(schedule) => Some((name.clone(), schedule)),
+                Err(err) => {
+                    log::error!("[TASK] Ignoring schedule of task '{}': {}", name, err);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns a copy of the config with all secrets masked, safe to share
+    /// e.g. in a support bundle. Empty secrets are kept, so a missing secret
+    /// is still visible.
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        redact(&mut config.token);
+        redact(&mut config.database.password);
+
+        config
+    }
+}
+
+/// The value replacing redacted secrets.
+const REDACTED: &str = "<redacted>";
+
+fn redact(secret: &mut String) {
+    if !secret.is_empty() {
+        *secret = String::from(REDACTED);
+    }
+}
+
+/// Logging configuration section. The level of targets without a filter is
+/// `loglevel`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Logging {
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Levels overriding `loglevel` for a target and all its submodules, e.g.
+    /// `"robbot_core::store" = "debug"`. Targets outside of the bot are only
+    /// logged if they have a filter.
+    #[serde(default)]
+    pub filters: HashMap<String, LevelFilter>,
+    /// Write to a file instead of stdout.
+    #[serde(default)]
+    pub file: Option<LogFile>,
+}
+
+/// The format of log lines.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines.
+    #[default]
+    Pretty,
+    /// One JSON object per line.
+    Json,
+}
+
+/// A log file rotated once it reaches `max_size` bytes. Rotated files are
+/// renamed to `<path>.1`, `<path>.2` and so on, only the `keep` most recent
+/// ones are kept.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogFile {
+    pub path: String,
+    #[serde(default = "default_log_max_size")]
+    pub max_size: u64,
+    #[serde(default = "default_log_keep")]
+    pub keep: usize,
+}
+
+fn default_log_max_size() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_log_keep() -> usize {
+    5
+}
+
 /// Database configuration section. Not all
 /// fields are required for all driver types.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -34,6 +181,45 @@ pub struct Database {
     pub user: String,
     pub password: String,
     pub database: String,
+    /// Number of additional connection attempts made on startup.
+    #[serde(default)]
+    pub retries: u32,
+    /// Run in degraded mode with an in-memory store if the database
+    /// cannot be reached on startup.
+    #[serde(default)]
+    pub fallback: bool,
+}
+
+/// Messages ignored in addition to messages in ignored channels.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Ignore {
+    /// Ignore messages sent by bots.
+    #[serde(default)]
+    pub bots: bool,
+    /// Ignore messages sent by webhooks.
+    #[serde(default)]
+    pub webhooks: bool,
+}
+
+/// Privileged intents enabled for the application.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Intents {
+    /// Whether the message content intent is enabled. Without it messages
+    /// in guilds arrive without content, disabling prefix commands.
+    #[serde(default = "default_true")]
+    pub message_content: bool,
+}
+
+impl Default for Intents {
+    fn default() -> Self {
+        Self {
+            message_content: true,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl Database {
@@ -47,7 +233,7 @@ impl Database {
 
 #[cfg(test)]
 mod tests {
-    use super::Database;
+    use super::{Config, Database};
 
     #[test]
     fn test_database_connect_string() {
@@ -58,6 +244,7 @@ mod tests {
             user: String::from("robbot"),
             password: String::from("pw"),
             database: String::from("db"),
+            ..Default::default()
         };
 
         assert_eq!(
@@ -65,4 +252,27 @@ mod tests {
             "mysql://robbot:pw@127.0.0.1:3306/db?ssl-mode=DISABLED"
         )
     }
+
+    #[test]
+    fn test_config_redacted() {
+        let mut config = Config {
+            token: String::from("secret token"),
+            ..Default::default()
+        };
+        config.database.password = String::from("pw");
+        config.database.user = String::from("robbot");
+
+        let redacted = config.redacted();
+        assert_eq!(redacted.token, "<redacted>");
+        assert_eq!(redacted.database.password, "<redacted>");
+        assert_eq!(redacted.database.user, "robbot");
+
+        let json = serde_json::to_string(&redacted).unwrap();
+        assert!(!json.contains("secret token"));
+        assert!(!json.contains("\"pw\""));
+
+        // Missing secrets stay visible.
+        config.database.password.clear();
+        assert_eq!(config.redacted().database.password, "");
+    }
 }
@@ -1,9 +1,21 @@
+use crate::chunks::{ChunkError, ChunkSummary};
+use crate::extensions::Extensions;
+use crate::settings::{TimeDisplay, TimeSettings};
 use crate::state::State;
-use robbot::arguments::{CommandArguments, OwnedArguments};
+use crate::store::Error;
+use chrono::{DateTime, Utc};
+use robbot::arguments::{ChannelEvent, CommandArguments, MessageRef, OwnedArguments};
+use robbot::builder::{CreateMessage, EditMessage};
+use robbot::context::{Error as ContextError, MESSAGE_MAX_LEN};
+use serenity::client::bridge::gateway::ChunkGuildFilter;
 use serenity::client::Context as RawContext;
 use std::{ops::Deref, sync::Arc};
+use tokio::time::Duration;
 
 use robbot::model::channel::{GuildMessage, Message};
+use robbot::model::emoji::Emoji;
+use robbot::model::guild::{can_manage_role, Role};
+use robbot::model::id::{ChannelId, GuildId, MessageId, RoleId, UserId};
 
 use robbot::hook::{HookEvent, HookEventWrapper};
 
@@ -16,6 +28,9 @@ pub type GuildMessageContext = Context<GuildMessage>;
 /// An alias for `Context<()>`. This context is received by tasks.
 pub type TaskContext = Context<()>;
 
+/// The time [`Context::request_guild_members`] waits for all member chunks.
+const CHUNK_TIMEOUT: Duration = Duration::from_secs(60);
+
 #[derive(Clone, Debug)]
 pub struct Context<T>
 where
@@ -23,6 +38,7 @@ where
 {
     inner: robbot::Context<T, Arc<State>>,
     pub args: CommandArguments,
+    extensions: Extensions,
 }
 
 impl<T> Context<T>
@@ -37,6 +53,7 @@ where
                 state,
             },
             args: CommandArguments::from(OwnedArguments::new()),
+            extensions: Extensions::new(),
         }
     }
 
@@ -53,6 +70,7 @@ where
                 state,
             },
             args,
+            extensions: Extensions::new(),
         }
     }
 
@@ -60,11 +78,303 @@ where
     where
         U: Send + Sync,
     {
-        let Self { inner, args } = self;
+        let Self {
+            inner,
+            args,
+            extensions,
+        } = self;
 
         let (inner, old_event) = inner.swap(event);
 
-        (Context { inner, args }, old_event)
+        (
+            Context {
+                inner,
+                args,
+                extensions,
+            },
+            old_event,
+        )
+    }
+
+    /// Returns the [`Extensions`] of the invocation. All clones of a `Context`
+    /// share the same `Extensions`.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Requests all members of `guild_id` over the gateway and waits until
+    /// all member chunks arrived. The received members are added to the cache,
+    /// even if not all chunks arrive before the timeout.
+    ///
+    /// The request is sent through the shard of this context, the guild must
+    /// be on the same shard. Requires the `GUILD_MEMBERS` intent.
+    pub async fn request_guild_members(
+        &self,
+        guild_id: GuildId,
+    ) -> Result<ChunkSummary, ChunkError> {
+        let request = self.state.member_chunks().register(guild_id);
+
+        self.raw_ctx.shard.chunk_guild(
+            guild_id.into(),
+            None,
+            ChunkGuildFilter::None,
+            Some(request.nonce().to_owned()),
+        );
+
+        request.wait(CHUNK_TIMEOUT).await
+    }
+
+    /// Returns `true` if a response was sent using [`Self::respond`], [`Self::ack`]
+    /// or [`Self::ack_err`] during this invocation.
+    pub fn has_responded(&self) -> bool {
+        self.extensions.contains::<Responded>()
+    }
+
+    /// Returns the id of the last message sent using [`Self::respond`] during
+    /// this invocation.
+    pub fn last_response(&self) -> Option<MessageId> {
+        self.extensions
+            .get::<LastResponse>()
+            .map(|response| response.message_id)
+    }
+}
+
+impl<T> Context<T>
+where
+    T: Send + Sync,
+{
+    /// Adds a role managed by `manager` to a member. Returns `false` without
+    /// adding the role if a manager with a higher priority manages it, see
+    /// [`ManagedRoles`].
+    ///
+    /// [`ManagedRoles`]: crate::roles::ManagedRoles
+    pub async fn add_managed_role(
+        &self,
+        manager: &str,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+    ) -> Result<bool, ContextError> {
+        if self
+            .state
+            .managed_roles()
+            .check(guild_id, role_id, manager)
+            .is_err()
+        {
+            return Ok(false);
+        }
+
+        self.add_member_role(guild_id, user_id, role_id).await?;
+        Ok(true)
+    }
+
+    /// Returns `true` if the bot can add the role to and remove it from
+    /// members, see [`can_manage_role`]. Returns `false` if the role does not
+    /// exist. Returns `true` if the guild or the member of the bot is not
+    /// cached, role edits then fail on their own.
+    ///
+    /// [`can_manage_role`]: robbot::model::guild::can_manage_role
+    pub async fn can_manage_role(&self, guild_id: GuildId, role_id: RoleId) -> bool {
+        let user_id = self.raw_ctx.cache.current_user_id().await;
+
+        let member = match self.raw_ctx.cache.member(guild_id.0, user_id).await {
+            Some(member) => member,
+            None => return true,
+        };
+
+        self.raw_ctx
+            .cache
+            .guild_field(guild_id.0, |guild| {
+                let role = match guild.roles.get(&role_id.into()) {
+                    Some(role) => Role::from(role.clone()),
+                    None => return false,
+                };
+
+                // The owner is not bound by the role hierarchy.
+                if guild.owner_id == user_id {
+                    return !role.is_everyone() && !role.managed;
+                }
+
+                let roles: Vec<_> = guild
+                    .roles
+                    .values()
+                    .filter(|role| role.id.0 == guild_id.0 || member.roles.contains(&role.id))
+                    .cloned()
+                    .map(Role::from)
+                    .collect();
+
+                can_manage_role(&roles, &role)
+            })
+            .await
+            .unwrap_or(true)
+    }
+
+    /// Removes a role managed by `manager` from a member. Returns `false`
+    /// without removing the role if a manager with a higher priority manages
+    /// it, see [`ManagedRoles`].
+    ///
+    /// [`ManagedRoles`]: crate::roles::ManagedRoles
+    pub async fn remove_managed_role(
+        &self,
+        manager: &str,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+    ) -> Result<bool, ContextError> {
+        if self
+            .state
+            .managed_roles()
+            .check(guild_id, role_id, manager)
+            .is_err()
+        {
+            return Ok(false);
+        }
+
+        self.remove_member_role(guild_id, user_id, role_id).await?;
+        Ok(true)
+    }
+}
+
+impl<T> Context<T>
+where
+    T: ChannelEvent + AsRef<ChannelId> + AsRef<MessageId> + Send + Sync,
+{
+    /// Responds to the message of the event. The response is tracked in the
+    /// [`Extensions`] of the invocation, see [`Self::has_responded`].
+    pub async fn respond<M>(&self, message: M) -> Result<Message, ContextError>
+    where
+        M: Into<CreateMessage>,
+    {
+        let message = self.inner.respond(message).await?;
+        self.extensions.insert(Responded);
+        self.extensions.insert(LastResponse {
+            channel_id: message.channel_id,
+            message_id: message.id,
+        });
+
+        Ok(message)
+    }
+
+    /// Edits the last response of this invocation to `content`, or responds
+    /// with `content` if no message was sent yet. Allows updating a status
+    /// message in a loop. Acknowledgements using a reaction are not messages,
+    /// a new message is sent after them.
+    ///
+    /// Returns [`ContextError::ContentTooLong`] if the response would be
+    /// edited to more than [`MESSAGE_MAX_LEN`] characters.
+    pub async fn edit_or_respond<C>(&self, content: C) -> Result<Message, ContextError>
+    where
+        C: ToString,
+    {
+        let content = content.to_string();
+
+        let response = match self.extensions.get::<LastResponse>() {
+            Some(response) => response,
+            None => return self.respond(content).await,
+        };
+
+        let len = content.chars().count();
+        if len > MESSAGE_MAX_LEN {
+            return Err(ContextError::ContentTooLong { len });
+        }
+
+        self.edit_message(
+            response.channel_id,
+            response.message_id,
+            EditMessage::new(|m| {
+                m.content(content);
+            }),
+        )
+        .await
+    }
+
+    /// Acknowledges the message of the event with a ✅ reaction. Falls back
+    /// to a message in direct messages or if the reaction cannot be added,
+    /// e.g. when the bot is missing the Add Reactions permission.
+    pub async fn ack(&self) -> Result<(), ContextError> {
+        self.acknowledge('✅', ":white_check_mark:").await
+    }
+
+    /// Acknowledges the message of the event with a ❌ reaction. Falls back
+    /// to a message like [`Self::ack`].
+    pub async fn ack_err(&self) -> Result<(), ContextError> {
+        self.acknowledge('❌', ":x:").await
+    }
+
+    async fn acknowledge(&self, emoji: char, fallback: &str) -> Result<(), ContextError> {
+        if let Some(guild_id) = self.event.guild_id() {
+            let message = MessageRef::new(
+                Some(guild_id),
+                Some(*self.event.as_ref()),
+                *AsRef::<MessageId>::as_ref(&self.event),
+            );
+
+            match self
+                .react_to(message, Emoji::Unicode(emoji.to_string()))
+                .await
+            {
+                Ok(()) => {
+                    self.extensions.insert(Responded);
+                    return Ok(());
+                }
+                Err(err) => log::debug!("Failed to acknowledge using a reaction: {:?}", err),
+            }
+        }
+
+        self.respond(fallback).await?;
+        Ok(())
+    }
+}
+
+/// Marks an invocation that has sent a response.
+#[derive(Copy, Clone, Debug)]
+struct Responded;
+
+/// The last message sent using [`Context::respond`] during an invocation.
+#[derive(Copy, Clone, Debug)]
+struct LastResponse {
+    channel_id: ChannelId,
+    message_id: MessageId,
+}
+
+impl<T> Context<T>
+where
+    T: AsRef<Message> + Send + Sync,
+{
+    /// Returns `true` if the message of the event should be ignored, either because
+    /// it was sent in an ignored channel or by an ignored bot or webhook. Hooks
+    /// reacting to messages should check this before acting on a message.
+    pub async fn is_ignored_channel(&self) -> Result<bool, Error> {
+        self.state
+            .ignores()
+            .is_ignored_message(self.event.as_ref())
+            .await
+    }
+}
+
+impl<T> Context<T>
+where
+    T: ChannelEvent + Send + Sync,
+{
+    /// Returns the time settings of the guild of the event. Direct messages
+    /// use the default settings.
+    pub async fn time_settings(&self) -> Result<TimeSettings, Error> {
+        match self.event.guild_id() {
+            Some(guild_id) => self.state.settings().time(guild_id).await,
+            None => Ok(TimeSettings::default()),
+        }
+    }
+
+    /// Formats `time` using the time settings of the guild of the event, see
+    /// [`TimeSettings::format`]. Use [`time_settings`] to format many times.
+    ///
+    /// [`time_settings`]: Self::time_settings
+    pub async fn format_time(
+        &self,
+        time: DateTime<Utc>,
+        display: TimeDisplay,
+    ) -> Result<String, Error> {
+        Ok(self.time_settings().await?.format(time, display))
     }
 }
 
@@ -0,0 +1,723 @@
+use crate::store::{Error, StoreHandler};
+use crate::time::DAY;
+
+use robbot::store::{delete, get_one, insert};
+use robbot::task::parse_duration;
+use robbot::StoreData;
+
+use chrono::Duration;
+use parking_lot::Mutex;
+use tokio::sync::{Mutex as AsyncMutex, RwLock};
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// The maximum number of rows deleted in a single statement.
+const CHUNK_SIZE: usize = 100;
+
+/// A persistent cooldown of a command for a scope. Only used for commands
+/// without a quota, the [`QuotaEntry`] keeps the cooldown otherwise.
+#[derive(Clone, Debug, StoreData)]
+pub struct CooldownEntry {
+    pub command_path: String,
+    /// The id of the user the cooldown applies to.
+    pub scope_id: u64,
+    /// The time the cooldown expires as a unix timestamp in seconds.
+    pub expires_at: i64,
+}
+
+/// The number of uses of a command by a scope on a UTC day.
+#[derive(Clone, Debug, StoreData)]
+pub struct QuotaEntry {
+    pub command_path: String,
+    /// The id of the user the quota applies to.
+    pub scope_id: u64,
+    /// The UTC day as the number of days since the unix epoch.
+    pub day: i64,
+    pub count: u64,
+    /// The time a persistent cooldown of the command expires as a unix
+    /// timestamp in seconds, `0` if there is none.
+    pub expires_at: i64,
+}
+
+/// The cooldown and quota of a command.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The time a user has to wait between two uses of the command.
+    pub cooldown: Option<Duration>,
+    /// Keep cooldowns in the store, so they survive restarts.
+    pub persistent: bool,
+    /// The maximum number of uses per user and UTC day. Quotas are always
+    /// kept in the store.
+    pub quota: Option<u64>,
+}
+
+impl Limits {
+    /// Returns `true` if the command has neither a cooldown nor a quota.
+    pub fn is_empty(&self) -> bool {
+        self.cooldown.is_none() && self.quota.is_none()
+    }
+
+    /// Sets the cooldown from a duration like `30s` or `1d`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cooldown` is not a valid duration. Commands are created at
+    /// startup, an invalid cooldown is therefore caught immediately.
+    pub fn set_cooldown(&mut self, cooldown: &str) {
+        let cooldown = parse_duration(cooldown)
+            .unwrap_or_else(|| panic!("invalid command cooldown: {:?}", cooldown));
+
+        self.cooldown = Some(cooldown);
+    }
+}
+
+/// The result of [`CooldownHandler::acquire`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Acquire {
+    /// The use was recorded.
+    Allowed,
+    /// The command is on cooldown until `expires_at`.
+    Cooldown { expires_at: i64 },
+    /// The quota is used up until `resets_at`.
+    Quota { resets_at: i64 },
+}
+
+type Key = (String, u64);
+
+/// The usage of a key, `None` until it was loaded from the store.
+type UsageLock = Arc<AsyncMutex<Option<Usage>>>;
+
+/// The cached uses of a command by a scope.
+#[derive(Copy, Clone, Debug, Default)]
+struct Usage {
+    /// The expiry of the cooldown, `0` if there is none.
+    expires_at: i64,
+    /// The day of the last use counted in `count`.
+    day: i64,
+    count: u64,
+    /// The [`CooldownEntry`] of the key exists in the store.
+    cooldown_stored: bool,
+    /// The [`QuotaEntry`] of the key exists in the store.
+    quota_stored: bool,
+}
+
+impl Usage {
+    /// Returns `true` if the usage still limits a use at `now`.
+    fn is_active(&self, now: i64) -> bool {
+        self.expires_at > now || self.day >= now.div_euclid(DAY)
+    }
+}
+
+/// Enforces the [`Limits`] of commands.
+///
+/// Cooldowns and quotas are cached in memory. Persistent cooldowns and quotas
+/// are additionally written to the store and loaded on the first use after a
+/// restart. Both positive and negative results of the store are cached, so
+/// only the first use of a command by a user reads from the store, and every
+/// allowed use writes a single row.
+///
+/// Uses of a command by the same user are checked and recorded one at a
+/// time, concurrent uses cannot pass the same cooldown or quota.
+///
+/// While the store is degraded, uses are only recorded in memory.
+#[derive(Clone, Debug)]
+pub struct CooldownHandler {
+    store: StoreHandler,
+    /// The usage of every key. The lock of a key is held while checking and
+    /// recording a use.
+    usages: Arc<Mutex<HashMap<Key, UsageLock>>>,
+    /// Held shared by [`acquire`] and exclusively by [`cleanup`], so rows
+    /// are never deleted while a use is recorded.
+    ///
+    /// [`acquire`]: Self::acquire
+    /// [`cleanup`]: Self::cleanup
+    cleanup: Arc<RwLock<()>>,
+}
+
+impl CooldownHandler {
+    pub fn new(store: StoreHandler) -> Self {
+        Self {
+            store,
+            usages: Arc::default(),
+            cleanup: Arc::default(),
+        }
+    }
+
+    /// Records a use of the command at `command_path` by `scope_id` at `now`
+    /// if it is within the `limits` of the command.
+    pub async fn acquire(
+        &self,
+        command_path: &str,
+        scope_id: u64,
+        limits: Limits,
+        now: i64,
+    ) -> Result<Acquire, Error> {
+        if limits.is_empty() {
+            return Ok(Acquire::Allowed);
+        }
+
+        let key = (command_path.to_owned(), scope_id);
+        let day = now.div_euclid(DAY);
+
+        let _cleanup = self.cleanup.read().await;
+        let usage = self.usages.lock().entry(key.clone()).or_default().clone();
+        let mut usage = usage.lock().await;
+
+        let current = match *usage {
+            Some(current) => current,
+            None => self.load(&key, limits).await?,
+        };
+        *usage = Some(current);
+
+        // Check all limits before recording the use in any of them.
+        if limits.cooldown.is_some() && current.expires_at > now {
+            return Ok(Acquire::Cooldown {
+                expires_at: current.expires_at,
+            });
+        }
+
+        let count = match current.day == day {
+            true => current.count,
+            false => 0,
+        };
+
+        if let Some(quota) = limits.quota {
+            if count >= quota {
+                return Ok(Acquire::Quota {
+                    resets_at: (day + 1) * DAY,
+                });
+            }
+        }
+
+        let mut next = current;
+        if let Some(cooldown) = limits.cooldown {
+            next.expires_at = now + cooldown.num_seconds();
+        }
+        if limits.quota.is_some() {
+            next.day = day;
+            next.count = count + 1;
+        }
+
+        // Reserve the use before writing it. Concurrent uses wait for the lock
+        // of the key and see it.
+        *usage = Some(next);
+
+        match self.store(&key, limits, &mut next).await {
+            Ok(()) => {
+                *usage = Some(next);
+                Ok(Acquire::Allowed)
+            }
+            Err(err) => {
+                *usage = Some(current);
+                Err(err)
+            }
+        }
+    }
+
+    /// Loads the usage of `key` from the store. Reads the [`QuotaEntry`] if
+    /// the command has a quota, the [`CooldownEntry`] if it only has a
+    /// persistent cooldown and nothing otherwise.
+    async fn load(&self, key: &Key, limits: Limits) -> Result<Usage, Error> {
+        let mut usage = Usage::default();
+
+        if limits.quota.is_some() {
+            let entry = get_one!(self.store, QuotaEntry => {
+                command_path == key.0.clone(),
+                scope_id == key.1,
+            })
+            .await?;
+
+            if let Some(entry) = entry {
+                usage.expires_at = entry.expires_at;
+                usage.day = entry.day;
+                usage.count = entry.count;
+                usage.quota_stored = true;
+            }
+        } else if limits.persistent {
+            let entry = get_one!(self.store, CooldownEntry => {
+                command_path == key.0.clone(),
+                scope_id == key.1,
+            })
+            .await?;
+
+            if let Some(entry) = entry {
+                usage.expires_at = entry.expires_at;
+                usage.cooldown_stored = true;
+            }
+        }
+
+        Ok(usage)
+    }
+
+    /// Writes `usage` to the row loaded by [`load`], updating it if it exists.
+    /// While the store is degraded uses are only kept in memory.
+    ///
+    /// [`load`]: Self::load
+    async fn store(&self, key: &Key, limits: Limits, usage: &mut Usage) -> Result<(), Error> {
+        if self.store.is_degraded() {
+            return Ok(());
+        }
+
+        if limits.quota.is_some() {
+            let entry = QuotaEntry {
+                command_path: key.0.clone(),
+                scope_id: key.1,
+                day: usage.day,
+                count: usage.count,
+                expires_at: match limits.persistent {
+                    true => usage.expires_at,
+                    false => 0,
+                },
+            };
+
+            match usage.quota_stored {
+                true => {
+                    let query = self
+                        .store
+                        .make_query::<QuotaEntry>()
+                        .command_path(key.0.clone())
+                        .scope_id(key.1);
+
+                    self.store.update(query, entry).await?;
+                }
+                false => insert!(self.store, entry).await?,
+            }
+
+            usage.quota_stored = true;
+        } else if limits.persistent {
+            let entry = CooldownEntry {
+                command_path: key.0.clone(),
+                scope_id: key.1,
+                expires_at: usage.expires_at,
+            };
+
+            match usage.cooldown_stored {
+                true => {
+                    let query = self
+                        .store
+                        .make_query::<CooldownEntry>()
+                        .command_path(key.0.clone())
+                        .scope_id(key.1);
+
+                    self.store.update(query, entry).await?;
+                }
+                false => insert!(self.store, entry).await?,
+            }
+
+            usage.cooldown_stored = true;
+        }
+
+        Ok(())
+    }
+
+    /// Removes all cooldowns expired and all quotas of days before `now`,
+    /// both from the cache and the store. Uses wait until the cleanup is
+    /// done.
+    pub async fn cleanup(&self, now: i64) -> Result<(), Error> {
+        let _cleanup = self.cleanup.write().await;
+        let day = now.div_euclid(DAY);
+
+        // No use is being recorded, all locks of the keys are free.
+        let usages: Vec<_> = {
+            let mut usages = self.usages.lock();
+            usages.retain(|_, usage| match usage.try_lock() {
+                Ok(usage) => usage.is_some_and(|usage| usage.is_active(now)),
+                Err(_) => true,
+            });
+
+            usages
+                .iter()
+                .map(|(key, usage)| (key.clone(), usage.clone()))
+                .collect()
+        };
+
+        let expired: Vec<CooldownEntry> = self
+            .store
+            .get_all(self.store.make_descriptor::<CooldownEntry>())
+            .await?
+            .into_iter()
+            .filter(|entry: &CooldownEntry| entry.expires_at <= now)
+            .collect();
+
+        let mut timestamps: Vec<i64> = expired.iter().map(|entry| entry.expires_at).collect();
+        timestamps.sort_unstable();
+        timestamps.dedup();
+
+        for chunk in timestamps.chunks(CHUNK_SIZE) {
+            let timestamps = chunk.to_vec();
+
+            delete!(self.store, CooldownEntry => {
+                expires_at in timestamps,
+            })
+            .await?;
+        }
+
+        // Rows of past days still holding a cooldown are kept.
+        let stale: Vec<QuotaEntry> = self
+            .store
+            .get_all(self.store.make_descriptor::<QuotaEntry>())
+            .await?
+            .into_iter()
+            .filter(|entry: &QuotaEntry| entry.day < day && entry.expires_at <= now)
+            .collect();
+
+        let mut days: Vec<i64> = stale.iter().map(|entry| entry.day).collect();
+        days.sort_unstable();
+        days.dedup();
+
+        let mut timestamps: Vec<i64> = stale.iter().map(|entry| entry.expires_at).collect();
+        timestamps.sort_unstable();
+        timestamps.dedup();
+
+        // Matches exactly the stale rows: rows of past days with a cooldown
+        // after `now` never match one of the expired `timestamps`.
+        for days in days.chunks(CHUNK_SIZE) {
+            for timestamps in timestamps.chunks(CHUNK_SIZE) {
+                let days = days.to_vec();
+                let timestamps = timestamps.to_vec();
+
+                delete!(self.store, QuotaEntry => {
+                    day in days,
+                    expires_at in timestamps,
+                })
+                .await?;
+            }
+        }
+
+        let deleted_cooldowns: HashSet<Key> = expired
+            .into_iter()
+            .map(|entry| (entry.command_path, entry.scope_id))
+            .collect();
+        let deleted_quotas: HashSet<Key> = stale
+            .into_iter()
+            .map(|entry| (entry.command_path, entry.scope_id))
+            .collect();
+
+        for (key, usage) in usages {
+            if let Some(usage) = usage.lock().await.as_mut() {
+                usage.cooldown_stored &= !deleted_cooldowns.contains(&key);
+                usage.quota_stored &= !deleted_quotas.contains(&key);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Acquire, CooldownEntry, CooldownHandler, Limits, QuotaEntry};
+    use crate::store::mem::MemStore;
+    use crate::store::StoreHandler;
+    use crate::time::DAY;
+
+    use chrono::Duration;
+    use robbot::store::{create, get, insert};
+
+    async fn store() -> (StoreHandler, MemStore) {
+        let mem = MemStore::default();
+        let store = StoreHandler::new_in_memory(mem.clone());
+        create!(store, CooldownEntry).await.unwrap();
+        create!(store, QuotaEntry).await.unwrap();
+
+        (store, mem)
+    }
+
+    fn cooldown(secs: i64, persistent: bool) -> Limits {
+        Limits {
+            cooldown: Some(Duration::seconds(secs)),
+            persistent,
+            quota: None,
+        }
+    }
+
+    fn quota(quota: u64) -> Limits {
+        Limits {
+            quota: Some(quota),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cooldown() {
+        let (store, _) = store().await;
+        let handler = CooldownHandler::new(store);
+        let limits = cooldown(60, false);
+
+        assert_eq!(
+            handler.acquire("claim", 1, limits, 1000).await.unwrap(),
+            Acquire::Allowed
+        );
+        assert_eq!(
+            handler.acquire("claim", 1, limits, 1059).await.unwrap(),
+            Acquire::Cooldown { expires_at: 1060 }
+        );
+        // Other users and commands have their own cooldowns.
+        assert_eq!(
+            handler.acquire("claim", 2, limits, 1001).await.unwrap(),
+            Acquire::Allowed
+        );
+        assert_eq!(
+            handler.acquire("other", 1, limits, 1001).await.unwrap(),
+            Acquire::Allowed
+        );
+        assert_eq!(
+            handler.acquire("claim", 1, limits, 1060).await.unwrap(),
+            Acquire::Allowed
+        );
+
+        // Commands without limits are always allowed.
+        for _ in 0..3 {
+            assert_eq!(
+                handler
+                    .acquire("free", 1, Limits::default(), 1000)
+                    .await
+                    .unwrap(),
+                Acquire::Allowed
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cooldown_restart() {
+        let (store, _) = store().await;
+
+        let handler = CooldownHandler::new(store.clone());
+        handler
+            .acquire("claim", 1, cooldown(DAY, true), 1000)
+            .await
+            .unwrap();
+        handler
+            .acquire("slow", 1, cooldown(DAY, false), 1000)
+            .await
+            .unwrap();
+
+        // Only persistent cooldowns survive a restart.
+        let handler = CooldownHandler::new(store);
+        assert_eq!(
+            handler
+                .acquire("claim", 1, cooldown(DAY, true), 2000)
+                .await
+                .unwrap(),
+            Acquire::Cooldown {
+                expires_at: 1000 + DAY
+            }
+        );
+        assert_eq!(
+            handler
+                .acquire("slow", 1, cooldown(DAY, false), 2000)
+                .await
+                .unwrap(),
+            Acquire::Allowed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cooldown_cache() {
+        let (store, mem) = store().await;
+        let handler = CooldownHandler::new(store);
+        let limits = cooldown(60, true);
+
+        assert_eq!(
+            handler.acquire("claim", 1, limits, 1000).await.unwrap(),
+            Acquire::Allowed
+        );
+        assert_eq!(
+            handler.acquire("claim", 1, limits, 1060).await.unwrap(),
+            Acquire::Allowed
+        );
+
+        // The store is only queried on the first use, later changes are not
+        // seen.
+        insert!(
+            mem,
+            CooldownEntry {
+                command_path: String::from("claim"),
+                scope_id: 2,
+                expires_at: 5000,
+            }
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            handler.acquire("claim", 2, limits, 1000).await.unwrap(),
+            Acquire::Cooldown { expires_at: 5000 }
+        );
+
+        let handler = CooldownHandler::new(StoreHandler::new_in_memory(mem.clone()));
+        assert_eq!(
+            handler.acquire("claim", 3, limits, 1000).await.unwrap(),
+            Acquire::Allowed
+        );
+        insert!(
+            mem,
+            CooldownEntry {
+                command_path: String::from("claim"),
+                scope_id: 3,
+                expires_at: 9000,
+            }
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            handler.acquire("claim", 3, limits, 1100).await.unwrap(),
+            Acquire::Allowed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_quota() {
+        let (store, _) = store().await;
+        let handler = CooldownHandler::new(store.clone());
+        let day = 100 * DAY;
+
+        for _ in 0..2 {
+            assert_eq!(
+                handler.acquire("claim", 1, quota(2), day).await.unwrap(),
+                Acquire::Allowed
+            );
+        }
+
+        // Quotas survive a restart.
+        let handler = CooldownHandler::new(store);
+        assert_eq!(
+            handler
+                .acquire("claim", 1, quota(2), day + DAY - 1)
+                .await
+                .unwrap(),
+            Acquire::Quota {
+                resets_at: day + DAY
+            }
+        );
+        assert_eq!(
+            handler.acquire("claim", 2, quota(2), day).await.unwrap(),
+            Acquire::Allowed
+        );
+
+        // Quotas reset at midnight UTC.
+        assert_eq!(
+            handler
+                .acquire("claim", 1, quota(2), day + DAY)
+                .await
+                .unwrap(),
+            Acquire::Allowed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cooldown_and_quota() {
+        let (store, _) = store().await;
+        let handler = CooldownHandler::new(store);
+        let limits = Limits {
+            quota: Some(1),
+            ..cooldown(60, true)
+        };
+
+        assert_eq!(
+            handler.acquire("claim", 1, limits, 1000).await.unwrap(),
+            Acquire::Allowed
+        );
+        assert_eq!(
+            handler.acquire("claim", 1, limits, 1100).await.unwrap(),
+            Acquire::Quota { resets_at: DAY }
+        );
+        // A rejected use does not start a new cooldown.
+        assert_eq!(
+            handler.acquire("claim", 1, limits, DAY).await.unwrap(),
+            Acquire::Allowed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cleanup() {
+        let (store, _) = store().await;
+        let handler = CooldownHandler::new(store.clone());
+
+        handler
+            .acquire("a", 1, cooldown(60, true), 1000)
+            .await
+            .unwrap();
+        handler
+            .acquire("b", 1, cooldown(600, true), 1000)
+            .await
+            .unwrap();
+        handler.acquire("c", 1, quota(1), 1000).await.unwrap();
+
+        handler.cleanup(DAY + 100).await.unwrap();
+        assert!(get!(store, CooldownEntry).await.unwrap().is_empty());
+        assert!(get!(store, QuotaEntry).await.unwrap().is_empty());
+
+        handler
+            .acquire("a", 1, cooldown(60, true), DAY + 100)
+            .await
+            .unwrap();
+        handler.cleanup(DAY + 120).await.unwrap();
+        assert_eq!(get!(store, CooldownEntry).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cooldown_degraded() {
+        let mem = MemStore::default();
+        create!(mem, QuotaEntry).await.unwrap();
+
+        let handler = CooldownHandler::new(StoreHandler::new_degraded("", mem.clone()));
+        let limits = quota(1);
+
+        // Quotas are still enforced, but not written to the store.
+        assert_eq!(
+            handler.acquire("claim", 1, limits, 1000).await.unwrap(),
+            Acquire::Allowed
+        );
+        assert_eq!(
+            handler.acquire("claim", 1, limits, 1000).await.unwrap(),
+            Acquire::Quota { resets_at: DAY }
+        );
+        assert!(get!(mem, QuotaEntry).await.unwrap().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_acquire_concurrent() {
+        let (store, _) = store().await;
+        let handler = CooldownHandler::new(store.clone());
+        let limits = Limits {
+            quota: Some(3),
+            ..Default::default()
+        };
+
+        // Concurrent uses on a cold cache must not pass the quota.
+        let tasks: Vec<_> = (0..16)
+            .map(|_| {
+                let handler = handler.clone();
+                tokio::spawn(async move { handler.acquire("claim", 1, limits, 1000).await })
+            })
+            .collect();
+
+        let mut allowed = 0;
+        for task in tasks {
+            if task.await.unwrap().unwrap() == Acquire::Allowed {
+                allowed += 1;
+            }
+        }
+        assert_eq!(allowed, 3);
+
+        let entries = get!(store, QuotaEntry).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].count, 3);
+
+        // Two concurrent uses of a persistent cooldown.
+        let handler = CooldownHandler::new(store);
+        let limits = cooldown(60, true);
+        let (a, b) = tokio::join!(
+            handler.acquire("slow", 1, limits, 1000),
+            handler.acquire("slow", 1, limits, 1000),
+        );
+
+        let mut results = [a.unwrap(), b.unwrap()];
+        results.sort_by_key(|result| *result != Acquire::Allowed);
+        assert_eq!(
+            results,
+            [Acquire::Allowed, Acquire::Cooldown { expires_at: 1060 }]
+        );
+    }
+}
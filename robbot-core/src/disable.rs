@@ -0,0 +1,215 @@
+//! Commands, hooks and tasks can be disabled at runtime. Hooks and tasks are
+//! disabled automatically when they panic, all of them can be disabled and
+//! re-enabled manually.
+//!
+//! Every handler keeps a [`DisabledSet`] recording the [`DisableReason`] of
+//! each disabled entry until it is enabled again.
+use robbot::model::id::UserId;
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use tokio::sync::broadcast;
+
+use std::any::Any;
+use std::collections::HashMap;
+
+/// The number of unreceived [`DisableEvent`]s kept for slow subscribers.
+const EVENT_QUEUE_SIZE: usize = 16;
+
+/// Who or what disabled an entry.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DisabledBy {
+    /// Disabled by the bot, e.g. after a panic.
+    Automatic,
+    /// Disabled manually by a user.
+    User(UserId),
+}
+
+/// Why and when an entry was disabled.
+#[derive(Clone, Debug)]
+pub struct DisableReason {
+    pub by: DisabledBy,
+    pub at: DateTime<Utc>,
+    /// The panic message for automatic disables, the command used otherwise.
+    pub detail: String,
+}
+
+impl DisableReason {
+    /// Creates a new `DisableReason` for an automatic disable.
+    pub fn automatic<T>(detail: T) -> Self
+    where
+        T: ToString,
+    {
+        Self {
+            by: DisabledBy::Automatic,
+            at: Utc::now(),
+            detail: detail.to_string(),
+        }
+    }
+
+    /// Creates a new `DisableReason` for an entry disabled by `user_id`.
+    pub fn manual<T>(user_id: UserId, detail: T) -> Self
+    where
+        T: ToString,
+    {
+        Self {
+            by: DisabledBy::User(user_id),
+            at: Utc::now(),
+            detail: detail.to_string(),
+        }
+    }
+
+    /// Creates a new automatic `DisableReason` from the payload of a panic.
+    pub fn panic(payload: &(dyn Any + Send)) -> Self {
+        let message = match payload.downcast_ref::<&str>() {
+            Some(message) => message,
+            None => match payload.downcast_ref::<String>() {
+                Some(message) => message.as_str(),
+                None => "Box<dyn Any>",
+            },
+        };
+
+        Self::automatic(format!("panicked: {}", message))
+    }
+
+    pub fn is_automatic(&self) -> bool {
+        self.by == DisabledBy::Automatic
+    }
+}
+
+/// Sent to subscribers of a [`DisabledSet`] when an entry is disabled.
+#[derive(Clone, Debug)]
+pub struct DisableEvent {
+    pub name: String,
+    pub reason: DisableReason,
+}
+
+/// The disabled entries of a handler, by name.
+#[derive(Debug)]
+pub struct DisabledSet {
+    entries: RwLock<HashMap<String, DisableReason>>,
+    events: broadcast::Sender<DisableEvent>,
+}
+
+impl DisabledSet {
+    /// Creates a new, empty `DisabledSet`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Disables the entry `name`. Returns `false` if the entry is already
+    /// disabled, the original reason is kept in that case.
+    pub fn disable(&self, name: &str, reason: DisableReason) -> bool {
+        {
+            let mut entries = self.entries.write();
+            if entries.contains_key(name) {
+                return false;
+            }
+
+            entries.insert(name.to_owned(), reason.clone());
+        }
+
+        log::warn!("[CORE] Disabled '{}': {}", name, reason.detail);
+
+        // Nobody may be listening.
+        let _ = self.events.send(DisableEvent {
+            name: name.to_owned(),
+            reason,
+        });
+
+        true
+    }
+
+    /// Enables the entry `name` and returns the reason it was disabled for.
+    /// Returns `None` if the entry was not disabled.
+    pub fn enable(&self, name: &str) -> Option<DisableReason> {
+        self.entries.write().remove(name)
+    }
+
+    /// Returns the reason the entry `name` is disabled for. Returns `None`
+    /// if the entry is not disabled.
+    pub fn get(&self, name: &str) -> Option<DisableReason> {
+        self.entries.read().get(name).cloned()
+    }
+
+    /// Returns `true` if the entry `name` is disabled.
+    pub fn is_disabled(&self, name: &str) -> bool {
+        self.entries.read().contains_key(name)
+    }
+
+    /// Returns all disabled entries, ordered by name.
+    pub fn list(&self) -> Vec<(String, DisableReason)> {
+        let mut entries: Vec<_> = self
+            .entries
+            .read()
+            .iter()
+            .map(|(name, reason)| (name.clone(), reason.clone()))
+            .collect();
+
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+
+    /// Returns a receiver for all entries disabled from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<DisableEvent> {
+        self.events.subscribe()
+    }
+}
+
+impl Default for DisabledSet {
+    fn default() -> Self {
+        Self {
+            entries: RwLock::default(),
+            events: broadcast::channel(EVENT_QUEUE_SIZE).0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DisableReason, DisabledBy, DisabledSet};
+
+    use robbot::model::id::UserId;
+
+    #[test]
+    fn test_disabled_set() {
+        let set = DisabledSet::new();
+        let mut rx = set.subscribe();
+
+        assert!(!set.is_disabled("test"));
+        assert!(set.disable("test", DisableReason::manual(UserId(1), "disable test")));
+
+        // The first reason is kept.
+        assert!(!set.disable("test", DisableReason::automatic("panicked")));
+
+        let reason = set.get("test").unwrap();
+        assert_eq!(reason.by, DisabledBy::User(UserId(1)));
+        assert_eq!(reason.detail, "disable test");
+
+        let event = rx.try_recv().unwrap();
+        assert_eq!(event.name, "test");
+        assert!(rx.try_recv().is_err());
+
+        assert_eq!(set.list().len(), 1);
+
+        assert_eq!(set.enable("test").unwrap().detail, "disable test");
+        assert!(!set.is_disabled("test"));
+        assert!(set.get("test").is_none());
+        assert!(set.enable("test").is_none());
+    }
+
+    #[test]
+    fn test_disable_reason_panic() {
+        let payload = std::panic::catch_unwind(|| panic!("oh no")).unwrap_err();
+        let reason = DisableReason::panic(payload.as_ref());
+
+        assert!(reason.is_automatic());
+        assert_eq!(reason.detail, "panicked: oh no");
+
+        let payload = std::panic::catch_unwind(|| panic!("{}", 42)).unwrap_err();
+        assert_eq!(
+            DisableReason::panic(payload.as_ref()).detail,
+            "panicked: 42"
+        );
+    }
+}
@@ -1 +1,102 @@
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use tokio::time;
+
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+
 pub type Executor<T> = robbot::executor::Executor<T>;
+
+/// The time hooks and tasks may run before they are given up, read from the
+/// `[timeouts]` section of the config file. Timeouts are in seconds, `0`
+/// disables the timeout. Tasks and hooks can override the default with the
+/// `timeout` argument of their attribute.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timeouts {
+    #[serde(default = "Timeouts::default_task")]
+    pub task: u64,
+    #[serde(default = "Timeouts::default_hook")]
+    pub hook: u64,
+}
+
+impl Timeouts {
+    fn default_task() -> u64 {
+        10 * 60
+    }
+
+    fn default_hook() -> u64 {
+        30
+    }
+
+    /// Returns the default timeout of tasks.
+    pub fn task(&self) -> Option<Duration> {
+        (self.task != 0).then(|| Duration::from_secs(self.task))
+    }
+
+    /// Returns the default timeout of hooks.
+    pub fn hook(&self) -> Option<Duration> {
+        (self.hook != 0).then(|| Duration::from_secs(self.hook))
+    }
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            task: Self::default_task(),
+            hook: Self::default_hook(),
+        }
+    }
+}
+
+/// The outcome of a run of a hook or task, see [`guard`].
+pub(crate) enum Outcome {
+    Completed(robbot::Result),
+    Panicked(Box<dyn Any + Send>),
+    /// The run did not complete within the timeout and was dropped.
+    TimedOut(Duration),
+}
+
+/// Runs `future` to completion, catching panics. The future is dropped once
+/// it runs longer than `timeout`, `None` never drops it.
+pub(crate) async fn guard<F>(future: F, timeout: Option<Duration>) -> Outcome
+where
+    F: Future<Output = robbot::Result>,
+{
+    let future = AssertUnwindSafe(future).catch_unwind();
+
+    let res = match timeout {
+        Some(timeout) => match time::timeout(timeout, future).await {
+            Ok(res) => res,
+            Err(_) => return Outcome::TimedOut(timeout),
+        },
+        None => future.await,
+    };
+
+    match res {
+        Ok(res) => Outcome::Completed(res),
+        Err(payload) => Outcome::Panicked(payload),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{guard, Outcome};
+
+    use futures::future;
+
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_guard() {
+        let outcome = guard(future::ready(Ok(())), Some(Duration::from_secs(1))).await;
+        assert!(matches!(outcome, Outcome::Completed(Ok(()))));
+
+        let outcome = guard(future::pending(), Some(Duration::from_secs(30))).await;
+        assert!(matches!(outcome, Outcome::TimedOut(timeout) if timeout.as_secs() == 30));
+
+        let outcome = guard(async { panic!("test") }, None).await;
+        assert!(matches!(outcome, Outcome::Panicked(_)));
+    }
+}
@@ -0,0 +1,106 @@
+use parking_lot::RwLock;
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+/// A typed container for data attached to a single command invocation.
+///
+/// `Extensions` stores at most one value per type. It is created for every
+/// invocation and shared by all clones of the [`Context`], which allows
+/// [`CommandMiddleware`]s to pass data to each other and to the executor
+/// without computing it twice.
+///
+/// [`Context`]: crate::context::Context
+/// [`CommandMiddleware`]: crate::middleware::CommandMiddleware
+#[derive(Clone, Default)]
+pub struct Extensions {
+    map: Arc<RwLock<HashMap<TypeId, Box<dyn Any + Send + Sync>>>>,
+}
+
+impl Extensions {
+    /// Creates a new, empty `Extensions` container.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts `value`, returning the previous value of the same type.
+    pub fn insert<T>(&self, value: T) -> Option<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map
+            .write()
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Returns a clone of the value of type `T`.
+    pub fn get<T>(&self) -> Option<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.map
+            .read()
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+            .cloned()
+    }
+
+    /// Returns `true` if a value of type `T` is present.
+    pub fn contains<T>(&self) -> bool
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map.read().contains_key(&TypeId::of::<T>())
+    }
+
+    /// Removes and returns the value of type `T`.
+    pub fn remove<T>(&self) -> Option<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map
+            .write()
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+}
+
+impl Debug for Extensions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.read().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Extensions;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Marker(u64);
+
+    #[test]
+    fn test_extensions() {
+        let extensions = Extensions::new();
+        assert_eq!(extensions.get::<Marker>(), None);
+
+        assert_eq!(extensions.insert(Marker(1)), None);
+        assert_eq!(extensions.insert(Marker(2)), Some(Marker(1)));
+        assert_eq!(extensions.insert(3u64), None);
+
+        // Clones share the same values.
+        let clone = extensions.clone();
+        assert_eq!(clone.get::<Marker>(), Some(Marker(2)));
+        assert_eq!(clone.get::<u64>(), Some(3));
+        assert!(!clone.contains::<u32>());
+
+        assert_eq!(clone.remove::<Marker>(), Some(Marker(2)));
+        assert!(!extensions.contains::<Marker>());
+    }
+}
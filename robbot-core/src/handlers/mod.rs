@@ -1,5 +1,3 @@
-mod message;
-
 use crate::command::SubCommand;
 
 use robbot::arguments::OwnedArguments;
@@ -9,7 +7,7 @@ use std::error::Error as StdError;
 #[derive(Debug)]
 pub enum Error {
     UnknownCommand(OwnedArguments),
-    InvalidCommandUsage(SubCommand, OwnedArguments),
+    InvalidCommandUsage(Box<SubCommand>, OwnedArguments),
     GuildOnly,
     #[cfg(feature = "permissions")]
     NoPermission,
@@ -1,14 +1,25 @@
 use crate::context::Context;
+use crate::disable::{DisableReason, DisabledSet};
+use crate::executor::{guard, Outcome};
 
 use robbot::executor::Executor;
+use robbot::hook::{
+    ChannelCreateData, GuildMemberAdditionData, GuildMemberRemovalData, GuildMemberUpdateData,
+    MessageData, MessageDeleteData, ReactionAddData, ReactionRemoveAllData, ReactionRemoveData,
+};
 use robbot::hook::{EventData, EventKind, HookEvent};
-use robbot::hook::{GuildMemberUpdateData, MessageData};
+use robbot::model::InvalidModelData;
 
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
 use tokio::sync::{broadcast, mpsc, oneshot};
 use tokio::task;
+use tokio::time::{self, Instant};
 
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 const QUEUE_SIZE: usize = 32;
 
@@ -18,12 +29,131 @@ pub struct Hook {
     pub on_event: EventKind,
 }
 
+/// An alias for `Context<ChannelCreateData>`.
+pub type ChannelCreateContext = Context<ChannelCreateData>;
+
 /// An alias for `Context<MessageData>`.
 pub type MessageContext = Context<MessageData>;
 
+/// An alias for `Context<GuildMemberAdditionData>`.
+pub type GuildMemberAdditionContext = Context<GuildMemberAdditionData>;
+
+/// An alias for `Context<GuildMemberRemovalData>`.
+pub type GuildMemberRemovalContext = Context<GuildMemberRemovalData>;
+
 /// An alias for `Context<GuildMemberUpdateContext>`.
 pub type GuildMemberUpdateContext = Context<GuildMemberUpdateData>;
 
+/// An alias for `Context<MessageDeleteData>`.
+pub type MessageDeleteContext = Context<MessageDeleteData>;
+
+/// An alias for `Context<ReactionAddData>`.
+pub type ReactionAddContext = Context<ReactionAddData>;
+
+/// An alias for `Context<ReactionRemoveData>`.
+pub type ReactionRemoveContext = Context<ReactionRemoveData>;
+
+/// An alias for `Context<ReactionRemoveAllData>`.
+pub type ReactionRemoveAllContext = Context<ReactionRemoveAllData>;
+
+/// An alias for `Context<Vec<ReactionAddData>>`, see [`BatchHookExecutor`].
+pub type ReactionAddBatchContext = Context<Vec<ReactionAddData>>;
+
+/// An alias for `Context<Vec<ReactionRemoveData>>`, see [`BatchHookExecutor`].
+pub type ReactionRemoveBatchContext = Context<Vec<ReactionRemoveData>>;
+
+/// The context of a hook handling batches of events, `Context<Vec<T>>`.
+pub trait BatchEventWrapper {
+    type HookEvent: HookEvent;
+}
+
+impl<T> BatchEventWrapper for Context<Vec<T>>
+where
+    T: Send + Sync + HookEvent,
+{
+    type HookEvent = T;
+}
+
+/// How events are collected into batches for hooks declared with
+/// `#[hook(batch = true)]`, read from the `[batching]` section of the config
+/// file.
+///
+/// A batch starts with the next event and collects the events arriving
+/// within `window` milliseconds, up to `max_size` events. Events already
+/// queued are added without waiting, so a window of `0` still batches the
+/// events of a burst the hook fell behind on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Batching {
+    #[serde(default = "Batching::default_window")]
+    pub window: u64,
+    #[serde(default = "Batching::default_max_size")]
+    pub max_size: usize,
+}
+
+impl Batching {
+    fn default_window() -> u64 {
+        10
+    }
+
+    fn default_max_size() -> usize {
+        100
+    }
+
+    /// Receives the next batch of events from `rx`. Returns `None` once the
+    /// channel is closed and all events were received. `name` identifies the
+    /// hook when events are skipped.
+    pub async fn recv<T>(&self, rx: &mut broadcast::Receiver<T>, name: &str) -> Option<Vec<T>>
+    where
+        T: Clone,
+    {
+        let first = loop {
+            match rx.recv().await {
+                Ok(event) => break event,
+                // Keep running after falling behind, only the missed events are lost.
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("[HOOK] Hook '{}' skipped {} events", name, skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        };
+
+        let deadline = Instant::now() + Duration::from_millis(self.window);
+        let mut batch = vec![first];
+
+        while batch.len() < self.max_size {
+            let event = match rx.try_recv() {
+                Ok(event) => event,
+                Err(TryRecvError::Empty) => match time::timeout_at(deadline, rx.recv()).await {
+                    Ok(Ok(event)) => event,
+                    Ok(Err(RecvError::Lagged(skipped))) => {
+                        log::warn!("[HOOK] Hook '{}' skipped {} events", name, skipped);
+                        continue;
+                    }
+                    Ok(Err(RecvError::Closed)) | Err(_) => break,
+                },
+                Err(TryRecvError::Lagged(skipped)) => {
+                    log::warn!("[HOOK] Hook '{}' skipped {} events", name, skipped);
+                    continue;
+                }
+                Err(TryRecvError::Closed) => break,
+            };
+
+            batch.push(event);
+        }
+
+        Some(batch)
+    }
+}
+
+impl Default for Batching {
+    fn default() -> Self {
+        Self {
+            window: Self::default_window(),
+            max_size: Self::default_max_size(),
+        }
+    }
+}
+
 struct InnerHookController {
     hooks: Vec<Hook>,
     channels: HashMap<EventKind, broadcast::Sender<(EventData, Context<()>)>>,
@@ -113,15 +243,23 @@ enum Message {
 #[derive(Debug)]
 pub struct HookController {
     tx: mpsc::Sender<Message>,
+    disabled: DisabledSet,
 }
 
 impl HookController {
     pub fn new(ctx: Arc<RwLock<Option<Context<()>>>>) -> Self {
         Self {
             tx: InnerHookController::new(ctx).start(),
+            disabled: DisabledSet::new(),
         }
     }
 
+    /// Returns the disabled hooks, by name. Hooks are disabled automatically
+    /// when they panic.
+    pub fn disabled(&self) -> &DisabledSet {
+        &self.disabled
+    }
+
     pub async fn add_hook(&self, hook: Hook) -> broadcast::Receiver<(EventData, Context<()>)> {
         let event = hook.on_event;
         let _ = self.tx.send(Message::AddHook(hook)).await;
@@ -155,35 +293,270 @@ pub struct HookExecutor<T>
 where
     T: HookEvent + Send + Sync + 'static,
 {
+    name: String,
     rx: broadcast::Receiver<(EventData, Context<()>)>,
     executor: Executor<Context<T>>,
+    timeout: Option<Duration>,
 }
 
 impl<T> HookExecutor<T>
 where
     T: HookEvent + Send + Sync + 'static,
 {
-    pub fn new(
+    pub fn new<N>(
+        name: N,
         rx: broadcast::Receiver<(EventData, Context<()>)>,
         executor: Executor<Context<T>>,
-    ) -> Self {
-        Self { rx, executor }
+    ) -> Self
+    where
+        N: ToString,
+    {
+        Self {
+            name: name.to_string(),
+            rx,
+            executor,
+            timeout: None,
+        }
+    }
+
+    /// Sets the time the hook may take to handle an event. `None` uses the
+    /// default of the config, see [`Timeouts`].
+    ///
+    /// [`Timeouts`]: crate::executor::Timeouts
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
     }
 
     pub fn run(mut self) {
         tokio::task::spawn(async move {
-            while let Ok((data, ctx)) = self.rx.recv().await {
+            loop {
+                let (data, ctx) = match self.rx.recv().await {
+                    Ok(event) => event,
+                    // Keep running after falling behind, only the missed events are lost.
+                    Err(RecvError::Lagged(skipped)) => {
+                        log::warn!("[HOOK] Hook '{}' skipped {} events", self.name, skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
                 if let Ok(event) = T::try_from(data) {
+                    let state = ctx.state.clone();
+                    if state.hooks().disabled().is_disabled(&self.name) {
+                        continue;
+                    }
+
+                    let timeout = match self.timeout {
+                        Some(timeout) => Some(timeout),
+                        None => state.config.timeouts.hook(),
+                    };
+
                     let (ctx, _) = ctx.swap(event);
 
-                    match self.executor.call(ctx).await {
-                        Ok(_) => (),
-                        Err(err) => {
-                            log::error!("Hook failed to execute: {:?}", err);
-                        }
+                    run(
+                        &self.name,
+                        self.executor.call(ctx),
+                        timeout,
+                        state.hooks().disabled(),
+                    )
+                    .await;
+                }
+            }
+        });
+    }
+}
+
+/// Runs a hook handling batches of events, see [`Batching`]. Hooks opt in
+/// with `#[hook(batch = true)]` and take a `Context<Vec<T>>`, e.g.
+/// [`ReactionAddBatchContext`]. Other hooks of the same event still receive
+/// every event on its own. The timeout applies to a whole batch.
+pub struct BatchHookExecutor<T>
+where
+    T: HookEvent + Send + Sync + 'static,
+{
+    name: String,
+    rx: broadcast::Receiver<(EventData, Context<()>)>,
+    executor: Executor<Context<Vec<T>>>,
+    batching: Batching,
+    timeout: Option<Duration>,
+}
+
+impl<T> BatchHookExecutor<T>
+where
+    T: HookEvent + Send + Sync + 'static,
+{
+    pub fn new<N>(
+        name: N,
+        rx: broadcast::Receiver<(EventData, Context<()>)>,
+        executor: Executor<Context<Vec<T>>>,
+        batching: Batching,
+    ) -> Self
+    where
+        N: ToString,
+    {
+        Self {
+            name: name.to_string(),
+            rx,
+            executor,
+            batching,
+            timeout: None,
+        }
+    }
+
+    /// Sets the time the hook may take to handle a batch. `None` uses the
+    /// default of the config, see [`Timeouts`].
+    ///
+    /// [`Timeouts`]: crate::executor::Timeouts
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn run(mut self) {
+        tokio::task::spawn(async move {
+            while let Some(batch) = self.batching.recv(&mut self.rx, &self.name).await {
+                let mut ctx = None;
+                let mut events = Vec::with_capacity(batch.len());
+                for (data, event_ctx) in batch {
+                    if let Ok(event) = T::try_from(data) {
+                        events.push(event);
+                        ctx = Some(event_ctx);
                     }
                 }
+
+                let ctx = match ctx {
+                    Some(ctx) => ctx,
+                    None => continue,
+                };
+
+                let state = ctx.state.clone();
+                if state.hooks().disabled().is_disabled(&self.name) {
+                    continue;
+                }
+
+                let timeout = match self.timeout {
+                    Some(timeout) => Some(timeout),
+                    None => state.config.timeouts.hook(),
+                };
+
+                let (ctx, _) = ctx.swap(events);
+
+                run(
+                    &self.name,
+                    self.executor.call(ctx),
+                    timeout,
+                    state.hooks().disabled(),
+                )
+                .await;
             }
         });
     }
 }
+
+/// Runs a hook for a single event or batch. A panic would otherwise end the loop and
+/// silently stop the hook, hooks that panic are disabled instead. A hook
+/// running longer than `timeout` is dropped and handles the next event.
+async fn run<F>(name: &str, future: F, timeout: Option<Duration>, disabled: &DisabledSet)
+where
+    F: Future<Output = robbot::Result>,
+{
+    match guard(future, timeout).await {
+        Outcome::Completed(Ok(())) => (),
+        // Events the models cannot represent are skipped.
+        Outcome::Completed(Err(robbot::Error::Other(err))) if err.is::<InvalidModelData>() => {
+            log::warn!("[HOOK] Hook '{}' skipped an event: {}", name, err);
+        }
+        Outcome::Completed(Err(err)) => {
+            log::error!("Hook failed to execute: {:?}", err);
+        }
+        Outcome::Panicked(payload) => {
+            let reason = DisableReason::panic(payload.as_ref());
+            disabled.disable(name, reason);
+        }
+        Outcome::TimedOut(timeout) => {
+            log::error!("[HOOK] Hook '{}' timed out after {:?}", name, timeout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run, Batching};
+    use crate::disable::DisabledSet;
+
+    use futures::future;
+    use tokio::sync::broadcast;
+
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_hook_run_timeout() {
+        let disabled = DisabledSet::new();
+        let timeout = Some(Duration::from_secs(30));
+
+        // Completes once the timeout elapsed on the paused clock.
+        run("a", future::pending(), timeout, &disabled).await;
+        assert!(!disabled.is_disabled("a"));
+
+        run("a", async { panic!("test") }, timeout, &disabled).await;
+        assert!(disabled.is_disabled("a"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_batching_recv() {
+        let batching = Batching {
+            window: 10,
+            max_size: 3,
+        };
+        let (tx, mut rx) = broadcast::channel(16);
+
+        for event in 0..5 {
+            tx.send(event).unwrap();
+        }
+
+        // Queued events are batched up to the maximum size.
+        assert_eq!(batching.recv(&mut rx, "a").await, Some(vec![0, 1, 2]));
+        assert_eq!(batching.recv(&mut rx, "a").await, Some(vec![3, 4]));
+
+        // Events arriving within the window join the batch, later ones start
+        // the next batch.
+        let sender = tx.clone();
+        tokio::spawn(async move {
+            sender.send(5).unwrap();
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            sender.send(6).unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            sender.send(7).unwrap();
+        });
+        assert_eq!(batching.recv(&mut rx, "a").await, Some(vec![5, 6]));
+        assert_eq!(batching.recv(&mut rx, "a").await, Some(vec![7]));
+
+        // A window of zero only takes the queued events.
+        let batching = Batching {
+            window: 0,
+            max_size: 100,
+        };
+        tx.send(8).unwrap();
+        tx.send(9).unwrap();
+        assert_eq!(batching.recv(&mut rx, "a").await, Some(vec![8, 9]));
+
+        drop(tx);
+        assert_eq!(batching.recv(&mut rx, "a").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_batching_recv_lagged() {
+        let batching = Batching::default();
+        let (tx, mut rx) = broadcast::channel(4);
+
+        for event in 0..6 {
+            tx.send(event).unwrap();
+        }
+        drop(tx);
+
+        // The oldest events were overwritten, the rest is still received.
+        assert_eq!(batching.recv(&mut rx, "a").await, Some(vec![2, 3, 4, 5]));
+        assert_eq!(batching.recv(&mut rx, "a").await, None);
+    }
+}
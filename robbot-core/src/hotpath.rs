@@ -0,0 +1,452 @@
+//! A bounded cache of the per-guild data read for every guild message.
+//!
+//! Most guild messages are not commands, deciding that must neither query the
+//! store nor allocate. The [`HotPathCache`] keeps the [`GuildInfo`] of the
+//! most recently used guilds. Entries are loaded lazily, reloaded once they
+//! are older than the TTL and dropped as soon as the underlying store data
+//! changes (see [`InvalidationBus`]). Reading a cached entry takes a single
+//! read lock.
+//!
+//! [`InvalidationBus`]: crate::store::invalidate::InvalidationBus
+use crate::ignore::IgnoredChannel;
+use crate::settings::{GuildSetting, TimeSettings};
+use crate::store::{Error, StoreHandler};
+
+use robbot::model::id::{ChannelId, GuildId};
+use robbot::store::get;
+
+use parking_lot::RwLock;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The number of guilds cached by default.
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+/// The time after which a cached guild is loaded again by default. Changes
+/// made through the [`StoreHandler`] are visible immediately, the TTL only
+/// bounds how long changes made around it stay invisible.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(600);
+
+/// The resources cached in [`GuildInfo`].
+const RESOURCES: &[&str] = &["IgnoredChannel", "GuildSetting"];
+
+/// The cached data of a guild.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GuildInfo {
+    /// The channels in which messages are ignored, see [`IgnoreHandler`].
+    ///
+    /// [`IgnoreHandler`]: crate::ignore::IgnoreHandler
+    pub ignored_channels: HashSet<ChannelId>,
+    /// The time settings of the guild, see [`SettingsHandler`].
+    ///
+    /// [`SettingsHandler`]: crate::settings::SettingsHandler
+    pub time: TimeSettings,
+}
+
+impl GuildInfo {
+    async fn load(store: &StoreHandler, guild_id: GuildId) -> Result<Self, Error> {
+        let ignored_channels = get!(store, IgnoredChannel => {
+            guild_id == guild_id,
+        })
+        .await?
+        .into_iter()
+        .map(|channel| channel.channel_id)
+        .collect();
+
+        let settings = get!(store, GuildSetting => {
+            guild_id == guild_id,
+        })
+        .await?;
+
+        Ok(Self {
+            ignored_channels,
+            time: TimeSettings::new(&settings),
+        })
+    }
+}
+
+/// The usage statistics of a [`HotPathCache`] since it was created.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// The number of lookups served from the cache.
+    pub hits: u64,
+    /// The number of lookups of guilds that were not cached or expired.
+    pub misses: u64,
+    /// The number of cached guilds, including expired ones.
+    pub len: usize,
+}
+
+#[derive(Debug)]
+struct Entry {
+    info: Arc<GuildInfo>,
+    loaded_at: Instant,
+    /// The key of the entry in [`Entries::order`]. Might be older than
+    /// `last_access`.
+    tick: u64,
+    /// The tick of the last access, used to find the least recently used
+    /// entry. Updated without the write lock.
+    last_access: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    guilds: HashMap<GuildId, Entry>,
+    /// The guilds ordered by the `tick` of their entry.
+    order: BTreeMap<u64, GuildId>,
+    /// The guilds currently loaded from the store with the token of the
+    /// latest load. Invalidating a guild removes it, the load is then not
+    /// cached as it might contain the old data.
+    loading: HashMap<GuildId, u64>,
+}
+
+impl Entries {
+    fn remove(&mut self, guild_id: GuildId) {
+        if let Some(entry) = self.guilds.remove(&guild_id) {
+            self.order.remove(&entry.tick);
+        }
+
+        self.loading.remove(&guild_id);
+    }
+
+    fn clear(&mut self) {
+        self.guilds.clear();
+        self.order.clear();
+        self.loading.clear();
+    }
+
+    /// Removes the least recently used entry.
+    ///
+    /// Entries are ordered by the tick they were inserted or last moved at.
+    /// Entries accessed since are moved to their last access while looking
+    /// for the least recently used one, every access causes at most one
+    /// move.
+    fn evict(&mut self) {
+        while let Some((tick, guild_id)) = self.order.pop_first() {
+            let entry = match self.guilds.get_mut(&guild_id) {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            let last_access = entry.last_access.load(Ordering::Relaxed);
+            if last_access > tick {
+                entry.tick = last_access;
+                self.order.insert(last_access, guild_id);
+                continue;
+            }
+
+            self.guilds.remove(&guild_id);
+            return;
+        }
+    }
+}
+
+/// A least recently used cache of [`GuildInfo`] holding at most `capacity`
+/// guilds.
+///
+/// Recency is tracked with a counter stored in every entry, a hit never needs
+/// the write lock. The order of the entries is only updated when a guild is
+/// loaded into a full cache.
+#[derive(Clone, Debug)]
+pub struct HotPathCache {
+    store: StoreHandler,
+    capacity: usize,
+    ttl: Duration,
+    entries: Arc<RwLock<Entries>>,
+    clock: Arc<AtomicU64>,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+impl HotPathCache {
+    /// Creates a new `HotPathCache` holding up to `capacity` guilds, at least
+    /// one.
+    pub fn new(store: StoreHandler, capacity: usize, ttl: Duration) -> Self {
+        let entries: Arc<RwLock<Entries>> = Arc::default();
+
+        for resource in RESOURCES {
+            let entries = entries.clone();
+
+            store
+                .invalidations()
+                .subscribe(resource, move |invalidation| {
+                    let mut entries = entries.write();
+
+                    match invalidation.guild_id {
+                        Some(guild_id) => entries.remove(guild_id),
+                        None => entries.clear(),
+                    }
+                });
+        }
+
+        Self {
+            store,
+            capacity: capacity.max(1),
+            ttl,
+            entries,
+            clock: Arc::default(),
+            hits: Arc::default(),
+            misses: Arc::default(),
+        }
+    }
+
+    /// Returns the cached data of the guild, or `None` if it is not cached or
+    /// expired. Never touches the store.
+    pub fn get(&self, guild_id: GuildId) -> Option<Arc<GuildInfo>> {
+        let entries = self.entries.read();
+
+        let entry = match entries.guilds.get(&guild_id) {
+            Some(entry) if entry.loaded_at.elapsed() < self.ttl => entry,
+            _ => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+        };
+
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        entry.last_access.store(self.tick(), Ordering::Relaxed);
+        Some(entry.info.clone())
+    }
+
+    /// Returns the data of the guild, loading it from the store if it is not
+    /// cached.
+    pub async fn guild(&self, guild_id: GuildId) -> Result<Arc<GuildInfo>, Error> {
+        if let Some(info) = self.get(guild_id) {
+            return Ok(info);
+        }
+
+        let token = self.begin_load(guild_id);
+
+        match GuildInfo::load(&self.store, guild_id).await {
+            Ok(info) => {
+                let info = Arc::new(info);
+                self.finish_load(guild_id, token, info.clone());
+                Ok(info)
+            }
+            Err(err) => {
+                let mut entries = self.entries.write();
+                if entries.loading.get(&guild_id) == Some(&token) {
+                    entries.loading.remove(&guild_id);
+                }
+
+                Err(err)
+            }
+        }
+    }
+
+    /// Marks the guild as loading. Returns the token passed to
+    /// [`finish_load`].
+    ///
+    /// [`finish_load`]: Self::finish_load
+    fn begin_load(&self, guild_id: GuildId) -> u64 {
+        let token = self.tick();
+        self.entries.write().loading.insert(guild_id, token);
+        token
+    }
+
+    /// Caches `info` unless the guild was invalidated or loaded again since
+    /// the load with `token` began.
+    fn finish_load(&self, guild_id: GuildId, token: u64, info: Arc<GuildInfo>) {
+        let mut entries = self.entries.write();
+        if entries.loading.get(&guild_id) != Some(&token) {
+            return;
+        }
+
+        // Replaces an expired entry.
+        entries.remove(guild_id);
+
+        if entries.guilds.len() >= self.capacity {
+            entries.evict();
+        }
+
+        let tick = self.tick();
+        entries.order.insert(tick, guild_id);
+        entries.guilds.insert(
+            guild_id,
+            Entry {
+                info,
+                loaded_at: Instant::now(),
+                tick,
+                last_access: AtomicU64::new(tick),
+            },
+        );
+    }
+
+    /// Removes the cached data of the guild. It is loaded from the store again
+    /// on the next access. Only required for changes made without the
+    /// [`StoreHandler`].
+    pub fn invalidate(&self, guild_id: GuildId) {
+        self.entries.write().remove(guild_id);
+    }
+
+    /// Returns the number of cached guilds, including expired ones.
+    pub fn len(&self) -> usize {
+        self.entries.read().guilds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the usage statistics of the cache.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            len: self.len(),
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheStats, GuildInfo, HotPathCache};
+    use crate::ignore::IgnoredChannel;
+    use crate::store::mem::MemStore;
+    use crate::store::StoreHandler;
+
+    use robbot::model::id::{ChannelId, GuildId};
+    use robbot::store::{create, insert};
+
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    async fn cache(capacity: usize, ttl: Duration) -> (HotPathCache, StoreHandler) {
+        let store = StoreHandler::new_in_memory(MemStore::default());
+        create!(store, IgnoredChannel).await.unwrap();
+
+        (HotPathCache::new(store.clone(), capacity, ttl), store)
+    }
+
+    async fn ignore(store: &StoreHandler, guild_id: u64, channel_id: u64) {
+        insert!(
+            store,
+            IgnoredChannel {
+                guild_id: GuildId(guild_id),
+                channel_id: ChannelId(channel_id),
+            }
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_hot_path_cache_lru() {
+        let (cache, store) = cache(2, Duration::from_secs(60)).await;
+        ignore(&store, 1, 10).await;
+
+        assert!(cache.get(GuildId(1)).is_none());
+        let info = cache.guild(GuildId(1)).await.unwrap();
+        assert!(info.ignored_channels.contains(&ChannelId(10)));
+        assert_eq!(cache.get(GuildId(1)), Some(info));
+
+        cache.guild(GuildId(2)).await.unwrap();
+        // Guild 1 was used more recently than guild 2.
+        cache.get(GuildId(1)).unwrap();
+        cache.guild(GuildId(3)).await.unwrap();
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(GuildId(1)).is_some());
+        assert!(cache.get(GuildId(2)).is_none());
+        assert!(cache.get(GuildId(3)).is_some());
+
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 4,
+                misses: 5,
+                len: 2,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hot_path_cache_invalidation() {
+        let (cache, store) = cache(10, Duration::from_secs(60)).await;
+        cache.guild(GuildId(1)).await.unwrap();
+        cache.guild(GuildId(2)).await.unwrap();
+
+        // Writes through the store drop the guild.
+        ignore(&store, 1, 10).await;
+        assert!(cache.get(GuildId(1)).is_none());
+        assert!(cache.get(GuildId(2)).is_some());
+        assert!(cache
+            .guild(GuildId(1))
+            .await
+            .unwrap()
+            .ignored_channels
+            .contains(&ChannelId(10)));
+
+        cache.invalidate(GuildId(2));
+        assert!(cache.get(GuildId(2)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_hot_path_cache_invalidation_during_load() {
+        let (cache, _) = cache(10, Duration::from_secs(60)).await;
+        let info = Arc::new(GuildInfo::default());
+
+        // Invalidating other guilds does not affect the load.
+        let token = cache.begin_load(GuildId(1));
+        cache.invalidate(GuildId(2));
+        cache.finish_load(GuildId(1), token, info.clone());
+        assert!(cache.get(GuildId(1)).is_some());
+
+        // The guild might have changed during the load.
+        let token = cache.begin_load(GuildId(2));
+        cache.invalidate(GuildId(2));
+        cache.finish_load(GuildId(2), token, info.clone());
+        assert!(cache.get(GuildId(2)).is_none());
+
+        // Only the latest load of a guild is cached.
+        let first = cache.begin_load(GuildId(3));
+        let second = cache.begin_load(GuildId(3));
+        cache.finish_load(GuildId(3), first, info.clone());
+        assert!(cache.get(GuildId(3)).is_none());
+        cache.finish_load(GuildId(3), second, info);
+        assert!(cache.get(GuildId(3)).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_hot_path_cache_lru_order() {
+        let (cache, _) = cache(3, Duration::from_secs(60)).await;
+
+        for guild_id in 1..=3 {
+            cache.guild(GuildId(guild_id)).await.unwrap();
+        }
+
+        // Guild 2 is the least recently used one.
+        cache.get(GuildId(1)).unwrap();
+        cache.get(GuildId(3)).unwrap();
+        cache.get(GuildId(1)).unwrap();
+
+        cache.guild(GuildId(4)).await.unwrap();
+        assert!(cache.get(GuildId(2)).is_none());
+
+        cache.guild(GuildId(5)).await.unwrap();
+        assert!(cache.get(GuildId(3)).is_none());
+        assert!(cache.get(GuildId(1)).is_some());
+        assert!(cache.get(GuildId(4)).is_some());
+        assert!(cache.get(GuildId(5)).is_some());
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_hot_path_cache_ttl() {
+        let (cache, _) = cache(10, Duration::ZERO).await;
+
+        cache.guild(GuildId(1)).await.unwrap();
+        assert!(cache.get(GuildId(1)).is_none());
+        assert_eq!(cache.len(), 1);
+
+        // Reloading replaces the expired entry.
+        cache.guild(GuildId(1)).await.unwrap();
+        assert_eq!(cache.len(), 1);
+    }
+}
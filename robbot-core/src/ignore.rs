@@ -0,0 +1,286 @@
+use crate::config::Ignore;
+use crate::hotpath::HotPathCache;
+use crate::store::{Error, StoreHandler};
+
+use robbot::model::channel::Message;
+use robbot::model::id::{ChannelId, GuildId};
+use robbot::store::{delete, insert};
+use robbot::StoreData;
+
+/// A channel in which messages are ignored.
+#[derive(Clone, Debug, StoreData)]
+pub struct IgnoredChannel {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+}
+
+/// Keeps track of ignored channels. Messages in ignored channels are not routed
+/// to commands and hooks should skip them (see [`Context::is_ignored_channel`]).
+///
+/// The ignored channels of a guild are read from the [`HotPathCache`], which
+/// drops them when the [`IgnoredChannel`]s of the guild are changed in the
+/// store.
+///
+/// [`Context::is_ignored_channel`]: crate::context::Context::is_ignored_channel
+#[derive(Clone, Debug)]
+pub struct IgnoreHandler {
+    store: StoreHandler,
+    config: Ignore,
+    cache: HotPathCache,
+}
+
+impl IgnoreHandler {
+    pub fn new(store: StoreHandler, config: Ignore, cache: HotPathCache) -> Self {
+        Self {
+            store,
+            config,
+            cache,
+        }
+    }
+
+    /// Returns `true` if `message` should be ignored. Messages are ignored if they
+    /// were sent in an ignored channel or by a bot or webhook when configured.
+    pub async fn is_ignored_message(&self, message: &Message) -> Result<bool, Error> {
+        if self.config.bots && message.author.bot && message.webhook_id.is_none() {
+            return Ok(true);
+        }
+
+        if self.config.webhooks && message.webhook_id.is_some() {
+            return Ok(true);
+        }
+
+        match message.guild_id {
+            Some(guild_id) => self.is_ignored(guild_id, message.channel_id).await,
+            None => Ok(false),
+        }
+    }
+
+    /// Returns `true` if the channel is ignored.
+    pub async fn is_ignored(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    ) -> Result<bool, Error> {
+        let guild = self.cache.guild(guild_id).await?;
+
+        Ok(guild.ignored_channels.contains(&channel_id))
+    }
+
+    /// Returns all ignored channels in the guild.
+    pub async fn list(&self, guild_id: GuildId) -> Result<Vec<ChannelId>, Error> {
+        let guild = self.cache.guild(guild_id).await?;
+
+        let mut channels: Vec<_> = guild.ignored_channels.iter().copied().collect();
+        channels.sort();
+
+        Ok(channels)
+    }
+
+    /// Ignores the channel. Returns `false` if the channel was already ignored.
+    pub async fn add(&self, guild_id: GuildId, channel_id: ChannelId) -> Result<bool, Error> {
+        if self.is_ignored(guild_id, channel_id).await? {
+            return Ok(false);
+        }
+
+        insert!(
+            self.store,
+            IgnoredChannel {
+                guild_id,
+                channel_id
+            }
+        )
+        .await?;
+
+        Ok(true)
+    }
+
+    /// Stops ignoring the channel. Returns `false` if the channel was not ignored.
+    pub async fn remove(&self, guild_id: GuildId, channel_id: ChannelId) -> Result<bool, Error> {
+        if !self.is_ignored(guild_id, channel_id).await? {
+            return Ok(false);
+        }
+
+        delete!(self.store, IgnoredChannel => {
+            guild_id == guild_id,
+            channel_id == channel_id,
+        })
+        .await?;
+
+        Ok(true)
+    }
+
+    /// Removes the cached channels of the guild. They are loaded from the store
+    /// again on the next access. Only required for changes made without the
+    /// [`StoreHandler`].
+    pub fn invalidate(&self, guild_id: GuildId) {
+        self.cache.invalidate(guild_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IgnoreHandler, IgnoredChannel};
+    use crate::config::Ignore;
+    use crate::hotpath::{HotPathCache, DEFAULT_CAPACITY, DEFAULT_TTL};
+    use crate::store::mem::MemStore;
+    use crate::store::StoreHandler;
+
+    use robbot::model::channel::{Message, MessageKind};
+    use robbot::model::id::{ChannelId, GuildId, MessageId, UserId, WebhookId};
+    use robbot::model::user::User;
+    use robbot::store::{create, delete, insert};
+
+    fn message(channel_id: u64, bot: bool, webhook_id: Option<u64>) -> Message {
+        Message {
+            id: MessageId(1),
+            attachments: Vec::new(),
+            author: User {
+                id: UserId(2),
+                avatar: None,
+                bot,
+                discriminator: 0,
+                name: String::new(),
+                banner: None,
+                accent_color: None,
+            },
+            channel_id: ChannelId(channel_id),
+            content: String::new(),
+            edited_timestamp: None,
+            embeds: Vec::new(),
+            guild_id: Some(GuildId(3)),
+            kind: MessageKind::Regular,
+            member: None,
+            mention_everyone: false,
+            mention_roles: Vec::new(),
+            mention_channels: Vec::new(),
+            mentions: Vec::new(),
+            pinned: false,
+            reactions: Vec::new(),
+            timestamp: chrono::Utc::now(),
+            tts: false,
+            webhook_id: webhook_id.map(WebhookId),
+            message_reference: None,
+            referenced_message: None,
+        }
+    }
+
+    async fn handler(config: Ignore) -> (IgnoreHandler, MemStore) {
+        let mem = MemStore::default();
+        let store = StoreHandler::new_in_memory(mem.clone());
+        create!(store, IgnoredChannel).await.unwrap();
+
+        let cache = HotPathCache::new(store.clone(), DEFAULT_CAPACITY, DEFAULT_TTL);
+        (IgnoreHandler::new(store, config, cache), mem)
+    }
+
+    #[tokio::test]
+    async fn test_ignore_handler_short_circuit() {
+        let (handler, _) = handler(Ignore::default()).await;
+
+        assert!(!handler
+            .is_ignored_message(&message(4, false, None))
+            .await
+            .unwrap());
+        assert!(handler.add(GuildId(3), ChannelId(4)).await.unwrap());
+        assert!(!handler.add(GuildId(3), ChannelId(4)).await.unwrap());
+        assert!(handler
+            .is_ignored_message(&message(4, false, None))
+            .await
+            .unwrap());
+        assert!(!handler
+            .is_ignored_message(&message(5, false, None))
+            .await
+            .unwrap());
+
+        // Bots and webhooks are not ignored by default.
+        assert!(!handler
+            .is_ignored_message(&message(5, true, None))
+            .await
+            .unwrap());
+        assert!(!handler
+            .is_ignored_message(&message(5, true, Some(6)))
+            .await
+            .unwrap());
+
+        assert!(handler.remove(GuildId(3), ChannelId(4)).await.unwrap());
+        assert!(!handler.remove(GuildId(3), ChannelId(4)).await.unwrap());
+        assert!(!handler
+            .is_ignored_message(&message(4, false, None))
+            .await
+            .unwrap());
+
+        let (handler, _) = handler_with(true, false).await;
+        assert!(handler
+            .is_ignored_message(&message(5, true, None))
+            .await
+            .unwrap());
+        assert!(!handler
+            .is_ignored_message(&message(5, true, Some(6)))
+            .await
+            .unwrap());
+
+        let (handler, _) = handler_with(false, true).await;
+        assert!(!handler
+            .is_ignored_message(&message(5, true, None))
+            .await
+            .unwrap());
+        assert!(handler
+            .is_ignored_message(&message(5, true, Some(6)))
+            .await
+            .unwrap());
+    }
+
+    async fn handler_with(bots: bool, webhooks: bool) -> (IgnoreHandler, MemStore) {
+        handler(Ignore { bots, webhooks }).await
+    }
+
+    #[tokio::test]
+    async fn test_ignore_handler_invalidate() {
+        let (handler, store) = handler(Ignore::default()).await;
+
+        assert!(!handler.is_ignored(GuildId(3), ChannelId(4)).await.unwrap());
+
+        // Changes made without the handler are only visible after invalidation.
+        insert!(
+            store,
+            IgnoredChannel {
+                guild_id: GuildId(3),
+                channel_id: ChannelId(4),
+            }
+        )
+        .await
+        .unwrap();
+        assert!(!handler.is_ignored(GuildId(3), ChannelId(4)).await.unwrap());
+
+        handler.invalidate(GuildId(3));
+        assert!(handler.is_ignored(GuildId(3), ChannelId(4)).await.unwrap());
+        assert_eq!(handler.list(GuildId(3)).await.unwrap(), [ChannelId(4)]);
+    }
+
+    #[tokio::test]
+    async fn test_ignore_handler_store_invalidation() {
+        let store = StoreHandler::new_in_memory(MemStore::default());
+        create!(store, IgnoredChannel).await.unwrap();
+
+        let cache = HotPathCache::new(store.clone(), DEFAULT_CAPACITY, DEFAULT_TTL);
+        let handler = IgnoreHandler::new(store.clone(), Ignore::default(), cache);
+        assert!(!handler.is_ignored(GuildId(3), ChannelId(4)).await.unwrap());
+
+        // Changes made through the store invalidate the cache.
+        insert!(
+            store,
+            IgnoredChannel {
+                guild_id: GuildId(3),
+                channel_id: ChannelId(4),
+            }
+        )
+        .await
+        .unwrap();
+        assert!(handler.is_ignored(GuildId(3), ChannelId(4)).await.unwrap());
+
+        delete!(store, IgnoredChannel => { guild_id == GuildId(3) })
+            .await
+            .unwrap();
+        assert!(!handler.is_ignored(GuildId(3), ChannelId(4)).await.unwrap());
+    }
+}
@@ -1,18 +1,49 @@
+pub mod bus;
+pub mod chunks;
 pub mod command;
+pub mod concurrency;
 pub mod config;
 pub mod context;
+pub mod cooldown;
+pub mod disable;
 pub mod executor;
+pub mod extensions;
 pub mod handlers;
 pub mod hook;
+pub mod hotpath;
+pub mod ignore;
+pub mod maintenance;
+pub mod middleware;
 pub mod module;
+pub mod recurrence;
+pub mod roles;
 pub mod router;
+pub mod settings;
 pub mod state;
 pub mod store;
 pub mod task;
+pub mod theme;
+pub mod time;
 
-pub mod prefix;
+mod macros;
 
 #[cfg(feature = "permissions")]
 pub mod permissions;
 
 pub use robbot;
+
+/// Everything required by most plugins. Includes the prelude of [`robbot`].
+pub mod prelude {
+    pub use robbot::prelude::*;
+
+    pub use crate::context::{Context, GuildMessageContext, MessageContext};
+    pub use crate::state::State;
+    pub use crate::theme::EMBED_COLOR;
+}
+
+/// Items used by macros. Not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use chrono::Duration;
+    pub use tokio;
+}
@@ -0,0 +1,41 @@
+#[deprecated = "Use the `robbot::task` attribute macro instead."]
+#[macro_export]
+macro_rules! task {
+    ($name:ident, $schedule:expr, $executor:expr $(,)?) => {
+        fn $name() -> $crate::task::Task {
+            $crate::task::Task {
+                name: stringify!($name).to_owned(),
+                schedule: $schedule,
+                executor: $crate::robbot::executor::Executor::from_fn($executor),
+                on_load: true,
+                concurrency_group: None,
+                timeout: None,
+                mutates_data: false,
+            }
+        }
+    };
+}
+
+#[deprecated = "Use the `robbot::task` attribute macro instead."]
+#[macro_export]
+macro_rules! task_schedule {
+    // Interval
+    (sec: $sec:expr) => {{
+        $crate::robbot::task::TaskSchedule::Interval($crate::__private::Duration::seconds($sec))
+    }};
+    (min: $min:expr) => {{
+        $crate::robbot::task::TaskSchedule::Interval($crate::__private::Duration::minutes($min))
+    }};
+    (hrs: $hrs:expr) => {{
+        $crate::robbot::task::TaskSchedule::Interval($crate::__private::Duration::hours($hrs))
+    }};
+    (hourly) => {{
+        $crate::task_schedule!(min: 60)
+    }};
+    (daily) => {{
+        $crate::task_schedule!(hrs: 24)
+    }};
+    (weekly) => {{
+        $crate::task_schedule!(hrs: 24 * 7)
+    }};
+}
@@ -0,0 +1,146 @@
+//! Read-only maintenance mode.
+//!
+//! While maintenance mode is active the bot stays online but does not write
+//! to the store: commands marked `mutates_data` are rejected by the
+//! dispatcher and tasks marked `mutates_data` are skipped. Commands and tasks
+//! that only read keep working.
+//!
+//! The mode is toggled with the `maintenance` command and can be enabled on
+//! startup with the `[maintenance]` section of the config file. Components
+//! running for a long time can [`subscribe`] to changes of the mode.
+//!
+//! [`subscribe`]: Maintenance::subscribe
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use std::sync::Arc;
+
+/// The state of maintenance mode. Also read from the `[maintenance]`
+/// section of the config file as the state on startup.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceMode {
+    #[serde(default)]
+    pub enabled: bool,
+    /// A notice appended to rejected commands, e.g. the expected end of the
+    /// maintenance.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+impl MaintenanceMode {
+    /// Returns the response to a command marked `mutates_data`, or `None` if
+    /// the command may run.
+    pub fn rejection(&self, mutates_data: bool) -> Option<String> {
+        if !self.enabled || !mutates_data {
+            return None;
+        }
+
+        let mut content =
+            String::from(":construction: The bot is in maintenance mode, changes are disabled.");
+
+        if let Some(message) = &self.message {
+            content.push('\n');
+            content.push_str(message);
+        }
+
+        Some(content)
+    }
+}
+
+/// The shared handle to the current [`MaintenanceMode`].
+#[derive(Clone, Debug)]
+pub struct Maintenance {
+    tx: Arc<watch::Sender<MaintenanceMode>>,
+}
+
+impl Maintenance {
+    /// Creates a new `Maintenance` starting in `mode`.
+    pub fn new(mode: MaintenanceMode) -> Self {
+        let (tx, _) = watch::channel(mode);
+
+        Self { tx: Arc::new(tx) }
+    }
+
+    /// Returns the current mode.
+    pub fn get(&self) -> MaintenanceMode {
+        self.tx.borrow().clone()
+    }
+
+    /// Returns `true` if maintenance mode is active.
+    pub fn is_enabled(&self) -> bool {
+        self.tx.borrow().enabled
+    }
+
+    /// Enables maintenance mode with an optional notice. Replaces the notice
+    /// if maintenance mode is already active.
+    pub fn enable(&self, message: Option<String>) {
+        self.tx.send_replace(MaintenanceMode {
+            enabled: true,
+            message,
+        });
+    }
+
+    /// Disables maintenance mode. Returns `false` if it was not active.
+    pub fn disable(&self) -> bool {
+        self.tx.send_replace(MaintenanceMode::default()).enabled
+    }
+
+    /// Returns a receiver notified whenever the mode changes.
+    pub fn subscribe(&self) -> watch::Receiver<MaintenanceMode> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for Maintenance {
+    fn default() -> Self {
+        Self::new(MaintenanceMode::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Maintenance, MaintenanceMode};
+
+    #[test]
+    fn test_maintenance_rejection() {
+        let mode = MaintenanceMode::default();
+        assert_eq!(mode.rejection(true), None);
+        assert_eq!(mode.rejection(false), None);
+
+        let mode = MaintenanceMode {
+            enabled: true,
+            message: None,
+        };
+        assert!(mode.rejection(true).unwrap().contains("maintenance mode"));
+        assert_eq!(mode.rejection(false), None);
+
+        let mode = MaintenanceMode {
+            enabled: true,
+            message: Some(String::from("Back at 18:00 UTC.")),
+        };
+        assert!(mode
+            .rejection(true)
+            .unwrap()
+            .ends_with("\nBack at 18:00 UTC."));
+        assert_eq!(mode.rejection(false), None);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_toggle() {
+        let maintenance = Maintenance::default();
+        let mut rx = maintenance.subscribe();
+        assert!(!maintenance.is_enabled());
+
+        maintenance.enable(Some(String::from("Migrating")));
+        rx.changed().await.unwrap();
+        assert!(rx.borrow().enabled);
+        assert_eq!(rx.borrow().message.as_deref(), Some("Migrating"));
+        assert!(maintenance.is_enabled());
+
+        assert!(maintenance.disable());
+        rx.changed().await.unwrap();
+        assert_eq!(*rx.borrow(), MaintenanceMode::default());
+        assert!(!maintenance.disable());
+    }
+}
@@ -0,0 +1,78 @@
+use crate::command::SubCommand;
+use crate::context::MessageContext;
+
+use async_trait::async_trait;
+use parking_lot::RwLock;
+
+use std::fmt::{self, Debug, Formatter};
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use robbot::model::id::UserId;
+
+/// A step in the command pipeline, running around the executor of a command.
+///
+/// For every invocation the dispatcher calls [`before`] on all registered
+/// middlewares in order of registration. If a middleware returns
+/// [`ControlFlow::Break`], the invocation is aborted and no further middlewares
+/// or the executor run. The middleware is responsible for responding to the
+/// caller in this case.
+///
+/// After the executor returned, [`after`] is called in reverse order on all
+/// middlewares whose `before` ran.
+///
+/// Middlewares share data through the [`Extensions`] of the context.
+///
+/// [`before`]: Self::before
+/// [`after`]: Self::after
+/// [`Extensions`]: crate::extensions::Extensions
+#[async_trait]
+pub trait CommandMiddleware: Send + Sync {
+    /// Returns the name of the middleware, used for logging.
+    fn name(&self) -> &str;
+
+    async fn before(&self, ctx: &MessageContext, command: &SubCommand) -> ControlFlow<()>;
+
+    async fn after(&self, _ctx: &MessageContext, _command: &SubCommand, _result: &robbot::Result) {}
+}
+
+/// The user a command is run for. The dispatcher inserts `EffectiveUser` into
+/// the [`Extensions`] of every invocation. It is the author of the message,
+/// unless the command is run through `sudo`.
+///
+/// [`Extensions`]: crate::extensions::Extensions
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EffectiveUser(pub UserId);
+
+/// The ordered list of [`CommandMiddleware`]s run by the dispatcher.
+#[derive(Clone, Default)]
+pub struct Middlewares {
+    middlewares: Arc<RwLock<Vec<Arc<dyn CommandMiddleware>>>>,
+}
+
+impl Middlewares {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `middleware` to the end of the chain.
+    pub fn push<M>(&self, middleware: M)
+    where
+        M: CommandMiddleware + 'static,
+    {
+        self.middlewares.write().push(Arc::new(middleware));
+    }
+
+    /// Returns all middlewares in order of registration.
+    pub fn list(&self) -> Vec<Arc<dyn CommandMiddleware>> {
+        self.middlewares.read().clone()
+    }
+}
+
+impl Debug for Middlewares {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.middlewares.read().iter().map(|m| m.name().to_owned()))
+            .finish()
+    }
+}
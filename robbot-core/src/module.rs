@@ -1,11 +1,15 @@
-use crate::command::{AddOptions, Command, CommandHandler, RemoveOptions};
+use crate::command::{self, AddOptions, Command, CommandHandler, RemoveOptions};
+use crate::context::Context;
+use crate::state::State;
 
+use robbot::command::PermissionNode;
+use robbot::executor::Executor;
 use robbot::module::ModuleId;
 
 use std::borrow::Borrow;
 use std::collections::HashSet;
 use std::hash::{Hash, Hasher};
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 
 use parking_lot::RwLock;
@@ -20,12 +24,20 @@ pub enum Error {
     DuplicateIdent,
     #[error("MaxAmountReached: reached the maximum amount of modules")]
     MaxAmountReached,
+    #[error("Commands: {0}")]
+    Commands(#[from] command::Error),
 }
 
 #[derive(Clone)]
 pub struct Module {
     pub name: String,
     pub commands: HashSet<Command>,
+    /// The permission nodes checked by the module.
+    pub permissions: Vec<PermissionNode>,
+    /// Called once when the first gateway ready event arrives.
+    pub on_ready: Option<Executor<Context<()>>>,
+    /// Called during graceful shutdown, before the store is closed.
+    pub on_shutdown: Option<Executor<Arc<State>>>,
 }
 
 impl Borrow<str> for Module {
@@ -55,11 +67,26 @@ impl Hash for Module {
 pub struct LoadedModule {
     pub name: String,
     pub id: ModuleId,
+    pub permissions: Vec<PermissionNode>,
+    pub on_ready: Option<Executor<Context<()>>>,
+    pub on_shutdown: Option<Executor<Arc<State>>>,
 }
 
 impl LoadedModule {
-    fn new(name: String, id: ModuleId) -> Self {
-        Self { name, id }
+    fn new(
+        name: String,
+        id: ModuleId,
+        permissions: Vec<PermissionNode>,
+        on_ready: Option<Executor<Context<()>>>,
+        on_shutdown: Option<Executor<Arc<State>>>,
+    ) -> Self {
+        Self {
+            name,
+            id,
+            permissions,
+            on_ready,
+            on_shutdown,
+        }
     }
 }
 
@@ -86,19 +113,31 @@ impl Hash for LoadedModule {
     }
 }
 
+/// A module that failed to load.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FailedModule {
+    pub name: String,
+    pub reason: String,
+}
+
 #[derive(Debug)]
 struct InnerModuleHandler {
     map: RwLock<HashSet<LoadedModule>>,
+    failed: RwLock<Vec<FailedModule>>,
     counter: AtomicU32,
     command_handler: CommandHandler,
+    /// Whether the `on_ready` hooks already ran.
+    ready: AtomicBool,
 }
 
 impl InnerModuleHandler {
     fn new(command_handler: CommandHandler) -> Self {
         Self {
             map: RwLock::default(),
+            failed: RwLock::default(),
             counter: AtomicU32::new(0),
             command_handler,
+            ready: AtomicBool::new(false),
         }
     }
 }
@@ -132,6 +171,20 @@ impl ModuleHandler {
         modules.iter().cloned().collect()
     }
 
+    /// Returns the permission nodes of all modules, sorted and without duplicates.
+    pub fn permission_nodes(&self) -> Vec<PermissionNode> {
+        let modules = self.inner.map.read();
+
+        let mut nodes: Vec<_> = modules
+            .iter()
+            .flat_map(|module| module.permissions.iter().copied())
+            .collect();
+
+        nodes.sort_unstable();
+        nodes.dedup();
+        nodes
+    }
+
     /// Adds a new module to the handler. If the module has commands those will be
     /// associated under the same module id. Removing the module causes all associated
     /// commands to be removed.
@@ -153,11 +206,16 @@ impl ModuleHandler {
             self.inner
                 .command_handler
                 .inner
-                .add_commands(module.commands, options)
-                .unwrap();
+                .add_commands(module.commands, options)?;
         }
 
-        let module = LoadedModule::new(module.name, id);
+        let module = LoadedModule::new(
+            module.name,
+            id,
+            module.permissions,
+            module.on_ready,
+            module.on_shutdown,
+        );
 
         modules.insert(module);
 
@@ -188,6 +246,101 @@ impl ModuleHandler {
         Ok(())
     }
 
+    /// Removes a module that failed to load, including the commands it added so
+    /// far, and records it as failed.
+    pub fn fail_module(&self, name: &str, err: &command::Error) {
+        let reason = match err {
+            command::Error::DuplicateName { path, module_id } => {
+                let owner = self
+                    .list_modules()
+                    .into_iter()
+                    .find(|module| module.id == *module_id)
+                    .map(|module| format!("module '{}'", module.name))
+                    .unwrap_or_else(|| String::from("the builtin commands"));
+
+                format!("command `{}` is already registered by {}", path, owner)
+            }
+            err => err.to_string(),
+        };
+
+        let _ = self.remove_module(name);
+
+        self.inner.failed.write().push(FailedModule {
+            name: name.to_owned(),
+            reason,
+        });
+    }
+
+    /// Returns the modules that failed to load, see [`fail_module`].
+    ///
+    /// [`fail_module`]: Self::fail_module
+    pub fn failed_modules(&self) -> Vec<FailedModule> {
+        self.inner.failed.read().clone()
+    }
+
+    /// Logs the number of commands of every loaded module and the reason of
+    /// every module that failed to load.
+    pub fn log_summary(&self) {
+        let mut modules = self.list_modules();
+        modules.sort_unstable_by_key(|module| module.id.0);
+
+        for module in modules {
+            log::info!(
+                "[CORE] Loaded module '{}' with {} commands",
+                module.name,
+                self.inner.command_handler.count_module(module.id)
+            );
+        }
+
+        for module in self.failed_modules() {
+            log::error!(
+                "[CORE] Module '{}' failed to load: {}",
+                module.name,
+                module.reason
+            );
+        }
+    }
+
+    /// Calls the `on_ready` hooks of all modules. Only the first call runs the hooks,
+    /// later calls (e.g. after a reconnect) do nothing.
+    pub async fn ready(&self, ctx: Context<()>) {
+        if self.inner.ready.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        for module in self.list_modules() {
+            if let Some(on_ready) = module.on_ready {
+                log::debug!("[CORE] Calling on_ready for module '{}'", module.name);
+
+                if let Err(err) = on_ready.call(ctx.clone()).await {
+                    log::error!(
+                        "[CORE] on_ready for module '{}' failed: {:?}",
+                        module.name,
+                        err
+                    );
+                }
+            }
+        }
+    }
+
+    /// Calls the `on_shutdown` hooks of all modules. This should be called once
+    /// during shutdown, while the store is still available.
+    pub async fn shutdown(&self, state: Arc<State>) {
+        for module in self.list_modules() {
+            if let Some(on_shutdown) = module.on_shutdown {
+                log::debug!("[CORE] Calling on_shutdown for module '{}'", module.name);
+
+                if let Err(err) = on_shutdown.call(state.clone()).await {
+                    log::error!(
+                        "[CORE] on_shutdown for module '{}' failed: {:?}",
+                        module.name,
+                        err
+                    );
+                }
+            }
+        }
+    }
+
     /// Creates a new unique `ModuleId`.
     fn generate_id(&self) -> Result<ModuleId> {
         let val = self.inner.counter.fetch_add(1, Ordering::SeqCst);
@@ -200,3 +353,122 @@ impl ModuleHandler {
         Ok(ModuleId(val))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, FailedModule, Module, ModuleHandler};
+    use crate::command::{AddOptions, Command, CommandHandler};
+    use crate::config::Config;
+    use crate::state::State;
+
+    use robbot::command::PermissionNode;
+    use robbot::executor::Executor;
+    use robbot::Result;
+
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    static SHUTDOWN_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    async fn on_shutdown(_state: Arc<State>) -> Result {
+        SHUTDOWN_CALLS.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_module_handler_shutdown() {
+        let handler = ModuleHandler::new(CommandHandler::new());
+
+        for name in ["a", "b"] {
+            handler
+                .add_module(Module {
+                    name: name.to_owned(),
+                    commands: HashSet::new(),
+                    permissions: Vec::new(),
+                    on_ready: None,
+                    on_shutdown: Some(Executor::from_fn(on_shutdown)),
+                })
+                .unwrap();
+        }
+
+        handler
+            .add_module(Module {
+                name: String::from("c"),
+                commands: HashSet::new(),
+                permissions: Vec::new(),
+                on_ready: None,
+                on_shutdown: None,
+            })
+            .unwrap();
+
+        let state = Arc::new(State::new(Config::default()));
+        handler.shutdown(state).await;
+
+        assert_eq!(SHUTDOWN_CALLS.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_module_handler_permission_nodes() {
+        const MANAGE: PermissionNode = PermissionNode::new("a.manage");
+        const SYNC: PermissionNode = PermissionNode::new("b.sync");
+
+        let handler = ModuleHandler::new(CommandHandler::new());
+
+        for (name, permissions) in [("b", vec![SYNC, MANAGE]), ("a", vec![MANAGE])] {
+            handler
+                .add_module(Module {
+                    name: name.to_owned(),
+                    commands: HashSet::new(),
+                    permissions,
+                    on_ready: None,
+                    on_shutdown: None,
+                })
+                .unwrap();
+        }
+
+        assert_eq!(handler.permission_nodes(), [MANAGE, SYNC]);
+    }
+
+    #[test]
+    fn test_module_handler_fail_module() {
+        let module = |name: &str, commands: &[&str]| Module {
+            name: name.to_owned(),
+            commands: commands.iter().map(Command::new).collect(),
+            permissions: Vec::new(),
+            on_ready: None,
+            on_shutdown: None,
+        };
+
+        let commands = CommandHandler::new();
+        let handler = ModuleHandler::new(commands.clone());
+
+        handler.add_module(module("a", &["gate"])).unwrap();
+        let id = handler.add_module(module("b", &[])).unwrap();
+
+        let options = || AddOptions::new().module_id(id);
+        commands
+            .add_commands([Command::new("poll")], options())
+            .unwrap();
+        let err = commands
+            .add_commands([Command::new("gate")], options())
+            .unwrap_err();
+
+        handler.fail_module("b", &err);
+
+        // The commands added before the conflict are removed as well.
+        assert!(handler.get_module(&"b").is_none());
+        assert_eq!(commands.list_root_commands(), ["gate"]);
+        assert_eq!(
+            handler.failed_modules(),
+            [FailedModule {
+                name: String::from("b"),
+                reason: String::from("command `gate` is already registered by module 'a'"),
+            }]
+        );
+
+        let err = handler.add_module(module("c", &["gate"])).unwrap_err();
+        assert!(matches!(err, Error::Commands(_)));
+        assert!(handler.get_module(&"c").is_none());
+    }
+}
@@ -1,24 +1,22 @@
 use crate::context::Context;
-use crate::store::mysql::MysqlStore;
-use crate::store::Error;
+use crate::store::{Error, StoreHandler};
 
 use robbot::model::channel::Message;
 use robbot::model::id::{GuildId, RoleId, UserId};
 use robbot::model::InvalidModelData;
 use robbot::store::get;
-use robbot::store::lazy::LazyStore;
 use robbot::StoreData;
 
 // TODO: Make PermissionHandler with any type of store.
 #[derive(Clone, Debug)]
 pub struct PermissionHandler {
-    store: LazyStore<MysqlStore>,
+    store: StoreHandler,
 }
 
 impl PermissionHandler {
     /// Creates a new `PermissionHandler`. The pointer `store` must be valid
     /// for the lifetime of `PermissionHandler`.
-    pub fn new(store: LazyStore<MysqlStore>) -> Self {
+    pub fn new(store: StoreHandler) -> Self {
         Self { store }
     }
 
@@ -156,7 +154,7 @@ pub async fn has_permission(ctx: &Context<Message>, permissions: &[String]) -> R
 
     let member = match &ctx.event.member {
         Some(member) => member,
-        None => return Err(InvalidModelData.into()),
+        None => return Err(InvalidModelData("guild message without member").into()),
     };
 
     let user_id = ctx.event.author.id;
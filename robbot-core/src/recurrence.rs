@@ -0,0 +1,347 @@
+//! Recurring schedules, e.g. "every Thursday 20:00 except on holidays".
+//!
+//! A [`Schedule`] starts at a point in time and repeats according to its
+//! [`Recurrence`]. Occurrences falling on one of the exception dates of the
+//! schedule are skipped. All times are in UTC, a schedule at 20:00 stays at
+//! 20:00 UTC across daylight saving time changes.
+use crate::time::DAY;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+
+use std::collections::BTreeSet;
+
+/// How a [`Schedule`] repeats.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Recurrence {
+    /// Only the start of the schedule.
+    None,
+    /// Every `n` days after the start. `0` is treated as [`None`].
+    ///
+    /// [`None`]: Recurrence::None
+    EveryNDays(u32),
+    /// Every week on `weekday` at `time`.
+    Weekly { weekday: Weekday, time: NaiveTime },
+    /// Every month on `day` at `time`. Months shorter than `day` use their
+    /// last day instead, e.g. the 28th or 29th of February for `31`.
+    Monthly { day: u32, time: NaiveTime },
+}
+
+/// A recurring point in time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    /// The first possible occurrence. Weekly and monthly schedules occur on
+    /// the first matching day at or after the start.
+    pub start: DateTime<Utc>,
+    pub recurrence: Recurrence,
+    /// Dates (UTC) on which occurrences are skipped.
+    pub exceptions: BTreeSet<NaiveDate>,
+}
+
+impl Schedule {
+    /// Creates a new `Schedule` without exceptions.
+    pub fn new(start: DateTime<Utc>, recurrence: Recurrence) -> Self {
+        Self {
+            start,
+            recurrence,
+            exceptions: BTreeSet::new(),
+        }
+    }
+
+    /// Returns the first occurrence strictly after `after` that does not fall
+    /// on an exception date, or `None` if there is none.
+    pub fn next_occurrence(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut next = self.first_after(after)?;
+
+        // Occurrences are on distinct days, a finite set of exceptions only
+        // skips finitely many of them.
+        while self.exceptions.contains(&next.date_naive()) {
+            next = self.first_after(next)?;
+        }
+
+        Some(next)
+    }
+
+    /// Returns up to `count` occurrences after `after`, see
+    /// [`next_occurrence`].
+    ///
+    /// [`next_occurrence`]: Self::next_occurrence
+    pub fn next_occurrences(&self, after: DateTime<Utc>, count: usize) -> Vec<DateTime<Utc>> {
+        let mut occurrences = Vec::with_capacity(count);
+        let mut after = after;
+
+        while occurrences.len() < count {
+            match self.next_occurrence(after) {
+                Some(next) => {
+                    occurrences.push(next);
+                    after = next;
+                }
+                None => break,
+            }
+        }
+
+        occurrences
+    }
+
+    /// Returns the first occurrence strictly after `after`, ignoring
+    /// exceptions.
+    fn first_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = self.start;
+        let valid = |time: DateTime<Utc>| time >= start && time > after;
+        // The day to start searching from.
+        let from = after.max(start).date_naive();
+
+        match self.recurrence {
+            Recurrence::None | Recurrence::EveryNDays(0) => valid(start).then_some(start),
+            Recurrence::EveryNDays(n) => {
+                if after < start {
+                    return Some(start);
+                }
+
+                let period = i64::from(n) * DAY;
+                let periods = (after - start).num_seconds() / period + 1;
+                Some(start + Duration::seconds(periods * period))
+            }
+            Recurrence::Weekly { weekday, time } => {
+                let days = (7 + weekday.num_days_from_monday()
+                    - from.weekday().num_days_from_monday())
+                    % 7;
+
+                let next = at(from + Duration::days(i64::from(days)), time);
+                match valid(next) {
+                    true => Some(next),
+                    false => Some(next + Duration::weeks(1)),
+                }
+            }
+            Recurrence::Monthly { day, time } => {
+                let next = at(day_in_month(from.year(), from.month(), day), time);
+                if valid(next) {
+                    return Some(next);
+                }
+
+                let (year, month) = match from.month() {
+                    12 => (from.year() + 1, 1),
+                    month => (from.year(), month + 1),
+                };
+                Some(at(day_in_month(year, month, day), time))
+            }
+        }
+    }
+}
+
+/// Returns `date` at `time` in UTC.
+fn at(date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_time(time))
+}
+
+/// Returns the `day` of a month, or the last day of the month if it is
+/// shorter.
+fn day_in_month(year: i32, month: u32, day: u32) -> NaiveDate {
+    let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+    let next = match month {
+        12 => NaiveDate::from_ymd_opt(year + 1, 1, 1).unwrap(),
+        _ => NaiveDate::from_ymd_opt(year, month + 1, 1).unwrap(),
+    };
+    let last = (next - first).num_days() as u32;
+
+    first + Duration::days(i64::from(day.clamp(1, last) - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{day_in_month, Recurrence, Schedule};
+
+    use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+
+    fn time(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn hm(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_day_in_month() {
+        assert_eq!(day_in_month(2023, 1, 31), date(2023, 1, 31));
+        assert_eq!(day_in_month(2023, 2, 31), date(2023, 2, 28));
+        assert_eq!(day_in_month(2024, 2, 31), date(2024, 2, 29));
+        assert_eq!(day_in_month(2023, 4, 31), date(2023, 4, 30));
+        assert_eq!(day_in_month(2023, 12, 31), date(2023, 12, 31));
+        assert_eq!(day_in_month(2023, 6, 0), date(2023, 6, 1));
+    }
+
+    #[test]
+    fn test_next_occurrence_none() {
+        let start = time(2023, 5, 4, 20, 0);
+        let schedule = Schedule::new(start, Recurrence::None);
+
+        assert_eq!(
+            schedule.next_occurrence(time(2023, 5, 1, 0, 0)),
+            Some(start)
+        );
+        assert_eq!(schedule.next_occurrence(start), None);
+        assert_eq!(schedule.next_occurrence(time(2023, 6, 1, 0, 0)), None);
+
+        let schedule = Schedule::new(start, Recurrence::EveryNDays(0));
+        assert_eq!(
+            schedule.next_occurrence(time(2023, 5, 1, 0, 0)),
+            Some(start)
+        );
+        assert_eq!(schedule.next_occurrence(start), None);
+    }
+
+    #[test]
+    fn test_next_occurrence_every_n_days() {
+        let start = time(2023, 2, 27, 18, 30);
+        let schedule = Schedule::new(start, Recurrence::EveryNDays(3));
+
+        assert_eq!(
+            schedule.next_occurrence(time(2023, 1, 1, 0, 0)),
+            Some(start)
+        );
+        // Strictly after an occurrence.
+        assert_eq!(
+            schedule.next_occurrence(start),
+            Some(time(2023, 3, 2, 18, 30))
+        );
+        assert_eq!(
+            schedule.next_occurrence(time(2023, 3, 2, 18, 29)),
+            Some(time(2023, 3, 2, 18, 30))
+        );
+        // Stays at the same UTC time across a DST change in Europe.
+        let schedule = Schedule::new(time(2023, 3, 20, 20, 0), Recurrence::EveryNDays(7));
+        assert_eq!(
+            schedule.next_occurrence(time(2023, 3, 26, 0, 0)),
+            Some(time(2023, 3, 27, 20, 0))
+        );
+        // Across the end of a leap year february.
+        let schedule = Schedule::new(time(2024, 2, 27, 18, 30), Recurrence::EveryNDays(3));
+        assert_eq!(
+            schedule.next_occurrence(time(2024, 2, 28, 0, 0)),
+            Some(time(2024, 3, 1, 18, 30))
+        );
+    }
+
+    #[test]
+    fn test_next_occurrence_weekly() {
+        // 2023-05-04 is a thursday.
+        let schedule = Schedule::new(
+            time(2023, 5, 1, 0, 0),
+            Recurrence::Weekly {
+                weekday: Weekday::Thu,
+                time: hm(20, 0),
+            },
+        );
+
+        assert_eq!(
+            schedule.next_occurrence(time(2023, 4, 1, 0, 0)),
+            Some(time(2023, 5, 4, 20, 0))
+        );
+        assert_eq!(
+            schedule.next_occurrence(time(2023, 5, 4, 19, 59)),
+            Some(time(2023, 5, 4, 20, 0))
+        );
+        assert_eq!(
+            schedule.next_occurrence(time(2023, 5, 4, 20, 0)),
+            Some(time(2023, 5, 11, 20, 0))
+        );
+        assert_eq!(
+            schedule.next_occurrence(time(2023, 5, 7, 12, 0)),
+            Some(time(2023, 5, 11, 20, 0))
+        );
+        // Across the end of a year.
+        assert_eq!(
+            schedule.next_occurrence(time(2023, 12, 29, 0, 0)),
+            Some(time(2024, 1, 4, 20, 0))
+        );
+
+        // Starting on the weekday after the time skips to the next week.
+        let schedule = Schedule::new(
+            time(2023, 5, 4, 21, 0),
+            Recurrence::Weekly {
+                weekday: Weekday::Thu,
+                time: hm(20, 0),
+            },
+        );
+        assert_eq!(
+            schedule.next_occurrence(time(2023, 5, 1, 0, 0)),
+            Some(time(2023, 5, 11, 20, 0))
+        );
+    }
+
+    #[test]
+    fn test_next_occurrence_monthly() {
+        let schedule = Schedule::new(
+            time(2023, 1, 1, 0, 0),
+            Recurrence::Monthly {
+                day: 31,
+                time: hm(12, 0),
+            },
+        );
+
+        assert_eq!(
+            schedule.next_occurrences(time(2023, 1, 1, 0, 0), 5),
+            [
+                time(2023, 1, 31, 12, 0),
+                time(2023, 2, 28, 12, 0),
+                time(2023, 3, 31, 12, 0),
+                time(2023, 4, 30, 12, 0),
+                time(2023, 5, 31, 12, 0),
+            ]
+        );
+        assert_eq!(
+            schedule.next_occurrence(time(2024, 2, 1, 0, 0)),
+            Some(time(2024, 2, 29, 12, 0))
+        );
+        assert_eq!(
+            schedule.next_occurrence(time(2023, 12, 31, 12, 0)),
+            Some(time(2024, 1, 31, 12, 0))
+        );
+
+        let schedule = Schedule::new(
+            time(2023, 1, 15, 0, 0),
+            Recurrence::Monthly {
+                day: 1,
+                time: hm(9, 0),
+            },
+        );
+        assert_eq!(
+            schedule.next_occurrence(time(2023, 1, 1, 0, 0)),
+            Some(time(2023, 2, 1, 9, 0))
+        );
+    }
+
+    #[test]
+    fn test_next_occurrence_exceptions() {
+        let mut schedule = Schedule::new(
+            time(2023, 12, 1, 0, 0),
+            Recurrence::Weekly {
+                weekday: Weekday::Mon,
+                time: hm(18, 0),
+            },
+        );
+        schedule.exceptions.insert(date(2023, 12, 25));
+        schedule.exceptions.insert(date(2024, 1, 1));
+
+        assert_eq!(
+            schedule.next_occurrences(time(2023, 12, 12, 0, 0), 3),
+            [
+                time(2023, 12, 18, 18, 0),
+                time(2024, 1, 8, 18, 0),
+                time(2024, 1, 15, 18, 0),
+            ]
+        );
+
+        let mut schedule = Schedule::new(time(2023, 12, 25, 18, 0), Recurrence::None);
+        schedule.exceptions.insert(date(2023, 12, 25));
+        assert_eq!(schedule.next_occurrence(time(2023, 12, 1, 0, 0)), None);
+        assert!(schedule
+            .next_occurrences(time(2023, 12, 1, 0, 0), 3)
+            .is_empty());
+    }
+}
@@ -0,0 +1,308 @@
+//! Roles managed automatically by plugins.
+//!
+//! Plugins adding or removing roles on their own register the roles they
+//! manage with a priority. Role edits made using [`Context::add_managed_role`]
+//! and [`Context::remove_managed_role`] are skipped if a manager with a higher
+//! priority manages the same role, so two plugins never undo each other's
+//! changes. Managers with the same priority are not stopped, but their
+//! conflict is reported. Every conflict is reported once per role and pair of
+//! managers, see [`ManagedRoles::subscribe`].
+//!
+//! [`Context::add_managed_role`]: crate::context::Context::add_managed_role
+//! [`Context::remove_managed_role`]: crate::context::Context::remove_managed_role
+use robbot::model::id::{GuildId, RoleId};
+
+use parking_lot::{Mutex, RwLock};
+use tokio::sync::broadcast;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+
+/// The number of unreceived [`RoleConflict`]s kept for slow subscribers.
+const EVENT_QUEUE_SIZE: usize = 16;
+
+/// A manager of a role.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Registration {
+    /// The name of the manager, usually the name of the module.
+    pub manager: String,
+    pub priority: i32,
+}
+
+/// A manager tried to edit a role also managed by `owner`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RoleConflict {
+    pub guild_id: GuildId,
+    pub role_id: RoleId,
+    /// The manager that edited the role.
+    pub manager: String,
+    /// The manager with the highest priority of the role.
+    pub owner: String,
+    /// Whether the edit was skipped. Edits are only skipped if `owner` has a
+    /// higher priority than `manager`.
+    pub skipped: bool,
+}
+
+impl Display for RoleConflict {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.skipped {
+            true => write!(
+                f,
+                "`{}` did not change the role {}, it is managed by `{}` with a higher priority",
+                self.manager,
+                self.role_id.mention(),
+                self.owner
+            ),
+            false => write!(
+                f,
+                "`{}` and `{}` both manage the role {} with the same priority",
+                self.manager,
+                self.owner,
+                self.role_id.mention()
+            ),
+        }
+    }
+}
+
+/// The registry of all managed roles.
+#[derive(Debug)]
+pub struct ManagedRoles {
+    roles: RwLock<HashMap<(GuildId, RoleId), Vec<Registration>>>,
+    /// Conflicts already reported.
+    reported: Mutex<HashSet<RoleConflict>>,
+    events: broadcast::Sender<RoleConflict>,
+}
+
+impl ManagedRoles {
+    pub fn new() -> Self {
+        Self {
+            roles: RwLock::default(),
+            reported: Mutex::default(),
+            events: broadcast::channel(EVENT_QUEUE_SIZE).0,
+        }
+    }
+
+    /// Registers `manager` as a manager of the role, replacing its previous
+    /// registration.
+    pub fn register(&self, guild_id: GuildId, role_id: RoleId, manager: &str, priority: i32) {
+        let mut roles = self.roles.write();
+        let registrations = roles.entry((guild_id, role_id)).or_default();
+
+        registrations.retain(|r| r.manager != manager);
+        registrations.push(Registration {
+            manager: manager.to_owned(),
+            priority,
+        });
+    }
+
+    /// Removes the registration of `manager` for the role.
+    pub fn unregister(&self, guild_id: GuildId, role_id: RoleId, manager: &str) {
+        let mut roles = self.roles.write();
+
+        if let Some(registrations) = roles.get_mut(&(guild_id, role_id)) {
+            registrations.retain(|r| r.manager != manager);
+
+            if registrations.is_empty() {
+                roles.remove(&(guild_id, role_id));
+            }
+        }
+    }
+
+    /// Removes all registrations of `manager` in the guild, e.g. before
+    /// registering the roles of a guild again.
+    pub fn unregister_guild(&self, guild_id: GuildId, manager: &str) {
+        self.roles.write().retain(|(id, _), registrations| {
+            if *id == guild_id {
+                registrations.retain(|r| r.manager != manager);
+            }
+
+            !registrations.is_empty()
+        });
+    }
+
+    /// Returns all managed roles of the guild with their managers, the
+    /// manager with the highest priority first.
+    pub fn list(&self, guild_id: GuildId) -> Vec<(RoleId, Vec<Registration>)> {
+        let mut roles: Vec<_> = self
+            .roles
+            .read()
+            .iter()
+            .filter(|((id, _), _)| *id == guild_id)
+            .map(|((_, role_id), registrations)| {
+                let mut registrations = registrations.clone();
+                registrations.sort_by_key(|r| std::cmp::Reverse(r.priority));
+
+                (*role_id, registrations)
+            })
+            .collect();
+
+        roles.sort_by_key(|(role_id, _)| *role_id);
+        roles
+    }
+
+    /// Checks whether `manager` may edit the role. Returns the conflict if
+    /// the edit must be skipped. Managers without a registration for the role
+    /// are outranked by all registered managers.
+    pub fn check(
+        &self,
+        guild_id: GuildId,
+        role_id: RoleId,
+        manager: &str,
+    ) -> Result<(), RoleConflict> {
+        let conflict = {
+            let roles = self.roles.read();
+            let registrations = match roles.get(&(guild_id, role_id)) {
+                Some(registrations) => registrations,
+                None => return Ok(()),
+            };
+
+            let priority = registrations
+                .iter()
+                .find(|r| r.manager == manager)
+                .map(|r| r.priority);
+
+            let owner = registrations
+                .iter()
+                .filter(|r| r.manager != manager)
+                .max_by_key(|r| r.priority);
+
+            match (owner, priority) {
+                (Some(owner), Some(priority)) if owner.priority < priority => return Ok(()),
+                (Some(owner), priority) => RoleConflict {
+                    guild_id,
+                    role_id,
+                    manager: manager.to_owned(),
+                    owner: owner.manager.clone(),
+                    skipped: priority.is_none_or(|priority| owner.priority > priority),
+                },
+                (None, _) => return Ok(()),
+            }
+        };
+
+        if self.reported.lock().insert(conflict.clone()) {
+            log::warn!("[ROLES] Conflict in guild {}: {}", guild_id, conflict);
+            let _ = self.events.send(conflict.clone());
+        }
+
+        match conflict.skipped {
+            true => Err(conflict),
+            false => Ok(()),
+        }
+    }
+
+    /// Returns a receiver for all conflicts reported from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<RoleConflict> {
+        self.events.subscribe()
+    }
+}
+
+impl Default for ManagedRoles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ManagedRoles, Registration};
+
+    use robbot::model::id::{GuildId, RoleId};
+
+    const GUILD: GuildId = GuildId(1);
+    const ROLE: RoleId = RoleId(2);
+
+    #[test]
+    fn test_managed_roles_conflict() {
+        // The lower priority manager is skipped in both registration orders.
+        for order in [["sync", "temp"], ["temp", "sync"]] {
+            let roles = ManagedRoles::new();
+            let mut rx = roles.subscribe();
+
+            for manager in order {
+                let priority = match manager {
+                    "sync" => 10,
+                    _ => 0,
+                };
+
+                roles.register(GUILD, ROLE, manager, priority);
+            }
+
+            assert!(roles.check(GUILD, ROLE, "sync").is_ok());
+
+            let conflict = roles.check(GUILD, ROLE, "temp").unwrap_err();
+            assert_eq!(conflict.owner, "sync");
+            assert!(conflict.skipped);
+
+            // Reported only once.
+            assert!(roles.check(GUILD, ROLE, "temp").is_err());
+            assert_eq!(rx.try_recv().unwrap(), conflict);
+            assert!(rx.try_recv().is_err());
+
+            // Other roles and guilds are not managed.
+            assert!(roles.check(GUILD, RoleId(3), "temp").is_ok());
+            assert!(roles.check(GuildId(4), ROLE, "temp").is_ok());
+        }
+    }
+
+    #[test]
+    fn test_managed_roles_same_priority() {
+        let roles = ManagedRoles::new();
+        let mut rx = roles.subscribe();
+
+        roles.register(GUILD, ROLE, "a", 5);
+        roles.register(GUILD, ROLE, "b", 5);
+
+        // Neither is skipped, the conflict is still reported.
+        assert!(roles.check(GUILD, ROLE, "a").is_ok());
+        assert!(roles.check(GUILD, ROLE, "b").is_ok());
+        assert!(!rx.try_recv().unwrap().skipped);
+        assert!(!rx.try_recv().unwrap().skipped);
+
+        // Unregistered managers are always outranked.
+        assert!(roles.check(GUILD, ROLE, "c").unwrap_err().skipped);
+    }
+
+    #[test]
+    fn test_managed_roles_registrations() {
+        let roles = ManagedRoles::new();
+
+        roles.register(GUILD, ROLE, "a", 1);
+        roles.register(GUILD, ROLE, "b", 2);
+        roles.register(GUILD, ROLE, "a", 3);
+        roles.register(GUILD, RoleId(3), "a", 1);
+        roles.register(GuildId(4), ROLE, "a", 1);
+
+        assert_eq!(
+            roles.list(GUILD),
+            [
+                (
+                    ROLE,
+                    vec![
+                        Registration {
+                            manager: String::from("a"),
+                            priority: 3,
+                        },
+                        Registration {
+                            manager: String::from("b"),
+                            priority: 2,
+                        },
+                    ]
+                ),
+                (
+                    RoleId(3),
+                    vec![Registration {
+                        manager: String::from("a"),
+                        priority: 1,
+                    }]
+                ),
+            ]
+        );
+
+        roles.unregister_guild(GUILD, "a");
+        assert_eq!(roles.list(GUILD).len(), 1);
+        assert_eq!(roles.list(GuildId(4)).len(), 1);
+
+        roles.unregister(GUILD, ROLE, "b");
+        assert!(roles.list(GUILD).is_empty());
+    }
+}
@@ -1,9 +1,101 @@
+use crate::config::Config;
+use crate::ignore::IgnoreHandler;
+
 use robbot::{
     arguments::{ArgumentsExt, OwnedArguments},
     command::Command,
+    model::{channel::Message, id::UserId},
 };
 use std::collections::HashSet;
 
+/// Returns `true` if commands are never routed from `message`, see
+/// [`IgnoreHandler::is_ignored_message`]. Messages are routed if checking
+/// the ignored channels fails.
+pub async fn is_ignored(ignores: &IgnoreHandler, message: &Message) -> bool {
+    match ignores.is_ignored_message(message).await {
+        Ok(ignored) => ignored,
+        Err(err) => {
+            log::error!("[CORE] Failed to check ignored channels: {}", err);
+            false
+        }
+    }
+}
+
+/// Returns the command string of `message`, or `None` if it is not a
+/// command. Uses [`strip_prefix_direct`] for direct messages if
+/// `dm_without_prefix` is enabled, [`strip_prefix`] otherwise.
+pub fn route<'a>(
+    config: &Config,
+    message: &'a Message,
+    mention: Option<UserId>,
+) -> Option<&'a str> {
+    match message.guild_id.is_none() && config.dm_without_prefix {
+        true => strip_prefix_direct(&message.content, &config.prefix, mention),
+        false => strip_prefix(&message.content, &config.prefix, mention),
+    }
+}
+
+/// Returns the command string of a message with the content `content`, or
+/// `None` if the message is not a command. The message must start with
+/// `prefix` or, if `mention` is given, a mention of that user. Leading
+/// whitespace is ignored.
+///
+/// Messages starting with inline code or a code block and messages
+/// starting with a backslash-escaped prefix (`\!`) are never commands.
+pub fn strip_prefix<'a>(
+    content: &'a str,
+    prefix: &str,
+    mention: Option<UserId>,
+) -> Option<&'a str> {
+    let content = content.trim_start();
+
+    if content.starts_with('`') {
+        return None;
+    }
+
+    if let Some(rest) = content.strip_prefix('\\') {
+        if rest.starts_with(prefix) {
+            return None;
+        }
+    }
+
+    if let Some(rest) = content.strip_prefix(prefix) {
+        return Some(rest);
+    }
+
+    let user_id = mention?;
+    let rest = content.strip_prefix("<@")?;
+    let rest = rest.strip_prefix('!').unwrap_or(rest);
+
+    // Compared without formatting the id, this runs for every message. Ids
+    // never have a sign or leading zeros.
+    let (id, rest) = rest.split_once('>')?;
+    if id.starts_with(['+', '0']) || id.parse() != Ok(user_id.0) {
+        return None;
+    }
+
+    Some(rest.trim_start())
+}
+
+/// Returns the command string of a direct message with the content `content`.
+/// Like [`strip_prefix`], but the prefix is optional. Messages starting with
+/// inline code or an escaped prefix are still never commands.
+pub fn strip_prefix_direct<'a>(
+    content: &'a str,
+    prefix: &str,
+    mention: Option<UserId>,
+) -> Option<&'a str> {
+    if let Some(rest) = strip_prefix(content, prefix, mention) {
+        return Some(rest);
+    }
+
+    let content = content.trim_start();
+    match content.starts_with(['`', '\\']) {
+        true => None,
+        false => Some(content),
+    }
+}
+
 pub fn parse_args<T>(input: T) -> OwnedArguments
 where
     T: AsRef<str>,
@@ -36,26 +128,214 @@ where
     args.iter().filter(|arg| !arg.is_empty()).collect()
 }
 
+/// Finds the command matching `args` and removes the path of the command from
+/// `args`. Returns `None` if no command matches.
 pub fn find_command<'life0, T, U>(commands: &'life0 HashSet<T>, args: &mut U) -> Option<&'life0 T>
 where
     T: Command,
     U: ArgumentsExt,
 {
-    if args.is_empty() {
-        return None;
+    let (command, depth) = resolve_command(commands, args.as_ref())?;
+
+    for _ in 0..depth {
+        args.pop();
     }
 
-    let mut command = commands.get(&args.pop().unwrap())?;
+    Some(command)
+}
 
-    while let Some(arg) = args.get(0) {
-        match command.sub_commands().get(arg) {
+/// Returns the deepest command matching the leading `args` and the number of
+/// arguments that make up its path. Arguments following the path are never
+/// matched, even if they equal the name of a subcommand further down. Returns
+/// `None` if no root command matches.
+pub fn resolve_command<'life0, T>(
+    commands: &'life0 HashSet<T>,
+    args: &[String],
+) -> Option<(&'life0 T, usize)>
+where
+    T: Command,
+{
+    let mut args = args.iter();
+
+    let mut command = commands.get(args.next()?.as_str())?;
+    let mut depth = 1;
+
+    for arg in args {
+        match command.sub_commands().get(arg.as_str()) {
             Some(cmd) => {
-                args.pop();
                 command = cmd;
+                depth += 1;
             }
             None => break,
         }
     }
 
-    Some(command)
+    Some((command, depth))
+}
+
+#[cfg(test)]
+#[allow(clippy::mutable_key_type)]
+mod tests {
+    use super::{find_command, parse_args, resolve_command, strip_prefix, strip_prefix_direct};
+    use crate::command::Command;
+
+    use robbot::arguments::{ArgumentsExt, CommandArguments};
+    use robbot::command::Command as _;
+    use robbot::model::id::UserId;
+
+    use std::collections::HashSet;
+
+    fn command(name: &str, sub_commands: Vec<Command>) -> Command {
+        let mut command = Command::new(name);
+        command.sub_commands = sub_commands.into_iter().collect();
+        command
+    }
+
+    fn commands() -> HashSet<Command> {
+        [
+            command(
+                "log",
+                vec![command("set", vec![]), command("unset", vec![])],
+            ),
+            command("logs", vec![]),
+            command(
+                "permissions",
+                vec![
+                    command("add", vec![]),
+                    command("role", vec![command("add", vec![])]),
+                ],
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn resolve(input: &str) -> Option<(String, usize)> {
+        let commands = commands();
+        let args = parse_args(input);
+
+        resolve_command(&commands, args.as_ref()).map(|(cmd, depth)| (cmd.name().to_owned(), depth))
+    }
+
+    #[test]
+    fn test_resolve_command() {
+        assert_eq!(resolve("log"), Some((String::from("log"), 1)));
+        assert_eq!(resolve("log set #general"), Some((String::from("set"), 2)));
+        assert_eq!(
+            resolve("permissions role add @Role node"),
+            Some((String::from("add"), 3))
+        );
+        assert_eq!(resolve("unknown"), None);
+    }
+
+    #[test]
+    fn test_resolve_command_empty() {
+        assert_eq!(resolve(""), None);
+        assert_eq!(resolve("   "), None);
+    }
+
+    #[test]
+    fn test_resolve_command_ambiguous_prefix() {
+        // Names are matched exactly, never by prefix.
+        assert_eq!(resolve("logs"), Some((String::from("logs"), 1)));
+        assert_eq!(resolve("lo"), None);
+        assert_eq!(resolve("log se"), Some((String::from("log"), 1)));
+        assert_eq!(resolve("logs set"), Some((String::from("logs"), 1)));
+    }
+
+    #[test]
+    fn test_resolve_command_subcommand_like_args() {
+        // The second `add` is an argument of `permissions add`.
+        assert_eq!(
+            resolve("permissions add add"),
+            Some((String::from("add"), 2))
+        );
+        // Routing stops at the first argument that is not a subcommand.
+        assert_eq!(
+            resolve("permissions @User add"),
+            Some((String::from("permissions"), 1))
+        );
+        // Subcommands of other commands are not matched.
+        assert_eq!(resolve("log add"), Some((String::from("log"), 1)));
+    }
+
+    #[test]
+    fn test_find_command_consumes_path() {
+        let commands = commands();
+        let mut args = CommandArguments::from(parse_args("log set #general"));
+
+        let command = find_command(&commands, &mut args).unwrap();
+        assert_eq!(command.name(), "set");
+        assert_eq!(args.as_parsed_args().as_ref(), ["log", "set"]);
+        assert_eq!(args.as_ref(), ["#general"]);
+
+        let mut args = CommandArguments::from(parse_args(""));
+        assert!(find_command(&commands, &mut args).is_none());
+        assert!(args.is_empty());
+    }
+
+    #[test]
+    fn test_strip_prefix() {
+        assert_eq!(strip_prefix("!help", "!", None), Some("help"));
+        assert_eq!(strip_prefix("  !help log", "!", None), Some("help log"));
+        assert_eq!(strip_prefix("hello !help", "!", None), None);
+        assert_eq!(strip_prefix("", "!", None), None);
+
+        // Multi-character prefixes.
+        assert_eq!(strip_prefix("rb help", "rb ", None), Some("help"));
+        assert_eq!(strip_prefix("rbhelp", "rb ", None), None);
+    }
+
+    #[test]
+    fn test_strip_prefix_code() {
+        assert_eq!(strip_prefix("`!help`", "!", None), None);
+        assert_eq!(strip_prefix("```!help```", "!", None), None);
+        assert_eq!(strip_prefix(" ```\n!help\n```", "!", None), None);
+
+        // Code later in the message is part of the arguments.
+        assert_eq!(strip_prefix("!say `code`", "!", None), Some("say `code`"));
+    }
+
+    #[test]
+    fn test_strip_prefix_escaped() {
+        assert_eq!(strip_prefix("\\!help", "!", None), None);
+        assert_eq!(strip_prefix("  \\!help", "!", None), None);
+
+        // A backslash not followed by the prefix is not an escape.
+        assert_eq!(strip_prefix("\\help", "\\", None), Some("help"));
+    }
+
+    #[test]
+    fn test_strip_prefix_mention() {
+        let bot = Some(UserId(1234));
+
+        assert_eq!(strip_prefix("<@1234> help", "!", bot), Some("help"));
+        assert_eq!(strip_prefix("<@!1234>  help", "!", bot), Some("help"));
+        assert_eq!(strip_prefix("!help", "!", bot), Some("help"));
+
+        // Other users and disabled mention prefixes.
+        assert_eq!(strip_prefix("<@12345> help", "!", bot), None);
+        assert_eq!(strip_prefix("<@123> help", "!", bot), None);
+        assert_eq!(strip_prefix("<@01234> help", "!", bot), None);
+        assert_eq!(strip_prefix("<@+1234> help", "!", bot), None);
+        assert_eq!(strip_prefix("<@1234 help", "!", bot), None);
+        assert_eq!(strip_prefix("<@1234> help", "!", None), None);
+        assert_eq!(strip_prefix("`<@1234> help`", "!", bot), None);
+    }
+
+    #[test]
+    fn test_strip_prefix_direct() {
+        let bot = Some(UserId(1234));
+
+        assert_eq!(strip_prefix_direct("help", "!", None), Some("help"));
+        assert_eq!(
+            strip_prefix_direct("  help log", "!", None),
+            Some("help log")
+        );
+        assert_eq!(strip_prefix_direct("!help", "!", None), Some("help"));
+        assert_eq!(strip_prefix_direct("<@1234> help", "!", bot), Some("help"));
+
+        assert_eq!(strip_prefix_direct("`help`", "!", None), None);
+        assert_eq!(strip_prefix_direct("\\!help", "!", None), None);
+    }
 }
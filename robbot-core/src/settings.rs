@@ -0,0 +1,422 @@
+//! Settings changing how the bot presents itself in a guild.
+//!
+//! Every [`Setting`] of a guild is stored as a [`GuildSetting`] and only
+//! exists if the guild changed it. The parsed settings are read from the
+//! [`HotPathCache`], so formatting a time never touches the store for a
+//! cached guild.
+//!
+//! Times are formatted with [`TimeSettings::format`], or the
+//! [`Context::format_time`] shortcut in message contexts.
+//!
+//! [`Context::format_time`]: crate::context::Context::format_time
+use crate::hotpath::HotPathCache;
+use crate::store::{Error, StoreHandler};
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use robbot::model::id::GuildId;
+use robbot::store::{delete, get, insert};
+use robbot::StoreData;
+use thiserror::Error;
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// The value of a [`Setting`] in a guild.
+#[derive(Clone, Debug, StoreData)]
+pub struct GuildSetting {
+    pub guild_id: GuildId,
+    /// The name of the [`Setting`].
+    pub key: String,
+    pub value: String,
+}
+
+/// A setting guilds can change.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Setting {
+    /// The timezone times are shown in, e.g. `Europe/Berlin`.
+    Timezone,
+    /// Whether times are shown with a 24-hour or 12-hour clock.
+    TimeFormat,
+}
+
+impl Setting {
+    pub const ALL: &'static [Self] = &[Self::Timezone, Self::TimeFormat];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Timezone => "timezone",
+            Self::TimeFormat => "timeformat",
+        }
+    }
+
+    /// Checks whether `value` is a valid value of the setting. Returns the
+    /// value as it is stored.
+    pub fn parse_value(self, value: &str) -> Result<String, SettingError> {
+        match self {
+            Self::Timezone => parse_timezone(value).map(|tz| tz.name().to_owned()),
+            Self::TimeFormat => value.parse::<TimeFormat>().map(|format| format.to_string()),
+        }
+    }
+}
+
+impl Display for Setting {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Setting {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|setting| setting.name().eq_ignore_ascii_case(s))
+            .ok_or(())
+    }
+}
+
+/// An invalid value of a [`Setting`].
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum SettingError {
+    #[error("`{0}` is not a known timezone, use a name from the tz database like `Europe/Berlin` or `America/New_York`")]
+    InvalidTimezone(String),
+    #[error("`{0}` is not a valid time format, expected `24h` or `12h`")]
+    InvalidTimeFormat(String),
+}
+
+/// Parses the name of a timezone of the tz database, e.g. `Europe/Berlin`.
+pub fn parse_timezone(s: &str) -> Result<Tz, SettingError> {
+    s.parse()
+        .map_err(|_| SettingError::InvalidTimezone(s.to_owned()))
+}
+
+/// The clock used to show times.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// `15:30`
+    #[default]
+    H24,
+    /// `3:30 PM`
+    H12,
+}
+
+impl Display for TimeFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::H24 => "24h",
+            Self::H12 => "12h",
+        })
+    }
+}
+
+impl FromStr for TimeFormat {
+    type Err = SettingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "24h" => Ok(Self::H24),
+            "12h" => Ok(Self::H12),
+            _ => Err(SettingError::InvalidTimeFormat(s.to_owned())),
+        }
+    }
+}
+
+/// Where a formatted time is shown.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimeDisplay {
+    /// Message content and embed descriptions, where Discord renders dynamic
+    /// timestamps (`<t:...>`) in the timezone and locale of the reader.
+    /// Dynamic timestamps are used unless the guild changed its time
+    /// settings.
+    Markdown,
+    /// Text not rendering dynamic timestamps, e.g. embed field names,
+    /// footers and log messages.
+    Plain,
+}
+
+/// The time settings of a guild.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TimeSettings {
+    /// The timezone of the guild, `None` if not set.
+    pub timezone: Option<Tz>,
+    /// The clock of the guild, `None` if not set.
+    pub format: Option<TimeFormat>,
+}
+
+impl TimeSettings {
+    /// Creates the `TimeSettings` from the stored settings of a guild.
+    /// Invalid values are ignored.
+    pub fn new(settings: &[GuildSetting]) -> Self {
+        let mut time = Self::default();
+
+        for setting in settings {
+            match setting.key.parse() {
+                Ok(Setting::Timezone) => time.timezone = parse_timezone(&setting.value).ok(),
+                Ok(Setting::TimeFormat) => time.format = setting.value.parse().ok(),
+                Err(()) => (),
+            }
+        }
+
+        time
+    }
+
+    /// Returns `true` if the guild did not change any time setting.
+    pub fn is_default(&self) -> bool {
+        self.timezone.is_none() && self.format.is_none()
+    }
+
+    /// Formats `time` for display in the guild, e.g.
+    /// `2024-03-31 15:30 CEST`. Times are shown in UTC with a 24-hour clock
+    /// unless the guild changed the settings.
+    pub fn format(&self, time: DateTime<Utc>, display: TimeDisplay) -> String {
+        if display == TimeDisplay::Markdown && self.is_default() {
+            return format!("<t:{}:f>", time.timestamp());
+        }
+
+        let time = time.with_timezone(&self.timezone.unwrap_or(Tz::UTC));
+
+        let format = match self.format.unwrap_or_default() {
+            TimeFormat::H24 => "%Y-%m-%d %H:%M %Z",
+            TimeFormat::H12 => "%Y-%m-%d %-I:%M %p %Z",
+        };
+
+        time.format(format).to_string()
+    }
+}
+
+/// Reads and changes the [`Setting`]s of guilds.
+///
+/// Settings are read from the [`HotPathCache`], which drops them when the
+/// [`GuildSetting`]s of the guild are changed in the store.
+#[derive(Clone, Debug)]
+pub struct SettingsHandler {
+    store: StoreHandler,
+    cache: HotPathCache,
+}
+
+impl SettingsHandler {
+    pub fn new(store: StoreHandler, cache: HotPathCache) -> Self {
+        Self { store, cache }
+    }
+
+    /// Returns the time settings of the guild.
+    pub async fn time(&self, guild_id: GuildId) -> Result<TimeSettings, Error> {
+        let guild = self.cache.guild(guild_id).await?;
+
+        Ok(guild.time)
+    }
+
+    /// Returns the values of all settings changed in the guild.
+    pub async fn list(&self, guild_id: GuildId) -> Result<Vec<(Setting, String)>, Error> {
+        let settings = get!(self.store, GuildSetting => {
+            guild_id == guild_id,
+        })
+        .await?;
+
+        Ok(Setting::ALL
+            .iter()
+            .filter_map(|setting| {
+                settings
+                    .iter()
+                    .find(|s| s.key == setting.name())
+                    .map(|s| (*setting, s.value.clone()))
+            })
+            .collect())
+    }
+
+    /// Sets the value of the setting in the guild. `value` must be valid, see
+    /// [`Setting::parse_value`].
+    pub async fn set(
+        &self,
+        guild_id: GuildId,
+        setting: Setting,
+        value: String,
+    ) -> Result<(), Error> {
+        self.reset(guild_id, setting).await?;
+
+        insert!(
+            self.store,
+            GuildSetting {
+                guild_id,
+                key: setting.name().to_owned(),
+                value,
+            }
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Resets the setting in the guild to its default.
+    pub async fn reset(&self, guild_id: GuildId, setting: Setting) -> Result<(), Error> {
+        delete!(self.store, GuildSetting => {
+            guild_id == guild_id,
+            key == setting.name().to_owned(),
+        })
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_timezone, GuildSetting, Setting, SettingError, SettingsHandler, TimeDisplay,
+        TimeFormat, TimeSettings,
+    };
+    use crate::hotpath::{HotPathCache, DEFAULT_CAPACITY, DEFAULT_TTL};
+    use crate::store::mem::MemStore;
+    use crate::store::StoreHandler;
+
+    use chrono::{DateTime, NaiveDateTime, Utc};
+    use chrono_tz::Tz;
+    use robbot::model::id::GuildId;
+    use robbot::store::create;
+
+    fn time(s: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+            .unwrap()
+            .and_utc()
+    }
+
+    fn settings(timezone: &str, format: TimeFormat) -> TimeSettings {
+        TimeSettings {
+            timezone: Some(parse_timezone(timezone).unwrap()),
+            format: Some(format),
+        }
+    }
+
+    #[test]
+    fn test_format_default() {
+        let settings = TimeSettings::default();
+        let t = time("2024-03-31 13:30");
+
+        assert_eq!(
+            settings.format(t, TimeDisplay::Markdown),
+            format!("<t:{}:f>", t.timestamp())
+        );
+        assert_eq!(
+            settings.format(t, TimeDisplay::Plain),
+            "2024-03-31 13:30 UTC"
+        );
+
+        // Changed settings are never rendered by Discord.
+        let settings = TimeSettings {
+            timezone: None,
+            format: Some(TimeFormat::H12),
+        };
+        assert_eq!(
+            settings.format(t, TimeDisplay::Markdown),
+            "2024-03-31 1:30 PM UTC"
+        );
+    }
+
+    #[test]
+    fn test_format_dst() {
+        let berlin = settings("Europe/Berlin", TimeFormat::H24);
+
+        // Start of summer time, 02:00 CET is skipped.
+        for (utc, local) in [
+            ("2024-03-31 00:59", "2024-03-31 01:59 CET"),
+            ("2024-03-31 01:00", "2024-03-31 03:00 CEST"),
+            // End of summer time, 02:30 local exists twice.
+            ("2024-10-27 00:30", "2024-10-27 02:30 CEST"),
+            ("2024-10-27 01:30", "2024-10-27 02:30 CET"),
+        ] {
+            assert_eq!(berlin.format(time(utc), TimeDisplay::Markdown), local);
+        }
+
+        let new_york = settings("America/New_York", TimeFormat::H12);
+
+        for (utc, local) in [
+            ("2024-03-10 06:59", "2024-03-10 1:59 AM EST"),
+            ("2024-03-10 07:00", "2024-03-10 3:00 AM EDT"),
+            ("2024-11-03 05:30", "2024-11-03 1:30 AM EDT"),
+            ("2024-11-03 06:30", "2024-11-03 1:30 AM EST"),
+            ("2024-11-03 18:05", "2024-11-03 1:05 PM EST"),
+        ] {
+            assert_eq!(new_york.format(time(utc), TimeDisplay::Plain), local);
+        }
+    }
+
+    #[test]
+    fn test_parse_settings() {
+        assert_eq!(parse_timezone("Europe/Berlin"), Ok(Tz::Europe__Berlin));
+        assert_eq!(
+            parse_timezone("Europe/Atlantis"),
+            Err(SettingError::InvalidTimezone(String::from(
+                "Europe/Atlantis"
+            )))
+        );
+        assert!(parse_timezone("Europe/Atlantis")
+            .unwrap_err()
+            .to_string()
+            .contains("Europe/Berlin"));
+
+        assert_eq!(
+            Setting::TimeFormat.parse_value("12H"),
+            Ok(String::from("12h"))
+        );
+        assert_eq!(
+            Setting::TimeFormat.parse_value("13h"),
+            Err(SettingError::InvalidTimeFormat(String::from("13h")))
+        );
+        assert_eq!("TimeFormat".parse(), Ok(Setting::TimeFormat));
+
+        // Invalid and unknown stored values are ignored.
+        let setting = |key: &str, value: &str| GuildSetting {
+            guild_id: GuildId(1),
+            key: key.to_owned(),
+            value: value.to_owned(),
+        };
+        let time = TimeSettings::new(&[
+            setting("timezone", "Europe/Atlantis"),
+            setting("timeformat", "12h"),
+            setting("language", "de"),
+        ]);
+        assert_eq!(
+            time,
+            TimeSettings {
+                timezone: None,
+                format: Some(TimeFormat::H12),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_settings_handler() {
+        let store = StoreHandler::new_in_memory(MemStore::default());
+        create!(store, GuildSetting).await.unwrap();
+
+        let cache = HotPathCache::new(store.clone(), DEFAULT_CAPACITY, DEFAULT_TTL);
+        let handler = SettingsHandler::new(store, cache);
+        let guild_id = GuildId(1);
+
+        assert!(handler.time(guild_id).await.unwrap().is_default());
+
+        handler
+            .set(guild_id, Setting::Timezone, String::from("Europe/Berlin"))
+            .await
+            .unwrap();
+        handler
+            .set(guild_id, Setting::Timezone, String::from("Asia/Tokyo"))
+            .await
+            .unwrap();
+
+        // Changes are visible through the cache immediately.
+        let time = handler.time(guild_id).await.unwrap();
+        assert_eq!(time.timezone, Some(Tz::Asia__Tokyo));
+        assert_eq!(
+            handler.list(guild_id).await.unwrap(),
+            [(Setting::Timezone, String::from("Asia/Tokyo"))]
+        );
+
+        handler.reset(guild_id, Setting::Timezone).await.unwrap();
+        assert!(handler.time(guild_id).await.unwrap().is_default());
+        assert!(handler.list(guild_id).await.unwrap().is_empty());
+    }
+}
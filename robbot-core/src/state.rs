@@ -1,13 +1,24 @@
-use robbot::store::lazy::LazyStore;
-
+use crate::bus::EventBus;
+use crate::chunks::MemberChunks;
 use crate::command::CommandHandler;
+use crate::concurrency::{ConcurrencyGroups, GuildExecutions};
 use crate::config::Config;
 use crate::context::Context;
+use crate::cooldown::CooldownHandler;
 use crate::hook::HookController;
+use crate::hotpath::{self, HotPathCache};
+use crate::ignore::IgnoreHandler;
+use crate::maintenance::Maintenance;
+use crate::middleware::Middlewares;
 use crate::module::ModuleHandler;
-use crate::store::mysql::MysqlStore;
+use crate::roles::ManagedRoles;
+use crate::settings::SettingsHandler;
+use crate::store::invalidate::InvalidationBus;
+use crate::store::{StoreHandler, StoreLock};
 use crate::task::TaskScheduler;
 
+use robbot::model::id::UserId;
+
 #[cfg(feature = "permissions")]
 use crate::permissions::PermissionHandler;
 
@@ -21,11 +32,25 @@ pub struct State {
     commands: CommandHandler,
     tasks: TaskScheduler,
     hooks: HookController,
+    middlewares: Middlewares,
+    concurrency: ConcurrencyGroups,
+    executions: GuildExecutions,
+    member_chunks: MemberChunks,
     modules: ModuleHandler,
-    store: LazyStore<MysqlStore>,
+    store: StoreHandler,
+    store_lock: StoreLock,
+    hot_path: HotPathCache,
+    ignores: IgnoreHandler,
+    settings: SettingsHandler,
+    cooldowns: CooldownHandler,
+    managed_roles: ManagedRoles,
+    maintenance: Maintenance,
+    bus: EventBus,
     #[cfg(feature = "permissions")]
     permissions: PermissionHandler,
     pub connect_time: Arc<RwLock<Option<Instant>>>,
+    /// The user id of the bot, known once the bot is ready.
+    pub bot_id: Arc<RwLock<Option<UserId>>>,
     pub context: Arc<RwLock<Option<Context<()>>>>,
 }
 
@@ -34,16 +59,29 @@ impl State {
         let context: Arc<RwLock<Option<Context<()>>>> = Arc::default();
 
         let commands = CommandHandler::new();
-        let tasks = TaskScheduler::new();
+        let tasks = TaskScheduler::with_overrides(config.task_schedules());
         let hooks = HookController::new(context.clone());
+        let concurrency = ConcurrencyGroups::new(config.concurrency.clone());
+        let executions = GuildExecutions::new(config.executions);
 
         let modules = ModuleHandler::new(commands.clone());
 
-        let store: LazyStore<MysqlStore> = LazyStore::new(&config.database.connect_string());
+        let store = StoreHandler::new(&config.database.connect_string());
+        let hot_path = HotPathCache::new(
+            store.clone(),
+            hotpath::DEFAULT_CAPACITY,
+            hotpath::DEFAULT_TTL,
+        );
+        let ignores = IgnoreHandler::new(store.clone(), config.ignore.clone(), hot_path.clone());
+        let settings = SettingsHandler::new(store.clone(), hot_path.clone());
+        let cooldowns = CooldownHandler::new(store.clone());
 
         #[cfg(feature = "permissions")]
         let permissions = PermissionHandler::new(store.clone());
 
+        let maintenance = Maintenance::new(config.maintenance.clone());
+        let bus = EventBus::new(context.clone(), config.timeouts.hook());
+
         let connect_time = Arc::default();
         let config = Arc::new(config);
 
@@ -52,11 +90,24 @@ impl State {
             commands,
             tasks,
             hooks,
+            middlewares: Middlewares::new(),
+            concurrency,
+            executions,
+            member_chunks: MemberChunks::new(),
             modules,
             store,
+            store_lock: StoreLock::new(),
+            hot_path,
+            ignores,
+            settings,
+            cooldowns,
+            managed_roles: ManagedRoles::new(),
+            maintenance,
+            bus,
             #[cfg(feature = "permissions")]
             permissions,
             connect_time,
+            bot_id: Arc::default(),
             context,
         }
     }
@@ -76,16 +127,87 @@ impl State {
         &self.hooks
     }
 
+    /// Returns a reference to the [`Middlewares`] run around every command.
+    pub fn middlewares(&self) -> &Middlewares {
+        &self.middlewares
+    }
+
+    /// Returns a reference to the [`ConcurrencyGroups`] limiting concurrent
+    /// commands and tasks.
+    pub fn concurrency(&self) -> &ConcurrencyGroups {
+        &self.concurrency
+    }
+
+    /// Returns a reference to the [`GuildExecutions`] limiting concurrent
+    /// commands per guild.
+    pub fn executions(&self) -> &GuildExecutions {
+        &self.executions
+    }
+
+    /// Returns a reference to the pending guild member chunk requests.
+    pub fn member_chunks(&self) -> &MemberChunks {
+        &self.member_chunks
+    }
+
     /// Returns a reference to the internal [`ModuleHandler`].
     pub fn modules(&self) -> &ModuleHandler {
         &self.modules
     }
 
-    /// Returns a reference to the internal [`LazyStore`].
-    pub fn store(&self) -> &LazyStore<MysqlStore> {
+    /// Returns a reference to the internal [`StoreHandler`].
+    pub fn store(&self) -> &StoreHandler {
         &self.store
     }
 
+    /// Returns a reference to the [`InvalidationBus`] of the store. Caches of
+    /// store data subscribe to it to drop entries changed in the store.
+    pub fn invalidations(&self) -> &InvalidationBus {
+        self.store.invalidations()
+    }
+
+    /// Returns a reference to the [`StoreLock`] coordinating sync jobs with
+    /// exports and imports of the store.
+    pub fn store_lock(&self) -> &StoreLock {
+        &self.store_lock
+    }
+
+    /// Returns a reference to the [`HotPathCache`] of the per-guild data read
+    /// for every message.
+    pub fn hot_path(&self) -> &HotPathCache {
+        &self.hot_path
+    }
+
+    /// Returns a reference to the internal [`IgnoreHandler`].
+    pub fn ignores(&self) -> &IgnoreHandler {
+        &self.ignores
+    }
+
+    /// Returns a reference to the internal [`SettingsHandler`].
+    pub fn settings(&self) -> &SettingsHandler {
+        &self.settings
+    }
+
+    /// Returns a reference to the internal [`CooldownHandler`].
+    pub fn cooldowns(&self) -> &CooldownHandler {
+        &self.cooldowns
+    }
+
+    /// Returns a reference to the registry of [`ManagedRoles`].
+    pub fn managed_roles(&self) -> &ManagedRoles {
+        &self.managed_roles
+    }
+
+    /// Returns a reference to the [`Maintenance`] mode of the bot.
+    pub fn maintenance(&self) -> &Maintenance {
+        &self.maintenance
+    }
+
+    /// Returns a reference to the [`EventBus`] used by plugins to notify each
+    /// other.
+    pub fn bus(&self) -> &EventBus {
+        &self.bus
+    }
+
     /// Returns a reference to the internal [`PermissionHandler`].
     #[cfg(feature = "permissions")]
     pub fn permissions(&self) -> &PermissionHandler {
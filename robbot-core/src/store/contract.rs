@@ -0,0 +1,534 @@
+//! Contract tests shared by all [`Store`] implementations. Every store must
+//! produce the same results for the same scenario.
+//!
+//! The MySQL tests only run when the `ROBBOT_TEST_MYSQL` environment variable
+//! contains a connection string to a test database.
+
+use super::mem::MemStore;
+use super::mysql::MysqlStore;
+
+use robbot::store::{
+    create, delete, get, get_one, insert, Decimal, Deserialize, OrderBy, Serialize, Store,
+    StoreEnum,
+};
+use robbot::StoreData;
+
+use std::fmt::Debug;
+
+#[derive(Clone, Debug, PartialEq, Eq, StoreData)]
+struct ContractData {
+    id: u64,
+    category: u64,
+    name: String,
+}
+
+impl ContractData {
+    fn new(id: u64, category: u64, name: &str) -> Self {
+        Self {
+            id,
+            category,
+            name: name.to_owned(),
+        }
+    }
+}
+
+/// Sorts `items` by id, for results without a guaranteed order.
+fn sorted(mut items: Vec<ContractData>) -> Vec<ContractData> {
+    items.sort_by_key(|item| item.id);
+    items
+}
+
+async fn run_scenario<S>(store: S)
+where
+    S: Store + Sync,
+    S::Error: Debug,
+    u64: Serialize<S> + Deserialize<S>,
+    String: Serialize<S> + Deserialize<S>,
+{
+    create!(store, ContractData).await.unwrap();
+
+    // Start from an empty table.
+    for category in [1, 2] {
+        delete!(store, ContractData => { category == category })
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(get!(store, ContractData).await.unwrap(), []);
+    assert_eq!(
+        get_one!(store, ContractData => { id == 1 }).await.unwrap(),
+        None
+    );
+
+    let a = ContractData::new(1, 1, "c");
+    let b = ContractData::new(2, 2, "a");
+    let c = ContractData::new(3, 1, "b");
+
+    for data in [a.clone(), b.clone(), c.clone()] {
+        insert!(store, data).await.unwrap();
+    }
+
+    assert_eq!(
+        sorted(get!(store, ContractData).await.unwrap()),
+        [a.clone(), b.clone(), c.clone()]
+    );
+    assert_eq!(
+        sorted(
+            get!(store, ContractData => { category == 1 })
+                .await
+                .unwrap()
+        ),
+        [a.clone(), c.clone()]
+    );
+    assert_eq!(
+        get_one!(store, ContractData => { id == 2 }).await.unwrap(),
+        Some(b.clone())
+    );
+
+    let descriptor = store.make_descriptor::<ContractData>();
+    let query = store.make_query::<ContractData>();
+    assert_eq!(
+        store
+            .get_ordered(descriptor, query, OrderBy::asc("name"))
+            .await
+            .unwrap(),
+        [b.clone(), c.clone(), a.clone()]
+    );
+
+    let query = store.make_query::<ContractData>().category(1);
+    assert_eq!(
+        store
+            .get_ordered(descriptor, query, OrderBy::desc("id"))
+            .await
+            .unwrap(),
+        [c.clone(), a.clone()]
+    );
+
+    // Unknown fields are rejected instead of being ignored.
+    let query = store.make_query::<ContractData>();
+    assert!(store
+        .get_ordered(
+            descriptor,
+            query,
+            OrderBy::asc("id; DROP TABLE ContractData")
+        )
+        .await
+        .is_err());
+
+    assert_eq!(
+        sorted(
+            get!(store, ContractData => { id in [1, 2, 5] })
+                .await
+                .unwrap()
+        ),
+        [a.clone(), b.clone()]
+    );
+    assert_eq!(
+        get!(store, ContractData => { id in [1, 2, 3], category == 2 })
+            .await
+            .unwrap(),
+        std::slice::from_ref(&b)
+    );
+    assert_eq!(
+        get!(store, ContractData => { id in Vec::new() })
+            .await
+            .unwrap(),
+        []
+    );
+
+    delete!(store, ContractData => { name == String::from("b") })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        sorted(get!(store, ContractData).await.unwrap()),
+        [a.clone(), b.clone()]
+    );
+
+    delete!(store, ContractData => { category == 1 })
+        .await
+        .unwrap();
+    delete!(store, ContractData => { category == 2 })
+        .await
+        .unwrap();
+
+    assert_eq!(get!(store, ContractData).await.unwrap(), []);
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, StoreData)]
+struct WideData {
+    id: u128,
+    balance: i128,
+    amount: Decimal,
+}
+
+impl WideData {
+    fn new(id: u128, balance: i128, amount: &str) -> Self {
+        Self {
+            id,
+            balance,
+            amount: amount.parse().unwrap(),
+        }
+    }
+}
+
+/// Values outside of the 64-bit range must survive a round trip exactly.
+async fn run_wide_scenario<S>(store: S)
+where
+    S: Store + Sync,
+    S::Error: Debug,
+    u128: Serialize<S> + Deserialize<S>,
+    i128: Serialize<S> + Deserialize<S>,
+    Decimal: Serialize<S> + Deserialize<S>,
+{
+    create!(store, WideData).await.unwrap();
+
+    let a = WideData::new(u128::MAX, i128::MIN, "-0.000000000000000000000000000001");
+    let b = WideData::new(1, -1, "12345678901234567890.123456789");
+    let c = WideData::new(u128::from(u64::MAX) + 1, i128::MAX, "0.5");
+
+    for data in [&a, &b, &c] {
+        delete!(store, WideData => { id == data.id }).await.unwrap();
+    }
+
+    for data in [a.clone(), b.clone(), c.clone()] {
+        insert!(store, data).await.unwrap();
+    }
+
+    assert_eq!(
+        get_one!(store, WideData => { id == u128::MAX })
+            .await
+            .unwrap(),
+        Some(a.clone())
+    );
+    assert_eq!(
+        get_one!(store, WideData => { amount == "0.50".parse().unwrap() })
+            .await
+            .unwrap(),
+        Some(c.clone())
+    );
+    assert_eq!(
+        get!(store, WideData => { balance in [i128::MIN, -1], id == 1 })
+            .await
+            .unwrap(),
+        std::slice::from_ref(&b)
+    );
+
+    let descriptor = store.make_descriptor::<WideData>();
+    let query = store.make_query::<WideData>();
+    assert_eq!(
+        store
+            .get_ordered(descriptor, query, OrderBy::asc("amount"))
+            .await
+            .unwrap(),
+        [a.clone(), c.clone(), b.clone()]
+    );
+
+    let query = store.make_query::<WideData>();
+    assert_eq!(
+        store
+            .get_ordered(descriptor, query, OrderBy::desc("balance"))
+            .await
+            .unwrap(),
+        [c.clone(), b.clone(), a.clone()]
+    );
+
+    for data in [&a, &b, &c] {
+        delete!(store, WideData => { id == data.id }).await.unwrap();
+    }
+
+    // Decimals are limited to the digits of the MySQL column, the largest
+    // ones survive a round trip.
+    assert_eq!(Decimal::new(i128::MAX, 0), None);
+
+    for amount in [Decimal::MAX, Decimal::MIN] {
+        let data = WideData {
+            id: 2,
+            balance: 0,
+            amount,
+        };
+
+        delete!(store, WideData => { id == 2 }).await.unwrap();
+        insert!(store, data.clone()).await.unwrap();
+        assert_eq!(
+            get_one!(store, WideData => { id == 2 }).await.unwrap(),
+            Some(data)
+        );
+        delete!(store, WideData => { id == 2 }).await.unwrap();
+    }
+
+    assert_eq!(get!(store, WideData).await.unwrap(), []);
+}
+
+#[derive(Clone, Debug, PartialEq, StoreData)]
+struct PrimitiveData {
+    id: u64,
+    flag: bool,
+    small: i8,
+    short: i16,
+    int: i32,
+    long: i64,
+    byte: u8,
+    ushort: u16,
+    uint: u32,
+    float: f32,
+    double: f64,
+    text: String,
+}
+
+/// Checks that an equality condition on `$field` matches exactly `$data`.
+macro_rules! assert_matches {
+    ($store:expr, $field:ident, $data:expr) => {
+        assert_eq!(
+            get!($store, PrimitiveData => { $field == $data.$field.clone() })
+                .await
+                .unwrap(),
+            std::slice::from_ref(&$data),
+            "condition on `{}`",
+            stringify!($field),
+        );
+    };
+}
+
+/// Every primitive must round trip and match equality conditions the same
+/// way on every store.
+async fn run_primitive_scenario<S>(store: S)
+where
+    S: Store + Sync,
+    S::Error: Debug,
+    bool: Serialize<S> + Deserialize<S>,
+    i8: Serialize<S> + Deserialize<S>,
+    i16: Serialize<S> + Deserialize<S>,
+    i32: Serialize<S> + Deserialize<S>,
+    i64: Serialize<S> + Deserialize<S>,
+    u8: Serialize<S> + Deserialize<S>,
+    u16: Serialize<S> + Deserialize<S>,
+    u32: Serialize<S> + Deserialize<S>,
+    u64: Serialize<S> + Deserialize<S>,
+    f32: Serialize<S> + Deserialize<S>,
+    f64: Serialize<S> + Deserialize<S>,
+    String: Serialize<S> + Deserialize<S>,
+{
+    create!(store, PrimitiveData).await.unwrap();
+
+    for id in [1, 2] {
+        delete!(store, PrimitiveData => { id == id }).await.unwrap();
+    }
+
+    let a = PrimitiveData {
+        id: 1,
+        flag: true,
+        small: i8::MIN,
+        short: i16::MIN,
+        int: i32::MIN,
+        long: i64::MIN,
+        byte: u8::MAX,
+        ushort: u16::MAX,
+        uint: u32::MAX,
+        float: -1.25,
+        double: 0.5,
+        text: String::from("true"),
+    };
+    let b = PrimitiveData {
+        id: 2,
+        flag: false,
+        small: 1,
+        short: 1,
+        int: 1,
+        long: 1,
+        byte: 1,
+        ushort: 1,
+        uint: 1,
+        float: 0.0,
+        double: 0.0,
+        text: String::new(),
+    };
+
+    for data in [a.clone(), b.clone()] {
+        insert!(store, data).await.unwrap();
+    }
+
+    assert_eq!(
+        get_one!(store, PrimitiveData => { id == 1 }).await.unwrap(),
+        Some(a.clone())
+    );
+
+    for data in [a.clone(), b.clone()] {
+        assert_matches!(store, id, data);
+        assert_matches!(store, flag, data);
+        assert_matches!(store, small, data);
+        assert_matches!(store, short, data);
+        assert_matches!(store, int, data);
+        assert_matches!(store, long, data);
+        assert_matches!(store, byte, data);
+        assert_matches!(store, ushort, data);
+        assert_matches!(store, uint, data);
+        assert_matches!(store, float, data);
+        assert_matches!(store, double, data);
+        assert_matches!(store, text, data);
+    }
+
+    assert_eq!(
+        get!(store, PrimitiveData => { flag == true, id == 1 })
+            .await
+            .unwrap(),
+        std::slice::from_ref(&a)
+    );
+    assert_eq!(
+        get!(store, PrimitiveData => { flag == true, id == 2 })
+            .await
+            .unwrap(),
+        []
+    );
+    assert_eq!(
+        get!(store, PrimitiveData => { flag in [false] })
+            .await
+            .unwrap(),
+        std::slice::from_ref(&b)
+    );
+    // Floats compare by value.
+    assert_eq!(
+        get!(store, PrimitiveData => { double == -0.0 })
+            .await
+            .unwrap(),
+        std::slice::from_ref(&b)
+    );
+
+    for id in [1, 2] {
+        delete!(store, PrimitiveData => { id == id }).await.unwrap();
+    }
+
+    assert_eq!(get!(store, PrimitiveData).await.unwrap(), []);
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, StoreEnum)]
+enum VariantKind {
+    Word,
+    Invite,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, StoreData)]
+struct VariantData {
+    id: u64,
+    #[store(as = "string")]
+    kind: VariantKind,
+}
+
+// Stored in the same resource as `VariantData`, with the same layout.
+mod raw {
+    use robbot::StoreData;
+
+    #[derive(Clone, Debug, StoreData)]
+    pub struct VariantData {
+        pub id: u64,
+        pub kind: String,
+    }
+}
+
+/// A stored value that is not a known variant fails to read instead of
+/// panicking.
+async fn run_unknown_variant_scenario<S>(store: S)
+where
+    S: Store + Sync,
+    S::Error: Debug,
+    u64: Serialize<S> + Deserialize<S>,
+    String: Serialize<S> + Deserialize<S>,
+{
+    create!(store, VariantData).await.unwrap();
+
+    for id in [1, 2] {
+        delete!(store, VariantData => { id == id }).await.unwrap();
+    }
+
+    let known = VariantData {
+        id: 1,
+        kind: VariantKind::Invite,
+    };
+    insert!(store, known.clone()).await.unwrap();
+    assert_eq!(
+        get_one!(store, VariantData => { id == 1 }).await.unwrap(),
+        Some(known)
+    );
+
+    insert!(
+        store,
+        raw::VariantData {
+            id: 2,
+            kind: String::from("emoji"),
+        }
+    )
+    .await
+    .unwrap();
+
+    assert!(get_one!(store, VariantData => { id == 2 }).await.is_err());
+    assert!(get!(store, VariantData => { id == 2 }).await.is_err());
+    assert!(get!(store, VariantData).await.is_err());
+
+    let descriptor = store.make_descriptor::<VariantData>();
+    let query = store.make_query::<VariantData>();
+    assert!(store
+        .get_ordered(descriptor, query, OrderBy::asc("id"))
+        .await
+        .is_err());
+
+    for id in [1, 2] {
+        delete!(store, VariantData => { id == id }).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_contract_mem_store() {
+    let store = MemStore::connect("").await.unwrap();
+    run_scenario(store.clone()).await;
+    run_wide_scenario(store.clone()).await;
+    run_primitive_scenario(store.clone()).await;
+    run_unknown_variant_scenario(store).await;
+}
+
+#[tokio::test]
+async fn test_contract_mysql_store() {
+    let uri = match std::env::var("ROBBOT_TEST_MYSQL") {
+        Ok(uri) => uri,
+        Err(_) => return,
+    };
+
+    let store = MysqlStore::connect(&uri).await.unwrap();
+    run_scenario(store.clone()).await;
+    run_wide_scenario(store.clone()).await;
+    run_primitive_scenario(store.clone()).await;
+    run_unknown_variant_scenario(store).await;
+}
+
+#[tokio::test]
+async fn test_mem_store_insertion_order() {
+    let store = MemStore::connect("").await.unwrap();
+
+    let items: Vec<_> = (0..16)
+        .map(|i| ContractData::new(15 - i, i % 2, "x"))
+        .collect();
+
+    for item in items.iter().cloned() {
+        insert!(store, item).await.unwrap();
+    }
+
+    assert_eq!(get!(store, ContractData).await.unwrap(), items);
+
+    let odd: Vec<_> = items.iter().filter(|i| i.category == 1).cloned().collect();
+    assert_eq!(
+        get!(store, ContractData => { category == 1 })
+            .await
+            .unwrap(),
+        odd
+    );
+
+    // Equal values keep their insertion order.
+    let descriptor = store.make_descriptor::<ContractData>();
+    let query = store.make_query::<ContractData>();
+    assert_eq!(
+        store
+            .get_ordered(descriptor, query, OrderBy::asc("name"))
+            .await
+            .unwrap(),
+        items
+    );
+}
@@ -0,0 +1,957 @@
+//! Exporting and importing the contents of a [`StoreHandler`].
+//!
+//! Exports use a versioned line-based JSON format. The first line is a header
+//! containing the format version. Every resource starts with a line containing
+//! its name and the number of rows, followed by one line per row:
+//!
+//! ```text
+//! {"version":1}
+//! {"resource":"LogChannel","rows":2}
+//! {"channel_id":1234,"guild_id":5678}
+//! {"channel_id":4321,"guild_id":8765}
+//! ```
+//!
+//! Exports are split into multiple parts on line boundaries. Concatenating all
+//! parts in order restores the full export. Parts are passed on as soon as
+//! they are full and imports read them one at a time, so only a single part
+//! is kept in memory.
+
+use super::mem::MemStore;
+use super::{Result, StoreHandler};
+
+use robbot::store::{
+    DataDescriptor, DataQuery, Decimal, Deserialize, Deserializer, Serialize, Serializer, Store,
+    StoreData, UnknownVariant,
+};
+
+use futures::future::BoxFuture;
+use serde_json::{Map, Value};
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+use std::error::Error as StdError;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+/// The current version of the export format.
+pub const EXPORT_VERSION: u64 = 1;
+
+/// The number of rows passed to a resource at once while importing.
+const IMPORT_BATCH_SIZE: usize = 256;
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("unsupported export version {0}, expected version {}", EXPORT_VERSION)]
+    Version(u64),
+    #[error("invalid export: {0}")]
+    Invalid(String),
+    #[error("export aborted, the receiver of the parts was dropped")]
+    Aborted,
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl ExportError {
+    fn invalid<T>(msg: T) -> Self
+    where
+        T: ToString,
+    {
+        Self::Invalid(msg.to_string())
+    }
+}
+
+/// The result of importing a single resource.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImportStatus {
+    /// The number of imported rows.
+    Imported(usize),
+    /// The resource is not registered in the store, its rows were skipped.
+    Skipped(usize),
+}
+
+/// Writes the export and sends every part once it is full.
+pub(super) struct ExportWriter {
+    part: Vec<u8>,
+    max_size: usize,
+    parts: mpsc::Sender<Vec<u8>>,
+}
+
+impl ExportWriter {
+    async fn new(max_size: usize, parts: mpsc::Sender<Vec<u8>>) -> Result<Self> {
+        let mut writer = Self {
+            part: Vec::new(),
+            max_size,
+            parts,
+        };
+
+        let mut header = Map::new();
+        header.insert(String::from("version"), Value::from(EXPORT_VERSION));
+        writer.write_line(&header).await?;
+
+        Ok(writer)
+    }
+
+    async fn write_resource(&mut self, name: &str, rows: usize) -> Result<()> {
+        let mut line = Map::new();
+        line.insert(String::from("resource"), Value::from(name));
+        line.insert(String::from("rows"), Value::from(rows));
+
+        self.write_line(&line).await
+    }
+
+    async fn write_line(&mut self, line: &Map<String, Value>) -> Result<()> {
+        let mut buf = serde_json::to_vec(line).map_err(ExportError::from)?;
+        buf.push(b'\n');
+
+        if !self.part.is_empty() && self.part.len() + buf.len() > self.max_size {
+            let part = std::mem::replace(&mut self.part, buf);
+            self.send(part).await?;
+        } else {
+            self.part.extend(buf);
+        }
+
+        Ok(())
+    }
+
+    /// Sends the last part. There is always one, it contains at least the
+    /// header.
+    async fn finish(mut self) -> Result<()> {
+        let part = std::mem::take(&mut self.part);
+        self.send(part).await
+    }
+
+    async fn send(&mut self, part: Vec<u8>) -> Result<()> {
+        self.parts
+            .send(part)
+            .await
+            .map_err(|_| ExportError::Aborted.into())
+    }
+}
+
+/// A single row of an export.
+type Row = Map<String, Value>;
+
+/// Type-erased functions to export and import a single registered resource.
+pub(super) struct Resource<S>
+where
+    S: Store + Clone,
+{
+    pub(super) export:
+        for<'a> fn(&'a StoreHandler<S>, &'a mut ExportWriter) -> BoxFuture<'a, Result<usize>>,
+    pub(super) import: for<'a> fn(&'a StoreHandler<S>, Vec<Row>) -> BoxFuture<'a, Result<usize>>,
+    pub(super) validate: fn(Vec<Row>) -> Result<()>,
+    pub(super) wipe: for<'a> fn(&'a StoreHandler<S>) -> BoxFuture<'a, Result<()>>,
+    pub(super) count: for<'a> fn(&'a StoreHandler<S>) -> BoxFuture<'a, Result<usize>>,
+}
+
+impl<S> Resource<S>
+where
+    S: Store + Clone + Send + Sync + 'static,
+    S::Error: StdError + Send + Sync + 'static,
+{
+    pub(super) fn new<T>() -> Self
+    where
+        T: StoreData<S> + StoreData<MemStore> + Send + Sync + 'static,
+        <T as StoreData<S>>::DataDescriptor: DataDescriptor<T, MemStore> + Default + Send + Sync,
+        <T as StoreData<S>>::DataQuery: DataQuery<T, MemStore> + Send,
+    {
+        Self {
+            export: export_resource::<T, S>,
+            import: import_resource::<T, S>,
+            validate: validate_resource::<T>,
+            wipe: wipe_resource::<T, S>,
+            count: count_resource::<T, S>,
+        }
+    }
+}
+
+impl<S> Debug for Resource<S>
+where
+    S: Store + Clone,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Resource").finish_non_exhaustive()
+    }
+}
+
+fn export_resource<'a, T, S>(
+    handler: &'a StoreHandler<S>,
+    writer: &'a mut ExportWriter,
+) -> BoxFuture<'a, Result<usize>>
+where
+    S: Store + Clone + Send + Sync + 'static,
+    S::Error: StdError + Send + Sync + 'static,
+    T: StoreData<S> + StoreData<MemStore> + Send + Sync + 'static,
+    <T as StoreData<S>>::DataDescriptor: DataDescriptor<T, MemStore> + Default + Send + Sync,
+{
+    Box::pin(async move {
+        let rows: Vec<T> = handler.get_all(handler.make_descriptor::<T>()).await?;
+        let len = rows.len();
+
+        writer
+            .write_resource(&<T as StoreData<S>>::resource_name(), len)
+            .await?;
+
+        // Rows are serialized and dropped one at a time, full parts are sent
+        // right away.
+        for row in rows {
+            let mut serializer = JsonSerializer::default();
+            <T as StoreData<MemStore>>::serialize(&row, &mut serializer)?;
+            drop(row);
+
+            writer.write_line(&serializer.row).await?;
+        }
+
+        Ok(len)
+    })
+}
+
+fn import_resource<'a, T, S>(
+    handler: &'a StoreHandler<S>,
+    rows: Vec<Row>,
+) -> BoxFuture<'a, Result<usize>>
+where
+    S: Store + Clone + Send + Sync + 'static,
+    S::Error: StdError + Send + Sync + 'static,
+    T: StoreData<S> + StoreData<MemStore> + Send + Sync + 'static,
+{
+    Box::pin(async move {
+        let len = rows.len();
+
+        for row in rows {
+            let mut deserializer = JsonDeserializer::new(row);
+            let data = <T as StoreData<MemStore>>::deserialize(&mut deserializer)?;
+
+            handler.insert(data).await?;
+        }
+
+        Ok(len)
+    })
+}
+
+fn validate_resource<T>(rows: Vec<Row>) -> Result<()>
+where
+    T: StoreData<MemStore>,
+{
+    for row in rows {
+        let mut deserializer = JsonDeserializer::new(row);
+        T::deserialize(&mut deserializer)?;
+    }
+
+    Ok(())
+}
+
+fn wipe_resource<T, S>(handler: &StoreHandler<S>) -> BoxFuture<'_, Result<()>>
+where
+    S: Store + Clone + Send + Sync + 'static,
+    S::Error: StdError + Send + Sync + 'static,
+    T: StoreData<S> + StoreData<MemStore> + Send + Sync + 'static,
+    <T as StoreData<S>>::DataQuery: DataQuery<T, MemStore> + Send,
+{
+    // An empty query matches all rows.
+    Box::pin(handler.delete::<T, _>(<T as StoreData<S>>::query()))
+}
+
+fn count_resource<T, S>(handler: &StoreHandler<S>) -> BoxFuture<'_, Result<usize>>
+where
+    S: Store + Clone + Send + Sync + 'static,
+    S::Error: StdError + Send + Sync + 'static,
+    T: StoreData<S> + StoreData<MemStore> + Send + Sync + 'static,
+    <T as StoreData<S>>::DataDescriptor: DataDescriptor<T, MemStore> + Default + Send + Sync,
+{
+    Box::pin(async move {
+        let rows: Vec<T> = handler.get_all(handler.make_descriptor::<T>()).await?;
+        Ok(rows.len())
+    })
+}
+
+impl<S> StoreHandler<S>
+where
+    S: Store + Clone + Send + Sync + 'static,
+    S::Error: StdError + Send + Sync + 'static,
+{
+    /// Returns the number of rows of every resource created through this
+    /// `StoreHandler`. All rows are loaded to count them, this is meant for
+    /// diagnostics only.
+    pub async fn row_counts(&self) -> Result<Vec<(String, usize)>> {
+        let mut counts = Vec::new();
+
+        for (name, resource) in self.resources() {
+            counts.push((name, (resource.count)(self).await?));
+        }
+
+        Ok(counts)
+    }
+
+    /// Exports all rows of all resources created through this `StoreHandler`.
+    /// The export is split into parts of at most `max_size` bytes, unless a
+    /// single row exceeds it. Every part is sent to `parts` once it is full.
+    ///
+    /// Returns the number of exported rows per resource.
+    pub async fn export(
+        &self,
+        max_size: usize,
+        parts: mpsc::Sender<Vec<u8>>,
+    ) -> Result<Vec<(String, usize)>> {
+        let mut writer = ExportWriter::new(max_size, parts).await?;
+        let mut rows = Vec::new();
+
+        for (name, resource) in self.resources() {
+            let count = (resource.export)(self, &mut writer).await?;
+            rows.push((name, count));
+        }
+
+        writer.finish().await?;
+
+        Ok(rows)
+    }
+
+    /// Starts importing an export created by [`export`]. If `wipe` is `true`,
+    /// all existing rows of every resource contained in the export are deleted
+    /// before importing it. Otherwise the rows are added to the existing rows.
+    ///
+    /// Rows are imported while the parts are passed to the [`Importer`]. An
+    /// invalid part fails the import, rows of previous parts are kept. Use
+    /// [`validate_import`] to check all parts first.
+    ///
+    /// [`export`]: Self::export
+    /// [`validate_import`]: Self::validate_import
+    pub fn import(&self, wipe: bool) -> Importer<'_, S> {
+        Importer::new(self, wipe, false)
+    }
+
+    /// Starts checking an export created by [`export`] without changing the
+    /// store. Fails on the same exports as [`import`].
+    ///
+    /// [`export`]: Self::export
+    /// [`import`]: Self::import
+    pub fn validate_import(&self) -> Importer<'_, S> {
+        Importer::new(self, false, true)
+    }
+}
+
+/// Imports an export part by part. Parts may end anywhere, lines split across
+/// parts are joined.
+///
+/// Created by [`StoreHandler::import`] and [`StoreHandler::validate_import`].
+#[derive(Debug)]
+pub struct Importer<'a, S>
+where
+    S: Store + Clone,
+{
+    handler: &'a StoreHandler<S>,
+    resources: Vec<(String, Arc<Resource<S>>)>,
+    wipe: bool,
+    /// Only deserialize the rows instead of importing them.
+    validate: bool,
+    /// The incomplete last line of the previous part.
+    pending: Vec<u8>,
+    header: bool,
+    /// The resource the next rows belong to.
+    current: Option<ImportResource<S>>,
+    results: Vec<(String, ImportStatus)>,
+}
+
+/// The resource currently imported by an [`Importer`].
+#[derive(Debug)]
+struct ImportResource<S>
+where
+    S: Store + Clone,
+{
+    name: String,
+    /// `None` if the resource is not registered in the store.
+    resource: Option<Arc<Resource<S>>>,
+    rows: usize,
+    remaining: usize,
+    imported: usize,
+    batch: Vec<Row>,
+}
+
+impl<'a, S> Importer<'a, S>
+where
+    S: Store + Clone + Send + Sync + 'static,
+    S::Error: StdError + Send + Sync + 'static,
+{
+    fn new(handler: &'a StoreHandler<S>, wipe: bool, validate: bool) -> Self {
+        Self {
+            handler,
+            resources: handler.resources(),
+            wipe,
+            validate,
+            pending: Vec::new(),
+            header: false,
+            current: None,
+            results: Vec::new(),
+        }
+    }
+
+    /// Reads the next part of the export.
+    pub async fn write(&mut self, part: &[u8]) -> Result<()> {
+        let mut buf = std::mem::take(&mut self.pending);
+        buf.extend_from_slice(part);
+
+        let mut start = 0;
+        while let Some(len) = buf[start..].iter().position(|b| *b == b'\n') {
+            let line =
+                serde_json::from_slice(&buf[start..start + len]).map_err(ExportError::from)?;
+            start += len + 1;
+
+            self.read_line(line).await?;
+        }
+
+        buf.drain(..start);
+        self.pending = buf;
+
+        Ok(())
+    }
+
+    /// Finishes the import after all parts were written. Returns the result
+    /// for every resource contained in the export.
+    pub async fn finish(mut self) -> Result<Vec<(String, ImportStatus)>> {
+        // The last line might not be terminated.
+        if !self.pending.iter().all(u8::is_ascii_whitespace) {
+            let pending = std::mem::take(&mut self.pending);
+            let line = serde_json::from_slice(&pending).map_err(ExportError::from)?;
+
+            self.read_line(line).await?;
+        }
+
+        if !self.header {
+            return Err(ExportError::invalid("missing header").into());
+        }
+
+        if let Some(current) = self.current {
+            return Err(ExportError::invalid(format!(
+                "missing rows for resource {}",
+                current.name
+            ))
+            .into());
+        }
+
+        Ok(self.results)
+    }
+
+    async fn read_line(&mut self, line: Row) -> Result<()> {
+        if !self.header {
+            match line.get("version").and_then(Value::as_u64) {
+                Some(EXPORT_VERSION) => (),
+                Some(version) => return Err(ExportError::Version(version).into()),
+                None => return Err(ExportError::invalid("missing version").into()),
+            }
+
+            self.header = true;
+            return Ok(());
+        }
+
+        match &mut self.current {
+            Some(current) => {
+                current.batch.push(line);
+                current.remaining -= 1;
+
+                if current.batch.len() == IMPORT_BATCH_SIZE {
+                    self.flush().await?;
+                }
+            }
+            None => self.start_resource(line).await?,
+        }
+
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|current| current.remaining == 0)
+        {
+            self.flush().await?;
+
+            // There is always a current resource.
+            let current = self.current.take().unwrap();
+            let status = match current.resource {
+                Some(_) => ImportStatus::Imported(current.imported),
+                None => ImportStatus::Skipped(current.rows),
+            };
+
+            self.results.push((current.name, status));
+        }
+
+        Ok(())
+    }
+
+    async fn start_resource(&mut self, line: Row) -> Result<()> {
+        let name = line
+            .get("resource")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ExportError::invalid("expected resource"))?
+            .to_owned();
+        let rows = line
+            .get("rows")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| ExportError::invalid("expected row count"))? as usize;
+
+        let resource = self
+            .resources
+            .iter()
+            .find(|(resource, _)| *resource == name)
+            .map(|(_, resource)| resource.clone());
+
+        if self.wipe && !self.validate {
+            if let Some(resource) = &resource {
+                (resource.wipe)(self.handler).await?;
+            }
+        }
+
+        self.current = Some(ImportResource {
+            name,
+            resource,
+            rows,
+            remaining: rows,
+            imported: 0,
+            batch: Vec::with_capacity(IMPORT_BATCH_SIZE.min(rows)),
+        });
+
+        Ok(())
+    }
+
+    /// Imports the rows of the current resource read so far.
+    async fn flush(&mut self) -> Result<()> {
+        let current = match &mut self.current {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+
+        let batch = std::mem::take(&mut current.batch);
+        match &current.resource {
+            Some(resource) if self.validate => (resource.validate)(batch)?,
+            Some(resource) => current.imported += (resource.import)(self.handler, batch).await?,
+            None => (),
+        }
+
+        Ok(())
+    }
+}
+
+/// A [`Serializer`] writing a single row into a JSON object.
+#[derive(Debug, Default)]
+struct JsonSerializer {
+    row: Map<String, Value>,
+    value: Value,
+}
+
+impl JsonSerializer {
+    fn write<T>(&mut self, value: T) -> std::result::Result<(), ExportError>
+    where
+        T: Into<Value>,
+    {
+        self.value = value.into();
+        Ok(())
+    }
+}
+
+impl Serializer<MemStore> for JsonSerializer {
+    type Error = ExportError;
+
+    fn serialize_bool(&mut self, v: bool) -> std::result::Result<(), Self::Error> {
+        self.write(v)
+    }
+
+    fn serialize_i8(&mut self, v: i8) -> std::result::Result<(), Self::Error> {
+        self.write(v)
+    }
+
+    fn serialize_i16(&mut self, v: i16) -> std::result::Result<(), Self::Error> {
+        self.write(v)
+    }
+
+    fn serialize_i32(&mut self, v: i32) -> std::result::Result<(), Self::Error> {
+        self.write(v)
+    }
+
+    fn serialize_i64(&mut self, v: i64) -> std::result::Result<(), Self::Error> {
+        self.write(v)
+    }
+
+    fn serialize_u8(&mut self, v: u8) -> std::result::Result<(), Self::Error> {
+        self.write(v)
+    }
+
+    fn serialize_u16(&mut self, v: u16) -> std::result::Result<(), Self::Error> {
+        self.write(v)
+    }
+
+    fn serialize_u32(&mut self, v: u32) -> std::result::Result<(), Self::Error> {
+        self.write(v)
+    }
+
+    fn serialize_u64(&mut self, v: u64) -> std::result::Result<(), Self::Error> {
+        self.write(v)
+    }
+
+    fn serialize_f32(&mut self, v: f32) -> std::result::Result<(), Self::Error> {
+        self.write(v)
+    }
+
+    fn serialize_f64(&mut self, v: f64) -> std::result::Result<(), Self::Error> {
+        self.write(v)
+    }
+
+    fn serialize_str(&mut self, v: &str) -> std::result::Result<(), Self::Error> {
+        self.write(v)
+    }
+
+    // Wide numbers are written as strings, JSON numbers lose precision past
+    // 2^53 in most readers.
+    fn serialize_u128(&mut self, v: u128) -> std::result::Result<(), Self::Error> {
+        self.write(v.to_string())
+    }
+
+    fn serialize_i128(&mut self, v: i128) -> std::result::Result<(), Self::Error> {
+        self.write(v.to_string())
+    }
+
+    fn serialize_decimal(&mut self, v: &Decimal) -> std::result::Result<(), Self::Error> {
+        self.write(v.to_string())
+    }
+
+    fn serialize_field<T>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> std::result::Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize<MemStore>,
+    {
+        value.serialize(self)?;
+
+        let value = std::mem::take(&mut self.value);
+        self.row.insert(key.to_owned(), value);
+
+        Ok(())
+    }
+
+    fn serialize_field_in<T>(
+        &mut self,
+        key: &'static str,
+        _values: &[T],
+    ) -> std::result::Result<(), Self::Error>
+    where
+        T: Serialize<MemStore>,
+    {
+        Err(ExportError::invalid(format!(
+            "cannot export multiple values in the field `{}`",
+            key
+        )))
+    }
+}
+
+/// A [`Deserializer`] reading a single row from a JSON object.
+#[derive(Debug)]
+struct JsonDeserializer {
+    row: Map<String, Value>,
+    key: &'static str,
+    value: Option<Value>,
+}
+
+impl JsonDeserializer {
+    fn new(row: Map<String, Value>) -> Self {
+        Self {
+            row,
+            key: "",
+            value: None,
+        }
+    }
+
+    fn read<T, F>(&mut self, f: F) -> std::result::Result<T, ExportError>
+    where
+        F: FnOnce(&Value) -> Option<T>,
+    {
+        self.value
+            .take()
+            .as_ref()
+            .and_then(f)
+            .ok_or_else(|| ExportError::invalid(format!("invalid value for field `{}`", self.key)))
+    }
+
+    fn read_int<T>(&mut self) -> std::result::Result<T, ExportError>
+    where
+        T: TryFrom<i64> + TryFrom<u64>,
+    {
+        self.read(|value| match value.as_u64() {
+            Some(v) => T::try_from(v).ok(),
+            None => value.as_i64().and_then(|v| T::try_from(v).ok()),
+        })
+    }
+}
+
+impl Deserializer<MemStore> for JsonDeserializer {
+    type Error = ExportError;
+
+    fn deserialize_bool(&mut self) -> std::result::Result<bool, Self::Error> {
+        self.read(Value::as_bool)
+    }
+
+    fn deserialize_i8(&mut self) -> std::result::Result<i8, Self::Error> {
+        self.read_int()
+    }
+
+    fn deserialize_i16(&mut self) -> std::result::Result<i16, Self::Error> {
+        self.read_int()
+    }
+
+    fn deserialize_i32(&mut self) -> std::result::Result<i32, Self::Error> {
+        self.read_int()
+    }
+
+    fn deserialize_i64(&mut self) -> std::result::Result<i64, Self::Error> {
+        self.read_int()
+    }
+
+    fn deserialize_u8(&mut self) -> std::result::Result<u8, Self::Error> {
+        self.read_int()
+    }
+
+    fn deserialize_u16(&mut self) -> std::result::Result<u16, Self::Error> {
+        self.read_int()
+    }
+
+    fn deserialize_u32(&mut self) -> std::result::Result<u32, Self::Error> {
+        self.read_int()
+    }
+
+    fn deserialize_u64(&mut self) -> std::result::Result<u64, Self::Error> {
+        self.read_int()
+    }
+
+    fn deserialize_f32(&mut self) -> std::result::Result<f32, Self::Error> {
+        self.read(|value| value.as_f64().map(|v| v as f32))
+    }
+
+    fn deserialize_f64(&mut self) -> std::result::Result<f64, Self::Error> {
+        self.read(Value::as_f64)
+    }
+
+    fn deserialize_string(&mut self) -> std::result::Result<String, Self::Error> {
+        self.read(|value| value.as_str().map(ToOwned::to_owned))
+    }
+
+    fn deserialize_u128(&mut self) -> std::result::Result<u128, Self::Error> {
+        self.read(|value| value.as_str()?.parse().ok())
+    }
+
+    fn deserialize_i128(&mut self) -> std::result::Result<i128, Self::Error> {
+        self.read(|value| value.as_str()?.parse().ok())
+    }
+
+    fn deserialize_decimal(&mut self) -> std::result::Result<Decimal, Self::Error> {
+        self.read(|value| value.as_str()?.parse().ok())
+    }
+
+    fn deserialize_field<T>(&mut self, key: &'static str) -> std::result::Result<T, Self::Error>
+    where
+        T: Sized + Deserialize<MemStore>,
+    {
+        self.key = key;
+        self.value = Some(
+            self.row
+                .remove(key)
+                .ok_or_else(|| ExportError::invalid(format!("missing field `{}`", key)))?,
+        );
+
+        T::deserialize(self)
+    }
+
+    fn unknown_variant(&mut self, err: UnknownVariant) -> Self::Error {
+        ExportError::invalid(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExportError, ImportStatus};
+    use crate::store::mem::MemStore;
+    use crate::store::{Result, StoreHandler};
+
+    use robbot::store::{create, get, insert, Decimal};
+    use robbot::StoreData;
+
+    use tokio::sync::mpsc;
+
+    use std::error::Error as StdError;
+
+    #[derive(Clone, Debug, PartialEq, StoreData)]
+    struct ExportData {
+        id: u64,
+        offset: i32,
+        name: String,
+        enabled: bool,
+    }
+
+    #[derive(Clone, Debug, PartialEq, StoreData)]
+    struct OtherData {
+        value: f64,
+    }
+
+    #[derive(Clone, Debug, PartialEq, StoreData)]
+    struct WideData {
+        id: u128,
+        balance: i128,
+        amount: Decimal,
+    }
+
+    fn handler() -> StoreHandler<MemStore> {
+        StoreHandler::new_in_memory(MemStore::default())
+    }
+
+    /// Exports `handler` and collects the parts.
+    async fn export(
+        handler: &StoreHandler<MemStore>,
+        max_size: usize,
+    ) -> (Vec<Vec<u8>>, Vec<(String, usize)>) {
+        let (sender, mut receiver) = mpsc::channel(1);
+
+        let collect = async {
+            let mut parts = Vec::new();
+            while let Some(part) = receiver.recv().await {
+                parts.push(part);
+            }
+            parts
+        };
+
+        let (rows, parts) = tokio::join!(handler.export(max_size, sender), collect);
+        (parts, rows.unwrap())
+    }
+
+    async fn import(
+        handler: &StoreHandler<MemStore>,
+        buf: &[u8],
+        wipe: bool,
+    ) -> Result<Vec<(String, ImportStatus)>> {
+        let mut importer = handler.import(wipe);
+        importer.write(buf).await?;
+        importer.finish().await
+    }
+
+    fn data(id: u64) -> ExportData {
+        ExportData {
+            id,
+            offset: -(id as i32),
+            name: format!("name \"{}\"\n", id),
+            enabled: id > 100,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_import() {
+        let source = handler();
+        create!(source, ExportData).await.unwrap();
+        create!(source, OtherData).await.unwrap();
+
+        let rows: Vec<_> = (0..300).map(data).collect();
+        for row in rows.iter().cloned() {
+            insert!(source, row).await.unwrap();
+        }
+        insert!(source, OtherData { value: 1.5 }).await.unwrap();
+
+        // Force the export into multiple parts.
+        let (parts, export_rows) = export(&source, 1024).await;
+        assert!(parts.len() > 1);
+        assert!(parts.iter().all(|part| part.len() <= 1024));
+        assert_eq!(
+            export_rows,
+            [
+                (String::from("ExportData"), 300),
+                (String::from("OtherData"), 1)
+            ]
+        );
+        assert_eq!(source.row_counts().await.unwrap(), export_rows);
+
+        // Dropping the receiver aborts the export.
+        let (sender, receiver) = mpsc::channel(1);
+        drop(receiver);
+        assert!(source.export(1024, sender).await.is_err());
+
+        // `OtherData` is not registered on the target and skipped.
+        let target = handler();
+        create!(target, ExportData).await.unwrap();
+        insert!(target, data(1000)).await.unwrap();
+
+        let mut importer = target.import(false);
+        for part in &parts {
+            importer.write(part).await.unwrap();
+        }
+        let result = importer.finish().await.unwrap();
+        assert_eq!(
+            result,
+            [
+                (String::from("ExportData"), ImportStatus::Imported(300)),
+                (String::from("OtherData"), ImportStatus::Skipped(1))
+            ]
+        );
+
+        let mut expected = vec![data(1000)];
+        expected.extend(rows.iter().cloned());
+        assert_eq!(get!(target, ExportData).await.unwrap(), expected);
+
+        // Wiping replaces all existing rows. Lines may be split across parts.
+        let mut importer = target.import(true);
+        for chunk in parts.concat().chunks(100) {
+            importer.write(chunk).await.unwrap();
+        }
+        importer.finish().await.unwrap();
+        assert_eq!(get!(target, ExportData).await.unwrap(), rows);
+    }
+
+    #[tokio::test]
+    async fn test_import_invalid() {
+        let target = handler();
+        create!(target, ExportData).await.unwrap();
+
+        let err = import(&target, b"{\"version\":2}\n", false)
+            .await
+            .unwrap_err();
+        let err: &(dyn StdError + 'static) = err.as_ref();
+        assert!(matches!(
+            err.downcast_ref::<ExportError>(),
+            Some(ExportError::Version(2))
+        ));
+
+        let buf = b"{\"version\":1}\n{\"resource\":\"ExportData\",\"rows\":2}\n{\"id\":1,\"offset\":0,\"name\":\"\",\"enabled\":true}\n";
+        assert!(import(&target, buf, false).await.is_err());
+        assert!(import(&target, b"", false).await.is_err());
+
+        // Validating an invalid export does not touch the store.
+        insert!(target, data(1)).await.unwrap();
+        let buf = b"{\"version\":1}\n{\"resource\":\"ExportData\",\"rows\":2}\n{\"id\":2,\"offset\":0,\"name\":\"\",\"enabled\":true}\n{\"id\":-1,\"offset\":0,\"name\":\"\",\"enabled\":true}\n";
+        let mut importer = target.validate_import();
+        assert!(importer.write(buf).await.is_err());
+        assert_eq!(get!(target, ExportData).await.unwrap(), [data(1)]);
+
+        assert!(import(&target, buf, false).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_export_wide() {
+        let source = handler();
+        create!(source, WideData).await.unwrap();
+
+        let row = WideData {
+            id: u128::MAX,
+            balance: i128::MIN,
+            amount: "-12345678.000000000000000000000000000001".parse().unwrap(),
+        };
+        insert!(source, row.clone()).await.unwrap();
+
+        // Wide numbers are exported as strings.
+        let buf = export(&source, 1024).await.0.concat();
+        let export = String::from_utf8(buf.clone()).unwrap();
+        assert!(export.contains("\"id\":\"340282366920938463463374607431768211455\""));
+        assert!(export.contains("\"amount\":\"-12345678.000000000000000000000000000001\""));
+
+        let target = handler();
+        create!(target, WideData).await.unwrap();
+        import(&target, &buf, false).await.unwrap();
+        assert_eq!(get!(target, WideData).await.unwrap(), [row]);
+
+        let buf = b"{\"version\":1}\n{\"resource\":\"WideData\",\"rows\":1}\n{\"id\":\"1\",\"balance\":\"0\",\"amount\":\"1e5\"}\n";
+        assert!(import(&target, buf, false).await.is_err());
+    }
+}
@@ -0,0 +1,696 @@
+use super::export::Resource;
+use super::invalidate::{self, Invalidation, InvalidationBus};
+use super::mem::MemStore;
+use super::mysql::MysqlStore;
+use super::{Error, Result};
+
+use robbot::model::id::GuildId;
+use robbot::store::lazy::LazyStore;
+use robbot::store::{DataDescriptor, DataQuery, OrderBy, Serialize, Serializer, Store, StoreData};
+
+use parking_lot::RwLock;
+use thiserror::Error;
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error as StdError;
+use std::hash::Hash;
+use std::result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The time to wait between two connection attempts.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Returned by [`StoreHandler::check_writable`] when the bot is running
+/// in degraded mode.
+#[derive(Copy, Clone, Debug, Error)]
+#[error("bot is running without its database")]
+pub struct Degraded;
+
+/// Returned by [`StoreHandler::update`] when a versioned item was changed
+/// since it was read.
+#[derive(Copy, Clone, Debug, Error)]
+#[error("data was modified concurrently")]
+pub struct Conflict;
+
+/// A handle to the store used by the bot.
+///
+/// If the configured store `S` is unreachable the `StoreHandler` can run in
+/// *degraded* mode, using a [`MemStore`] until the real store becomes
+/// available again. Writing data while degraded would lose that data on
+/// recovery, so all writes fail with [`Degraded`]. Commands should call
+/// [`check_writable`] before doing anything they cannot undo.
+///
+/// [`check_writable`]: Self::check_writable
+#[derive(Clone, Debug)]
+pub struct StoreHandler<S = MysqlStore>
+where
+    S: Store + Clone,
+{
+    inner: Arc<InnerStoreHandler<S>>,
+}
+
+#[derive(Debug)]
+struct InnerStoreHandler<S>
+where
+    S: Store + Clone,
+{
+    uri: String,
+    backend: RwLock<Backend<S>>,
+    degraded: AtomicBool,
+    /// All resources created through this handler, by resource name.
+    resources: RwLock<BTreeMap<String, Arc<Resource<S>>>>,
+    invalidations: InvalidationBus,
+}
+
+#[derive(Clone, Debug)]
+enum Backend<S>
+where
+    S: Store + Clone,
+{
+    Primary(LazyStore<S>),
+    Fallback(MemStore),
+}
+
+impl<S> StoreHandler<S>
+where
+    S: Store + Clone + Send + Sync + 'static,
+    S::Error: StdError + Send + Sync + 'static,
+{
+    /// Creates a new `StoreHandler`. The connection to the store is opened
+    /// when it is first used.
+    pub fn new(uri: &str) -> Self {
+        Self::from_backend(uri, Backend::Primary(LazyStore::new(uri)), false)
+    }
+
+    /// Creates a new `StoreHandler` in degraded mode, backed by `fallback`.
+    pub fn new_degraded(uri: &str, fallback: MemStore) -> Self {
+        Self::from_backend(uri, Backend::Fallback(fallback), true)
+    }
+
+    /// Creates a new `StoreHandler` backed by `store` only, e.g. for tests.
+    /// Unlike in degraded mode writes are accepted, there is no other store
+    /// to lose them to.
+    pub fn new_in_memory(store: MemStore) -> Self {
+        Self::from_backend("", Backend::Fallback(store), false)
+    }
+
+    fn from_backend(uri: &str, backend: Backend<S>, degraded: bool) -> Self {
+        Self {
+            inner: Arc::new(InnerStoreHandler {
+                uri: uri.to_owned(),
+                backend: RwLock::new(backend),
+                degraded: AtomicBool::new(degraded),
+                resources: RwLock::default(),
+                invalidations: InvalidationBus::new(),
+            }),
+        }
+    }
+
+    /// Opens the connection to the store, trying up to `retries` additional
+    /// times after the first failed attempt.
+    ///
+    /// If all attempts fail and `fallback` is `true`, the `StoreHandler`
+    /// switches into degraded mode and keeps trying to connect in the
+    /// background. Otherwise the last connection error is returned.
+    pub async fn connect(&self, retries: u32, fallback: bool) -> Result<()> {
+        let mut attempt = 0;
+
+        let err = loop {
+            match S::connect(&self.inner.uri).await {
+                Ok(store) => {
+                    self.recover(store);
+                    return Ok(());
+                }
+                Err(err) => {
+                    log::warn!(
+                        "[STORE] Failed to connect to store (attempt {}/{}): {}",
+                        attempt + 1,
+                        retries + 1,
+                        err
+                    );
+
+                    if attempt >= retries {
+                        break err;
+                    }
+                }
+            }
+
+            attempt += 1;
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        };
+
+        if !fallback {
+            return Err(err.into());
+        }
+
+        log::error!("[STORE] Store unavailable, running in degraded mode");
+        self.degrade(MemStore::default());
+
+        let handler = self.clone();
+        tokio::task::spawn(async move {
+            loop {
+                tokio::time::sleep(RETRY_INTERVAL).await;
+
+                match S::connect(&handler.inner.uri).await {
+                    Ok(store) => {
+                        handler.recover(store);
+                        break;
+                    }
+                    Err(err) => log::debug!("[STORE] Reconnect failed: {}", err),
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Switches the `StoreHandler` into degraded mode, using `fallback`
+    /// for all operations.
+    pub fn degrade(&self, fallback: MemStore) {
+        *self.inner.backend.write() = Backend::Fallback(fallback);
+        self.inner.degraded.store(true, Ordering::Release);
+
+        // Cached data may not exist in the fallback store.
+        self.inner.invalidations.publish_all();
+    }
+
+    /// Swaps in the connected `store`, leaving degraded mode.
+    pub fn recover(&self, store: S) {
+        *self.inner.backend.write() =
+            Backend::Primary(LazyStore::from_store(&self.inner.uri, store));
+
+        if self.inner.degraded.swap(false, Ordering::AcqRel) {
+            log::info!("[STORE] Store connection recovered, leaving degraded mode");
+        }
+
+        // Cached data may be from the fallback store.
+        self.inner.invalidations.publish_all();
+    }
+
+    /// Returns `true` if the `StoreHandler` is running in degraded mode.
+    pub fn is_degraded(&self) -> bool {
+        self.inner.degraded.load(Ordering::Acquire)
+    }
+
+    /// Returns an error if the `StoreHandler` is running in degraded mode.
+    /// Writes fail in degraded mode, commands should call this before
+    /// starting anything they cannot undo.
+    pub fn check_writable(&self) -> Result<()> {
+        if self.is_degraded() {
+            Err(Degraded.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the [`InvalidationBus`] notified after every successful insert
+    /// and delete.
+    pub fn invalidations(&self) -> &InvalidationBus {
+        &self.inner.invalidations
+    }
+
+    fn invalidate<T>(&self, guild_id: Option<GuildId>)
+    where
+        T: StoreData<S>,
+    {
+        self.inner.invalidations.publish(&Invalidation {
+            resource: T::resource_name(),
+            guild_id,
+        });
+    }
+
+    fn backend(&self) -> Backend<S> {
+        self.inner.backend.read().clone()
+    }
+
+    /// Creates the resource for `T` in the store. The resource is registered in
+    /// the `StoreHandler` and included in all future [`export`]s.
+    ///
+    /// [`export`]: Self::export
+    pub async fn create<T, D>(&self, descriptor: D) -> Result<()>
+    where
+        T: StoreData<S> + StoreData<MemStore> + Send + Sync + 'static,
+        D: DataDescriptor<T, S> + DataDescriptor<T, MemStore> + Send + Sync,
+        <T as StoreData<S>>::DataDescriptor: DataDescriptor<T, MemStore> + Default + Send + Sync,
+        <T as StoreData<S>>::DataQuery: DataQuery<T, MemStore> + Send,
+    {
+        match self.backend() {
+            Backend::Primary(store) => store.create(descriptor).await.map_err(Error::from)?,
+            Backend::Fallback(store) => store.create(descriptor).await.map_err(Error::from)?,
+        }
+
+        self.inner.resources.write().insert(
+            <T as StoreData<S>>::resource_name(),
+            Arc::new(Resource::new::<T>()),
+        );
+
+        Ok(())
+    }
+
+    /// Returns all resources created through this `StoreHandler`, ordered by
+    /// their name.
+    pub(super) fn resources(&self) -> Vec<(String, Arc<Resource<S>>)> {
+        self.inner
+            .resources
+            .read()
+            .iter()
+            .map(|(name, resource)| (name.clone(), resource.clone()))
+            .collect()
+    }
+
+    pub async fn delete<T, Q>(&self, query: Q) -> Result<()>
+    where
+        T: StoreData<S> + StoreData<MemStore> + Send + Sync + 'static,
+        Q: DataQuery<T, S> + DataQuery<T, MemStore> + Send,
+    {
+        self.check_writable()?;
+
+        let guild_id = invalidate::query_guild_id::<T, S, Q>(&query);
+
+        match self.backend() {
+            Backend::Primary(store) => store.delete(query).await.map_err(Error::from)?,
+            Backend::Fallback(store) => store.delete(query).await.map_err(Error::from)?,
+        }
+
+        self.invalidate::<T>(guild_id);
+        Ok(())
+    }
+
+    pub async fn get<T, D, Q>(&self, descriptor: D, query: Q) -> Result<Vec<T>>
+    where
+        T: StoreData<S> + StoreData<MemStore> + Send + Sync + 'static,
+        D: DataDescriptor<T, S> + DataDescriptor<T, MemStore> + Send + Sync,
+        Q: DataQuery<T, S> + DataQuery<T, MemStore> + Send,
+    {
+        match self.backend() {
+            Backend::Primary(store) => store.get(descriptor, query).await.map_err(Error::from),
+            Backend::Fallback(store) => store.get(descriptor, query).await.map_err(Error::from),
+        }
+    }
+
+    pub async fn get_all<T, D>(&self, descriptor: D) -> Result<Vec<T>>
+    where
+        T: StoreData<S> + StoreData<MemStore> + Send + Sync + 'static,
+        D: DataDescriptor<T, S> + DataDescriptor<T, MemStore> + Send + Sync,
+    {
+        match self.backend() {
+            Backend::Primary(store) => store.get_all(descriptor).await.map_err(Error::from),
+            Backend::Fallback(store) => store.get_all(descriptor).await.map_err(Error::from),
+        }
+    }
+
+    pub async fn get_ordered<T, D, Q>(
+        &self,
+        descriptor: D,
+        query: Q,
+        order: OrderBy,
+    ) -> Result<Vec<T>>
+    where
+        T: StoreData<S> + StoreData<MemStore> + Send + Sync + 'static,
+        D: DataDescriptor<T, S> + DataDescriptor<T, MemStore> + Send + Sync,
+        Q: DataQuery<T, S> + DataQuery<T, MemStore> + Send,
+    {
+        match self.backend() {
+            Backend::Primary(store) => store
+                .get_ordered(descriptor, query, order)
+                .await
+                .map_err(Error::from),
+            Backend::Fallback(store) => store
+                .get_ordered(descriptor, query, order)
+                .await
+                .map_err(Error::from),
+        }
+    }
+
+    pub async fn get_one<T, D, Q>(&self, descriptor: D, query: Q) -> Result<Option<T>>
+    where
+        T: StoreData<S> + StoreData<MemStore> + Send + Sync + 'static,
+        D: DataDescriptor<T, S> + DataDescriptor<T, MemStore> + Send + Sync,
+        Q: DataQuery<T, S> + DataQuery<T, MemStore> + Send + Sync,
+    {
+        match self.backend() {
+            Backend::Primary(store) => store.get_one(descriptor, query).await.map_err(Error::from),
+            Backend::Fallback(store) => store.get_one(descriptor, query).await.map_err(Error::from),
+        }
+    }
+
+    /// Returns all items of type `C` that belong to one of `parents`, grouped
+    /// by the key of their parent. Only a single query is sent to the store,
+    /// regardless of the number of parents.
+    ///
+    /// `parent_key` returns the key of a parent and `child_key` returns the key
+    /// of the parent a child belongs to. `query` builds the query for the
+    /// children from the keys of all parents, usually using an `_in` filter:
+    ///
+    /// ```ignore
+    /// let members = store
+    ///     .children(&links, |l| l.id, |m: &GuildMember| m.link_id, |q, ids| q.link_id_in(ids))
+    ///     .await?;
+    /// ```
+    ///
+    /// Every parent key is contained in the returned map, parents without
+    /// children map to an empty [`Vec`].
+    pub async fn children<C, P, K, Q>(
+        &self,
+        parents: &[P],
+        parent_key: impl Fn(&P) -> K,
+        child_key: impl Fn(&C) -> K,
+        query: impl FnOnce(<C as StoreData<S>>::DataQuery, Vec<K>) -> Q,
+    ) -> Result<HashMap<K, Vec<C>>>
+    where
+        C: StoreData<S> + StoreData<MemStore> + Send + Sync + 'static,
+        <C as StoreData<S>>::DataDescriptor: DataDescriptor<C, MemStore> + Default + Send + Sync,
+        <C as StoreData<S>>::DataQuery: Default,
+        Q: DataQuery<C, S> + DataQuery<C, MemStore> + Send,
+        K: Clone + Eq + Hash,
+    {
+        let mut children: HashMap<K, Vec<C>> = HashMap::with_capacity(parents.len());
+
+        let mut keys = Vec::with_capacity(parents.len());
+        for parent in parents {
+            let key = parent_key(parent);
+
+            if !children.contains_key(&key) {
+                children.insert(key.clone(), Vec::new());
+                keys.push(key);
+            }
+        }
+
+        if keys.is_empty() {
+            return Ok(children);
+        }
+
+        let descriptor = self.make_descriptor::<C>();
+        let query = query(self.make_query::<C>(), keys);
+
+        for child in self.get(descriptor, query).await? {
+            if let Some(entries) = children.get_mut(&child_key(&child)) {
+                entries.push(child);
+            }
+        }
+
+        Ok(children)
+    }
+
+    pub async fn insert<T>(&self, data: T) -> Result<()>
+    where
+        T: StoreData<S> + StoreData<MemStore> + Send + Sync + 'static,
+    {
+        self.check_writable()?;
+
+        let guild_id = invalidate::data_guild_id::<T, S>(&data);
+
+        match self.backend() {
+            Backend::Primary(store) => store.insert(data).await.map_err(Error::from)?,
+            Backend::Fallback(store) => store.insert(data).await.map_err(Error::from)?,
+        }
+
+        self.invalidate::<T>(guild_id);
+        Ok(())
+    }
+
+    /// Replaces all items matching `query` with `data`.
+    ///
+    /// If `T` has a `#[store(version)]` field, only items still having the
+    /// version of `data` are replaced and the version is incremented. If no
+    /// item matches [`Conflict`] is returned: the item was changed or deleted
+    /// since `data` was read, the caller should read it again and retry.
+    pub async fn update<T, Q>(&self, query: Q, mut data: T) -> Result<()>
+    where
+        T: StoreData<S> + StoreData<MemStore> + Send + Sync + 'static,
+        Q: DataQuery<T, S> + DataQuery<T, MemStore> + Send,
+        u64: Serialize<S>,
+    {
+        self.check_writable()?;
+
+        let guild_id = invalidate::query_guild_id::<T, S, Q>(&query);
+
+        let version = <T as StoreData<S>>::version(&data);
+        if let Some(version) = version {
+            <T as StoreData<S>>::set_version(&mut data, version.wrapping_add(1));
+        }
+
+        let query = Versioned { query, version };
+
+        let updated = match self.backend() {
+            Backend::Primary(store) => store.update(query, data).await.map_err(Error::from)?,
+            Backend::Fallback(store) => store.update(query, data).await.map_err(Error::from)?,
+        };
+
+        if version.is_some() && updated == 0 {
+            return Err(Conflict.into());
+        }
+
+        self.invalidate::<T>(guild_id);
+        Ok(())
+    }
+
+    pub fn make_descriptor<T>(&self) -> <T as StoreData<S>>::DataDescriptor
+    where
+        T: StoreData<S>,
+        <T as StoreData<S>>::DataDescriptor: Default,
+    {
+        Default::default()
+    }
+
+    pub fn make_query<T>(&self) -> <T as StoreData<S>>::DataQuery
+    where
+        T: StoreData<S>,
+        <T as StoreData<S>>::DataQuery: Default,
+    {
+        Default::default()
+    }
+}
+
+/// A query additionally matching the `version` field, if given.
+struct Versioned<Q> {
+    query: Q,
+    version: Option<u64>,
+}
+
+impl<T, U, Q> DataQuery<T, U> for Versioned<Q>
+where
+    T: StoreData<U>,
+    U: Store,
+    Q: DataQuery<T, U>,
+    u64: Serialize<U>,
+{
+    fn serialize<S>(&self, serializer: &mut S) -> result::Result<(), S::Error>
+    where
+        S: Serializer<U>,
+    {
+        self.query.serialize(serializer)?;
+
+        if let Some(version) = &self.version {
+            serializer.serialize_field("version", version)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Conflict, Degraded, StoreHandler};
+    use crate::store::mem::MemStore;
+
+    use robbot::store::{delete, get, get_one, insert, Store};
+    use robbot::StoreData;
+
+    #[derive(Clone, Debug, PartialEq, StoreData)]
+    struct TestData {
+        id: u64,
+    }
+
+    #[derive(Clone, Debug, PartialEq, StoreData)]
+    struct TestChild {
+        id: u64,
+        parent_id: u64,
+    }
+
+    #[derive(Clone, Debug, PartialEq, StoreData)]
+    struct TestSettings {
+        id: u64,
+        value: String,
+        #[store(version)]
+        version: u64,
+    }
+
+    #[tokio::test]
+    async fn test_store_handler_recover() {
+        let fallback = MemStore::default();
+        let primary = MemStore::default();
+        primary.insert(TestData { id: 2 }).await.unwrap();
+
+        let handler: StoreHandler<MemStore> = StoreHandler::new_degraded("", fallback.clone());
+        assert!(handler.is_degraded());
+        assert!(handler.check_writable().is_err());
+
+        // Writes are rejected while degraded, reads use the fallback.
+        let err = insert!(handler, TestData { id: 3 }).await.unwrap_err();
+        assert!(err.is::<Degraded>());
+        let err = delete!(handler, TestData => { id == 1 }).await.unwrap_err();
+        assert!(err.is::<Degraded>());
+        let query = handler.make_query::<TestData>().id(1);
+        let err = handler.update(query, TestData { id: 3 }).await.unwrap_err();
+        assert!(err.is::<Degraded>());
+
+        fallback.insert(TestData { id: 1 }).await.unwrap();
+        assert_eq!(get!(handler, TestData).await.unwrap(), [TestData { id: 1 }]);
+
+        handler.recover(primary);
+        assert!(!handler.is_degraded());
+        assert!(handler.check_writable().is_ok());
+
+        assert_eq!(get!(handler, TestData).await.unwrap(), [TestData { id: 2 }]);
+        assert_eq!(
+            get!(fallback, TestData).await.unwrap(),
+            [TestData { id: 1 }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_store_handler_children() {
+        let handler: StoreHandler<MemStore> = StoreHandler::new_in_memory(MemStore::default());
+
+        for (id, parent_id) in [(1, 1), (2, 2), (3, 1), (4, 4)] {
+            insert!(handler, TestChild { id, parent_id }).await.unwrap();
+        }
+
+        let parents = [TestData { id: 1 }, TestData { id: 2 }, TestData { id: 3 }];
+
+        let children = handler
+            .children(
+                &parents,
+                |parent| parent.id,
+                |child: &TestChild| child.parent_id,
+                |query, ids| query.parent_id_in(ids),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(children.len(), 3);
+        assert_eq!(
+            children[&1],
+            [
+                TestChild {
+                    id: 1,
+                    parent_id: 1
+                },
+                TestChild {
+                    id: 3,
+                    parent_id: 1
+                }
+            ]
+        );
+        assert_eq!(
+            children[&2],
+            [TestChild {
+                id: 2,
+                parent_id: 2
+            }]
+        );
+        assert_eq!(children[&3], []);
+
+        let children = handler
+            .children(
+                &[] as &[TestData],
+                |parent| parent.id,
+                |child: &TestChild| child.parent_id,
+                |query, ids| query.parent_id_in(ids),
+            )
+            .await
+            .unwrap();
+        assert!(children.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_store_handler_update_conflict() {
+        let handler: StoreHandler<MemStore> = StoreHandler::new_in_memory(MemStore::default());
+
+        insert!(
+            handler,
+            TestSettings {
+                id: 1,
+                value: String::from("a"),
+                version: 0,
+            }
+        )
+        .await
+        .unwrap();
+
+        // Both read the same version before either writes.
+        let first = get_one!(handler, TestSettings => { id == 1 })
+            .await
+            .unwrap()
+            .unwrap();
+        let second = get_one!(handler, TestSettings => { id == 1 })
+            .await
+            .unwrap()
+            .unwrap();
+
+        let query = || handler.make_query::<TestSettings>().id(1);
+
+        handler
+            .update(
+                query(),
+                TestSettings {
+                    value: String::from("b"),
+                    ..first
+                },
+            )
+            .await
+            .unwrap();
+
+        let err = handler
+            .update(
+                query(),
+                TestSettings {
+                    value: String::from("c"),
+                    ..second
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(err.is::<Conflict>());
+
+        let stored = get!(handler, TestSettings).await.unwrap();
+        assert_eq!(
+            stored,
+            [TestSettings {
+                id: 1,
+                value: String::from("b"),
+                version: 1,
+            }]
+        );
+
+        // Reading again and retrying succeeds.
+        let third = stored.into_iter().next().unwrap();
+        handler
+            .update(
+                query(),
+                TestSettings {
+                    value: String::from("c"),
+                    ..third
+                },
+            )
+            .await
+            .unwrap();
+
+        let stored = get_one!(handler, TestSettings => { id == 1 })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.value, "c");
+        assert_eq!(stored.version, 2);
+
+        // Updating a deleted item is a conflict as well.
+        let err = handler
+            .update(handler.make_query::<TestSettings>().id(2), stored)
+            .await
+            .unwrap_err();
+        assert!(err.is::<Conflict>());
+    }
+}
@@ -0,0 +1,402 @@
+//! Notifications about changed store data.
+//!
+//! Caches of store data register a callback for the resources they cache on
+//! the [`InvalidationBus`] of the [`StoreHandler`]. After every successful
+//! insert and delete the `StoreHandler` publishes an [`Invalidation`] for the
+//! changed resource, targeted at a single guild if the guild is known from
+//! the written data or the delete query. Callbacks run synchronously on the
+//! writing task and should only drop cached entries.
+//!
+//! [`StoreHandler`]: super::StoreHandler
+use robbot::model::id::GuildId;
+use robbot::store::{DataQuery, Serialize, Serializer, Store, StoreData};
+
+use parking_lot::RwLock;
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+/// The name of the field used to target an [`Invalidation`] at a guild.
+const GUILD_ID_FIELD: &str = "guild_id";
+
+type Callback = Arc<dyn Fn(&Invalidation) + Send + Sync>;
+
+/// Data of a resource changed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Invalidation {
+    /// The name of the changed resource, see [`StoreData::resource_name`].
+    pub resource: String,
+    /// The guild whose data changed. `None` if the change may affect any
+    /// guild, e.g. for data without a `guild_id` field or for deletes not
+    /// filtered by a single guild.
+    pub guild_id: Option<GuildId>,
+}
+
+/// Routes [`Invalidation`]s to the callbacks registered for their resource.
+#[derive(Default)]
+pub struct InvalidationBus {
+    callbacks: RwLock<HashMap<String, Vec<Callback>>>,
+}
+
+impl InvalidationBus {
+    /// Creates a new `InvalidationBus` without any callbacks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `callback` to be called for every [`Invalidation`] of the
+    /// resource `resource`.
+    pub fn subscribe<F>(&self, resource: &str, callback: F)
+    where
+        F: Fn(&Invalidation) + Send + Sync + 'static,
+    {
+        self.callbacks
+            .write()
+            .entry(resource.to_owned())
+            .or_default()
+            .push(Arc::new(callback));
+    }
+
+    /// Calls all callbacks registered for the resource of `invalidation`.
+    pub fn publish(&self, invalidation: &Invalidation) {
+        // Callbacks may subscribe themselves, don't hold the lock while
+        // calling them.
+        let callbacks = match self.callbacks.read().get(&invalidation.resource) {
+            Some(callbacks) => callbacks.clone(),
+            None => return,
+        };
+
+        for callback in callbacks {
+            callback(invalidation);
+        }
+    }
+
+    /// Publishes an untargeted [`Invalidation`] for every resource with
+    /// registered callbacks, e.g. after switching to another store.
+    pub fn publish_all(&self) {
+        let resources: Vec<String> = self.callbacks.read().keys().cloned().collect();
+
+        for resource in resources {
+            self.publish(&Invalidation {
+                resource,
+                guild_id: None,
+            });
+        }
+    }
+}
+
+impl Debug for InvalidationBus {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let callbacks: HashMap<_, _> = self
+            .callbacks
+            .read()
+            .iter()
+            .map(|(resource, callbacks)| (resource.clone(), callbacks.len()))
+            .collect();
+
+        f.debug_struct("InvalidationBus")
+            .field("callbacks", &callbacks)
+            .finish()
+    }
+}
+
+/// Returns the guild of `data`, if it has a `guild_id` field.
+pub(super) fn data_guild_id<T, S>(data: &T) -> Option<GuildId>
+where
+    T: StoreData<S>,
+    S: Store,
+{
+    let mut probe = GuildIdProbe::default();
+    let _ = data.serialize(&mut probe);
+    probe.guild_id()
+}
+
+/// Returns the guild matched by `query`, if it only matches a single guild.
+pub(super) fn query_guild_id<T, S, Q>(query: &Q) -> Option<GuildId>
+where
+    T: StoreData<S>,
+    S: Store,
+    Q: DataQuery<T, S>,
+{
+    let mut probe = GuildIdProbe::default();
+    let _ = query.serialize(&mut probe);
+    probe.guild_id()
+}
+
+/// A [`Serializer`] recording the value of the `guild_id` field and
+/// discarding everything else.
+#[derive(Debug, Default)]
+struct GuildIdProbe {
+    /// Whether the `guild_id` field is being serialized.
+    in_field: bool,
+    values: Vec<u64>,
+}
+
+impl GuildIdProbe {
+    fn guild_id(&self) -> Option<GuildId> {
+        match self.values.as_slice() {
+            [guild_id] => Some(GuildId(*guild_id)),
+            _ => None,
+        }
+    }
+}
+
+impl<S> Serializer<S> for GuildIdProbe
+where
+    S: Store,
+{
+    type Error = Infallible;
+
+    fn serialize_bool(&mut self, _v: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_i8(&mut self, _v: i8) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_i16(&mut self, _v: i16) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_i32(&mut self, _v: i32) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_i64(&mut self, _v: i64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_u8(&mut self, _v: u8) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_u16(&mut self, _v: u16) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_u32(&mut self, _v: u32) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_u64(&mut self, v: u64) -> Result<(), Self::Error> {
+        if self.in_field {
+            self.values.push(v);
+        }
+
+        Ok(())
+    }
+
+    fn serialize_f32(&mut self, _v: f32) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_f64(&mut self, _v: f64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_str(&mut self, _v: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize<S>,
+    {
+        if key == GUILD_ID_FIELD {
+            self.in_field = true;
+            value.serialize(self)?;
+            self.in_field = false;
+        }
+
+        Ok(())
+    }
+
+    fn serialize_field_in<T>(&mut self, key: &'static str, values: &[T]) -> Result<(), Self::Error>
+    where
+        T: Serialize<S>,
+    {
+        if key == GUILD_ID_FIELD {
+            self.in_field = true;
+            for value in values {
+                value.serialize(self)?;
+            }
+            self.in_field = false;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{data_guild_id, query_guild_id, Invalidation, InvalidationBus};
+    use crate::store::mem::MemStore;
+    use crate::store::StoreHandler;
+
+    use robbot::model::id::GuildId;
+    use robbot::store::{delete, get_one, insert};
+    use robbot::StoreData;
+
+    use parking_lot::{Mutex, RwLock};
+
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[derive(Clone, Debug, StoreData)]
+    struct GuildPrefix {
+        guild_id: GuildId,
+        prefix: String,
+    }
+
+    #[derive(Clone, Debug, StoreData)]
+    struct Global {
+        value: u64,
+    }
+
+    /// A minimal prefix cache as kept by a command module.
+    #[derive(Clone, Default)]
+    struct PrefixCache {
+        cache: Arc<RwLock<HashMap<GuildId, String>>>,
+    }
+
+    impl PrefixCache {
+        fn new(store: &StoreHandler<MemStore>) -> Self {
+            let this = Self::default();
+
+            let cache = this.cache.clone();
+            store
+                .invalidations()
+                .subscribe("GuildPrefix", move |invalidation| {
+                    match invalidation.guild_id {
+                        Some(guild_id) => {
+                            cache.write().remove(&guild_id);
+                        }
+                        None => cache.write().clear(),
+                    }
+                });
+
+            this
+        }
+
+        async fn get(&self, store: &StoreHandler<MemStore>, guild_id: GuildId) -> String {
+            if let Some(prefix) = self.cache.read().get(&guild_id) {
+                return prefix.clone();
+            }
+
+            let prefix = get_one!(store, GuildPrefix => { guild_id == guild_id })
+                .await
+                .unwrap()
+                .map(|prefix| prefix.prefix)
+                .unwrap_or_else(|| String::from("!"));
+
+            self.cache.write().insert(guild_id, prefix.clone());
+            prefix
+        }
+    }
+
+    #[test]
+    fn test_invalidation_bus() {
+        let bus = InvalidationBus::new();
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        {
+            let received = received.clone();
+            bus.subscribe("GuildPrefix", move |invalidation| {
+                received.lock().push(invalidation.clone());
+            });
+        }
+
+        let invalidation = Invalidation {
+            resource: String::from("GuildPrefix"),
+            guild_id: Some(GuildId(1)),
+        };
+        bus.publish(&invalidation);
+
+        // Other resources are not routed.
+        bus.publish(&Invalidation {
+            resource: String::from("Other"),
+            guild_id: None,
+        });
+
+        bus.publish_all();
+
+        assert_eq!(
+            *received.lock(),
+            [
+                invalidation,
+                Invalidation {
+                    resource: String::from("GuildPrefix"),
+                    guild_id: None,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_guild_id_probe() {
+        let prefix = GuildPrefix {
+            guild_id: GuildId(5),
+            prefix: String::from("?"),
+        };
+        assert_eq!(data_guild_id::<_, MemStore>(&prefix), Some(GuildId(5)));
+        assert_eq!(data_guild_id::<_, MemStore>(&Global { value: 5 }), None);
+
+        let query = GuildPrefixQuery::default().guild_id(GuildId(5));
+        assert_eq!(
+            query_guild_id::<GuildPrefix, MemStore, _>(&query),
+            Some(GuildId(5))
+        );
+
+        let query = GuildPrefixQuery::default().guild_id_in([GuildId(5)]);
+        assert_eq!(
+            query_guild_id::<GuildPrefix, MemStore, _>(&query),
+            Some(GuildId(5))
+        );
+
+        // Multiple or no guilds are not targeted.
+        let query = GuildPrefixQuery::default().guild_id_in([GuildId(5), GuildId(6)]);
+        assert_eq!(query_guild_id::<GuildPrefix, MemStore, _>(&query), None);
+
+        let query = GuildPrefixQuery::default().prefix(String::from("?"));
+        assert_eq!(query_guild_id::<GuildPrefix, MemStore, _>(&query), None);
+    }
+
+    #[tokio::test]
+    async fn test_store_write_invalidates_cache() {
+        let store: StoreHandler<MemStore> = StoreHandler::new_in_memory(MemStore::default());
+        let cache = PrefixCache::new(&store);
+
+        assert_eq!(cache.get(&store, GuildId(1)).await, "!");
+        assert_eq!(cache.get(&store, GuildId(2)).await, "!");
+
+        // A command changing the prefix only writes to the store.
+        insert!(
+            store,
+            GuildPrefix {
+                guild_id: GuildId(1),
+                prefix: String::from("?"),
+            }
+        )
+        .await
+        .unwrap();
+
+        // Only the changed guild is invalidated.
+        assert!(!cache.cache.read().contains_key(&GuildId(1)));
+        assert!(cache.cache.read().contains_key(&GuildId(2)));
+        assert_eq!(cache.get(&store, GuildId(1)).await, "?");
+
+        delete!(store, GuildPrefix => { guild_id == GuildId(1) })
+            .await
+            .unwrap();
+        assert_eq!(cache.get(&store, GuildId(1)).await, "!");
+
+        // Deletes without a single guild invalidate all guilds.
+        delete!(store, GuildPrefix => { prefix == String::from("?") })
+            .await
+            .unwrap();
+        assert!(cache.cache.read().is_empty());
+    }
+}
@@ -0,0 +1,57 @@
+use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+
+use std::sync::Arc;
+
+/// Coordinates operations touching large parts of the store.
+///
+/// Sync jobs, which write data in the background, hold a shared lock while they
+/// run. Exclusive operations like [`StoreHandler::export`] and
+/// [`StoreHandler::import`] should only run while holding the exclusive lock,
+/// so they never observe a partially synced store.
+///
+/// [`StoreHandler::export`]: super::StoreHandler::export
+/// [`StoreHandler::import`]: super::StoreHandler::import
+#[derive(Clone, Debug, Default)]
+pub struct StoreLock {
+    lock: Arc<RwLock<()>>,
+}
+
+impl StoreLock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Acquires a shared lock for a sync job. Returns `None` if an exclusive
+    /// operation is currently running.
+    pub fn try_sync(&self) -> Option<OwnedRwLockReadGuard<()>> {
+        self.lock.clone().try_read_owned().ok()
+    }
+
+    /// Acquires the exclusive lock. Returns `None` if a sync job or another
+    /// exclusive operation is currently running.
+    pub fn try_exclusive(&self) -> Option<OwnedRwLockWriteGuard<()>> {
+        self.lock.clone().try_write_owned().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StoreLock;
+
+    #[test]
+    fn test_store_lock() {
+        let lock = StoreLock::new();
+
+        let sync = lock.try_sync().unwrap();
+        assert!(lock.try_sync().is_some());
+        assert!(lock.try_exclusive().is_none());
+        drop(sync);
+
+        let exclusive = lock.try_exclusive().unwrap();
+        assert!(lock.try_sync().is_none());
+        assert!(lock.try_exclusive().is_none());
+        drop(exclusive);
+
+        assert!(lock.try_exclusive().is_some());
+    }
+}
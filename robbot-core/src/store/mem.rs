@@ -1,11 +1,13 @@
 use robbot::store::{
-    DataDescriptor, DataQuery, Deserialize, Deserializer, Serialize, Serializer, Store, StoreData,
-    TypeSerializer,
+    DataDescriptor, DataQuery, Decimal, Deserialize, Deserializer, Direction, OrderBy, Serialize,
+    Serializer, Store, StoreData, TypeSerializer, UnknownVariant,
 };
 
 use async_trait::async_trait;
 use parking_lot::RwLock;
+use thiserror::Error;
 
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::convert::Infallible;
 use std::mem;
@@ -15,9 +17,17 @@ use std::sync::Arc;
 
 /// An efficient [`Store`] that keeps all entries in memory.
 ///
+/// Items are always returned in insertion order by [`get`], [`get_all`] and
+/// [`get_one`]. Items with equal values are returned in insertion order by
+/// [`get_ordered`].
 ///
 /// Warning: As the name suggests, this store keeps all entries in memory. Dropping the store
 /// or restarting the bot will cause all entries to be lost.
+///
+/// [`get`]: Store::get
+/// [`get_all`]: Store::get_all
+/// [`get_one`]: Store::get_one
+/// [`get_ordered`]: Store::get_ordered
 #[derive(Clone, Debug, Default)]
 pub struct MemStore {
     // inner: Arc<RwLock<HashMap<String, Vec<Vec<u8>>>>>,
@@ -30,66 +40,122 @@ pub struct MemStore {
 struct Entry {
     /// The full memory buffer.
     buf: Vec<u8>,
-    keys: HashMap<String, *const u8>,
+    /// The fields of the entry with their types.
+    keys: HashMap<String, TypePtr>,
 }
 
 impl Entry {
-    /// Compare this entry with another. If the key provided by `other` doesn't exist
-    /// on this entry, `None` is returned.
+    /// Creates a new `Entry` from `data`.
+    fn new<T>(data: &T) -> Self
+    where
+        T: StoreData<MemStore>,
+    {
+        let mut serializer = SizeSerializer::new();
+        data.serialize(&mut serializer).unwrap();
+
+        let mut serializer = MemSerializer::new(serializer.size);
+        data.serialize(&mut serializer).unwrap();
+
+        Self {
+            buf: serializer.buf,
+            keys: serializer.keys,
+        }
+    }
+
+    /// Returns `true` if the entry satisfies all requirements of `query`.
+    /// Entries without a field of the query never match.
+    ///
+    /// # Safety
+    /// The pointers of `query` must point into its buffer.
+    unsafe fn matches(&self, query: &QuerySerializer) -> bool {
+        query
+            .keys
+            .iter()
+            .all(|(key, vals)| vals.iter().any(|val| self.eq(key, *val)))
+    }
+
+    /// Returns `true` if the field `key` of this entry equals `other`. Entries
+    /// without the field never match.
+    ///
+    /// Values of different types are only equal if both are integers or
+    /// `bool`s with the same value, like MySQL compares them. This happens if
+    /// the entry was created from a different type sharing the resource.
+    /// Floats are compared by value, `0.0` equals `-0.0` and `NaN` equals
+    /// nothing.
     ///
     /// # Safety
-    /// This method expects `key` to exist on `self.keys` and [`TypePtr`] to point to the same
-    /// type as the matching key in `self.key` points to. **Having the two pointers point to
-    /// **differently sized types causes unidentified behavoir.
+    /// `other` must point to a value of its [`StoreType`].
     unsafe fn eq(&self, key: &str, other: TypePtr) -> bool {
-        let left_ptr = match self.keys.get(key) {
-            Some(ptr) => *ptr,
+        let left = match self.keys.get(key) {
+            Some(left) => *left,
             None => return false,
         };
 
+        if left.kind != other.kind {
+            return match (read_integer(left), read_integer(other)) {
+                (Some(left), Some(right)) => left == right,
+                _ => false,
+            };
+        }
+
+        let left_ptr = left.ptr;
         let right_ptr = other.ptr;
 
-        // Compare the contents of `left_ptr` with `right_ptr`. It is not needed to convert
-        // the bytes into the actual types. Simply comparing the byte slices asserts equality.
+        // Compare the contents of `left_ptr` with `right_ptr`. Integers have
+        // a single representation, comparing the byte slices asserts equality.
         match other.kind {
-            StoreType::Bool | StoreType::I8 | StoreType::U8 => {
-                let left = *left_ptr;
-                let right = *right_ptr;
-
-                left == right
-            }
+            // Any non-zero byte is `true`.
+            StoreType::Bool => (*left_ptr != 0) == (*right_ptr != 0),
+            StoreType::I8 | StoreType::U8 => *left_ptr == *right_ptr,
             StoreType::I16 | StoreType::U16 => {
                 let left = slice::from_raw_parts(left_ptr, 2);
                 let right = slice::from_raw_parts(right_ptr, 2);
 
                 left == right
             }
-            StoreType::I32 | StoreType::U32 | StoreType::F32 => {
+            StoreType::I32 | StoreType::U32 => {
                 let left = slice::from_raw_parts(left_ptr, 4);
                 let right = slice::from_raw_parts(right_ptr, 4);
 
                 left == right
             }
-            StoreType::I64 | StoreType::U64 | StoreType::F64 => {
+            StoreType::I64 | StoreType::U64 => {
                 let left = slice::from_raw_parts(left_ptr, 8);
                 let right = slice::from_raw_parts(right_ptr, 8);
 
                 left == right
             }
+            StoreType::F32 => {
+                ptr::read_unaligned(left_ptr as *const f32)
+                    == ptr::read_unaligned(right_ptr as *const f32)
+            }
+            StoreType::F64 => {
+                ptr::read_unaligned(left_ptr as *const f64)
+                    == ptr::read_unaligned(right_ptr as *const f64)
+            }
+            StoreType::I128 | StoreType::U128 => {
+                let left = slice::from_raw_parts(left_ptr, 16);
+                let right = slice::from_raw_parts(right_ptr, 16);
+
+                left == right
+            }
+            // Decimals are normalized, equal values have equal bytes.
+            StoreType::Decimal => {
+                let left = slice::from_raw_parts(left_ptr, DECIMAL_SIZE);
+                let right = slice::from_raw_parts(right_ptr, DECIMAL_SIZE);
+
+                left == right
+            }
             StoreType::String => {
                 let left = {
-                    let len = slice::from_raw_parts(left_ptr, mem::size_of::<usize>());
-                    let len: [u8; mem::size_of::<usize>()] = mem::transmute_copy(&len[0]);
-                    let len = usize::from_ne_bytes(len);
+                    let len = ptr::read_unaligned(left_ptr as *const usize);
 
                     // Read the whole string including the prefixed length.
                     slice::from_raw_parts(left_ptr, mem::size_of::<usize>() + len)
                 };
 
                 let right = {
-                    let len = slice::from_raw_parts(right_ptr, mem::size_of::<usize>());
-                    let len: [u8; mem::size_of::<usize>()] = mem::transmute_copy(&len[0]);
-                    let len = usize::from_ne_bytes(len);
+                    let len = ptr::read_unaligned(right_ptr as *const usize);
 
                     slice::from_raw_parts(right_ptr, mem::size_of::<usize>() + len)
                 };
@@ -99,29 +165,150 @@ impl Entry {
         }
     }
 
-    /// Copies the entry's buffer to create type `T`.
-    ///
-    /// # Safety
-    /// This method simply copies bytes from the buffer until enough bytes were written to
-    /// create `T`. Providing an a different type `T` than the [`Entry`] was created from
-    /// causes `T` to contain junk data. **Providing a type `T` that is bigger than the buffer
-    /// causes unidentified behavoir.**
-    unsafe fn copy_into<T>(&self) -> T
+    /// Compares the field `key` of this entry with the same field of `other`. If either
+    /// entry doesn't have the field as a `kind`, they are considered equal.
+    unsafe fn cmp(&self, other: &Self, key: &str, kind: StoreType) -> Ordering {
+        unsafe fn read<T>(ptr: *const u8) -> T {
+            ptr::read_unaligned(ptr as *const T)
+        }
+
+        let (left_ptr, right_ptr) = match (self.keys.get(key), other.keys.get(key)) {
+            (Some(left), Some(right)) if left.kind == kind && right.kind == kind => {
+                (left.ptr, right.ptr)
+            }
+            _ => return Ordering::Equal,
+        };
+
+        match kind {
+            StoreType::Bool | StoreType::U8 => read::<u8>(left_ptr).cmp(&read(right_ptr)),
+            StoreType::I8 => read::<i8>(left_ptr).cmp(&read(right_ptr)),
+            StoreType::I16 => read::<i16>(left_ptr).cmp(&read(right_ptr)),
+            StoreType::I32 => read::<i32>(left_ptr).cmp(&read(right_ptr)),
+            StoreType::I64 => read::<i64>(left_ptr).cmp(&read(right_ptr)),
+            StoreType::U16 => read::<u16>(left_ptr).cmp(&read(right_ptr)),
+            StoreType::U32 => read::<u32>(left_ptr).cmp(&read(right_ptr)),
+            StoreType::U64 => read::<u64>(left_ptr).cmp(&read(right_ptr)),
+            StoreType::I128 => read::<i128>(left_ptr).cmp(&read(right_ptr)),
+            StoreType::U128 => read::<u128>(left_ptr).cmp(&read(right_ptr)),
+            StoreType::Decimal => read_decimal(left_ptr).cmp(&read_decimal(right_ptr)),
+            StoreType::F32 => read::<f32>(left_ptr)
+                .partial_cmp(&read(right_ptr))
+                .unwrap_or(Ordering::Equal),
+            StoreType::F64 => read::<f64>(left_ptr)
+                .partial_cmp(&read(right_ptr))
+                .unwrap_or(Ordering::Equal),
+            StoreType::String => {
+                let left = {
+                    let len = read::<usize>(left_ptr);
+                    slice::from_raw_parts(left_ptr.add(mem::size_of::<usize>()), len)
+                };
+
+                let right = {
+                    let len = read::<usize>(right_ptr);
+                    slice::from_raw_parts(right_ptr.add(mem::size_of::<usize>()), len)
+                };
+
+                left.cmp(right)
+            }
+        }
+    }
+
+    /// Reads the entry's buffer to create type `T`. Providing a different type
+    /// `T` than the [`Entry`] was created from causes `T` to contain junk data
+    /// or returns an error, reads never leave the buffer.
+    fn copy_into<T>(&self) -> Result<T, MemError>
     where
         T: StoreData<MemStore>,
     {
-        let mut deserializer = MemDeserializer::new(self.buf.as_ptr());
-        T::deserialize(&mut deserializer).unwrap()
+        deserialize_entry(&self.buf)
     }
 }
 
+/// Reads a `T` from `buf` in the entry format of the [`MemStore`]. All reads
+/// are bounds-checked, arbitrary buffers return an error instead of reading
+/// out of bounds.
+#[doc(hidden)]
+pub fn deserialize_entry<T>(buf: &[u8]) -> Result<T, MemError>
+where
+    T: StoreData<MemStore>,
+{
+    let mut deserializer = MemDeserializer::new(buf);
+    T::deserialize(&mut deserializer)
+}
+
+/// An error reading an entry of the [`MemStore`].
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum MemError {
+    /// The entry contains a value that is not a variant of a [`StoreEnum`]
+    /// anymore.
+    ///
+    /// [`StoreEnum`]: robbot::store::StoreEnum
+    #[error(transparent)]
+    UnknownVariant(#[from] UnknownVariant),
+    /// The entry ended before the value was read completely.
+    #[error("unexpected end of entry")]
+    UnexpectedEnd,
+    /// The entry contains bytes that are not a valid value of the type.
+    #[error("invalid {0} in entry")]
+    InvalidValue(&'static str),
+    /// A query refers to a field the resource does not have.
+    #[error("unknown field `{0}`")]
+    UnknownField(&'static str),
+}
+
+/// The number of bytes of a [`Decimal`]: the mantissa followed by the scale.
+const DECIMAL_SIZE: usize = mem::size_of::<i128>() + mem::size_of::<u32>();
+
+/// Reads the value of an integer or `bool`, `None` for other types or
+/// values outside of the range of `i128`.
+///
+/// # Safety
+/// `value` must point to a value of its [`StoreType`].
+unsafe fn read_integer(value: TypePtr) -> Option<i128> {
+    unsafe fn read<T>(ptr: *const u8) -> T {
+        ptr::read_unaligned(ptr as *const T)
+    }
+
+    let ptr = value.ptr;
+    match value.kind {
+        StoreType::Bool => Some((*ptr != 0) as i128),
+        StoreType::I8 => Some(read::<i8>(ptr).into()),
+        StoreType::I16 => Some(read::<i16>(ptr).into()),
+        StoreType::I32 => Some(read::<i32>(ptr).into()),
+        StoreType::I64 => Some(read::<i64>(ptr).into()),
+        StoreType::I128 => Some(read::<i128>(ptr)),
+        StoreType::U8 => Some((*ptr).into()),
+        StoreType::U16 => Some(read::<u16>(ptr).into()),
+        StoreType::U32 => Some(read::<u32>(ptr).into()),
+        StoreType::U64 => Some(read::<u64>(ptr).into()),
+        StoreType::U128 => read::<u128>(ptr).try_into().ok(),
+        StoreType::F32 | StoreType::F64 | StoreType::String | StoreType::Decimal => None,
+    }
+}
+
+/// Reads a [`Decimal`] written by [`MemSerializer::serialize_decimal`].
+///
+/// # Safety
+/// `ptr` must point to at least [`DECIMAL_SIZE`] bytes.
+unsafe fn read_decimal(ptr: *const u8) -> Decimal {
+    let mantissa = ptr::read_unaligned(ptr as *const i128);
+    let scale = ptr::read_unaligned(ptr.add(mem::size_of::<i128>()) as *const u32);
+
+    // The scale is valid, it was written from a `Decimal`.
+    Decimal::new(mantissa, scale).unwrap_or(Decimal::ZERO)
+}
+
 // `Entry` is Send and Sync since all pointers in `self.keys` point into `self.buf`.
 unsafe impl Send for Entry {}
 unsafe impl Sync for Entry {}
 
 #[async_trait]
 impl Store for MemStore {
-    type Error = Infallible;
+    /// Entries are only unreadable if they contain a value that is not a
+    /// variant of a [`StoreEnum`] anymore.
+    ///
+    /// [`StoreEnum`]: robbot::store::StoreEnum
+    type Error = MemError;
     type Serializer = MemSerializer;
 
     async fn connect(_uri: &str) -> Result<Self, Self::Error> {
@@ -146,19 +333,8 @@ impl Store for MemStore {
         if let Some(entries) = inner.get_mut(&T::resource_name()) {
             let query = serialize_query(query);
 
-            entries.retain(|entry| {
-                for (key, val) in &query.keys {
-                    // SAFETY: `T` is the same type as `entry` was created from.
-                    unsafe {
-                        if !entry.eq(key, *val) {
-                            return true;
-                        }
-                    }
-                }
-
-                // All checks were `true`, remove the entry.
-                false
-            });
+            // SAFETY: `T` is the same type as `entry` was created from.
+            entries.retain(|entry| unsafe { !entry.matches(&query) });
         }
 
         Ok(())
@@ -178,12 +354,12 @@ impl Store for MemStore {
                 let mut values = Vec::with_capacity(entries.len());
 
                 'outer: for entry in entries {
-                    for (key, val) in &query.keys {
+                    for (key, vals) in &query.keys {
                         match entry.keys.get(key) {
                             Some(_) => {
-                                // SAFETY: `T` is the same type as `entry` was created from.
+                                // SAFETY: The pointers of `query` point into its buffer.
                                 unsafe {
-                                    if !entry.eq(key, *val) {
+                                    if !vals.iter().any(|val| entry.eq(key, *val)) {
                                         continue 'outer;
                                     }
                                 }
@@ -193,9 +369,7 @@ impl Store for MemStore {
                     }
 
                     // The entry satisfies all requirements of `query`.
-                    unsafe {
-                        values.push(entry.copy_into());
-                    }
+                    values.push(entry.copy_into()?);
                 }
 
                 // Shrink values down as much as possible.
@@ -217,12 +391,74 @@ impl Store for MemStore {
                 let mut values = Vec::with_capacity(entries.len());
 
                 for entry in entries {
-                    let mut deserializer = MemDeserializer::new_from_slice(&entry.buf);
-                    let value = T::deserialize(&mut deserializer)?;
+                    values.push(entry.copy_into()?);
+                }
+
+                Ok(values)
+            }
+            None => Ok(Vec::new()),
+        }
+    }
 
-                    values.push(value);
+    async fn get_ordered<T, D, Q>(
+        &self,
+        descriptor: D,
+        query: Q,
+        order: OrderBy,
+    ) -> Result<Vec<T>, Self::Error>
+    where
+        T: StoreData<Self> + Send + Sync + 'static,
+        D: DataDescriptor<T, Self> + Send + Sync,
+        Q: DataQuery<T, Self> + Send,
+    {
+        let mut serializer = KindSerializer::new();
+        descriptor.serialize(&mut serializer).unwrap();
+
+        let kind = match serializer.keys.get(order.key) {
+            Some(kind) => *kind,
+            None => return Err(MemError::UnknownField(order.key)),
+        };
+
+        let inner = self.inner.read();
+        match inner.get(&T::resource_name()) {
+            Some(entries) => {
+                let query = serialize_query(query);
+
+                let mut matches = Vec::new();
+
+                'outer: for entry in entries {
+                    for (key, vals) in &query.keys {
+                        match entry.keys.get(key) {
+                            Some(_) => {
+                                // SAFETY: The pointers of `query` point into its buffer.
+                                unsafe {
+                                    if !vals.iter().any(|val| entry.eq(key, *val)) {
+                                        continue 'outer;
+                                    }
+                                }
+                            }
+                            None => return Ok(Vec::new()),
+                        }
+                    }
+
+                    matches.push(entry);
                 }
 
+                // SAFETY: `kind` is the type of the field `order.key` of `T`.
+                matches.sort_by(|a, b| unsafe {
+                    let ordering = a.cmp(b, order.key, kind);
+
+                    match order.direction {
+                        Direction::Asc => ordering,
+                        Direction::Desc => ordering.reverse(),
+                    }
+                });
+
+                let values = matches
+                    .into_iter()
+                    .map(|entry| entry.copy_into())
+                    .collect::<Result<_, _>>()?;
+
                 Ok(values)
             }
             None => Ok(Vec::new()),
@@ -241,12 +477,12 @@ impl Store for MemStore {
                 let query = serialize_query(query);
 
                 'outer: for entry in entries {
-                    for (key, val) in &query.keys {
+                    for (key, vals) in &query.keys {
                         match entry.keys.get(key) {
                             Some(_) => {
-                                // SAFETY: `T` is the same type as `entry` was created from.
+                                // SAFETY: The pointers of `query` point into its buffer.
                                 unsafe {
-                                    if !entry.eq(key, *val) {
+                                    if !vals.iter().any(|val| entry.eq(key, *val)) {
                                         continue 'outer;
                                     }
                                 }
@@ -256,9 +492,7 @@ impl Store for MemStore {
                     }
 
                     // The entry satisfies all requirements of `query`.
-                    unsafe {
-                        return Ok(Some(entry.copy_into()));
-                    }
+                    return entry.copy_into().map(Some);
                 }
 
                 Ok(None)
@@ -271,16 +505,7 @@ impl Store for MemStore {
     where
         T: StoreData<Self> + Send + Sync + 'static,
     {
-        let mut serializer = SizeSerializer::new();
-        data.serialize(&mut serializer)?;
-
-        let mut serializer = MemSerializer::new(serializer.size);
-        data.serialize(&mut serializer)?;
-
-        let entry = Entry {
-            buf: serializer.buf,
-            keys: serializer.keys,
-        };
+        let entry = Entry::new(&data);
 
         let mut inner = self.inner.write();
         match inner.get_mut(&T::resource_name()) {
@@ -294,6 +519,33 @@ impl Store for MemStore {
 
         Ok(())
     }
+
+    async fn update<T, Q>(&self, query: Q, data: T) -> Result<u64, Self::Error>
+    where
+        T: StoreData<Self> + Send + Sync + 'static,
+        Q: DataQuery<T, Self> + Send,
+    {
+        let query = serialize_query(query);
+
+        // The query is checked and the entries are replaced under the same
+        // lock, no other write can happen in between.
+        let mut inner = self.inner.write();
+        let entries = match inner.get_mut(&T::resource_name()) {
+            Some(entries) => entries,
+            None => return Ok(0),
+        };
+
+        let mut updated = 0;
+        for entry in entries.iter_mut() {
+            // SAFETY: `T` is the same type as `entry` was created from.
+            if unsafe { entry.matches(&query) } {
+                *entry = Entry::new(&data);
+                updated += 1;
+            }
+        }
+
+        Ok(updated)
+    }
 }
 
 /// The [`Serializer`] for [`MemStore`]. The exact number of bytes written must be known before
@@ -306,7 +558,8 @@ impl Store for MemStore {
 #[derive(Clone, Debug)]
 pub struct MemSerializer {
     buf: Vec<u8>,
-    keys: HashMap<String, *const u8>,
+    keys: HashMap<String, TypePtr>,
+    last_type: StoreType,
 }
 
 impl MemSerializer {
@@ -315,6 +568,8 @@ impl MemSerializer {
         Self {
             buf: Vec::with_capacity(capacity),
             keys: HashMap::new(),
+            // Only read after a `serialize_*` method set it.
+            last_type: StoreType::Bool,
         }
     }
 
@@ -359,6 +614,7 @@ impl Serializer<MemStore> for MemSerializer {
             self.write([v as u8]);
         }
 
+        self.last_type = StoreType::Bool;
         Ok(())
     }
 
@@ -367,6 +623,7 @@ impl Serializer<MemStore> for MemSerializer {
             self.write([v as u8]);
         }
 
+        self.last_type = StoreType::I8;
         Ok(())
     }
 
@@ -375,6 +632,7 @@ impl Serializer<MemStore> for MemSerializer {
             self.write(v.to_ne_bytes());
         }
 
+        self.last_type = StoreType::I16;
         Ok(())
     }
 
@@ -383,6 +641,7 @@ impl Serializer<MemStore> for MemSerializer {
             self.write(v.to_ne_bytes());
         }
 
+        self.last_type = StoreType::I32;
         Ok(())
     }
 
@@ -391,6 +650,7 @@ impl Serializer<MemStore> for MemSerializer {
             self.write(v.to_ne_bytes());
         }
 
+        self.last_type = StoreType::I64;
         Ok(())
     }
 
@@ -399,6 +659,7 @@ impl Serializer<MemStore> for MemSerializer {
             self.write([v]);
         }
 
+        self.last_type = StoreType::U8;
         Ok(())
     }
 
@@ -407,6 +668,7 @@ impl Serializer<MemStore> for MemSerializer {
             self.write(v.to_ne_bytes());
         }
 
+        self.last_type = StoreType::U16;
         Ok(())
     }
 
@@ -415,6 +677,7 @@ impl Serializer<MemStore> for MemSerializer {
             self.write(v.to_ne_bytes());
         }
 
+        self.last_type = StoreType::U32;
         Ok(())
     }
 
@@ -423,6 +686,7 @@ impl Serializer<MemStore> for MemSerializer {
             self.write(v.to_ne_bytes());
         }
 
+        self.last_type = StoreType::U64;
         Ok(())
     }
 
@@ -431,6 +695,7 @@ impl Serializer<MemStore> for MemSerializer {
             self.write(v.to_ne_bytes());
         }
 
+        self.last_type = StoreType::F32;
         Ok(())
     }
 
@@ -439,6 +704,7 @@ impl Serializer<MemStore> for MemSerializer {
             self.write(v.to_ne_bytes());
         }
 
+        self.last_type = StoreType::F64;
         Ok(())
     }
 
@@ -449,126 +715,143 @@ impl Serializer<MemStore> for MemSerializer {
             self.write(v.as_bytes().to_owned());
         }
 
+        self.last_type = StoreType::String;
         Ok(())
     }
 
-    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
-    where
-        T: ?Sized + Serialize<MemStore>,
-    {
-        let ptr = unsafe { self.next_ptr() };
-
-        self.keys.insert(key.to_string(), ptr);
+    fn serialize_u128(&mut self, v: u128) -> Result<(), Self::Error> {
+        unsafe {
+            self.write(v.to_ne_bytes());
+        }
 
-        value.serialize(self)
+        self.last_type = StoreType::U128;
+        Ok(())
     }
-}
 
-/// Note: `MemDeserializer` never changes the given buffer. It only copies it.
-struct MemDeserializer {
-    ptr: *const u8,
-}
+    fn serialize_i128(&mut self, v: i128) -> Result<(), Self::Error> {
+        unsafe {
+            self.write(v.to_ne_bytes());
+        }
 
-impl MemDeserializer {
-    /// Creates a new `MemDeserializer` that starts reading at `ptr`.
-    fn new(ptr: *const u8) -> Self {
-        Self { ptr }
+        self.last_type = StoreType::I128;
+        Ok(())
     }
 
-    /// Creates a new `MemDeserializer` that starts reading at the first slice entry.
-    fn new_from_slice(buf: &[u8]) -> Self {
-        Self { ptr: buf.as_ptr() }
+    fn serialize_decimal(&mut self, v: &Decimal) -> Result<(), Self::Error> {
+        unsafe {
+            self.write(v.mantissa().to_ne_bytes());
+            self.write(v.scale().to_ne_bytes());
+        }
+
+        self.last_type = StoreType::Decimal;
+        Ok(())
     }
-}
 
-impl MemDeserializer {
-    /// Reads `T` from `self.buf` by taking the first from the buffer and transmuting them
-    /// into `T`.
-    ///
-    /// # Safety
-    /// Calling this method causes unidentified behavoir if `self.buf` is smaller than
-    /// [`size_of::<T>()`].
-    ///
-    /// [`size_of::<T>()`]: mem::size_of
-    #[inline]
-    unsafe fn read<T>(&mut self) -> T {
-        let size = mem::size_of::<T>();
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize<MemStore>,
+    {
+        let ptr = unsafe { self.next_ptr() };
 
-        let value = mem::transmute_copy(&*self.ptr);
+        value.serialize(self)?;
 
-        self.ptr = self.ptr.add(size);
+        self.keys
+            .insert(key.to_string(), TypePtr::new(ptr, self.last_type));
 
-        value
+        Ok(())
     }
 
-    /// Reads the exact number of bytes from the buffer to fill `buf`.
-    ///
-    /// # Safety
-    /// It is unidentified behavoir if `self.ptr` was created from a buffer with a smaller
-    /// size.
-    #[inline]
-    unsafe fn read_exact(&mut self, buf: &mut [u8]) {
-        let value = ptr::copy_nonoverlapping(self.ptr, buf.as_mut_ptr(), buf.len());
-        self.ptr = self.ptr.add(buf.len());
-        value
+    /// Entries only contain single values, `serialize_field_in` is only valid in queries.
+    fn serialize_field_in<T>(&mut self, key: &'static str, _values: &[T]) -> Result<(), Self::Error>
+    where
+        T: Serialize<MemStore>,
+    {
+        panic!("Cannot store multiple values in the field `{}`", key);
     }
 }
 
-impl Deserializer<MemStore> for MemDeserializer {
-    type Error = Infallible;
-
-    fn deserialize_bool(&mut self) -> Result<bool, Self::Error> {
-        unsafe { Ok(self.read()) }
-    }
+/// Reads values written by [`MemSerializer`] from a buffer. All reads are
+/// bounds-checked.
+struct MemDeserializer<'a> {
+    buf: &'a [u8],
+}
 
-    fn deserialize_i8(&mut self) -> Result<i8, Self::Error> {
-        unsafe { Ok(self.read()) }
+impl<'a> MemDeserializer<'a> {
+    /// Creates a new `MemDeserializer` that starts reading at the start of `buf`.
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
     }
 
-    fn deserialize_i16(&mut self) -> Result<i16, Self::Error> {
-        unsafe { Ok(self.read()) }
-    }
+    /// Takes the next `len` bytes from the buffer.
+    fn take(&mut self, len: usize) -> Result<&'a [u8], MemError> {
+        if self.buf.len() < len {
+            return Err(MemError::UnexpectedEnd);
+        }
 
-    fn deserialize_i32(&mut self) -> Result<i32, Self::Error> {
-        unsafe { Ok(self.read()) }
+        let (bytes, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(bytes)
     }
 
-    fn deserialize_i64(&mut self) -> Result<i64, Self::Error> {
-        unsafe { Ok(self.read()) }
+    /// Takes the next `N` bytes from the buffer.
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], MemError> {
+        let mut buf = [0; N];
+        buf.copy_from_slice(self.take(N)?);
+        Ok(buf)
     }
+}
 
-    fn deserialize_u8(&mut self) -> Result<u8, Self::Error> {
-        unsafe { Ok(self.read()) }
-    }
+macro_rules! deserialize_num {
+    ($($fn:ident => $ty:ty),* $(,)?) => {
+        $(
+            fn $fn(&mut self) -> Result<$ty, Self::Error> {
+                Ok(<$ty>::from_ne_bytes(self.take_array()?))
+            }
+        )*
+    };
+}
 
-    fn deserialize_u16(&mut self) -> Result<u16, Self::Error> {
-        unsafe { Ok(self.read()) }
-    }
+impl Deserializer<MemStore> for MemDeserializer<'_> {
+    type Error = MemError;
 
-    fn deserialize_u32(&mut self) -> Result<u32, Self::Error> {
-        unsafe { Ok(self.read()) }
+    fn deserialize_bool(&mut self) -> Result<bool, Self::Error> {
+        match self.take_array::<1>()? {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => Err(MemError::InvalidValue("bool")),
+        }
     }
 
-    fn deserialize_u64(&mut self) -> Result<u64, Self::Error> {
-        unsafe { Ok(self.read()) }
+    deserialize_num! {
+        deserialize_i8 => i8,
+        deserialize_i16 => i16,
+        deserialize_i32 => i32,
+        deserialize_i64 => i64,
+        deserialize_u8 => u8,
+        deserialize_u16 => u16,
+        deserialize_u32 => u32,
+        deserialize_u64 => u64,
+        deserialize_f32 => f32,
+        deserialize_f64 => f64,
+        deserialize_u128 => u128,
+        deserialize_i128 => i128,
     }
 
-    fn deserialize_f32(&mut self) -> Result<f32, Self::Error> {
-        unsafe { Ok(self.read()) }
-    }
+    fn deserialize_string(&mut self) -> Result<String, Self::Error> {
+        let len = usize::from_ne_bytes(self.take_array()?);
+        let bytes = self.take(len)?;
 
-    fn deserialize_f64(&mut self) -> Result<f64, Self::Error> {
-        unsafe { Ok(self.read()) }
+        match std::str::from_utf8(bytes) {
+            Ok(s) => Ok(s.to_owned()),
+            Err(_) => Err(MemError::InvalidValue("string")),
+        }
     }
 
-    fn deserialize_string(&mut self) -> Result<String, Self::Error> {
-        unsafe {
-            let len: usize = self.read();
-            let mut bytes = vec![0; len];
-            self.read_exact(&mut bytes);
+    fn deserialize_decimal(&mut self) -> Result<Decimal, Self::Error> {
+        let mantissa = i128::from_ne_bytes(self.take_array()?);
+        let scale = u32::from_ne_bytes(self.take_array()?);
 
-            Ok(String::from_utf8_unchecked(bytes))
-        }
+        Decimal::new(mantissa, scale).ok_or(MemError::InvalidValue("decimal"))
     }
 
     fn deserialize_field<T>(&mut self, _key: &'static str) -> Result<T, Self::Error>
@@ -577,6 +860,10 @@ impl Deserializer<MemStore> for MemDeserializer {
     {
         T::deserialize(self)
     }
+
+    fn unknown_variant(&mut self, err: UnknownVariant) -> Self::Error {
+        err.into()
+    }
 }
 
 /// A Serializer to predict the exact number of bytes a [`StoreData`] type will have
@@ -636,56 +923,192 @@ impl Serializer<MemStore> for SizeSerializer {
         Ok(())
     }
 
-    fn serialize_u64(&mut self, _: u64) -> Result<(), Self::Error> {
-        self.size += mem::size_of::<u64>();
+    fn serialize_u64(&mut self, _: u64) -> Result<(), Self::Error> {
+        self.size += mem::size_of::<u64>();
+        Ok(())
+    }
+
+    fn serialize_f32(&mut self, _: f32) -> Result<(), Self::Error> {
+        self.size += mem::size_of::<f32>();
+        Ok(())
+    }
+
+    fn serialize_f64(&mut self, _: f64) -> Result<(), Self::Error> {
+        self.size += mem::size_of::<f64>();
+        Ok(())
+    }
+
+    fn serialize_str(&mut self, v: &str) -> Result<(), Self::Error> {
+        self.size += mem::size_of::<usize>();
+        self.size += v.len();
+        Ok(())
+    }
+
+    fn serialize_u128(&mut self, _: u128) -> Result<(), Self::Error> {
+        self.size += mem::size_of::<u128>();
+        Ok(())
+    }
+
+    fn serialize_i128(&mut self, _: i128) -> Result<(), Self::Error> {
+        self.size += mem::size_of::<i128>();
+        Ok(())
+    }
+
+    fn serialize_decimal(&mut self, _: &Decimal) -> Result<(), Self::Error> {
+        self.size += DECIMAL_SIZE;
+        Ok(())
+    }
+
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize<MemStore>,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_field_in<T>(&mut self, _key: &'static str, values: &[T]) -> Result<(), Self::Error>
+    where
+        T: Serialize<MemStore>,
+    {
+        for value in values {
+            value.serialize(self)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// All variants for primitive types.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum StoreType {
+    Bool,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+    String,
+    U128,
+    I128,
+    Decimal,
+}
+
+/// A [`TypeSerializer`] that records the [`StoreType`] of all fields.
+struct KindSerializer {
+    keys: HashMap<&'static str, StoreType>,
+    last_type: StoreType,
+}
+
+impl KindSerializer {
+    fn new() -> Self {
+        Self {
+            keys: HashMap::new(),
+            // `last_type` should never be used before calling a `serialize_*` method.
+            // Therefore the value of this type does not matter.
+            last_type: StoreType::Bool,
+        }
+    }
+}
+
+impl TypeSerializer<MemStore> for KindSerializer {
+    type Error = Infallible;
+
+    fn serialize_bool(&mut self) -> Result<(), Self::Error> {
+        self.last_type = StoreType::Bool;
+        Ok(())
+    }
+
+    fn serialize_i8(&mut self) -> Result<(), Self::Error> {
+        self.last_type = StoreType::I8;
+        Ok(())
+    }
+
+    fn serialize_i16(&mut self) -> Result<(), Self::Error> {
+        self.last_type = StoreType::I16;
+        Ok(())
+    }
+
+    fn serialize_i32(&mut self) -> Result<(), Self::Error> {
+        self.last_type = StoreType::I32;
+        Ok(())
+    }
+
+    fn serialize_i64(&mut self) -> Result<(), Self::Error> {
+        self.last_type = StoreType::I64;
+        Ok(())
+    }
+
+    fn serialize_u8(&mut self) -> Result<(), Self::Error> {
+        self.last_type = StoreType::U8;
+        Ok(())
+    }
+
+    fn serialize_u16(&mut self) -> Result<(), Self::Error> {
+        self.last_type = StoreType::U16;
+        Ok(())
+    }
+
+    fn serialize_u32(&mut self) -> Result<(), Self::Error> {
+        self.last_type = StoreType::U32;
+        Ok(())
+    }
+
+    fn serialize_u64(&mut self) -> Result<(), Self::Error> {
+        self.last_type = StoreType::U64;
+        Ok(())
+    }
+
+    fn serialize_f32(&mut self) -> Result<(), Self::Error> {
+        self.last_type = StoreType::F32;
+        Ok(())
+    }
+
+    fn serialize_f64(&mut self) -> Result<(), Self::Error> {
+        self.last_type = StoreType::F64;
+        Ok(())
+    }
+
+    fn serialize_str(&mut self) -> Result<(), Self::Error> {
+        self.last_type = StoreType::String;
         Ok(())
     }
 
-    fn serialize_f32(&mut self, _: f32) -> Result<(), Self::Error> {
-        self.size += mem::size_of::<f32>();
+    fn serialize_u128(&mut self) -> Result<(), Self::Error> {
+        self.last_type = StoreType::U128;
         Ok(())
     }
 
-    fn serialize_f64(&mut self, _: f64) -> Result<(), Self::Error> {
-        self.size += mem::size_of::<f64>();
+    fn serialize_i128(&mut self) -> Result<(), Self::Error> {
+        self.last_type = StoreType::I128;
         Ok(())
     }
 
-    fn serialize_str(&mut self, v: &str) -> Result<(), Self::Error> {
-        self.size += mem::size_of::<usize>();
-        self.size += v.len();
+    fn serialize_decimal(&mut self) -> Result<(), Self::Error> {
+        self.last_type = StoreType::Decimal;
         Ok(())
     }
 
-    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str) -> Result<(), Self::Error>
     where
         T: ?Sized + Serialize<MemStore>,
     {
-        value.serialize(self)
-    }
-}
+        T::serialize_type(self)?;
+        self.keys.insert(key, self.last_type);
 
-/// All variants for primitive types.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum StoreType {
-    Bool,
-    I8,
-    I16,
-    I32,
-    I64,
-    U8,
-    U16,
-    U32,
-    U64,
-    F32,
-    F64,
-    String,
+        Ok(())
+    }
 }
 
 /// `QuerySerializer` works like `MemSerializer` but also keeps tracks of all types.
+/// A key matches if it is equal to any of its values.
 struct QuerySerializer {
     buf: Vec<u8>,
-    keys: HashMap<String, TypePtr>,
+    keys: HashMap<String, Vec<TypePtr>>,
     last_type: StoreType,
 }
 
@@ -849,6 +1272,34 @@ impl Serializer<MemStore> for QuerySerializer {
         Ok(())
     }
 
+    fn serialize_u128(&mut self, v: u128) -> Result<(), Self::Error> {
+        unsafe {
+            self.write(v.to_ne_bytes());
+        }
+
+        self.last_type = StoreType::U128;
+        Ok(())
+    }
+
+    fn serialize_i128(&mut self, v: i128) -> Result<(), Self::Error> {
+        unsafe {
+            self.write(v.to_ne_bytes());
+        }
+
+        self.last_type = StoreType::I128;
+        Ok(())
+    }
+
+    fn serialize_decimal(&mut self, v: &Decimal) -> Result<(), Self::Error> {
+        unsafe {
+            self.write(v.mantissa().to_ne_bytes());
+            self.write(v.scale().to_ne_bytes());
+        }
+
+        self.last_type = StoreType::Decimal;
+        Ok(())
+    }
+
     fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + Serialize<MemStore>,
@@ -859,7 +1310,26 @@ impl Serializer<MemStore> for QuerySerializer {
         value.serialize(self)?;
 
         self.keys
-            .insert(key.to_owned(), TypePtr::new(ptr, self.last_type));
+            .insert(key.to_owned(), vec![TypePtr::new(ptr, self.last_type)]);
+
+        Ok(())
+    }
+
+    fn serialize_field_in<T>(&mut self, key: &'static str, values: &[T]) -> Result<(), Self::Error>
+    where
+        T: Serialize<MemStore>,
+    {
+        let mut ptrs = Vec::with_capacity(values.len());
+
+        for value in values {
+            let ptr = unsafe { self.next_ptr() };
+
+            value.serialize(self)?;
+
+            ptrs.push(TypePtr::new(ptr, self.last_type));
+        }
+
+        self.keys.insert(key.to_owned(), ptrs);
 
         Ok(())
     }
@@ -1104,6 +1574,54 @@ impl Serialize<MemStore> for String {
     }
 }
 
+impl Serialize<MemStore> for u128 {
+    fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+    where
+        S: Serializer<MemStore>,
+    {
+        serializer.serialize_u128(*self)
+    }
+
+    fn serialize_type<S>(serializer: &mut S) -> Result<(), S::Error>
+    where
+        S: TypeSerializer<MemStore>,
+    {
+        serializer.serialize_u128()
+    }
+}
+
+impl Serialize<MemStore> for i128 {
+    fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+    where
+        S: Serializer<MemStore>,
+    {
+        serializer.serialize_i128(*self)
+    }
+
+    fn serialize_type<S>(serializer: &mut S) -> Result<(), S::Error>
+    where
+        S: TypeSerializer<MemStore>,
+    {
+        serializer.serialize_i128()
+    }
+}
+
+impl Serialize<MemStore> for Decimal {
+    fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+    where
+        S: Serializer<MemStore>,
+    {
+        serializer.serialize_decimal(self)
+    }
+
+    fn serialize_type<S>(serializer: &mut S) -> Result<(), S::Error>
+    where
+        S: TypeSerializer<MemStore>,
+    {
+        serializer.serialize_decimal()
+    }
+}
+
 // ======================================================
 // === Implement [`Deserialize`] for supported types. ===
 // ======================================================
@@ -1216,11 +1734,41 @@ impl Deserialize<MemStore> for String {
     }
 }
 
+impl Deserialize<MemStore> for u128 {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<MemStore>,
+    {
+        deserializer.deserialize_u128()
+    }
+}
+
+impl Deserialize<MemStore> for i128 {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<MemStore>,
+    {
+        deserializer.deserialize_i128()
+    }
+}
+
+impl Deserialize<MemStore> for Decimal {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<MemStore>,
+    {
+        deserializer.deserialize_decimal()
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
-    use super::{MemDeserializer, MemSerializer, MemStore};
-    use robbot::store::{delete, get, insert, Deserializer, Serializer, Store};
+    use super::{deserialize_entry, MemDeserializer, MemError, MemSerializer, MemStore};
+    use robbot::model::emoji::Emoji;
+    use robbot::store::{
+        delete, get, insert, Deserializer, OrderBy, Serializer, Store, StoreEnum, UnknownVariant,
+    };
     use robbot::StoreData;
 
     use std::mem;
@@ -1281,6 +1829,283 @@ mod tests {
         assert_eq!(entries, vec![data2]);
     }
 
+    #[tokio::test]
+    async fn test_store_emoji() {
+        #[derive(Clone, Debug, StoreData, PartialEq, Eq)]
+        struct ReactionRole {
+            message_id: u64,
+            emoji: Emoji,
+        }
+
+        let store = MemStore::connect("").await.unwrap();
+
+        let roles = [
+            ReactionRole {
+                message_id: 1,
+                emoji: "<a:party_blob:5678>".parse().unwrap(),
+            },
+            ReactionRole {
+                message_id: 2,
+                emoji: "👨‍👩‍👧".parse().unwrap(),
+            },
+        ];
+
+        for role in &roles {
+            insert!(store, role.clone()).await.unwrap();
+        }
+
+        let entries = get!(store, ReactionRole).await.unwrap();
+        assert_eq!(entries, roles);
+
+        let entries = get!(store, ReactionRole => {
+            emoji == roles[1].emoji.clone(),
+        })
+        .await
+        .unwrap();
+        assert_eq!(entries, [roles[1].clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_store_flatten() {
+        #[derive(Clone, Debug, StoreData, PartialEq, Eq)]
+        struct Schedule {
+            start: i64,
+            repeat: u64,
+        }
+
+        #[derive(Clone, Debug, StoreData, PartialEq, Eq)]
+        struct Event {
+            name: String,
+            #[store(flatten)]
+            schedule: Schedule,
+            guild_id: u64,
+        }
+
+        let event = |name: &str, start, repeat| Event {
+            name: name.to_owned(),
+            schedule: Schedule { start, repeat },
+            guild_id: 1,
+        };
+
+        let store = MemStore::connect("").await.unwrap();
+
+        let a = event("a", 100, 60);
+        let b = event("b", 50, 0);
+        let c = event("c", 100, 0);
+        for data in [&a, &b, &c] {
+            insert!(store, data.clone()).await.unwrap();
+        }
+
+        // Round trip.
+        let entries = get!(store, Event).await.unwrap();
+        assert_eq!(entries, vec![a.clone(), b.clone(), c.clone()]);
+
+        // Query a flattened field.
+        let entries = get!(store, Event => {
+            schedule.start == 100,
+        })
+        .await
+        .unwrap();
+        assert_eq!(entries, vec![a.clone(), c.clone()]);
+
+        let entries = get!(store, Event => {
+            schedule.start == 100,
+            schedule.repeat in vec![0, 30],
+            guild_id == 1,
+        })
+        .await
+        .unwrap();
+        assert_eq!(entries, vec![c.clone()]);
+
+        // Order by a flattened field.
+        let query = store.make_query::<Event>();
+        let entries = store
+            .get_ordered(
+                store.make_descriptor::<Event>(),
+                query,
+                OrderBy::asc("schedule_start"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(entries[0], b);
+
+        delete!(store, Event => {
+            schedule.repeat == 0,
+        })
+        .await
+        .unwrap();
+
+        let entries = get!(store, Event).await.unwrap();
+        assert_eq!(entries, vec![a]);
+    }
+
+    #[tokio::test]
+    async fn test_store_mixed_types() {
+        #[derive(Clone, Debug, StoreData, PartialEq, Eq)]
+        struct Rule {
+            id: u64,
+            enabled: bool,
+        }
+
+        // Stored in the same resource as `Rule`, with wider fields.
+        mod raw {
+            use robbot::StoreData;
+
+            #[derive(Clone, Debug, StoreData)]
+            pub struct Rule {
+                pub id: String,
+                pub enabled: u64,
+            }
+        }
+
+        let store = MemStore::connect("").await.unwrap();
+
+        let a = Rule {
+            id: 1,
+            enabled: true,
+        };
+        let b = Rule {
+            id: 2,
+            enabled: false,
+        };
+        for data in [&a, &b] {
+            insert!(store, data.clone()).await.unwrap();
+        }
+
+        let entries = get!(store, Rule => {
+            enabled == true,
+        })
+        .await
+        .unwrap();
+        assert_eq!(entries, vec![a.clone()]);
+
+        // Integers and bools compare by value, other types never match.
+        delete!(store, raw::Rule => {
+            id == String::from("2"),
+        })
+        .await
+        .unwrap();
+        delete!(store, raw::Rule => {
+            enabled == 1,
+        })
+        .await
+        .unwrap();
+
+        let entries = get!(store, Rule).await.unwrap();
+        assert_eq!(entries, vec![b]);
+    }
+
+    #[tokio::test]
+    async fn test_store_enum() {
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, StoreEnum)]
+        enum Kind {
+            Word,
+            #[store(rename = "invite")]
+            InviteLink,
+            Mention = 5,
+        }
+
+        #[derive(Clone, Debug, StoreData, PartialEq, Eq)]
+        struct Rule {
+            #[store(as = "u8")]
+            kind: Kind,
+            #[store(as = "string")]
+            name: Kind,
+        }
+
+        // Stored in the same resource as `Rule`, with the same layout.
+        mod raw {
+            use robbot::StoreData;
+
+            #[derive(Clone, Debug, StoreData)]
+            pub struct Rule {
+                pub kind: u8,
+                pub name: String,
+            }
+        }
+
+        let store = MemStore::connect("").await.unwrap();
+
+        let a = Rule {
+            kind: Kind::Word,
+            name: Kind::InviteLink,
+        };
+        let b = Rule {
+            kind: Kind::Mention,
+            name: Kind::Mention,
+        };
+        for data in [&a, &b] {
+            insert!(store, data.clone()).await.unwrap();
+        }
+
+        // Round trip.
+        let entries = get!(store, Rule).await.unwrap();
+        assert_eq!(entries, vec![a.clone(), b.clone()]);
+
+        let entries = get!(store, raw::Rule).await.unwrap();
+        assert_eq!(entries[0].kind, 0);
+        assert_eq!(entries[0].name, "invite");
+        assert_eq!(entries[1].kind, 5);
+        assert_eq!(entries[1].name, "mention");
+
+        // Query by variant.
+        let entries = get!(store, Rule => {
+            name == Kind::InviteLink,
+        })
+        .await
+        .unwrap();
+        assert_eq!(entries, vec![a.clone()]);
+
+        delete!(store, Rule => {
+            kind in vec![Kind::Mention, Kind::InviteLink],
+        })
+        .await
+        .unwrap();
+
+        let entries = get!(store, Rule).await.unwrap();
+        assert_eq!(entries, vec![a]);
+
+        // Unknown stored values.
+        insert!(
+            store,
+            raw::Rule {
+                kind: 9,
+                name: String::from("word"),
+            }
+        )
+        .await
+        .unwrap();
+
+        let err = get!(store, Rule).await.unwrap_err();
+        assert_eq!(
+            err,
+            MemError::UnknownVariant(UnknownVariant {
+                key: "kind",
+                ty: "Kind",
+                value: String::from("9"),
+            })
+        );
+
+        delete!(store, raw::Rule => { kind == 9 }).await.unwrap();
+        insert!(
+            store,
+            raw::Rule {
+                kind: 1,
+                name: String::from("spam"),
+            }
+        )
+        .await
+        .unwrap();
+
+        let err = get!(store, Rule => { kind == Kind::InviteLink })
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown value `spam` for `Kind` in field `name`"
+        );
+    }
+
     #[test]
     fn test_serializer() {
         let mut serializer = MemSerializer::new(mem::size_of::<(u8, i8, u16)>());
@@ -1319,7 +2144,7 @@ mod tests {
     #[test]
     fn test_deserializer() {
         #[cfg(target_endian = "little")]
-        let mut deserializer = MemDeserializer::new_from_slice(&[32, 253, 0, 1]);
+        let mut deserializer = MemDeserializer::new(&[32, 253, 0, 1]);
 
         #[cfg(target_endian = "big")]
         let mut deserializer = MemDeserializer::new(&[32, 253, 1, 0]);
@@ -1340,7 +2165,55 @@ mod tests {
             b'd', b'!',
         ];
 
-        let mut deserializer = MemDeserializer::new_from_slice(input);
+        let mut deserializer = MemDeserializer::new(input);
         assert_eq!(deserializer.deserialize_string().unwrap(), "Hello World!");
     }
+
+    #[test]
+    fn test_deserialize_entry_corrupt() {
+        #[derive(Clone, Debug, PartialEq, StoreData)]
+        struct Data {
+            flag: bool,
+            name: String,
+        }
+
+        let len = |len: usize| len.to_ne_bytes().to_vec();
+
+        let mut buf = vec![1];
+        buf.extend(len(2));
+        buf.extend(b"ok");
+        assert_eq!(
+            deserialize_entry::<Data>(&buf).unwrap(),
+            Data {
+                flag: true,
+                name: String::from("ok"),
+            }
+        );
+
+        // Regression inputs of the `mem_deserializer` fuzz target.
+        assert_eq!(deserialize_entry::<Data>(&[]), Err(MemError::UnexpectedEnd));
+        assert_eq!(
+            deserialize_entry::<Data>(&[2]),
+            Err(MemError::InvalidValue("bool"))
+        );
+        assert_eq!(
+            deserialize_entry::<Data>(&[0, 1, 2]),
+            Err(MemError::UnexpectedEnd)
+        );
+
+        let mut buf = vec![0];
+        buf.extend(len(usize::MAX));
+        assert_eq!(
+            deserialize_entry::<Data>(&buf),
+            Err(MemError::UnexpectedEnd)
+        );
+
+        let mut buf = vec![0];
+        buf.extend(len(2));
+        buf.extend([0xC3, 0x28]);
+        assert_eq!(
+            deserialize_entry::<Data>(&buf),
+            Err(MemError::InvalidValue("string"))
+        );
+    }
 }
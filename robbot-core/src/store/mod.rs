@@ -1,6 +1,16 @@
+#[cfg(test)]
+mod contract;
+mod export;
+mod handler;
+pub mod invalidate;
+mod lock;
 pub mod mem;
 pub mod mysql;
 
+pub use export::{ExportError, ImportStatus, Importer, EXPORT_VERSION};
+pub use handler::{Conflict, Degraded, StoreHandler};
+pub use lock::StoreLock;
+
 use std::error::Error as StdError;
 use std::fmt::{self, Display, Formatter};
 use std::result;
@@ -10,6 +20,16 @@ pub type Result<T> = result::Result<T, Error>;
 #[derive(Debug)]
 pub struct Error(Box<dyn StdError + Send + Sync + 'static>);
 
+impl Error {
+    /// Returns `true` if the error is of type `T`, e.g. [`Conflict`].
+    pub fn is<T>(&self) -> bool
+    where
+        T: StdError + 'static,
+    {
+        self.0.is::<T>()
+    }
+}
+
 impl AsRef<dyn StdError + 'static> for Error {
     fn as_ref(&self) -> &(dyn StdError + 'static) {
         &*self.0
@@ -1,19 +1,41 @@
 use async_trait::async_trait;
 use futures::TryStreamExt;
 use robbot::store::{
-    DataDescriptor, DataQuery, Deserialize, Deserializer, Serialize, Serializer, Store, StoreData,
-    TypeSerializer,
+    DataDescriptor, DataQuery, Decimal, Deserialize, Deserializer, Direction, OrderBy, Serialize,
+    Serializer, Store, StoreData, TypeSerializer, UnknownVariant,
 };
 use sqlx::{
     mysql::{MySqlPool, MySqlRow},
     Row,
 };
 
+use std::error::Error as StdError;
 use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
 
 pub type Error = sqlx::Error;
 
+/// The column type of `u128` and `i128` values. Both fit into 39 digits.
+const WIDE_INT_TYPE: &str = "DECIMAL(39, 0)";
+
+/// The column type of [`Decimal`] values. 65 is the largest precision MySQL
+/// supports, leaving 35 digits before the decimal point. [`Decimal`] rejects
+/// larger values.
+const DECIMAL_TYPE: &str = "DECIMAL(65, 30)";
+
+/// The column type of `bool` values. Rows inserted without the column must
+/// not contain `NULL`, it cannot be read as a `bool`.
+const BOOL_TYPE: &str = "BOOLEAN NOT NULL DEFAULT FALSE";
+
 /// A Store using the MySQL database.
+///
+/// Items returned by [`get`] and [`get_all`] are in the order chosen by the database,
+/// which is usually but not necessarily insertion order. Use [`get_ordered`] for a
+/// guaranteed order.
+///
+/// [`get`]: Store::get
+/// [`get_all`]: Store::get_all
+/// [`get_ordered`]: Store::get_ordered
 #[derive(Clone, Debug)]
 pub struct MysqlStore {
     pool: MySqlPool,
@@ -37,14 +59,34 @@ impl Store for MysqlStore {
     {
         let table_name = T::resource_name();
 
-        let mut serializer = MysqlSerializer::new(table_name, QueryKind::Create);
+        let mut serializer = MysqlSerializer::new(table_name.clone(), QueryKind::Create);
         descriptor.serialize(&mut serializer).unwrap();
 
-        let sql = serializer.into_sql();
+        let query = serializer.query;
+        let sql = query.to_string();
         log::debug!("[MySQL] Executing SQL create query: \"{}\"", sql);
 
         sqlx::query(&sql).execute(&self.pool).await?;
 
+        // Tables created before a field was added miss its column.
+        let rows = sqlx::query(
+            "SELECT COLUMN_NAME FROM information_schema.columns WHERE table_schema = DATABASE() AND table_name = ?",
+        )
+        .bind(&table_name)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let existing = rows
+            .iter()
+            .map(|row| row.try_get_unchecked::<String, _>(0))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for sql in add_missing_columns(&query, &existing) {
+            log::info!("[MySQL] Adding missing column: \"{}\"", sql);
+
+            sqlx::query(&sql).execute(&self.pool).await?;
+        }
+
         Ok(())
     }
 
@@ -91,7 +133,7 @@ impl Store for MysqlStore {
 
         while let Some(row) = rows.try_next().await? {
             let mut deserializer = MysqlDeserializer::new(row);
-            let data = T::deserialize(&mut deserializer).unwrap();
+            let data = T::deserialize(&mut deserializer)?;
 
             entries.push(data);
         }
@@ -118,7 +160,59 @@ impl Store for MysqlStore {
 
         while let Some(row) = rows.try_next().await? {
             let mut deserializer = MysqlDeserializer::new(row);
-            let data = T::deserialize(&mut deserializer).unwrap();
+            let data = T::deserialize(&mut deserializer)?;
+
+            entries.push(data);
+        }
+
+        Ok(entries)
+    }
+
+    async fn get_ordered<T, D, Q>(
+        &self,
+        descriptor: D,
+        query: Q,
+        order: OrderBy,
+    ) -> Result<Vec<T>, Error>
+    where
+        T: StoreData<Self> + Send,
+        D: DataDescriptor<T, Self> + Send,
+        Q: DataQuery<T, Self> + Send,
+    {
+        let table_name = T::resource_name();
+
+        let mut serializer = MysqlSerializer::new(table_name, QueryKind::Select);
+        descriptor.serialize(&mut serializer).unwrap();
+
+        // The key is written into the query as is, only known columns are
+        // accepted.
+        if !serializer.has_column(order.key) {
+            return Err(Error::ColumnNotFound(order.key.to_owned()));
+        }
+
+        serializer.enable_condition();
+        query.serialize(&mut serializer).unwrap();
+
+        let direction = match order.direction {
+            Direction::Asc => "ASC",
+            Direction::Desc => "DESC",
+        };
+
+        let sql = format!(
+            "{} ORDER BY {} {}",
+            serializer.into_sql(),
+            order.key,
+            direction
+        );
+        log::debug!("[MySQL] Executing SQL select query: \"{}\"", sql);
+
+        let mut rows = sqlx::query(&sql).fetch(&self.pool);
+
+        let mut entries = Vec::new();
+
+        while let Some(row) = rows.try_next().await? {
+            let mut deserializer = MysqlDeserializer::new(row);
+            let data = T::deserialize(&mut deserializer)?;
 
             entries.push(data);
         }
@@ -143,10 +237,13 @@ impl Store for MysqlStore {
         let sql = serializer.into_sql();
         log::debug!("[MySQL] Executing SQL select query: \"{}\"", sql);
 
-        let row = sqlx::query(&sql).fetch_one(&self.pool).await.unwrap();
+        let row = match sqlx::query(&sql).fetch_optional(&self.pool).await? {
+            Some(row) => row,
+            None => return Ok(None),
+        };
 
         let mut deserializer = MysqlDeserializer::new(row);
-        let data = T::deserialize(&mut deserializer).unwrap();
+        let data = T::deserialize(&mut deserializer)?;
 
         Ok(Some(data))
     }
@@ -167,6 +264,61 @@ impl Store for MysqlStore {
 
         Ok(())
     }
+
+    async fn update<T, Q>(&self, query: Q, data: T) -> Result<u64, Error>
+    where
+        T: StoreData<Self> + Send,
+        Q: DataQuery<T, Self> + Send,
+    {
+        let table_name = T::resource_name();
+
+        let mut serializer = MysqlSerializer::new(table_name, QueryKind::Update);
+        data.serialize(&mut serializer).unwrap();
+
+        serializer.enable_condition();
+        query.serialize(&mut serializer).unwrap();
+
+        let sql = serializer.into_sql();
+        log::debug!("[MySQL] Executing SQL update query: \"{}\"", sql);
+
+        // MySQL only counts rows that changed.
+        let res = sqlx::query(&sql).execute(&self.pool).await?;
+
+        Ok(res.rows_affected())
+    }
+}
+
+/// Returns the statements adding all columns of the create query `query`
+/// not contained in `existing`. The columns are `NOT NULL`, existing rows
+/// get the default of the column type, e.g. `0` for numbers.
+fn add_missing_columns(query: &Query, existing: &[String]) -> Vec<String> {
+    let (table_name, columns, values) = match query {
+        Query::Create {
+            table_name,
+            columns,
+            values,
+        } => (table_name, columns, values),
+        _ => return Vec::new(),
+    };
+
+    columns
+        .iter()
+        .zip(values)
+        // Column names are case insensitive.
+        .filter(|(column, _)| !existing.iter().any(|c| c.eq_ignore_ascii_case(column)))
+        .map(|(column, value)| {
+            // Some column types already include their constraints.
+            let constraint = match value.contains("NOT NULL") {
+                true => "",
+                false => " NOT NULL",
+            };
+
+            format!(
+                "ALTER TABLE {} ADD COLUMN {} {}{}",
+                table_name, column, value, constraint
+            )
+        })
+        .collect()
 }
 
 /// Type of the sql query being built.
@@ -176,6 +328,7 @@ enum QueryKind {
     Delete,
     Insert,
     Select,
+    Update,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -200,6 +353,12 @@ enum Query {
         columns: Vec<String>,
         conditions: ConditionsExpr,
     },
+    Update {
+        table_name: String,
+        columns: Vec<String>,
+        values: Vec<String>,
+        conditions: ConditionsExpr,
+    },
 }
 
 impl Display for Query {
@@ -246,6 +405,23 @@ impl Display for Query {
                 table_name,
                 conditions
             ),
+            Self::Update {
+                table_name,
+                columns,
+                values,
+                conditions,
+            } => write!(
+                f,
+                "UPDATE {} SET {}{}",
+                table_name,
+                columns
+                    .iter()
+                    .zip(values)
+                    .map(|(column, value)| format!("{} = {}", column, value))
+                    .collect::<Vec<String>>()
+                    .join(","),
+                conditions
+            ),
         }
     }
 }
@@ -254,6 +430,8 @@ impl Display for Query {
 enum Comparator {
     /// The equality comparator `=`.
     Eq,
+    /// The set membership comparator `IN`.
+    In,
     // /// The not equal comparator `!=`.
     // Ne,
     // /// The greater than comparator `>`.
@@ -270,6 +448,7 @@ impl Display for Comparator {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         let string = match self {
             Self::Eq => "=",
+            Self::In => "IN",
             // Self::Ne => "!=",
             // Self::Gt => ">",
             // Self::Ge => ">=",
@@ -345,6 +524,9 @@ impl Display for Condition {
 pub struct MysqlSerializer {
     query: Query,
     condition: Option<Condition>,
+    /// Collects values instead of writing them if `Some`. Used to build
+    /// the value list of an `IN` condition.
+    values: Option<Vec<String>>,
     // kind: QueryKind,
     // cols: Vec<String>,
     // vals: Vec<String>,
@@ -379,8 +561,15 @@ impl MysqlSerializer {
                     columns: Vec::new(),
                     conditions: ConditionsExpr::new(),
                 },
+                QueryKind::Update => Query::Update {
+                    table_name,
+                    columns: Vec::new(),
+                    values: Vec::new(),
+                    conditions: ConditionsExpr::new(),
+                },
             },
             condition: None,
+            values: None,
         }
     }
 
@@ -406,10 +595,24 @@ impl MysqlSerializer {
                 Query::Select {
                     ref mut columns, ..
                 } => columns.push(val),
+                Query::Update {
+                    ref mut columns, ..
+                } => columns.push(val),
             },
         }
     }
 
+    /// Returns `true` if `column` is one of the columns of the query.
+    fn has_column(&self, column: &str) -> bool {
+        match &self.query {
+            Query::Create { columns, .. }
+            | Query::Insert { columns, .. }
+            | Query::Select { columns, .. }
+            | Query::Update { columns, .. } => columns.iter().any(|col| col == column),
+            Query::Delete { .. } => false,
+        }
+    }
+
     /// Writes a value into the query. The usage depends on the type of the
     /// query. If the condition section of the query is reached, the value
     /// is instead used in the conditional expression, finalizing the conditional
@@ -420,6 +623,11 @@ impl MysqlSerializer {
     {
         let val = value.to_string();
 
+        if let Some(values) = &mut self.values {
+            values.push(val);
+            return;
+        }
+
         match &mut self.condition {
             Some(ref mut condition) => {
                 condition.value = val;
@@ -436,6 +644,9 @@ impl MysqlSerializer {
                     Query::Select {
                         ref mut conditions, ..
                     } => conditions.push(condition),
+                    Query::Update {
+                        ref mut conditions, ..
+                    } => conditions.push(condition),
                 }
 
                 // Create a new empty condition.
@@ -446,6 +657,7 @@ impl MysqlSerializer {
                 Query::Delete { .. } => unreachable!(),
                 Query::Insert { ref mut values, .. } => values.push(val),
                 Query::Select { .. } => (),
+                Query::Update { ref mut values, .. } => values.push(val),
             },
         }
     }
@@ -467,7 +679,7 @@ impl Serializer<MysqlStore> for MysqlSerializer {
 
     fn serialize_bool(&mut self, v: bool) -> Result<(), Self::Error> {
         let val = match self.query {
-            Query::Create { .. } => "BOOLEAN",
+            Query::Create { .. } => BOOL_TYPE,
             _ => match v {
                 false => "FALSE",
                 true => "TRUE",
@@ -577,6 +789,33 @@ impl Serializer<MysqlStore> for MysqlSerializer {
         Ok(())
     }
 
+    fn serialize_u128(&mut self, v: u128) -> Result<(), Self::Error> {
+        match self.query {
+            Query::Create { .. } => self.write_value(WIDE_INT_TYPE),
+            _ => self.write_value(v),
+        }
+
+        Ok(())
+    }
+
+    fn serialize_i128(&mut self, v: i128) -> Result<(), Self::Error> {
+        match self.query {
+            Query::Create { .. } => self.write_value(WIDE_INT_TYPE),
+            _ => self.write_value(v),
+        }
+
+        Ok(())
+    }
+
+    fn serialize_decimal(&mut self, v: &Decimal) -> Result<(), Self::Error> {
+        match self.query {
+            Query::Create { .. } => self.write_value(DECIMAL_TYPE),
+            _ => self.write_value(v),
+        }
+
+        Ok(())
+    }
+
     fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + Serialize<MysqlStore>,
@@ -586,13 +825,39 @@ impl Serializer<MysqlStore> for MysqlSerializer {
 
         Ok(())
     }
+
+    fn serialize_field_in<T>(&mut self, key: &'static str, values: &[T]) -> Result<(), Self::Error>
+    where
+        T: Serialize<MysqlStore>,
+    {
+        self.values = Some(Vec::with_capacity(values.len()));
+        for value in values {
+            value.serialize(self)?;
+        }
+        let values = self.values.take().unwrap();
+
+        match &mut self.condition {
+            Some(condition) => condition.comparator = Comparator::In,
+            None => panic!("Cannot store multiple values in the column `{}`", key),
+        }
+
+        self.write_column(key);
+
+        // `IN ()` is invalid SQL. `IN (NULL)` never matches any row.
+        match values.is_empty() {
+            false => self.write_value(format!("({})", values.join(","))),
+            true => self.write_value("(NULL)"),
+        }
+
+        Ok(())
+    }
 }
 
 impl TypeSerializer<MysqlStore> for MysqlSerializer {
     type Error = Error;
 
     fn serialize_bool(&mut self) -> Result<(), Self::Error> {
-        self.write_value("BOOLEAN");
+        self.write_value(BOOL_TYPE);
         Ok(())
     }
 
@@ -651,6 +916,21 @@ impl TypeSerializer<MysqlStore> for MysqlSerializer {
         Ok(())
     }
 
+    fn serialize_u128(&mut self) -> Result<(), Self::Error> {
+        self.write_value(WIDE_INT_TYPE);
+        Ok(())
+    }
+
+    fn serialize_i128(&mut self) -> Result<(), Self::Error> {
+        self.write_value(WIDE_INT_TYPE);
+        Ok(())
+    }
+
+    fn serialize_decimal(&mut self) -> Result<(), Self::Error> {
+        self.write_value(DECIMAL_TYPE);
+        Ok(())
+    }
+
     fn serialize_field<T>(&mut self, key: &'static str) -> Result<(), Self::Error>
     where
         T: ?Sized + Serialize<MysqlStore>,
@@ -677,6 +957,22 @@ impl MysqlDeserializer {
     fn column(&self) -> &'static str {
         self.column.unwrap()
     }
+
+    /// Parse a `DECIMAL` column from its text representation. sqlx can only
+    /// decode these into types of the `decimal` or `bigdecimal` crates.
+    fn parse_decimal<T>(&self) -> Result<T, Error>
+    where
+        T: FromStr,
+        T::Err: StdError + Send + Sync + 'static,
+    {
+        let column = self.column();
+        let v: &str = self.row.try_get_unchecked(column)?;
+
+        v.parse().map_err(|err| Error::ColumnDecode {
+            index: column.to_owned(),
+            source: Box::new(err),
+        })
+    }
 }
 
 impl Deserializer<MysqlStore> for MysqlDeserializer {
@@ -754,6 +1050,18 @@ impl Deserializer<MysqlStore> for MysqlDeserializer {
         Ok(v)
     }
 
+    fn deserialize_u128(&mut self) -> Result<u128, Self::Error> {
+        self.parse_decimal()
+    }
+
+    fn deserialize_i128(&mut self) -> Result<i128, Self::Error> {
+        self.parse_decimal()
+    }
+
+    fn deserialize_decimal(&mut self) -> Result<Decimal, Self::Error> {
+        self.parse_decimal()
+    }
+
     fn deserialize_field<T>(&mut self, key: &'static str) -> Result<T, Self::Error>
     where
         T: Deserialize<MysqlStore>,
@@ -761,6 +1069,13 @@ impl Deserializer<MysqlStore> for MysqlDeserializer {
         self.column = Some(key);
         T::deserialize(self)
     }
+
+    fn unknown_variant(&mut self, err: UnknownVariant) -> Self::Error {
+        Error::ColumnDecode {
+            index: err.key.to_owned(),
+            source: Box::new(err),
+        }
+    }
 }
 
 // ====================================================
@@ -975,6 +1290,54 @@ impl Serialize<MysqlStore> for String {
     }
 }
 
+impl Serialize<MysqlStore> for u128 {
+    fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+    where
+        S: Serializer<MysqlStore>,
+    {
+        serializer.serialize_u128(*self)
+    }
+
+    fn serialize_type<S>(serializer: &mut S) -> Result<(), S::Error>
+    where
+        S: TypeSerializer<MysqlStore>,
+    {
+        serializer.serialize_u128()
+    }
+}
+
+impl Serialize<MysqlStore> for i128 {
+    fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+    where
+        S: Serializer<MysqlStore>,
+    {
+        serializer.serialize_i128(*self)
+    }
+
+    fn serialize_type<S>(serializer: &mut S) -> Result<(), S::Error>
+    where
+        S: TypeSerializer<MysqlStore>,
+    {
+        serializer.serialize_i128()
+    }
+}
+
+impl Serialize<MysqlStore> for Decimal {
+    fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+    where
+        S: Serializer<MysqlStore>,
+    {
+        serializer.serialize_decimal(self)
+    }
+
+    fn serialize_type<S>(serializer: &mut S) -> Result<(), S::Error>
+    where
+        S: TypeSerializer<MysqlStore>,
+    {
+        serializer.serialize_decimal()
+    }
+}
+
 // ======================================================
 // === Implement [`Deserialize`] for supported types. ===
 // ======================================================
@@ -1087,12 +1450,40 @@ impl Deserialize<MysqlStore> for String {
     }
 }
 
+impl Deserialize<MysqlStore> for u128 {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<MysqlStore>,
+    {
+        deserializer.deserialize_u128()
+    }
+}
+
+impl Deserialize<MysqlStore> for i128 {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<MysqlStore>,
+    {
+        deserializer.deserialize_i128()
+    }
+}
+
+impl Deserialize<MysqlStore> for Decimal {
+    fn deserialize<D>(deserializer: &mut D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<MysqlStore>,
+    {
+        deserializer.deserialize_decimal()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        Comparator, Condition, ConditionsExpr, MysqlSerializer, MysqlStore, Query, QueryKind,
+        add_missing_columns, Comparator, Condition, ConditionsExpr, MysqlSerializer, MysqlStore,
+        Query, QueryKind,
     };
-    use robbot::store::{Serializer, TypeSerializer};
+    use robbot::store::{Decimal, Serializer, TypeSerializer};
 
     macro_rules! serialize {
         ($serializer:expr, $key:expr, $val:expr) => {
@@ -1105,6 +1496,17 @@ mod tests {
         };
     }
 
+    macro_rules! serialize_in {
+        ($serializer:expr, $key:expr, $vals:expr) => {
+            <MysqlSerializer as Serializer<MysqlStore>>::serialize_field_in(
+                &mut $serializer,
+                $key,
+                $vals,
+            )
+            .unwrap();
+        };
+    }
+
     macro_rules! serialize_type {
         ($serializer:expr, $key:expr, $t:ty) => {
             <MysqlSerializer as TypeSerializer<MysqlStore>>::serialize_field::<$t>(
@@ -1214,6 +1616,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_add_missing_columns() {
+        let mut serializer = MysqlSerializer::new(String::from("test"), QueryKind::Create);
+        serialize_type!(serializer, "id", i32);
+        serialize_type!(serializer, "name", String);
+        serialize_type!(serializer, "version", u64);
+        serialize_type!(serializer, "enabled", bool);
+
+        let existing = [String::from("ID"), String::from("name")];
+        assert_eq!(
+            add_missing_columns(&serializer.query, &existing),
+            [
+                "ALTER TABLE test ADD COLUMN version BIGINT UNSIGNED NOT NULL",
+                "ALTER TABLE test ADD COLUMN enabled BOOLEAN NOT NULL DEFAULT FALSE",
+            ]
+        );
+
+        let existing = [
+            String::from("id"),
+            String::from("name"),
+            String::from("version"),
+            String::from("enabled"),
+        ];
+        assert!(add_missing_columns(&serializer.query, &existing).is_empty());
+    }
+
     #[test]
     fn test_serializer_sql() {
         let mut serializer = MysqlSerializer::new(String::from("test"), QueryKind::Create);
@@ -1233,6 +1661,15 @@ mod tests {
             "CREATE TABLE IF NOT EXISTS test (id INT,name BIGINT)"
         );
 
+        let mut serializer = MysqlSerializer::new(String::from("test"), QueryKind::Create);
+        serialize_type!(serializer, "id", i32);
+        serialize_type!(serializer, "enabled", bool);
+
+        assert_eq!(
+            serializer.into_sql(),
+            "CREATE TABLE IF NOT EXISTS test (id INT,enabled BOOLEAN NOT NULL DEFAULT FALSE)"
+        );
+
         let mut serializer = MysqlSerializer::new(String::from("test"), QueryKind::Delete);
         serializer.enable_condition();
         serialize!(serializer, "id", &3);
@@ -1279,6 +1716,174 @@ mod tests {
         assert_eq!(
             serializer.into_sql(),
             "SELECT id,name FROM test WHERE id = 3 AND name = 'abc'"
-        )
+        );
+
+        let mut serializer = MysqlSerializer::new(String::from("test"), QueryKind::Select);
+        serialize_type!(serializer, "id", u8);
+        serialize_type!(serializer, "name", u8);
+        serializer.enable_condition();
+        serialize_in!(serializer, "id", &[1, 2, 3]);
+        serialize!(serializer, "name", "abc");
+
+        assert_eq!(
+            serializer.into_sql(),
+            "SELECT id,name FROM test WHERE id IN (1,2,3) AND name = 'abc'"
+        );
+
+        let mut serializer = MysqlSerializer::new(String::from("test"), QueryKind::Update);
+        serialize!(serializer, "id", &3);
+        serialize!(serializer, "name", "abc");
+        serialize!(serializer, "version", &8u64);
+        serializer.enable_condition();
+        serialize!(serializer, "id", &3);
+        serialize!(serializer, "version", &7u64);
+
+        assert_eq!(
+            serializer.into_sql(),
+            "UPDATE test SET id = 3,name = 'abc',version = 8 WHERE id = 3 AND version = 7"
+        );
+
+        let mut serializer = MysqlSerializer::new(String::from("test"), QueryKind::Delete);
+        serializer.enable_condition();
+        serialize_in!(serializer, "name", &[String::from("a"), String::from("b'")]);
+
+        assert_eq!(
+            serializer.into_sql(),
+            "DELETE FROM test WHERE name IN ('a','b\\'')"
+        );
+
+        let mut serializer = MysqlSerializer::new(String::from("test"), QueryKind::Delete);
+        serializer.enable_condition();
+        serialize_in!(serializer, "id", &[] as &[u64]);
+
+        assert_eq!(serializer.into_sql(), "DELETE FROM test WHERE id IN (NULL)");
+
+        let mut serializer = MysqlSerializer::new(String::from("test"), QueryKind::Create);
+        serialize_type!(serializer, "a", u128);
+        serialize_type!(serializer, "b", i128);
+        serialize_type!(serializer, "c", Decimal);
+
+        assert_eq!(
+            serializer.into_sql(),
+            "CREATE TABLE IF NOT EXISTS test (a DECIMAL(39, 0),b DECIMAL(39, 0),c DECIMAL(65, 30))"
+        );
+
+        let mut serializer = MysqlSerializer::new(String::from("test"), QueryKind::Insert);
+        serialize!(serializer, "a", &u128::MAX);
+        serialize!(serializer, "b", &i128::MIN);
+        serialize!(serializer, "c", &"-0.005".parse::<Decimal>().unwrap());
+
+        assert_eq!(
+            serializer.into_sql(),
+            "INSERT INTO test (a,b,c) VALUES (340282366920938463463374607431768211455,-170141183460469231731687303715884105728,-0.005)"
+        );
+    }
+
+    #[test]
+    fn test_serializer_flatten() {
+        use robbot::store::{DataDescriptor, DataQuery, StoreData};
+
+        #[derive(Clone, Debug, StoreData)]
+        struct Schedule {
+            start: i64,
+            repeat: u64,
+        }
+
+        #[derive(Clone, Debug, StoreData)]
+        struct Event {
+            id: u64,
+            #[store(flatten)]
+            schedule: Schedule,
+        }
+
+        let mut serializer = MysqlSerializer::new(String::from("Event"), QueryKind::Create);
+        EventDescriptor.serialize(&mut serializer).unwrap();
+
+        assert_eq!(
+            serializer.into_sql(),
+            "CREATE TABLE IF NOT EXISTS Event (id BIGINT UNSIGNED,schedule_start BIGINT,schedule_repeat BIGINT UNSIGNED)"
+        );
+
+        let event = Event {
+            id: 1,
+            schedule: Schedule {
+                start: 100,
+                repeat: 60,
+            },
+        };
+
+        let mut serializer = MysqlSerializer::new(String::from("Event"), QueryKind::Insert);
+        StoreData::<MysqlStore>::serialize(&event, &mut serializer).unwrap();
+
+        assert_eq!(
+            serializer.into_sql(),
+            "INSERT INTO Event (id,schedule_start,schedule_repeat) VALUES (1,100,60)"
+        );
+
+        let query = EventQuery::default().schedule(|query| query.start(100).repeat_in([30, 60]));
+
+        let mut serializer = MysqlSerializer::new(String::from("Event"), QueryKind::Delete);
+        serializer.enable_condition();
+        DataQuery::<Event, MysqlStore>::serialize(&query, &mut serializer).unwrap();
+
+        assert_eq!(
+            serializer.into_sql(),
+            "DELETE FROM Event WHERE schedule_start = 100 AND schedule_repeat IN (30,60)"
+        );
+    }
+
+    #[test]
+    fn test_serializer_enum() {
+        use robbot::store::{DataDescriptor, DataQuery, StoreData, StoreEnum};
+
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, StoreEnum)]
+        enum Kind {
+            Word,
+            #[store(rename = "invite")]
+            InviteLink,
+            Mention = 5,
+        }
+
+        #[derive(Clone, Debug, StoreData)]
+        struct Rule {
+            #[store(as = "u8")]
+            kind: Kind,
+            #[store(as = "string")]
+            name: Kind,
+        }
+
+        let mut serializer = MysqlSerializer::new(String::from("Rule"), QueryKind::Create);
+        RuleDescriptor.serialize(&mut serializer).unwrap();
+
+        assert_eq!(
+            serializer.into_sql(),
+            "CREATE TABLE IF NOT EXISTS Rule (kind TINYINT UNSIGNED,name TEXT)"
+        );
+
+        let rule = Rule {
+            kind: Kind::Mention,
+            name: Kind::InviteLink,
+        };
+
+        let mut serializer = MysqlSerializer::new(String::from("Rule"), QueryKind::Insert);
+        StoreData::<MysqlStore>::serialize(&rule, &mut serializer).unwrap();
+
+        assert_eq!(
+            serializer.into_sql(),
+            "INSERT INTO Rule (kind,name) VALUES (5,'invite')"
+        );
+
+        let query = RuleQuery::default()
+            .kind(Kind::InviteLink)
+            .name_in([Kind::Word, Kind::Mention]);
+
+        let mut serializer = MysqlSerializer::new(String::from("Rule"), QueryKind::Delete);
+        serializer.enable_condition();
+        DataQuery::<Rule, MysqlStore>::serialize(&query, &mut serializer).unwrap();
+
+        assert_eq!(
+            serializer.into_sql(),
+            "DELETE FROM Rule WHERE kind = 1 AND name IN ('word','mention')"
+        );
     }
 }
@@ -1,13 +1,18 @@
 use crate::context::Context;
+use crate::disable::{DisableReason, DisabledSet};
+use crate::executor::{guard, Outcome};
 
 use robbot::executor::Executor;
 use robbot::task::TaskSchedule;
 
 use chrono::{DateTime, Duration, Utc};
+use parking_lot::Mutex;
 use tokio::sync::{mpsc, oneshot};
 use tokio::{select, task, time};
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::Arc;
 
 const SCHEDULER_MESSAGEQUEUE_SIZE: usize = 32;
 
@@ -21,6 +26,16 @@ pub struct Task {
     pub executor: Executor<Context<()>>,
     /// Makes the task execute immediately when it is added.
     pub on_load: bool,
+    /// The concurrency group the task runs in. See [`crate::concurrency`].
+    pub concurrency_group: Option<String>,
+    /// The time a run may take before it is dropped. `None` uses the
+    /// default of the config, see [`Timeouts`].
+    ///
+    /// [`Timeouts`]: crate::executor::Timeouts
+    pub timeout: Option<time::Duration>,
+    /// Whether the task writes to the store. Such tasks are skipped in
+    /// maintenance mode, see [`crate::maintenance`].
+    pub mutates_data: bool,
 }
 
 impl Task {
@@ -34,6 +49,9 @@ impl Task {
             schedule,
             executor,
             on_load: false,
+            concurrency_group: None,
+            timeout: None,
+            mutates_data: false,
         }
     }
 }
@@ -44,37 +62,111 @@ impl From<LoadedTask> for Task {
             name: task.name,
             schedule: task.schedule,
             executor: task.executor,
+            concurrency_group: task.concurrency_group,
+            timeout: task.timeout,
+            mutates_data: task.mutates_data,
 
             on_load: false,
         }
     }
 }
 
+/// A snapshot of the state of a task in the [`TaskScheduler`].
+#[derive(Clone, Debug)]
+pub struct TaskInfo {
+    pub name: String,
+    pub schedule: TaskSchedule,
+    /// The schedule the task was registered with, if the current schedule is
+    /// overridden by the config or [`TaskScheduler::reschedule`]. `None` if
+    /// the task runs on its default schedule.
+    pub default_schedule: Option<TaskSchedule>,
+    /// The time the task executes next. `None` if the task never executes again.
+    pub next_exec: Option<DateTime<Utc>>,
+    /// The time the task was last started. `None` if the task never ran.
+    pub last_exec: Option<DateTime<Utc>>,
+    /// The result of the last completed run. `None` if no run has completed yet.
+    /// Errors are stored in their debug representation.
+    pub last_result: Option<Result<(), String>>,
+    /// Whether the task is still scheduled to execute.
+    pub enabled: bool,
+    /// The reason the task is disabled. Disabled tasks keep their schedule,
+    /// but are skipped until they are enabled again.
+    pub disabled: Option<DisableReason>,
+}
+
+/// The record of the last run of a task.
+#[derive(Clone, Debug, Default)]
+struct TaskRun {
+    started: Option<DateTime<Utc>>,
+    result: Option<Result<(), String>>,
+}
+
+impl TaskRun {
+    /// Records the start of a new run at `now`, clearing the result of the
+    /// previous run.
+    fn start(&mut self, now: DateTime<Utc>) {
+        self.started = Some(now);
+        self.result = None;
+    }
+}
+
 #[derive(Clone)]
 struct LoadedTask {
     name: String,
     schedule: TaskSchedule,
+    /// The schedule the task was registered with.
+    default_schedule: TaskSchedule,
     executor: Executor<Context<()>>,
+    concurrency_group: Option<String>,
+    timeout: Option<time::Duration>,
+    mutates_data: bool,
     /// The time the task should be called again. Used to order the task queue.
     next_execution_time: DateTime<Utc>,
+    /// Shared with the spawned executor, which records the result when it completes.
+    last_run: Arc<Mutex<TaskRun>>,
 }
 
 impl LoadedTask {
     /// Converts a [`Task`] into a `LoadedTask` using `now` as the current time.
+    /// The task runs on `schedule` if given, otherwise on its own schedule.
     /// Returns `None` if a task will never execute.
-    fn from(task: Task, now: DateTime<Utc>) -> Option<Self> {
+    fn from(task: Task, schedule: Option<TaskSchedule>, now: DateTime<Utc>) -> Option<Self> {
+        let schedule = schedule.unwrap_or_else(|| task.schedule.clone());
+
         let next_execution_time = match task.on_load {
             true => now,
-            false => task.schedule.advance(now)?,
+            false => schedule.advance(now)?,
         };
 
         Some(Self {
             name: task.name,
-            schedule: task.schedule,
+            default_schedule: task.schedule,
+            schedule,
             executor: task.executor,
+            concurrency_group: task.concurrency_group,
+            timeout: task.timeout,
+            mutates_data: task.mutates_data,
             next_execution_time,
+            last_run: Arc::default(),
         })
     }
+
+    /// Returns a [`TaskInfo`] snapshot of the task.
+    fn info(&self, enabled: bool, disabled: &DisabledSet) -> TaskInfo {
+        let last_run = self.last_run.lock().clone();
+
+        TaskInfo {
+            name: self.name.clone(),
+            schedule: self.schedule.clone(),
+            default_schedule: (self.schedule != self.default_schedule)
+                .then(|| self.default_schedule.clone()),
+            next_exec: enabled.then_some(self.next_execution_time),
+            last_exec: last_run.started,
+            last_result: last_run.result,
+            enabled,
+            disabled: disabled.get(&self.name),
+        }
+    }
 }
 
 #[derive(Clone, Default)]
@@ -87,8 +179,7 @@ impl TaskQueue {
     fn push(&mut self, task: LoadedTask) {
         for (i, t) in self.tasks.iter().enumerate() {
             if task.next_execution_time < t.next_execution_time {
-                self.tasks.push_front(task);
-                self.tasks.swap(i, 0);
+                self.tasks.insert(i, task);
                 return;
             }
         }
@@ -121,6 +212,12 @@ impl TaskQueue {
         self.tasks.get(index)
     }
 
+    /// Removes and returns the task with the given `name`.
+    fn remove(&mut self, name: &str) -> Option<LoadedTask> {
+        let index = self.tasks.iter().position(|task| task.name == name)?;
+        self.tasks.remove(index)
+    }
+
     /// Returns the number of queued tasks.
     fn len(&self) -> usize {
         self.tasks.len()
@@ -134,31 +231,95 @@ impl TaskQueue {
 #[derive(Clone, Default)]
 struct InnerTaskScheduler {
     tasks: TaskQueue,
+    /// Tasks that never execute again. They are kept to report their last run.
+    finished: Vec<LoadedTask>,
     context: Option<Context<()>>,
+    /// Schedules replacing the schedule of tasks, by task name.
+    overrides: HashMap<String, TaskSchedule>,
+    disabled: Arc<DisabledSet>,
 }
 
 impl InnerTaskScheduler {
-    fn new() -> Self {
-        Self::default()
+    fn new(overrides: HashMap<String, TaskSchedule>, disabled: Arc<DisabledSet>) -> Self {
+        Self {
+            overrides,
+            disabled,
+            ..Default::default()
+        }
     }
 
     fn add_task(&mut self, task: Task) {
-        log::info!("[TASK] Added new task '{}'", task.name);
+        let schedule = self.overrides.get(&task.name).cloned();
+
+        match &schedule {
+            Some(schedule) => log::info!(
+                "[TASK] Added new task '{}' (schedule overridden: {})",
+                task.name,
+                schedule
+            ),
+            None => log::info!("[TASK] Added new task '{}'", task.name),
+        }
 
         // Only add the task if it ever executes.
         let now = Utc::now();
-        if let Some(task) = LoadedTask::from(task, now) {
+        if let Some(task) = LoadedTask::from(task, schedule, now) {
             self.tasks.push(task);
         }
     }
 
-    fn get_tasks(&self, tx: oneshot::Sender<Vec<(Task, DateTime<Utc>)>>) {
+    fn reschedule(&mut self, name: &str, schedule: TaskSchedule) -> bool {
+        let mut task = match self.tasks.remove(name) {
+            Some(task) => task,
+            None => match self.finished.iter().position(|task| task.name == name) {
+                Some(index) => self.finished.remove(index),
+                None => return false,
+            },
+        };
+
+        log::info!("[TASK] Rescheduled task '{}' to {}", name, schedule);
+        task.schedule = schedule;
+
+        match task.schedule.advance(Utc::now()) {
+            Some(next_execution_time) => {
+                task.next_execution_time = next_execution_time;
+                self.tasks.push(task);
+            }
+            None => self.finished.push(task),
+        }
+
+        true
+    }
+
+    fn unknown_overrides(&self, tx: oneshot::Sender<Vec<String>>) {
+        let mut names: Vec<_> = self
+            .overrides
+            .keys()
+            .filter(|name| {
+                !self
+                    .tasks
+                    .tasks
+                    .iter()
+                    .chain(self.finished.iter())
+                    .any(|task| task.name == **name)
+            })
+            .cloned()
+            .collect();
+        names.sort();
+
+        let _ = tx.send(names);
+    }
+
+    fn get_tasks(&self, tx: oneshot::Sender<Vec<TaskInfo>>) {
         let tasks = self
             .tasks
             .tasks
-            .clone()
-            .into_iter()
-            .map(|task| (task.clone().into(), task.next_execution_time))
+            .iter()
+            .map(|task| task.info(true, &self.disabled))
+            .chain(
+                self.finished
+                    .iter()
+                    .map(|task| task.info(false, &self.disabled)),
+            )
             .collect();
 
         let _ = tx.send(tasks);
@@ -172,29 +333,66 @@ impl InnerTaskScheduler {
         // Wait until the execution time is reached.
         let mut task = self.tasks.await_pop().await.unwrap();
 
-        {
+        let maintenance = self
+            .context
+            .as_ref()
+            .is_some_and(|ctx| ctx.state.maintenance().is_enabled());
+
+        if self.disabled.is_disabled(&task.name) {
+            log::debug!("Skipping disabled task {}", task.name);
+        } else if task.mutates_data && maintenance {
+            log::info!("Skipping task {} in maintenance mode", task.name);
+        } else {
             let task = task.clone();
             let ctx = self.context.clone();
+            let disabled = self.disabled.clone();
+
+            task.last_run.lock().start(Utc::now());
 
             task::spawn(async move {
+                let ctx = ctx.unwrap();
+
+                let _permit = match &task.concurrency_group {
+                    Some(group) => {
+                        let ticket = ctx.state.concurrency().enqueue(group);
+
+                        if let Some(queued) = ticket.queued() {
+                            log::info!(
+                                "Task {} is queued behind {} other operations",
+                                task.name,
+                                queued
+                            );
+                        }
+
+                        Some(ticket.wait().await)
+                    }
+                    None => None,
+                };
+
                 log::info!("Spawning task {}", task.name);
 
-                let res = task.executor.call(ctx.unwrap()).await;
-                match res {
-                    Ok(_) => log::info!("Task {} completed", task.name),
-                    Err(err) => log::error!("Task {} failed: {:?}", task.name, err),
-                }
+                let timeout = match task.timeout {
+                    Some(timeout) => Some(timeout),
+                    None => ctx.state.config.timeouts.task(),
+                };
+
+                let res = run(&task.name, task.executor.call(ctx), timeout, &disabled).await;
+
+                task.last_run.lock().result = Some(res);
             });
         }
 
         let now = Utc::now();
 
         // Put the task back into the queue. If `advance` returns `None` the task will
-        // never execute again, so ignore it.
-        if let Some(next_execution_time) = task.schedule.advance(now) {
-            task.next_execution_time = next_execution_time;
+        // never execute again, so move it to the finished tasks.
+        match task.schedule.advance(now) {
+            Some(next_execution_time) => {
+                task.next_execution_time = next_execution_time;
 
-            self.tasks.push(task);
+                self.tasks.push(task);
+            }
+            None => self.finished.push(task),
         }
     }
 
@@ -203,6 +401,10 @@ impl InnerTaskScheduler {
             TaskSchedulerMessage::AddTask(task) => self.add_task(task),
             TaskSchedulerMessage::GetTasks(tx) => self.get_tasks(tx),
             TaskSchedulerMessage::UpdateContext(ctx) => self.update_context(ctx),
+            TaskSchedulerMessage::Reschedule(name, schedule, tx) => {
+                let _ = tx.send(self.reschedule(&name, schedule));
+            }
+            TaskSchedulerMessage::UnknownOverrides(tx) => self.unknown_overrides(tx),
         }
     }
 
@@ -230,24 +432,78 @@ impl InnerTaskScheduler {
     }
 }
 
+/// Runs a task and returns the result recorded as its last run. Tasks that
+/// panic are disabled. A task running longer than `timeout` is dropped and
+/// fails, it runs again on its next scheduled time.
+async fn run<F>(
+    name: &str,
+    future: F,
+    timeout: Option<time::Duration>,
+    disabled: &DisabledSet,
+) -> Result<(), String>
+where
+    F: Future<Output = robbot::Result>,
+{
+    match guard(future, timeout).await {
+        Outcome::Completed(Ok(())) => {
+            log::info!("Task {} completed", name);
+            Ok(())
+        }
+        Outcome::Completed(Err(err)) => {
+            log::error!("Task {} failed: {:?}", name, err);
+            Err(format!("{:?}", err))
+        }
+        Outcome::Panicked(payload) => {
+            let reason = DisableReason::panic(payload.as_ref());
+            let detail = reason.detail.clone();
+
+            disabled.disable(name, reason);
+            Err(detail)
+        }
+        Outcome::TimedOut(timeout) => {
+            log::error!("Task {} timed out after {:?}", name, timeout);
+            Err(format!("timed out after {:?}", timeout))
+        }
+    }
+}
+
 enum TaskSchedulerMessage {
     AddTask(Task),
-    GetTasks(oneshot::Sender<Vec<(Task, DateTime<Utc>)>>),
+    GetTasks(oneshot::Sender<Vec<TaskInfo>>),
     UpdateContext(Option<Context<()>>),
+    Reschedule(String, TaskSchedule, oneshot::Sender<bool>),
+    UnknownOverrides(oneshot::Sender<Vec<String>>),
 }
 
 #[derive(Clone, Debug)]
 pub struct TaskScheduler {
     tx: mpsc::Sender<TaskSchedulerMessage>,
+    disabled: Arc<DisabledSet>,
 }
 
 impl TaskScheduler {
     /// Creates a new `TaskScheduler` with a new internal
     /// task queue.
     pub fn new() -> Self {
-        let inner = InnerTaskScheduler::new();
+        Self::with_overrides(HashMap::new())
+    }
 
-        Self { tx: inner.start() }
+    /// Creates a new `TaskScheduler` which runs the tasks named in `overrides`
+    /// on the given schedule instead of the schedule they are added with.
+    pub fn with_overrides(overrides: HashMap<String, TaskSchedule>) -> Self {
+        let disabled = Arc::new(DisabledSet::new());
+        let inner = InnerTaskScheduler::new(overrides, disabled.clone());
+
+        Self {
+            tx: inner.start(),
+            disabled,
+        }
+    }
+
+    /// Returns the disabled tasks, by name. Tasks are disabled automatically
+    /// when they panic.
+    pub fn disabled(&self) -> &DisabledSet {
+        &self.disabled
     }
 
     /// Add a new task to the task queue.
@@ -255,7 +511,9 @@ impl TaskScheduler {
         let _ = self.tx.send(TaskSchedulerMessage::AddTask(task)).await;
     }
 
-    pub async fn get_tasks(&self) -> Vec<(Task, DateTime<Utc>)> {
+    /// Returns a snapshot of all tasks, including tasks that never execute
+    /// again. Scheduled tasks are ordered by their next execution time.
+    pub async fn get_tasks(&self) -> Vec<TaskInfo> {
         let (tx, rx) = oneshot::channel();
 
         let _ = self.tx.send(TaskSchedulerMessage::GetTasks(tx)).await;
@@ -263,6 +521,40 @@ impl TaskScheduler {
         rx.await.unwrap()
     }
 
+    /// Changes the schedule of the task `name`. The next execution is computed
+    /// from the current time using the new `schedule`. Returns `false` if no
+    /// task with the name exists.
+    pub async fn reschedule<T>(&self, name: T, schedule: TaskSchedule) -> bool
+    where
+        T: ToString,
+    {
+        let (tx, rx) = oneshot::channel();
+
+        let _ = self
+            .tx
+            .send(TaskSchedulerMessage::Reschedule(
+                name.to_string(),
+                schedule,
+                tx,
+            ))
+            .await;
+
+        rx.await.unwrap()
+    }
+
+    /// Returns the names of all schedule overrides that do not belong to any
+    /// task, sorted by name.
+    pub async fn unknown_overrides(&self) -> Vec<String> {
+        let (tx, rx) = oneshot::channel();
+
+        let _ = self
+            .tx
+            .send(TaskSchedulerMessage::UnknownOverrides(tx))
+            .await;
+
+        rx.await.unwrap()
+    }
+
     /// Updates the `Context` for the task executor. If the context was previously
     /// `None` and is set to a non-`None` value, the executor will start executing
     /// tasks.
@@ -276,3 +568,147 @@ impl Default for TaskScheduler {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{run, LoadedTask, Task, TaskQueue, TaskScheduler};
+    use crate::context::Context;
+    use crate::disable::{DisableReason, DisabledSet};
+
+    use robbot::executor::Executor;
+    use robbot::task::TaskSchedule;
+
+    use chrono::{Duration, Utc};
+    use futures::future;
+
+    use std::collections::HashMap;
+
+    async fn noop(_ctx: Context<()>) -> robbot::Result {
+        Ok(())
+    }
+
+    fn task(name: &str) -> Task {
+        Task::new(name, TaskSchedule::hourly(), Executor::from_fn(noop))
+    }
+
+    #[tokio::test]
+    async fn test_task_scheduler_overrides() {
+        let overrides = HashMap::from([
+            (String::from("a"), TaskSchedule::minutely()),
+            (String::from("unknown"), TaskSchedule::daily()),
+        ]);
+
+        let scheduler = TaskScheduler::with_overrides(overrides);
+        scheduler.add_task(task("a")).await;
+        scheduler.add_task(task("b")).await;
+
+        let tasks = scheduler.get_tasks().await;
+        assert_eq!(tasks[0].name, "a");
+        assert_eq!(tasks[0].schedule, TaskSchedule::minutely());
+        assert_eq!(tasks[0].default_schedule, Some(TaskSchedule::hourly()));
+        assert_eq!(tasks[1].name, "b");
+        assert_eq!(tasks[1].schedule, TaskSchedule::hourly());
+        assert_eq!(tasks[1].default_schedule, None);
+
+        assert_eq!(scheduler.unknown_overrides().await, ["unknown"]);
+    }
+
+    #[tokio::test]
+    async fn test_task_scheduler_reschedule() {
+        let scheduler = TaskScheduler::new();
+        scheduler.add_task(task("a")).await;
+        scheduler.add_task(task("b")).await;
+
+        assert!(scheduler.reschedule("b", TaskSchedule::minutely()).await);
+        assert!(!scheduler.reschedule("c", TaskSchedule::minutely()).await);
+
+        // `b` now runs before `a`.
+        let tasks = scheduler.get_tasks().await;
+        assert_eq!(tasks[0].name, "b");
+        assert_eq!(tasks[0].schedule, TaskSchedule::minutely());
+        assert_eq!(tasks[0].default_schedule, Some(TaskSchedule::hourly()));
+        assert!(tasks[0].next_exec < tasks[1].next_exec);
+
+        // Rescheduling to the default schedule removes the override.
+        assert!(scheduler.reschedule("b", TaskSchedule::hourly()).await);
+        let tasks = scheduler.get_tasks().await;
+        assert_eq!(tasks[1].name, "b");
+        assert_eq!(tasks[1].default_schedule, None);
+    }
+
+    #[tokio::test]
+    async fn test_task_scheduler_disabled() {
+        let scheduler = TaskScheduler::new();
+        scheduler.add_task(task("a")).await;
+
+        assert!(scheduler.get_tasks().await[0].disabled.is_none());
+
+        scheduler
+            .disabled()
+            .disable("a", DisableReason::automatic("panicked: test"));
+
+        // The reason is kept across snapshots and reschedules.
+        assert!(scheduler.reschedule("a", TaskSchedule::minutely()).await);
+        let tasks = scheduler.get_tasks().await;
+        assert!(tasks[0].enabled);
+        assert_eq!(tasks[0].disabled.as_ref().unwrap().detail, "panicked: test");
+
+        assert!(scheduler.disabled().enable("a").is_some());
+        assert!(scheduler.get_tasks().await[0].disabled.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_task_run_timeout() {
+        let disabled = DisabledSet::new();
+        let timeout = Some(std::time::Duration::from_secs(300));
+
+        let res = run("a", future::pending(), timeout, &disabled).await;
+        assert_eq!(res, Err(String::from("timed out after 300s")));
+        // Timeouts don't disable the task.
+        assert!(!disabled.is_disabled("a"));
+
+        let res = run("a", future::ready(Ok(())), timeout, &disabled).await;
+        assert_eq!(res, Ok(()));
+
+        let res = run("a", async { panic!("test") }, timeout, &disabled).await;
+        assert_eq!(res, Err(String::from("panicked: test")));
+        assert!(disabled.is_disabled("a"));
+    }
+
+    #[tokio::test]
+    async fn test_task_run_start() {
+        let task = LoadedTask::from(task("a"), None, Utc::now()).unwrap();
+        let disabled = DisabledSet::new();
+
+        let started = Utc::now();
+        task.last_run.lock().start(started);
+        task.last_run.lock().result = Some(Err(String::from("failed")));
+        assert!(task.info(true, &disabled).last_result.is_some());
+
+        // A new run is shown as running, not with the result of the last run.
+        task.last_run.lock().start(started + Duration::hours(1));
+        let info = task.info(true, &disabled);
+        assert_eq!(info.last_exec, Some(started + Duration::hours(1)));
+        assert_eq!(info.last_result, None);
+    }
+
+    #[tokio::test]
+    async fn test_task_queue_order() {
+        let now = Utc::now();
+        let mut queue = TaskQueue::default();
+
+        for (name, minutes) in [("a", 1), ("b", 2), ("d", 5), ("c", 3)] {
+            let task = LoadedTask::from(
+                task(name),
+                Some(TaskSchedule::Interval(Duration::minutes(minutes))),
+                now,
+            )
+            .unwrap();
+
+            queue.push(task);
+        }
+
+        let names: Vec<_> = queue.tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c", "d"]);
+    }
+}
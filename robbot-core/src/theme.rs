@@ -0,0 +1,15 @@
+//! The colors used in embeds sent by the bot. Plugins should use these
+//! instead of their own colors so all responses look the same.
+use robbot::util::color::Color;
+
+/// The color of regular responses.
+pub const EMBED_COLOR: Color = Color::from_rgb(0xFF, 0xA6, 0x00);
+
+/// The color of errors, e.g. in the log channel.
+pub const COLOR_ERROR: Color = Color::from_rgb(255, 0, 0);
+
+/// The color of warnings.
+pub const COLOR_WARN: Color = Color::from_rgb(252, 240, 20);
+
+/// The color of informational messages.
+pub const COLOR_INFO: Color = Color::from_rgb(15, 86, 252);
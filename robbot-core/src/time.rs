@@ -0,0 +1,4 @@
+//! Constants for working with unix timestamps in seconds.
+
+/// The number of seconds in a day.
+pub const DAY: i64 = 60 * 60 * 24;
@@ -1,9 +1,10 @@
 use proc_macro2::Span;
 use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Ident};
 
 pub(crate) fn expand_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    let name = input.ident.to_string();
 
     let fn_body = match input.data {
         Data::Struct(ref data) => match data.fields {
@@ -11,11 +12,11 @@ pub(crate) fn expand_macro(input: proc_macro::TokenStream) -> proc_macro::TokenS
                 let idents: Vec<Ident> = fields
                     .named
                     .iter()
-                    .map(|f| f.ident.clone().unwrap())
+                    .filter_map(|f| f.ident.clone())
                     .collect();
 
                 let recurse = fields.named.iter().map(|f| {
-                    let ident = f.ident.clone().unwrap();
+                    let ident = &f.ident;
                     let ty = f.ty.clone();
 
                     quote! {
@@ -63,7 +64,14 @@ pub(crate) fn expand_macro(input: proc_macro::TokenStream) -> proc_macro::TokenS
 
             for (i, variant) in data.variants.iter().enumerate() {
                 if i > u8::MAX as usize {
-                    panic!("The maxium number of enum variants is {}", u8::MAX);
+                    let err = Error::new_spanned(
+                        variant,
+                        format!(
+                            "`Decode` supports at most {} enum variants",
+                            u8::MAX as usize + 1
+                        ),
+                    );
+                    return proc_macro::TokenStream::from(err.to_compile_error());
                 }
                 let i = i as u8;
 
@@ -74,11 +82,11 @@ pub(crate) fn expand_macro(input: proc_macro::TokenStream) -> proc_macro::TokenS
                         let idents: Vec<Ident> = fields
                             .named
                             .iter()
-                            .map(|f| f.ident.clone().unwrap())
+                            .filter_map(|f| f.ident.clone())
                             .collect();
 
                         let recurse = fields.named.iter().map(|f| {
-                            let ident = f.ident.clone().unwrap();
+                            let ident = &f.ident;
                             let ty = f.ty.clone();
 
                             quote! {
@@ -138,12 +146,16 @@ pub(crate) fn expand_macro(input: proc_macro::TokenStream) -> proc_macro::TokenS
 
                 Ok(match enum_variant {
                     #(#match_arms)*
-                    _ => unreachable!(),
+                    variant => return Err(robbot::remote::Error::InvalidVariant {
+                        ty: #name,
+                        variant,
+                    }),
                 })
             }
         }
-        Data::Union(_data) => {
-            unimplemented!();
+        Data::Union(_) => {
+            let err = Error::new_spanned(&input.ident, "`Decode` cannot be derived for unions");
+            return proc_macro::TokenStream::from(err.to_compile_error());
         }
     };
 
@@ -1,6 +1,6 @@
 use proc_macro2::Span;
 use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, Index};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Ident, Index};
 
 pub(crate) fn expand_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -11,7 +11,7 @@ pub(crate) fn expand_macro(input: proc_macro::TokenStream) -> proc_macro::TokenS
         Data::Struct(ref data) => match data.fields {
             Fields::Named(ref fields) => {
                 for f in fields.named.iter() {
-                    let ident = f.ident.clone().unwrap();
+                    let ident = &f.ident;
 
                     let expanded = quote! {
                         self.#ident.encode(encoder)?;
@@ -38,7 +38,14 @@ pub(crate) fn expand_macro(input: proc_macro::TokenStream) -> proc_macro::TokenS
 
             for (i, variant) in data.variants.iter().enumerate() {
                 if i > u8::MAX as usize {
-                    panic!("The maxium number of enum variants is {}", u8::MAX);
+                    let err = Error::new_spanned(
+                        variant,
+                        format!(
+                            "`Encode` supports at most {} enum variants",
+                            u8::MAX as usize + 1
+                        ),
+                    );
+                    return proc_macro::TokenStream::from(err.to_compile_error());
                 }
                 let i = i as u8;
 
@@ -49,7 +56,7 @@ pub(crate) fn expand_macro(input: proc_macro::TokenStream) -> proc_macro::TokenS
                         let idents: Vec<Ident> = fields
                             .named
                             .iter()
-                            .map(|f| f.ident.clone().unwrap())
+                            .filter_map(|f| f.ident.clone())
                             .collect();
 
                         let expanded = quote! {
@@ -102,7 +109,10 @@ pub(crate) fn expand_macro(input: proc_macro::TokenStream) -> proc_macro::TokenS
 
             encode_calls.push(expanded);
         }
-        Data::Union(_) => unimplemented!(),
+        Data::Union(_) => {
+            let err = Error::new_spanned(&input.ident, "`Encode` cannot be derived for unions");
+            return proc_macro::TokenStream::from(err.to_compile_error());
+        }
     }
 
     let ident = input.ident;
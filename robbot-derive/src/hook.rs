@@ -1,37 +1,70 @@
 use proc_macro2::Span;
 use quote::quote;
-use syn::{parse_macro_input, FnArg, Ident, ItemFn};
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Error, Expr, ExprLit, FnArg, Ident, ItemFn, Lit, Result};
+
+use crate::kvmap::KeyValueMap;
+use crate::task::{expand_timeout, parse_timeout};
+
+use std::time::Duration;
 
 pub fn expand_macro(
-    _attr: proc_macro::TokenStream,
+    attr: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
+    let args = parse_macro_input!(attr as Hook);
     let input = parse_macro_input!(input as ItemFn);
 
     let ident = input.clone().sig.ident;
     let ident_str = ident.to_string();
 
     if input.sig.inputs.len() != 1 {
-        panic!("Unrecognised hook event kind");
+        let err = Error::new_spanned(
+            &input.sig,
+            "expected a hook taking a single context argument, e.g. `ctx: MessageContext`",
+        );
+        return proc_macro::TokenStream::from(err.to_compile_error());
     }
 
     let context = match &input.sig.inputs[0] {
-        FnArg::Receiver(_) => panic!("Unrecognised hook event kind"),
+        FnArg::Receiver(receiver) => {
+            let err = Error::new_spanned(
+                receiver,
+                "expected a context argument, hooks cannot take `self`",
+            );
+            return proc_macro::TokenStream::from(err.to_compile_error());
+        }
         FnArg::Typed(pat_type) => (*pat_type.ty).clone(),
     };
 
+    let timeout = expand_timeout(args.timeout);
+
     let callback_ident = Ident::new(&format!("__hookcb_{}", ident), Span::call_site());
     let mut callback_fn = input;
     callback_fn.sig.ident = callback_ident.clone();
 
-    let expanded = quote! {
-        #callback_fn
+    let run = match args.batch {
+        true => quote! {
+            use robbot_core::hook::BatchEventWrapper;
 
-        pub async fn #ident(state: &robbot_core::state::State) -> ::robbot::Result {
-            use ::robbot::executor::Executor;
-            use ::robbot::hook::{HookEvent, HookEventWrapper};
+            let hook = robbot_core::hook::Hook {
+                name: #ident_str.to_string(),
+                on_event: <#context as BatchEventWrapper>::HookEvent::kind(),
+            };
 
-            let executor = robbot_core::executor::Executor::from_fn(#callback_ident);
+            let rx = state.hooks().add_hook(hook).await;
+
+            robbot_core::hook::BatchHookExecutor::new(
+                #ident_str,
+                rx,
+                executor,
+                state.config.batching,
+            )
+            .timeout(#timeout)
+            .run();
+        },
+        false => quote! {
+            use ::robbot::hook::HookEventWrapper;
 
             let hook = robbot_core::hook::Hook {
                 name: #ident_str.to_string(),
@@ -40,7 +73,22 @@ pub fn expand_macro(
 
             let rx = state.hooks().add_hook(hook).await;
 
-            robbot_core::hook::HookExecutor::new(rx, executor).run();
+            robbot_core::hook::HookExecutor::new(#ident_str, rx, executor)
+                .timeout(#timeout)
+                .run();
+        },
+    };
+
+    let expanded = quote! {
+        #callback_fn
+
+        pub async fn #ident(state: &robbot_core::state::State) -> ::robbot::Result {
+            use ::robbot::executor::Executor;
+            use ::robbot::hook::HookEvent;
+
+            let executor = robbot_core::executor::Executor::from_fn(#callback_ident);
+
+            #run
 
             Ok(())
         }
@@ -48,3 +96,35 @@ pub fn expand_macro(
 
     proc_macro::TokenStream::from(expanded)
 }
+
+#[derive(Debug, Default)]
+struct Hook {
+    timeout: Option<Duration>,
+    /// Whether the hook handles batches of events.
+    batch: bool,
+}
+
+impl Parse for Hook {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let args = KeyValueMap::parse(input)?;
+
+        let batch = match args.get("batch") {
+            Some(Expr::Lit(ExprLit {
+                lit: Lit::Bool(lit),
+                ..
+            })) => lit.value,
+            Some(expr) => {
+                return Err(Error::new_spanned(
+                    expr,
+                    "expected a boolean, e.g. `batch = true`",
+                ))
+            }
+            None => false,
+        };
+
+        Ok(Self {
+            timeout: parse_timeout(&args)?,
+            batch,
+        })
+    }
+}
@@ -2,9 +2,19 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 
 use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
 use quote::{quote, ToTokens};
+use syn::Expr;
 
 static ID: AtomicUsize = AtomicUsize::new(0);
 
+/// Returns the identifier if `expr` is a path consisting of a single
+/// identifier, e.g. the key of a `key = value` pair.
+pub fn path_ident(expr: &Expr) -> Option<&Ident> {
+    match expr {
+        Expr::Path(path) => path.path.get_ident(),
+        _ => None,
+    }
+}
+
 /// An internal (mangled) identifier.
 #[derive(Clone, Debug)]
 pub struct InternalIdent(Ident);
@@ -2,7 +2,9 @@ use std::collections::HashMap;
 
 use syn::parse::{Parse, ParseStream, Result};
 use syn::punctuated::Punctuated;
-use syn::{Expr, Token};
+use syn::{Error, Expr, Token};
+
+use crate::ident::path_ident;
 
 /// A key-value map.
 ///
@@ -14,7 +16,7 @@ pub struct KeyValueMap {
 
 impl KeyValueMap {
     pub fn get(&self, key: &str) -> Option<&Expr> {
-        self.map.get(key).map(|expr| expr.as_ref()).flatten()
+        self.map.get(key).and_then(|expr| expr.as_ref())
     }
 }
 
@@ -27,20 +29,33 @@ impl Parse for KeyValueMap {
         for arg in args {
             match arg {
                 Expr::Assign(expr) => {
-                    let ident = match &*expr.left {
-                        Expr::Path(expr) => expr.path.segments.first().unwrap().ident.clone(),
-                        _ => panic!("Invalid expr: {:?}, expected Expr::Path", expr.left),
+                    let ident = match path_ident(&expr.left) {
+                        Some(ident) => ident.clone(),
+                        None => {
+                            return Err(Error::new_spanned(
+                                &expr.left,
+                                "expected an identifier as the key of a `key = value` pair",
+                            ))
+                        }
                     };
 
                     let key = ident.to_string();
 
                     if map.contains_key(&key) {
-                        panic!("Key {} already exists", key);
+                        return Err(Error::new_spanned(
+                            ident,
+                            format!("duplicate key `{}`", key),
+                        ));
                     }
 
                     map.insert(key, Some(*expr.right));
                 }
-                _ => panic!("Invalid expr: {:?}, expected Expr::Assign", arg),
+                arg => {
+                    return Err(Error::new_spanned(
+                        arg,
+                        "expected `key = value` pair, found a bare expression",
+                    ))
+                }
             }
         }
 
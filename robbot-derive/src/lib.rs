@@ -6,19 +6,22 @@ mod kvmap;
 mod module;
 mod store;
 mod storedata;
+mod storeenum;
 mod task;
 
 use proc_macro::TokenStream;
-use proc_macro2::Span;
+use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::quote;
 use std::collections::HashMap;
 use syn::{
     parse::{Parse, ParseStream, Result},
     parse_macro_input,
     punctuated::Punctuated,
-    Expr, Ident, ItemFn, Token,
+    Error, Expr, Ident, ItemFn, Token,
 };
 
+use crate::ident::path_ident;
+
 #[proc_macro_attribute]
 pub fn command(attr: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(attr as Args);
@@ -33,14 +36,16 @@ pub fn command(attr: TokenStream, input: TokenStream) -> TokenStream {
 
     let command_ident = exec_fn.sig.ident.clone();
 
-    let recurse = args.args.iter().map(|(ident, expr)| match expr {
-        Some(expr) => {
-            let ident = Ident::new(&format!("set_{}", ident), Span::call_site());
+    let recurse = args
+        .args
+        .iter()
+        .map(|(ident, expr)| expand_arg(ident, expr.as_ref()))
+        .collect::<Result<Vec<_>>>();
 
-            quote! { cmd.#ident(#expr); }
-        }
-        _ => unimplemented!(),
-    });
+    let recurse = match recurse {
+        Ok(recurse) => recurse,
+        Err(err) => return TokenStream::from(err.to_compile_error()),
+    };
 
     let expanded = quote! {
         #exec_fn
@@ -62,6 +67,63 @@ pub fn command(attr: TokenStream, input: TokenStream) -> TokenStream {
     TokenStream::from(expanded)
 }
 
+/// Expands a single argument of `#[command(...)]` into a call to the setter
+/// of the command.
+fn expand_arg(ident: &Ident, expr: Option<&Expr>) -> Result<TokenStream2> {
+    match expr {
+        // `bot_permissions = [MANAGE_ROLES]` refers to the `Permissions` constants.
+        Some(Expr::Array(array)) if ident == "bot_permissions" => {
+            let perms = array.elems.iter().map(|elem| match elem {
+                Expr::Path(path) if path.path.get_ident().is_some() => {
+                    quote! { ::robbot::model::permissions::Permissions::#path }
+                }
+                elem => quote! { #elem },
+            });
+
+            Ok(quote! { cmd.set_bot_permissions([#(#perms),*]); })
+        }
+        // `arg_choices(level = ["error", "warn"])` sets the choices of each argument.
+        Some(Expr::Call(call)) if ident == "arg_choices" => {
+            let choices =
+                call.args
+                    .iter()
+                    .map(|arg| match arg {
+                        Expr::Assign(assign) => {
+                            let name =
+                                match path_ident(&assign.left) {
+                                    Some(ident) => ident.to_string(),
+                                    None => return Err(Error::new_spanned(
+                                        &assign.left,
+                                        "expected the name of an argument in `arg_choices(...)`",
+                                    )),
+                                };
+                            let values = &assign.right;
+
+                            Ok(quote! { cmd.set_arg_choices(#name, #values); })
+                        }
+                        arg => Err(Error::new_spanned(
+                            arg,
+                            "expected `argument = [\"choice\", ...]` in `arg_choices(...)`",
+                        )),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+            Ok(quote! { #(#choices)* })
+        }
+        Some(expr) => {
+            let ident = Ident::new(&format!("set_{}", ident), ident.span());
+
+            Ok(quote! { cmd.#ident(#expr); })
+        }
+        // Flags without a value, e.g. `ack_only`.
+        None => {
+            let ident = Ident::new(&format!("set_{}", ident), ident.span());
+
+            Ok(quote! { cmd.#ident(true); })
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 struct Args {
     args: HashMap<Ident, Option<Expr>>,
@@ -74,22 +136,52 @@ impl Parse for Args {
         let mut map = HashMap::new();
 
         for arg in args {
-            match arg {
-                Expr::Assign(expr) => {
-                    let ident = match *expr.left {
-                        Expr::Path(expr) => expr.path.segments.first().unwrap().ident.clone(),
-                        _ => panic!("Invalid expr: {:?}", expr.left),
-                    };
-
-                    map.insert(ident, Some(*expr.right));
+            let (ident, value) = match arg {
+                Expr::Assign(expr) => match path_ident(&expr.left) {
+                    Some(ident) => (ident.clone(), Some(*expr.right)),
+                    None => {
+                        return Err(Error::new_spanned(
+                            &expr.left,
+                            "expected an identifier as the key of a `key = value` pair in #[command(...)]",
+                        ))
+                    }
+                },
+                // Flags without a value, e.g. `guild_only`.
+                Expr::Path(path) => match path.path.get_ident() {
+                    Some(ident) => (ident.clone(), None),
+                    None => {
+                        return Err(Error::new_spanned(
+                            path,
+                            "expected an identifier as a flag in #[command(...)]",
+                        ))
+                    }
+                },
+                // Only `arg_choices(...)` takes arguments.
+                Expr::Call(call) => match path_ident(&call.func).cloned() {
+                    Some(ident) if ident == "arg_choices" => (ident, Some(Expr::Call(call))),
+                    _ => {
+                        return Err(Error::new_spanned(
+                            &call.func,
+                            "expected `arg_choices(...)`, other arguments of #[command(...)] are `key = value` pairs or flags",
+                        ))
+                    }
+                },
+                arg => {
+                    return Err(Error::new_spanned(
+                        arg,
+                        "expected `key = value` pair or flag in #[command(...)], found a bare expression",
+                    ))
                 }
-                Expr::Path(expr) => {
-                    let ident = expr.path.segments.first().unwrap().ident.clone();
+            };
 
-                    map.insert(ident, None);
-                }
-                _ => panic!("Invalid expr: {:?}", arg),
+            if map.contains_key(&ident) {
+                return Err(Error::new_spanned(
+                    &ident,
+                    format!("duplicate argument `{}` in #[command(...)]", ident),
+                ));
             }
+
+            map.insert(ident, value);
         }
 
         Ok(Self { args: map })
@@ -106,11 +198,16 @@ pub fn hook(attr: TokenStream, input: TokenStream) -> TokenStream {
     hook::expand_macro(attr, input)
 }
 
-#[proc_macro_derive(StoreData)]
+#[proc_macro_derive(StoreData, attributes(store))]
 pub fn storedata(input: TokenStream) -> TokenStream {
     storedata::expand_macro(input)
 }
 
+#[proc_macro_derive(StoreEnum, attributes(store))]
+pub fn storeenum(input: TokenStream) -> TokenStream {
+    storeenum::expand_macro(input)
+}
+
 #[proc_macro_derive(Encode)]
 pub fn encode(input: TokenStream) -> TokenStream {
     encode::expand_macro(input)
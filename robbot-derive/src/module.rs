@@ -1,9 +1,8 @@
-use proc_macro2::Literal;
-use proc_macro2::TokenStream;
+use proc_macro2::{Literal, Span, TokenStream};
 use quote::quote;
 use quote::{ToTokens, TokenStreamExt};
 use syn::parse::{Parse, ParseStream, Result};
-use syn::{braced, bracketed, parse_macro_input, Expr, ExprPath, Ident, Path, Token, Type};
+use syn::{braced, bracketed, parse_macro_input, Error, Expr, ExprPath, Ident, Path, Token, Type};
 
 pub fn expand_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let module = parse_macro_input!(input as Module);
@@ -19,6 +18,9 @@ struct Module {
     store: Option<StoreDataTypes>,
     tasks: Option<Tasks>,
     hooks: Option<Hooks>,
+    permissions: Option<Expr>,
+    on_ready: Option<ExprPath>,
+    on_shutdown: Option<ExprPath>,
 }
 
 impl Parse for Module {
@@ -28,8 +30,20 @@ impl Parse for Module {
         let mut store: Option<StoreDataTypes> = None;
         let mut tasks: Option<Tasks> = None;
         let mut hooks: Option<Hooks> = None;
-
-        for _ in ["name", "cmds", "store", "tasks", "hooks"] {
+        let mut permissions: Option<Expr> = None;
+        let mut on_ready: Option<ExprPath> = None;
+        let mut on_shutdown: Option<ExprPath> = None;
+
+        for _ in [
+            "name",
+            "cmds",
+            "store",
+            "tasks",
+            "hooks",
+            "permissions",
+            "on_ready",
+            "on_shutdown",
+        ] {
             if input.is_empty() {
                 break;
             }
@@ -54,14 +68,36 @@ impl Parse for Module {
                 }
                 "tasks" => tasks = Some(input.parse::<KeyValuePair<Ident, Tasks>>()?.into_value()),
                 "hooks" => hooks = Some(input.parse::<KeyValuePair<Ident, Hooks>>()?.into_value()),
-                _ => panic!("Invalid key: {}", ident),
+                "permissions" => {
+                    permissions = Some(input.parse::<KeyValuePair<Ident, Expr>>()?.into_value())
+                }
+                "on_ready" => {
+                    on_ready = Some(input.parse::<KeyValuePair<Ident, ExprPath>>()?.into_value())
+                }
+                "on_shutdown" => {
+                    on_shutdown = Some(input.parse::<KeyValuePair<Ident, ExprPath>>()?.into_value())
+                }
+                _ => {
+                    return Err(Error::new_spanned(
+                        &ident,
+                        format!(
+                            "unknown key `{}` in module!, expected one of `name`, `cmds`, `store`, `tasks`, `hooks`, `permissions`, `on_ready` or `on_shutdown`",
+                            ident
+                        ),
+                    ))
+                }
             }
         }
 
         // A name always needs to be given.
         let name = match name {
             Some(name) => name,
-            None => panic!("No name value given for module"),
+            None => {
+                return Err(Error::new(
+                    Span::call_site(),
+                    "missing `name` in module!, e.g. `name: \"example\"`",
+                ))
+            }
         };
 
         Ok(Self {
@@ -70,6 +106,9 @@ impl Parse for Module {
             store,
             tasks,
             hooks,
+            permissions,
+            on_ready,
+            on_shutdown,
         })
     }
 }
@@ -82,21 +121,45 @@ impl ToTokens for Module {
             store,
             tasks,
             hooks,
+            permissions,
+            on_ready,
+            on_shutdown,
         } = self;
 
+        let permissions = match permissions {
+            Some(expr) => quote! { ::std::iter::IntoIterator::into_iter(#expr).copied().collect() },
+            None => quote! { ::std::vec::Vec::new() },
+        };
+
+        let on_ready = match on_ready {
+            Some(path) => quote! { Some(::robbot::executor::Executor::from_fn(#path)) },
+            None => quote! { None },
+        };
+
+        let on_shutdown = match on_shutdown {
+            Some(path) => quote! { Some(::robbot::executor::Executor::from_fn(#path)) },
+            None => quote! { None },
+        };
+
         let output = quote! {
             pub async fn init(state: &robbot_core::state::State) -> robbot::Result {
                 let module = robbot_core::module::Module {
                     name: #name.to_string(),
                     commands: std::collections::HashSet::new(),
+                    permissions: #permissions,
+                    on_ready: #on_ready,
+                    on_shutdown: #on_shutdown,
                 };
 
                 let id = state.modules().add_module(module)?;
 
-                for cmd in #commands {
-                    let options = robbot_core::command::AddOptions::new().module_id(id);
+                let options = robbot_core::command::AddOptions::new().module_id(id);
 
-                    state.commands().add_commands([cmd], options)?;
+                // A conflicting command fails the whole module, other modules
+                // keep loading.
+                if let Err(err) = state.commands().add_commands(#commands, options) {
+                    state.modules().fail_module(&#name.to_string(), &err);
+                    return Err(err.into());
                 }
 
                 #store
@@ -181,7 +244,7 @@ impl Parse for CommandMap {
                 let mut cmds = Vec::new();
 
                 while !content.is_empty() {
-                    let path = content.parse::<ExprPath>().unwrap();
+                    let path = content.parse::<ExprPath>()?;
                     content.parse::<Token![,]>()?;
                     cmds.push(path);
                 }
@@ -249,7 +312,7 @@ impl ToTokens for StoreDataTypes {
         let token = match types.len() {
             0 => quote! {{}},
             _ => quote! {
-                let res = ::tokio::try_join! {
+                let res = ::robbot_core::__private::tokio::try_join! {
                     #(
                         ::robbot::store::create!(state.store(), #types),
                     )*
@@ -335,7 +398,7 @@ impl ToTokens for Hooks {
         let token = match hooks.len() {
             0 => quote! {{}},
             _ => quote! {
-                let res = ::tokio::try_join! {
+                let res = ::robbot_core::__private::tokio::try_join! {
                     #(
                         #hooks(&state),
                     )*
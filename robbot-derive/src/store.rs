@@ -1,17 +1,24 @@
 use proc_macro::TokenStream;
 use quote::{quote, ToTokens, TokenStreamExt};
 use syn::parse::{Parse, ParseStream, Result};
-use syn::{braced, parse_macro_input, Expr, Path, Token, Type};
+use syn::{braced, parse_macro_input, Error, Expr, Ident, Path, Token, Type};
 
 pub fn create(input: TokenStream) -> TokenStream {
     let QueryBuilder {
         store,
         datatype,
         filter,
+        arrow,
     } = parse_macro_input!(input as QueryBuilder);
 
     let expanded = match filter {
-        Some(_) => panic!("Use of create! with filtered query is unsupported"),
+        Some(_) => {
+            let err = Error::new_spanned(
+                arrow,
+                "`create!` does not take a filter, expected `create!(store, Type)`",
+            );
+            return TokenStream::from(err.to_compile_error());
+        }
         None => quote! {
             {
                 use ::robbot::store::Store;
@@ -31,6 +38,7 @@ pub fn delete(input: TokenStream) -> TokenStream {
         store,
         datatype,
         filter,
+        ..
     } = parse_macro_input!(input as QueryBuilder);
 
     let expanded = match filter {
@@ -43,7 +51,13 @@ pub fn delete(input: TokenStream) -> TokenStream {
                 #store.delete(query)
             }
         },
-        None => panic!("Use of delete! without a filtered query is currently not supported"),
+        None => {
+            let err = Error::new_spanned(
+                datatype,
+                "`delete!` requires a filter, expected `delete!(store, Type => { field == value })`",
+            );
+            return TokenStream::from(err.to_compile_error());
+        }
     };
 
     TokenStream::from(expanded)
@@ -60,6 +74,7 @@ pub fn get_one(input: TokenStream) -> TokenStream {
         store,
         datatype,
         filter,
+        ..
     } = parse_macro_input!(input as QueryBuilder);
 
     let expanded = match filter {
@@ -73,7 +88,13 @@ pub fn get_one(input: TokenStream) -> TokenStream {
                 #store.get_one(descriptor, query)
             }
         },
-        None => panic!("Use of get_one! without a filtered query is currently not supported"),
+        None => {
+            let err = Error::new_spanned(
+                datatype,
+                "`get_one!` requires a filter, expected `get_one!(store, Type => { field == value })`",
+            );
+            return TokenStream::from(err.to_compile_error());
+        }
     };
 
     TokenStream::from(expanded)
@@ -112,6 +133,8 @@ struct QueryBuilder {
     store: Expr,
     datatype: Type,
     filter: Option<Vec<QueryFilter>>,
+    /// The `=>` preceding the filter.
+    arrow: Option<Token![=>]>,
 }
 
 impl Parse for QueryBuilder {
@@ -121,14 +144,14 @@ impl Parse for QueryBuilder {
         let datatype = input.parse()?;
 
         let mut filter = None;
-        if input.parse::<Token![=>]>().is_ok() {
+        let arrow = input.parse::<Option<Token![=>]>>()?;
+        if arrow.is_some() {
             let content;
             braced!(content in input);
 
-            filter = Some(Vec::new());
+            let mut filters = Vec::new();
             loop {
-                let val = content.parse()?;
-                filter.as_mut().unwrap().push(val);
+                filters.push(content.parse()?);
 
                 if content.peek(Token![,]) {
                     content.parse::<Token![,]>()?;
@@ -138,12 +161,15 @@ impl Parse for QueryBuilder {
                     break;
                 }
             }
+
+            filter = Some(filters);
         }
 
         Ok(Self {
             store,
             datatype,
             filter,
+            arrow,
         })
     }
 }
@@ -183,29 +209,82 @@ impl ToTokens for QueryBuilder {
 #[derive(Clone, Debug)]
 struct QueryFilter {
     field: Path,
+    /// The fields inside the flattened field `field`, e.g. `start` for
+    /// `schedule.start == value`.
+    nested: Vec<Ident>,
+    op: FilterOp,
     value: Expr,
 }
 
+/// The comparison of a [`QueryFilter`].
+#[derive(Copy, Clone, Debug)]
+enum FilterOp {
+    /// `field == value`
+    Eq,
+    /// `field in values`
+    In,
+}
+
 impl Parse for QueryFilter {
     fn parse(input: ParseStream) -> Result<Self> {
-        let field = input.parse().unwrap();
+        let field = input.parse()?;
 
-        // Currently only equality is supported.
-        input.parse::<Token![==]>()?;
+        let mut nested = Vec::new();
+        while input.peek(Token![.]) {
+            input.parse::<Token![.]>()?;
+            nested.push(input.parse()?);
+        }
+
+        let op = if input.peek(Token![in]) {
+            input.parse::<Token![in]>()?;
+            FilterOp::In
+        } else {
+            input.parse::<Token![==]>()?;
+            FilterOp::Eq
+        };
 
         let value = input.parse()?;
 
-        Ok(Self { field, value })
+        Ok(Self {
+            field,
+            nested,
+            op,
+            value,
+        })
     }
 }
 
 impl ToTokens for QueryFilter {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        let field = self.field.clone();
         let value = self.value.clone();
 
-        tokens.append_all(quote! {
+        let mut field = match self.nested.last() {
+            Some(ident) => Path::from(ident.clone()),
+            None => self.field.clone(),
+        };
+
+        if let (FilterOp::In, Some(segment)) = (&self.op, field.segments.last_mut()) {
+            segment.ident = Ident::new(&format!("{}_in", segment.ident), segment.ident.span());
+        }
+
+        let mut filter = quote! {
             #field(#value)
-        });
+        };
+
+        // Wrap the filter in the accessors of the flattened fields, from the
+        // innermost to the outermost: `schedule.start == v` becomes
+        // `schedule(|query| query.start(v))`.
+        let outer = std::iter::once(self.field.clone())
+            .chain(self.nested.iter().cloned().map(Path::from))
+            .rev()
+            .skip(1);
+
+        for field in outer {
+            filter = quote! {
+                #field(|query| query.#filter)
+            };
+        }
+
+        tokens.append_all(filter);
     }
 }
@@ -1,35 +1,245 @@
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, Type};
+use syn::{
+    parse_macro_input, Data, DeriveInput, Field, Fields, Ident, Lit, Meta, NestedMeta, Path, Type,
+};
+
+/// The representation of a field marked `#[store(as = "..")]`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Repr {
+    U8,
+    String,
+}
 
-pub(crate) fn expand_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let input = parse_macro_input!(input as DeriveInput);
+impl Repr {
+    /// Returns the type the field is stored as.
+    fn ty(self) -> TokenStream {
+        match self {
+            Self::U8 => quote! { u8 },
+            Self::String => quote! { ::std::string::String },
+        }
+    }
 
-    let mut field_types = Vec::new();
-    let mut field_idents = Vec::new();
+    /// Returns the representation of `value`, a reference to the field.
+    fn serialize(self, ty: &Type, value: TokenStream) -> TokenStream {
+        match self {
+            Self::U8 => quote! {
+                <#ty as robbot::store::StoreEnum>::to_repr(#value)
+            },
+            Self::String => quote! {
+                ::std::string::String::from(<#ty as robbot::store::StoreEnum>::to_name(#value))
+            },
+        }
+    }
 
-    match input.data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                for f in fields.named.iter() {
-                    field_types.push(f.ty.clone());
-                    field_idents.push(f.ident.as_ref().unwrap().clone());
+    /// Returns the value of type `ty` represented by `repr`, or `None`.
+    fn deserialize(self, ty: &Type, repr: TokenStream) -> TokenStream {
+        match self {
+            Self::U8 => quote! {
+                <#ty as robbot::store::StoreEnum>::from_repr(#repr)
+            },
+            Self::String => quote! {
+                <#ty as robbot::store::StoreEnum>::from_name(&#repr)
+            },
+        }
+    }
+}
+
+/// A field of a struct deriving `StoreData`.
+struct StoreField {
+    ident: Ident,
+    ty: Type,
+    /// The field is marked `#[store(flatten)]` and its type derives
+    /// `StoreData`. The fields of the type are stored prefixed with the name
+    /// of the field.
+    flatten: Option<Flattened>,
+    /// The field is marked `#[store(version)]`. It must be `version: u64`
+    /// and is incremented on every update.
+    version: bool,
+    /// The field is marked `#[store(as = "..")]` and its type implements
+    /// `StoreEnum`. The field is stored using the given representation.
+    repr: Option<Repr>,
+}
+
+/// The types generated next to the type of a flattened field by its
+/// `StoreData` derive.
+struct Flattened {
+    /// The `DataQuery`, e.g. `ScheduleQuery` for a field of type `Schedule`.
+    query: Path,
+    /// The `DataDescriptor`, e.g. `ScheduleDescriptor`.
+    descriptor: Path,
+}
+
+impl Flattened {
+    fn new(ty: &Type) -> syn::Result<Self> {
+        Ok(Self {
+            query: companion(ty, "Query")?,
+            descriptor: companion(ty, "Descriptor")?,
+        })
+    }
+}
+
+/// Returns the path of the type generated next to `ty` by its `StoreData`
+/// derive, e.g. `ScheduleQuery` for `Schedule`.
+fn companion(ty: &Type, suffix: &str) -> syn::Result<Path> {
+    const NOT_A_STRUCT: &str = "flattened fields must be a struct deriving `StoreData`";
+
+    let mut path = match ty {
+        Type::Path(ty) if ty.qself.is_none() => ty.path.clone(),
+        ty => return Err(syn::Error::new_spanned(ty, NOT_A_STRUCT)),
+    };
+
+    let segment = match path.segments.last_mut() {
+        Some(segment) => segment,
+        None => return Err(syn::Error::new_spanned(ty, NOT_A_STRUCT)),
+    };
+
+    if !segment.arguments.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &segment.arguments,
+            "flattened fields cannot have generic types",
+        ));
+    }
+
+    segment.ident = Ident::new(
+        &format!("{}{}", segment.ident, suffix),
+        segment.ident.span(),
+    );
+    Ok(path)
+}
+
+impl StoreField {
+    fn parse(field: &Field) -> syn::Result<Self> {
+        let mut flatten = false;
+        let mut version = false;
+        let mut repr = None;
+
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path.is_ident("store"))
+        {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        "expected `store(flatten)`, `store(version)` or `store(as = \"..\")`",
+                    ))
+                }
+            };
+
+            for nested in list.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("flatten") => {
+                        flatten = true;
+                    }
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("version") => {
+                        version = true;
+                    }
+                    NestedMeta::Meta(Meta::NameValue(meta)) if meta.path.is_ident("as") => {
+                        repr = match &meta.lit {
+                            Lit::Str(lit) if lit.value() == "u8" => Some(Repr::U8),
+                            Lit::Str(lit) if lit.value() == "string" => Some(Repr::String),
+                            lit => {
+                                return Err(syn::Error::new_spanned(
+                                    lit,
+                                    "expected `as = \"u8\"` or `as = \"string\"`",
+                                ))
+                            }
+                        };
+                    }
+                    nested => {
+                        return Err(syn::Error::new_spanned(
+                            nested,
+                            "unknown store attribute, expected `flatten`, `version` or `as`",
+                        ))
+                    }
                 }
             }
-            _ => unimplemented!(),
+        }
+
+        let ident = match &field.ident {
+            Some(ident) => ident.clone(),
+            None => {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "`StoreData` can only be derived for structs with named fields",
+                ))
+            }
+        };
+
+        let field = Self {
+            ident,
+            ty: field.ty.clone(),
+            flatten: match flatten {
+                true => Some(Flattened::new(&field.ty)?),
+                false => None,
+            },
+            version,
+            repr,
+        };
+
+        if field.repr.is_some() && (field.flatten.is_some() || field.version) {
+            return Err(syn::Error::new_spanned(
+                &field.ident,
+                "`store(as)` cannot be combined with `store(flatten)` or `store(version)`",
+            ));
+        }
+
+        if field.version {
+            let is_u64 = matches!(&field.ty, Type::Path(ty) if ty.path.is_ident("u64"));
+
+            if field.ident != "version" || !is_u64 || field.flatten.is_some() {
+                return Err(syn::Error::new_spanned(
+                    &field.ident,
+                    "`store(version)` must be used on a field `version: u64`",
+                ));
+            }
+        }
+
+        Ok(field)
+    }
+}
+
+pub(crate) fn expand_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => fields.named.iter().map(StoreField::parse).collect(),
+            _ => Err(syn::Error::new_spanned(
+                &input.ident,
+                "`StoreData` can only be derived for structs with named fields",
+            )),
         },
-        _ => unimplemented!(),
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            "`StoreData` can only be derived for structs",
+        )),
+    };
+
+    let fields: Vec<StoreField> = match fields {
+        Ok(fields) => fields,
+        Err(err) => return proc_macro::TokenStream::from(err.to_compile_error()),
+    };
+
+    if let Some(field) = fields.iter().filter(|field| field.version).nth(1) {
+        let err = syn::Error::new_spanned(&field.ident, "duplicate `store(version)` field");
+        return proc_macro::TokenStream::from(err.to_compile_error());
     }
 
     let ident = input.ident;
 
-    let storedata = expand_storedata(&ident, &field_idents, &field_types);
-    let dataquery = expand_dataquery(&ident, &field_idents, &field_types);
-    let dataquery_self = expand_dataquery_self(&ident, &field_idents, &field_types);
-    let datadescriptor = expand_datadescriptor_self(&ident, &field_idents, &field_types);
+    let storedata = expand_storedata(&ident, &fields);
+    let storekeys = expand_storekeys(&ident, &fields);
+    let dataquery = expand_dataquery(&ident, &fields);
+    let dataquery_self = expand_dataquery_self(&ident, &fields);
+    let datadescriptor = expand_datadescriptor_self(&ident, &fields);
 
     let expanded = quote! {
         #storedata
+        #storekeys
         #dataquery
         #dataquery_self
         #datadescriptor
@@ -38,30 +248,161 @@ pub(crate) fn expand_macro(input: proc_macro::TokenStream) -> proc_macro::TokenS
     proc_macro::TokenStream::from(expanded)
 }
 
-fn expand_storedata(ident: &Ident, field_idents: &[Ident], field_types: &[Type]) -> TokenStream {
-    let trait_bounds = expand_type_trait_bounds(field_types);
+/// Expands a reference to the `static` `PrefixedKeys` of the flattened
+/// `field`.
+fn expand_prefixed_keys(field: &StoreField) -> TokenStream {
+    let ty = &field.ty;
+    let name = field.ident.to_string();
 
-    let resource_name = ident.to_string();
+    quote! {
+        {
+            static KEYS: robbot::store::PrefixedKeys = robbot::store::PrefixedKeys::new(
+                #name,
+                <#ty as robbot::store::StoreKeys>::keys,
+            );
+            &KEYS
+        }
+    }
+}
 
-    let impl_serialize = field_idents.iter().map(|ident| {
-        let name = ident.to_string();
+fn expand_storekeys(ident: &Ident, fields: &[StoreField]) -> TokenStream {
+    let keys = if fields.iter().any(|field| field.flatten.is_some()) {
+        let keys = fields.iter().map(|field| {
+            if field.flatten.is_some() {
+                let keys = expand_prefixed_keys(field);
+
+                quote! {
+                    keys.extend(#keys.keys());
+                }
+            } else {
+                let name = field.ident.to_string();
+
+                quote! {
+                    keys.push(#name);
+                }
+            }
+        });
 
         quote! {
-            serializer.serialize_field(#name, &self.#ident)?;
+            static KEYS: ::std::sync::OnceLock<::std::vec::Vec<&'static str>> =
+                ::std::sync::OnceLock::new();
+
+            KEYS.get_or_init(|| {
+                let mut keys = ::std::vec::Vec::new();
+                #(#keys)*
+                keys
+            })
+        }
+    } else {
+        let names = fields.iter().map(|field| field.ident.to_string());
+
+        quote! {
+            &[#(#names),*]
+        }
+    };
+
+    quote! {
+        impl robbot::store::StoreKeys for #ident {
+            fn keys() -> &'static [&'static str] {
+                #keys
+            }
+        }
+    }
+}
+
+/// Expands the serialization of all `fields` of `self` into `serializer`.
+fn expand_serialize_fields(fields: &[StoreField]) -> TokenStream {
+    let serialize = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let ty = &field.ty;
+        let name = ident.to_string();
+
+        if field.flatten.is_some() {
+            let keys = expand_prefixed_keys(field);
+
+            quote! {
+                <#ty as robbot::store::StoreData<T>>::serialize(
+                    &self.#ident,
+                    &mut robbot::store::Prefixed::new(serializer, #keys),
+                )?;
+            }
+        } else if let Some(repr) = field.repr {
+            let value = repr.serialize(ty, quote! { &self.#ident });
+
+            quote! {
+                serializer.serialize_field(#name, &#value)?;
+            }
+        } else {
+            quote! {
+                serializer.serialize_field(#name, &self.#ident)?;
+            }
         }
     });
 
-    let impl_deserialize = field_idents.iter().map(|ident| {
+    quote! {
+        #(#serialize)*
+    }
+}
+
+fn expand_storedata(ident: &Ident, fields: &[StoreField]) -> TokenStream {
+    let trait_bounds = expand_type_trait_bounds(fields);
+
+    let resource_name = ident.to_string();
+
+    let impl_serialize = expand_serialize_fields(fields);
+
+    let impl_deserialize = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let ty = &field.ty;
         let name = ident.to_string();
 
-        quote! {
-            let #ident = deserializer.deserialize_field(#name)?;
+        if field.flatten.is_some() {
+            let keys = expand_prefixed_keys(field);
+
+            quote! {
+                let #ident = <#ty as robbot::store::StoreData<T>>::deserialize(
+                    &mut robbot::store::Prefixed::new(deserializer, #keys),
+                )?;
+            }
+        } else if let Some(repr) = field.repr {
+            let repr_ty = repr.ty();
+            let value = repr.deserialize(ty, quote! { __repr });
+
+            quote! {
+                let __repr: #repr_ty = deserializer.deserialize_field(#name)?;
+                let #ident = match #value {
+                    ::std::option::Option::Some(value) => value,
+                    ::std::option::Option::None => {
+                        let err = robbot::store::UnknownVariant::new::<#ty>(#name, __repr);
+                        return ::std::result::Result::Err(deserializer.unknown_variant(err));
+                    }
+                };
+            }
+        } else {
+            quote! {
+                let #ident = deserializer.deserialize_field(#name)?;
+            }
         }
     });
 
+    let field_idents = fields.iter().map(|field| &field.ident);
+
     let dataquery_ident = Ident::new(&format!("{}Query", ident), Span::call_site());
     let datadescriptor_ident = Ident::new(&format!("{}Descriptor", ident), Span::call_site());
 
+    let impl_version = match fields.iter().any(|field| field.version) {
+        true => quote! {
+            fn version(&self) -> ::std::option::Option<u64> {
+                ::std::option::Option::Some(self.version)
+            }
+
+            fn set_version(&mut self, version: u64) {
+                self.version = version;
+            }
+        },
+        false => quote! {},
+    };
+
     quote! {
         impl<T> robbot::store::StoreData<T> for #ident
         where
@@ -79,7 +420,7 @@ fn expand_storedata(ident: &Ident, field_idents: &[Ident], field_types: &[Type])
             where
                 S: robbot::store::Serializer<T>,
             {
-                #(#impl_serialize)*
+                #impl_serialize
                 ::std::result::Result::Ok(())
             }
 
@@ -97,45 +438,125 @@ fn expand_storedata(ident: &Ident, field_idents: &[Ident], field_types: &[Type])
             fn query() -> Self::DataQuery {
                 #dataquery_ident::default()
             }
+
+            #impl_version
         }
     }
 }
 
-fn expand_dataquery(ident: &Ident, field_idents: &[Ident], field_types: &[Type]) -> TokenStream {
-    let trait_bounds = expand_type_trait_bounds(field_types);
+fn expand_dataquery(ident: &Ident, fields: &[StoreField]) -> TokenStream {
+    let trait_bounds = expand_type_trait_bounds(fields);
 
     let dataquery_ident = Ident::new(&format!("{}Query", ident), Span::call_site());
 
-    let dataquery_fields = field_idents
-        .iter()
-        .zip(field_types.iter())
-        .map(|(ident, ty)| {
+    let dataquery_fields = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let ty = &field.ty;
+
+        if let Some(flattened) = &field.flatten {
+            let query = &flattened.query;
+
+            quote! {
+                #ident: Option<#query>,
+            }
+        } else {
+            let in_ident = Ident::new(&format!("{}_in", ident), Span::call_site());
+
             quote! {
                 #ident: Option<#ty>,
+                #in_ident: Option<Vec<#ty>>,
             }
-        });
+        }
+    });
+
+    let dataquery_fns = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let ty = &field.ty;
+
+        if let Some(flattened) = &field.flatten {
+            let query = &flattened.query;
+
+            quote! {
+                /// Matches the fields of the flattened field using the query
+                /// built by `f`.
+                pub fn #ident<F>(mut self, f: F) -> Self
+                where
+                    F: ::std::ops::FnOnce(#query) -> #query,
+                {
+                    let query = self.#ident.take().unwrap_or_default();
+                    self.#ident = ::std::option::Option::Some(f(query));
+                    self
+                }
+            }
+        } else {
+            let in_ident = Ident::new(&format!("{}_in", ident), Span::call_site());
 
-    let dataquery_fns = field_idents
-        .iter()
-        .zip(field_types.iter())
-        .map(|(ident, ty)| {
             quote! {
                 pub fn #ident(mut self, t: #ty) -> Self {
                     self.#ident = ::std::option::Option::Some(t);
                     self
                 }
+
+                /// Matches any of the given values.
+                pub fn #in_ident<I>(mut self, t: I) -> Self
+                where
+                    I: ::std::iter::IntoIterator<Item = #ty>,
+                {
+                    self.#in_ident = ::std::option::Option::Some(t.into_iter().collect());
+                    self
+                }
             }
-        });
+        }
+    });
 
-    let impl_serialize = field_idents.iter().map(|ident| {
+    let impl_serialize = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let ty = &field.ty;
         let name = ident.to_string();
 
-        quote! {
-            {
-                if let Some(val) = self.#ident.as_ref() {
-                    serializer.serialize_field(#name, val)?;
+        if field.flatten.is_some() {
+            let keys = expand_prefixed_keys(field);
+
+            quote! {
+                if let Some(query) = self.#ident.as_ref() {
+                    robbot::store::DataQuery::<#ty, T>::serialize(
+                        query,
+                        &mut robbot::store::Prefixed::new(serializer, #keys),
+                    )?;
                 }
             }
+        } else {
+            let in_ident = Ident::new(&format!("{}_in", ident), Span::call_site());
+
+            match field.repr {
+                Some(repr) => {
+                    let value = repr.serialize(ty, quote! { val });
+
+                    quote! {
+                        {
+                            if let Some(val) = self.#ident.as_ref() {
+                                serializer.serialize_field(#name, &#value)?;
+                            }
+
+                            if let Some(vals) = self.#in_ident.as_ref() {
+                                let vals: Vec<_> = vals.iter().map(|val| #value).collect();
+                                serializer.serialize_field_in(#name, &vals)?;
+                            }
+                        }
+                    }
+                }
+                None => quote! {
+                    {
+                        if let Some(val) = self.#ident.as_ref() {
+                            serializer.serialize_field(#name, val)?;
+                        }
+
+                        if let Some(vals) = self.#in_ident.as_ref() {
+                            serializer.serialize_field_in(#name, vals)?;
+                        }
+                    }
+                },
+            }
         }
     });
 
@@ -166,20 +587,10 @@ fn expand_dataquery(ident: &Ident, field_idents: &[Ident], field_types: &[Type])
     }
 }
 
-fn expand_dataquery_self(
-    ident: &Ident,
-    field_idents: &[Ident],
-    field_types: &[Type],
-) -> TokenStream {
-    let trait_bounds = expand_type_trait_bounds(field_types);
-
-    let impl_serialize = field_idents.iter().map(|ident| {
-        let name = ident.to_string();
+fn expand_dataquery_self(ident: &Ident, fields: &[StoreField]) -> TokenStream {
+    let trait_bounds = expand_type_trait_bounds(fields);
 
-        quote! {
-            serializer.serialize_field(#name, &self.#ident)?;
-        }
-    });
+    let impl_serialize = expand_serialize_fields(fields);
 
     quote! {
         impl<T> robbot::store::DataQuery<#ident, T> for #ident
@@ -191,7 +602,7 @@ fn expand_dataquery_self(
             where
                 S: robbot::store::Serializer<T>,
             {
-                #(#impl_serialize)*
+                #impl_serialize
 
                 ::std::result::Result::Ok(())
             }
@@ -200,38 +611,81 @@ fn expand_dataquery_self(
 }
 
 /// Expand the required trait bounds for all unique types.
-/// This includes the `Serialize<T>` and `Deserialize<T>` trait.
-fn expand_type_trait_bounds(types: &[Type]) -> TokenStream {
+/// This includes the `Serialize<T>` and `Deserialize<T>` trait, or the
+/// `StoreData<T>` trait and the traits of its companion types for
+/// flattened fields.
+fn expand_type_trait_bounds(fields: &[StoreField]) -> TokenStream {
     // Collect all unique types.
-    let mut traits = Vec::new();
-    for ty in types {
-        if !traits.contains(ty) {
-            traits.push(ty.clone());
+    let mut types: Vec<&Type> = Vec::new();
+    let mut flattened: Vec<(&Type, &Flattened)> = Vec::new();
+    let mut reprs: Vec<Repr> = Vec::new();
+    for field in fields {
+        if let Some(repr) = field.repr {
+            if !reprs.contains(&repr) {
+                reprs.push(repr);
+            }
+        } else if let Some(companions) = &field.flatten {
+            if !flattened.iter().any(|(ty, _)| **ty == field.ty) {
+                flattened.push((&field.ty, companions));
+            }
+        } else if !types.contains(&&field.ty) {
+            types.push(&field.ty);
         }
     }
 
+    let flattened = flattened.iter().map(|(ty, flattened)| {
+        let query = &flattened.query;
+        let descriptor = &flattened.descriptor;
+
+        quote! {
+            #ty: robbot::store::StoreData<T>,
+            #query: robbot::store::DataQuery<#ty, T>,
+            #descriptor: robbot::store::DataDescriptor<#ty, T>,
+        }
+    });
+
+    let reprs = reprs.into_iter().map(Repr::ty);
+
     quote! {
         #(
-            #traits: robbot::store::Serialize<T> + robbot::store::Deserialize<T>,
+            #types: robbot::store::Serialize<T> + robbot::store::Deserialize<T>,
+        )*
+        #(
+            #reprs: robbot::store::Serialize<T> + robbot::store::Deserialize<T>,
         )*
+        #(#flattened)*
     }
 }
 
-fn expand_datadescriptor_self(
-    ident: &Ident,
-    field_idents: &[Ident],
-    field_types: &[Type],
-) -> TokenStream {
-    let trait_bounds = expand_type_trait_bounds(field_types);
+fn expand_datadescriptor_self(ident: &Ident, fields: &[StoreField]) -> TokenStream {
+    let trait_bounds = expand_type_trait_bounds(fields);
 
     let datadescriptor_ident = Ident::new(&format!("{}Descriptor", ident), Span::call_site());
 
-    let impl_serialize = field_idents.iter().zip(field_types).map(|(ident, ty)| {
-        let name = ident.to_string();
-        let ty = ty.clone();
+    let impl_serialize = fields.iter().map(|field| {
+        let ty = &field.ty;
+        let name = field.ident.to_string();
 
-        quote! {
-            serializer.serialize_field::<#ty>(#name)?;
+        if let Some(flattened) = &field.flatten {
+            let descriptor = &flattened.descriptor;
+            let keys = expand_prefixed_keys(field);
+
+            quote! {
+                robbot::store::DataDescriptor::<#ty, T>::serialize(
+                    &#descriptor,
+                    &mut robbot::store::Prefixed::new(serializer, #keys),
+                )?;
+            }
+        } else if let Some(repr) = field.repr {
+            let repr_ty = repr.ty();
+
+            quote! {
+                serializer.serialize_field::<#repr_ty>(#name)?;
+            }
+        } else {
+            quote! {
+                serializer.serialize_field::<#ty>(#name)?;
+            }
         }
     });
 
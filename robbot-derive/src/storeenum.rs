@@ -0,0 +1,157 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit, Meta, NestedMeta, Variant};
+
+/// A variant of an enum deriving `StoreEnum`.
+struct StoreVariant<'a> {
+    variant: &'a Variant,
+    /// The string representation of the variant.
+    name: String,
+}
+
+impl<'a> StoreVariant<'a> {
+    fn parse(variant: &'a Variant) -> syn::Result<Self> {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "`StoreEnum` can only be derived for fieldless enums",
+            ));
+        }
+
+        let mut name = None;
+
+        for attr in variant
+            .attrs
+            .iter()
+            .filter(|attr| attr.path.is_ident("store"))
+        {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        "expected `store(rename = \"..\")`",
+                    ))
+                }
+            };
+
+            for nested in list.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::NameValue(meta)) if meta.path.is_ident("rename") => {
+                        match meta.lit {
+                            Lit::Str(lit) => name = Some(lit.value()),
+                            lit => return Err(syn::Error::new_spanned(lit, "expected a string")),
+                        }
+                    }
+                    nested => {
+                        return Err(syn::Error::new_spanned(
+                            nested,
+                            "unknown store attribute, expected `rename`",
+                        ))
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            variant,
+            name: name.unwrap_or_else(|| snake_case(&variant.ident.to_string())),
+        })
+    }
+}
+
+pub(crate) fn expand_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(&input) {
+        Ok(expanded) => proc_macro::TokenStream::from(expanded),
+        Err(err) => proc_macro::TokenStream::from(err.to_compile_error()),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "`StoreEnum` can only be derived for enums",
+            ))
+        }
+    };
+
+    let variants = data
+        .variants
+        .iter()
+        .map(StoreVariant::parse)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    for (i, variant) in variants.iter().enumerate() {
+        if variants[..i].iter().any(|v| v.name == variant.name) {
+            return Err(syn::Error::new_spanned(
+                variant.variant,
+                format!("duplicate store name `{}`", variant.name),
+            ));
+        }
+    }
+
+    let ident = &input.ident;
+    let name = ident.to_string();
+
+    let idents: Vec<_> = variants.iter().map(|v| &v.variant.ident).collect();
+    let names: Vec<_> = variants.iter().map(|v| &v.name).collect();
+
+    Ok(quote! {
+        impl robbot::store::StoreEnum for #ident {
+            const NAME: &'static str = #name;
+
+            fn to_repr(&self) -> u8 {
+                match self {
+                    #(Self::#idents => Self::#idents as u8,)*
+                }
+            }
+
+            fn from_repr(repr: u8) -> ::std::option::Option<Self> {
+                #(
+                    if repr == Self::#idents as u8 {
+                        return ::std::option::Option::Some(Self::#idents);
+                    }
+                )*
+
+                ::std::option::Option::None
+            }
+
+            fn to_name(&self) -> &'static str {
+                match self {
+                    #(Self::#idents => #names,)*
+                }
+            }
+
+            fn from_name(name: &str) -> ::std::option::Option<Self> {
+                match name {
+                    #(#names => ::std::option::Option::Some(Self::#idents),)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+    })
+}
+
+/// Converts a `CamelCase` identifier into `snake_case`.
+fn snake_case(ident: &str) -> String {
+    let mut name = String::with_capacity(ident.len());
+
+    for (i, c) in ident.chars().enumerate() {
+        if c.is_uppercase() {
+            if i != 0 {
+                name.push('_');
+            }
+
+            name.extend(c.to_lowercase());
+        } else {
+            name.push(c);
+        }
+    }
+
+    name
+}
@@ -1,14 +1,11 @@
 use std::time::Duration;
 
 use proc_macro::TokenStream;
-use proc_macro2::TokenStream as TokenStream2;
+use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::quote;
 use quote::ToTokens;
 use syn::parse::{Parse, ParseStream};
-use syn::Expr;
-use syn::Lit;
-use syn::LitStr;
-use syn::{parse_macro_input, ItemFn, Result};
+use syn::{parse_macro_input, Error, Expr, ExprLit, ItemFn, Lit, LitStr, Result};
 
 use crate::ident::InternalIdent;
 use crate::kvmap::KeyValueMap;
@@ -27,8 +24,14 @@ pub fn task(attr: TokenStream, input: TokenStream) -> TokenStream {
     exec_fn.sig.ident = exec_fn_ident.ident();
 
     let name = args.name.unwrap_or(fn_ident.to_string());
-    let schedule = args.schedule.unwrap();
+    let schedule = args.schedule;
     let on_load = args.on_load.unwrap_or(false);
+    let concurrency_group = match args.concurrency_group {
+        Some(group) => quote! { Some(::std::string::String::from(#group)) },
+        None => quote! { None },
+    };
+    let timeout = expand_timeout(args.timeout);
+    let mutates_data = args.mutates_data.unwrap_or(false);
 
     let expanded = quote! {
         #fn_vis fn #fn_ident() -> ::robbot_core::task::Task {
@@ -40,6 +43,9 @@ pub fn task(attr: TokenStream, input: TokenStream) -> TokenStream {
                 name: ::std::string::String::from(#name),
                 schedule: #schedule,
                 on_load: #on_load,
+                concurrency_group: #concurrency_group,
+                timeout: #timeout,
+                mutates_data: #mutates_data,
                 executor: ::robbot_core::executor::Executor::from_fn(#exec_fn_ident),
             }
         }
@@ -48,118 +54,182 @@ pub fn task(attr: TokenStream, input: TokenStream) -> TokenStream {
     TokenStream::from(expanded)
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 struct Task {
     name: Option<String>,
-    schedule: Option<TaskSchedule>,
+    schedule: TaskSchedule,
     on_load: Option<bool>,
+    concurrency_group: Option<String>,
+    timeout: Option<Duration>,
+    mutates_data: Option<bool>,
 }
 
 impl Parse for Task {
     fn parse(input: ParseStream) -> Result<Self> {
         let args = KeyValueMap::parse(input)?;
 
-        let name = args.get("name").map(|expr| match expr {
-            Expr::Lit(lit) => match &lit.lit {
-                Lit::Str(s) => s.value(),
-                _ => panic!("Expected string literal"),
-            },
-            _ => panic!("Expected literal"),
-        });
-
-        // Schedule
-        let mut schedule = None;
-        if let Some(arg) = args.get("interval") {
-            let interval = syn::parse2::<IntervalSchedule>(arg.into_token_stream())?;
-            schedule = Some(TaskSchedule::Interval(interval.interval));
-        }
+        let name = args.get("name").map(parse_str).transpose()?;
 
-        // TODO: IMPL
-        if let Some(_) = args.get("at") {
-            schedule = Some(TaskSchedule::At(()));
-        }
+        let schedule = match (args.get("interval"), args.get("at")) {
+            (Some(arg), _) => {
+                let interval = syn::parse2::<IntervalSchedule>(arg.into_token_stream())?;
+                TaskSchedule::Interval(interval.interval)
+            }
+            (None, Some(arg)) => {
+                return Err(Error::new_spanned(
+                    arg,
+                    "`at` schedules are not supported yet, use `interval = \"..\"`",
+                ))
+            }
+            (None, None) => {
+                return Err(Error::new(
+                    Span::call_site(),
+                    "missing schedule, expected `interval = \"..\"`, e.g. `interval = \"1m\"`",
+                ))
+            }
+        };
+
+        let on_load = parse_bool(&args, "on_load")?;
+
+        let concurrency_group = args.get("concurrency_group").map(parse_str).transpose()?;
 
-        assert!(schedule.is_some());
+        let timeout = parse_timeout(&args)?;
 
-        // on load
-        let on_load = args.get("on_load").map(|expr| match expr {
-            Expr::Lit(lit) => match &lit.lit {
-                Lit::Bool(val) => val.value(),
-                _ => panic!("Expected bool literal"),
-            },
-            _ => panic!("Expected literal"),
-        });
+        let mutates_data = parse_bool(&args, "mutates_data")?;
 
         Ok(Self {
             name,
             schedule,
             on_load,
+            concurrency_group,
+            timeout,
+            mutates_data,
         })
     }
 }
 
-/// A single time span component (e.g.`1s`).
-#[derive(Clone, Debug)]
-struct DateTimeComponent {
-    num: u64,
-    unit: String,
+/// Parses the value of a boolean argument, e.g. `on_load = true`.
+fn parse_bool(args: &KeyValueMap, key: &str) -> Result<Option<bool>> {
+    args.get(key)
+        .map(|expr| match expr {
+            Expr::Lit(ExprLit {
+                lit: Lit::Bool(val),
+                ..
+            }) => Ok(val.value()),
+            expr => Err(Error::new_spanned(
+                expr,
+                format!("expected `true` or `false` for `{}`", key),
+            )),
+        })
+        .transpose()
 }
 
-impl DateTimeComponent {
-    pub fn duration(self) -> Duration {
-        let secs = match self.unit.as_str() {
-            "s" => self.num,
-            "m" => self.num * 60,
-            "h" => self.num * 60 * 60,
-            "d" => self.num * 60 * 60 * 24,
-            "w" => self.num * 60 * 60 * 24 * 7,
-            _ => panic!("Unknown time unit"),
-        };
+/// Parses the value of a string argument, e.g. `name = "task"`.
+fn parse_str(expr: &Expr) -> Result<String> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(s), ..
+        }) => Ok(s.value()),
+        expr => Err(Error::new_spanned(expr, "expected a string literal")),
+    }
+}
+
+/// Parses the `timeout` argument of a task or hook, e.g. `timeout = "5m"`.
+pub(crate) fn parse_timeout(args: &KeyValueMap) -> Result<Option<Duration>> {
+    match args.get("timeout") {
+        Some(arg) => {
+            let timeout = syn::parse2::<IntervalSchedule>(arg.into_token_stream())?;
+            Ok(Some(timeout.interval))
+        }
+        None => Ok(None),
+    }
+}
 
-        Duration::from_secs(secs)
+/// Expands an optional timeout into an `Option<std::time::Duration>`.
+pub(crate) fn expand_timeout(timeout: Option<Duration>) -> TokenStream2 {
+    match timeout {
+        Some(timeout) => {
+            let secs = timeout.as_secs();
+            quote! { Some(::std::time::Duration::from_secs(#secs)) }
+        }
+        None => quote! { None },
     }
 }
 
+/// A single time span component (e.g.`1s`).
+#[derive(Clone, Debug)]
+struct DateTimeComponent {
+    duration: Duration,
+}
+
 impl Parse for DateTimeComponent {
     fn parse(input: ParseStream) -> Result<Self> {
-        let lit = input.parse::<LitStr>()?.value();
+        let lit = input.parse::<LitStr>()?;
+        let value = lit.value();
 
         // Ending index of the integer literal (exlusive).
-        let end = lit
-            .chars()
-            .enumerate()
-            .find(|(_, c)| !c.is_ascii_digit())
-            .map(|(i, _)| i);
-
-        if end.is_none() {
-            panic!("Missing time unit");
-        }
-        let end = end.unwrap();
+        let end = match value.find(|c: char| !c.is_ascii_digit()) {
+            Some(end) => end,
+            None => {
+                return Err(Error::new_spanned(
+                    lit,
+                    "missing time unit, expected a duration like \"30s\", \"5m\", \"1h\", \"1d\" or \"1w\"",
+                ))
+            }
+        };
 
-        let num: u64 = lit[..end].parse().unwrap();
-        let unit: String = lit[end..].to_string();
+        let num: u64 = match value[..end].parse() {
+            Ok(num) => num,
+            Err(_) => {
+                return Err(Error::new_spanned(
+                    lit,
+                    "expected a number followed by a time unit, e.g. \"5m\"",
+                ))
+            }
+        };
 
-        Ok(Self { num, unit })
+        let unit = match &value[end..] {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 60 * 60 * 24,
+            "w" => 60 * 60 * 24 * 7,
+            unit => {
+                return Err(Error::new_spanned(
+                    lit,
+                    format!(
+                        "unknown time unit `{}`, expected one of `s`, `m`, `h`, `d` or `w`",
+                        unit
+                    ),
+                ))
+            }
+        };
+
+        match num.checked_mul(unit) {
+            Some(secs) => Ok(Self {
+                duration: Duration::from_secs(secs),
+            }),
+            None => Err(Error::new_spanned(lit, "duration is too long")),
+        }
     }
 }
 
 #[derive(Debug)]
 enum TaskSchedule {
     Interval(Duration),
-    At(()),
 }
 
 impl ToTokens for TaskSchedule {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
         let tt = match self {
             Self::Interval(dur) => {
-                let secs: i64 = dur.as_secs().try_into().unwrap();
+                // Checked by `IntervalSchedule`.
+                let secs = dur.as_secs() as i64;
 
                 quote! {
                     ::robbot::task::TaskSchedule::Interval(::chrono::Duration::seconds(#secs))
                 }
             }
-            _ => unimplemented!(),
         };
 
         tokens.extend(tt);
@@ -172,15 +242,17 @@ struct IntervalSchedule {
 
 impl Parse for IntervalSchedule {
     fn parse(input: ParseStream) -> Result<Self> {
-        let mut components = Vec::new();
+        let span = input.span();
 
+        let mut interval = Duration::new(0, 0);
         while !input.is_empty() {
-            components.push(DateTimeComponent::parse(input)?);
+            interval = interval
+                .checked_add(DateTimeComponent::parse(input)?.duration)
+                .ok_or_else(|| Error::new(span, "duration is too long"))?;
         }
 
-        let mut interval = Duration::new(0, 0);
-        for comp in components {
-            interval += comp.duration();
+        if interval.as_secs() > i64::MAX as u64 {
+            return Err(Error::new(span, "duration is too long"));
         }
 
         Ok(Self { interval })
@@ -0,0 +1,9 @@
+//! Checks the diagnostics of the macros on malformed input. Run with
+//! `TRYBUILD=overwrite` to update the expected output after changing a
+//! diagnostic.
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,6 @@
+use robbot_derive::command;
+
+#[command(arg_choices(level))]
+async fn level() {}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use robbot_derive::command;
+
+#[command(description = "Ping.", 1 + 2)]
+async fn ping() {}
+
+fn main() {}
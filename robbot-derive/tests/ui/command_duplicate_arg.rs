@@ -0,0 +1,6 @@
+use robbot_derive::command;
+
+#[command(description = "Ping.", description = "Pong.")]
+async fn ping() {}
+
+fn main() {}
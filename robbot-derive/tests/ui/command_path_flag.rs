@@ -0,0 +1,6 @@
+use robbot_derive::command;
+
+#[command(description = "Ping.", flags::guild_only)]
+async fn ping() {}
+
+fn main() {}
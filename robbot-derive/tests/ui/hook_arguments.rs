@@ -0,0 +1,6 @@
+use robbot_derive::hook;
+
+#[hook]
+async fn message(a: u8, b: u8) {}
+
+fn main() {}
@@ -0,0 +1,5 @@
+robbot_derive::module! {
+    tasks: [],
+}
+
+fn main() {}
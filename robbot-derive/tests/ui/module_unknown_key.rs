@@ -0,0 +1,6 @@
+robbot_derive::module! {
+    name: "example",
+    commands: {},
+}
+
+fn main() {}
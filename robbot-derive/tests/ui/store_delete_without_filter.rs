@@ -0,0 +1,6 @@
+struct Rule;
+
+fn main() {
+    let store = ();
+    robbot_derive::delete!(store, Rule);
+}
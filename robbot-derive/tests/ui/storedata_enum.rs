@@ -0,0 +1,6 @@
+#[derive(robbot_derive::StoreData)]
+enum Rule {
+    Word,
+}
+
+fn main() {}
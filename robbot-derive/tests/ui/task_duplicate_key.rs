@@ -0,0 +1,6 @@
+use robbot_derive::task;
+
+#[task(interval = "1m", interval = "5m")]
+async fn cleanup() {}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use robbot_derive::task;
+
+#[task(on_load = true)]
+async fn cleanup() {}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use robbot_derive::task;
+
+#[task(interval = "5x")]
+async fn cleanup() {}
+
+fn main() {}
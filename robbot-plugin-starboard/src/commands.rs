@@ -0,0 +1,156 @@
+use super::{nodes, StarEntry, StarboardConfig, DEFAULT_THRESHOLD};
+
+use robbot::arguments::ChannelMention;
+use robbot::prelude::ArgumentsExt;
+use robbot::store::{delete, get_one, insert};
+use robbot::{command, Error, Result};
+use robbot_core::context::GuildMessageContext;
+
+/// The options of [`set`] following the channel.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct SetArgs {
+    threshold: u64,
+    keep: bool,
+    nsfw: bool,
+}
+
+/// Parses the optional threshold and the flags `--keep` and `--nsfw`.
+fn parse_set<I>(args: I) -> Option<SetArgs>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut threshold = None;
+    let mut keep = false;
+    let mut nsfw = false;
+
+    for arg in args {
+        match arg.as_str() {
+            "--keep" => keep = true,
+            "--nsfw" => nsfw = true,
+            arg if threshold.is_none() => match arg.parse() {
+                Ok(value) if value > 0 => threshold = Some(value),
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+
+    Some(SetArgs {
+        threshold: threshold.unwrap_or(DEFAULT_THRESHOLD),
+        keep,
+        nsfw,
+    })
+}
+
+#[command(
+    description = "Set the starboard channel. Messages reaching the threshold of ⭐ reactions (3 by default) are posted to the channel. Posts are removed when the stars drop below the threshold unless `--keep` is given. Messages from NSFW channels are only posted with `--nsfw`.",
+    usage = "<@Channel> [Threshold] [--keep] [--nsfw]",
+    example = "#starboard 5 --keep",
+    permissions = [nodes::MANAGE],
+    audited,
+    mutates_data,
+)]
+async fn set(mut ctx: GuildMessageContext) -> Result {
+    let channel: ChannelMention = ctx.args.pop_parse()?;
+
+    let mut args = Vec::new();
+    while let Some(arg) = ctx.args.pop() {
+        args.push(arg);
+    }
+
+    let args = parse_set(args).ok_or(Error::InvalidCommandUsage)?;
+
+    ctx.state.store().check_writable()?;
+
+    let guild_id = ctx.event.guild_id;
+
+    let existing = get_one!(ctx.state.store(), StarboardConfig => {
+        guild_id == guild_id,
+    })
+    .await?;
+
+    let config = StarboardConfig {
+        guild_id,
+        channel_id: channel.id,
+        threshold: args.threshold,
+        keep: args.keep,
+        nsfw: args.nsfw,
+        version: existing.as_ref().map_or(0, |config| config.version),
+    };
+
+    match existing {
+        Some(_) => {
+            let store = ctx.state.store();
+            let query = store.make_query::<StarboardConfig>().guild_id(guild_id);
+
+            store.update(query, config).await?;
+        }
+        None => insert!(ctx.state.store(), config).await?,
+    }
+
+    ctx.respond(format!(
+        ":white_check_mark: Posting messages with {} ⭐ to {}.",
+        args.threshold, channel
+    ))
+    .await?;
+    Ok(())
+}
+
+#[command(
+    description = "Remove the starboard. Existing posts are kept.",
+    permissions = [nodes::MANAGE],
+    audited,
+    mutates_data,
+)]
+async fn unset(ctx: GuildMessageContext) -> Result {
+    ctx.state.store().check_writable()?;
+
+    let guild_id = ctx.event.guild_id;
+
+    delete!(ctx.state.store(), StarboardConfig => {
+        guild_id == guild_id,
+    })
+    .await?;
+
+    delete!(ctx.state.store(), StarEntry => {
+        guild_id == guild_id,
+    })
+    .await?;
+
+    ctx.respond(":white_check_mark: Removed the starboard.")
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_set, SetArgs, DEFAULT_THRESHOLD};
+
+    fn args(s: &[&str]) -> Vec<String> {
+        s.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_set() {
+        assert_eq!(
+            parse_set(args(&[])),
+            Some(SetArgs {
+                threshold: DEFAULT_THRESHOLD,
+                keep: false,
+                nsfw: false,
+            })
+        );
+        assert_eq!(
+            parse_set(args(&["--nsfw", "5", "--keep"])),
+            Some(SetArgs {
+                threshold: 5,
+                keep: true,
+                nsfw: true,
+            })
+        );
+
+        for s in [&["0"][..], &["-1"], &["five"], &["5", "6"], &["--all"]] {
+            assert_eq!(parse_set(args(s)), None, "{:?}", s);
+        }
+    }
+}
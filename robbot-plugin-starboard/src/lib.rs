@@ -0,0 +1,693 @@
+//! # Starboard plugin
+//! Reposts messages with enough ⭐ reactions to a starboard channel. Every
+//! starred message has a [`StarEntry`] counting its stars, which is updated
+//! by the reaction hooks. Once the count reaches the threshold of the guild
+//! the message is posted to the starboard, further changes edit the star
+//! count of the post. Posts are removed again when the count drops below the
+//! threshold, unless the guild keeps them.
+//!
+//! Stars of the author of a message and stars on messages of bots are not
+//! counted. Messages from NSFW channels are not posted unless enabled.
+//!
+//! # Writing a plugin crate
+//! This plugin lives in its own crate and only uses the public APIs of
+//! `robbot` and `robbot-core`, so it doubles as the template for plugins
+//! maintained outside of this repository. A minimal plugin crate depends on
+//! both crates:
+//!
+//! ```toml
+//! [dependencies]
+//! robbot = "0.7.0"
+//! robbot-core = { version = "0.7.0", default-features = false }
+//! ```
+//!
+//! and declares its commands, hooks, tasks and stores with [`module!`], which
+//! generates a public `init` function:
+//!
+//! ```ignore
+//! use robbot::{command, module, Result};
+//! use robbot_core::context::MessageContext;
+//!
+//! module! {
+//!     name: "hello",
+//!     cmds: {
+//!         "hello": {
+//!             world,
+//!         },
+//!     },
+//! }
+//!
+//! #[command(description = "Say hello.")]
+//! async fn world(ctx: MessageContext) -> Result {
+//!     ctx.respond("Hello, World!").await?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! The bot loads the plugin by calling `init` with its [`State`] on startup,
+//! see `robbot-bin/src/plugins/mod.rs`. [`robbot_core::prelude`] contains the
+//! commonly used items, the colors of embeds are in [`robbot_core::theme`].
+//!
+//! [`module!`]: robbot::module
+//! [`State`]: robbot_core::state::State
+mod commands;
+
+use robbot::arguments::MessageRef;
+use robbot::builder::{EditMessage, EmbedBuilder, MessageBuilder};
+use robbot::model::channel::{Attachment, Message};
+use robbot::model::id::{ChannelId, GuildId, MessageId, UserId};
+use robbot::store::{delete, get, get_one, insert};
+use robbot::{hook, module, Error, Result, StoreData};
+use robbot_core::context::Context;
+use robbot_core::hook::{
+    MessageDeleteContext, ReactionAddBatchContext, ReactionRemoveAllContext,
+    ReactionRemoveBatchContext,
+};
+
+use std::collections::HashMap;
+use std::result;
+
+/// The emoji counted as a star.
+pub const STAR: &str = "⭐";
+
+/// The threshold used if `starboard set` is given none.
+pub const DEFAULT_THRESHOLD: u64 = 3;
+
+/// The maximum length of the content shown in a starboard post.
+const MAX_CONTENT_LEN: usize = 2048;
+
+pub mod nodes {
+    robbot::permission_nodes! {
+        "starboard" => {
+            MANAGE = "manage",
+        }
+    }
+}
+
+module! {
+    name: "starboard",
+    cmds: {
+        "starboard": {
+            commands::set,
+            commands::unset,
+        },
+    },
+    store: [
+        StarboardConfig,
+        StarEntry,
+    ],
+    hooks: [
+        reaction_add,
+        reaction_remove,
+        reaction_remove_all,
+        message_delete,
+    ],
+    permissions: nodes::NODES,
+}
+
+/// The starboard of a guild.
+#[derive(Clone, Debug, StoreData)]
+pub struct StarboardConfig {
+    pub guild_id: GuildId,
+    /// The channel messages are posted to.
+    pub channel_id: ChannelId,
+    /// The number of stars required to post a message.
+    pub threshold: u64,
+    /// Whether posts are kept when the stars drop below the threshold.
+    pub keep: bool,
+    /// Whether messages from NSFW channels are posted.
+    pub nsfw: bool,
+    /// Incremented on every update, see [`StoreHandler::update`].
+    ///
+    /// [`StoreHandler::update`]: robbot_core::store::StoreHandler::update
+    #[store(version)]
+    pub version: u64,
+}
+
+/// The stars of a message.
+#[derive(Clone, Debug, StoreData)]
+pub struct StarEntry {
+    pub guild_id: GuildId,
+    /// The channel of the starred message.
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    /// The post on the starboard. `0` if the message is not posted.
+    pub starboard_message_id: MessageId,
+    pub stars: u64,
+}
+
+impl StarEntry {
+    fn is_posted(&self) -> bool {
+        self.starboard_message_id.0 != 0
+    }
+}
+
+/// The change to the starboard after the stars of a message changed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Action {
+    None,
+    Post,
+    Edit,
+    Remove,
+}
+
+/// Returns the change to the starboard for a message with `stars` stars.
+fn action(config: &StarboardConfig, stars: u64, posted: bool) -> Action {
+    match (stars >= config.threshold, posted) {
+        (true, false) => Action::Post,
+        (true, true) => Action::Edit,
+        (false, true) if config.keep => Action::Edit,
+        (false, true) => Action::Remove,
+        (false, false) => Action::None,
+    }
+}
+
+/// Returns the content of a starboard post, showing the stars and source
+/// channel.
+fn post_content(stars: u64, channel_id: ChannelId) -> String {
+    format!("{} **{}** {}", STAR, stars, channel_id.mention())
+}
+
+/// Returns the URL of the first image attached to a message.
+fn image_url(attachments: &[Attachment]) -> Option<&str> {
+    attachments
+        .iter()
+        .find(|attachment| match &attachment.content_type {
+            Some(content_type) => content_type.starts_with("image/"),
+            None => attachment.width.is_some(),
+        })
+        .map(|attachment| attachment.url.as_str())
+}
+
+/// Renders the starboard post of a message.
+fn render(message: &Message, guild_id: GuildId) -> EmbedBuilder {
+    let link = MessageRef::new(Some(guild_id), Some(message.channel_id), message.id);
+
+    let mut content: String = message.content.chars().take(MAX_CONTENT_LEN).collect();
+    if content.len() < message.content.len() {
+        content.push('…');
+    }
+
+    let mut embed = EmbedBuilder::new()
+        .author(&message.author.name)
+        .author_icon(message.author.avatar_url())
+        .field("Source", format!("[Jump to message]({})", link), false)
+        .footer(message.id)
+        .timestamp(message.timestamp);
+
+    if !content.is_empty() {
+        embed = embed.description(content);
+    }
+
+    if let Some(url) = image_url(&message.attachments) {
+        embed = embed.image(url);
+    }
+
+    embed
+}
+
+/// Returns the starboard of a guild, if one is set.
+pub async fn starboard_config<T>(
+    ctx: &Context<T>,
+    guild_id: GuildId,
+) -> result::Result<Option<StarboardConfig>, Error>
+where
+    T: Send + Sync,
+{
+    let config = get_one!(ctx.state.store(), StarboardConfig => {
+        guild_id == guild_id,
+    })
+    .await?;
+
+    Ok(config)
+}
+
+/// Returns `true` if the channel is marked as NSFW.
+async fn is_nsfw<T>(
+    ctx: &Context<T>,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> result::Result<bool, Error>
+where
+    T: Send + Sync,
+{
+    let channels = ctx.channels(guild_id).await?;
+
+    Ok(channels
+        .iter()
+        .any(|channel| channel.id == channel_id && channel.nsfw))
+}
+
+/// Replaces the stored entry of a message. Entries without stars that are
+/// not posted are deleted.
+async fn store_entry<T>(ctx: &Context<T>, entry: &StarEntry) -> Result
+where
+    T: Send + Sync,
+{
+    delete!(ctx.state.store(), StarEntry => {
+        guild_id == entry.guild_id,
+        message_id == entry.message_id,
+    })
+    .await?;
+
+    if entry.stars != 0 || entry.is_posted() {
+        insert!(ctx.state.store(), entry.clone()).await?;
+    }
+
+    Ok(())
+}
+
+/// The star reactions changing the stars of a message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Change {
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    /// The users adding or removing a star, `None` if the user is unknown.
+    users: Vec<Option<UserId>>,
+    /// `1` for added stars, `-1` for removed stars. `0` resets the stars,
+    /// e.g. after all reactions were removed.
+    delta: i64,
+}
+
+/// Groups star reactions, given as `(guild, channel, message, user)`, into
+/// one change per message. Changes are ordered by the first reaction on
+/// their message.
+fn changes<I>(reactions: I, delta: i64) -> Vec<Change>
+where
+    I: IntoIterator<Item = (GuildId, ChannelId, MessageId, Option<UserId>)>,
+{
+    let mut changes: Vec<Change> = Vec::new();
+    let mut indices: HashMap<(GuildId, MessageId), usize> = HashMap::new();
+
+    for (guild_id, channel_id, message_id, user_id) in reactions {
+        match indices.get(&(guild_id, message_id)) {
+            Some(&index) => changes[index].users.push(user_id),
+            None => {
+                indices.insert((guild_id, message_id), changes.len());
+                changes.push(Change {
+                    guild_id,
+                    channel_id,
+                    message_id,
+                    users: vec![user_id],
+                    delta,
+                });
+            }
+        }
+    }
+
+    changes
+}
+
+/// Applies changes to the stars of messages. The starboards and entries of
+/// all messages are read with one query each.
+async fn update_all<T>(ctx: &Context<T>, changes: Vec<Change>) -> Result
+where
+    T: Send + Sync,
+{
+    let mut guild_ids: Vec<_> = changes.iter().map(|change| change.guild_id).collect();
+    guild_ids.sort_unstable();
+    guild_ids.dedup();
+
+    if guild_ids.is_empty() {
+        return Ok(());
+    }
+
+    let configs: HashMap<_, _> = get!(ctx.state.store(), StarboardConfig => {
+        guild_id in guild_ids,
+    })
+    .await?
+    .into_iter()
+    .map(|config| (config.guild_id, config))
+    .collect();
+
+    let changes: Vec<_> = changes
+        .into_iter()
+        .filter(|change| configs.contains_key(&change.guild_id))
+        .collect();
+
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let message_ids: Vec<_> = changes.iter().map(|change| change.message_id).collect();
+    let mut entries: HashMap<_, _> = get!(ctx.state.store(), StarEntry => {
+        message_id in message_ids,
+    })
+    .await?
+    .into_iter()
+    .map(|entry| ((entry.guild_id, entry.message_id), entry))
+    .collect();
+
+    // A failed message does not stop the other messages of the batch.
+    let mut result = Ok(());
+    for change in &changes {
+        let entry = entries.remove(&(change.guild_id, change.message_id));
+
+        if let Err(err) = update(ctx, &configs[&change.guild_id], change, entry).await {
+            result = Err(err);
+        }
+    }
+
+    result
+}
+
+/// Applies a change to the stars of a message and updates the starboard.
+/// `entry` is the stored entry of the message, if it has one.
+async fn update<T>(
+    ctx: &Context<T>,
+    config: &StarboardConfig,
+    change: &Change,
+    entry: Option<StarEntry>,
+) -> Result
+where
+    T: Send + Sync,
+{
+    let Change {
+        guild_id,
+        channel_id,
+        message_id,
+        ..
+    } = *change;
+
+    // Stars on starboard posts are not counted.
+    if channel_id == config.channel_id {
+        return Ok(());
+    }
+
+    ctx.state.store().check_writable()?;
+
+    let message = ctx.fetch_message(channel_id, message_id).await?;
+    if message.author.bot {
+        return Ok(());
+    }
+
+    let stars = change
+        .users
+        .iter()
+        .filter(|user_id| **user_id != Some(message.author.id))
+        .count() as i64;
+    if change.delta != 0 && stars == 0 {
+        return Ok(());
+    }
+
+    let mut entry = entry.unwrap_or(StarEntry {
+        guild_id,
+        channel_id,
+        message_id,
+        starboard_message_id: MessageId(0),
+        stars: 0,
+    });
+
+    entry.stars = match change.delta {
+        0 => 0,
+        delta => entry.stars.saturating_add_signed(delta * stars),
+    };
+
+    match action(config, entry.stars, entry.is_posted()) {
+        Action::None => (),
+        Action::Post => {
+            if !config.nsfw && is_nsfw(ctx, guild_id, channel_id).await? {
+                return store_entry(ctx, &entry).await;
+            }
+
+            let post = ctx
+                .send_message(
+                    config.channel_id,
+                    MessageBuilder::new()
+                        .content(post_content(entry.stars, channel_id))
+                        .embed(render(&message, guild_id)),
+                )
+                .await?;
+
+            entry.starboard_message_id = post.id;
+        }
+        Action::Edit => {
+            ctx.edit_message(
+                config.channel_id,
+                entry.starboard_message_id,
+                EditMessage::new(|m| {
+                    m.content(post_content(entry.stars, channel_id));
+                }),
+            )
+            .await?;
+        }
+        Action::Remove => {
+            ctx.delete_message(config.channel_id, entry.starboard_message_id)
+                .await?;
+
+            entry.starboard_message_id = MessageId(0);
+        }
+    }
+
+    store_entry(ctx, &entry).await
+}
+
+// The reaction hooks handle batches, a burst of stars on one message only
+// reads and updates it once.
+#[hook(batch = true)]
+async fn reaction_add(ctx: ReactionAddBatchContext) -> Result {
+    let stars = ctx.event.iter().filter_map(|event| {
+        let reaction = &event.0;
+        let guild_id = reaction.guild_id?;
+
+        reaction.emoji.unicode_eq(STAR).then(|| {
+            (
+                guild_id.into(),
+                reaction.channel_id.into(),
+                reaction.message_id.into(),
+                reaction.user_id.map(UserId::from),
+            )
+        })
+    });
+
+    update_all(&ctx, changes(stars, 1)).await
+}
+
+#[hook(batch = true)]
+async fn reaction_remove(ctx: ReactionRemoveBatchContext) -> Result {
+    let stars = ctx.event.iter().filter_map(|event| {
+        let reaction = &event.0;
+        let guild_id = reaction.guild_id?;
+
+        reaction.emoji.unicode_eq(STAR).then(|| {
+            (
+                guild_id.into(),
+                reaction.channel_id.into(),
+                reaction.message_id.into(),
+                reaction.user_id.map(UserId::from),
+            )
+        })
+    });
+
+    update_all(&ctx, changes(stars, -1)).await
+}
+
+#[hook]
+async fn reaction_remove_all(ctx: ReactionRemoveAllContext) -> Result {
+    let message_id: MessageId = ctx.event.message_id.into();
+
+    // The event has no guild, message ids are unique across guilds.
+    let entry = match get_one!(ctx.state.store(), StarEntry => {
+        message_id == message_id,
+    })
+    .await?
+    {
+        Some(entry) => entry,
+        None => return Ok(()),
+    };
+
+    let config = match starboard_config(&ctx, entry.guild_id).await? {
+        Some(config) => config,
+        None => return Ok(()),
+    };
+
+    let change = Change {
+        guild_id: entry.guild_id,
+        channel_id: entry.channel_id,
+        message_id,
+        users: vec![None],
+        delta: 0,
+    };
+
+    update(&ctx, &config, &change, Some(entry)).await
+}
+
+#[hook]
+async fn message_delete(ctx: MessageDeleteContext) -> Result {
+    let guild_id: GuildId = match ctx.event.guild_id {
+        Some(guild_id) => guild_id.into(),
+        None => return Ok(()),
+    };
+    let message_id: MessageId = ctx.event.message_id.into();
+
+    if ctx.state.store().check_writable().is_err() {
+        return Ok(());
+    }
+
+    // A deleted starboard post is not posted again.
+    let post = get_one!(ctx.state.store(), StarEntry => {
+        guild_id == guild_id,
+        starboard_message_id == message_id,
+    })
+    .await?;
+
+    if let Some(entry) = post {
+        return store_entry(
+            &ctx,
+            &StarEntry {
+                starboard_message_id: MessageId(0),
+                stars: 0,
+                ..entry
+            },
+        )
+        .await;
+    }
+
+    let entry = match get_one!(ctx.state.store(), StarEntry => {
+        guild_id == guild_id,
+        message_id == message_id,
+    })
+    .await?
+    {
+        Some(entry) => entry,
+        None => return Ok(()),
+    };
+
+    delete!(ctx.state.store(), StarEntry => {
+        guild_id == guild_id,
+        message_id == message_id,
+    })
+    .await?;
+
+    if entry.is_posted() {
+        if let Some(config) = starboard_config(&ctx, guild_id).await? {
+            ctx.delete_message(config.channel_id, entry.starboard_message_id)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{action, changes, image_url, post_content, Action, Change, StarboardConfig};
+
+    use robbot::model::channel::Attachment;
+    use robbot::model::id::{AttachmentId, ChannelId, GuildId, MessageId, UserId};
+
+    fn config(keep: bool) -> StarboardConfig {
+        StarboardConfig {
+            guild_id: GuildId(1),
+            channel_id: ChannelId(2),
+            threshold: 3,
+            keep,
+            nsfw: false,
+            version: 0,
+        }
+    }
+
+    fn attachment(url: &str, content_type: Option<&str>, width: Option<u64>) -> Attachment {
+        Attachment {
+            id: AttachmentId(1),
+            filename: String::from("file"),
+            height: width,
+            proxy_url: url.to_owned(),
+            size: 0,
+            url: url.to_owned(),
+            width,
+            content_type: content_type.map(str::to_owned),
+            ephemeral: false,
+        }
+    }
+
+    #[test]
+    fn test_action() {
+        let config = config(false);
+        assert_eq!(action(&config, 2, false), Action::None);
+        assert_eq!(action(&config, 3, false), Action::Post);
+        assert_eq!(action(&config, 4, true), Action::Edit);
+        assert_eq!(action(&config, 2, true), Action::Remove);
+
+        // Kept posts are only edited.
+        let config = super::StarboardConfig {
+            keep: true,
+            ..config
+        };
+        assert_eq!(action(&config, 2, true), Action::Edit);
+        assert_eq!(action(&config, 0, true), Action::Edit);
+        assert_eq!(action(&config, 0, false), Action::None);
+    }
+
+    #[test]
+    fn test_post_content() {
+        assert_eq!(post_content(3, ChannelId(5)), "⭐ **3** <#5>");
+    }
+
+    #[test]
+    fn test_image_url() {
+        assert_eq!(image_url(&[]), None);
+
+        let attachments = [
+            attachment("a.txt", Some("text/plain"), None),
+            attachment("b.png", Some("image/png"), Some(10)),
+            attachment("c.png", Some("image/png"), Some(10)),
+        ];
+        assert_eq!(image_url(&attachments), Some("b.png"));
+
+        // Attachments without a content type are images if they have a size.
+        let attachments = [
+            attachment("a.bin", None, None),
+            attachment("b.jpg", None, Some(10)),
+        ];
+        assert_eq!(image_url(&attachments), Some("b.jpg"));
+    }
+
+    #[test]
+    fn test_changes() {
+        let star = |guild_id, message_id, user_id: Option<u64>| {
+            (
+                GuildId(guild_id),
+                ChannelId(10),
+                MessageId(message_id),
+                user_id.map(UserId),
+            )
+        };
+
+        let reactions = [
+            star(1, 100, Some(1)),
+            star(1, 200, Some(2)),
+            star(1, 100, Some(3)),
+            star(2, 100, None),
+        ];
+
+        assert_eq!(
+            changes(reactions, 1),
+            [
+                Change {
+                    guild_id: GuildId(1),
+                    channel_id: ChannelId(10),
+                    message_id: MessageId(100),
+                    users: vec![Some(UserId(1)), Some(UserId(3))],
+                    delta: 1,
+                },
+                Change {
+                    guild_id: GuildId(1),
+                    channel_id: ChannelId(10),
+                    message_id: MessageId(200),
+                    users: vec![Some(UserId(2))],
+                    delta: 1,
+                },
+                Change {
+                    guild_id: GuildId(2),
+                    channel_id: ChannelId(10),
+                    message_id: MessageId(100),
+                    users: vec![None],
+                    delta: 1,
+                },
+            ]
+        );
+        assert!(changes([], -1).is_empty());
+    }
+}
@@ -1,5 +1,9 @@
 use crate::bot::Error;
-use crate::model::id::{ChannelId, RoleId, UserId};
+use crate::context::Context;
+use crate::model::channel::{GuildMessage, Message};
+use crate::model::id::{ChannelId, GuildId, MessageId, RoleId, UserId};
+
+use thiserror::Error;
 
 use std::fmt::{self, Display, Formatter};
 use std::iter::FromIterator;
@@ -119,7 +123,7 @@ impl<'life0> Deref for Arguments<'life0> {
     type Target = [String];
 
     fn deref(&self) -> &Self::Target {
-        self.0.deref()
+        self.0
     }
 }
 
@@ -182,7 +186,7 @@ impl OwnedArguments {
     }
 
     /// Returns a new [`Arguments`] slice over all arguments.
-    pub fn as_args(&self) -> Arguments {
+    pub fn as_args(&self) -> Arguments<'_> {
         Arguments::new(&self.0)
     }
 
@@ -323,7 +327,7 @@ impl CommandArguments {
         Self::default()
     }
 
-    pub fn as_args(&self) -> Arguments {
+    pub fn as_args(&self) -> Arguments<'_> {
         Arguments::new(self.as_ref())
     }
 
@@ -347,13 +351,13 @@ impl CommandArguments {
 
     /// Returns an [`Arguments`] list containing all arguments before
     /// any routing was applied.
-    pub fn as_full_args(&self) -> Arguments {
+    pub fn as_full_args(&self) -> Arguments<'_> {
         Arguments::new(&self.owned)
     }
 
     /// Returns an [`Arguments`] list containing only arguments that were
     /// parsed (removed).
-    pub fn as_parsed_args(&self) -> Arguments {
+    pub fn as_parsed_args(&self) -> Arguments<'_> {
         let slice: &[String] = self.owned.as_ref();
 
         Arguments::new(&slice[..self.offset])
@@ -442,12 +446,10 @@ impl FromStr for ChannelMention {
     type Err = InvalidMention;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if !s.starts_with("<#") || !s.ends_with('>') {
-            return Err(InvalidMention);
-        }
-
-        // SAFETY: The subslice is in bounds of the original slice.
-        let id = unsafe { s.get_unchecked(2..s.len() - 1) };
+        let id = s
+            .strip_prefix("<#")
+            .and_then(|s| s.strip_suffix('>'))
+            .ok_or(InvalidMention)?;
 
         let id = id.parse().or(Err(InvalidMention))?;
 
@@ -481,12 +483,10 @@ impl FromStr for RoleMention {
     type Err = InvalidMention;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if !s.starts_with("<@&") || !s.ends_with('>') {
-            return Err(InvalidMention);
-        }
-
-        // SAFETY: The subslice is in bounds of the original slice.
-        let id = unsafe { s.get_unchecked(3..s.len() - 1) };
+        let id = s
+            .strip_prefix("<@&")
+            .and_then(|s| s.strip_suffix('>'))
+            .ok_or(InvalidMention)?;
 
         let id = id.parse().or(Err(InvalidMention))?;
 
@@ -520,35 +520,238 @@ impl FromStr for UserMention {
     type Err = InvalidMention;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if !s.starts_with("<@") || !s.ends_with('>') {
-            return Err(InvalidMention);
+        let id = s
+            .strip_prefix("<@")
+            .and_then(|s| s.strip_suffix('>'))
+            .ok_or(InvalidMention)?;
+
+        let id = id.strip_prefix('!').unwrap_or(id);
+
+        let id = id.parse().or(Err(InvalidMention))?;
+
+        Ok(Self { id: UserId(id) })
+    }
+}
+
+/// An event that originated from a channel, e.g. a [`Message`]. Used to resolve
+/// arguments relative to the channel a command was invoked in.
+pub trait ChannelEvent {
+    /// Returns the guild of the channel, or `None` for direct messages.
+    fn guild_id(&self) -> Option<GuildId>;
+
+    fn channel_id(&self) -> ChannelId;
+}
+
+impl ChannelEvent for Message {
+    fn guild_id(&self) -> Option<GuildId> {
+        self.guild_id
+    }
+
+    fn channel_id(&self) -> ChannelId {
+        self.channel_id
+    }
+}
+
+impl ChannelEvent for GuildMessage {
+    fn guild_id(&self) -> Option<GuildId> {
+        Some(self.guild_id)
+    }
+
+    fn channel_id(&self) -> ChannelId {
+        self.channel_id
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Error)]
+pub enum InvalidMessageRef {
+    #[error("invalid message reference")]
+    Invalid,
+    #[error("the message belongs to another guild")]
+    OtherGuild,
+}
+
+/// A reference to a message. `MessageRef` accepts the following formats:
+/// - A message link: `https://discord.com/channels/{guild_id}/{channel_id}/{message_id}`.
+///   Links from the `ptb` and `canary` clients, the legacy `discordapp.com`
+///   domain, a trailing slash and links wrapped in `<>` are accepted too.
+///   Links to direct messages use `@me` instead of the guild id.
+/// - The `{channel_id}-{message_id}` shorthand copied by the Discord client.
+/// - A bare message id.
+///
+/// The guild and channel are `None` if they are not part of the argument. Use
+/// [`resolve`] to fill them in from the invoking channel.
+///
+/// [`resolve`]: Self::resolve
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MessageRef {
+    pub guild_id: Option<GuildId>,
+    pub channel_id: Option<ChannelId>,
+    pub message_id: MessageId,
+}
+
+impl MessageRef {
+    /// Creates a new `MessageRef`.
+    pub fn new<T>(guild_id: Option<GuildId>, channel_id: Option<ChannelId>, message_id: T) -> Self
+    where
+        T: Into<MessageId>,
+    {
+        Self {
+            guild_id,
+            channel_id,
+            message_id: message_id.into(),
+        }
+    }
+
+    /// Resolves the reference relative to the channel the event of `ctx` was
+    /// sent in. See [`resolve_in`] for details.
+    ///
+    /// [`resolve_in`]: Self::resolve_in
+    pub fn resolve<T, S>(&self, ctx: &Context<T, S>) -> Result<Self, InvalidMessageRef>
+    where
+        T: ChannelEvent + Send + Sync,
+        S: Send + Sync,
+    {
+        self.resolve_in(ctx.event.guild_id(), ctx.event.channel_id())
+    }
+
+    /// Resolves the reference relative to the channel `channel_id` in the guild
+    /// `guild_id`. A missing channel is set to `channel_id` and the guild is
+    /// always set to `guild_id`.
+    ///
+    /// Returns [`InvalidMessageRef::OtherGuild`] if the reference points to a
+    /// message in another guild.
+    pub fn resolve_in(
+        &self,
+        guild_id: Option<GuildId>,
+        channel_id: ChannelId,
+    ) -> Result<Self, InvalidMessageRef> {
+        if self.guild_id.is_some() && self.guild_id != guild_id {
+            return Err(InvalidMessageRef::OtherGuild);
+        }
+
+        Ok(Self {
+            guild_id,
+            channel_id: Some(self.channel_id.unwrap_or(channel_id)),
+            message_id: self.message_id,
+        })
+    }
+}
+
+/// The resolved reference to a message.
+impl From<&Message> for MessageRef {
+    fn from(message: &Message) -> Self {
+        Self::new(message.guild_id, Some(message.channel_id), message.id)
+    }
+}
+
+/// Displays the canonical message link, or only the message id if the channel
+/// is unknown.
+impl Display for MessageRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match (self.guild_id, self.channel_id) {
+            (Some(guild_id), Some(channel_id)) => write!(
+                f,
+                "https://discord.com/channels/{}/{}/{}",
+                guild_id, channel_id, self.message_id
+            ),
+            (None, Some(channel_id)) => write!(
+                f,
+                "https://discord.com/channels/@me/{}/{}",
+                channel_id, self.message_id
+            ),
+            (_, None) => write!(f, "{}", self.message_id),
+        }
+    }
+}
+
+impl FromStr for MessageRef {
+    type Err = InvalidMessageRef;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn parse_id<T>(s: &str) -> Result<T, InvalidMessageRef>
+        where
+            T: From<u64>,
+        {
+            s.parse::<u64>()
+                .map(T::from)
+                .or(Err(InvalidMessageRef::Invalid))
+        }
+
+        // Bare message id.
+        if let Ok(message_id) = s.parse::<u64>() {
+            return Ok(Self::new(None, None, message_id));
+        }
+
+        // {channel_id}-{message_id}
+        if let Some((channel_id, message_id)) = s.split_once('-') {
+            if let (Ok(channel_id), Ok(message_id)) =
+                (parse_id(channel_id), parse_id::<MessageId>(message_id))
+            {
+                return Ok(Self::new(None, Some(channel_id), message_id));
+            }
         }
 
-        // SAFETY: The subslice is in bounds of the original slice.
-        let id = unsafe { s.get_unchecked(2..s.len() - 1) };
+        // Links wrapped in <> don't show an embed.
+        let s = s
+            .strip_prefix('<')
+            .and_then(|s| s.strip_suffix('>'))
+            .unwrap_or(s);
+
+        let s = s
+            .strip_prefix("https://")
+            .or_else(|| s.strip_prefix("http://"))
+            .ok_or(InvalidMessageRef::Invalid)?;
+
+        let (host, path) = s.split_once('/').ok_or(InvalidMessageRef::Invalid)?;
+
+        let host = host
+            .strip_prefix("ptb.")
+            .or_else(|| host.strip_prefix("canary."))
+            .unwrap_or(host);
+        if host != "discord.com" && host != "discordapp.com" {
+            return Err(InvalidMessageRef::Invalid);
+        }
 
-        let id = match id.get(0..1) {
-            // SAFETY: The subslice is in bounds of the original slice.
-            Some("!") => unsafe { id.get_unchecked(1..id.len()) },
-            _ => id,
+        let path = path.strip_suffix('/').unwrap_or(path);
+
+        let mut segments = path.split('/');
+        let (guild_id, channel_id, message_id) = match (
+            segments.next(),
+            segments.next(),
+            segments.next(),
+            segments.next(),
+            segments.next(),
+        ) {
+            (Some("channels"), Some(guild_id), Some(channel_id), Some(message_id), None) => {
+                (guild_id, channel_id, message_id)
+            }
+            _ => return Err(InvalidMessageRef::Invalid),
         };
 
-        let id = id.parse().or(Err(InvalidMention))?;
+        let guild_id = match guild_id {
+            "@me" => None,
+            guild_id => Some(parse_id(guild_id)?),
+        };
 
-        Ok(Self { id: UserId(id) })
+        Ok(Self::new(
+            guild_id,
+            Some(parse_id(channel_id)?),
+            parse_id::<MessageId>(message_id)?,
+        ))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::{
-        ArgumentsExt, ChannelMention, CommandArguments, InvalidMention, OwnedArguments,
-        RoleMention, UserMention,
+        ArgumentsExt, ChannelMention, CommandArguments, InvalidMention, InvalidMessageRef,
+        MessageRef, OwnedArguments, RoleMention, UserMention,
     };
+    use crate::model::id::{ChannelId, GuildId};
 
     #[test]
     fn test_owned_arguments() {
-        let mut arguments: OwnedArguments = vec!["Hello", "123"].iter().collect();
+        let mut arguments: OwnedArguments = ["Hello", "123"].iter().collect();
         assert_eq!(arguments, vec!["Hello", "123"]);
 
         arguments.push(String::from("arg3"));
@@ -557,7 +760,7 @@ mod tests {
 
     #[test]
     fn test_command_arguments() {
-        let args: OwnedArguments = vec!["Hello", "123", "arg3"].iter().collect();
+        let args: OwnedArguments = ["Hello", "123", "arg3"].iter().collect();
         let mut args = CommandArguments::from(args);
 
         assert_eq!(args, vec!["Hello", "123", "arg3"]);
@@ -626,4 +829,104 @@ mod tests {
         let s = "<@!>";
         assert_eq!(s.parse::<UserMention>().unwrap_err(), InvalidMention);
     }
+
+    #[test]
+    fn test_message_ref() {
+        let link = MessageRef::new(Some(GuildId(1)), Some(ChannelId(2)), 3);
+
+        for s in [
+            "https://discord.com/channels/1/2/3",
+            "https://discord.com/channels/1/2/3/",
+            "https://ptb.discord.com/channels/1/2/3",
+            "https://canary.discord.com/channels/1/2/3/",
+            "https://discordapp.com/channels/1/2/3",
+            "http://discord.com/channels/1/2/3",
+            "<https://discord.com/channels/1/2/3>",
+        ] {
+            assert_eq!(s.parse::<MessageRef>().unwrap(), link, "{}", s);
+        }
+
+        assert_eq!(
+            "https://discord.com/channels/@me/2/3"
+                .parse::<MessageRef>()
+                .unwrap(),
+            MessageRef::new(None, Some(ChannelId(2)), 3)
+        );
+        assert_eq!(
+            "2-3".parse::<MessageRef>().unwrap(),
+            MessageRef::new(None, Some(ChannelId(2)), 3)
+        );
+        assert_eq!(
+            "3".parse::<MessageRef>().unwrap(),
+            MessageRef::new(None, None, 3)
+        );
+
+        for s in [
+            "",
+            "abc",
+            "2-",
+            "-3",
+            "https://example.com/channels/1/2/3",
+            "https://discord.com/channels/1/2",
+            "https://discord.com/channels/1/2/3/4",
+            "https://discord.com/channels/1/2/3//",
+            "https://discord.com/guilds/1/2/3",
+            "https://discord.com/channels/a/2/3",
+            "discord.com/channels/1/2/3",
+        ] {
+            assert_eq!(
+                s.parse::<MessageRef>().unwrap_err(),
+                InvalidMessageRef::Invalid,
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn test_message_ref_resolve() {
+        let guild_id = Some(GuildId(1));
+        let channel_id = ChannelId(4);
+
+        // Links must point to the invoking guild.
+        let link = MessageRef::new(Some(GuildId(1)), Some(ChannelId(2)), 3);
+        assert_eq!(link.resolve_in(guild_id, channel_id).unwrap(), link);
+        assert_eq!(
+            link.resolve_in(Some(GuildId(5)), channel_id).unwrap_err(),
+            InvalidMessageRef::OtherGuild
+        );
+        assert_eq!(
+            link.resolve_in(None, channel_id).unwrap_err(),
+            InvalidMessageRef::OtherGuild
+        );
+
+        // Bare ids use the invoking channel.
+        let id = MessageRef::new(None, None, 3);
+        assert_eq!(
+            id.resolve_in(guild_id, channel_id).unwrap(),
+            MessageRef::new(guild_id, Some(channel_id), 3)
+        );
+
+        let shorthand = MessageRef::new(None, Some(ChannelId(2)), 3);
+        assert_eq!(shorthand.resolve_in(guild_id, channel_id).unwrap(), link);
+    }
+
+    #[test]
+    fn test_message_ref_display() {
+        for s in [
+            "https://discord.com/channels/1/2/3",
+            "https://discord.com/channels/@me/2/3",
+            "3",
+        ] {
+            assert_eq!(s.parse::<MessageRef>().unwrap().to_string(), s);
+        }
+
+        assert_eq!(
+            "https://canary.discord.com/channels/1/2/3/"
+                .parse::<MessageRef>()
+                .unwrap()
+                .to_string(),
+            "https://discord.com/channels/1/2/3"
+        );
+    }
 }
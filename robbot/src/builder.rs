@@ -1,11 +1,16 @@
 use crate as robbot;
-use crate::model::channel::MessageReference;
+use crate::model::channel::{
+    ChannelKind, MessageReference, PermissionOverwrite, PermissionOverwriteKind,
+};
 use crate::model::id::{ChannelId, RoleId};
+use crate::model::permissions::Permissions;
 use crate::util::color::Color;
 use crate::{Decode, Encode};
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::convert::{From, Into};
+use thiserror::Error;
 
 /// [`CreateMessage`] is used to construct a new
 /// message.
@@ -13,7 +18,8 @@ use std::convert::{From, Into};
 pub struct CreateMessage {
     content: Option<String>,
     reference_message: Option<MessageReference>,
-    embed: Option<CreateEmbed>,
+    embeds: Vec<CreateEmbed>,
+    files: Vec<CreateAttachment>,
 }
 
 impl CreateMessage {
@@ -43,11 +49,35 @@ impl CreateMessage {
         self
     }
 
+    /// Sets the embed of the message, replacing all previously added embeds.
     pub fn embed<F>(&mut self, f: F) -> &mut Self
     where
         F: FnOnce(&mut CreateEmbed),
     {
-        self.embed = Some(CreateEmbed::new(f));
+        self.embeds = vec![CreateEmbed::new(f)];
+        self
+    }
+
+    /// Adds an embed to the message. Discord accepts up to 10 embeds per
+    /// message.
+    pub fn add_embed<T>(&mut self, embed: T) -> &mut Self
+    where
+        T: Into<CreateEmbed>,
+    {
+        self.embeds.push(embed.into());
+        self
+    }
+
+    /// Attaches a file with the name `filename` and the contents `data` to
+    /// the message. Discord accepts up to 10 files per message.
+    pub fn attachment<T>(&mut self, filename: T, data: Vec<u8>) -> &mut Self
+    where
+        T: ToString,
+    {
+        self.files.push(CreateAttachment {
+            filename: filename.to_string(),
+            data,
+        });
         self
     }
 
@@ -63,15 +93,36 @@ impl CreateMessage {
             ));
         }
 
-        if let Some(embed) = self.embed {
-            builder.embed(|e| {
-                embed.fill_builder(e);
-                e
+        if !self.embeds.is_empty() {
+            let embeds = self
+                .embeds
+                .into_iter()
+                .map(|embed| {
+                    let mut builder = serenity::builder::CreateEmbed::default();
+                    embed.fill_builder(&mut builder);
+                    builder
+                })
+                .collect();
+
+            builder.set_embeds(embeds);
+        }
+
+        for file in self.files {
+            builder.add_file(serenity::http::AttachmentType::Bytes {
+                data: file.data.into(),
+                filename: file.filename,
             });
         }
     }
 }
 
+/// A file attached to a [`CreateMessage`].
+#[derive(Clone, Debug, Serialize, Deserialize, Encode, Decode)]
+pub struct CreateAttachment {
+    filename: String,
+    data: Vec<u8>,
+}
+
 impl<T> From<T> for CreateMessage
 where
     T: AsRef<str>,
@@ -89,6 +140,12 @@ pub struct CreateEmbed {
     description: Option<String>,
     title: Option<String>,
     footer: Option<CreateEmbedFooter>,
+    fields: Vec<CreateEmbedField>,
+    author: Option<CreateEmbedAuthor>,
+    thumbnail: Option<String>,
+    image: Option<String>,
+    /// The timestamp in RFC 3339 format.
+    timestamp: Option<String>,
 }
 
 impl CreateEmbed {
@@ -152,6 +209,59 @@ impl CreateEmbed {
                 b
             });
         }
+
+        for field in self.fields {
+            builder.field(field.name, field.value, field.inline);
+        }
+
+        if let Some(author) = self.author {
+            builder.author(|b| {
+                author.fill_builder(b);
+                b
+            });
+        }
+
+        if let Some(thumbnail) = self.thumbnail {
+            builder.thumbnail(thumbnail);
+        }
+
+        if let Some(image) = self.image {
+            builder.image(image);
+        }
+
+        if let Some(timestamp) = self.timestamp {
+            builder.timestamp(timestamp);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Encode, Decode)]
+pub struct CreateEmbedField {
+    name: String,
+    value: String,
+    inline: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Encode, Decode)]
+pub struct CreateEmbedAuthor {
+    name: Option<String>,
+    icon_url: Option<String>,
+    url: Option<String>,
+}
+
+impl CreateEmbedAuthor {
+    pub fn fill_builder(self, builder: &mut serenity::builder::CreateEmbedAuthor) {
+        if let Some(name) = self.name {
+            builder.name(name);
+        }
+
+        if let Some(icon_url) = self.icon_url {
+            builder.icon_url(icon_url);
+        }
+
+        if let Some(url) = self.url {
+            builder.url(url);
+        }
     }
 }
 
@@ -198,6 +308,472 @@ impl CreateEmbedFooter {
     }
 }
 
+/// An owned builder for embeds. Unlike [`CreateEmbed::new`] the builder can
+/// be passed around and extended incrementally, e.g. when adding fields in
+/// a loop.
+///
+/// Discord rejects embeds exceeding its size limits. Converting the builder,
+/// e.g. by sending it, truncates the embed to fit (see [`Self::build`]).
+/// [`Self::try_build`] returns an [`EmbedLimitError`] instead, for callers
+/// that would rather split the content.
+///
+/// ```
+/// use robbot::builder::EmbedBuilder;
+///
+/// let embed = EmbedBuilder::new()
+///     .title("Status")
+///     .field("Uptime", "3 days", true)
+///     .fields_from(vec![("Guilds", "12", true), ("Users", "340", true)])
+///     .footer("robbot");
+/// ```
+#[derive(Clone, Debug, Default)]
+#[must_use]
+pub struct EmbedBuilder {
+    embed: CreateEmbed,
+}
+
+impl EmbedBuilder {
+    /// The maximum number of characters of the title.
+    pub const MAX_TITLE: usize = 256;
+    /// The maximum number of characters of the description.
+    pub const MAX_DESCRIPTION: usize = 4096;
+    /// The maximum number of fields.
+    pub const MAX_FIELDS: usize = 25;
+    /// The maximum number of characters of a field name.
+    pub const MAX_FIELD_NAME: usize = 256;
+    /// The maximum number of characters of a field value.
+    pub const MAX_FIELD_VALUE: usize = 1024;
+    /// The maximum number of characters of the footer text.
+    pub const MAX_FOOTER: usize = 2048;
+    /// The maximum number of characters of the author name.
+    pub const MAX_AUTHOR: usize = 256;
+    /// The maximum number of characters of all texts of the embed combined.
+    pub const MAX_TOTAL: usize = 6000;
+
+    /// Creates a new, empty `EmbedBuilder`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn title<T>(mut self, title: T) -> Self
+    where
+        T: ToString,
+    {
+        self.embed.title(title);
+        self
+    }
+
+    pub fn description<T>(mut self, description: T) -> Self
+    where
+        T: ToString,
+    {
+        self.embed.description(description);
+        self
+    }
+
+    pub fn color<T>(mut self, color: T) -> Self
+    where
+        T: Into<Color>,
+    {
+        self.embed.color(color);
+        self
+    }
+
+    /// Adds a field to the embed. Fields beyond [`Self::MAX_FIELDS`] are
+    /// collapsed into a single field when the embed is built.
+    pub fn field<N, V>(mut self, name: N, value: V, inline: bool) -> Self
+    where
+        N: ToString,
+        V: ToString,
+    {
+        self.embed.fields.push(CreateEmbedField {
+            name: name.to_string(),
+            value: value.to_string(),
+            inline,
+        });
+        self
+    }
+
+    /// Adds all `(name, value, inline)` fields of `iter` to the embed.
+    pub fn fields_from<I, N, V>(self, iter: I) -> Self
+    where
+        I: IntoIterator<Item = (N, V, bool)>,
+        N: ToString,
+        V: ToString,
+    {
+        iter.into_iter()
+            .fold(self, |builder, (name, value, inline)| {
+                builder.field(name, value, inline)
+            })
+    }
+
+    /// Sets the text of the footer.
+    pub fn footer<T>(mut self, text: T) -> Self
+    where
+        T: ToString,
+    {
+        self.embed
+            .footer
+            .get_or_insert_with(CreateEmbedFooter::default)
+            .text(text);
+        self
+    }
+
+    /// Sets the icon of the footer.
+    pub fn footer_icon<T>(mut self, icon_url: T) -> Self
+    where
+        T: ToString,
+    {
+        self.embed
+            .footer
+            .get_or_insert_with(CreateEmbedFooter::default)
+            .icon_url(icon_url);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.embed.timestamp = Some(timestamp.to_rfc3339());
+        self
+    }
+
+    /// Sets the name of the author.
+    pub fn author<T>(mut self, name: T) -> Self
+    where
+        T: ToString,
+    {
+        self.author_mut().name = Some(name.to_string());
+        self
+    }
+
+    /// Sets the icon of the author.
+    pub fn author_icon<T>(mut self, icon_url: T) -> Self
+    where
+        T: ToString,
+    {
+        self.author_mut().icon_url = Some(icon_url.to_string());
+        self
+    }
+
+    /// Sets the link of the author name.
+    pub fn author_url<T>(mut self, url: T) -> Self
+    where
+        T: ToString,
+    {
+        self.author_mut().url = Some(url.to_string());
+        self
+    }
+
+    pub fn thumbnail<T>(mut self, url: T) -> Self
+    where
+        T: ToString,
+    {
+        self.embed.thumbnail = Some(url.to_string());
+        self
+    }
+
+    /// Sets the large image shown below the description.
+    pub fn image<T>(mut self, url: T) -> Self
+    where
+        T: ToString,
+    {
+        self.embed.image = Some(url.to_string());
+        self
+    }
+
+    fn author_mut(&mut self) -> &mut CreateEmbedAuthor {
+        self.embed
+            .author
+            .get_or_insert_with(CreateEmbedAuthor::default)
+    }
+
+    /// Returns the number of characters counting towards [`Self::MAX_TOTAL`].
+    pub fn len(&self) -> usize {
+        let embed = &self.embed;
+
+        let len = |s: &Option<String>| s.as_deref().map_or(0, |s| s.chars().count());
+
+        len(&embed.title)
+            + len(&embed.description)
+            + embed
+                .fields
+                .iter()
+                .map(|field| field.name.chars().count() + field.value.chars().count())
+                .sum::<usize>()
+            + embed.footer.as_ref().map_or(0, |footer| len(&footer.text))
+            + embed.author.as_ref().map_or(0, |author| len(&author.name))
+    }
+
+    /// Returns `true` if the embed contains no text.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the embed if it is within all limits of Discord, otherwise the
+    /// first exceeded limit.
+    pub fn try_build(self) -> Result<CreateEmbed, EmbedLimitError> {
+        let embed = &self.embed;
+
+        let check = |s: Option<&str>, max, err: fn(usize) -> EmbedLimitError| match s
+            .map(|s| s.chars().count())
+        {
+            Some(len) if len > max => Err(err(len)),
+            _ => Ok(()),
+        };
+
+        check(
+            embed.title.as_deref(),
+            Self::MAX_TITLE,
+            EmbedLimitError::Title,
+        )?;
+        check(
+            embed.description.as_deref(),
+            Self::MAX_DESCRIPTION,
+            EmbedLimitError::Description,
+        )?;
+        check(
+            embed
+                .footer
+                .as_ref()
+                .and_then(|footer| footer.text.as_deref()),
+            Self::MAX_FOOTER,
+            EmbedLimitError::Footer,
+        )?;
+        check(
+            embed
+                .author
+                .as_ref()
+                .and_then(|author| author.name.as_deref()),
+            Self::MAX_AUTHOR,
+            EmbedLimitError::Author,
+        )?;
+
+        if embed.fields.len() > Self::MAX_FIELDS {
+            return Err(EmbedLimitError::Fields(embed.fields.len()));
+        }
+
+        for (index, field) in embed.fields.iter().enumerate() {
+            let len = field.name.chars().count();
+            if len > Self::MAX_FIELD_NAME {
+                return Err(EmbedLimitError::FieldName { index, len });
+            }
+
+            let len = field.value.chars().count();
+            if len > Self::MAX_FIELD_VALUE {
+                return Err(EmbedLimitError::FieldValue { index, len });
+            }
+        }
+
+        let len = self.len();
+        if len > Self::MAX_TOTAL {
+            return Err(EmbedLimitError::Total(len));
+        }
+
+        Ok(self.embed)
+    }
+
+    /// Returns the embed truncated to the limits of Discord:
+    /// - texts longer than their limit are cut and end with `…`
+    /// - fields beyond [`Self::MAX_FIELDS`] are replaced by a last field
+    ///   `…and N more`
+    /// - if the embed exceeds [`Self::MAX_TOTAL`] the description is
+    ///   shortened, then fields are collapsed from the end
+    pub fn build(mut self) -> CreateEmbed {
+        let embed = &mut self.embed;
+
+        if let Some(title) = &mut embed.title {
+            truncate(title, Self::MAX_TITLE);
+        }
+        if let Some(description) = &mut embed.description {
+            truncate(description, Self::MAX_DESCRIPTION);
+        }
+        if let Some(text) = embed
+            .footer
+            .as_mut()
+            .and_then(|footer| footer.text.as_mut())
+        {
+            truncate(text, Self::MAX_FOOTER);
+        }
+        if let Some(name) = embed
+            .author
+            .as_mut()
+            .and_then(|author| author.name.as_mut())
+        {
+            truncate(name, Self::MAX_AUTHOR);
+        }
+
+        for field in &mut embed.fields {
+            truncate(&mut field.name, Self::MAX_FIELD_NAME);
+            truncate(&mut field.value, Self::MAX_FIELD_VALUE);
+        }
+
+        let mut hidden = 0;
+        if embed.fields.len() > Self::MAX_FIELDS {
+            hidden = embed.fields.len() - (Self::MAX_FIELDS - 1);
+            embed.fields.truncate(Self::MAX_FIELDS - 1);
+            embed.fields.push(overflow_field(hidden));
+        }
+
+        let excess = self.len().saturating_sub(Self::MAX_TOTAL);
+        if excess > 0 {
+            if let Some(description) = &mut self.embed.description {
+                let len = description.chars().count();
+                match len.checked_sub(excess) {
+                    // Keep at least the ellipsis.
+                    Some(max) if max > 0 => truncate(description, max),
+                    _ => self.embed.description = None,
+                }
+            }
+        }
+
+        // Only the fields are left to shorten.
+        while self.len() > Self::MAX_TOTAL {
+            if hidden > 0 {
+                self.embed.fields.pop();
+            }
+
+            if self.embed.fields.pop().is_none() {
+                break;
+            }
+
+            hidden += 1;
+            self.embed.fields.push(overflow_field(hidden));
+        }
+
+        self.embed
+    }
+}
+
+/// Cuts `s` to at most `max` characters. Cut strings end with `…`.
+fn truncate(s: &mut String, max: usize) {
+    if let Some((index, _)) = s.char_indices().nth(max) {
+        // The ellipsis replaces the last kept character.
+        let end = s[..index]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(index, _)| index);
+
+        s.truncate(end);
+        s.push('…');
+    }
+}
+
+/// Returns the field replacing `hidden` fields that did not fit.
+fn overflow_field(hidden: usize) -> CreateEmbedField {
+    CreateEmbedField {
+        name: String::from("…"),
+        value: format!("…and {} more", hidden),
+        inline: false,
+    }
+}
+
+/// A limit of Discord exceeded by an embed, returned by
+/// [`EmbedBuilder::try_build`]. Lengths are in characters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Error)]
+pub enum EmbedLimitError {
+    #[error("title has {0} characters, the limit is 256")]
+    Title(usize),
+    #[error("description has {0} characters, the limit is 4096")]
+    Description(usize),
+    #[error("embed has {0} fields, the limit is 25")]
+    Fields(usize),
+    #[error("name of field {index} has {len} characters, the limit is 256")]
+    FieldName { index: usize, len: usize },
+    #[error("value of field {index} has {len} characters, the limit is 1024")]
+    FieldValue { index: usize, len: usize },
+    #[error("footer has {0} characters, the limit is 2048")]
+    Footer(usize),
+    #[error("author name has {0} characters, the limit is 256")]
+    Author(usize),
+    #[error("embed has {0} characters in total, the limit is 6000")]
+    Total(usize),
+}
+
+impl From<EmbedBuilder> for CreateEmbed {
+    fn from(builder: EmbedBuilder) -> Self {
+        builder.build()
+    }
+}
+
+impl From<EmbedBuilder> for CreateMessage {
+    fn from(builder: EmbedBuilder) -> Self {
+        MessageBuilder::new().embed(builder).into()
+    }
+}
+
+/// An owned builder for messages, see [`EmbedBuilder`].
+///
+/// ```
+/// use robbot::builder::{EmbedBuilder, MessageBuilder};
+///
+/// let message = MessageBuilder::new()
+///     .content("Results:")
+///     .embed(EmbedBuilder::new().title("Page 1"))
+///     .embed(EmbedBuilder::new().title("Page 2"));
+/// ```
+#[derive(Clone, Debug, Default)]
+#[must_use]
+pub struct MessageBuilder {
+    message: CreateMessage,
+}
+
+impl MessageBuilder {
+    /// Creates a new, empty `MessageBuilder`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn content<T>(mut self, content: T) -> Self
+    where
+        T: ToString,
+    {
+        self.message.content(content);
+        self
+    }
+
+    pub fn reference_message<T>(mut self, reference: T) -> Self
+    where
+        T: Into<MessageReference>,
+    {
+        self.message.reference_message(reference);
+        self
+    }
+
+    /// Adds an embed to the message. Discord accepts up to 10 embeds per
+    /// message.
+    pub fn embed<T>(mut self, embed: T) -> Self
+    where
+        T: Into<CreateEmbed>,
+    {
+        self.message.add_embed(embed);
+        self
+    }
+
+    /// Adds all embeds of `iter` to the message.
+    pub fn embeds<I, T>(self, iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<CreateEmbed>,
+    {
+        iter.into_iter().fold(self, Self::embed)
+    }
+
+    /// Attaches a file with the name `filename` and the contents `data` to
+    /// the message. Discord accepts up to 10 files per message.
+    pub fn attachment<T>(mut self, filename: T, data: Vec<u8>) -> Self
+    where
+        T: ToString,
+    {
+        self.message.attachment(filename, data);
+        self
+    }
+}
+
+impl From<MessageBuilder> for CreateMessage {
+    fn from(builder: MessageBuilder) -> Self {
+        builder.message
+    }
+}
+
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct EditMember {
     deafen: Option<bool>,
@@ -206,6 +782,9 @@ pub struct EditMember {
     roles: Option<Vec<RoleId>>,
     voice_channel: Option<ChannelId>,
     voice_disconnect: Option<bool>,
+    #[serde(with = "crate::remote::serde_bridge::datetime::option")]
+    timeout_until: Option<DateTime<Utc>>,
+    remove_timeout: Option<bool>,
 }
 
 impl EditMember {
@@ -257,6 +836,21 @@ impl EditMember {
         self
     }
 
+    /// Times the member out until `until`. Discord rejects timeouts longer
+    /// than 28 days.
+    pub fn timeout_until(&mut self, until: DateTime<Utc>) -> &mut Self {
+        self.remove_timeout = None;
+        self.timeout_until = Some(until);
+        self
+    }
+
+    /// Removes the timeout of the member, if there is one.
+    pub fn remove_timeout(&mut self) -> &mut Self {
+        self.timeout_until = None;
+        self.remove_timeout = Some(true);
+        self
+    }
+
     pub fn fill_builder(self, builder: &mut serenity::builder::EditMember) {
         if let Some(deafen) = self.deafen {
             builder.deafen(deafen);
@@ -286,12 +880,21 @@ impl EditMember {
         if self.voice_disconnect.is_some() {
             builder.disconnect_member();
         }
+
+        if let Some(until) = self.timeout_until {
+            builder.disable_communication_until_datetime(until);
+        }
+
+        if self.remove_timeout.is_some() {
+            builder.enable_communication();
+        }
     }
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct EditMessage {
     content: Option<String>,
+    embeds: Option<Vec<CreateEmbed>>,
 }
 
 impl EditMessage {
@@ -312,9 +915,307 @@ impl EditMessage {
         self
     }
 
+    /// Sets the embed of the message, replacing all existing embeds.
+    pub fn embed<T>(&mut self, embed: T) -> &mut Self
+    where
+        T: Into<CreateEmbed>,
+    {
+        self.embeds = Some(vec![embed.into()]);
+        self
+    }
+
     pub(crate) fn fill_builder(self, builder: &mut serenity::builder::EditMessage) {
         if let Some(content) = self.content {
             builder.content(content);
         }
+
+        if let Some(embeds) = self.embeds {
+            let embeds = embeds
+                .into_iter()
+                .map(|embed| {
+                    let mut builder = serenity::builder::CreateEmbed::default();
+                    embed.fill_builder(&mut builder);
+                    builder
+                })
+                .collect();
+
+            builder.set_embeds(embeds);
+        }
+    }
+}
+
+impl From<EmbedBuilder> for EditMessage {
+    fn from(builder: EmbedBuilder) -> Self {
+        Self::new(|m| {
+            m.embed(builder);
+        })
+    }
+}
+
+/// [`CreateChannel`] is used to construct a new guild channel or category.
+#[derive(Clone, Debug, Serialize, Deserialize, Encode, Decode)]
+pub struct CreateChannel {
+    name: String,
+    kind: ChannelKind,
+    topic: Option<String>,
+    category: Option<ChannelId>,
+    nsfw: Option<bool>,
+    position: Option<u32>,
+    permission_overwrites: Vec<PermissionOverwrite>,
+}
+
+impl CreateChannel {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: FnOnce(&mut Self),
+    {
+        let mut builder = Self::default();
+        f(&mut builder);
+        builder
+    }
+
+    pub fn name<T>(&mut self, name: T) -> &mut Self
+    where
+        T: ToString,
+    {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Sets the kind of the channel. Defaults to [`ChannelKind::Text`].
+    pub fn kind(&mut self, kind: ChannelKind) -> &mut Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn topic<T>(&mut self, topic: T) -> &mut Self
+    where
+        T: ToString,
+    {
+        self.topic = Some(topic.to_string());
+        self
+    }
+
+    /// Sets the category the channel is created in.
+    pub fn category(&mut self, category: ChannelId) -> &mut Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn nsfw(&mut self, nsfw: bool) -> &mut Self {
+        self.nsfw = Some(nsfw);
+        self
+    }
+
+    pub fn position(&mut self, position: u32) -> &mut Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Adds a permission overwrite for a role or member. Use the id of the guild
+    /// as the role id to target `@everyone`.
+    pub fn permission_overwrite(
+        &mut self,
+        kind: PermissionOverwriteKind,
+        allow: Permissions,
+        deny: Permissions,
+    ) -> &mut Self {
+        self.permission_overwrites
+            .push(PermissionOverwrite { allow, deny, kind });
+        self
+    }
+
+    pub fn fill_builder(self, builder: &mut serenity::builder::CreateChannel) {
+        builder.name(self.name);
+        builder.kind(self.kind.into());
+
+        if let Some(topic) = self.topic {
+            builder.topic(topic);
+        }
+
+        if let Some(category) = self.category {
+            builder.category(category);
+        }
+
+        if let Some(nsfw) = self.nsfw {
+            builder.nsfw(nsfw);
+        }
+
+        if let Some(position) = self.position {
+            builder.position(position);
+        }
+
+        if !self.permission_overwrites.is_empty() {
+            builder.permissions(
+                self.permission_overwrites
+                    .into_iter()
+                    .map(serenity::model::channel::PermissionOverwrite::from),
+            );
+        }
+    }
+}
+
+impl Default for CreateChannel {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            kind: ChannelKind::Text,
+            topic: None,
+            category: None,
+            nsfw: None,
+            position: None,
+            permission_overwrites: Vec::new(),
+        }
+    }
+}
+
+impl<T> From<T> for CreateChannel
+where
+    T: AsRef<str>,
+{
+    fn from(t: T) -> Self {
+        let mut builder = Self::default();
+        builder.name(t.as_ref());
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{truncate, EmbedBuilder, EmbedLimitError};
+
+    fn text(len: usize) -> String {
+        "a".repeat(len)
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut s = String::from("Hello");
+        truncate(&mut s, 5);
+        assert_eq!(s, "Hello");
+
+        truncate(&mut s, 4);
+        assert_eq!(s, "Hel…");
+
+        let mut s = String::from("äöüäöü");
+        truncate(&mut s, 3);
+        assert_eq!(s, "äö…");
+    }
+
+    #[test]
+    fn test_embed_builder_try_build() {
+        assert!(EmbedBuilder::new()
+            .title(text(256))
+            .description(text(4096))
+            .try_build()
+            .is_ok());
+
+        assert_eq!(
+            EmbedBuilder::new()
+                .title(text(257))
+                .try_build()
+                .unwrap_err(),
+            EmbedLimitError::Title(257)
+        );
+        assert_eq!(
+            EmbedBuilder::new()
+                .description(text(4097))
+                .try_build()
+                .unwrap_err(),
+            EmbedLimitError::Description(4097)
+        );
+        assert_eq!(
+            EmbedBuilder::new()
+                .fields_from((0..26).map(|i| (i, i, true)))
+                .try_build()
+                .unwrap_err(),
+            EmbedLimitError::Fields(26)
+        );
+        assert_eq!(
+            EmbedBuilder::new()
+                .field("a", "b", false)
+                .field(text(257), "b", false)
+                .try_build()
+                .unwrap_err(),
+            EmbedLimitError::FieldName { index: 1, len: 257 }
+        );
+        assert_eq!(
+            EmbedBuilder::new()
+                .field("a", text(1025), false)
+                .try_build()
+                .unwrap_err(),
+            EmbedLimitError::FieldValue {
+                index: 0,
+                len: 1025
+            }
+        );
+        assert_eq!(
+            EmbedBuilder::new()
+                .footer(text(2049))
+                .try_build()
+                .unwrap_err(),
+            EmbedLimitError::Footer(2049)
+        );
+        assert_eq!(
+            EmbedBuilder::new()
+                .author(text(257))
+                .try_build()
+                .unwrap_err(),
+            EmbedLimitError::Author(257)
+        );
+        assert_eq!(
+            EmbedBuilder::new()
+                .description(text(4000))
+                .field("a", text(1000), false)
+                .field("b", text(1000), false)
+                .try_build()
+                .unwrap_err(),
+            EmbedLimitError::Total(6002)
+        );
+    }
+
+    #[test]
+    fn test_embed_builder_build() {
+        let embed = EmbedBuilder::new()
+            .title(text(300))
+            .field("a", text(2000), false)
+            .build();
+        assert_eq!(embed.title.unwrap().chars().count(), 256);
+        assert_eq!(embed.fields[0].value.chars().count(), 1024);
+        assert!(embed.fields[0].value.ends_with('…'));
+
+        let embed = EmbedBuilder::new()
+            .fields_from((0..30).map(|i| (i, i, true)))
+            .build();
+        assert_eq!(embed.fields.len(), 25);
+        assert_eq!(embed.fields[23].name, "23");
+        assert_eq!(embed.fields[24].value, "…and 6 more");
+    }
+
+    #[test]
+    fn test_embed_builder_build_total() {
+        let builder = EmbedBuilder::new()
+            .description(text(4000))
+            .field("a", text(1000), false)
+            .field("b", text(1000), false)
+            .build();
+        assert_eq!(builder.description.unwrap().chars().count(), 3998);
+        assert_eq!(builder.fields.len(), 2);
+
+        // Without a description the fields are collapsed from the end.
+        let builder = EmbedBuilder::new()
+            .fields_from((0..10).map(|i| (i, text(1000), false)))
+            .build();
+        assert_eq!(builder.fields.len(), 6);
+        assert_eq!(builder.fields[5].value, "…and 5 more");
+
+        let embed = EmbedBuilder::new()
+            .description(text(4096))
+            .fields_from((0..30).map(|i| (i, text(1024), false)));
+        let embed = EmbedBuilder {
+            embed: embed.build(),
+        };
+        assert!(embed.len() <= EmbedBuilder::MAX_TOTAL);
+        assert!(embed.try_build().is_ok());
     }
 }
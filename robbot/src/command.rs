@@ -1,3 +1,6 @@
+use crate::model::permissions::Permissions;
+
+use std::fmt::{self, Display, Formatter};
 use std::{borrow::Borrow, collections::HashSet, hash::Hash};
 
 pub trait Command: Sized + Hash + Eq + Borrow<str> {
@@ -31,6 +34,225 @@ pub trait Command: Sized + Hash + Eq + Borrow<str> {
     /// Note: User and role permissions are on a per guild basis, meaning that
     /// only guild-only commands are checked.
     fn permissions(&self) -> &[String];
+    /// Returns the Discord permissions the bot needs to run the
+    /// command. They are checked in the invoking channel before
+    /// the command runs.
+    /// Note: Only guild-only commands are checked.
+    fn bot_permissions(&self) -> Permissions;
+    /// Whether a successful invocation is acknowledged with a reaction
+    /// instead of a message if the command did not respond on its own.
+    fn ack_only(&self) -> bool;
+    /// Returns the values accepted by arguments of the command. They are
+    /// listed in the help command and checked before the command runs.
+    fn arg_choices(&self) -> &[ArgChoices];
     fn sub_commands(&self) -> &HashSet<Self>;
     fn executor(&self) -> Option<&Self::Executor>;
 }
+
+/// The values accepted by a single argument of a [`Command`]. The argument is
+/// identified by its name in the usage of the command, e.g. `level` for
+/// `<Target> <Level>`, ignoring case. Underscores in the name match spaces, e.g.
+/// `kick_days` for `<Kick Days>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArgChoices {
+    pub name: String,
+    pub choices: Vec<String>,
+}
+
+impl ArgChoices {
+    pub fn new<I, T>(name: impl ToString, choices: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: ToString,
+    {
+        Self {
+            name: name.to_string(),
+            choices: choices.into_iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    /// Returns `true` if `value` is one of the choices, ignoring case.
+    pub fn contains(&self, value: &str) -> bool {
+        self.choices.iter().any(|c| c.eq_ignore_ascii_case(value))
+    }
+}
+
+/// An argument given to a [`Command`] that is not one of its [`ArgChoices`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidChoice<'a> {
+    pub choices: &'a ArgChoices,
+    pub value: &'a str,
+}
+
+impl Display for InvalidChoice<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` is not a valid value for `{}`, expected one of `{}`",
+            self.value,
+            self.choices.name,
+            self.choices.choices.join("`, `")
+        )
+    }
+}
+
+/// Checks the positional arguments `args` of `command` against its
+/// [`ArgChoices`]. Arguments are matched by their position in the usage of
+/// the command. Only arguments before the first flag (`--flag`) are checked
+/// and missing arguments are left to the command.
+pub fn check_choices<'a, T>(command: &'a T, args: &'a [String]) -> Result<(), InvalidChoice<'a>>
+where
+    T: Command,
+{
+    let positional = args
+        .iter()
+        .position(|arg| arg.starts_with("--"))
+        .unwrap_or(args.len());
+
+    for choices in command.arg_choices() {
+        let value = usage_position(command.usage(), &choices.name)
+            .filter(|index| *index < positional)
+            .map(|index| args[index].as_str());
+
+        if let Some(value) = value {
+            if !choices.contains(value) {
+                return Err(InvalidChoice { choices, value });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the [`ArgChoices`] of the first argument of `parent` if `name` is one
+/// of them. A sub command with that name shadows the choice: the sub command
+/// always runs instead of `parent` with the argument. Commands without an
+/// executor take no arguments and cannot be shadowed.
+pub fn shadowed_choice<'a, T>(parent: &'a T, name: &str) -> Option<&'a ArgChoices>
+where
+    T: Command,
+{
+    parent.executor()?;
+
+    parent.arg_choices().iter().find(|choices| {
+        usage_position(parent.usage(), &choices.name) == Some(0) && choices.contains(name)
+    })
+}
+
+/// Returns the position of the argument `name` in `usage`. Every `<>` or `[]`
+/// group is one argument, quotes around the name are ignored.
+fn usage_position(usage: &str, name: &str) -> Option<usize> {
+    let mut position = 0;
+    let mut rest = usage;
+
+    while let Some(start) = rest.find(['<', '[']) {
+        let close = match &rest[start..start + 1] {
+            "<" => '>',
+            _ => ']',
+        };
+
+        let end = start + rest[start..].find(close)?;
+        let arg = rest[start + 1..end].trim_matches('"');
+
+        if arg.eq_ignore_ascii_case(&name.replace('_', " ")) {
+            return Some(position);
+        }
+
+        position += 1;
+        rest = &rest[end + 1..];
+    }
+
+    None
+}
+
+/// A permission node checked by the `permissions` of a [`Command`], e.g.
+/// `permissions.manage`. Nodes are usually declared using [`permission_nodes!`].
+///
+/// [`permission_nodes!`]: crate::permission_nodes
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PermissionNode(&'static str);
+
+impl PermissionNode {
+    pub const fn new(node: &'static str) -> Self {
+        Self(node)
+    }
+
+    pub const fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl AsRef<str> for PermissionNode {
+    fn as_ref(&self) -> &str {
+        self.0
+    }
+}
+
+impl Display for PermissionNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+/// Declares typed [`PermissionNode`] constants within a namespace, usually the
+/// name of the module. Also declares `NODES`, a list of all declared nodes used
+/// to register the nodes of a module.
+///
+/// # Examples
+///
+/// ```
+/// mod nodes {
+///     robbot::permission_nodes! {
+///         "guildsync" => {
+///             MANAGE = "manage",
+///             SYNC = "sync",
+///         }
+///     }
+/// }
+///
+/// assert_eq!(nodes::MANAGE.as_str(), "guildsync.manage");
+/// assert_eq!(nodes::NODES, [nodes::MANAGE, nodes::SYNC]);
+/// ```
+#[macro_export]
+macro_rules! permission_nodes {
+    ($namespace:literal => { $($name:ident = $node:literal),* $(,)? }) => {
+        $(
+            pub const $name: $crate::command::PermissionNode =
+                $crate::command::PermissionNode::new(concat!($namespace, ".", $node));
+        )*
+
+        /// All permission nodes declared in this namespace.
+        pub const NODES: &[$crate::command::PermissionNode] = &[$($name),*];
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{usage_position, ArgChoices};
+
+    #[test]
+    fn test_usage_position() {
+        assert_eq!(usage_position("<Target> <Level>", "level"), Some(1));
+        assert_eq!(usage_position("<Target> <Level>", "target"), Some(0));
+        assert_eq!(
+            usage_position("<Remind Days> [Kick Days]", "kick_days"),
+            Some(1)
+        );
+        assert_eq!(
+            usage_position("<\"Question\"> <\"Option\">...", "option"),
+            Some(1)
+        );
+        assert_eq!(usage_position("<Category> <Days>d|off", "days"), Some(1));
+        assert_eq!(usage_position("<Target> <Level>", "user"), None);
+        assert_eq!(usage_position("", "level"), None);
+    }
+
+    #[test]
+    fn test_arg_choices_contains() {
+        let choices = ArgChoices::new("level", ["error", "warn"]);
+
+        assert!(choices.contains("warn"));
+        assert!(choices.contains("WARN"));
+        assert!(!choices.contains("info"));
+    }
+}
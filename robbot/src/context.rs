@@ -1,17 +1,40 @@
 use std::fmt::{self, Debug, Formatter};
 
-use crate::builder::{CreateMessage, EditMember, EditMessage};
-use crate::model::channel::Message;
+use crate::arguments::MessageRef;
+use crate::builder::{CreateChannel, CreateMessage, EditMember, EditMessage};
+use crate::model::channel::{
+    CategoryChannel, ChannelKind, GuildChannel, Message, MessageReaction, PermissionOverwrite,
+};
 
+use crate::model::emoji::Emoji;
 use crate::model::guild::Member;
-use crate::model::id::{ChannelId, GuildId, MessageId, UserId};
+use crate::model::id::{ChannelId, GuildId, MessageId, RoleId, UserId};
+use crate::model::permissions::Permissions;
 
+use chrono::{DateTime, Duration, Utc};
 use futures::{Stream, StreamExt};
 use serenity::model::channel::ReactionType;
 
 use async_trait::async_trait;
 use thiserror::Error;
 
+/// The maximum number of messages deleted by a single bulk delete request.
+const BULK_DELETE_MAX: usize = 100;
+
+/// The maximum number of messages returned by a single request for the
+/// message history of a channel.
+const MESSAGES_MAX: usize = 100;
+
+/// The maximum number of characters in the content of a message.
+pub const MESSAGE_MAX_LEN: usize = 2000;
+
+/// Returns the age from which messages can no longer be deleted in bulk.
+/// Discord rejects messages older than 14 days, a margin of one minute
+/// accounts for clock drift.
+fn bulk_delete_max_age() -> Duration {
+    Duration::days(14) - Duration::minutes(1)
+}
+
 #[async_trait]
 pub trait ContextOld {
     type Error;
@@ -87,6 +110,21 @@ pub trait ContextOld {
 pub enum Error {
     #[error(transparent)]
     Raw(#[from] serenity::Error),
+    /// The content does not fit into a single message. Messages can only be
+    /// edited into a single message, content to be split into multiple
+    /// messages must be sent instead.
+    #[error("cannot edit a message to {len} characters, the maximum is {MESSAGE_MAX_LEN}")]
+    ContentTooLong { len: usize },
+    /// The emoji of a reaction does not exist or cannot be used by the bot.
+    #[error("unknown emoji")]
+    UnknownEmoji,
+    /// The bot is missing a permission required for the request.
+    #[error("missing permissions")]
+    MissingPermissions,
+    /// A [`MessageRef`] without a channel was given. Use
+    /// [`MessageRef::resolve`] to fill in the channel.
+    #[error("the message reference has no channel")]
+    UnresolvedMessageRef,
 }
 
 #[derive(Clone)]
@@ -175,6 +213,51 @@ where
         Ok(msg.into())
     }
 
+    /// Sends a direct message to a user. Returns `Ok(None)` if the user does not
+    /// accept direct messages from the bot, e.g. because they disabled direct
+    /// messages from server members.
+    pub async fn direct_message<M>(
+        &self,
+        user_id: UserId,
+        message: M,
+    ) -> Result<Option<Message>, Error>
+    where
+        M: Into<CreateMessage>,
+    {
+        let builder = message.into();
+
+        let channel = serenity::model::id::UserId(user_id.0)
+            .create_dm_channel(&self.raw_ctx)
+            .await?;
+
+        let res = channel
+            .id
+            .send_message(&self.raw_ctx, |m| {
+                builder.fill_builder(m);
+                m
+            })
+            .await;
+
+        match res {
+            Ok(msg) => Ok(Some(msg.into())),
+            Err(err) if is_cannot_message_user(&err) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Fetches a message using the REST API.
+    pub async fn fetch_message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> Result<Message, Error> {
+        let message = serenity::model::id::ChannelId(channel_id.0)
+            .message(&self.raw_ctx, message_id)
+            .await?;
+
+        Ok(message.into())
+    }
+
     pub async fn delete_message(
         &self,
         channel_id: ChannelId,
@@ -187,6 +270,61 @@ where
         Ok(())
     }
 
+    /// Deletes multiple messages in a channel. Messages are deleted in batches
+    /// of up to 100 messages. Messages older than 14 days cannot be deleted
+    /// in bulk and are deleted one by one instead.
+    pub async fn bulk_delete_messages<I>(
+        &self,
+        channel_id: ChannelId,
+        message_ids: I,
+    ) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = MessageId>,
+    {
+        let deleter = ChannelDeleter {
+            raw_ctx: &self.raw_ctx,
+            channel_id,
+        };
+
+        delete_messages(&deleter, message_ids.into_iter().collect(), Utc::now()).await
+    }
+
+    /// Returns up to `limit` of the most recent messages in a channel, newest
+    /// first.
+    pub async fn messages(
+        &self,
+        channel_id: ChannelId,
+        limit: usize,
+    ) -> Result<Vec<Message>, Error> {
+        let mut messages: Vec<Message> = Vec::with_capacity(limit);
+
+        while messages.len() < limit {
+            let page_size = (limit - messages.len()).min(MESSAGES_MAX);
+            let before = messages.last().map(|message| message.id);
+
+            let page = serenity::model::id::ChannelId(channel_id.0)
+                .messages(&self.raw_ctx, |b| {
+                    b.limit(page_size as u64);
+
+                    if let Some(before) = before {
+                        b.before(before);
+                    }
+
+                    b
+                })
+                .await?;
+
+            let done = page.len() < page_size;
+            messages.extend(page.into_iter().map(Message::from));
+
+            if done {
+                break;
+            }
+        }
+
+        Ok(messages)
+    }
+
     pub async fn pin_message(
         &self,
         channel_id: ChannelId,
@@ -251,6 +389,90 @@ where
         Ok(())
     }
 
+    /// Deletes a reaction from a message. Deletes the reaction of the bot if
+    /// `user_id` is `None`.
+    pub async fn delete_reaction<R>(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        user_id: Option<UserId>,
+        reaction: R,
+    ) -> Result<(), Error>
+    where
+        R: Into<ReactionType>,
+    {
+        let reaction = reaction.into();
+
+        self.raw_ctx
+            .http
+            .delete_reaction(
+                channel_id.0,
+                message_id.0,
+                user_id.map(|user_id| user_id.0),
+                &reaction,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Adds the reaction `emoji` of the bot to a message.
+    ///
+    /// Returns [`Error::UnknownEmoji`] if the emoji cannot be used and
+    /// [`Error::MissingPermissions`] if the bot cannot add reactions.
+    pub async fn react_to(&self, message: MessageRef, emoji: Emoji) -> Result<(), Error> {
+        let (channel_id, message_id) = channel_message(&message)?;
+
+        self.raw_ctx
+            .http
+            .create_reaction(channel_id.0, message_id.0, &emoji.into())
+            .await
+            .map_err(reaction_error)
+    }
+
+    /// Removes the reaction `emoji` of `user_id` from a message. Removes the
+    /// reaction of the bot if `user_id` is `None`. Errors are mapped like
+    /// [`Self::react_to`].
+    pub async fn remove_reaction(
+        &self,
+        message: MessageRef,
+        emoji: Emoji,
+        user_id: Option<UserId>,
+    ) -> Result<(), Error> {
+        let (channel_id, message_id) = channel_message(&message)?;
+
+        self.raw_ctx
+            .http
+            .delete_reaction(
+                channel_id.0,
+                message_id.0,
+                user_id.map(|user_id| user_id.0),
+                &emoji.into(),
+            )
+            .await
+            .map_err(reaction_error)
+    }
+
+    /// Removes all reactions from a message. Requires the Manage Messages
+    /// permission, returns [`Error::MissingPermissions`] otherwise.
+    pub async fn clear_reactions(&self, message: MessageRef) -> Result<(), Error> {
+        let (channel_id, message_id) = channel_message(&message)?;
+
+        self.raw_ctx
+            .http
+            .delete_message_reactions(channel_id.0, message_id.0)
+            .await
+            .map_err(reaction_error)
+    }
+
+    /// Returns the emojis the bot reacted with on a message.
+    pub async fn own_reactions(&self, message: MessageRef) -> Result<Vec<Emoji>, Error> {
+        let (channel_id, message_id) = channel_message(&message)?;
+        let message = self.fetch_message(channel_id, message_id).await?;
+
+        Ok(own_reactions(message.reactions))
+    }
+
     pub async fn member(&self, guild_id: GuildId, user_id: UserId) -> Result<Member, Error> {
         let member = serenity::model::id::GuildId(guild_id.0)
             .member(&self.raw_ctx, user_id)
@@ -280,6 +502,41 @@ where
         Ok(member.into())
     }
 
+    /// Times a member of a guild out until `until`, preventing them from
+    /// sending messages, reacting and speaking. Timeouts end on their own and
+    /// are limited to 28 days.
+    pub async fn timeout_member(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        until: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        self.edit_member(
+            guild_id,
+            user_id,
+            EditMember::new(|m| {
+                m.timeout_until(until);
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes the timeout of a member of a guild, if there is one.
+    pub async fn remove_timeout(&self, guild_id: GuildId, user_id: UserId) -> Result<(), Error> {
+        self.edit_member(
+            guild_id,
+            user_id,
+            EditMember::new(|m| {
+                m.remove_timeout();
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
     /// Kicks a member from a guild with an optional reason.
     pub async fn kick(
         &self,
@@ -323,6 +580,156 @@ where
         Ok(())
     }
 
+    /// Creates a new channel in a guild.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use robbot::context::Context;
+    /// #
+    /// use robbot::builder::CreateChannel;
+    /// use robbot::model::channel::PermissionOverwriteKind;
+    /// use robbot::model::id::{GuildId, RoleId};
+    /// use robbot::model::permissions::Permissions;
+    ///
+    /// # async fn run(ctx: &Context<(), ()>) -> Result<(), Box<dyn std::error::Error>> {
+    /// #
+    /// let guild_id = GuildId(1234);
+    ///
+    /// // A read-only channel. The @everyone role shares the id of the guild.
+    /// ctx.create_channel(guild_id, CreateChannel::new(|c| {
+    ///     c.name("announcements");
+    ///     c.permission_overwrite(
+    ///         PermissionOverwriteKind::Role(RoleId(guild_id.0)),
+    ///         Permissions::empty(),
+    ///         Permissions::SEND_MESSAGES,
+    ///     );
+    /// })).await?;
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create_channel<B>(
+        &self,
+        guild_id: GuildId,
+        builder: B,
+    ) -> Result<GuildChannel, Error>
+    where
+        B: Into<CreateChannel>,
+    {
+        let builder = builder.into();
+
+        let channel = serenity::model::id::GuildId(guild_id.0)
+            .create_channel(&self.raw_ctx, |c| {
+                builder.fill_builder(c);
+                c
+            })
+            .await?;
+
+        Ok(channel.into())
+    }
+
+    /// Creates a new category in a guild. The kind of the `builder` is always
+    /// set to [`ChannelKind::Category`].
+    pub async fn create_category<B>(
+        &self,
+        guild_id: GuildId,
+        builder: B,
+    ) -> Result<CategoryChannel, Error>
+    where
+        B: Into<CreateChannel>,
+    {
+        let mut builder = builder.into();
+        builder.kind(ChannelKind::Category);
+
+        let channel = self.create_channel(guild_id, builder).await?;
+
+        Ok(CategoryChannel {
+            id: channel.id,
+            guild_id: channel.guild_id,
+            category_id: channel.category_id,
+            position: channel.position,
+            kind: channel.kind,
+            name: channel.name,
+            nsfw: channel.nsfw,
+            permission_overwrites: channel.permission_overwrites,
+        })
+    }
+
+    /// Returns all channels of a guild.
+    pub async fn channels(&self, guild_id: GuildId) -> Result<Vec<GuildChannel>, Error> {
+        let channels = serenity::model::id::GuildId(guild_id.0)
+            .channels(&self.raw_ctx)
+            .await?;
+
+        Ok(channels.into_values().map(Into::into).collect())
+    }
+
+    /// Creates or replaces the permission overwrite for the target of
+    /// `overwrite` in a channel.
+    pub async fn create_permission(
+        &self,
+        channel_id: ChannelId,
+        overwrite: PermissionOverwrite,
+    ) -> Result<(), Error> {
+        serenity::model::id::ChannelId(channel_id.0)
+            .create_permission(&self.raw_ctx, &overwrite.into())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Creates a new role with the given `permissions` in a guild.
+    pub async fn create_role<N>(
+        &self,
+        guild_id: GuildId,
+        name: N,
+        permissions: Permissions,
+    ) -> Result<RoleId, Error>
+    where
+        N: ToString,
+    {
+        let role = serenity::model::id::GuildId(guild_id.0)
+            .create_role(&self.raw_ctx, |r| {
+                r.name(name);
+                r.permissions(permissions.into());
+                r
+            })
+            .await?;
+
+        Ok(role.id.into())
+    }
+
+    /// Adds a role to a member of a guild.
+    pub async fn add_member_role(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+    ) -> Result<(), Error> {
+        self.raw_ctx
+            .http
+            .add_member_role(guild_id.0, user_id.0, role_id.0)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Removes a role from a member of a guild.
+    pub async fn remove_member_role(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+    ) -> Result<(), Error> {
+        self.raw_ctx
+            .http
+            .remove_member_role(guild_id.0, user_id.0, role_id.0)
+            .await?;
+
+        Ok(())
+    }
+
     pub fn guild(&self, guild_id: GuildId) -> GuildContext<'_, T, S> {
         GuildContext {
             ctx: self,
@@ -358,6 +765,18 @@ where
 
         self.create_reaction(message_id, channel_id, reaction).await
     }
+
+    /// Fetches the content of the message of the event using the REST API.
+    /// Used when the message arrived without content because the message
+    /// content intent is not enabled. The content is only returned for
+    /// direct messages and messages mentioning the bot in that case.
+    pub async fn fetch_message_content(&self) -> Result<String, Error> {
+        let channel_id = *self.event.as_ref();
+        let message_id = *self.event.as_ref();
+
+        let message = self.fetch_message(channel_id, message_id).await?;
+        Ok(message.content)
+    }
 }
 
 #[derive(Debug)]
@@ -411,6 +830,51 @@ where
         self.ctx.unban(self.guild_id, user_id).await
     }
 
+    pub async fn timeout_member(&self, user_id: UserId, until: DateTime<Utc>) -> Result<(), Error> {
+        self.ctx.timeout_member(self.guild_id, user_id, until).await
+    }
+
+    pub async fn remove_timeout(&self, user_id: UserId) -> Result<(), Error> {
+        self.ctx.remove_timeout(self.guild_id, user_id).await
+    }
+
+    pub async fn create_channel<B>(&self, builder: B) -> Result<GuildChannel, Error>
+    where
+        B: Into<CreateChannel>,
+    {
+        self.ctx.create_channel(self.guild_id, builder).await
+    }
+
+    pub async fn create_category<B>(&self, builder: B) -> Result<CategoryChannel, Error>
+    where
+        B: Into<CreateChannel>,
+    {
+        self.ctx.create_category(self.guild_id, builder).await
+    }
+
+    pub async fn channels(&self) -> Result<Vec<GuildChannel>, Error> {
+        self.ctx.channels(self.guild_id).await
+    }
+
+    pub async fn create_role<N>(&self, name: N, permissions: Permissions) -> Result<RoleId, Error>
+    where
+        N: ToString,
+    {
+        self.ctx.create_role(self.guild_id, name, permissions).await
+    }
+
+    pub async fn add_member_role(&self, user_id: UserId, role_id: RoleId) -> Result<(), Error> {
+        self.ctx
+            .add_member_role(self.guild_id, user_id, role_id)
+            .await
+    }
+
+    pub async fn remove_member_role(&self, user_id: UserId, role_id: RoleId) -> Result<(), Error> {
+        self.ctx
+            .remove_member_role(self.guild_id, user_id, role_id)
+            .await
+    }
+
     pub async fn move_member(
         &self,
         user_id: UserId,
@@ -439,7 +903,7 @@ where
     S: Send + Sync,
 {
     fn as_ref(&self) -> &Context<T, S> {
-        &self.ctx
+        self.ctx
     }
 }
 
@@ -455,3 +919,332 @@ where
             .finish()
     }
 }
+
+/// Deletes messages of a single channel, see
+/// [`Context::bulk_delete_messages`].
+#[async_trait]
+trait MessageDeleter {
+    async fn delete_one(&self, message_id: MessageId) -> Result<(), Error>;
+
+    /// Deletes 2 to 100 messages at once.
+    async fn delete_bulk(&self, message_ids: &[MessageId]) -> Result<(), Error>;
+}
+
+struct ChannelDeleter<'a> {
+    raw_ctx: &'a serenity::client::Context,
+    channel_id: ChannelId,
+}
+
+#[async_trait]
+impl<'a> MessageDeleter for ChannelDeleter<'a> {
+    async fn delete_one(&self, message_id: MessageId) -> Result<(), Error> {
+        serenity::model::id::ChannelId(self.channel_id.0)
+            .delete_message(self.raw_ctx, message_id)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_bulk(&self, message_ids: &[MessageId]) -> Result<(), Error> {
+        let message_ids: Vec<serenity::model::id::MessageId> =
+            message_ids.iter().map(|id| (*id).into()).collect();
+
+        serenity::model::id::ChannelId(self.channel_id.0)
+            .delete_messages(self.raw_ctx, message_ids)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Splits `message_ids` into batches for bulk deletion and messages that must
+/// be deleted individually because they are too old for bulk deletion.
+/// Duplicate ids are removed.
+fn split_bulk_delete(
+    mut message_ids: Vec<MessageId>,
+    now: DateTime<Utc>,
+) -> (Vec<Vec<MessageId>>, Vec<MessageId>) {
+    message_ids.sort_unstable();
+    message_ids.dedup();
+
+    let cutoff = now - bulk_delete_max_age();
+
+    let (bulk, mut single): (Vec<_>, Vec<_>) = message_ids
+        .into_iter()
+        .partition(|id| id.created_at() > cutoff);
+
+    let mut batches: Vec<Vec<MessageId>> = bulk
+        .chunks(BULK_DELETE_MAX)
+        .map(|batch| batch.to_vec())
+        .collect();
+
+    // Bulk deletes require at least two messages.
+    if batches.last().map(|batch| batch.len()) == Some(1) {
+        single.extend(batches.pop().unwrap());
+    }
+
+    (batches, single)
+}
+
+async fn delete_messages<D>(
+    deleter: &D,
+    message_ids: Vec<MessageId>,
+    now: DateTime<Utc>,
+) -> Result<(), Error>
+where
+    D: MessageDeleter + Sync,
+{
+    let (batches, single) = split_bulk_delete(message_ids, now);
+
+    for batch in batches {
+        deleter.delete_bulk(&batch).await?;
+    }
+
+    for message_id in single {
+        deleter.delete_one(message_id).await?;
+    }
+
+    Ok(())
+}
+
+/// Returns the channel and message of a resolved [`MessageRef`].
+fn channel_message(message: &MessageRef) -> Result<(ChannelId, MessageId), Error> {
+    match message.channel_id {
+        Some(channel_id) => Ok((channel_id, message.message_id)),
+        None => Err(Error::UnresolvedMessageRef),
+    }
+}
+
+/// Returns the emojis of `reactions` the bot reacted with.
+fn own_reactions(reactions: Vec<MessageReaction>) -> Vec<Emoji> {
+    reactions
+        .into_iter()
+        .filter(|reaction| reaction.me)
+        .map(|reaction| reaction.reaction_type.into())
+        .collect()
+}
+
+/// Returns the JSON error code of an unsuccessful request.
+fn error_code(err: &serenity::Error) -> Option<isize> {
+    match err {
+        serenity::Error::Http(err) => match &**err {
+            serenity::http::HttpError::UnsuccessfulRequest(resp) => Some(resp.error.code),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Maps the error of a reaction request to a typed [`Error`].
+fn reaction_error(err: serenity::Error) -> Error {
+    match error_code(&err).and_then(reaction_error_kind) {
+        Some(kind) => kind,
+        None => Error::Raw(err),
+    }
+}
+
+/// Returns the typed [`Error`] of the JSON error code of a reaction request.
+fn reaction_error_kind(code: isize) -> Option<Error> {
+    /// JSON error code for "Unknown Emoji".
+    const UNKNOWN_EMOJI: isize = 10014;
+    /// JSON error code for "Missing Permissions".
+    const MISSING_PERMISSIONS: isize = 50013;
+    /// JSON error code for "Reaction was blocked".
+    const REACTION_BLOCKED: isize = 90001;
+
+    match code {
+        UNKNOWN_EMOJI => Some(Error::UnknownEmoji),
+        MISSING_PERMISSIONS | REACTION_BLOCKED => Some(Error::MissingPermissions),
+        _ => None,
+    }
+}
+
+/// Returns `true` if `err` is the error returned by Discord when sending a
+/// direct message to a user that does not accept them.
+fn is_cannot_message_user(err: &serenity::Error) -> bool {
+    /// JSON error code for "Cannot send messages to this user".
+    const CANNOT_MESSAGE_USER: isize = 50007;
+
+    match err {
+        serenity::Error::Http(err) => matches!(
+            &**err,
+            serenity::http::HttpError::UnsuccessfulRequest(resp)
+                if resp.error.code == CANNOT_MESSAGE_USER
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        channel_message, delete_messages, own_reactions, reaction_error_kind, split_bulk_delete,
+        Error, MessageDeleter,
+    };
+    use crate::arguments::MessageRef;
+    use crate::model::channel::{MessageReaction, ReactionType};
+    use crate::model::emoji::Emoji;
+    use crate::model::id::{ChannelId, EmojiId, MessageId};
+
+    use async_trait::async_trait;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    use std::sync::Mutex;
+
+    /// The first second of 2015, the Discord epoch.
+    const DISCORD_EPOCH: i64 = 1_420_070_400_000;
+
+    /// Returns the id of a message created at `time`. `n` distinguishes
+    /// messages created at the same time.
+    fn message_id(time: DateTime<Utc>, n: u64) -> MessageId {
+        let millis = (time.timestamp_millis() - DISCORD_EPOCH) as u64;
+        MessageId((millis << 22) + n)
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Call {
+        One(MessageId),
+        Bulk(usize),
+    }
+
+    #[derive(Default)]
+    struct MockDeleter {
+        calls: Mutex<Vec<Call>>,
+    }
+
+    #[async_trait]
+    impl MessageDeleter for MockDeleter {
+        async fn delete_one(&self, message_id: MessageId) -> Result<(), Error> {
+            self.calls.lock().unwrap().push(Call::One(message_id));
+            Ok(())
+        }
+
+        async fn delete_bulk(&self, message_ids: &[MessageId]) -> Result<(), Error> {
+            assert!((2..=100).contains(&message_ids.len()));
+
+            self.calls
+                .lock()
+                .unwrap()
+                .push(Call::Bulk(message_ids.len()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_split_bulk_delete() {
+        let now = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+
+        let recent = message_id(now - Duration::days(1), 0);
+        let old = message_id(now - Duration::days(15), 0);
+        // Just inside the safety margin.
+        let border = message_id(now - Duration::days(14) + Duration::seconds(30), 0);
+
+        let (batches, single) = split_bulk_delete(vec![recent, old, border, recent], now);
+        assert!(batches.is_empty());
+        assert_eq!(single, [old, border, recent]);
+
+        let ids: Vec<_> = (0..250)
+            .map(|n| message_id(now - Duration::hours(1), n))
+            .collect();
+        let (batches, single) = split_bulk_delete(ids.clone(), now);
+        assert_eq!(
+            batches.iter().map(Vec::len).collect::<Vec<_>>(),
+            [100, 100, 50]
+        );
+        assert!(single.is_empty());
+
+        // A remaining single message cannot be deleted in bulk.
+        let (batches, single) = split_bulk_delete(ids[..101].to_vec(), now);
+        assert_eq!(batches.len(), 1);
+        assert_eq!(single, [ids[100]]);
+    }
+
+    #[test]
+    fn test_delete_messages() {
+        let now = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+
+        let mut ids: Vec<_> = (0..150)
+            .map(|n| message_id(now - Duration::minutes(5), n))
+            .collect();
+        let old = message_id(now - Duration::days(30), 0);
+        ids.push(old);
+
+        let deleter = MockDeleter::default();
+        futures::executor::block_on(delete_messages(&deleter, ids, now)).unwrap();
+
+        assert_eq!(
+            *deleter.calls.lock().unwrap(),
+            [Call::Bulk(100), Call::Bulk(50), Call::One(old)]
+        );
+
+        let deleter = MockDeleter::default();
+        futures::executor::block_on(delete_messages(&deleter, Vec::new(), now)).unwrap();
+        assert!(deleter.calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_channel_message() {
+        let message = MessageRef::new(None, Some(ChannelId(1)), 2);
+        assert_eq!(
+            channel_message(&message).unwrap(),
+            (ChannelId(1), MessageId(2))
+        );
+
+        let message = MessageRef::new(None, None, 2);
+        assert!(matches!(
+            channel_message(&message),
+            Err(Error::UnresolvedMessageRef)
+        ));
+    }
+
+    #[test]
+    fn test_reaction_error_kind() {
+        assert!(matches!(
+            reaction_error_kind(10014),
+            Some(Error::UnknownEmoji)
+        ));
+        assert!(matches!(
+            reaction_error_kind(50013),
+            Some(Error::MissingPermissions)
+        ));
+        assert!(matches!(
+            reaction_error_kind(90001),
+            Some(Error::MissingPermissions)
+        ));
+        assert!(reaction_error_kind(10008).is_none());
+    }
+
+    #[test]
+    fn test_own_reactions() {
+        let reaction = |me, reaction_type| MessageReaction {
+            count: 1,
+            me,
+            reaction_type,
+        };
+
+        let reactions = vec![
+            reaction(true, ReactionType::Unicode(String::from("✅"))),
+            reaction(false, ReactionType::Unicode(String::from("❌"))),
+            reaction(
+                true,
+                ReactionType::Custom {
+                    animated: true,
+                    id: EmojiId(1),
+                    name: Some(String::from("party_blob")),
+                },
+            ),
+        ];
+
+        assert_eq!(
+            own_reactions(reactions),
+            [
+                Emoji::Unicode(String::from("✅")),
+                Emoji::Custom {
+                    id: EmojiId(1),
+                    name: String::from("party_blob"),
+                    animated: true,
+                },
+            ]
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::model::channel::Message;
+use crate::model::id as model_id;
 
 use serenity::model::{
     channel::{GuildChannel, Reaction},
@@ -49,11 +50,13 @@ pub enum EventKind {
     GuildMemberRemoval,
     GuildMemberUpdate,
     Message,
+    MessageDelete,
     ReactionAdd,
     ReactionRemove,
     ReactionRemoveAll,
 }
 
+#[derive(Copy, Clone, Debug)]
 pub struct InvalidEventKindError;
 
 impl FromStr for EventKind {
@@ -67,6 +70,7 @@ impl FromStr for EventKind {
             "GuildMemberRemoval" => Ok(Self::GuildMemberRemoval),
             "GuildMemberUpdate" => Ok(Self::GuildMemberUpdate),
             "Message" => Ok(Self::Message),
+            "MessageDelete" => Ok(Self::MessageDelete),
             "ReactionAdd" => Ok(Self::ReactionAdd),
             "ReactionRemove" => Ok(Self::ReactionRemove),
             "ReactionRemoveAll" => Ok(Self::ReactionRemoveAll),
@@ -87,6 +91,7 @@ impl std::fmt::Display for EventKind {
                 Self::GuildMemberRemoval => "GuildMemberRemoval",
                 Self::GuildMemberUpdate => "GuildMemberUpdate",
                 Self::Message => "Message",
+                Self::MessageDelete => "MessageDelete",
                 Self::ReactionAdd => "ReactionAdd",
                 Self::ReactionRemove => "ReactionRemove",
                 Self::ReactionRemoveAll => "ReactionRemoveAll",
@@ -112,6 +117,7 @@ pub enum EventData {
     GuildMemberRemoval(Box<GuildMemberRemovalData>),
     GuildMemberUpdate(Box<GuildMemberUpdateData>),
     Message(Box<MessageData>),
+    MessageDelete(Box<MessageDeleteData>),
     ReactionAdd(Box<ReactionAddData>),
     ReactionRemove(Box<ReactionRemoveData>),
     ReactionRemoveAll(Box<ReactionRemoveAllData>),
@@ -126,6 +132,7 @@ impl EventData {
             Self::GuildMemberRemoval(_) => EventKind::GuildMemberRemoval,
             Self::GuildMemberUpdate(_) => EventKind::GuildMemberUpdate,
             Self::Message(_) => EventKind::Message,
+            Self::MessageDelete(_) => EventKind::MessageDelete,
             Self::ReactionAdd(_) => EventKind::ReactionAdd,
             Self::ReactionRemove(_) => EventKind::ReactionRemove,
             Self::ReactionRemoveAll(_) => EventKind::ReactionRemoveAll,
@@ -161,6 +168,31 @@ pub struct GuildMemberUpdateData {
 #[derive(Clone, Debug)]
 pub struct MessageData(pub Message);
 
+impl AsRef<Message> for MessageData {
+    fn as_ref(&self) -> &Message {
+        &self.0
+    }
+}
+
+impl AsRef<model_id::ChannelId> for MessageData {
+    fn as_ref(&self) -> &model_id::ChannelId {
+        &self.0.channel_id
+    }
+}
+
+impl AsRef<model_id::MessageId> for MessageData {
+    fn as_ref(&self) -> &model_id::MessageId {
+        &self.0.id
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MessageDeleteData {
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub guild_id: Option<GuildId>,
+}
+
 #[derive(Clone, Debug)]
 pub struct ReactionAddData(pub Reaction);
 
@@ -204,7 +236,12 @@ macro_rules! impl_hookevent {
     };
 }
 
+impl_hookevent!(ChannelCreateData, ChannelCreate);
 impl_hookevent!(GuildMemberAdditionData, GuildMemberAddition);
 impl_hookevent!(GuildMemberRemovalData, GuildMemberRemoval);
 impl_hookevent!(GuildMemberUpdateData, GuildMemberUpdate);
 impl_hookevent!(MessageData, Message);
+impl_hookevent!(MessageDeleteData, MessageDelete);
+impl_hookevent!(ReactionAddData, ReactionAdd);
+impl_hookevent!(ReactionRemoveData, ReactionRemove);
+impl_hookevent!(ReactionRemoveAllData, ReactionRemoveAll);
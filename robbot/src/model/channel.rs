@@ -3,7 +3,7 @@ use crate::util::color::Color;
 use crate::{Decode, Encode};
 
 use super::guild::PartialMember;
-use super::id::{AttachmentId, ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId};
+use super::id::{AttachmentId, ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId, WebhookId};
 use super::permissions::Permissions;
 use super::user::User;
 
@@ -18,6 +18,7 @@ pub struct Message {
     pub author: User,
     pub channel_id: ChannelId,
     pub content: String,
+    #[serde(with = "crate::remote::serde_bridge::datetime::option")]
     pub edited_timestamp: Option<DateTime<Utc>>,
     pub embeds: Vec<Embed>,
     pub guild_id: Option<GuildId>,
@@ -29,12 +30,21 @@ pub struct Message {
     pub mentions: Vec<User>,
     pub pinned: bool,
     pub reactions: Vec<MessageReaction>,
+    #[serde(with = "crate::remote::serde_bridge::datetime")]
     pub timestamp: DateTime<Utc>,
     pub tts: bool,
+    /// The id of the webhook that sent the message, if it was sent by a webhook.
+    pub webhook_id: Option<WebhookId>,
     pub message_reference: Option<MessageReference>,
     pub referenced_message: Option<Box<Message>>,
 }
 
+impl AsRef<Message> for Message {
+    fn as_ref(&self) -> &Message {
+        self
+    }
+}
+
 impl AsRef<ChannelId> for Message {
     fn as_ref(&self) -> &ChannelId {
         &self.channel_id
@@ -140,6 +150,7 @@ pub struct MessageReaction {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Encode, Decode)]
+#[non_exhaustive]
 pub enum ReactionType {
     Custom {
         animated: bool,
@@ -158,6 +169,7 @@ pub struct GuildMessage {
     pub author: User,
     pub channel_id: ChannelId,
     pub content: String,
+    #[serde(with = "crate::remote::serde_bridge::datetime::option")]
     pub edited_timestamp: Option<DateTime<Utc>>,
     pub embeds: Vec<Embed>,
     pub guild_id: GuildId,
@@ -169,8 +181,11 @@ pub struct GuildMessage {
     pub mentions: Vec<User>,
     pub pinned: bool,
     pub reactions: Vec<MessageReaction>,
+    #[serde(with = "crate::remote::serde_bridge::datetime")]
     pub timestamp: DateTime<Utc>,
     pub tts: bool,
+    /// The id of the webhook that sent the message, if it was sent by a webhook.
+    pub webhook_id: Option<WebhookId>,
     pub message_reference: Option<MessageReference>,
     pub referenced_message: Option<Box<GuildMessage>>,
 }
@@ -229,6 +244,7 @@ impl TryFrom<Message> for GuildMessage {
             reactions: value.reactions,
             timestamp: value.timestamp,
             tts: value.tts,
+            webhook_id: value.webhook_id,
             message_reference: value.message_reference,
             referenced_message,
         })
@@ -249,6 +265,7 @@ pub struct Attachment {
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, Encode, Decode)]
+#[non_exhaustive]
 pub enum MessageKind {
     Regular,
     GroupRecipientAddition,
@@ -290,6 +307,7 @@ pub struct Reaction {
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+#[non_exhaustive]
 pub enum ChannelKind {
     Text,
     Private,
@@ -305,6 +323,7 @@ pub enum ChannelKind {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Encode, Decode)]
+#[non_exhaustive]
 pub enum Channel {
     Guild(GuildChannel),
     Private(PrivateChannel),
@@ -319,6 +338,7 @@ pub struct GuildChannel {
     pub guild_id: GuildId,
     pub kind: ChannelKind,
     pub last_message_id: Option<MessageId>,
+    #[serde(with = "crate::remote::serde_bridge::datetime::option")]
     pub last_pin_timestamp: Option<DateTime<Utc>>,
     pub name: String,
     pub permission_overwrites: Vec<PermissionOverwrite>,
@@ -345,6 +365,7 @@ pub enum PermissionOverwriteKind {
 pub struct PrivateChannel {
     pub id: ChannelId,
     pub last_message_id: Option<MessageId>,
+    #[serde(with = "crate::remote::serde_bridge::datetime::option")]
     pub last_pin_timestamp: Option<DateTime<Utc>>,
     pub kind: ChannelKind,
     pub recipient: User,
@@ -1,4 +1,4 @@
-use super::channel;
+use super::{channel, convert_all, InvalidModelData};
 
 use serenity::model::channel::{
     Attachment, Channel, ChannelCategory, ChannelType, Embed, EmbedAuthor, EmbedField, EmbedFooter,
@@ -29,9 +29,10 @@ impl From<Message> for channel::Message {
                 .collect(),
             mentions: src.mentions.into_iter().map(|v| v.into()).collect(),
             pinned: src.pinned,
-            reactions: src.reactions.into_iter().map(|v| v.into()).collect(),
+            reactions: convert_all(src.reactions),
             timestamp: src.timestamp,
             tts: src.tts,
+            webhook_id: src.webhook_id.map(|v| v.into()),
             message_reference: src.message_reference.map(|v| v.into()),
             referenced_message: src.referenced_message.map(|v| Box::new(Self::from(*v))),
         }
@@ -189,40 +190,46 @@ impl From<MessageReference> for channel::MessageReference {
     }
 }
 
-impl From<MessageReaction> for channel::MessageReaction {
-    fn from(src: MessageReaction) -> Self {
-        Self {
+impl TryFrom<MessageReaction> for channel::MessageReaction {
+    type Error = InvalidModelData;
+
+    fn try_from(src: MessageReaction) -> Result<Self, Self::Error> {
+        Ok(Self {
             count: src.count,
             me: src.me,
-            reaction_type: src.reaction_type.into(),
-        }
+            reaction_type: src.reaction_type.try_into()?,
+        })
     }
 }
 
-impl From<ReactionType> for channel::ReactionType {
-    fn from(src: ReactionType) -> Self {
+impl TryFrom<ReactionType> for channel::ReactionType {
+    type Error = InvalidModelData;
+
+    fn try_from(src: ReactionType) -> Result<Self, Self::Error> {
         match src {
-            ReactionType::Custom { animated, id, name } => Self::Custom {
+            ReactionType::Custom { animated, id, name } => Ok(Self::Custom {
                 animated,
                 id: id.into(),
                 name,
-            },
-            ReactionType::Unicode(s) => Self::Unicode(s),
-            _ => unreachable!(),
+            }),
+            ReactionType::Unicode(s) => Ok(Self::Unicode(s)),
+            _ => Err(InvalidModelData("unsupported reaction type")),
         }
     }
 }
 
-impl From<Reaction> for channel::Reaction {
-    fn from(src: Reaction) -> Self {
-        Self {
+impl TryFrom<Reaction> for channel::Reaction {
+    type Error = InvalidModelData;
+
+    fn try_from(src: Reaction) -> Result<Self, Self::Error> {
+        Ok(Self {
             channel_id: src.channel_id.into(),
-            emoji: src.emoji.into(),
+            emoji: src.emoji.try_into()?,
             message_id: src.message_id.into(),
             user_id: src.user_id.map(|v| v.into()),
             guild_id: src.guild_id.map(|v| v.into()),
             member: src.member.map(|v| v.into()),
-        }
+        })
     }
 }
 
@@ -246,6 +253,26 @@ impl From<ChannelType> for channel::ChannelKind {
     }
 }
 
+impl From<channel::ChannelKind> for ChannelType {
+    fn from(src: channel::ChannelKind) -> Self {
+        use channel::ChannelKind::*;
+
+        match src {
+            Text => Self::Text,
+            Private => Self::Private,
+            Voice => Self::Voice,
+            Category => Self::Category,
+            News => Self::News,
+            Store => Self::Store,
+            NewsThread => Self::NewsThread,
+            PublicThread => Self::PublicThread,
+            PrivateThread => Self::PrivateThread,
+            Stage => Self::Stage,
+            Unknown => Self::Unknown,
+        }
+    }
+}
+
 impl From<GuildChannel> for channel::GuildChannel {
     fn from(src: GuildChannel) -> Self {
         Self {
@@ -257,11 +284,7 @@ impl From<GuildChannel> for channel::GuildChannel {
             last_message_id: src.last_message_id.map(|v| v.into()),
             last_pin_timestamp: src.last_pin_timestamp,
             name: src.name,
-            permission_overwrites: src
-                .permission_overwrites
-                .into_iter()
-                .map(|v| v.into())
-                .collect(),
+            permission_overwrites: convert_all(src.permission_overwrites),
             position: src.position,
             topic: src.topic,
             user_limit: src.user_limit,
@@ -270,8 +293,20 @@ impl From<GuildChannel> for channel::GuildChannel {
     }
 }
 
-impl From<PermissionOverwrite> for channel::PermissionOverwrite {
-    fn from(src: PermissionOverwrite) -> Self {
+impl TryFrom<PermissionOverwrite> for channel::PermissionOverwrite {
+    type Error = InvalidModelData;
+
+    fn try_from(src: PermissionOverwrite) -> Result<Self, Self::Error> {
+        Ok(Self {
+            allow: src.allow.into(),
+            deny: src.deny.into(),
+            kind: src.kind.try_into()?,
+        })
+    }
+}
+
+impl From<channel::PermissionOverwrite> for PermissionOverwrite {
+    fn from(src: channel::PermissionOverwrite) -> Self {
         Self {
             allow: src.allow.into(),
             deny: src.deny.into(),
@@ -280,23 +315,36 @@ impl From<PermissionOverwrite> for channel::PermissionOverwrite {
     }
 }
 
-impl From<PermissionOverwriteType> for channel::PermissionOverwriteKind {
-    fn from(src: PermissionOverwriteType) -> Self {
+impl TryFrom<PermissionOverwriteType> for channel::PermissionOverwriteKind {
+    type Error = InvalidModelData;
+
+    fn try_from(src: PermissionOverwriteType) -> Result<Self, Self::Error> {
+        match src {
+            PermissionOverwriteType::Member(user_id) => Ok(Self::Member(user_id.into())),
+            PermissionOverwriteType::Role(role_id) => Ok(Self::Role(role_id.into())),
+            _ => Err(InvalidModelData("unsupported permission overwrite type")),
+        }
+    }
+}
+
+impl From<channel::PermissionOverwriteKind> for PermissionOverwriteType {
+    fn from(src: channel::PermissionOverwriteKind) -> Self {
         match src {
-            PermissionOverwriteType::Member(user_id) => Self::Member(user_id.into()),
-            PermissionOverwriteType::Role(role_id) => Self::Role(role_id.into()),
-            v => panic!("Unknown PermissionOverwriteType: {:?}", v),
+            channel::PermissionOverwriteKind::Member(user_id) => Self::Member(user_id.into()),
+            channel::PermissionOverwriteKind::Role(role_id) => Self::Role(role_id.into()),
         }
     }
 }
 
-impl From<Channel> for channel::Channel {
-    fn from(src: Channel) -> Self {
+impl TryFrom<Channel> for channel::Channel {
+    type Error = InvalidModelData;
+
+    fn try_from(src: Channel) -> Result<Self, Self::Error> {
         match src {
-            Channel::Guild(channel) => Self::Guild(channel.into()),
-            Channel::Private(channel) => Self::Private(channel.into()),
-            Channel::Category(channel) => Self::Category(channel.into()),
-            v => panic!("Unknown Channel: {:?}", v),
+            Channel::Guild(channel) => Ok(Self::Guild(channel.into())),
+            Channel::Private(channel) => Ok(Self::Private(channel.into())),
+            Channel::Category(channel) => Ok(Self::Category(channel.into())),
+            _ => Err(InvalidModelData("unsupported channel")),
         }
     }
 }
@@ -323,11 +371,78 @@ impl From<ChannelCategory> for channel::CategoryChannel {
             kind: src.kind.into(),
             name: src.name,
             nsfw: src.nsfw,
-            permission_overwrites: src
-                .permission_overwrites
-                .into_iter()
-                .map(|v| v.into())
-                .collect(),
+            permission_overwrites: convert_all(src.permission_overwrites),
         }
     }
 }
+
+/// Conversions of real gateway payloads, including fields serenity doesn't
+/// know about. Unknown fields are dropped, everything the models have a field
+/// for is preserved.
+#[cfg(test)]
+mod tests {
+    use crate::model::channel::{self, ReactionType};
+    use crate::model::id::{ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId};
+
+    use serenity::model::channel::{Message, Reaction};
+
+    #[test]
+    fn test_message_in_thread() {
+        let src: Message =
+            serde_json::from_str(include_str!("fixtures/message_in_thread.json")).unwrap();
+        let message = channel::Message::from(src);
+
+        // The thread is the channel of the message.
+        assert_eq!(message.channel_id, ChannelId(1030530800000000000));
+        assert_eq!(message.guild_id, Some(GuildId(583806438531661826)));
+        assert_eq!(message.content, "hello from a thread");
+
+        // Members in message payloads are sent without their user.
+        let member = message.member.as_ref().unwrap();
+        assert!(member.user.is_none());
+        assert_eq!(member.roles, [RoleId(583806438531661827)]);
+        assert_eq!(message.author.id, UserId(251021436722987008));
+
+        let message = channel::GuildMessage::try_from(message).unwrap();
+        assert_eq!(message.id, MessageId(1030530862124302426));
+    }
+
+    #[test]
+    fn test_message_with_super_reactions() {
+        let src: Message =
+            serde_json::from_str(include_str!("fixtures/message_with_super_reactions.json"))
+                .unwrap();
+        let message = channel::Message::from(src);
+
+        // Super reactions are counted like normal reactions.
+        assert_eq!(message.reactions.len(), 2);
+        assert_eq!(message.reactions[0].count, 3);
+        assert!(message.reactions[0].me);
+        assert!(matches!(
+            &message.reactions[0].reaction_type,
+            ReactionType::Unicode(name) if name == "⭐"
+        ));
+        assert!(matches!(
+            message.reactions[1].reaction_type,
+            ReactionType::Custom {
+                id: EmojiId(1234),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_super_reaction_add() {
+        let src: Reaction =
+            serde_json::from_str(include_str!("fixtures/super_reaction_add.json")).unwrap();
+        let reaction = channel::Reaction::try_from(src).unwrap();
+
+        assert_eq!(reaction.message_id, MessageId(1130530862124302426));
+        assert_eq!(reaction.user_id, Some(UserId(251021436722987008)));
+        assert!(matches!(&reaction.emoji, ReactionType::Unicode(name) if name == "⭐"));
+        assert_eq!(
+            reaction.member.unwrap().user.unwrap().id,
+            UserId(251021436722987008)
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use crate as robbot;
+use crate::model::channel::ReactionType;
+use crate::model::id::EmojiId;
+use crate::model::InvalidModelData;
+use crate::{Decode, Encode};
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// An emoji usable as a reaction, either a unicode emoji or a custom emoji
+/// of a guild.
+///
+/// Custom emojis are equal if their ids are equal, their name and animation
+/// are ignored. Unicode emojis are equal if their strings are equal: skin
+/// tone modifiers and ZWJ sequences are compared as opaque strings.
+///
+/// # Examples
+///
+/// ```
+/// # use robbot::model::emoji::Emoji;
+/// # use robbot::model::id::EmojiId;
+/// let emoji: Emoji = "<:rust:1234>".parse().unwrap();
+/// // Custom emojis are compared by id.
+/// assert_eq!(emoji, Emoji::Custom { id: EmojiId(1234), name: String::from("ferris"), animated: false });
+/// assert_eq!(emoji.to_string(), "<:rust:1234>");
+///
+/// let emoji: Emoji = "🔥".parse().unwrap();
+/// assert_eq!(emoji, Emoji::Unicode(String::from("🔥")));
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize, Encode, Decode)]
+pub enum Emoji {
+    Unicode(String),
+    Custom {
+        id: EmojiId,
+        name: String,
+        animated: bool,
+    },
+}
+
+impl Emoji {
+    /// Returns the id of a custom emoji.
+    pub fn id(&self) -> Option<EmojiId> {
+        match self {
+            Self::Unicode(_) => None,
+            Self::Custom { id, .. } => Some(*id),
+        }
+    }
+}
+
+impl PartialEq for Emoji {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Unicode(a), Self::Unicode(b)) => a == b,
+            (Self::Custom { id: a, .. }, Self::Custom { id: b, .. }) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Emoji {}
+
+impl Hash for Emoji {
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        match self {
+            Self::Unicode(s) => {
+                state.write_u8(0);
+                s.hash(state);
+            }
+            Self::Custom { id, .. } => {
+                state.write_u8(1);
+                id.hash(state);
+            }
+        }
+    }
+}
+
+/// Formats the emoji as it is written in a message: unicode emojis as they
+/// are and custom emojis as `<:name:id>` or `<a:name:id>`.
+impl Display for Emoji {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unicode(s) => f.write_str(s),
+            Self::Custom { id, name, animated } => {
+                let prefix = if *animated { "a" } else { "" };
+                write!(f, "<{}:{}:{}>", prefix, name, id)
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InvalidEmoji;
+
+impl Display for InvalidEmoji {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid emoji")
+    }
+}
+
+impl std::error::Error for InvalidEmoji {}
+
+/// Parses a custom emoji with the format `<:name:id>` or `<a:name:id>`, or a
+/// unicode emoji. Unicode emojis are not checked against the list of known
+/// emojis, every string without whitespace containing at least one
+/// non-ASCII character is accepted.
+impl FromStr for Emoji {
+    type Err = InvalidEmoji;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(inner) = s.strip_prefix('<') {
+            let inner = inner.strip_suffix('>').ok_or(InvalidEmoji)?;
+
+            let (animated, inner) = match inner.strip_prefix("a:") {
+                Some(inner) => (true, inner),
+                None => (false, inner.strip_prefix(':').ok_or(InvalidEmoji)?),
+            };
+
+            let (name, id) = inner.split_once(':').ok_or(InvalidEmoji)?;
+
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(InvalidEmoji);
+            }
+
+            let id = id.parse().or(Err(InvalidEmoji))?;
+
+            return Ok(Self::Custom {
+                id: EmojiId(id),
+                name: name.to_owned(),
+                animated,
+            });
+        }
+
+        if s.is_ascii() || s.chars().any(char::is_whitespace) {
+            return Err(InvalidEmoji);
+        }
+
+        Ok(Self::Unicode(s.to_owned()))
+    }
+}
+
+impl From<ReactionType> for Emoji {
+    fn from(src: ReactionType) -> Self {
+        match src {
+            ReactionType::Unicode(s) => Self::Unicode(s),
+            ReactionType::Custom { animated, id, name } => Self::Custom {
+                id,
+                name: name.unwrap_or_default(),
+                animated,
+            },
+        }
+    }
+}
+
+impl From<Emoji> for ReactionType {
+    fn from(src: Emoji) -> Self {
+        match src {
+            Emoji::Unicode(s) => Self::Unicode(s),
+            Emoji::Custom { id, name, animated } => Self::Custom {
+                animated,
+                id,
+                name: Some(name),
+            },
+        }
+    }
+}
+
+impl TryFrom<serenity::model::channel::ReactionType> for Emoji {
+    type Error = InvalidModelData;
+
+    fn try_from(src: serenity::model::channel::ReactionType) -> Result<Self, Self::Error> {
+        ReactionType::try_from(src).map(Self::from)
+    }
+}
+
+impl From<Emoji> for serenity::model::channel::ReactionType {
+    fn from(src: Emoji) -> Self {
+        match src {
+            Emoji::Unicode(s) => Self::Unicode(s),
+            Emoji::Custom { id, name, animated } => Self::Custom {
+                animated,
+                id: id.into(),
+                name: Some(name),
+            },
+        }
+    }
+}
+
+impl PartialEq<serenity::model::channel::ReactionType> for Emoji {
+    fn eq(&self, other: &serenity::model::channel::ReactionType) -> bool {
+        use serenity::model::channel::ReactionType;
+
+        match (self, other) {
+            (Self::Unicode(a), ReactionType::Unicode(b)) => a == b,
+            (Self::Custom { id: a, .. }, ReactionType::Custom { id: b, .. }) => a.0 == b.0,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Emoji, InvalidEmoji};
+    use crate::model::id::EmojiId;
+
+    fn custom(id: u64, name: &str, animated: bool) -> Emoji {
+        Emoji::Custom {
+            id: EmojiId(id),
+            name: name.to_owned(),
+            animated,
+        }
+    }
+
+    fn unicode(s: &str) -> Emoji {
+        Emoji::Unicode(s.to_owned())
+    }
+
+    #[test]
+    fn test_emoji_parse_custom() {
+        let emoji: Emoji = "<:rust:1234>".parse().unwrap();
+        assert!(matches!(emoji, Emoji::Custom { animated: false, ref name, .. } if name == "rust"));
+        assert_eq!(emoji.id(), Some(EmojiId(1234)));
+
+        let emoji: Emoji = "<a:party_blob:5678>".parse().unwrap();
+        assert!(
+            matches!(emoji, Emoji::Custom { animated: true, ref name, .. } if name == "party_blob")
+        );
+
+        for s in [
+            "<:rust:>",
+            "<::1234>",
+            "<:rust:12a4>",
+            "<rust:1234>",
+            "<:rust:1234",
+            "<b:rust:1234>",
+            "<:ru st:1234>",
+            "<@1234>",
+        ] {
+            assert_eq!(s.parse::<Emoji>(), Err(InvalidEmoji), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_emoji_parse_unicode() {
+        for s in [
+            "🔥",
+            // Skin tone modifier.
+            "👍🏽",
+            // ZWJ sequence.
+            "👨‍👩‍👧",
+            // Keycap sequence.
+            "1️⃣",
+            // Flag.
+            "🇩🇪",
+        ] {
+            assert_eq!(s.parse::<Emoji>(), Ok(unicode(s)), "{}", s);
+        }
+
+        // Modifiers are part of the emoji.
+        assert_ne!(unicode("👍🏽"), unicode("👍"));
+
+        for s in ["", "fire", ":fire:", "🔥 🔥", "1"] {
+            assert_eq!(s.parse::<Emoji>(), Err(InvalidEmoji), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_emoji_display() {
+        for s in ["<:rust:1234>", "<a:party_blob:5678>", "👨‍👩‍👧"] {
+            assert_eq!(s.parse::<Emoji>().unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_emoji_eq() {
+        // Custom emojis are compared by id.
+        assert_eq!(custom(1, "a", false), custom(1, "b", true));
+        assert_ne!(custom(1, "a", false), custom(2, "a", false));
+        assert_ne!(custom(1, "a", false), unicode("a"));
+
+        let reaction = serenity::model::channel::ReactionType::Custom {
+            animated: false,
+            id: serenity::model::id::EmojiId(1),
+            name: None,
+        };
+        assert_eq!(custom(1, "a", false), reaction);
+        assert_eq!(Emoji::try_from(reaction).unwrap(), custom(1, "", false));
+
+        let reaction = serenity::model::channel::ReactionType::Unicode(String::from("🔥"));
+        assert_eq!(unicode("🔥"), reaction);
+        assert_ne!(unicode("🔥🔥"), reaction);
+    }
+
+    #[test]
+    fn test_emoji_reaction_type() {
+        use serenity::model::channel::ReactionType;
+
+        let reaction = ReactionType::from(unicode("👍🏽"));
+        assert!(matches!(reaction, ReactionType::Unicode(ref s) if s == "👍🏽"));
+
+        let reaction = ReactionType::from(custom(1234, "rust", false));
+        assert!(matches!(
+            reaction,
+            ReactionType::Custom { animated: false, id, name: Some(ref name) }
+                if id.0 == 1234 && name == "rust"
+        ));
+
+        let reaction = ReactionType::from(custom(5678, "party_blob", true));
+        assert!(matches!(
+            reaction,
+            ReactionType::Custom { animated: true, id, name: Some(ref name) }
+                if id.0 == 5678 && name == "party_blob"
+        ));
+
+        // The animated flag and name survive the round trip.
+        let emoji = Emoji::try_from(reaction).unwrap();
+        assert!(matches!(
+            emoji,
+            Emoji::Custom { animated: true, ref name, .. } if name == "party_blob"
+        ));
+    }
+}
@@ -2,6 +2,7 @@ use crate as robbot;
 use crate::{Decode, Encode};
 
 use super::id::{GuildId, RoleId};
+use super::permissions::Permissions;
 use super::user::User;
 
 use chrono::{DateTime, Utc};
@@ -11,6 +12,7 @@ use serde::{Deserialize, Serialize};
 pub struct Member {
     pub deaf: bool,
     pub guild_id: GuildId,
+    #[serde(with = "crate::remote::serde_bridge::datetime::option")]
     pub joined_at: Option<DateTime<Utc>>,
     pub mute: bool,
     pub nick: Option<String>,
@@ -18,11 +20,23 @@ pub struct Member {
     pub user: User,
     pub pending: bool,
     pub avatar: Option<String>,
+    /// The time the timeout of the member ends, if the member was timed out.
+    /// The timeout is over if the time is in the past.
+    #[serde(with = "crate::remote::serde_bridge::datetime::option")]
+    pub communication_disabled_until: Option<DateTime<Utc>>,
+}
+
+impl Member {
+    /// Returns `true` if the member is timed out at `now`.
+    pub fn is_timed_out(&self, now: DateTime<Utc>) -> bool {
+        matches!(self.communication_disabled_until, Some(until) if until > now)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Encode, Decode)]
 pub struct PartialMember {
     pub deaf: bool,
+    #[serde(with = "crate::remote::serde_bridge::datetime::option")]
     pub joined_at: Option<DateTime<Utc>>,
     pub mute: bool,
     pub nick: Option<String>,
@@ -32,6 +46,49 @@ pub struct PartialMember {
     pub user: Option<User>,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct Role {
+    pub id: RoleId,
+    pub guild_id: GuildId,
+    pub name: String,
+    /// The position of the role in the role list of the guild. Higher roles
+    /// have a higher position, the @everyone role has position 0.
+    pub position: i64,
+    pub permissions: Permissions,
+    /// Whether the role is managed by an integration, e.g. the role of a bot.
+    /// Managed roles cannot be added to or removed from members.
+    pub managed: bool,
+}
+
+impl Role {
+    /// Returns `true` if this is the @everyone role of the guild. The
+    /// @everyone role shares the id of the guild.
+    pub fn is_everyone(&self) -> bool {
+        self.id.0 == self.guild_id.0
+    }
+}
+
+/// Returns `true` if a member with `roles` can add `role` to and remove it
+/// from members. `roles` should include the @everyone role of the guild.
+///
+/// The member needs the [`Permissions::MANAGE_ROLES`] permission and its
+/// highest role must be above `role`, roles with an equal position cannot be
+/// managed. The @everyone role and managed roles can never be edited. The
+/// owner of the guild bypasses the hierarchy, this is not checked here.
+pub fn can_manage_role(roles: &[Role], role: &Role) -> bool {
+    if role.is_everyone() || role.managed {
+        return false;
+    }
+
+    let permissions: Permissions = roles.iter().map(|role| role.permissions).collect();
+    if !permissions.missing(Permissions::MANAGE_ROLES).is_empty() {
+        return false;
+    }
+
+    let highest = roles.iter().map(|role| role.position).max().unwrap_or(0);
+    highest > role.position
+}
+
 impl From<serenity::model::guild::Member> for Member {
     fn from(src: serenity::model::guild::Member) -> Self {
         Self {
@@ -44,6 +101,83 @@ impl From<serenity::model::guild::Member> for Member {
             user: src.user.into(),
             pending: src.pending,
             avatar: src.avatar,
+            communication_disabled_until: src.communication_disabled_until,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{can_manage_role, Role};
+    use crate::model::id::{GuildId, RoleId};
+    use crate::model::permissions::Permissions;
+
+    fn role(id: u64, position: i64, permissions: Permissions) -> Role {
+        Role {
+            id: RoleId(id),
+            guild_id: GuildId(1),
+            name: format!("Role {}", id),
+            position,
+            permissions,
+            managed: false,
+        }
+    }
+
+    #[test]
+    fn test_can_manage_role() {
+        let everyone = role(1, 0, Permissions::empty());
+        let bot = role(2, 5, Permissions::MANAGE_ROLES);
+        let roles = [everyone.clone(), bot.clone()];
+
+        assert!(can_manage_role(&roles, &role(3, 4, Permissions::empty())));
+        assert!(can_manage_role(&roles, &role(3, 1, Permissions::empty())));
+        // Equal and higher positions.
+        assert!(!can_manage_role(&roles, &role(3, 5, Permissions::empty())));
+        assert!(!can_manage_role(&roles, &role(3, 6, Permissions::empty())));
+        // A role of the bot itself.
+        assert!(!can_manage_role(&roles, &bot));
+
+        // The @everyone role is never manageable.
+        assert!(!can_manage_role(&roles, &everyone));
+
+        // Managed roles are never manageable.
+        let managed = Role {
+            managed: true,
+            ..role(3, 1, Permissions::empty())
+        };
+        assert!(!can_manage_role(&roles, &managed));
+    }
+
+    #[test]
+    fn test_can_manage_role_permissions() {
+        let target = role(4, 1, Permissions::empty());
+
+        // Without MANAGE_ROLES.
+        let roles = [
+            role(1, 0, Permissions::empty()),
+            role(2, 5, Permissions::SEND_MESSAGES),
+        ];
+        assert!(!can_manage_role(&roles, &target));
+
+        // MANAGE_ROLES granted by a lower role.
+        let roles = [
+            role(1, 0, Permissions::empty()),
+            role(2, 5, Permissions::empty()),
+            role(3, 2, Permissions::MANAGE_ROLES),
+        ];
+        assert!(can_manage_role(&roles, &target));
+
+        // MANAGE_ROLES granted to @everyone, but no role above the target.
+        let roles = [role(1, 0, Permissions::MANAGE_ROLES)];
+        assert!(!can_manage_role(&roles, &role(4, 0, Permissions::empty())));
+        assert!(!can_manage_role(&roles, &target));
+
+        // ADMINISTRATOR implies MANAGE_ROLES, but not the hierarchy.
+        let roles = [
+            role(1, 0, Permissions::empty()),
+            role(2, 5, Permissions::ADMINISTRATOR),
+        ];
+        assert!(can_manage_role(&roles, &target));
+        assert!(!can_manage_role(&roles, &role(4, 7, Permissions::empty())));
+    }
+}
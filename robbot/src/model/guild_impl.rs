@@ -1,6 +1,6 @@
 use super::guild;
 
-use serenity::model::guild::PartialMember;
+use serenity::model::guild::{PartialMember, Role};
 
 impl From<PartialMember> for guild::PartialMember {
     fn from(src: PartialMember) -> Self {
@@ -16,3 +16,16 @@ impl From<PartialMember> for guild::PartialMember {
         }
     }
 }
+
+impl From<Role> for guild::Role {
+    fn from(src: Role) -> Self {
+        Self {
+            id: src.id.into(),
+            guild_id: src.guild_id.into(),
+            name: src.name,
+            position: src.position,
+            permissions: src.permissions.into(),
+            managed: src.managed,
+        }
+    }
+}
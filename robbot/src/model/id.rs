@@ -4,7 +4,15 @@ use crate::{Decode, Encode};
 
 use serde::{Deserialize, Serialize};
 
+use chrono::{DateTime, TimeZone, Utc};
+
 use std::fmt::{self, Display, Formatter};
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+/// The first second of 2015 in milliseconds since the unix epoch. The timestamp
+/// of all snowflake ids is relative to this time.
+const DISCORD_EPOCH: u64 = 1_420_070_400_000;
 
 /// A unique identifier for an Attachment.
 #[derive(
@@ -22,6 +30,7 @@ use std::fmt::{self, Display, Formatter};
     Encode,
     Decode,
 )]
+#[serde(transparent)]
 pub struct AttachmentId(pub u64);
 
 /// A unique identifier for a Channel.
@@ -40,6 +49,7 @@ pub struct AttachmentId(pub u64);
     Encode,
     Decode,
 )]
+#[serde(transparent)]
 pub struct ChannelId(pub u64);
 
 /// A unique identifier for an Emoji.
@@ -58,6 +68,7 @@ pub struct ChannelId(pub u64);
     Encode,
     Decode,
 )]
+#[serde(transparent)]
 pub struct EmojiId(pub u64);
 
 /// A unique identifier for a Guild.
@@ -76,6 +87,7 @@ pub struct EmojiId(pub u64);
     Encode,
     Decode,
 )]
+#[serde(transparent)]
 pub struct GuildId(pub u64);
 
 /// A unique identifier for a Message.
@@ -94,6 +106,7 @@ pub struct GuildId(pub u64);
     Encode,
     Decode,
 )]
+#[serde(transparent)]
 pub struct MessageId(pub u64);
 
 /// A unique identifier for a Role.
@@ -112,6 +125,7 @@ pub struct MessageId(pub u64);
     Encode,
     Decode,
 )]
+#[serde(transparent)]
 pub struct RoleId(pub u64);
 
 /// A unique identifier for an User.
@@ -130,22 +144,75 @@ pub struct RoleId(pub u64);
     Encode,
     Decode,
 )]
+#[serde(transparent)]
 pub struct UserId(pub u64);
 
+/// A unique identifier for a Webhook.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    Encode,
+    Decode,
+)]
+#[serde(transparent)]
+pub struct WebhookId(pub u64);
+
 macro_rules! impl_id {
     ($t:tt) => {
+        impl $t {
+            /// Returns the creation time of the entity, derived from the timestamp
+            /// stored in the snowflake id.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            /// # use robbot::model::id::*;
+            #[doc = concat!("let id = ", stringify!($t), "(583806438531661826);")]
+            /// assert_eq!(id.created_at().to_rfc3339(), "2019-05-30T23:58:21.545+00:00");
+            /// ```
+            pub fn created_at(&self) -> DateTime<Utc> {
+                let millis = (self.0 >> 22) + DISCORD_EPOCH;
+
+                Utc.timestamp_millis_opt(millis as i64).unwrap()
+            }
+        }
+
         impl Display for $t {
             fn fmt(&self, f: &mut Formatter) -> fmt::Result {
                 self.0.fmt(f)
             }
         }
 
+        impl FromStr for $t {
+            type Err = ParseIntError;
+
+            /// Parses an id from its bare number, e.g. `583806438531661826`.
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                s.parse().map(Self)
+            }
+        }
+
         impl From<u64> for $t {
             fn from(id: u64) -> Self {
                 Self(id)
             }
         }
 
+        impl From<$t> for u64 {
+            fn from(id: $t) -> Self {
+                id.0
+            }
+        }
+
         impl From<serenity::model::id::$t> for $t {
             fn from(src: serenity::model::id::$t) -> Self {
                 Self(src.0)
@@ -173,6 +240,7 @@ impl_id!(GuildId);
 impl_id!(MessageId);
 impl_id!(RoleId);
 impl_id!(UserId);
+impl_id!(WebhookId);
 
 impl ChannelId {
     /// Creates a [`ChannelMention`] from an [`ChannelId`].
@@ -227,3 +295,52 @@ impl UserId {
         UserMention::new(self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ChannelId, GuildId, MessageId, RoleId, UserId};
+
+    #[test]
+    fn test_id_display() {
+        assert_eq!(GuildId(1).to_string(), "1");
+        assert_eq!(
+            ChannelId(583806438531661826).to_string(),
+            "583806438531661826"
+        );
+        assert_eq!(format!("{}", MessageId(2)), "2");
+
+        assert_eq!(ChannelId(3).mention().to_string(), "<#3>");
+        assert_eq!(RoleId(4).mention().to_string(), "<@&4>");
+        assert_eq!(UserId(5).mention().to_string(), "<@5>");
+    }
+
+    #[test]
+    fn test_id_from_str() {
+        assert_eq!("583806438531661826".parse(), Ok(UserId(583806438531661826)));
+        assert_eq!("0".parse(), Ok(GuildId(0)));
+
+        assert!("".parse::<RoleId>().is_err());
+        assert!("-1".parse::<RoleId>().is_err());
+        assert!("<@5>".parse::<UserId>().is_err());
+        assert!("18446744073709551616".parse::<MessageId>().is_err());
+    }
+
+    #[test]
+    fn test_id_u64() {
+        assert_eq!(ChannelId::from(7), ChannelId(7));
+        assert_eq!(u64::from(ChannelId(7)), 7);
+
+        let id: u64 = UserId(8).into();
+        assert_eq!(id, 8);
+    }
+
+    #[test]
+    fn test_id_serde() {
+        assert_eq!(serde_json::to_string(&GuildId(9)).unwrap(), "9");
+        assert_eq!(serde_json::from_str::<GuildId>("9").unwrap(), GuildId(9));
+        assert_eq!(
+            serde_json::to_string(&vec![RoleId(1), RoleId(2)]).unwrap(),
+            "[1,2]"
+        );
+    }
+}
@@ -1,4 +1,19 @@
+//! The models of the bot, converted from the models of serenity.
+//!
+//! Discord adds new variants and fields over time, which serenity only partly
+//! knows about. Conversions from serenity never panic on them:
+//! - Enums that Discord extends, e.g. [`ChannelKind`], are `#[non_exhaustive]`
+//!   and have an `Unknown` variant where a value is always required.
+//! - Values that cannot be represented at all are rejected using
+//!   [`InvalidModelData`] by a `TryFrom` conversion. Lists skip such values
+//!   with a warning, so one unsupported reaction doesn't drop a whole message.
+//! - Fields that Discord only sends sometimes are `Option`s, the conversions
+//!   never make up a value for them.
+//!
+//! [`ChannelKind`]: channel::ChannelKind
+
 pub mod channel;
+pub mod emoji;
 pub mod guild;
 pub mod id;
 pub mod permissions;
@@ -12,12 +27,59 @@ mod user_impl;
 use thiserror::Error;
 
 /// An error indicating the model data exists in a state that is not considered valid. One should
-/// always prefer using this error instead of panicking.
+/// always prefer using this error instead of panicking. Contains a description of the invalid
+/// data.
 ///
 /// This would for example be an [`Message`] with the `member` field being `Some(..)` and
 /// the `guild_id` being `None`.
 ///
 /// [`Message`]: channel::Message
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Error)]
-#[error("invalid model data")]
-pub struct InvalidModelData;
+#[error("invalid model data: {0}")]
+pub struct InvalidModelData(pub &'static str);
+
+/// Converts all items of `src` using `TryFrom`, skipping items that cannot be
+/// converted with a warning.
+pub(crate) fn convert_all<T, U>(src: Vec<T>) -> Vec<U>
+where
+    U: TryFrom<T, Error = InvalidModelData>,
+{
+    src.into_iter()
+        .filter_map(|item| match U::try_from(item) {
+            Ok(item) => Some(item),
+            Err(err) => {
+                log::warn!("[MODEL] Skipped {}", err);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{convert_all, InvalidModelData};
+
+    #[derive(Debug, PartialEq)]
+    struct Even(u32);
+
+    impl TryFrom<u32> for Even {
+        type Error = InvalidModelData;
+
+        fn try_from(src: u32) -> Result<Self, Self::Error> {
+            match src % 2 {
+                0 => Ok(Self(src)),
+                _ => Err(InvalidModelData("odd number")),
+            }
+        }
+    }
+
+    #[test]
+    fn test_convert_all() {
+        // Invalid items are skipped, the rest is kept in order.
+        assert_eq!(convert_all::<_, Even>(vec![1, 2, 3, 4]), [Even(2), Even(4)]);
+        assert_eq!(
+            InvalidModelData("odd number").to_string(),
+            "invalid model data: odd number"
+        );
+    }
+}
@@ -1,9 +1,361 @@
 use crate as robbot;
 use crate::{Decode, Encode};
 
+use super::channel::{PermissionOverwrite, PermissionOverwriteKind};
+use super::id::{GuildId, RoleId, UserId};
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize, Encode, Decode)]
+use std::fmt::{self, Display, Formatter};
+use std::ops::{BitAnd, BitOr, BitOrAssign, Not};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Encode, Decode)]
 pub struct Permissions {
     pub bits: u64,
 }
+
+macro_rules! permissions {
+    ($($name:ident = $bit:expr, $display:expr;)*) => {
+        impl Permissions {
+            $(
+                pub const $name: Self = Self { bits: 1 << $bit };
+            )*
+
+            /// All known permissions with their human-readable names.
+            const NAMED: &'static [(Self, &'static str)] = &[$((Self::$name, $display)),*];
+        }
+    };
+}
+
+permissions! {
+    CREATE_INSTANT_INVITE = 0, "Create Invite";
+    KICK_MEMBERS = 1, "Kick Members";
+    BAN_MEMBERS = 2, "Ban Members";
+    ADMINISTRATOR = 3, "Administrator";
+    MANAGE_CHANNELS = 4, "Manage Channels";
+    MANAGE_GUILD = 5, "Manage Server";
+    ADD_REACTIONS = 6, "Add Reactions";
+    VIEW_AUDIT_LOG = 7, "View Audit Log";
+    PRIORITY_SPEAKER = 8, "Priority Speaker";
+    STREAM = 9, "Video";
+    VIEW_CHANNEL = 10, "View Channel";
+    SEND_MESSAGES = 11, "Send Messages";
+    SEND_TTS_MESSAGES = 12, "Send Text-to-Speech Messages";
+    MANAGE_MESSAGES = 13, "Manage Messages";
+    EMBED_LINKS = 14, "Embed Links";
+    ATTACH_FILES = 15, "Attach Files";
+    READ_MESSAGE_HISTORY = 16, "Read Message History";
+    MENTION_EVERYONE = 17, "Mention Everyone";
+    USE_EXTERNAL_EMOJIS = 18, "Use External Emojis";
+    VIEW_GUILD_INSIGHTS = 19, "View Server Insights";
+    CONNECT = 20, "Connect";
+    SPEAK = 21, "Speak";
+    MUTE_MEMBERS = 22, "Mute Members";
+    DEAFEN_MEMBERS = 23, "Deafen Members";
+    MOVE_MEMBERS = 24, "Move Members";
+    USE_VAD = 25, "Use Voice Activity";
+    CHANGE_NICKNAME = 26, "Change Nickname";
+    MANAGE_NICKNAMES = 27, "Manage Nicknames";
+    MANAGE_ROLES = 28, "Manage Roles";
+    MANAGE_WEBHOOKS = 29, "Manage Webhooks";
+    MANAGE_EMOJIS = 30, "Manage Emojis and Stickers";
+    USE_SLASH_COMMANDS = 31, "Use Application Commands";
+    REQUEST_TO_SPEAK = 32, "Request to Speak";
+    MANAGE_THREADS = 34, "Manage Threads";
+    CREATE_PUBLIC_THREADS = 35, "Create Public Threads";
+    CREATE_PRIVATE_THREADS = 36, "Create Private Threads";
+    USE_EXTERNAL_STICKERS = 37, "Use External Stickers";
+    SEND_MESSAGES_IN_THREADS = 38, "Send Messages in Threads";
+    START_EMBEDDED_ACTIVITIES = 39, "Start Activities";
+    MODERATE_MEMBERS = 40, "Timeout Members";
+}
+
+impl Permissions {
+    /// Permissions that are implicitly denied if [`Self::SEND_MESSAGES`] is denied.
+    const SEND_MESSAGES_IMPLIED: Self = Self {
+        bits: Self::SEND_TTS_MESSAGES.bits
+            | Self::MENTION_EVERYONE.bits
+            | Self::EMBED_LINKS.bits
+            | Self::ATTACH_FILES.bits,
+    };
+
+    /// Returns an empty set of permissions.
+    pub const fn empty() -> Self {
+        Self { bits: 0 }
+    }
+
+    /// Returns a set containing all known permissions.
+    pub fn all() -> Self {
+        Self::NAMED
+            .iter()
+            .fold(Self::empty(), |acc, (perm, _)| acc | *perm)
+    }
+
+    /// Returns `true` if no permissions are set.
+    pub const fn is_empty(self) -> bool {
+        self.bits == 0
+    }
+
+    /// Returns `true` if all permissions in `other` are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.bits & other.bits == other.bits
+    }
+
+    /// Returns the permissions in `required` that are not set in `self`. Having
+    /// [`Self::ADMINISTRATOR`] implies all other permissions.
+    pub const fn missing(self, required: Self) -> Self {
+        if self.contains(Self::ADMINISTRATOR) {
+            return Self::empty();
+        }
+
+        Self {
+            bits: required.bits & !self.bits,
+        }
+    }
+
+    /// Returns the human-readable names of all set permissions, as shown in
+    /// the Discord client. Unknown bits are skipped.
+    pub fn names(self) -> Vec<&'static str> {
+        Self::NAMED
+            .iter()
+            .filter(|(perm, _)| self.contains(*perm))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+
+    /// Applies the permission `overwrites` of a channel in the guild `guild_id`
+    /// to the guild-wide permissions of a member. `roles` are the roles of the
+    /// member with the id `user_id`.
+    ///
+    /// Overwrites are applied in the same order as Discord applies them: the
+    /// `@everyone` overwrite, then all role overwrites, then the member overwrite.
+    /// Members with [`Self::ADMINISTRATOR`] bypass all overwrites.
+    pub fn with_overwrites(
+        self,
+        overwrites: &[PermissionOverwrite],
+        guild_id: GuildId,
+        user_id: UserId,
+        roles: &[RoleId],
+    ) -> Self {
+        if self.contains(Self::ADMINISTRATOR) {
+            return Self::all();
+        }
+
+        let mut permissions = self;
+
+        // The @everyone role shares the id of the guild.
+        let everyone = RoleId(guild_id.0);
+        for overwrite in overwrites {
+            if matches!(overwrite.kind, PermissionOverwriteKind::Role(id) if id == everyone) {
+                permissions = permissions.apply(overwrite.allow, overwrite.deny);
+            }
+        }
+
+        let (mut allow, mut deny) = (Self::empty(), Self::empty());
+        for overwrite in overwrites {
+            if let PermissionOverwriteKind::Role(id) = overwrite.kind {
+                if id != everyone && roles.contains(&id) {
+                    allow |= overwrite.allow;
+                    deny |= overwrite.deny;
+                }
+            }
+        }
+        permissions = permissions.apply(allow, deny);
+
+        for overwrite in overwrites {
+            if matches!(overwrite.kind, PermissionOverwriteKind::Member(id) if id == user_id) {
+                permissions = permissions.apply(overwrite.allow, overwrite.deny);
+            }
+        }
+
+        // Members that cannot view a channel have no permissions in it.
+        if !permissions.contains(Self::VIEW_CHANNEL) {
+            return Self::empty();
+        }
+
+        if !permissions.contains(Self::SEND_MESSAGES) {
+            permissions = permissions & !Self::SEND_MESSAGES_IMPLIED;
+        }
+
+        permissions
+    }
+
+    fn apply(self, allow: Self, deny: Self) -> Self {
+        (self & !deny) | allow
+    }
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl BitOr for Permissions {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self {
+            bits: self.bits | rhs.bits,
+        }
+    }
+}
+
+impl BitOrAssign for Permissions {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.bits |= rhs.bits;
+    }
+}
+
+impl BitAnd for Permissions {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self {
+            bits: self.bits & rhs.bits,
+        }
+    }
+}
+
+impl Not for Permissions {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self { bits: !self.bits }
+    }
+}
+
+impl FromIterator<Permissions> for Permissions {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = Permissions>,
+    {
+        iter.into_iter().fold(Self::empty(), |acc, perm| acc | perm)
+    }
+}
+
+/// Displays the human-readable names of all set permissions, separated by
+/// commas.
+impl Display for Permissions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.names().join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Permissions;
+    use crate::model::channel::{PermissionOverwrite, PermissionOverwriteKind};
+    use crate::model::id::{GuildId, RoleId, UserId};
+
+    fn overwrite(
+        kind: PermissionOverwriteKind,
+        allow: Permissions,
+        deny: Permissions,
+    ) -> PermissionOverwrite {
+        PermissionOverwrite { allow, deny, kind }
+    }
+
+    #[test]
+    fn test_permissions_missing() {
+        let perms = Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES;
+
+        assert!(perms.contains(Permissions::SEND_MESSAGES));
+        assert!(!perms.contains(Permissions::SEND_MESSAGES | Permissions::MANAGE_ROLES));
+        assert_eq!(
+            perms.missing(Permissions::SEND_MESSAGES | Permissions::MANAGE_ROLES),
+            Permissions::MANAGE_ROLES
+        );
+        assert!(Permissions::ADMINISTRATOR
+            .missing(Permissions::MANAGE_ROLES)
+            .is_empty());
+
+        assert_eq!(
+            (Permissions::MANAGE_ROLES | Permissions::MANAGE_MESSAGES).to_string(),
+            "Manage Messages, Manage Roles"
+        );
+    }
+
+    #[test]
+    fn test_permissions_with_overwrites() {
+        let guild_id = GuildId(1);
+        let user_id = UserId(2);
+        let role = RoleId(3);
+        let other_role = RoleId(4);
+
+        let base = Permissions::VIEW_CHANNEL
+            | Permissions::SEND_MESSAGES
+            | Permissions::EMBED_LINKS
+            | Permissions::MANAGE_MESSAGES;
+
+        // No overwrites.
+        assert_eq!(base.with_overwrites(&[], guild_id, user_id, &[role]), base);
+
+        // @everyone deny, role allow.
+        let overwrites = [
+            overwrite(
+                PermissionOverwriteKind::Role(RoleId(guild_id.0)),
+                Permissions::empty(),
+                Permissions::MANAGE_MESSAGES,
+            ),
+            overwrite(
+                PermissionOverwriteKind::Role(role),
+                Permissions::MANAGE_MESSAGES,
+                Permissions::empty(),
+            ),
+        ];
+        assert_eq!(
+            base.with_overwrites(&overwrites, guild_id, user_id, &[role]),
+            base
+        );
+        assert_eq!(
+            base.with_overwrites(&overwrites, guild_id, user_id, &[other_role]),
+            base & !Permissions::MANAGE_MESSAGES
+        );
+
+        // Member overwrites take precedence over role overwrites.
+        let overwrites = [
+            overwrite(
+                PermissionOverwriteKind::Role(role),
+                Permissions::MANAGE_MESSAGES,
+                Permissions::empty(),
+            ),
+            overwrite(
+                PermissionOverwriteKind::Member(user_id),
+                Permissions::empty(),
+                Permissions::MANAGE_MESSAGES,
+            ),
+        ];
+        assert_eq!(
+            base.with_overwrites(&overwrites, guild_id, user_id, &[role]),
+            base & !Permissions::MANAGE_MESSAGES
+        );
+
+        // Denying SEND_MESSAGES implicitly denies EMBED_LINKS.
+        let overwrites = [overwrite(
+            PermissionOverwriteKind::Role(role),
+            Permissions::empty(),
+            Permissions::SEND_MESSAGES,
+        )];
+        assert_eq!(
+            base.with_overwrites(&overwrites, guild_id, user_id, &[role]),
+            Permissions::VIEW_CHANNEL | Permissions::MANAGE_MESSAGES
+        );
+
+        // Denying VIEW_CHANNEL denies everything.
+        let overwrites = [overwrite(
+            PermissionOverwriteKind::Member(user_id),
+            Permissions::empty(),
+            Permissions::VIEW_CHANNEL,
+        )];
+        assert!(base
+            .with_overwrites(&overwrites, guild_id, user_id, &[role])
+            .is_empty());
+
+        // Administrators bypass overwrites.
+        assert_eq!(
+            Permissions::ADMINISTRATOR.with_overwrites(&overwrites, guild_id, user_id, &[role]),
+            Permissions::all()
+        );
+    }
+}
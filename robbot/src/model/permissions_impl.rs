@@ -7,3 +7,9 @@ impl From<Permissions> for permissions::Permissions {
         Self { bits: src.bits }
     }
 }
+
+impl From<permissions::Permissions> for Permissions {
+    fn from(src: permissions::Permissions) -> Self {
+        Self::from_bits_truncate(src.bits)
+    }
+}
@@ -43,6 +43,33 @@ impl User {
     pub fn mention(&self) -> UserMention {
         UserMention::new(self.id)
     }
+
+    /// Returns the URL of the avatar of the user, or of the default avatar if
+    /// the user has none.
+    pub fn avatar_url(&self) -> String {
+        match &self.avatar {
+            Some(avatar) => {
+                let ext = if avatar.starts_with("a_") {
+                    "gif"
+                } else {
+                    "png"
+                };
+                format!(
+                    "https://cdn.discordapp.com/avatars/{}/{}.{}",
+                    self.id, avatar, ext
+                )
+            }
+            None => {
+                // Users without a discriminator use the new default avatars.
+                let index = match self.discriminator {
+                    0 => (self.id.0 >> 22) % 6,
+                    discriminator => u64::from(discriminator) % 5,
+                };
+
+                format!("https://cdn.discordapp.com/embed/avatars/{}.png", index)
+            }
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Encode, Decode)]
@@ -13,8 +13,14 @@
 //!
 //! External types:
 //! - [`chrono::DateTime`]
+//!
+//! Types implementing serde's `Serialize` and `Deserialize` can use the same format
+//! through the [`serde_bridge`] module.
+
+pub mod serde_bridge;
 
 use std::io::{self, Read, Write};
+use std::mem;
 use std::string::FromUtf8Error;
 
 use thiserror::Error;
@@ -39,7 +45,27 @@ pub enum Error {
     Io(#[from] io::Error),
     #[error(transparent)]
     FromUtf8Error(#[from] FromUtf8Error),
-}
+    /// An error returned by a serde `Serialize` or `Deserialize` implementation.
+    #[error("{0}")]
+    Custom(String),
+    /// A decoded boolean was neither `0` nor `1`.
+    #[error("invalid boolean value {0}")]
+    InvalidBool(u8),
+    /// A decoded enum tag does not name a variant of the enum.
+    #[error("invalid variant {variant} of enum {ty}")]
+    InvalidVariant { ty: &'static str, variant: u8 },
+    /// A decoded length does not fit into `usize`.
+    #[error("length {0} exceeds usize")]
+    InvalidLength(u64),
+    /// A date cannot be encoded as nanoseconds since the unix epoch in an
+    /// `i64`, i.e. it is before 1677 or after 2262.
+    #[error("date {0} is out of the range of nanosecond timestamps")]
+    DateTimeOutOfRange(chrono::DateTime<chrono::Utc>),
+}
+
+/// The maximum number of bytes allocated upfront for a length-prefixed value.
+/// The length is untrusted, larger values grow while they are decoded.
+const MAX_PREALLOC: usize = 4096;
 
 pub struct Encoder<W>
 where
@@ -143,7 +169,7 @@ where
         match value {
             0 => Ok(false),
             1 => Ok(true),
-            v => panic!("Invalid decoded boolean value: {}", v),
+            v => Err(Error::InvalidBool(v)),
         }
     }
 
@@ -217,6 +243,13 @@ where
         Ok(f64::from_be_bytes(buf))
     }
 
+    /// Decodes a length prefix, e.g. of a [`Vec`].
+    pub fn decode_len(&mut self) -> Result<usize> {
+        let len = self.decode_u64()?;
+
+        usize::try_from(len).or(Err(Error::InvalidLength(len)))
+    }
+
     pub fn decode_bytes(&mut self, buf: &mut [u8]) -> Result<()> {
         self.read(buf)?;
         Ok(())
@@ -402,7 +435,7 @@ where
     where
         W: Write,
     {
-        let inner: &T = &*self;
+        let inner: &T = self;
 
         inner.encode(encoder)
     }
@@ -413,7 +446,11 @@ impl Encode for chrono::DateTime<chrono::Utc> {
     where
         W: Write,
     {
-        encoder.encode_i64(self.timestamp_nanos())
+        let timestamp_nanos = self
+            .timestamp_nanos_opt()
+            .ok_or(Error::DateTimeOutOfRange(*self))?;
+
+        encoder.encode_i64(timestamp_nanos)
     }
 }
 
@@ -524,8 +561,8 @@ where
     where
         R: Read,
     {
-        let len = decoder.decode_u64()?;
-        let mut vec = Vec::with_capacity(len as usize);
+        let len = decoder.decode_len()?;
+        let mut vec = Vec::with_capacity(len.min(MAX_PREALLOC / mem::size_of::<T>().max(1)));
 
         for _ in 0..len {
             let item = T::decode(decoder)?;
@@ -584,19 +621,40 @@ impl Decode for chrono::DateTime<chrono::Utc> {
     {
         let timestamp_nanos = decoder.decode_i64()?;
 
-        let secs = timestamp_nanos / 1_000_000;
-        let nsecs = (timestamp_nanos % 1_000_000) as u32;
-
-        Ok(chrono::DateTime::from_utc(
-            chrono::NaiveDateTime::from_timestamp(secs, nsecs),
-            chrono::Utc,
+        Ok(chrono::TimeZone::timestamp_nanos(
+            &chrono::Utc,
+            timestamp_nanos,
         ))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Encoder;
+    use super::{Decode, Decoder, Encode, Encoder, Error};
+    use crate::model::channel::ChannelKind;
+
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_encode_datetime_out_of_range() {
+        let mut buf = Vec::new();
+        let mut encoder = Encoder::new(&mut buf);
+
+        let datetime = Utc.timestamp_nanos(i64::MAX);
+        datetime.encode(&mut encoder).unwrap();
+        assert_eq!(buf, i64::MAX.to_be_bytes());
+
+        // Not silently encoded as the unix epoch.
+        let mut buf = Vec::new();
+        let mut encoder = Encoder::new(&mut buf);
+
+        let datetime = Utc.with_ymd_and_hms(2300, 1, 1, 0, 0, 0).unwrap();
+        assert!(matches!(
+            datetime.encode(&mut encoder),
+            Err(Error::DateTimeOutOfRange(date)) if date == datetime
+        ));
+        assert!(buf.is_empty());
+    }
 
     #[test]
     fn test_encoder_bool() {
@@ -753,4 +811,39 @@ mod tests {
             ]
         );
     }
+
+    // Regression inputs of the `decode` fuzz target.
+    #[test]
+    fn test_decoder_invalid() {
+        let mut decoder = Decoder::new(&[0x02][..]);
+        assert!(matches!(decoder.decode_bool(), Err(Error::InvalidBool(2))));
+
+        let mut decoder = Decoder::new(&[0x01, 0x07][..]);
+        assert!(matches!(
+            Option::<bool>::decode(&mut decoder),
+            Err(Error::InvalidBool(7))
+        ));
+
+        let mut decoder = Decoder::new(&[0xFF][..]);
+        assert!(matches!(
+            ChannelKind::decode(&mut decoder),
+            Err(Error::InvalidVariant {
+                ty: "ChannelKind",
+                variant: 0xFF
+            })
+        ));
+
+        // A huge length is not allocated upfront.
+        let mut decoder = Decoder::new(&[0xFF; 9][..]);
+        assert!(matches!(
+            Vec::<u64>::decode(&mut decoder),
+            Err(Error::Io(_)) | Err(Error::InvalidLength(_))
+        ));
+
+        let mut decoder = Decoder::new(&[0, 0, 0, 0, 0, 0, 0, 2, 0xC3, 0x28][..]);
+        assert!(matches!(
+            String::decode(&mut decoder),
+            Err(Error::FromUtf8Error(_))
+        ));
+    }
 }
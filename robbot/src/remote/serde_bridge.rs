@@ -0,0 +1,1489 @@
+//! A serde [`Serializer`] and [`Deserializer`] producing the [`Encode`]/[`Decode`]
+//! wire format.
+//!
+//! Types implementing [`Serialize`] can be written with [`to_encoded_vec`] and read
+//! back using [`Decode`] and vice versa. The format maps to the serde data model as
+//! follows:
+//! - Integers and floats are encoded big-endian with their native width. `i128` and
+//!   `u128` are not supported.
+//! - `char` is encoded as a `u32`.
+//! - Strings, byte arrays, sequences and maps are prefixed with their length as a `u64`.
+//!   Maps are encoded as a sequence of key-value pairs.
+//! - `Option` is prefixed with a `bool` indicating whether a value follows.
+//! - Structs and tuples encode their fields in order without any prefix. Unit types
+//!   are not encoded at all.
+//! - Enums are prefixed with the variant index as a `u8`, followed by the fields of
+//!   the variant.
+//!
+//! The format is not self-describing, so deserializing with `deserialize_any` is not
+//! supported.
+//!
+//! `chrono::DateTime` serializes as a string. Use the [`datetime`] module on
+//! `DateTime` fields to use the same format as [`Encode`].
+//!
+//! [`Encode`]: super::Encode
+//! [`Decode`]: super::Decode
+
+use super::{Decoder, Encoder, Error, Result};
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+use serde::{Deserializer, Serializer};
+
+use std::fmt::Display;
+use std::io::{Read, Write};
+
+/// Serializes `value` into a buffer using the [`Encode`] wire format.
+///
+/// [`Encode`]: super::Encode
+pub fn to_encoded_vec<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    let mut buf = Vec::new();
+    value.serialize(&mut Encoder::new(&mut buf))?;
+
+    Ok(buf)
+}
+
+/// Deserializes a `T` from `buf` using the [`Decode`] wire format.
+///
+/// [`Decode`]: super::Decode
+pub fn from_encoded_slice<T>(buf: &[u8]) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(&mut Decoder::new(buf))
+}
+
+impl ser::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: Display,
+    {
+        Self::Custom(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: Display,
+    {
+        Self::Custom(msg.to_string())
+    }
+}
+
+impl<W> Encoder<W>
+where
+    W: Write,
+{
+    fn encode_len(&mut self, len: Option<usize>) -> Result<()> {
+        match len {
+            Some(len) => self.encode_u64(len as u64),
+            None => Err(ser::Error::custom("sequence length must be known")),
+        }
+    }
+
+    fn encode_variant(&mut self, variant_index: u32) -> Result<()> {
+        match u8::try_from(variant_index) {
+            Ok(index) => self.encode_u8(index),
+            Err(_) => Err(ser::Error::custom(format!(
+                "The maxium number of enum variants is {}",
+                u8::MAX
+            ))),
+        }
+    }
+}
+
+impl<W> Serializer for &mut Encoder<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.encode_bool(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.encode_i8(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.encode_i16(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.encode_i32(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.encode_i64(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.encode_u8(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.encode_u16(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.encode_u32(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.encode_u64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.encode_f32(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.encode_f64(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.encode_u32(v as u32)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.encode_u64(v.len() as u64)?;
+        self.encode_bytes(v)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.encode_bool(false)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.encode_bool(true)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.encode_variant(variant_index)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.encode_variant(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self> {
+        self.encode_len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.encode_variant(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self> {
+        self.encode_len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.encode_variant(variant_index)?;
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl<W> ser::SerializeSeq for &mut Encoder<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W> ser::SerializeTuple for &mut Encoder<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W> ser::SerializeTupleStruct for &mut Encoder<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W> ser::SerializeTupleVariant for &mut Encoder<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W> ser::SerializeMap for &mut Encoder<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W> ser::SerializeStruct for &mut Encoder<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W> ser::SerializeStructVariant for &mut Encoder<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<R> Decoder<R>
+where
+    R: Read,
+{
+    fn decode_byte_buf(&mut self) -> Result<Vec<u8>> {
+        let len = self.decode_len()?;
+
+        // Read through `take` instead of allocating `len` bytes upfront, the length
+        // is untrusted.
+        let mut buf = Vec::new();
+        (&mut self.reader).take(len as u64).read_to_end(&mut buf)?;
+
+        if buf.len() != len {
+            return Err(de::Error::invalid_length(
+                buf.len(),
+                &"a length-prefixed buffer",
+            ));
+        }
+
+        Ok(buf)
+    }
+}
+
+impl<'de, R> Deserializer<'de> for &mut Decoder<R>
+where
+    R: Read,
+{
+    type Error = Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::custom(
+            "the encoded format is not self-describing",
+        ))
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.decode_u8()? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            v => Err(de::Error::invalid_value(
+                de::Unexpected::Unsigned(v as u64),
+                &"a boolean",
+            )),
+        }
+    }
+
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i8(self.decode_i8()?)
+    }
+
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i16(self.decode_i16()?)
+    }
+
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i32(self.decode_i32()?)
+    }
+
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i64(self.decode_i64()?)
+    }
+
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u8(self.decode_u8()?)
+    }
+
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u16(self.decode_u16()?)
+    }
+
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u32(self.decode_u32()?)
+    }
+
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u64(self.decode_u64()?)
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_f32(self.decode_f32()?)
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_f64(self.decode_f64()?)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let v = self.decode_u32()?;
+
+        match char::from_u32(v) {
+            Some(v) => visitor.visit_char(v),
+            None => Err(de::Error::invalid_value(
+                de::Unexpected::Unsigned(v as u64),
+                &"a char",
+            )),
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let buf = self.decode_byte_buf()?;
+
+        visitor.visit_string(String::from_utf8(buf)?)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_byte_buf(self.decode_byte_buf()?)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.decode_u8()? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            v => Err(de::Error::invalid_value(
+                de::Unexpected::Unsigned(v as u64),
+                &"an option tag",
+            )),
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let len = self.decode_len()?;
+
+        visitor.visit_seq(Access {
+            decoder: self,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(Access {
+            decoder: self,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let len = self.decode_len()?;
+
+        visitor.visit_map(Access {
+            decoder: self,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::custom("the encoded format has no identifiers"))
+    }
+
+    fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::custom(
+            "the encoded format is not self-describing",
+        ))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Accesses a fixed number of sequence elements or map entries.
+struct Access<'a, R>
+where
+    R: Read,
+{
+    decoder: &'a mut Decoder<R>,
+    remaining: usize,
+}
+
+impl<'de, R> de::SeqAccess<'de> for Access<'_, R>
+where
+    R: Read,
+{
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.decoder).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        // The length is untrusted, don't let visitors preallocate it.
+        Some(self.remaining.min(4096))
+    }
+}
+
+impl<'de, R> de::MapAccess<'de> for Access<'_, R>
+where
+    R: Read,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.decoder).map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.decoder)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining.min(4096))
+    }
+}
+
+impl<'de, R> de::EnumAccess<'de> for &mut Decoder<R>
+where
+    R: Read,
+{
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let index = self.decode_u8()? as u32;
+        let value = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(index))?;
+
+        Ok((value, self))
+    }
+}
+
+impl<'de, R> de::VariantAccess<'de> for &mut Decoder<R>
+where
+    R: Read,
+{
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+}
+
+/// Serializes a `chrono::DateTime<Utc>` in the [`Encode`] format (nanoseconds since
+/// the unix epoch as an `i64`) for formats that are not human readable, and as an
+/// RFC 3339 string otherwise. Use with `#[serde(with = "...")]`.
+///
+/// [`Encode`]: super::Encode
+pub mod datetime {
+    use crate::remote::Error;
+
+    use chrono::{DateTime, TimeZone, Utc};
+    use serde::{ser, Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Fails like [`Encode`] for dates out of the range of nanosecond
+    /// timestamps.
+    ///
+    /// [`Encode`]: crate::remote::Encode
+    pub fn serialize<S>(datetime: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            datetime.serialize(serializer)
+        } else {
+            match datetime.timestamp_nanos_opt() {
+                Some(timestamp_nanos) => serializer.serialize_i64(timestamp_nanos),
+                None => Err(ser::Error::custom(Error::DateTimeOutOfRange(*datetime))),
+            }
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            DateTime::deserialize(deserializer)
+        } else {
+            let timestamp_nanos = i64::deserialize(deserializer)?;
+            Ok(Utc.timestamp_nanos(timestamp_nanos))
+        }
+    }
+
+    /// The same as [`datetime`](super::datetime) for an `Option<DateTime<Utc>>`.
+    pub mod option {
+        use chrono::{DateTime, Utc};
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        #[derive(Serialize, Deserialize)]
+        struct Wrapper(#[serde(with = "super")] DateTime<Utc>);
+
+        pub fn serialize<S>(
+            datetime: &Option<DateTime<Utc>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            datetime.map(Wrapper).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let datetime: Option<Wrapper> = Option::deserialize(deserializer)?;
+            Ok(datetime.map(|datetime| datetime.0))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_encoded_slice, to_encoded_vec};
+    use crate::model::channel::{
+        Attachment, CategoryChannel, Channel, ChannelKind, Embed, EmbedAuthor, EmbedField,
+        EmbedFooter, EmbedImage, EmbedProvider, EmbedThumbnail, EmbedVideo, GuildChannel, Message,
+        MessageKind, MessageReaction, MessageReference, PermissionOverwrite,
+        PermissionOverwriteKind, PrivateChannel, Reaction, ReactionType,
+    };
+    use crate::model::guild::{Member, PartialMember};
+    use crate::model::id::{
+        AttachmentId, ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId, WebhookId,
+    };
+    use crate::model::permissions::Permissions;
+    use crate::model::user::{OnlineStatus, User};
+    use crate::remote::{Decode, Decoder, Encode, Encoder};
+    use crate::util::color::Color;
+
+    use chrono::{DateTime, TimeZone, Utc};
+    use proptest::collection::vec;
+    use proptest::option::of;
+    use proptest::prelude::*;
+    use proptest::sample::select;
+    use proptest::test_runner::TestCaseError;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    fn encode<T>(value: &T) -> Vec<u8>
+    where
+        T: Encode,
+    {
+        let mut buf = Vec::new();
+        value.encode(&mut Encoder::new(&mut buf)).unwrap();
+        buf
+    }
+
+    /// Asserts that `Encode` and the serde bridge produce the same output and that
+    /// decoding the output with either `Decode` or the serde bridge yields the same
+    /// value. The models don't implement `PartialEq`, so decoded values are compared
+    /// by encoding them again.
+    fn assert_bridge<T>(value: &T) -> Result<(), TestCaseError>
+    where
+        T: Encode + Decode + Serialize + DeserializeOwned,
+    {
+        let encoded = encode(value);
+        let bridged = to_encoded_vec(value).unwrap();
+        prop_assert_eq!(&encoded, &bridged);
+
+        let decoded = T::decode(&mut Decoder::new(&bridged[..])).unwrap();
+        prop_assert_eq!(&encode(&decoded), &encoded);
+
+        let decoded: T = from_encoded_slice(&encoded).unwrap();
+        prop_assert_eq!(&encode(&decoded), &encoded);
+
+        Ok(())
+    }
+
+    fn string() -> impl Strategy<Value = String> {
+        ".{0,8}"
+    }
+
+    fn datetime() -> impl Strategy<Value = DateTime<Utc>> {
+        any::<i64>().prop_map(|nanos| Utc.timestamp_nanos(nanos))
+    }
+
+    fn user() -> impl Strategy<Value = User> {
+        (
+            any::<u64>(),
+            of(string()),
+            any::<bool>(),
+            any::<u16>(),
+            string(),
+            of(string()),
+            of(any::<u32>()),
+        )
+            .prop_map(
+                |(id, avatar, bot, discriminator, name, banner, accent_color)| User {
+                    id: UserId(id),
+                    avatar,
+                    bot,
+                    discriminator,
+                    name,
+                    banner,
+                    accent_color: accent_color.map(Color),
+                },
+            )
+    }
+
+    fn member() -> impl Strategy<Value = Member> {
+        (
+            (any::<bool>(), any::<u64>(), of(datetime()), any::<bool>()),
+            (of(string()), vec(any::<u64>(), 0..4), user()),
+            (any::<bool>(), of(string()), of(datetime())),
+        )
+            .prop_map(
+                |(
+                    (deaf, guild_id, joined_at, mute),
+                    (nick, roles, user),
+                    (pending, avatar, communication_disabled_until),
+                )| {
+                    Member {
+                        deaf,
+                        guild_id: GuildId(guild_id),
+                        joined_at,
+                        mute,
+                        nick,
+                        roles: roles.into_iter().map(RoleId).collect(),
+                        user,
+                        pending,
+                        avatar,
+                        communication_disabled_until,
+                    }
+                },
+            )
+    }
+
+    fn partial_member() -> impl Strategy<Value = PartialMember> {
+        (
+            (any::<bool>(), of(datetime()), any::<bool>(), of(string())),
+            (vec(any::<u64>(), 0..4), any::<bool>()),
+            (of(any::<u64>()), of(user())),
+        )
+            .prop_map(
+                |((deaf, joined_at, mute, nick), (roles, pending), (guild_id, user))| {
+                    PartialMember {
+                        deaf,
+                        joined_at,
+                        mute,
+                        nick,
+                        roles: roles.into_iter().map(RoleId).collect(),
+                        pending,
+                        guild_id: guild_id.map(GuildId),
+                        user,
+                    }
+                },
+            )
+    }
+
+    fn attachment() -> impl Strategy<Value = Attachment> {
+        (
+            (any::<u64>(), string(), of(any::<u64>()), string()),
+            (any::<u64>(), string(), of(any::<u64>())),
+            (of(string()), any::<bool>()),
+        )
+            .prop_map(
+                |(
+                    (id, filename, height, proxy_url),
+                    (size, url, width),
+                    (content_type, ephemeral),
+                )| {
+                    Attachment {
+                        id: AttachmentId(id),
+                        filename,
+                        height,
+                        proxy_url,
+                        size,
+                        url,
+                        width,
+                        content_type,
+                        ephemeral,
+                    }
+                },
+            )
+    }
+
+    fn embed() -> impl Strategy<Value = Embed> {
+        let author = (of(string()), string(), of(string()), of(string())).prop_map(
+            |(icon_url, name, proxy_icon_url, url)| EmbedAuthor {
+                icon_url,
+                name,
+                proxy_icon_url,
+                url,
+            },
+        );
+        let field =
+            (any::<bool>(), string(), string()).prop_map(|(inline, name, value)| EmbedField {
+                inline,
+                name,
+                value,
+            });
+        let footer =
+            (of(string()), of(string()), string()).prop_map(|(icon_url, proxy_icon_url, text)| {
+                EmbedFooter {
+                    icon_url,
+                    proxy_icon_url,
+                    text,
+                }
+            });
+        let image = (any::<u64>(), string(), string(), any::<u64>()).prop_map(
+            |(height, proxy_url, url, width)| EmbedImage {
+                height,
+                proxy_url,
+                url,
+                width,
+            },
+        );
+        let provider = (string(), of(string())).prop_map(|(name, url)| EmbedProvider { name, url });
+        let thumbnail = (any::<u64>(), string(), string(), any::<u64>()).prop_map(
+            |(height, proxy_url, url, width)| EmbedThumbnail {
+                height,
+                proxy_url,
+                url,
+                width,
+            },
+        );
+        let video = (any::<u64>(), string(), any::<u64>())
+            .prop_map(|(height, url, width)| EmbedVideo { height, url, width });
+
+        (
+            (of(author), any::<u32>(), of(string()), vec(field, 0..3)),
+            (of(footer), of(image), string(), of(provider)),
+            (
+                of(thumbnail),
+                of(string()),
+                of(string()),
+                of(string()),
+                of(video),
+            ),
+        )
+            .prop_map(
+                |(
+                    (author, color, description, fields),
+                    (footer, image, kind, provider),
+                    (thumbnail, title, timestamp, url, video),
+                )| Embed {
+                    author,
+                    color: Color(color),
+                    description,
+                    fields,
+                    footer,
+                    image,
+                    kind,
+                    provider,
+                    thumbnail,
+                    title,
+                    timestamp,
+                    url,
+                    video,
+                },
+            )
+    }
+
+    fn reaction_type() -> impl Strategy<Value = ReactionType> {
+        prop_oneof![
+            (any::<bool>(), any::<u64>(), of(string())).prop_map(|(animated, id, name)| {
+                ReactionType::Custom {
+                    animated,
+                    id: EmojiId(id),
+                    name,
+                }
+            }),
+            string().prop_map(ReactionType::Unicode),
+        ]
+    }
+
+    fn message_kind() -> impl Strategy<Value = MessageKind> {
+        select(vec![
+            MessageKind::Regular,
+            MessageKind::GroupRecipientAddition,
+            MessageKind::PinsAdd,
+            MessageKind::MemberJoin,
+            MessageKind::InlineReply,
+            MessageKind::ThreadStarterMessage,
+            MessageKind::Unknown,
+        ])
+    }
+
+    fn message_reference() -> impl Strategy<Value = MessageReference> {
+        (of(any::<u64>()), any::<u64>(), of(any::<u64>())).prop_map(
+            |(message_id, channel_id, guild_id)| MessageReference {
+                message_id: message_id.map(MessageId),
+                channel_id: ChannelId(channel_id),
+                guild_id: guild_id.map(GuildId),
+            },
+        )
+    }
+
+    fn message_without_reference() -> impl Strategy<Value = Message> {
+        (
+            (any::<u64>(), vec(attachment(), 0..2), user(), any::<u64>()),
+            (
+                string(),
+                of(datetime()),
+                vec(embed(), 0..2),
+                of(any::<u64>()),
+            ),
+            (message_kind(), of(partial_member()), any::<bool>()),
+            (
+                vec(any::<u64>(), 0..3),
+                vec(any::<u64>(), 0..3),
+                vec(user(), 0..2),
+            ),
+            (any::<bool>(), vec(message_reaction(), 0..2), datetime()),
+            (any::<bool>(), of(any::<u64>()), of(message_reference())),
+        )
+            .prop_map(
+                |(
+                    (id, attachments, author, channel_id),
+                    (content, edited_timestamp, embeds, guild_id),
+                    (kind, member, mention_everyone),
+                    (mention_roles, mention_channels, mentions),
+                    (pinned, reactions, timestamp),
+                    (tts, webhook_id, message_reference),
+                )| Message {
+                    id: MessageId(id),
+                    attachments,
+                    author,
+                    channel_id: ChannelId(channel_id),
+                    content,
+                    edited_timestamp,
+                    embeds,
+                    guild_id: guild_id.map(GuildId),
+                    kind,
+                    member,
+                    mention_everyone,
+                    mention_roles: mention_roles.into_iter().map(RoleId).collect(),
+                    mention_channels: mention_channels.into_iter().map(ChannelId).collect(),
+                    mentions,
+                    pinned,
+                    reactions,
+                    timestamp,
+                    tts,
+                    webhook_id: webhook_id.map(WebhookId),
+                    message_reference,
+                    referenced_message: None,
+                },
+            )
+    }
+
+    fn message() -> impl Strategy<Value = Message> {
+        (message_without_reference(), of(message_without_reference())).prop_map(
+            |(mut message, referenced_message)| {
+                message.referenced_message = referenced_message.map(Box::new);
+                message
+            },
+        )
+    }
+
+    fn message_reaction() -> impl Strategy<Value = MessageReaction> {
+        (any::<u64>(), any::<bool>(), reaction_type()).prop_map(|(count, me, reaction_type)| {
+            MessageReaction {
+                count,
+                me,
+                reaction_type,
+            }
+        })
+    }
+
+    fn reaction() -> impl Strategy<Value = Reaction> {
+        (
+            any::<u64>(),
+            reaction_type(),
+            any::<u64>(),
+            of(any::<u64>()),
+            of(any::<u64>()),
+            of(partial_member()),
+        )
+            .prop_map(
+                |(channel_id, emoji, message_id, user_id, guild_id, member)| Reaction {
+                    channel_id: ChannelId(channel_id),
+                    emoji,
+                    message_id: MessageId(message_id),
+                    user_id: user_id.map(UserId),
+                    guild_id: guild_id.map(GuildId),
+                    member,
+                },
+            )
+    }
+
+    fn channel_kind() -> impl Strategy<Value = ChannelKind> {
+        select(vec![
+            ChannelKind::Text,
+            ChannelKind::Private,
+            ChannelKind::Voice,
+            ChannelKind::Category,
+            ChannelKind::News,
+            ChannelKind::Store,
+            ChannelKind::NewsThread,
+            ChannelKind::PublicThread,
+            ChannelKind::PrivateThread,
+            ChannelKind::Stage,
+            ChannelKind::Unknown,
+        ])
+    }
+
+    fn permission_overwrite() -> impl Strategy<Value = PermissionOverwrite> {
+        let kind = prop_oneof![
+            any::<u64>().prop_map(|id| PermissionOverwriteKind::Member(UserId(id))),
+            any::<u64>().prop_map(|id| PermissionOverwriteKind::Role(RoleId(id))),
+        ];
+
+        (any::<u64>(), any::<u64>(), kind).prop_map(|(allow, deny, kind)| PermissionOverwrite {
+            allow: Permissions { bits: allow },
+            deny: Permissions { bits: deny },
+            kind,
+        })
+    }
+
+    fn guild_channel() -> impl Strategy<Value = GuildChannel> {
+        (
+            (
+                any::<u64>(),
+                of(any::<u64>()),
+                of(any::<u64>()),
+                any::<u64>(),
+            ),
+            (channel_kind(), of(any::<u64>()), of(datetime()), string()),
+            (
+                vec(permission_overwrite(), 0..3),
+                any::<i64>(),
+                of(string()),
+            ),
+            (of(any::<u64>()), any::<bool>()),
+        )
+            .prop_map(
+                |(
+                    (id, bitrate, category_id, guild_id),
+                    (kind, last_message_id, last_pin_timestamp, name),
+                    (permission_overwrites, position, topic),
+                    (user_limit, nsfw),
+                )| GuildChannel {
+                    id: ChannelId(id),
+                    bitrate,
+                    category_id: category_id.map(ChannelId),
+                    guild_id: GuildId(guild_id),
+                    kind,
+                    last_message_id: last_message_id.map(MessageId),
+                    last_pin_timestamp,
+                    name,
+                    permission_overwrites,
+                    position,
+                    topic,
+                    user_limit,
+                    nsfw,
+                },
+            )
+    }
+
+    fn private_channel() -> impl Strategy<Value = PrivateChannel> {
+        (
+            any::<u64>(),
+            of(any::<u64>()),
+            of(datetime()),
+            channel_kind(),
+            user(),
+        )
+            .prop_map(
+                |(id, last_message_id, last_pin_timestamp, kind, recipient)| PrivateChannel {
+                    id: ChannelId(id),
+                    last_message_id: last_message_id.map(MessageId),
+                    last_pin_timestamp,
+                    kind,
+                    recipient,
+                },
+            )
+    }
+
+    fn category_channel() -> impl Strategy<Value = CategoryChannel> {
+        (
+            (any::<u64>(), any::<u64>(), of(any::<u64>()), any::<i64>()),
+            (channel_kind(), string(), any::<bool>()),
+            vec(permission_overwrite(), 0..3),
+        )
+            .prop_map(
+                |(
+                    (id, guild_id, category_id, position),
+                    (kind, name, nsfw),
+                    permission_overwrites,
+                )| {
+                    CategoryChannel {
+                        id: ChannelId(id),
+                        guild_id: GuildId(guild_id),
+                        category_id: category_id.map(ChannelId),
+                        position,
+                        kind,
+                        name,
+                        nsfw,
+                        permission_overwrites,
+                    }
+                },
+            )
+    }
+
+    fn channel() -> impl Strategy<Value = Channel> {
+        prop_oneof![
+            guild_channel().prop_map(Channel::Guild),
+            private_channel().prop_map(Channel::Private),
+            category_channel().prop_map(Channel::Category),
+        ]
+    }
+
+    fn online_status() -> impl Strategy<Value = OnlineStatus> {
+        select(vec![
+            OnlineStatus::Online,
+            OnlineStatus::Idle,
+            OnlineStatus::DoNotDisturb,
+            OnlineStatus::Invisible,
+            OnlineStatus::Offline,
+        ])
+    }
+
+    proptest! {
+        #[test]
+        fn test_serde_bridge_primitives(
+            a in any::<bool>(),
+            b in any::<(u8, u16, u32, u64)>(),
+            c in any::<(i8, i16, i32, i64)>(),
+            d in any::<(f32, f64)>(),
+            e in of(string()),
+            f in vec(string(), 0..4),
+            g in datetime(),
+        ) {
+            assert_bridge(&a)?;
+            assert_bridge(&b.0)?;
+            assert_bridge(&b.1)?;
+            assert_bridge(&b.2)?;
+            assert_bridge(&b.3)?;
+            assert_bridge(&c.0)?;
+            assert_bridge(&c.1)?;
+            assert_bridge(&c.2)?;
+            assert_bridge(&c.3)?;
+            // Compare floats by their bits, NaN != NaN.
+            prop_assert_eq!(encode(&d.0), to_encoded_vec(&d.0).unwrap());
+            prop_assert_eq!(encode(&d.1), to_encoded_vec(&d.1).unwrap());
+            assert_bridge(&e)?;
+            assert_bridge(&f)?;
+
+            let bridged = to_encoded_vec(&BridgedDateTime(g)).unwrap();
+            prop_assert_eq!(encode(&g), bridged);
+        }
+
+        #[test]
+        fn test_serde_bridge_ids(id in any::<u64>()) {
+            assert_bridge(&AttachmentId(id))?;
+            assert_bridge(&ChannelId(id))?;
+            assert_bridge(&EmojiId(id))?;
+            assert_bridge(&GuildId(id))?;
+            assert_bridge(&MessageId(id))?;
+            assert_bridge(&RoleId(id))?;
+            assert_bridge(&UserId(id))?;
+            assert_bridge(&WebhookId(id))?;
+            assert_bridge(&Color(id as u32))?;
+            assert_bridge(&Permissions { bits: id })?;
+        }
+
+        #[test]
+        fn test_serde_bridge_user(user in user(), status in online_status()) {
+            assert_bridge(&user)?;
+            assert_bridge(&status)?;
+        }
+
+        #[test]
+        fn test_serde_bridge_member(member in member(), partial_member in partial_member()) {
+            assert_bridge(&member)?;
+            assert_bridge(&partial_member)?;
+        }
+
+        #[test]
+        fn test_serde_bridge_message(message in message()) {
+            assert_bridge(&message)?;
+        }
+
+        #[test]
+        fn test_serde_bridge_reaction(reaction in reaction()) {
+            assert_bridge(&reaction)?;
+        }
+
+        #[test]
+        fn test_serde_bridge_channel(channel in channel()) {
+            assert_bridge(&channel)?;
+        }
+    }
+
+    #[derive(Serialize)]
+    struct BridgedDateTime(#[serde(with = "super::datetime")] DateTime<Utc>);
+
+    #[test]
+    fn test_serde_bridge_datetime_human_readable() {
+        let datetime = Utc.timestamp_opt(1_600_000_000, 0).unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&BridgedDateTime(datetime)).unwrap(),
+            serde_json::to_string(&datetime).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_serde_bridge_datetime_out_of_range() {
+        let datetime = Utc.with_ymd_and_hms(2300, 1, 1, 0, 0, 0).unwrap();
+
+        assert!(to_encoded_vec(&BridgedDateTime(datetime)).is_err());
+    }
+
+    #[test]
+    fn test_serde_bridge_invalid_bool() {
+        assert!(from_encoded_slice::<bool>(&[2]).is_err());
+        assert!(from_encoded_slice::<Option<u8>>(&[2, 0]).is_err());
+    }
+
+    #[test]
+    fn test_serde_bridge_truncated_string() {
+        let buf = [0, 0, 0, 0, 0, 0, 0, 0xFF, b'a'];
+        assert!(from_encoded_slice::<String>(&buf).is_err());
+    }
+}
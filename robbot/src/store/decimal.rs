@@ -0,0 +1,304 @@
+//! An exact fixed-point [`Decimal`] number, e.g. for amounts of money.
+
+use std::cmp::Ordering;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// The maximum number of digits after the decimal point of a [`Decimal`].
+pub const MAX_SCALE: u32 = 30;
+
+/// The maximum number of digits before the decimal point of a [`Decimal`].
+/// Together with [`MAX_SCALE`] this is the range of a MySQL `DECIMAL(65, 30)`
+/// column.
+pub const MAX_INT_DIGITS: u32 = 35;
+
+/// An exact decimal number with the value `mantissa * 10^-scale`.
+///
+/// Decimals are always normalized, trailing zeros after the decimal point are
+/// removed. `1.50` and `1.5` are the same value with the same representation.
+///
+/// ```
+/// # use robbot::store::Decimal;
+/// let price: Decimal = "19.90".parse().unwrap();
+///
+/// assert_eq!(price, Decimal::new(199, 1).unwrap());
+/// assert_eq!(price.to_string(), "19.9");
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    pub const ZERO: Self = Self {
+        mantissa: 0,
+        scale: 0,
+    };
+
+    /// The largest `Decimal`, [`MAX_INT_DIGITS`] nines.
+    pub const MAX: Self = Self {
+        mantissa: 10i128.pow(MAX_INT_DIGITS) - 1,
+        scale: 0,
+    };
+
+    /// The smallest `Decimal`, the negative of [`Decimal::MAX`].
+    pub const MIN: Self = Self {
+        mantissa: -Self::MAX.mantissa,
+        scale: 0,
+    };
+
+    /// Creates a new `Decimal` with the value `mantissa * 10^-scale`. Returns
+    /// `None` if `scale` is greater than [`MAX_SCALE`] or the value has more
+    /// than [`MAX_INT_DIGITS`] digits before the decimal point.
+    pub fn new(mut mantissa: i128, mut scale: u32) -> Option<Self> {
+        if scale > MAX_SCALE || !in_range(mantissa, scale) {
+            return None;
+        }
+
+        while scale > 0 && mantissa % 10 == 0 {
+            mantissa /= 10;
+            scale -= 1;
+        }
+
+        Some(Self { mantissa, scale })
+    }
+
+    /// Returns the mantissa of the normalized value.
+    pub const fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    /// Returns the number of digits after the decimal point of the normalized
+    /// value.
+    pub const fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub const fn is_negative(&self) -> bool {
+        self.mantissa < 0
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.scale.cmp(&other.scale) {
+            Ordering::Equal => self.mantissa.cmp(&other.mantissa),
+            Ordering::Less => cmp_scaled(self.mantissa, other.scale - self.scale, other.mantissa),
+            Ordering::Greater => {
+                cmp_scaled(other.mantissa, self.scale - other.scale, self.mantissa).reverse()
+            }
+        }
+    }
+}
+
+/// Returns `true` if `mantissa * 10^-scale` has at most [`MAX_INT_DIGITS`]
+/// digits before the decimal point.
+fn in_range(mantissa: i128, scale: u32) -> bool {
+    match 10u128.checked_pow(MAX_INT_DIGITS + scale) {
+        Some(limit) => mantissa.unsigned_abs() < limit,
+        // Every `i128` is below the limit.
+        None => true,
+    }
+}
+
+/// Compares `mantissa * 10^exp` with `other`.
+fn cmp_scaled(mantissa: i128, exp: u32, other: i128) -> Ordering {
+    match mantissa.checked_mul(10i128.pow(exp)) {
+        Some(scaled) => scaled.cmp(&other),
+        // The scaled value is greater in magnitude than any `i128`.
+        None => mantissa.cmp(&0),
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(v: i64) -> Self {
+        Self {
+            mantissa: v.into(),
+            scale: 0,
+        }
+    }
+}
+
+impl From<u64> for Decimal {
+    fn from(v: u64) -> Self {
+        Self {
+            mantissa: v.into(),
+            scale: 0,
+        }
+    }
+}
+
+/// Formats the decimal without an exponent, e.g. `-0.005`.
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_negative() {
+            f.write_str("-")?;
+        }
+
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+
+        if scale == 0 {
+            return f.write_str(&digits);
+        }
+
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (int, frac) = digits.split_at(digits.len() - scale);
+
+        write!(f, "{}.{}", int, frac)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Error)]
+pub enum ParseDecimalError {
+    #[error("invalid decimal")]
+    Invalid,
+    #[error("decimal has more than {MAX_INT_DIGITS} digits before the decimal point")]
+    Overflow,
+    #[error("decimal has more than {MAX_SCALE} digits after the decimal point")]
+    Scale,
+}
+
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+
+        let (int, frac) = match s.split_once('.') {
+            Some((int, frac)) if !frac.is_empty() => (int, frac),
+            Some(_) => return Err(ParseDecimalError::Invalid),
+            None => (s, ""),
+        };
+
+        if int.is_empty() || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit()) {
+            return Err(ParseDecimalError::Invalid);
+        }
+
+        let frac = frac.trim_end_matches('0');
+        if frac.len() > MAX_SCALE as usize {
+            return Err(ParseDecimalError::Scale);
+        }
+
+        let magnitude = int
+            .bytes()
+            .chain(frac.bytes())
+            .try_fold(0u128, |acc, b| {
+                acc.checked_mul(10)?.checked_add(u128::from(b - b'0'))
+            })
+            .ok_or(ParseDecimalError::Overflow)?;
+
+        let mantissa = match negative {
+            true if magnitude == i128::MIN.unsigned_abs() => i128::MIN,
+            true => -i128::try_from(magnitude).or(Err(ParseDecimalError::Overflow))?,
+            false => i128::try_from(magnitude).or(Err(ParseDecimalError::Overflow))?,
+        };
+
+        if !in_range(mantissa, frac.len() as u32) {
+            return Err(ParseDecimalError::Overflow);
+        }
+
+        Ok(Self {
+            mantissa,
+            scale: frac.len() as u32,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decimal, ParseDecimalError, MAX_INT_DIGITS, MAX_SCALE};
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_decimal_parse() {
+        assert_eq!(dec("1.50"), Decimal::new(15, 1).unwrap());
+        assert_eq!(dec("+007"), Decimal::from(7i64));
+        assert_eq!(dec("-0.0"), Decimal::ZERO);
+        assert_eq!(dec("-0.005"), Decimal::new(-5, 3).unwrap());
+        assert_eq!(
+            dec("0.000000000000000000000000000001"),
+            Decimal::new(1, MAX_SCALE).unwrap()
+        );
+        assert_eq!(dec("-99999999999999999999999999999999999"), Decimal::MIN);
+        assert_eq!(
+            dec("99999.999999999999999999999999999999"),
+            Decimal::new(99999999999999999999999999999999999, MAX_SCALE).unwrap()
+        );
+
+        assert_eq!("".parse::<Decimal>(), Err(ParseDecimalError::Invalid));
+        assert_eq!(".5".parse::<Decimal>(), Err(ParseDecimalError::Invalid));
+        assert_eq!("5.".parse::<Decimal>(), Err(ParseDecimalError::Invalid));
+        assert_eq!("1e5".parse::<Decimal>(), Err(ParseDecimalError::Invalid));
+        assert_eq!("--1".parse::<Decimal>(), Err(ParseDecimalError::Invalid));
+        assert_eq!(
+            "170141183460469231731687303715884105728".parse::<Decimal>(),
+            Err(ParseDecimalError::Overflow)
+        );
+        // Only `MAX_INT_DIGITS` digits fit into a MySQL column.
+        assert_eq!(
+            "100000000000000000000000000000000000".parse::<Decimal>(),
+            Err(ParseDecimalError::Overflow)
+        );
+        assert_eq!(
+            "-100000000000000000000000000000000000.5".parse::<Decimal>(),
+            Err(ParseDecimalError::Overflow)
+        );
+        assert_eq!(
+            "0.0000000000000000000000000000001".parse::<Decimal>(),
+            Err(ParseDecimalError::Scale)
+        );
+        assert_eq!(Decimal::new(1, MAX_SCALE + 1), None);
+        assert_eq!(Decimal::new(i128::MAX, 0), None);
+        assert_eq!(Decimal::new(10i128.pow(MAX_INT_DIGITS), 0), None);
+        assert_eq!(
+            Decimal::new(10i128.pow(MAX_INT_DIGITS), 1),
+            Some(dec("10000000000000000000000000000000000"))
+        );
+    }
+
+    #[test]
+    fn test_decimal_display() {
+        for s in [
+            "0",
+            "19.9",
+            "-0.005",
+            "123456789012345678.123456789012345678",
+            "-99999999999999999999999999999999999",
+        ] {
+            assert_eq!(dec(s).to_string(), s);
+        }
+
+        assert_eq!(Decimal::new(1500, 3).unwrap().to_string(), "1.5");
+    }
+
+    #[test]
+    fn test_decimal_cmp() {
+        assert!(dec("1.5") > dec("1.49"));
+        assert!(dec("-1.5") < dec("-1.49"));
+        assert!(dec("2") > dec("1.999999999999999999999999999999"));
+        assert!(dec("0") > dec("-0.000000000000000000000000000001"));
+
+        // Scaling the mantissa of the larger value overflows.
+        let max = Decimal::MAX;
+        let min = Decimal::MIN;
+        assert!(max > dec("0.000000000000000000000000000005"));
+        assert!(min < dec("-0.000000000000000000000000000005"));
+        assert!(dec("0.000000000000000000000000000005") < max);
+    }
+}
@@ -0,0 +1,81 @@
+//! Support for fieldless enums stored in a single field.
+//!
+//! A field whose type implements [`StoreEnum`] is stored using either the
+//! integer or the string representation of its value, chosen with
+//! `#[store(as = "u8")]` or `#[store(as = "string")]` on the field:
+//!
+//! ```ignore
+//! #[derive(StoreEnum)]
+//! pub enum RuleKind {
+//!     #[store(rename = "word")]
+//!     BannedWord,
+//!     InviteLink,
+//! }
+//!
+//! #[derive(StoreData)]
+//! pub struct AutomodRule {
+//!     #[store(as = "string")]
+//!     pub kind: RuleKind,
+//! }
+//! ```
+//!
+//! Queries match the representation of the value, `kind == RuleKind::InviteLink`
+//! works in the query macros. Reading a stored value that is not a known
+//! variant fails with [`UnknownVariant`].
+use thiserror::Error;
+
+/// A fieldless enum that can be stored as an integer or a string.
+///
+/// The [`StoreEnum`] derive macro implements `StoreEnum` for fieldless enums.
+/// The integer representation is the discriminant of the variant, the string
+/// representation is the name of the variant in `snake_case`, or the name
+/// given with `#[store(rename = "..")]` on the variant.
+///
+/// The discriminants of the variants must fit into a `u8`. Representations
+/// are written to the store, changing them makes stored values unreadable.
+///
+/// [`StoreEnum`]: ../derive.StoreEnum.html
+pub trait StoreEnum: Sized {
+    /// The name of the type, used in errors.
+    const NAME: &'static str;
+
+    /// Returns the integer representation of the value.
+    fn to_repr(&self) -> u8;
+
+    /// Returns the value with the integer representation `repr`, or `None`
+    /// if no variant has the representation.
+    fn from_repr(repr: u8) -> Option<Self>;
+
+    /// Returns the string representation of the value.
+    fn to_name(&self) -> &'static str;
+
+    /// Returns the value with the string representation `name`, or `None`
+    /// if no variant has the representation.
+    fn from_name(name: &str) -> Option<Self>;
+}
+
+/// A stored value is not a known variant of a [`StoreEnum`], e.g. because it
+/// was written by a newer version.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("unknown value `{value}` for `{ty}` in field `{key}`")]
+pub struct UnknownVariant {
+    /// The field of the value.
+    pub key: &'static str,
+    /// The [`StoreEnum::NAME`] of the type.
+    pub ty: &'static str,
+    /// The stored representation.
+    pub value: String,
+}
+
+impl UnknownVariant {
+    pub fn new<T>(key: &'static str, value: impl ToString) -> Self
+    where
+        T: StoreEnum,
+    {
+        Self {
+            key,
+            ty: T::NAME,
+            value: value.to_string(),
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Support for [`StoreData`] fields flattened into the fields of the
+//! containing data.
+//!
+//! A field marked `#[store(flatten)]` is stored as the fields of its type,
+//! each prefixed with the name of the field: a field `schedule` of a type
+//! with the fields `start` and `repeat` is stored in the fields
+//! `schedule_start` and `schedule_repeat`.
+//!
+//! [`StoreData`]: super::StoreData
+use super::{
+    Decimal, Deserialize, Deserializer, Serialize, Serializer, Store, TypeSerializer,
+    UnknownVariant,
+};
+
+use std::sync::OnceLock;
+
+/// The keys of some [`StoreData`], implemented by the [`StoreData`] derive
+/// macro. The keys of flattened fields are included with their prefix.
+///
+/// [`StoreData`]: super::StoreData
+pub trait StoreKeys {
+    /// Returns the keys of all fields in the order of their declaration.
+    fn keys() -> &'static [&'static str];
+}
+
+/// The keys of a flattened field: the keys of its type, prefixed with
+/// the name of the field.
+///
+/// The prefixed keys are built the first time they are used and kept for
+/// the lifetime of the program. The derive macro keeps them in `static`s,
+/// no lock is taken once they are built.
+#[derive(Debug)]
+pub struct PrefixedKeys {
+    prefix: &'static str,
+    keys: fn() -> &'static [&'static str],
+    prefixed: OnceLock<Box<[(&'static str, &'static str)]>>,
+}
+
+impl PrefixedKeys {
+    /// Creates new `PrefixedKeys` prefixing the `keys` of the flattened type
+    /// with `prefix`.
+    pub const fn new(prefix: &'static str, keys: fn() -> &'static [&'static str]) -> Self {
+        Self {
+            prefix,
+            keys,
+            prefixed: OnceLock::new(),
+        }
+    }
+
+    fn prefixed(&self) -> &[(&'static str, &'static str)] {
+        self.prefixed.get_or_init(|| {
+            (self.keys)()
+                .iter()
+                .map(|key| {
+                    let prefixed = format!("{}_{}", self.prefix, key);
+                    (*key, &*Box::leak(prefixed.into_boxed_str()))
+                })
+                .collect()
+        })
+    }
+
+    /// Returns all prefixed keys in the order of the keys of the flattened
+    /// type.
+    pub fn keys(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.prefixed().iter().map(|(_, prefixed)| *prefixed)
+    }
+
+    /// Returns the key `<prefix>_<key>`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not a key of the flattened type.
+    pub fn get(&self, key: &str) -> &'static str {
+        match self.prefixed().iter().find(|(k, _)| *k == key) {
+            Some((_, prefixed)) => prefixed,
+            None => panic!(
+                "`{}` is not a key of the flattened field `{}`",
+                key, self.prefix
+            ),
+        }
+    }
+}
+
+/// A [`Serializer`], [`Deserializer`] and [`TypeSerializer`] prefixing all
+/// keys before passing them on to the wrapped value. Used to (de)serialize
+/// flattened fields.
+#[derive(Debug)]
+pub struct Prefixed<'a, S> {
+    inner: &'a mut S,
+    keys: &'static PrefixedKeys,
+}
+
+impl<'a, S> Prefixed<'a, S> {
+    pub fn new(inner: &'a mut S, keys: &'static PrefixedKeys) -> Self {
+        Self { inner, keys }
+    }
+}
+
+macro_rules! forward_ser {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method(&mut self, v: $ty) -> Result<(), Self::Error> {
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+macro_rules! forward_type {
+    ($($method:ident),* $(,)?) => {
+        $(
+            fn $method(&mut self) -> Result<(), Self::Error> {
+                self.inner.$method()
+            }
+        )*
+    };
+}
+
+macro_rules! forward_de {
+    ($($method:ident -> $ty:ty),* $(,)?) => {
+        $(
+            fn $method(&mut self) -> Result<$ty, Self::Error> {
+                self.inner.$method()
+            }
+        )*
+    };
+}
+
+impl<'a, S, T> Serializer<T> for Prefixed<'a, S>
+where
+    S: Serializer<T>,
+    T: Store,
+{
+    type Error = S::Error;
+
+    forward_ser! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_str(&str),
+        serialize_u128(u128),
+        serialize_i128(i128),
+        serialize_decimal(&Decimal),
+    }
+
+    fn serialize_field<U>(&mut self, key: &'static str, value: &U) -> Result<(), Self::Error>
+    where
+        U: ?Sized + Serialize<T>,
+    {
+        self.inner.serialize_field(self.keys.get(key), value)
+    }
+
+    fn serialize_field_in<U>(&mut self, key: &'static str, values: &[U]) -> Result<(), Self::Error>
+    where
+        U: Serialize<T>,
+    {
+        self.inner.serialize_field_in(self.keys.get(key), values)
+    }
+}
+
+impl<'a, S, T> Deserializer<T> for Prefixed<'a, S>
+where
+    S: Deserializer<T>,
+    T: Store,
+{
+    type Error = S::Error;
+
+    forward_de! {
+        deserialize_bool -> bool,
+        deserialize_i8 -> i8,
+        deserialize_i16 -> i16,
+        deserialize_i32 -> i32,
+        deserialize_i64 -> i64,
+        deserialize_u8 -> u8,
+        deserialize_u16 -> u16,
+        deserialize_u32 -> u32,
+        deserialize_u64 -> u64,
+        deserialize_f32 -> f32,
+        deserialize_f64 -> f64,
+        deserialize_string -> String,
+        deserialize_u128 -> u128,
+        deserialize_i128 -> i128,
+        deserialize_decimal -> Decimal,
+    }
+
+    fn deserialize_field<U>(&mut self, key: &'static str) -> Result<U, Self::Error>
+    where
+        U: Sized + Deserialize<T>,
+    {
+        self.inner.deserialize_field(self.keys.get(key))
+    }
+
+    fn unknown_variant(&mut self, mut err: UnknownVariant) -> Self::Error {
+        err.key = self.keys.get(err.key);
+        self.inner.unknown_variant(err)
+    }
+}
+
+impl<'a, S, T> TypeSerializer<T> for Prefixed<'a, S>
+where
+    S: TypeSerializer<T>,
+    T: Store,
+{
+    type Error = S::Error;
+
+    forward_type! {
+        serialize_bool,
+        serialize_i8,
+        serialize_i16,
+        serialize_i32,
+        serialize_i64,
+        serialize_u8,
+        serialize_u16,
+        serialize_u32,
+        serialize_u64,
+        serialize_f32,
+        serialize_f64,
+        serialize_str,
+        serialize_u128,
+        serialize_i128,
+        serialize_decimal,
+    }
+
+    fn serialize_field<U>(&mut self, key: &'static str) -> Result<(), Self::Error>
+    where
+        U: ?Sized + Serialize<T>,
+    {
+        self.inner.serialize_field::<U>(self.keys.get(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PrefixedKeys, StoreKeys};
+
+    use std::sync::OnceLock;
+
+    struct Schedule;
+
+    impl StoreKeys for Schedule {
+        fn keys() -> &'static [&'static str] {
+            &["start", "repeat"]
+        }
+    }
+
+    struct Window;
+
+    impl StoreKeys for Window {
+        fn keys() -> &'static [&'static str] {
+            static SCHEDULE: PrefixedKeys = PrefixedKeys::new("schedule", Schedule::keys);
+            static KEYS: OnceLock<Vec<&'static str>> = OnceLock::new();
+
+            KEYS.get_or_init(|| {
+                let mut keys = vec!["id"];
+                keys.extend(SCHEDULE.keys());
+                keys
+            })
+        }
+    }
+
+    #[test]
+    fn test_prefixed_keys() {
+        static KEYS: PrefixedKeys = PrefixedKeys::new("schedule", Schedule::keys);
+
+        let key = KEYS.get("start");
+        assert_eq!(key, "schedule_start");
+
+        // Keys are only allocated once.
+        assert!(std::ptr::eq(key, KEYS.get("start")));
+        assert_eq!(
+            KEYS.keys().collect::<Vec<_>>(),
+            ["schedule_start", "schedule_repeat"]
+        );
+    }
+
+    #[test]
+    fn test_prefixed_keys_nested() {
+        static KEYS: PrefixedKeys = PrefixedKeys::new("window", Window::keys);
+
+        assert_eq!(
+            KEYS.keys().collect::<Vec<_>>(),
+            [
+                "window_id",
+                "window_schedule_start",
+                "window_schedule_repeat"
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_prefixed_keys_unknown() {
+        static KEYS: PrefixedKeys = PrefixedKeys::new("schedule", Schedule::keys);
+
+        KEYS.get("end");
+    }
+}
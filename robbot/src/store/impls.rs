@@ -3,6 +3,7 @@
 
 use super::{Deserialize, Deserializer, Serialize, Serializer, Store, TypeSerializer};
 
+use crate::model::emoji::Emoji;
 use crate::model::id::{ChannelId, GuildId, MessageId, RoleId, UserId};
 
 impl<T> Serialize<T> for ChannelId
@@ -179,3 +180,43 @@ where
         Ok(Self(v))
     }
 }
+
+/// Emojis are stored in their [`Display`] form.
+///
+/// [`Display`]: std::fmt::Display
+impl<T> Serialize<T> for Emoji
+where
+    T: Store,
+    String: Serialize<T>,
+{
+    fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+    where
+        S: Serializer<T>,
+    {
+        self.to_string().serialize(serializer)
+    }
+
+    fn serialize_type<S>(serializer: &mut S) -> Result<(), S::Error>
+    where
+        S: TypeSerializer<T>,
+    {
+        String::serialize_type(serializer)
+    }
+}
+
+/// Stored values that are not a valid emoji are returned as
+/// [`Emoji::Unicode`], the deserializer has no way to report them.
+impl<T> Deserialize<T> for Emoji
+where
+    T: Store,
+    String: Deserialize<T>,
+{
+    fn deserialize<D>(deserializer: &mut D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<T>,
+    {
+        let v = String::deserialize(deserializer)?;
+
+        Ok(v.parse().unwrap_or(Self::Unicode(v)))
+    }
+}
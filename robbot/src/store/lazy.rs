@@ -1,4 +1,4 @@
-use super::{DataDescriptor, DataQuery, Store, StoreData};
+use super::{DataDescriptor, DataQuery, OrderBy, Store, StoreData};
 
 use tokio::sync::RwLock;
 
@@ -35,6 +35,15 @@ where
         })
     }
 
+    /// Creates a new `LazyStore` from an already connected store.
+    pub fn from_store(uri: &str, store: S) -> Self {
+        let inner = InnerLazyStore::new_connected(uri, store);
+
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
     pub async fn create<T, D>(&self, descriptor: D) -> Result<(), S::Error>
     where
         T: StoreData<S> + Send + Sync + 'static,
@@ -76,6 +85,22 @@ where
         store.get_all(descriptor).await
     }
 
+    pub async fn get_ordered<T, D, Q>(
+        &self,
+        descriptor: D,
+        query: Q,
+        order: OrderBy,
+    ) -> Result<Vec<T>, S::Error>
+    where
+        T: StoreData<S> + Send + Sync + 'static,
+        D: DataDescriptor<T, S> + Send + Sync,
+        Q: DataQuery<T, S> + Send,
+    {
+        let store = self.inner.store().await?;
+
+        store.get_ordered(descriptor, query, order).await
+    }
+
     pub async fn get_one<T, D, Q>(&self, descriptor: D, query: Q) -> Result<Option<T>, S::Error>
     where
         T: StoreData<S> + Send + Sync + 'static,
@@ -96,6 +121,16 @@ where
         store.insert(data).await
     }
 
+    pub async fn update<T, Q>(&self, query: Q, data: T) -> Result<u64, S::Error>
+    where
+        T: StoreData<S> + Send + Sync + 'static,
+        Q: DataQuery<T, S> + Send,
+    {
+        let store = self.inner.store().await?;
+
+        store.update(query, data).await
+    }
+
     pub fn make_descriptor<T>(&self) -> T::DataDescriptor
     where
         T: StoreData<S>,
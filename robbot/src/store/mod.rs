@@ -1,3 +1,6 @@
+pub mod decimal;
+mod enums;
+mod flatten;
 pub mod id;
 mod impls;
 pub mod lazy;
@@ -5,7 +8,10 @@ pub mod lazy;
 use async_trait::async_trait;
 use std::error::Error;
 
-pub use robbot_derive::{create, delete, get, get_one, insert, StoreData};
+pub use decimal::Decimal;
+pub use enums::{StoreEnum, UnknownVariant};
+pub use flatten::{Prefixed, PrefixedKeys, StoreKeys};
+pub use robbot_derive::{create, delete, get, get_one, insert, StoreData, StoreEnum};
 
 #[async_trait]
 pub trait Store: Sized {
@@ -55,6 +61,27 @@ pub trait Store: Sized {
         T: StoreData<Self> + Send + Sync + 'static,
         D: DataDescriptor<T, Self> + Send + Sync;
 
+    /// Returns all items of type `T` matching the query `Q` sorted by
+    /// the field given in `order`. Items with equal values in the field
+    /// are returned in an unspecified order. Returns an error if `T` has
+    /// no field `order.key`.
+    ///
+    /// Note: [`get`] and [`get_all`] make no guarantees about the order
+    /// of the returned items. Use `get_ordered` if the order matters.
+    ///
+    /// [`get`]: Self::get
+    /// [`get_all`]: Self::get_all
+    async fn get_ordered<T, D, Q>(
+        &self,
+        descriptor: D,
+        query: Q,
+        order: OrderBy,
+    ) -> Result<Vec<T>, Self::Error>
+    where
+        T: StoreData<Self> + Send + Sync + 'static,
+        D: DataDescriptor<T, Self> + Send + Sync,
+        Q: DataQuery<T, Self> + Send;
+
     /// Returns the an item of type `T` matching the query `Q`
     /// from the store. If no items of type `T` are stored, `None`
     /// is returned.
@@ -73,6 +100,16 @@ pub trait Store: Sized {
     where
         T: StoreData<Self> + Send + Sync + 'static;
 
+    /// Replaces all items of type `T` matching the query `Q` with `data`
+    /// in a single operation. Returns the number of replaced items.
+    ///
+    /// Note: Items already equal to `data` might not be counted by all
+    /// stores.
+    async fn update<T, Q>(&self, query: Q, data: T) -> Result<u64, Self::Error>
+    where
+        T: StoreData<Self> + Send + Sync + 'static,
+        Q: DataQuery<T, Self> + Send;
+
     fn make_query<T>(&self) -> T::DataQuery
     where
         T: StoreData<Self>,
@@ -90,6 +127,39 @@ pub trait Store: Sized {
     }
 }
 
+/// The order of items returned by [`Store::get_ordered`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct OrderBy {
+    /// The name of the field to sort by.
+    pub key: &'static str,
+    pub direction: Direction,
+}
+
+impl OrderBy {
+    /// Sorts by the field `key` in ascending order.
+    pub const fn asc(key: &'static str) -> Self {
+        Self {
+            key,
+            direction: Direction::Asc,
+        }
+    }
+
+    /// Sorts by the field `key` in descending order.
+    pub const fn desc(key: &'static str) -> Self {
+        Self {
+            key,
+            direction: Direction::Desc,
+        }
+    }
+}
+
+/// The sort direction of an [`OrderBy`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Asc,
+    Desc,
+}
+
 /// A type for serializing some data into a query for store `S`.
 pub trait Serializer<S>
 where
@@ -133,11 +203,36 @@ where
     /// Serializes a `&str` value.
     fn serialize_str(&mut self, v: &str) -> Result<(), Self::Error>;
 
+    /// Serializes a `u128` value. Serializes the decimal string of the value
+    /// by default, for stores without a 128-bit integer type.
+    fn serialize_u128(&mut self, v: u128) -> Result<(), Self::Error> {
+        self.serialize_str(&v.to_string())
+    }
+
+    /// Serializes a `i128` value. Serializes the decimal string of the value
+    /// by default, for stores without a 128-bit integer type.
+    fn serialize_i128(&mut self, v: i128) -> Result<(), Self::Error> {
+        self.serialize_str(&v.to_string())
+    }
+
+    /// Serializes a [`Decimal`] value. Serializes the string of the value by
+    /// default, for stores without a decimal type.
+    fn serialize_decimal(&mut self, v: &Decimal) -> Result<(), Self::Error> {
+        self.serialize_str(&v.to_string())
+    }
+
     /// Serialize a single field. A field is a single key-value pair where the key
     /// is a `str` and the value is `T`.
     fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + Serialize<S>;
+
+    /// Serializes a single field matching any of the given `values`. This is
+    /// only used when serializing a [`DataQuery`]. A field with no `values`
+    /// matches nothing.
+    fn serialize_field_in<T>(&mut self, key: &'static str, values: &[T]) -> Result<(), Self::Error>
+    where
+        T: Serialize<S>;
 }
 
 /// A type for deserializing the response for store `S` into some data.
@@ -183,11 +278,54 @@ where
     /// Deserializes a `String` value.
     fn deserialize_string(&mut self) -> Result<String, Self::Error>;
 
+    /// Deserializes a `u128` value. Parses the string written by the default
+    /// [`Serializer::serialize_u128`] by default.
+    ///
+    /// # Panics
+    ///
+    /// The default implementation panics if the stored string is not a valid
+    /// `u128`, it has no way to report the invalid value.
+    fn deserialize_u128(&mut self) -> Result<u128, Self::Error> {
+        let v = self.deserialize_string()?;
+        Ok(v.parse()
+            .unwrap_or_else(|_| panic!("invalid stored u128 value: {:?}", v)))
+    }
+
+    /// Deserializes a `i128` value. Parses the string written by the default
+    /// [`Serializer::serialize_i128`] by default.
+    ///
+    /// # Panics
+    ///
+    /// The default implementation panics if the stored string is not a valid
+    /// `i128`, it has no way to report the invalid value.
+    fn deserialize_i128(&mut self) -> Result<i128, Self::Error> {
+        let v = self.deserialize_string()?;
+        Ok(v.parse()
+            .unwrap_or_else(|_| panic!("invalid stored i128 value: {:?}", v)))
+    }
+
+    /// Deserializes a [`Decimal`] value. Parses the string written by the
+    /// default [`Serializer::serialize_decimal`] by default.
+    ///
+    /// # Panics
+    ///
+    /// The default implementation panics if the stored string is not a valid
+    /// [`Decimal`], it has no way to report the invalid value.
+    fn deserialize_decimal(&mut self) -> Result<Decimal, Self::Error> {
+        let v = self.deserialize_string()?;
+        Ok(v.parse()
+            .unwrap_or_else(|_| panic!("invalid stored decimal value: {:?}", v)))
+    }
+
     /// Deserializes a single field. A field is a single key-value pair where
     /// the key is a `str` and the value is `T`.
     fn deserialize_field<T>(&mut self, key: &'static str) -> Result<T, Self::Error>
     where
         T: Sized + Deserialize<S>;
+
+    /// Returns the error for a stored value that is not a variant of a
+    /// [`StoreEnum`].
+    fn unknown_variant(&mut self, err: UnknownVariant) -> Self::Error;
 }
 
 pub trait TypeSerializer<S>
@@ -213,6 +351,24 @@ where
 
     fn serialize_str(&mut self) -> Result<(), Self::Error>;
 
+    /// Serializes the `u128` type. Uses the string type by default, see
+    /// [`Serializer::serialize_u128`].
+    fn serialize_u128(&mut self) -> Result<(), Self::Error> {
+        self.serialize_str()
+    }
+
+    /// Serializes the `i128` type. Uses the string type by default, see
+    /// [`Serializer::serialize_i128`].
+    fn serialize_i128(&mut self) -> Result<(), Self::Error> {
+        self.serialize_str()
+    }
+
+    /// Serializes the [`Decimal`] type. Uses the string type by default, see
+    /// [`Serializer::serialize_decimal`].
+    fn serialize_decimal(&mut self) -> Result<(), Self::Error> {
+        self.serialize_str()
+    }
+
     fn serialize_field<T>(&mut self, key: &'static str) -> Result<(), Self::Error>
     where
         T: ?Sized + Serialize<S>;
@@ -252,6 +408,20 @@ where
 /// The [`StoreData`] derive macro automatically implements `StoreData` for all
 /// stores that support all the structs contained fields.
 ///
+/// Fields marked `#[store(flatten)]` whose type also derives `StoreData` are
+/// stored as the fields of their type prefixed with the field name, e.g.
+/// `schedule_start`. Queries match them using the accessor of the field,
+/// `query.schedule(|query| query.start(0))`, or `schedule.start == 0` in the
+/// query macros.
+///
+/// A field `version: u64` marked `#[store(version)]` enables optimistic
+/// locking: updates only replace the stored item if its version did not
+/// change since it was read, and increment the version.
+///
+/// Fields whose type implements [`StoreEnum`] are marked `#[store(as = "u8")]`
+/// or `#[store(as = "string")]` to store their integer or string
+/// representation.
+///
 /// [`StoreData`]: ../derive.StoreData.html
 pub trait StoreData<T>: Sized
 where
@@ -278,6 +448,16 @@ where
 
     /// Returns a new default value of [`Self::DataQuery`].
     fn query() -> Self::DataQuery;
+
+    /// Returns the value of the `version` field if it is marked
+    /// `#[store(version)]`. Updates of versioned types only succeed if the
+    /// stored version still matches, see `StoreHandler::update`.
+    fn version(&self) -> Option<u64> {
+        None
+    }
+
+    /// Sets the `version` field. Does nothing if `Self` is not versioned.
+    fn set_version(&mut self, _version: u64) {}
 }
 
 /// A descriptor of how to construct some [`StoreData`].
@@ -1,7 +1,10 @@
 use crate::util::SmallOption;
 
-use chrono::{Datelike, Duration, Timelike};
-use std::ops::Add;
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike};
+use thiserror::Error;
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
 
 pub trait Task: Sized {
     type Executor;
@@ -39,22 +42,244 @@ impl TaskSchedule {
     /// Creates a new `TaskSchedule` that runs the once every day at `00:00:00`.
     pub fn at_midnight() -> Self {
         let mut dt_req = DateTimeRequirement::new();
+        dt_req.with_second(0);
+        dt_req.with_minute(0);
         dt_req.with_hour(0);
 
         Self::RepeatTime(dt_req)
     }
 
-    pub fn advance<T>(&self, datetime: T) -> Option<T>
+    /// Returns the next execution time after `datetime`, or `None` if the
+    /// task never executes again.
+    pub fn advance<Tz>(&self, datetime: DateTime<Tz>) -> Option<DateTime<Tz>>
     where
-        T: Datelike + Timelike + Add<Duration, Output = T>,
+        Tz: TimeZone,
     {
         match self {
-            Self::Interval(duration) => Some(datetime + *duration),
+            Self::Interval(duration) => datetime.checked_add_signed(*duration),
             Self::RepeatTime(requirements) => requirements.advance(datetime),
         }
     }
 }
 
+/// Displays a humanized description of the schedule, e.g. `every 1h 30m` or
+/// `daily at 00:00`. Schedules without a shorter description are displayed
+/// cron-like as `at <second> <minute> <hour> <day> <month> [year]`, using `*`
+/// for fields without a requirement.
+impl Display for TaskSchedule {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Interval(duration) => {
+                let secs = duration.num_seconds();
+
+                if secs <= 0 {
+                    return write!(f, "continuously");
+                }
+
+                let parts = [
+                    (secs / 86400, "d"),
+                    ((secs % 86400) / 3600, "h"),
+                    ((secs % 3600) / 60, "m"),
+                    (secs % 60, "s"),
+                ];
+
+                write!(f, "every")?;
+                for (value, unit) in parts {
+                    if value != 0 {
+                        write!(f, " {}{}", value, unit)?;
+                    }
+                }
+
+                Ok(())
+            }
+            Self::RepeatTime(req) => {
+                let second = as_option(req.second);
+                let minute = as_option(req.minute);
+                let hour = as_option(req.hour);
+                let day = as_option(req.day);
+                let month = as_option(req.month);
+
+                let is_daily = day.is_none() && month.is_none() && req.year.is_none();
+
+                match (second, minute, hour) {
+                    (Some(second), Some(minute), Some(hour)) if is_daily => {
+                        write!(f, "daily at {:02}:{:02}", hour, minute)?;
+
+                        if second != 0 {
+                            write!(f, ":{:02}", second)?;
+                        }
+
+                        Ok(())
+                    }
+                    (Some(second), Some(minute), None) if is_daily => {
+                        write!(f, "hourly at **:{:02}:{:02}", minute, second)
+                    }
+                    _ => {
+                        write!(f, "at")?;
+
+                        for value in [second, minute, hour, day, month] {
+                            match value {
+                                Some(value) => write!(f, " {}", value)?,
+                                None => write!(f, " *")?,
+                            }
+                        }
+
+                        if let Some(year) = req.year {
+                            write!(f, " {}", year)?;
+                        }
+
+                        Ok(())
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("invalid task schedule: {0}")]
+pub struct InvalidSchedule(String);
+
+/// The longest duration accepted by [`parse_duration`], 100 years. Longer
+/// durations could overflow when added to the current date.
+pub const MAX_DURATION_SECS: i64 = 100 * 365 * 86400;
+
+/// Parses a positive duration made up of numbers followed by a unit, e.g.
+/// `1h 30m` or `7d`. The units are `d`, `h`, `m` and `s`. Durations longer
+/// than [`MAX_DURATION_SECS`] are rejected.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let mut secs = 0i64;
+    let mut num = String::new();
+    for c in s.trim().chars() {
+        let unit = match c {
+            '0'..='9' => {
+                num.push(c);
+                continue;
+            }
+            ' ' if num.is_empty() => continue,
+            'd' => 86400,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+
+        let value: i64 = num.parse().ok()?;
+        secs = value
+            .checked_mul(unit)
+            .and_then(|value| secs.checked_add(value))?;
+        num.clear();
+    }
+
+    // A duration without a unit is ambiguous.
+    if !num.is_empty() || secs <= 0 || secs > MAX_DURATION_SECS {
+        return None;
+    }
+
+    Some(Duration::seconds(secs))
+}
+
+/// Parses a schedule in the formats produced by the [`Display`] implementation:
+/// - `every 1h 30m` (the `every` is optional, units are `d`, `h`, `m` and `s`)
+/// - `daily at HH:MM[:SS]`
+/// - `hourly at **:MM:SS`
+/// - `at <second> <minute> <hour> <day> <month> [year]`, using `*` for fields
+///   without a requirement
+impl FromStr for TaskSchedule {
+    type Err = InvalidSchedule;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidSchedule(s.to_owned());
+
+        fn parse_num<T>(s: &str) -> Option<T>
+        where
+            T: FromStr,
+        {
+            match s {
+                "" => None,
+                s => s.parse().ok(),
+            }
+        }
+
+        let s = s.trim();
+
+        let (second, minute, hour, day, month, year) =
+            if let Some(time) = s.strip_prefix("daily at ") {
+                let mut parts = time.split(':');
+                let hour = parts.next().and_then(parse_num).ok_or_else(invalid)?;
+                let minute = parts.next().and_then(parse_num).ok_or_else(invalid)?;
+                let second = match parts.next() {
+                    Some(second) => parse_num(second).ok_or_else(invalid)?,
+                    None => 0,
+                };
+
+                if parts.next().is_some() {
+                    return Err(invalid());
+                }
+
+                (Some(second), Some(minute), Some(hour), None, None, None)
+            } else if let Some(time) = s.strip_prefix("hourly at **:") {
+                let (minute, second) = time.split_once(':').ok_or_else(invalid)?;
+
+                (
+                    Some(parse_num(second).ok_or_else(invalid)?),
+                    Some(parse_num(minute).ok_or_else(invalid)?),
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+            } else if let Some(fields) = s.strip_prefix("at ") {
+                let fields: Vec<_> = fields.split_whitespace().collect();
+                if fields.len() != 5 && fields.len() != 6 {
+                    return Err(invalid());
+                }
+
+                let mut values = [None; 5];
+                for (value, field) in values.iter_mut().zip(&fields) {
+                    *value = match *field {
+                        "*" => None,
+                        field => Some(parse_num::<u8>(field).ok_or_else(invalid)?),
+                    };
+                }
+
+                let year = match fields.get(5) {
+                    Some(year) => Some(parse_num(year).ok_or_else(invalid)?),
+                    None => None,
+                };
+
+                let [second, minute, hour, day, month] = values;
+                (second, minute, hour, day, month, year)
+            } else {
+                let s = s.strip_prefix("every ").unwrap_or(s);
+
+                return parse_duration(s).map(Self::Interval).ok_or_else(invalid);
+            };
+
+        let mut req = DateTimeRequirement::new();
+        if let Some(second) = second {
+            req.with_second(second).ok_or_else(invalid)?;
+        }
+        if let Some(minute) = minute {
+            req.with_minute(minute).ok_or_else(invalid)?;
+        }
+        if let Some(hour) = hour {
+            req.with_hour(hour).ok_or_else(invalid)?;
+        }
+        if let Some(day) = day {
+            req.with_day(day).ok_or_else(invalid)?;
+        }
+        if let Some(month) = month {
+            req.with_month(month).ok_or_else(invalid)?;
+        }
+        if let Some(year) = year {
+            req.with_year(year);
+        }
+
+        Ok(Self::RepeatTime(req))
+    }
+}
+
 /// Defines a number of requirements on a date/time. These requirements are
 /// only satisfied when all values of the given date/time have the same values
 /// are the requirements.
@@ -206,130 +431,68 @@ impl DateTimeRequirement {
     where
         T: Datelike + Timelike,
     {
-        // Adds 2 `i8`s together and returns the sum as a `u32`.
-        // If the sum is negative or the sum over/underflows 0 is returned.
-        fn add(a: i8, b: i8) -> u32 {
-            match a.checked_add(b) {
-                Some(n) => {
-                    if n.is_negative() {
-                        0
+        // Returns the next value of a single field and whether the next greater field
+        // needs to be increased. `wanted` is the required value of the field, if any.
+        // `upcast` is `true` if the field needs to be increased because a smaller field
+        // wrapped around. `min` and `max` are the smallest and greatest valid values of
+        // the field.
+        fn next(current: u32, wanted: Option<u8>, upcast: bool, min: u32, max: u32) -> (u32, bool) {
+            match wanted {
+                Some(wanted) => {
+                    let wanted = wanted as u32;
+
+                    // The field needs to be strictly greater than the current value when
+                    // upcasting.
+                    if wanted > current || (wanted == current && !upcast) {
+                        (wanted, false)
+                    } else {
+                        (wanted, true)
+                    }
+                }
+                None => {
+                    if !upcast {
+                        (current, false)
+                    } else if current >= max {
+                        (min, true)
                     } else {
-                        n as u32
+                        (current + 1, false)
                     }
                 }
-                None => 0,
             }
         }
 
         // If the wanted time value is **before** the current value of `datetime`
         // the next greater time value needs to be increased (e.g. wanted seconds = 3,
-        // current seconds = 4, difference = -1: the minute value needs to be increased
-        // by 1).
-        let mut upcast = false;
-
-        if self.second.is_some() {
-            let second = unsafe { self.second.unwrap_unchecked() };
-
-            let current_second = datetime.second() as i8;
-            let diff = second as i8 - current_second;
-
-            if diff != 0 {
-                if diff < 0 {
-                    upcast = true;
-                }
-
-                datetime = datetime.with_second(current_second as u32 + diff as u32)?;
-            }
+        // current seconds = 4: the minute value needs to be increased by 1).
+        let (second, upcast) = next(datetime.second(), as_option(self.second), false, 0, 59);
+        if second != datetime.second() {
+            datetime = datetime.with_second(second)?;
         }
 
-        {
-            let current_minute = datetime.minute() as i8;
-            let minute = self.minute.unwrap_or(current_minute as u8);
-
-            let mut diff = minute as i8 - current_minute;
-
-            println!("{}", diff);
-
-            if upcast {
-                diff += 1;
-                upcast = false;
-            }
-
-            if diff != 0 {
-                if diff < 0 {
-                    upcast = true;
-                }
-
-                let new_minute = add(current_minute, diff);
-
-                datetime = datetime.with_minute(new_minute)?;
-            }
+        let (minute, upcast) = next(datetime.minute(), as_option(self.minute), upcast, 0, 59);
+        if minute != datetime.minute() {
+            datetime = datetime.with_minute(minute)?;
         }
 
-        {
-            let current_hour = datetime.hour() as i8;
-            let hour = self.hour.unwrap_or(current_hour as u8);
-
-            let mut diff = hour as i8 - current_hour;
-
-            if upcast {
-                diff += 1;
-                upcast = false;
-            }
-
-            if diff != 0 {
-                if diff < 0 {
-                    upcast = true;
-                }
-
-                let new_hour = add(current_hour as i8, diff);
-
-                datetime = datetime.with_hour(new_hour)?;
-            }
+        let (hour, upcast) = next(datetime.hour(), as_option(self.hour), upcast, 0, 23);
+        if hour != datetime.hour() {
+            datetime = datetime.with_hour(hour)?;
         }
 
-        {
-            let current_day = datetime.day() as i8;
-            let day = self.day.unwrap_or(current_day as u8);
-
-            let mut diff = day as i8 - current_day;
-
-            if upcast {
-                diff += 1;
-                upcast = false;
-            }
+        // The last day of the current month.
+        let last_day = (28..=31)
+            .rev()
+            .find(|day| datetime.with_day(*day).is_some())
+            .unwrap_or(28);
 
-            if diff != 0 {
-                if diff < 0 {
-                    upcast = true;
-                }
-
-                let new_day = add(current_day as i8, diff);
-
-                datetime = datetime.with_day(new_day)?;
-            }
+        let (day, upcast) = next(datetime.day(), as_option(self.day), upcast, 1, last_day);
+        if day != datetime.day() {
+            datetime = datetime.with_day(day)?;
         }
 
-        {
-            let current_month = datetime.month() as i8;
-            let month = self.month.unwrap_or(current_month as u8);
-
-            let mut diff = month as i8 - current_month;
-
-            if upcast {
-                diff += 1;
-                upcast = false;
-            }
-
-            if diff != 0 {
-                if diff < 0 {
-                    upcast = true;
-                }
-
-                let new_month = add(current_month, diff);
-
-                datetime = datetime.with_month(new_month)?;
-            }
+        let (month, upcast) = next(datetime.month(), as_option(self.month), upcast, 1, 12);
+        if month != datetime.month() {
+            datetime = datetime.with_month(month)?;
         }
 
         {
@@ -419,9 +582,18 @@ impl DateTimeRequirement {
     }
 }
 
+fn as_option(opt: SmallOption<u8>) -> Option<u8> {
+    if opt.is_some() {
+        // SAFETY: `opt` was checked to contain a value.
+        unsafe { Some(opt.unwrap_unchecked()) }
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{DateTimeRequirement, TaskSchedule};
+    use super::{parse_duration, DateTimeRequirement, InvalidSchedule, TaskSchedule};
 
     use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
 
@@ -437,10 +609,154 @@ mod tests {
         assert_eq!(schedule, TaskSchedule::Interval(Duration::days(1)));
     }
 
+    #[test]
+    fn test_task_schedule_display() {
+        assert_eq!(TaskSchedule::minutely().to_string(), "every 1m");
+        assert_eq!(TaskSchedule::hourly().to_string(), "every 1h");
+        assert_eq!(TaskSchedule::daily().to_string(), "every 1d");
+        assert_eq!(
+            TaskSchedule::Interval(Duration::seconds(86400 + 5400 + 5)).to_string(),
+            "every 1d 1h 30m 5s"
+        );
+        assert_eq!(
+            TaskSchedule::Interval(Duration::zero()).to_string(),
+            "continuously"
+        );
+
+        assert_eq!(TaskSchedule::at_midnight().to_string(), "daily at 00:00");
+
+        let mut req = DateTimeRequirement::new();
+        req.with_second(30).unwrap();
+        req.with_minute(15).unwrap();
+        req.with_hour(8).unwrap();
+        assert_eq!(
+            TaskSchedule::RepeatTime(req).to_string(),
+            "daily at 08:15:30"
+        );
+
+        let mut req = DateTimeRequirement::new();
+        req.with_second(0).unwrap();
+        req.with_minute(5).unwrap();
+        assert_eq!(
+            TaskSchedule::RepeatTime(req).to_string(),
+            "hourly at **:05:00"
+        );
+
+        let mut req = DateTimeRequirement::new();
+        req.with_minute(30).unwrap();
+        req.with_hour(4).unwrap();
+        assert_eq!(TaskSchedule::RepeatTime(req).to_string(), "at * 30 4 * *");
+
+        let mut req = DateTimeRequirement::new();
+        req.with_second(0).unwrap();
+        req.with_day(1).unwrap();
+        req.with_year(2030);
+        assert_eq!(
+            TaskSchedule::RepeatTime(req).to_string(),
+            "at 0 * * 1 * 2030"
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("1d"), Some(Duration::days(1)));
+        assert_eq!(parse_duration(" 1h 30m "), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("90s"), Some(Duration::seconds(90)));
+
+        for s in ["", "0m", "30", "1w", "d", "-1h"] {
+            assert_eq!(parse_duration(s), None, "{}", s);
+        }
+
+        // Out of the range of `Duration`, panicked before.
+        assert_eq!(parse_duration("9999999999999999s"), None);
+
+        // In the range of `Duration`, but overflows when added to a date.
+        assert_eq!(parse_duration("36500d"), Some(Duration::days(36500)));
+        assert_eq!(parse_duration("36500d 1s"), None);
+        assert_eq!(parse_duration("100000000d"), None);
+    }
+
+    #[test]
+    fn test_task_schedule_advance_overflow() {
+        let schedule = TaskSchedule::Interval(Duration::max_value());
+
+        assert_eq!(schedule.advance(chrono::Utc::now()), None);
+    }
+
+    #[test]
+    fn test_task_schedule_from_str() {
+        assert_eq!("every 1m".parse(), Ok(TaskSchedule::minutely()));
+        assert_eq!("1h".parse(), Ok(TaskSchedule::hourly()));
+        assert_eq!(
+            "every 1d 1h 30m 5s".parse(),
+            Ok(TaskSchedule::Interval(Duration::seconds(86400 + 5400 + 5)))
+        );
+        assert_eq!(
+            "1h30m".parse(),
+            Ok(TaskSchedule::Interval(Duration::minutes(90)))
+        );
+        assert_eq!("daily at 00:00".parse(), Ok(TaskSchedule::at_midnight()));
+
+        // All displayed schedules parse back into the same schedule.
+        let mut schedules = vec![
+            TaskSchedule::Interval(Duration::seconds(86400 + 5400 + 5)),
+            TaskSchedule::at_midnight(),
+        ];
+
+        let mut req = DateTimeRequirement::new();
+        req.with_second(30).unwrap();
+        req.with_minute(15).unwrap();
+        req.with_hour(8).unwrap();
+        schedules.push(TaskSchedule::RepeatTime(req));
+
+        let mut req = DateTimeRequirement::new();
+        req.with_second(0).unwrap();
+        req.with_minute(5).unwrap();
+        schedules.push(TaskSchedule::RepeatTime(req));
+
+        let mut req = DateTimeRequirement::new();
+        req.with_minute(30).unwrap();
+        req.with_hour(4).unwrap();
+        schedules.push(TaskSchedule::RepeatTime(req));
+
+        let mut req = DateTimeRequirement::new();
+        req.with_second(0).unwrap();
+        req.with_day(1).unwrap();
+        req.with_year(2030);
+        schedules.push(TaskSchedule::RepeatTime(req));
+
+        for schedule in schedules {
+            assert_eq!(schedule.to_string().parse(), Ok(schedule));
+        }
+
+        for s in [
+            "",
+            "continuously",
+            "every",
+            "every 0s",
+            "1 h",
+            "1x",
+            "30",
+            "daily at 24:00",
+            "daily at 12",
+            "hourly at **:60:00",
+            "at * * *",
+            "at a * * * *",
+            "every 100000000d",
+        ] {
+            assert_eq!(
+                s.parse::<TaskSchedule>(),
+                Err(InvalidSchedule(s.to_owned())),
+                "{}",
+                s
+            );
+        }
+    }
+
     #[test]
     fn test_date_time_requirement() {
-        let date = NaiveDate::from_ymd(2022, 2, 15);
-        let time = NaiveTime::from_hms(3, 7, 0);
+        let date = NaiveDate::from_ymd_opt(2022, 2, 15).unwrap();
+        let time = NaiveTime::from_hms_opt(3, 7, 0).unwrap();
         let dt = NaiveDateTime::new(date, time);
 
         let mut repeat_dt = DateTimeRequirement::new();
@@ -484,4 +800,57 @@ mod tests {
 
         assert_eq!(repeat_dt.advance(dt), None);
     }
+
+    #[test]
+    fn test_date_time_requirement_carry() {
+        fn datetime(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> NaiveDateTime {
+            NaiveDate::from_ymd_opt(y, m, d)
+                .unwrap()
+                .and_hms_opt(h, min, s)
+                .unwrap()
+        }
+
+        let mut repeat_dt = DateTimeRequirement::new();
+        repeat_dt.with_second(0).unwrap();
+
+        // Minute 59 carries into the next hour.
+        assert_eq!(
+            repeat_dt.advance(datetime(2022, 2, 15, 12, 59, 30)),
+            Some(datetime(2022, 2, 15, 13, 0, 0))
+        );
+
+        // Hour 23 carries into the next day.
+        assert_eq!(
+            repeat_dt.advance(datetime(2022, 2, 15, 23, 59, 30)),
+            Some(datetime(2022, 2, 16, 0, 0, 0))
+        );
+
+        // The last day of the month carries into the next month.
+        assert_eq!(
+            repeat_dt.advance(datetime(2022, 2, 28, 23, 59, 30)),
+            Some(datetime(2022, 3, 1, 0, 0, 0))
+        );
+
+        // The last day of the year carries into the next year.
+        assert_eq!(
+            repeat_dt.advance(datetime(2022, 12, 31, 23, 59, 30)),
+            Some(datetime(2023, 1, 1, 0, 0, 0))
+        );
+
+        // No carry if the second is already satisfied.
+        assert_eq!(
+            repeat_dt.advance(datetime(2022, 2, 15, 12, 59, 0)),
+            Some(datetime(2022, 2, 15, 12, 59, 0))
+        );
+
+        // A required minute before the current minute is not shifted by the carry.
+        let mut repeat_dt = DateTimeRequirement::new();
+        repeat_dt.with_second(0).unwrap();
+        repeat_dt.with_minute(5).unwrap();
+
+        assert_eq!(
+            repeat_dt.advance(datetime(2022, 2, 15, 12, 10, 30)),
+            Some(datetime(2022, 2, 15, 13, 5, 0))
+        );
+    }
 }
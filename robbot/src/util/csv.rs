@@ -0,0 +1,162 @@
+//! Writing CSV files (RFC 4180), e.g. for exports sent as attachments.
+
+/// Writes records into CSV files of at most `max_rows` records each. Every
+/// file starts with the header, so each one can be opened on its own.
+///
+/// Fields are escaped while they are written, records are never built as
+/// intermediate strings.
+///
+/// ```
+/// # use robbot::util::csv::CsvWriter;
+/// let mut writer = CsvWriter::new(["name", "id"], 100);
+/// writer.write_record(["Doe, John", "1"]);
+///
+/// let files = writer.finish();
+/// assert_eq!(files, [b"name,id\r\n\"Doe, John\",1\r\n".to_vec()]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CsvWriter {
+    header: Vec<u8>,
+    max_rows: usize,
+    files: Vec<Vec<u8>>,
+    current: Vec<u8>,
+    rows: usize,
+}
+
+impl CsvWriter {
+    /// Creates a new `CsvWriter` with the columns `header`. A `max_rows` of
+    /// `0` is treated as `1`.
+    pub fn new<I, T>(header: I, max_rows: usize) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let mut buf = Vec::new();
+        write_record(&mut buf, header);
+
+        Self {
+            current: buf.clone(),
+            header: buf,
+            max_rows: max_rows.max(1),
+            files: Vec::new(),
+            rows: 0,
+        }
+    }
+
+    /// Appends a record, starting a new file if the current one is full.
+    pub fn write_record<I, T>(&mut self, fields: I)
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        if self.rows == self.max_rows {
+            let file = std::mem::replace(&mut self.current, self.header.clone());
+            self.files.push(file);
+            self.rows = 0;
+        }
+
+        write_record(&mut self.current, fields);
+        self.rows += 1;
+    }
+
+    /// Returns the number of records written.
+    pub fn len(&self) -> usize {
+        self.files.len() * self.max_rows + self.rows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns all files. There is always at least one file, a writer without
+    /// records returns only the header.
+    pub fn finish(mut self) -> Vec<Vec<u8>> {
+        self.files.push(self.current);
+        self.files
+    }
+}
+
+fn write_record<I, T>(buf: &mut Vec<u8>, fields: I)
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<str>,
+{
+    for (index, field) in fields.into_iter().enumerate() {
+        if index != 0 {
+            buf.push(b',');
+        }
+
+        write_field(buf, field.as_ref());
+    }
+
+    buf.extend_from_slice(b"\r\n");
+}
+
+/// Writes a single field, quoting it if it contains a separator, a quote or a
+/// line break. Quotes are escaped by doubling them.
+fn write_field(buf: &mut Vec<u8>, field: &str) {
+    if !field.contains([',', '"', '\n', '\r']) {
+        buf.extend_from_slice(field.as_bytes());
+        return;
+    }
+
+    buf.push(b'"');
+    for (index, part) in field.split('"').enumerate() {
+        if index != 0 {
+            buf.extend_from_slice(b"\"\"");
+        }
+
+        buf.extend_from_slice(part.as_bytes());
+    }
+    buf.push(b'"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CsvWriter;
+
+    fn csv(files: Vec<Vec<u8>>) -> Vec<String> {
+        files
+            .into_iter()
+            .map(|file| String::from_utf8(file).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_csv_writer_escape() {
+        let mut writer = CsvWriter::new(["name", "tag"], 10);
+        writer.write_record(["Doe, John", "john#0001"]);
+        writer.write_record(["The \"Best\"", "\""]);
+        writer.write_record(["multi\nline", "cr\r"]);
+        writer.write_record(["", "plain"]);
+
+        assert_eq!(
+            csv(writer.finish()),
+            ["name,tag\r\n\"Doe, John\",john#0001\r\n\"The \"\"Best\"\"\",\"\"\"\"\r\n\"multi\nline\",\"cr\r\"\r\n,plain\r\n"]
+        );
+    }
+
+    #[test]
+    fn test_csv_writer_pages() {
+        let mut writer = CsvWriter::new(["id"], 2);
+        assert!(writer.is_empty());
+
+        for id in ["1", "2", "3", "4", "5"] {
+            writer.write_record([id]);
+        }
+        assert_eq!(writer.len(), 5);
+
+        assert_eq!(
+            csv(writer.finish()),
+            ["id\r\n1\r\n2\r\n", "id\r\n3\r\n4\r\n", "id\r\n5\r\n"]
+        );
+
+        // Only the header without records.
+        assert_eq!(csv(CsvWriter::new(["id"], 2).finish()), ["id\r\n"]);
+
+        // Full pages don't leave an empty file behind.
+        let mut writer = CsvWriter::new(["id"], 1);
+        writer.write_record(["1"]);
+        assert_eq!(csv(writer.finish()), ["id\r\n1\r\n"]);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod color;
+pub mod csv;
 pub mod option;
 
 pub use option::SmallOption;